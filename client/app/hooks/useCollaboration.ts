@@ -65,6 +65,7 @@ export interface UseCollaborationReturn {
 
   // Chat
   sendChatMessage: (content: string) => void;
+  deleteChatMessage: (messageId: string) => void;
 
   // Voice
   joinVoiceChat: () => void;
@@ -128,6 +129,15 @@ export function useCollaboration(
     handleChatBroadcast: (
       msg: Extract<ServerMessage, { type: "ChatBroadcast" }>,
     ) => void;
+    handleChatHistory: (
+      msg: Extract<ServerMessage, { type: "ChatHistory" }>,
+    ) => void;
+    handleChatDeleted: (
+      msg: Extract<ServerMessage, { type: "ChatDeleted" }>,
+    ) => void;
+    handleDisplayNameChanged: (
+      msg: Extract<ServerMessage, { type: "DisplayNameChanged" }>,
+    ) => void;
     handleVoiceToken: (
       msg: Extract<ServerMessage, { type: "VoiceToken" }>,
     ) => void;
@@ -159,6 +169,8 @@ export function useCollaboration(
     removeCollaborator,
     updateCursor,
     addChatMessage,
+    setChatMessages,
+    removeChatMessage,
   } = useCollaborationStore();
 
   const { updateFileContent, openFiles } = useFileStore();
@@ -270,6 +282,15 @@ export function useCollaboration(
         case "ChatBroadcast":
           handlers.handleChatBroadcast(message);
           break;
+        case "ChatHistory":
+          handlers.handleChatHistory(message);
+          break;
+        case "ChatDeleted":
+          handlers.handleChatDeleted(message);
+          break;
+        case "DisplayNameChanged":
+          handlers.handleDisplayNameChanged(message);
+          break;
         case "VoiceToken":
           handlers.handleVoiceToken(message);
           break;
@@ -344,8 +365,9 @@ export function useCollaboration(
         });
       }
 
-      // Load document state if provided
-      if (msg.document_state && documentManagerRef.current) {
+      // Load document state if provided; a resumed session only gets the
+      // changes since its previous one, which the sync below catches up on
+      if (msg.document_state && !msg.document_since && documentManagerRef.current) {
         try {
           documentManagerRef.current.loadFromBinary(msg.document_state);
           console.log("[WS] Loaded document state from server");
@@ -500,7 +522,7 @@ export function useCollaboration(
   const handleChatBroadcast = useCallback(
     (msg: Extract<ServerMessage, { type: "ChatBroadcast" }>) => {
       addChatMessage({
-        id: generateId(),
+        id: msg.message_id ?? generateId(),
         userId: msg.peer_id,
         userName: msg.peer_name,
         message: msg.content,
@@ -510,6 +532,53 @@ export function useCollaboration(
     [addChatMessage],
   );
 
+  const handleChatHistory = useCallback(
+    (msg: Extract<ServerMessage, { type: "ChatHistory" }>) => {
+      setChatMessages(
+        msg.messages.map((item) => ({
+          id: item.message_id ?? generateId(),
+          userId: item.peer_id,
+          userName: item.peer_name,
+          message: item.content,
+          timestamp: item.timestamp,
+        })),
+      );
+    },
+    [setChatMessages],
+  );
+
+  const handleChatDeleted = useCallback(
+    (msg: Extract<ServerMessage, { type: "ChatDeleted" }>) => {
+      removeChatMessage(msg.message_id);
+    },
+    [removeChatMessage],
+  );
+
+  // The project picks a unique name and color for each peer on join
+  const handleDisplayNameChanged = useCallback(
+    (msg: Extract<ServerMessage, { type: "DisplayNameChanged" }>) => {
+      if (msg.peer_id === peerId) {
+        setUserName(msg.name);
+        if (msg.color) {
+          setPeerColor(msg.color);
+        }
+        return;
+      }
+
+      const existing = useCollaborationStore
+        .getState()
+        .collaborators.find((c) => c.id === msg.peer_id);
+      if (existing) {
+        addCollaborator({
+          ...existing,
+          name: msg.name,
+          color: msg.color ?? existing.color,
+        });
+      }
+    },
+    [peerId, setUserName, addCollaborator],
+  );
+
   const handleVoiceToken = useCallback(
     (msg: Extract<ServerMessage, { type: "VoiceToken" }>) => {
       console.log("[WS] Voice token received for room:", msg.room_name);
@@ -568,9 +637,10 @@ export function useCollaboration(
       return new Promise((resolve, reject) => {
         try {
           // Build WebSocket URL - must include project_id in path
+          // An https:// server needs a secure socket
           const wsUrl = serverUrl.startsWith("ws")
             ? `${serverUrl}/ws/${projectId}`
-            : `ws://${serverUrl.replace(/^https?:\/\//, "")}/ws/${projectId}`;
+            : `${serverUrl.startsWith("https") ? "wss" : "ws"}://${serverUrl.replace(/^https?:\/\//, "")}/ws/${projectId}`;
 
           console.log("[WS] Connecting to:", wsUrl);
 
@@ -582,10 +652,13 @@ export function useCollaboration(
             console.log("[WS] Connected");
 
             // Send Hello message
+            // Ask for the color we had, so reconnecting keeps it
             const helloMsg = SyncProtocol.createHello(
               userName,
               peerId ?? undefined,
               sessionToken ?? undefined,
+              undefined,
+              peerColor ?? undefined,
             );
             ws.send(helloMsg);
 
@@ -640,6 +713,7 @@ export function useCollaboration(
     [
       serverUrl,
       peerId,
+      peerColor,
       sessionToken,
       autoReconnect,
       updateConnectionStatus,
@@ -784,19 +858,21 @@ export function useCollaboration(
       const projectId = projectIdRef.current;
       if (!projectId) return;
 
+      // The server echoes the message back once it passed moderation
       const msg = SyncProtocol.createChatMessage(projectId, content);
       sendBinary(msg);
+    },
+    [sendBinary],
+  );
 
-      // Optimistically add to local chat
-      addChatMessage({
-        id: generateId(),
-        userId: peerId || "unknown",
-        userName: userNameRef.current,
-        message: content,
-        timestamp: Date.now(),
-      });
+  const deleteChatMessage = useCallback(
+    (messageId: string) => {
+      const projectId = projectIdRef.current;
+      if (!projectId) return;
+
+      sendBinary(SyncProtocol.createChatDelete(projectId, messageId));
     },
-    [sendBinary, addChatMessage, peerId],
+    [sendBinary],
   );
 
   // ============================================================================
@@ -865,6 +941,9 @@ export function useCollaboration(
       handleCursorBroadcast,
       handlePresenceBroadcast,
       handleChatBroadcast,
+      handleChatHistory,
+      handleChatDeleted,
+      handleDisplayNameChanged,
       handleVoiceToken,
       handleError,
       handleGoodbye,
@@ -882,6 +961,9 @@ export function useCollaboration(
     handleCursorBroadcast,
     handlePresenceBroadcast,
     handleChatBroadcast,
+    handleChatHistory,
+    handleChatDeleted,
+    handleDisplayNameChanged,
     handleVoiceToken,
     handleError,
     handleGoodbye,
@@ -925,6 +1007,7 @@ export function useCollaboration(
 
     // Chat
     sendChatMessage,
+    deleteChatMessage,
 
     // Voice
     joinVoiceChat,
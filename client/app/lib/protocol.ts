@@ -38,6 +38,8 @@ export enum MessageType {
   CloseFile = 0x31,
   FileContent = 0x32,
   FileRequest = 0x33,
+  RequestFileVersion = 0x36,
+  FileVersion = 0x37,
 
   // Presence & Cursors
   PresenceUpdate = 0x40,
@@ -48,12 +50,27 @@ export enum MessageType {
   // Chat
   ChatMessage = 0x50,
   ChatHistory = 0x51,
+  ChatDelete = 0x52,
+  ChatDeleted = 0x53,
 
   // Voice
   VoiceJoin = 0x60,
   VoiceLeave = 0x61,
   VoiceToken = 0x62,
 
+  // Project list (lobby socket)
+  ProjectCreated = 0x80,
+  ProjectDeleted = 0x81,
+  PeerCountChanged = 0x82,
+
+  // Annotations (review comments and highlights on file text)
+  AnnotationAdd = 0x90,
+  AnnotationAdded = 0x91,
+  AnnotationResolve = 0x92,
+  AnnotationResolved = 0x93,
+  RequestAnnotations = 0x94,
+  Annotations = 0x95,
+
   // Admin/Debug
   Ping = 0xf0,
   Pong = 0xf1,
@@ -107,6 +124,26 @@ export interface ChatHistoryItem {
   peer_name: string;
   content: string;
   timestamp: number;
+  message_id: string | null;
+}
+
+export enum AnnotationKind {
+  Comment = "Comment",
+  Highlight = "Highlight",
+}
+
+/** A comment or highlight on a range of a file, offsets in characters */
+export interface AnnotationInfo {
+  annotation_id: string;
+  file_path: string;
+  start: number;
+  end: number;
+  kind: AnnotationKind;
+  author_id: string;
+  author_name: string;
+  body: string;
+  created_at: number;
+  resolved: boolean;
 }
 
 // ============================================================================
@@ -138,6 +175,8 @@ export type ClientMessage =
       client_id: string | null;
       client_name: string;
       session_token: string | null;
+      auth_token: string | null;
+      preferred_color: string | null;
     }
   | {
       type: "Goodbye";
@@ -147,6 +186,7 @@ export type ClientMessage =
       type: "JoinProject";
       project_id: string;
       request_state: boolean;
+      share_token: string | null;
     }
   | {
       type: "LeaveProject";
@@ -190,6 +230,38 @@ export type ClientMessage =
       project_id: string;
       content: string;
     }
+  | {
+      type: "ChatDelete";
+      project_id: string;
+      message_id: string;
+    }
+  | {
+      type: "AnnotationAdd";
+      project_id: string;
+      file_path: string;
+      start: number;
+      end: number;
+      kind: AnnotationKind;
+      body: string;
+    }
+  | {
+      type: "AnnotationResolve";
+      project_id: string;
+      file_path: string;
+      annotation_id: string;
+      resolved: boolean;
+    }
+  | {
+      type: "RequestAnnotations";
+      project_id: string;
+      file_path: string;
+    }
+  | {
+      type: "RequestFileVersion";
+      project_id: string;
+      file_path: string;
+      heads: string[];
+    }
   | {
       type: "VoiceJoin";
       project_id: string;
@@ -278,6 +350,10 @@ export type ServerMessage =
       project_id: string;
       peers: PeerInfo[];
       document_state: Uint8Array | null;
+      /** JSON array of nested file tree nodes */
+      file_tree: string | null;
+      /** Heads document_state continues from; null when it is the full document */
+      document_since: string[] | null;
     }
   | {
       type: "PeerJoined";
@@ -344,6 +420,64 @@ export type ServerMessage =
       peer_name: string;
       content: string;
       timestamp: number;
+      message_id: string | null;
+    }
+  | {
+      type: "ChatDeleted";
+      project_id: string;
+      message_id: string;
+      deleted_by: string;
+    }
+  | {
+      type: "DisplayNameChanged";
+      project_id: string;
+      peer_id: string;
+      name: string;
+      avatar_seed: string;
+      color: string | null;
+    }
+  | {
+      type: "ProjectCreated";
+      project_id: string;
+      name: string;
+      owner_id: string | null;
+      created_at: number;
+    }
+  | {
+      type: "ProjectDeleted";
+      project_id: string;
+    }
+  | {
+      type: "PeerCountChanged";
+      project_id: string;
+      peer_count: number;
+    }
+  | {
+      type: "AnnotationAdded";
+      project_id: string;
+      annotation: AnnotationInfo;
+    }
+  | {
+      type: "AnnotationResolved";
+      project_id: string;
+      file_path: string;
+      annotation_id: string;
+      resolved: boolean;
+      resolved_by: string;
+    }
+  | {
+      type: "Annotations";
+      project_id: string;
+      file_path: string;
+      annotations: AnnotationInfo[];
+    }
+  | {
+      type: "FileVersion";
+      project_id: string;
+      file_path: string;
+      heads: string[];
+      content: string | null;
+      language: string | null;
     }
   | {
       type: "ChatHistory";
@@ -558,6 +692,16 @@ function getClientMessageType(msg: ClientMessage): MessageType {
       return MessageType.PresenceUpdate;
     case "ChatMessage":
       return MessageType.ChatMessage;
+    case "ChatDelete":
+      return MessageType.ChatDelete;
+    case "AnnotationAdd":
+      return MessageType.AnnotationAdd;
+    case "AnnotationResolve":
+      return MessageType.AnnotationResolve;
+    case "RequestAnnotations":
+      return MessageType.RequestAnnotations;
+    case "RequestFileVersion":
+      return MessageType.RequestFileVersion;
     case "VoiceJoin":
       return MessageType.VoiceJoin;
     case "VoiceLeave":
@@ -582,6 +726,8 @@ function encodeClientPayload(
       encoder.writeOption(msg.client_id, (v) => encoder.writeString(v));
       encoder.writeString(msg.client_name);
       encoder.writeOption(msg.session_token, (v) => encoder.writeString(v));
+      encoder.writeOption(msg.auth_token, (v) => encoder.writeString(v));
+      encoder.writeOption(msg.preferred_color, (v) => encoder.writeString(v));
       break;
 
     case "Goodbye":
@@ -593,6 +739,7 @@ function encodeClientPayload(
       encoder.writeVariant(2);
       encoder.writeString(msg.project_id);
       encoder.writeBool(msg.request_state);
+      encoder.writeOption(msg.share_token, (v) => encoder.writeString(v));
       break;
 
     case "LeaveProject":
@@ -662,6 +809,46 @@ function encodeClientPayload(
       encoder.writeVariant(13);
       encoder.writeU64(msg.timestamp);
       break;
+
+    case "ChatDelete":
+      encoder.writeVariant(20);
+      encoder.writeString(msg.project_id);
+      encoder.writeString(msg.message_id);
+      break;
+
+    case "AnnotationAdd":
+      encoder.writeVariant(21);
+      encoder.writeString(msg.project_id);
+      encoder.writeString(msg.file_path);
+      encoder.writeU32(msg.start);
+      encoder.writeU32(msg.end);
+      encoder.writeVariant(msg.kind === AnnotationKind.Highlight ? 1 : 0);
+      encoder.writeString(msg.body);
+      break;
+
+    case "AnnotationResolve":
+      encoder.writeVariant(22);
+      encoder.writeString(msg.project_id);
+      encoder.writeString(msg.file_path);
+      encoder.writeString(msg.annotation_id);
+      encoder.writeBool(msg.resolved);
+      break;
+
+    case "RequestAnnotations":
+      encoder.writeVariant(23);
+      encoder.writeString(msg.project_id);
+      encoder.writeString(msg.file_path);
+      break;
+
+    case "RequestFileVersion":
+      encoder.writeVariant(24);
+      encoder.writeString(msg.project_id);
+      encoder.writeString(msg.file_path);
+      encoder.writeU64(msg.heads.length);
+      for (const head of msg.heads) {
+        encoder.writeString(head);
+      }
+      break;
   }
 }
 
@@ -723,6 +910,25 @@ function decodeChatHistoryItem(decoder: BincodeDecoder): ChatHistoryItem {
     peer_name: decoder.readString(),
     content: decoder.readString(),
     timestamp: decoder.readI64(),
+    message_id: decoder.readOption(() => decoder.readString()),
+  };
+}
+
+function decodeAnnotationInfo(decoder: BincodeDecoder): AnnotationInfo {
+  return {
+    annotation_id: decoder.readString(),
+    file_path: decoder.readString(),
+    start: decoder.readU32(),
+    end: decoder.readU32(),
+    kind:
+      decoder.readVariant() === 1
+        ? AnnotationKind.Highlight
+        : AnnotationKind.Comment,
+    author_id: decoder.readString(),
+    author_name: decoder.readString(),
+    body: decoder.readString(),
+    created_at: decoder.readI64(),
+    resolved: decoder.readBool(),
   };
 }
 
@@ -760,6 +966,10 @@ function decodeServerPayload(decoder: BincodeDecoder): ServerMessage {
         project_id: decoder.readString(),
         peers: decodeArray(decoder, () => decodePeerInfo(decoder)),
         document_state: decoder.readOption(() => decoder.readBytes()),
+        file_tree: decoder.readOption(() => decoder.readString()),
+        document_since: decoder.readOption(() =>
+          decodeArray(decoder, () => decoder.readString()),
+        ),
       };
 
     case 4: // PeerJoined
@@ -848,6 +1058,7 @@ function decodeServerPayload(decoder: BincodeDecoder): ServerMessage {
         peer_name: decoder.readString(),
         content: decoder.readString(),
         timestamp: decoder.readI64(),
+        message_id: decoder.readOption(() => decoder.readString()),
       };
 
     case 14: // ChatHistory
@@ -881,6 +1092,81 @@ function decodeServerPayload(decoder: BincodeDecoder): ServerMessage {
         uptime_seconds: decoder.readU64(),
       };
 
+    case 22: // DisplayNameChanged
+      return {
+        type: "DisplayNameChanged",
+        project_id: decoder.readString(),
+        peer_id: decoder.readString(),
+        name: decoder.readString(),
+        avatar_seed: decoder.readString(),
+        color: decoder.readOption(() => decoder.readString()),
+      };
+
+    case 24: // ChatDeleted
+      return {
+        type: "ChatDeleted",
+        project_id: decoder.readString(),
+        message_id: decoder.readString(),
+        deleted_by: decoder.readString(),
+      };
+
+    case 25: // ProjectCreated
+      return {
+        type: "ProjectCreated",
+        project_id: decoder.readString(),
+        name: decoder.readString(),
+        owner_id: decoder.readOption(() => decoder.readString()),
+        created_at: decoder.readI64(),
+      };
+
+    case 26: // ProjectDeleted
+      return {
+        type: "ProjectDeleted",
+        project_id: decoder.readString(),
+      };
+
+    case 27: // PeerCountChanged
+      return {
+        type: "PeerCountChanged",
+        project_id: decoder.readString(),
+        peer_count: decoder.readU32(),
+      };
+
+    case 28: // AnnotationAdded
+      return {
+        type: "AnnotationAdded",
+        project_id: decoder.readString(),
+        annotation: decodeAnnotationInfo(decoder),
+      };
+
+    case 29: // AnnotationResolved
+      return {
+        type: "AnnotationResolved",
+        project_id: decoder.readString(),
+        file_path: decoder.readString(),
+        annotation_id: decoder.readString(),
+        resolved: decoder.readBool(),
+        resolved_by: decoder.readString(),
+      };
+
+    case 30: // Annotations
+      return {
+        type: "Annotations",
+        project_id: decoder.readString(),
+        file_path: decoder.readString(),
+        annotations: decodeArray(decoder, () => decodeAnnotationInfo(decoder)),
+      };
+
+    case 31: // FileVersion
+      return {
+        type: "FileVersion",
+        project_id: decoder.readString(),
+        file_path: decoder.readString(),
+        heads: decodeArray(decoder, () => decoder.readString()),
+        content: decoder.readOption(() => decoder.readString()),
+        language: decoder.readOption(() => decoder.readString()),
+      };
+
     default:
       throw new Error(`Unknown server message variant: ${variant}`);
   }
@@ -965,6 +1251,8 @@ export class SyncProtocol {
     clientName: string,
     clientId?: string,
     sessionToken?: string,
+    authToken?: string,
+    preferredColor?: string,
   ): Uint8Array {
     return this.encodeClient({
       type: "Hello",
@@ -972,17 +1260,24 @@ export class SyncProtocol {
       client_id: clientId ?? null,
       client_name: clientName,
       session_token: sessionToken ?? null,
+      auth_token: authToken ?? null,
+      preferred_color: preferredColor ?? null,
     });
   }
 
   /**
    * Create a JoinProject message.
    */
-  static createJoinProject(projectId: string, requestState = true): Uint8Array {
+  static createJoinProject(
+    projectId: string,
+    requestState = true,
+    shareToken: string | null = null,
+  ): Uint8Array {
     return this.encodeClient({
       type: "JoinProject",
       project_id: projectId,
       request_state: requestState,
+      share_token: shareToken,
     });
   }
 
@@ -1089,6 +1384,87 @@ export class SyncProtocol {
     });
   }
 
+  /**
+   * Create a ChatDelete message.
+   */
+  static createChatDelete(projectId: string, messageId: string): Uint8Array {
+    return this.encodeClient({
+      type: "ChatDelete",
+      project_id: projectId,
+      message_id: messageId,
+    });
+  }
+
+  /**
+   * Create an AnnotationAdd message for the characters `start` to `end`.
+   */
+  static createAnnotationAdd(
+    projectId: string,
+    filePath: string,
+    start: number,
+    end: number,
+    kind: AnnotationKind,
+    body: string,
+  ): Uint8Array {
+    return this.encodeClient({
+      type: "AnnotationAdd",
+      project_id: projectId,
+      file_path: filePath,
+      start,
+      end,
+      kind,
+      body,
+    });
+  }
+
+  /**
+   * Create an AnnotationResolve message.
+   */
+  static createAnnotationResolve(
+    projectId: string,
+    filePath: string,
+    annotationId: string,
+    resolved: boolean,
+  ): Uint8Array {
+    return this.encodeClient({
+      type: "AnnotationResolve",
+      project_id: projectId,
+      file_path: filePath,
+      annotation_id: annotationId,
+      resolved,
+    });
+  }
+
+  /**
+   * Create a RequestAnnotations message.
+   */
+  static createRequestAnnotations(
+    projectId: string,
+    filePath: string,
+  ): Uint8Array {
+    return this.encodeClient({
+      type: "RequestAnnotations",
+      project_id: projectId,
+      file_path: filePath,
+    });
+  }
+
+  /**
+   * Create a RequestFileVersion message for a file as of `heads`.
+   */
+  static createRequestFileVersion(
+    projectId: string,
+    filePath: string,
+    heads: string[],
+  ): Uint8Array {
+    return this.encodeClient({
+      type: "RequestFileVersion",
+      project_id: projectId,
+      file_path: filePath,
+      heads,
+    });
+  }
+
   /**
    * Create a VoiceJoin message.
    */
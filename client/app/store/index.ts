@@ -283,6 +283,8 @@ interface CollaborationStore {
   ) => void;
 
   addChatMessage: (message: ChatMessage) => void;
+  setChatMessages: (messages: ChatMessage[]) => void;
+  removeChatMessage: (id: string) => void;
   clearChat: () => void;
 
   setDocumentManager: (manager: DocumentManager | null) => void;
@@ -390,6 +392,13 @@ export const useCollaborationStore = create<CollaborationStore>((set) => ({
       chatMessages: [...state.chatMessages, message].slice(-100), // Keep last 100 messages
     })),
 
+  setChatMessages: (messages) => set({ chatMessages: messages.slice(-100) }),
+
+  removeChatMessage: (id) =>
+    set((state) => ({
+      chatMessages: state.chatMessages.filter((message) => message.id !== id),
+    })),
+
   clearChat: () => set({ chatMessages: [] }),
 
   setDocumentManager: (manager) => set({ documentManagerRef: manager }),
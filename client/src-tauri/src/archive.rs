@@ -0,0 +1,375 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Component, Path, PathBuf};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+
+use crate::transfer::{file_node, unique_target, OverwritePolicy, TransferProgress};
+use crate::FileNode;
+
+// ============================================================================
+// ARCHIVE TYPES
+// ============================================================================
+
+/// Event emitted while an archive is created or extracted
+pub const ARCHIVE_PROGRESS_EVENT: &str = "fs://archive-progress";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+struct Progress {
+    app: AppHandle,
+    progress: TransferProgress,
+    last_emit: Instant,
+}
+
+impl Progress {
+    fn new(app: AppHandle, operation_id: Option<String>, total: usize) -> Self {
+        Progress {
+            app,
+            progress: TransferProgress {
+                operation_id: operation_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+                processed: 0,
+                total,
+                skipped: 0,
+                current_path: String::new(),
+                done: false,
+            },
+            last_emit: Instant::now(),
+        }
+    }
+
+    fn step(&mut self, path: &Path, skipped: bool) {
+        self.progress.processed += 1;
+        self.progress.skipped += usize::from(skipped);
+        self.progress.current_path = path.to_string_lossy().to_string();
+        if self.last_emit.elapsed() >= crate::transfer::PROGRESS_INTERVAL {
+            self.last_emit = Instant::now();
+            let _ = self.app.emit(ARCHIVE_PROGRESS_EVENT, self.progress.clone());
+        }
+    }
+
+    fn finish(mut self) {
+        self.progress.done = true;
+        let _ = self.app.emit(ARCHIVE_PROGRESS_EVENT, self.progress);
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn archive_format(path: &Path) -> Option<ArchiveFormat> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if name.ends_with(".zip") {
+        Some(ArchiveFormat::Zip)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveFormat::TarGz)
+    } else if name.ends_with(".tar") {
+        Some(ArchiveFormat::Tar)
+    } else {
+        None
+    }
+}
+
+/// An archive entry path that stays inside the target directory, or None
+/// for absolute paths and `..` components
+fn safe_entry_path(path: &Path) -> Option<PathBuf> {
+    let mut safe = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => safe.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (!safe.as_os_str().is_empty()).then_some(safe)
+}
+
+/// Where to write an extracted file, or None to skip it
+fn file_target(target: PathBuf, policy: OverwritePolicy) -> Result<Option<PathBuf>, String> {
+    if !target.exists() {
+        return Ok(Some(target));
+    }
+    match policy {
+        OverwritePolicy::Skip => Ok(None),
+        OverwritePolicy::Rename => Ok(Some(unique_target(&target))),
+        OverwritePolicy::Overwrite => {
+            if target.is_dir() {
+                std::fs::remove_dir_all(&target)
+            } else {
+                std::fs::remove_file(&target)
+            }
+            .map_err(|e| format!("Failed to replace {}: {}", target.display(), e))?;
+            Ok(Some(target))
+        }
+    }
+}
+
+fn create_parent(path: &Path) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Zip files and folders; entries are named relative to each path's parent
+/// so a selected folder keeps its name inside the archive
+fn write_zip(sources: &[PathBuf], target: &Path, progress: &mut Progress) -> Result<(), String> {
+    let file = File::create(target).map_err(|e| format!("Failed to create archive: {}", e))?;
+    let mut zip = zip::ZipWriter::new(BufWriter::new(file));
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    for source in sources {
+        let base = source.parent().unwrap_or(Path::new(""));
+        for entry in WalkDir::new(source).into_iter().filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let name = path
+                .strip_prefix(base)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            #[cfg(unix)]
+            let options = {
+                use std::os::unix::fs::PermissionsExt;
+                match entry.metadata() {
+                    Ok(metadata) => options.unix_permissions(metadata.permissions().mode()),
+                    Err(_) => options,
+                }
+            };
+
+            if entry.file_type().is_dir() {
+                zip.add_directory(name, options)
+                    .map_err(|e| format!("Failed to add {}: {}", path.display(), e))?;
+            } else if entry.file_type().is_file() {
+                zip.start_file(name, options)
+                    .map_err(|e| format!("Failed to add {}: {}", path.display(), e))?;
+                let mut input = File::open(path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                std::io::copy(&mut input, &mut zip)
+                    .map_err(|e| format!("Failed to add {}: {}", path.display(), e))?;
+                progress.step(path, false);
+            }
+            // Symlinks are left out; they may point outside the selection
+        }
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to write archive: {}", e))?;
+    Ok(())
+}
+
+fn extract_zip(
+    archive: &Path,
+    dest: &Path,
+    policy: OverwritePolicy,
+    progress: &mut Progress,
+) -> Result<(), String> {
+    let file = File::open(archive).map_err(|e| format!("Failed to open archive: {}", e))?;
+    let mut zip = zip::ZipArchive::new(BufReader::new(file))
+        .map_err(|e| format!("Invalid zip archive: {}", e))?;
+    progress.progress.total = zip.len();
+
+    for index in 0..zip.len() {
+        let mut entry = zip
+            .by_index(index)
+            .map_err(|e| format!("Failed to read archive entry: {}", e))?;
+        let Some(relative) = entry.enclosed_name().and_then(|p| safe_entry_path(&p)) else {
+            progress.step(Path::new(entry.name()), true);
+            continue;
+        };
+        let target = dest.join(&relative);
+
+        if entry.is_dir() {
+            std::fs::create_dir_all(&target)
+                .map_err(|e| format!("Failed to create directory: {}", e))?;
+            progress.step(&target, false);
+            continue;
+        }
+
+        let Some(target) = file_target(target, policy)? else {
+            progress.step(&relative, true);
+            continue;
+        };
+        create_parent(&target)?;
+        let mut output = File::create(&target)
+            .map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+        std::io::copy(&mut entry, &mut output)
+            .map_err(|e| format!("Failed to extract {}: {}", target.display(), e))?;
+
+        #[cfg(unix)]
+        if let Some(mode) = entry.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = std::fs::set_permissions(&target, std::fs::Permissions::from_mode(mode));
+        }
+        progress.step(&target, false);
+    }
+
+    Ok(())
+}
+
+fn extract_tar<R: std::io::Read>(
+    reader: R,
+    dest: &Path,
+    policy: OverwritePolicy,
+    progress: &mut Progress,
+) -> Result<(), String> {
+    let mut archive = tar::Archive::new(reader);
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("Invalid tar archive: {}", e))?;
+
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read archive entry: {}", e))?;
+        let path = entry
+            .path()
+            .map(|p| p.into_owned())
+            .map_err(|e| format!("Invalid archive entry path: {}", e))?;
+        let kind = entry.header().entry_type();
+
+        // Links could point outside the target directory
+        let Some(relative) = safe_entry_path(&path).filter(|_| kind.is_file() || kind.is_dir())
+        else {
+            progress.step(&path, true);
+            continue;
+        };
+        let target = dest.join(&relative);
+
+        if kind.is_dir() {
+            std::fs::create_dir_all(&target)
+                .map_err(|e| format!("Failed to create directory: {}", e))?;
+            progress.step(&target, false);
+            continue;
+        }
+
+        let Some(target) = file_target(target, policy)? else {
+            progress.step(&relative, true);
+            continue;
+        };
+        create_parent(&target)?;
+        entry
+            .unpack(&target)
+            .map_err(|e| format!("Failed to extract {}: {}", target.display(), e))?;
+        progress.step(&target, false);
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Zip files and folders into `dest_path`, reporting `fs://archive-progress`.
+///
+/// With the default rename policy an existing archive is kept and the new
+/// one gets a free "name (1).zip" name; skip refuses to write over it.
+#[tauri::command]
+pub async fn compress_paths(
+    app: AppHandle,
+    paths: Vec<String>,
+    dest_path: String,
+    policy: Option<OverwritePolicy>,
+    operation_id: Option<String>,
+) -> Result<FileNode, String> {
+    if paths.is_empty() {
+        return Err("Nothing to compress".to_string());
+    }
+    let sources: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+    if let Some(missing) = sources.iter().find(|p| !p.exists()) {
+        return Err(format!("Path does not exist: {}", missing.display()));
+    }
+
+    let mut target = PathBuf::from(&dest_path);
+    if target.exists() {
+        match policy.unwrap_or_default() {
+            OverwritePolicy::Skip => {
+                return Err(format!("Archive already exists: {}", dest_path));
+            }
+            OverwritePolicy::Rename => target = unique_target(&target),
+            OverwritePolicy::Overwrite => {}
+        }
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let total = sources
+            .iter()
+            .map(|source| {
+                WalkDir::new(source)
+                    .into_iter()
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.file_type().is_file())
+                    .count()
+            })
+            .sum();
+        let mut progress = Progress::new(app, operation_id, total);
+
+        create_parent(&target)?;
+        let result = write_zip(&sources, &target, &mut progress);
+        progress.finish();
+
+        if result.is_err() {
+            let _ = std::fs::remove_file(&target);
+        }
+        result.map(|_| file_node(&target))
+    })
+    .await
+    .map_err(|e| format!("Archive task failed: {}", e))?
+}
+
+/// Extract a .zip, .tar or .tar.gz archive into `dest_dir`.
+///
+/// Files that already exist follow the policy (renamed by default); entries
+/// that would land outside `dest_dir`, and links, are skipped. Progress
+/// totals are 0 for tar archives, which have no index to count.
+#[tauri::command]
+pub async fn extract_archive(
+    app: AppHandle,
+    archive_path: String,
+    dest_dir: String,
+    policy: Option<OverwritePolicy>,
+    operation_id: Option<String>,
+) -> Result<FileNode, String> {
+    let archive = PathBuf::from(&archive_path);
+    if !archive.is_file() {
+        return Err(format!("Path is not a file: {}", archive_path));
+    }
+    let format = archive_format(&archive)
+        .ok_or_else(|| format!("Unsupported archive type: {}", archive_path))?;
+
+    let dest = PathBuf::from(&dest_dir);
+    std::fs::create_dir_all(&dest).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let policy = policy.unwrap_or_default();
+
+    tokio::task::spawn_blocking(move || {
+        let mut progress = Progress::new(app, operation_id, 0);
+
+        let result = match format {
+            ArchiveFormat::Zip => extract_zip(&archive, &dest, policy, &mut progress),
+            ArchiveFormat::Tar | ArchiveFormat::TarGz => File::open(&archive)
+                .map_err(|e| format!("Failed to open archive: {}", e))
+                .and_then(|file| {
+                    let reader = BufReader::new(file);
+                    if format == ArchiveFormat::TarGz {
+                        let decoder = flate2::read::GzDecoder::new(reader);
+                        extract_tar(decoder, &dest, policy, &mut progress)
+                    } else {
+                        extract_tar(reader, &dest, policy, &mut progress)
+                    }
+                }),
+        };
+        progress.finish();
+
+        result.map(|_| file_node(&dest))
+    })
+    .await
+    .map_err(|e| format!("Archive task failed: {}", e))?
+}
@@ -0,0 +1,280 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+// ============================================================================
+// AUTOSAVE TYPES
+// ============================================================================
+
+/// How often changed buffers are written to the journal
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Larger buffers are not journaled
+const MAX_BUFFER_SIZE: usize = 20 * 1024 * 1024;
+
+/// Unsaved content of one editor buffer, as kept in the journal
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BufferSnapshot {
+    /// Chosen by the frontend; the file path for file buffers
+    pub buffer_id: String,
+    /// File the buffer edits; None for untitled buffers
+    pub path: Option<String>,
+    pub content: String,
+    pub language: Option<String>,
+    /// Hash of the file when it was opened (from read_file), to tell
+    /// whether it changed on disk since
+    pub base_hash: Option<String>,
+    /// Milliseconds since epoch
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoverableBuffer {
+    pub buffer_id: String,
+    pub path: Option<String>,
+    pub language: Option<String>,
+    pub updated_at: i64,
+    pub size_bytes: usize,
+    /// The file was modified or deleted after the buffer was opened, so
+    /// restoring it would overwrite those changes
+    pub disk_changed: bool,
+}
+
+/// Dirty buffers waiting to be written to the journal
+#[derive(Default)]
+pub struct AutosaveJournal {
+    pending: Mutex<HashMap<String, BufferSnapshot>>,
+    /// Buffers edited in this run, kept out of recover_unsaved
+    session: Mutex<HashSet<String>>,
+    flusher_started: AtomicBool,
+}
+
+impl AutosaveJournal {
+    /// Write pending buffers right away (e.g. when a window is closed)
+    pub fn flush_now(&self, app: &AppHandle) {
+        flush(app, self);
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn journal_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?
+        .join("autosave"))
+}
+
+/// Journal file of a buffer; FNV-1a keeps the name stable across runs
+fn journal_path(dir: &Path, buffer_id: &str) -> PathBuf {
+    let hash = buffer_id.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    dir.join(format!("{:016x}.json", hash))
+}
+
+fn read_snapshots(dir: &Path) -> Vec<BufferSnapshot> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|e| {
+            let json = std::fs::read(e.path()).ok()?;
+            serde_json::from_slice(&json)
+                .map_err(|err| {
+                    log::warn!(
+                        "Ignoring unreadable autosave {}: {}",
+                        e.path().display(),
+                        err
+                    )
+                })
+                .ok()
+        })
+        .collect()
+}
+
+fn flush(app: &AppHandle, journal: &AutosaveJournal) {
+    let pending: Vec<BufferSnapshot> = match journal.pending.lock() {
+        Ok(mut pending) => pending.drain().map(|(_, snapshot)| snapshot).collect(),
+        Err(_) => return,
+    };
+    if pending.is_empty() {
+        return;
+    }
+    let dir = match journal_dir(app) {
+        Ok(dir) => dir,
+        Err(e) => {
+            log::warn!("Autosave failed: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        log::warn!("Autosave failed to create {}: {}", dir.display(), e);
+        return;
+    }
+
+    for snapshot in pending {
+        let written = serde_json::to_vec(&snapshot)
+            .map_err(|e| e.to_string())
+            .and_then(|json| crate::atomic_write(&journal_path(&dir, &snapshot.buffer_id), &json));
+        if let Err(e) = written {
+            log::warn!("Autosave of {} failed: {}", snapshot.buffer_id, e);
+        }
+    }
+}
+
+/// Start the periodic flush on first use
+fn ensure_flusher(app: &AppHandle, journal: &AutosaveJournal) {
+    if journal.flusher_started.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app = app.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+        loop {
+            interval.tick().await;
+            let app = app.clone();
+            let _ = tokio::task::spawn_blocking(move || {
+                flush(&app, &app.state::<AutosaveJournal>());
+            })
+            .await;
+        }
+    });
+}
+
+fn forget(app: &AppHandle, journal: &AutosaveJournal, buffer_id: &str) -> Result<(), String> {
+    journal
+        .pending
+        .lock()
+        .map_err(|_| "Autosave journal is poisoned".to_string())?
+        .remove(buffer_id);
+
+    let path = journal_path(&journal_dir(app)?, buffer_id);
+    if path.exists() {
+        std::fs::remove_file(&path).map_err(|e| format!("Failed to remove autosave: {}", e))?;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Record the latest content of a dirty buffer; it reaches the journal on
+/// the next periodic flush (every couple of seconds)
+#[tauri::command]
+pub async fn buffer_changed(
+    app: AppHandle,
+    journal: State<'_, AutosaveJournal>,
+    buffer_id: String,
+    path: Option<String>,
+    content: String,
+    language: Option<String>,
+    base_hash: Option<String>,
+) -> Result<(), String> {
+    if content.len() > MAX_BUFFER_SIZE {
+        return Ok(());
+    }
+    ensure_flusher(&app, &journal);
+
+    journal
+        .session
+        .lock()
+        .map_err(|_| "Autosave journal is poisoned".to_string())?
+        .insert(buffer_id.clone());
+    journal
+        .pending
+        .lock()
+        .map_err(|_| "Autosave journal is poisoned".to_string())?
+        .insert(
+            buffer_id.clone(),
+            BufferSnapshot {
+                buffer_id,
+                path,
+                content,
+                language,
+                base_hash,
+                updated_at: chrono::Utc::now().timestamp_millis(),
+            },
+        );
+    Ok(())
+}
+
+/// Drop a buffer from the journal once it is saved, or closed without saving
+#[tauri::command]
+pub async fn buffer_saved(
+    app: AppHandle,
+    journal: State<'_, AutosaveJournal>,
+    buffer_id: String,
+) -> Result<(), String> {
+    forget(&app, &journal, &buffer_id)
+}
+
+/// Buffers left unsaved by an earlier run (a crash or forced quit), newest
+/// first. Restore one with restore_unsaved or drop it with buffer_saved.
+#[tauri::command]
+pub async fn recover_unsaved(
+    app: AppHandle,
+    journal: State<'_, AutosaveJournal>,
+) -> Result<Vec<RecoverableBuffer>, String> {
+    let session = journal
+        .session
+        .lock()
+        .map_err(|_| "Autosave journal is poisoned".to_string())?
+        .clone();
+
+    let mut buffers: Vec<RecoverableBuffer> = read_snapshots(&journal_dir(&app)?)
+        .into_iter()
+        .filter(|snapshot| !session.contains(&snapshot.buffer_id))
+        .map(|snapshot| {
+            let disk_changed = match (&snapshot.path, &snapshot.base_hash) {
+                (Some(path), Some(base_hash)) => std::fs::read(path)
+                    .map(|bytes| crate::content_hash(&bytes) != *base_hash)
+                    .unwrap_or(true),
+                _ => false,
+            };
+            RecoverableBuffer {
+                size_bytes: snapshot.content.len(),
+                buffer_id: snapshot.buffer_id,
+                path: snapshot.path,
+                language: snapshot.language,
+                updated_at: snapshot.updated_at,
+                disk_changed,
+            }
+        })
+        .collect();
+
+    buffers.sort_by_key(|b| std::cmp::Reverse(b.updated_at));
+    Ok(buffers)
+}
+
+/// Full content of a journaled buffer, to reopen it as a dirty tab. The
+/// journal keeps it until buffer_saved is called.
+#[tauri::command]
+pub async fn restore_unsaved(
+    app: AppHandle,
+    journal: State<'_, AutosaveJournal>,
+    buffer_id: String,
+) -> Result<BufferSnapshot, String> {
+    let path = journal_path(&journal_dir(&app)?, &buffer_id);
+    let json = std::fs::read(&path).map_err(|e| format!("Failed to read autosave: {}", e))?;
+    let snapshot: BufferSnapshot =
+        serde_json::from_slice(&json).map_err(|e| format!("Invalid autosave: {}", e))?;
+
+    // From now on the buffer belongs to this run again
+    journal
+        .session
+        .lock()
+        .map_err(|_| "Autosave journal is poisoned".to_string())?
+        .insert(snapshot.buffer_id.clone());
+    Ok(snapshot)
+}
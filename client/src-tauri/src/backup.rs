@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+// ============================================================================
+// BACKUP TYPES
+// ============================================================================
+
+/// Backups of rewritten files live under the app data directory
+const BACKUP_DIR: &str = "file_backups";
+const BACKUP_MANIFEST: &str = "manifest.json";
+
+/// Where each backed up file came from
+#[derive(Debug, Serialize, Deserialize)]
+struct BackupManifest {
+    /// Milliseconds since epoch
+    created_at: i64,
+    /// Absolute paths, in the order the backup files are numbered
+    files: Vec<String>,
+}
+
+/// Original contents of the files one bulk edit (replace, conversion)
+/// rewrites, so the whole edit can be undone with restore_backup
+pub(crate) struct FileBackup {
+    id: String,
+    dir: PathBuf,
+    manifest: BackupManifest,
+}
+
+impl FileBackup {
+    /// Start a backup; nothing is written until the first file is saved
+    pub(crate) fn new(app: &AppHandle) -> Result<Self, String> {
+        let id = uuid::Uuid::new_v4().to_string();
+        Ok(FileBackup {
+            dir: backups_dir(app)?.join(&id),
+            id,
+            manifest: BackupManifest {
+                created_at: chrono::Utc::now().timestamp_millis(),
+                files: Vec::new(),
+            },
+        })
+    }
+
+    /// Id to pass to restore_backup, once at least one file was saved
+    pub(crate) fn id(&self) -> Option<String> {
+        (!self.manifest.files.is_empty()).then(|| self.id.clone())
+    }
+
+    /// Back up `original` and atomically replace the file with `content`.
+    /// On failure the file is left as it was and not part of the backup.
+    pub(crate) fn write(
+        &mut self,
+        path: &Path,
+        original: &[u8],
+        content: &[u8],
+    ) -> Result<(), String> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+
+        let backup_file = self.dir.join(self.manifest.files.len().to_string());
+        let written = std::fs::write(&backup_file, original)
+            .map_err(|e| format!("Failed to back up file: {}", e))
+            .and_then(|_| crate::atomic_write(path, content));
+        if let Err(e) = written {
+            let _ = std::fs::remove_file(&backup_file);
+            return Err(e);
+        }
+
+        // Kept current so a later failure never leaves a file without its backup
+        self.manifest.files.push(path.to_string_lossy().to_string());
+        let json = serde_json::to_vec_pretty(&self.manifest)
+            .map_err(|e| format!("Failed to serialize backup manifest: {}", e))?;
+        crate::atomic_write(&self.dir.join(BACKUP_MANIFEST), &json)
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn backups_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?
+        .join(BACKUP_DIR))
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Restore the files rewritten by replace_in_files or convert_text_format
+/// from their backup, returning the restored paths. The backup is removed
+/// afterwards.
+#[tauri::command]
+pub async fn restore_backup(app: AppHandle, backup_id: String) -> Result<Vec<String>, String> {
+    if uuid::Uuid::parse_str(&backup_id).is_err() {
+        return Err(format!("Invalid backup id: {}", backup_id));
+    }
+    let dir = backups_dir(&app)?.join(&backup_id);
+
+    let json = std::fs::read(dir.join(BACKUP_MANIFEST))
+        .map_err(|e| format!("Failed to read backup: {}", e))?;
+    let manifest: BackupManifest =
+        serde_json::from_slice(&json).map_err(|e| format!("Invalid backup manifest: {}", e))?;
+
+    for (index, file) in manifest.files.iter().enumerate() {
+        let content = std::fs::read(dir.join(index.to_string()))
+            .map_err(|e| format!("Failed to read backup of {}: {}", file, e))?;
+        crate::atomic_write(Path::new(file), &content)?;
+    }
+
+    std::fs::remove_dir_all(&dir).map_err(|e| format!("Failed to remove backup: {}", e))?;
+    Ok(manifest.files)
+}
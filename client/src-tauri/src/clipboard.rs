@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, State};
+
+use crate::transfer::{self, OverwritePolicy, TransferMode};
+use crate::FileNode;
+
+// ============================================================================
+// CLIPBOARD TYPES
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardMode {
+    Copy,
+    Cut,
+}
+
+/// Files waiting to be pasted
+#[derive(Debug, Serialize, Clone)]
+pub struct FileClipboard {
+    pub paths: Vec<String>,
+    pub mode: ClipboardMode,
+    /// Copied in another application, e.g. the OS file manager
+    pub external: bool,
+}
+
+#[derive(Default)]
+struct ClipboardInner {
+    /// What was last copied or cut in the file tree
+    entry: Option<(Vec<PathBuf>, ClipboardMode)>,
+    /// Kept open: on Linux the OS clipboard loses what we set once it is dropped
+    os: Option<arboard::Clipboard>,
+}
+
+/// File tree clipboard, shared by every window so files can be pasted
+/// across them
+#[derive(Default)]
+pub struct ClipboardState {
+    inner: Mutex<ClipboardInner>,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+impl ClipboardInner {
+    fn os(&mut self) -> Option<&mut arboard::Clipboard> {
+        if self.os.is_none() {
+            self.os = arboard::Clipboard::new()
+                .map_err(|e| log::warn!("OS clipboard unavailable: {}", e))
+                .ok();
+        }
+        self.os.as_mut()
+    }
+
+    /// Files on the OS clipboard: a file list, or file URIs / absolute paths
+    /// copied as text
+    fn os_files(&mut self) -> Vec<PathBuf> {
+        let Some(os) = self.os() else {
+            return Vec::new();
+        };
+        if let Ok(files) = os.get().file_list() {
+            if !files.is_empty() {
+                return files;
+            }
+        }
+
+        let text = os.get_text().unwrap_or_default();
+        let paths: Vec<PathBuf> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| match url::Url::parse(line) {
+                Ok(url) if url.scheme() == "file" => url.to_file_path().ok(),
+                _ => Some(PathBuf::from(line)).filter(|p| p.is_absolute()),
+            })
+            .collect::<Option<_>>()
+            .unwrap_or_default();
+        // Only treat text as a file list when every line names an existing file
+        if paths.iter().all(|p| p.exists()) {
+            paths
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// The files a paste would use. Our own entry wins while the OS
+    /// clipboard still holds it (or holds no files); anything else copied
+    /// since then is pasted as a copy.
+    fn current(&mut self) -> Option<FileClipboard> {
+        let os_files = self.os_files();
+        match &self.entry {
+            Some((paths, mode)) if os_files.is_empty() || os_files == *paths => {
+                Some(FileClipboard {
+                    paths: paths
+                        .iter()
+                        .map(|p| p.to_string_lossy().to_string())
+                        .collect(),
+                    mode: *mode,
+                    external: false,
+                })
+            }
+            _ if !os_files.is_empty() => Some(FileClipboard {
+                paths: os_files
+                    .iter()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .collect(),
+                mode: ClipboardMode::Copy,
+                external: true,
+            }),
+            _ => None,
+        }
+    }
+}
+
+fn set_entry(
+    state: &ClipboardState,
+    paths: Vec<String>,
+    mode: ClipboardMode,
+) -> Result<(), String> {
+    let paths: Vec<PathBuf> = paths.into_iter().map(PathBuf::from).collect();
+    if paths.is_empty() {
+        return Err("Nothing to copy".to_string());
+    }
+    if let Some(missing) = paths.iter().find(|p| !p.exists()) {
+        return Err(format!("Path does not exist: {}", missing.display()));
+    }
+
+    let mut inner = state
+        .inner
+        .lock()
+        .map_err(|_| "Clipboard state is poisoned".to_string())?;
+    // Also offer the files to other applications; the file tree works without it
+    if let Some(os) = inner.os() {
+        if let Err(e) = os.set().file_list(&paths) {
+            log::warn!("Failed to put files on the OS clipboard: {}", e);
+        }
+    }
+    inner.entry = Some((paths, mode));
+    Ok(())
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Copy files and folders from the tree, also putting them on the OS
+/// clipboard so other applications can paste them
+#[tauri::command]
+pub async fn copy_to_clipboard(
+    state: State<'_, ClipboardState>,
+    paths: Vec<String>,
+) -> Result<(), String> {
+    set_entry(&state, paths, ClipboardMode::Copy)
+}
+
+/// Cut files and folders from the tree; they are moved when pasted
+#[tauri::command]
+pub async fn cut_to_clipboard(
+    state: State<'_, ClipboardState>,
+    paths: Vec<String>,
+) -> Result<(), String> {
+    set_entry(&state, paths, ClipboardMode::Cut)
+}
+
+/// What a paste would insert, including files copied in the OS file
+/// manager, or None when the clipboard holds no files
+#[tauri::command]
+pub async fn read_clipboard_files(
+    state: State<'_, ClipboardState>,
+) -> Result<Option<FileClipboard>, String> {
+    Ok(state
+        .inner
+        .lock()
+        .map_err(|_| "Clipboard state is poisoned".to_string())?
+        .current())
+}
+
+/// Paste the clipboard's files into `dest_dir`.
+///
+/// Copied files are copied (a file pasted next to itself gets a
+/// "name (1)" copy by default) and cut files are moved, after which the
+/// clipboard is cleared. Each pasted entry reports `fs://transfer-progress`
+/// under `operation_id`.
+#[tauri::command]
+pub async fn paste_from_clipboard(
+    app: AppHandle,
+    state: State<'_, ClipboardState>,
+    dest_dir: String,
+    policy: Option<OverwritePolicy>,
+    operation_id: Option<String>,
+) -> Result<Vec<FileNode>, String> {
+    let clipboard = state
+        .inner
+        .lock()
+        .map_err(|_| "Clipboard state is poisoned".to_string())?
+        .current()
+        .ok_or_else(|| "The clipboard holds no files".to_string())?;
+
+    let mode = match clipboard.mode {
+        ClipboardMode::Copy => TransferMode::Copy,
+        ClipboardMode::Cut => TransferMode::Move,
+    };
+    let mut pasted = Vec::with_capacity(clipboard.paths.len());
+    for path in clipboard.paths {
+        pasted.push(
+            transfer::run_transfer(
+                app.clone(),
+                mode,
+                path,
+                dest_dir.clone(),
+                policy,
+                operation_id.clone(),
+            )
+            .await?,
+        );
+    }
+
+    // Cut files now live elsewhere; pasting them again would fail
+    if clipboard.mode == ClipboardMode::Cut {
+        let mut inner = state
+            .inner
+            .lock()
+            .map_err(|_| "Clipboard state is poisoned".to_string())?;
+        inner.entry = None;
+        if let Some(os) = inner.os() {
+            let _ = os.clear();
+        }
+    }
+
+    Ok(pasted)
+}
+
+#[tauri::command]
+pub async fn clear_clipboard(state: State<'_, ClipboardState>) -> Result<(), String> {
+    state
+        .inner
+        .lock()
+        .map_err(|_| "Clipboard state is poisoned".to_string())?
+        .entry = None;
+    Ok(())
+}
@@ -0,0 +1,654 @@
+use crate::host::HostSession;
+use collab_protocol::{ClientMessage, ServerMessage, SyncProtocol, PROTOCOL_VERSION};
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+// ============================================================================
+// COLLAB TYPES
+// ============================================================================
+
+/// Document sync traffic: joins, Automerge sync messages, sync completion
+pub const COLLAB_SYNC_EVENT: &str = "collab://sync";
+
+/// Peers joining, leaving, changing status or name, opening files
+pub const COLLAB_PRESENCE_EVENT: &str = "collab://presence";
+
+/// Remote cursors and reactions
+pub const COLLAB_CURSOR_EVENT: &str = "collab://cursor";
+
+/// Chat broadcasts and history
+pub const COLLAB_CHAT_EVENT: &str = "collab://chat";
+
+/// Every other server message (errors, file content, activity, voice, stats)
+pub const COLLAB_MESSAGE_EVENT: &str = "collab://message";
+
+/// Offline/online transitions and the number of edits waiting to be replayed
+pub const COLLAB_CONNECTION_EVENT: &str = "collab://connection";
+
+/// Emitted once when the connection ends for good: every reconnect attempt
+/// failed (the window's `disconnect` does not emit it)
+pub const COLLAB_DISCONNECTED_EVENT: &str = "collab://disconnected";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+const MAX_RECONNECT_ATTEMPTS: u32 = 10;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CollabSession {
+    pub project_id: String,
+    pub peer_id: String,
+    pub color: String,
+    /// Pass back to `connect_to_project` to restore the session after a drop
+    pub session_token: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CollabDisconnected {
+    pub project_id: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CollabConnectionStatus {
+    pub project_id: String,
+    pub online: bool,
+    /// Document edits queued while offline
+    pub pending_changes: usize,
+    /// Current reconnect attempt, None while online
+    pub reconnect_attempt: Option<u32>,
+}
+
+/// Where to reconnect to, reusing the session token so the server restores the peer
+struct Reconnect {
+    url: String,
+    project_id: String,
+    display_name: String,
+    session_token: String,
+    share_token: Option<String>,
+}
+
+/// Edits made while the connection is down
+#[derive(Default)]
+struct OfflineQueue {
+    changes: VecDeque<ClientMessage>,
+    attempt: Option<u32>,
+}
+
+impl OfflineQueue {
+    /// Keep Automerge changes for replay; cursors, presence and the like are
+    /// stale by the time the connection is back and are dropped
+    fn push(&mut self, message: ClientMessage) -> bool {
+        let keep = matches!(message, ClientMessage::SyncMessage { .. });
+        if keep {
+            self.changes.push_back(message);
+        }
+        keep
+    }
+}
+
+struct Connection {
+    id: String,
+    project_id: String,
+    outgoing: mpsc::UnboundedSender<ClientMessage>,
+}
+
+impl Connection {
+    /// Ask the connection task to say goodbye and close the socket
+    fn close(&self) {
+        let _ = self.outgoing.send(ClientMessage::Goodbye { reason: None });
+    }
+}
+
+/// Server connections keyed by the label of the window that owns them
+#[derive(Default)]
+pub struct CollabRegistry {
+    connections: Mutex<HashMap<String, Connection>>,
+}
+
+impl CollabRegistry {
+    /// Disconnect the window's session (e.g. when it is closed)
+    pub fn close_window(&self, label: &str) {
+        if let Some(connection) = self
+            .connections
+            .lock()
+            .ok()
+            .and_then(|mut connections| connections.remove(label))
+        {
+            connection.close();
+        }
+    }
+
+    /// Queue a message on the window's connection
+    pub(crate) fn send(&self, label: &str, message: ClientMessage) -> Result<(), String> {
+        let connections = self
+            .connections
+            .lock()
+            .map_err(|_| "Collab registry is poisoned".to_string())?;
+        let connection = connections.get(label).ok_or("Not connected")?;
+
+        connection
+            .outgoing
+            .send(message)
+            .map_err(|_| "Connection is closed".to_string())
+    }
+
+    /// Forget a connection that ended on its own, unless it was already replaced
+    fn remove_if_current(&self, label: &str, connection_id: &str) -> bool {
+        let Ok(mut connections) = self.connections.lock() else {
+            return false;
+        };
+
+        if connections.get(label).map(|c| c.id.as_str()) == Some(connection_id) {
+            connections.remove(label);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn event_for(message: &ServerMessage) -> &'static str {
+    match message {
+        ServerMessage::ProjectJoined { .. }
+        | ServerMessage::ProjectLeft { .. }
+        | ServerMessage::SyncMessage { .. }
+        | ServerMessage::SyncComplete { .. } => COLLAB_SYNC_EVENT,
+        ServerMessage::PeerJoined { .. }
+        | ServerMessage::PeerLeft { .. }
+        | ServerMessage::PresenceBroadcast { .. }
+        | ServerMessage::DisplayNameChanged { .. }
+        | ServerMessage::FileOpened { .. }
+        | ServerMessage::FileClosed { .. } => COLLAB_PRESENCE_EVENT,
+        ServerMessage::CursorBroadcast { .. } | ServerMessage::ReactionBroadcast { .. } => {
+            COLLAB_CURSOR_EVENT
+        }
+        ServerMessage::ChatBroadcast { .. }
+        | ServerMessage::ChatHistory { .. }
+        | ServerMessage::ChatDeleted { .. } => COLLAB_CHAT_EVENT,
+        _ => COLLAB_MESSAGE_EVENT,
+    }
+}
+
+async fn connect_async_url(
+    url: &str,
+) -> Result<
+    (
+        Socket,
+        tokio_tungstenite::tungstenite::handshake::client::Response,
+    ),
+    String,
+> {
+    tokio_tungstenite::connect_async(url)
+        .await
+        .map_err(|e| format!("Failed to connect to {}: {}", url, e))
+}
+
+async fn send_message(socket: &mut Socket, message: &ClientMessage) -> Result<(), String> {
+    let bytes = SyncProtocol::encode_client(message)
+        .map_err(|e| format!("Failed to encode message: {}", e))?;
+    socket
+        .send(Message::Binary(bytes.to_vec()))
+        .await
+        .map_err(|e| format!("Failed to send message: {}", e))
+}
+
+/// Wait for the next binary protocol message, skipping control frames
+async fn next_server_message(socket: &mut Socket) -> Result<ServerMessage, String> {
+    while let Some(frame) = socket.next().await {
+        match frame.map_err(|e| format!("Connection error: {}", e))? {
+            Message::Binary(data) => {
+                return SyncProtocol::decode_server(&data)
+                    .map_err(|e| format!("Failed to decode message: {}", e))
+            }
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+
+    Err("Connection closed by server".to_string())
+}
+
+/// Wait for a change in the hosted folder (never resolves when not hosting)
+async fn next_folder_change(
+    host: &mut Option<HostSession>,
+) -> Option<notify::Result<notify::Event>> {
+    match host.as_mut() {
+        Some(host) => host.next_change().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Handshake with the server and join the project
+async fn open_session(
+    url: &str,
+    project_id: &str,
+    display_name: &str,
+    session_token: Option<String>,
+    share_token: Option<String>,
+) -> Result<(Socket, CollabSession), String> {
+    let (mut socket, _) = tokio::time::timeout(CONNECT_TIMEOUT, connect_async_url(url))
+        .await
+        .map_err(|_| format!("Timed out connecting to {}", url))??;
+
+    let welcome = tokio::time::timeout(CONNECT_TIMEOUT, next_server_message(&mut socket))
+        .await
+        .map_err(|_| "Timed out waiting for the server handshake".to_string())??;
+
+    let session = match welcome {
+        ServerMessage::Welcome {
+            protocol_version,
+            peer_id,
+            color,
+            session_token,
+            ..
+        } => {
+            if protocol_version != PROTOCOL_VERSION {
+                return Err(format!(
+                    "Server speaks protocol version {}, expected {}",
+                    protocol_version, PROTOCOL_VERSION
+                ));
+            }
+            CollabSession {
+                project_id: project_id.to_string(),
+                peer_id,
+                color,
+                session_token,
+            }
+        }
+        ServerMessage::Error { message, .. } => {
+            return Err(format!("Server refused connection: {}", message))
+        }
+        _ => return Err("Unexpected handshake from server".to_string()),
+    };
+
+    send_message(
+        &mut socket,
+        &ClientMessage::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            client_id: None,
+            client_name: display_name.to_string(),
+            session_token,
+            auth_token: None,
+            preferred_color: None,
+        },
+    )
+    .await?;
+    send_message(
+        &mut socket,
+        &ClientMessage::JoinProject {
+            project_id: project_id.to_string(),
+            request_state: true,
+            share_token,
+        },
+    )
+    .await?;
+
+    Ok((socket, session))
+}
+
+fn emit_status(app: &AppHandle, label: &str, target: &Reconnect, queue: &OfflineQueue) {
+    let _ = app.emit_to(
+        label,
+        COLLAB_CONNECTION_EVENT,
+        CollabConnectionStatus {
+            project_id: target.project_id.clone(),
+            online: queue.attempt.is_none(),
+            pending_changes: queue.changes.len(),
+            reconnect_attempt: queue.attempt,
+        },
+    );
+}
+
+/// Pump messages until the connection ends.
+///
+/// Returns Ok when the window closed it, or the reason it dropped.
+async fn pump(
+    app: &AppHandle,
+    label: &str,
+    socket: &mut Socket,
+    outgoing: &mut mpsc::UnboundedReceiver<ClientMessage>,
+    host: &mut Option<HostSession>,
+    queue: &mut OfflineQueue,
+) -> Result<(), String> {
+    loop {
+        tokio::select! {
+            message = outgoing.recv() => {
+                let Some(message) = message else {
+                    let _ = socket.close(None).await;
+                    return Ok(());
+                };
+                let goodbye = matches!(message, ClientMessage::Goodbye { .. });
+                if let Err(e) = send_message(socket, &message).await {
+                    queue.push(message);
+                    return Err(e);
+                }
+                if goodbye {
+                    let _ = socket.close(None).await;
+                    return Ok(());
+                }
+            }
+            frame = socket.next() => match frame {
+                Some(Ok(Message::Binary(data))) => match SyncProtocol::decode_server(&data) {
+                    Ok(ServerMessage::FileRequest { file_path, .. }) if host.is_some() => {
+                        let Some(host) = host.as_mut() else { continue };
+                        let reply = host.serve_file(&file_path).await;
+                        send_message(socket, &reply).await?;
+                    }
+                    Ok(message) => {
+                        let _ = app.emit_to(label, event_for(&message), message);
+                    }
+                    Err(e) => log::warn!("Ignoring undecodable server message: {}", e),
+                },
+                Some(Ok(Message::Close(_))) | None => {
+                    return Err("Connection closed by server".to_string());
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(format!("Connection error: {}", e)),
+            },
+            Some(event) = next_folder_change(host) => {
+                let Some(host) = host.as_mut() else { continue };
+                for message in host.handle_change(event) {
+                    if let Err(e) = send_message(socket, &message).await {
+                        log::warn!("Failed to sync hosted folder: {}", e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Reconnect with exponential backoff, queueing edits in the meantime.
+///
+/// Returns None when the window closed the connection while offline, or the
+/// last error once every attempt failed.
+async fn reconnect(
+    app: &AppHandle,
+    label: &str,
+    target: &mut Reconnect,
+    outgoing: &mut mpsc::UnboundedReceiver<ClientMessage>,
+    host: &mut Option<HostSession>,
+    queue: &mut OfflineQueue,
+) -> Result<Socket, Option<String>> {
+    let mut delay = RECONNECT_BASE_DELAY;
+    let mut last_error = None;
+
+    for attempt in 1..=MAX_RECONNECT_ATTEMPTS {
+        queue.attempt = Some(attempt);
+        emit_status(app, label, target, queue);
+
+        let backoff = tokio::time::sleep(delay);
+        tokio::pin!(backoff);
+        loop {
+            tokio::select! {
+                _ = &mut backoff => break,
+                message = outgoing.recv() => match message {
+                    None | Some(ClientMessage::Goodbye { .. }) => return Err(None),
+                    Some(message) => {
+                        if queue.push(message) {
+                            emit_status(app, label, target, queue);
+                        }
+                    }
+                },
+                Some(event) = next_folder_change(host) => {
+                    // Keep the tree current; it is uploaded whole on reconnect
+                    if let Some(host) = host.as_mut() {
+                        host.handle_change(event);
+                    }
+                }
+            }
+        }
+
+        match open_session(
+            &target.url,
+            &target.project_id,
+            &target.display_name,
+            Some(target.session_token.clone()),
+            target.share_token.clone(),
+        )
+        .await
+        {
+            Ok((socket, session)) => {
+                target.session_token = session.session_token;
+                return Ok(socket);
+            }
+            Err(e) => {
+                log::warn!("Reconnect attempt {} failed: {}", attempt, e);
+                last_error = Some(e);
+                delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+            }
+        }
+    }
+
+    Err(last_error)
+}
+
+/// Re-host the folder and replay queued edits on a fresh connection
+async fn resume(
+    socket: &mut Socket,
+    host: &mut Option<HostSession>,
+    queue: &mut OfflineQueue,
+) -> Result<(), String> {
+    if let Some(host) = host.as_mut() {
+        send_message(socket, &host.host_message()).await?;
+    }
+
+    while let Some(message) = queue.changes.pop_front() {
+        if let Err(e) = send_message(socket, &message).await {
+            queue.changes.push_front(message);
+            return Err(e);
+        }
+    }
+
+    queue.attempt = None;
+    Ok(())
+}
+
+/// Pump outgoing messages to the socket and server messages to the window,
+/// reconnecting whenever the connection drops
+async fn run_connection(
+    app: AppHandle,
+    label: String,
+    connection_id: String,
+    mut target: Reconnect,
+    mut socket: Socket,
+    mut outgoing: mpsc::UnboundedReceiver<ClientMessage>,
+    mut host: Option<HostSession>,
+) {
+    let mut queue = OfflineQueue::default();
+
+    let reason = loop {
+        let dropped = match pump(
+            &app,
+            &label,
+            &mut socket,
+            &mut outgoing,
+            &mut host,
+            &mut queue,
+        )
+        .await
+        {
+            Ok(()) => break None,
+            Err(reason) => reason,
+        };
+        log::warn!("Collab connection dropped: {}", dropped);
+
+        match reconnect(
+            &app,
+            &label,
+            &mut target,
+            &mut outgoing,
+            &mut host,
+            &mut queue,
+        )
+        .await
+        {
+            Ok(new_socket) => {
+                socket = new_socket;
+                match resume(&mut socket, &mut host, &mut queue).await {
+                    Ok(()) => emit_status(&app, &label, &target, &queue),
+                    Err(e) => log::warn!("Failed to replay offline changes: {}", e),
+                }
+            }
+            Err(None) => break None,
+            Err(Some(e)) => break Some(e),
+        }
+    };
+
+    let removed = app
+        .state::<CollabRegistry>()
+        .remove_if_current(&label, &connection_id);
+
+    if let (Some(reason), true) = (reason, removed) {
+        let _ = app.emit_to(
+            label.as_str(),
+            COLLAB_DISCONNECTED_EVENT,
+            CollabDisconnected {
+                project_id: target.project_id,
+                reason,
+            },
+        );
+    }
+}
+
+/// Connect a window to a project, join it and start pumping messages.
+///
+/// Any previous connection of the window is closed first. With `host` set the
+/// connection also serves the hosted folder to the server.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn collab_connect(
+    app: AppHandle,
+    label: &str,
+    registry: &CollabRegistry,
+    server_url: &str,
+    project_id: String,
+    display_name: Option<String>,
+    session_token: Option<String>,
+    share_token: Option<String>,
+    host: Option<HostSession>,
+) -> Result<CollabSession, String> {
+    let label = label.to_string();
+    registry.close_window(&label);
+
+    let url = format!("{}/ws/{}", server_url.trim_end_matches('/'), project_id);
+    let display_name = display_name.unwrap_or_default();
+    let (socket, session) = open_session(
+        &url,
+        &project_id,
+        &display_name,
+        session_token,
+        share_token.clone(),
+    )
+    .await?;
+
+    let connection_id = uuid::Uuid::new_v4().to_string();
+    let (outgoing, outgoing_rx) = mpsc::unbounded_channel();
+    registry
+        .connections
+        .lock()
+        .map_err(|_| "Collab registry is poisoned".to_string())?
+        .insert(
+            label.clone(),
+            Connection {
+                id: connection_id.clone(),
+                project_id: project_id.clone(),
+                outgoing,
+            },
+        );
+
+    let target = Reconnect {
+        url,
+        project_id,
+        display_name,
+        session_token: session.session_token.clone(),
+        share_token,
+    };
+    tokio::spawn(run_connection(
+        app,
+        label,
+        connection_id,
+        target,
+        socket,
+        outgoing_rx,
+        host,
+    ));
+
+    Ok(session)
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Connect the window to a project on a CodeCollab server and join it.
+///
+/// `server_url` is the WebSocket base (e.g. `ws://localhost:3001`). Server
+/// messages are forwarded as `collab://*` events; any previous connection of
+/// the window is closed first. With `share_token` the project is joined
+/// through a share link, with that link's role and scope.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn connect_to_project(
+    app: AppHandle,
+    window: Window,
+    registry: State<'_, CollabRegistry>,
+    server_url: String,
+    project_id: String,
+    display_name: Option<String>,
+    session_token: Option<String>,
+    share_token: Option<String>,
+) -> Result<CollabSession, String> {
+    collab_connect(
+        app,
+        window.label(),
+        &registry,
+        &server_url,
+        project_id,
+        display_name,
+        session_token,
+        share_token,
+        None,
+    )
+    .await
+}
+
+/// Leave the project and close the window's server connection
+#[tauri::command]
+pub async fn disconnect(window: Window, registry: State<'_, CollabRegistry>) -> Result<(), String> {
+    let connection = registry
+        .connections
+        .lock()
+        .map_err(|_| "Collab registry is poisoned".to_string())?
+        .remove(window.label())
+        .ok_or("Not connected")?;
+
+    let _ = connection.outgoing.send(ClientMessage::LeaveProject {
+        project_id: connection.project_id.clone(),
+    });
+    connection.close();
+    Ok(())
+}
+
+/// Send a protocol message (sync data, cursor, presence, chat...) to the server
+#[tauri::command]
+pub async fn send_collab_message(
+    window: Window,
+    registry: State<'_, CollabRegistry>,
+    message: ClientMessage,
+) -> Result<(), String> {
+    registry.send(window.label(), message)
+}
@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+use similar::{Algorithm, ChangeTag, TextDiff};
+use std::time::Duration;
+
+// ============================================================================
+// DIFF TYPES
+// ============================================================================
+
+/// Larger inputs are refused rather than diffed
+const MAX_DIFF_SIZE: usize = 20 * 1024 * 1024;
+
+/// After this long the diff settles for a coarser (still correct) result
+const DIFF_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Unchanged lines kept around each change when none is given
+const DEFAULT_CONTEXT_LINES: usize = 3;
+
+/// One side of a diff
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DiffSource {
+    /// Text held by the frontend (an editor buffer, a remote document)
+    Text { text: String },
+    /// A file on disk, in any encoding detect_text_format understands
+    File { path: String },
+    /// A file as committed at git HEAD; empty when HEAD does not have it
+    GitHead { path: String },
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffAlgorithm {
+    #[default]
+    Myers,
+    Patience,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LineKind {
+    Same,
+    Added,
+    Removed,
+}
+
+/// A line of a hunk with where it sits on each side (1-based), so the two
+/// panes of a side-by-side view can be lined up
+#[derive(Debug, Serialize, Clone)]
+pub struct DiffLine {
+    pub kind: LineKind,
+    /// None for added lines
+    pub old_line: Option<usize>,
+    /// None for removed lines
+    pub new_line: Option<usize>,
+    /// Without the line ending
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DiffHunk {
+    /// First line of the hunk on each side (1-based)
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TextDiffResult {
+    pub identical: bool,
+    pub old_line_count: usize,
+    pub new_line_count: usize,
+    pub added: usize,
+    pub removed: usize,
+    /// How similar the texts are, from 0.0 to 1.0
+    pub ratio: f32,
+    pub hunks: Vec<DiffHunk>,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn decode_text(bytes: Vec<u8>, name: &str) -> Result<String, String> {
+    if bytes.len() > MAX_DIFF_SIZE {
+        return Err(format!("{} is too large to diff", name));
+    }
+    crate::encoding::decode(&bytes)
+        .map(|(_, text)| text)
+        .ok_or_else(|| format!("{} is a binary file", name))
+}
+
+fn load_source(source: DiffSource) -> Result<String, String> {
+    match source {
+        DiffSource::Text { text } => {
+            if text.len() > MAX_DIFF_SIZE {
+                return Err("Text is too large to diff".to_string());
+            }
+            Ok(text)
+        }
+        DiffSource::File { path } => {
+            let bytes =
+                std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            decode_text(bytes, &path)
+        }
+        DiffSource::GitHead { path } => match crate::git::head_content(&path)? {
+            Some(bytes) => decode_text(bytes, &path),
+            None => Ok(String::new()),
+        },
+    }
+}
+
+fn line_diff(
+    old: &str,
+    new: &str,
+    algorithm: DiffAlgorithm,
+    context_lines: usize,
+) -> TextDiffResult {
+    let diff = TextDiff::configure()
+        .algorithm(match algorithm {
+            DiffAlgorithm::Myers => Algorithm::Myers,
+            DiffAlgorithm::Patience => Algorithm::Patience,
+        })
+        .timeout(DIFF_TIMEOUT)
+        .diff_lines(old, new);
+
+    let mut result = TextDiffResult {
+        identical: old == new,
+        old_line_count: diff.old_slices().len(),
+        new_line_count: diff.new_slices().len(),
+        added: 0,
+        removed: 0,
+        ratio: diff.ratio(),
+        hunks: Vec::new(),
+    };
+
+    for group in diff.grouped_ops(context_lines) {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let mut hunk = DiffHunk {
+            old_start: first.old_range().start + 1,
+            old_lines: last.old_range().end - first.old_range().start,
+            new_start: first.new_range().start + 1,
+            new_lines: last.new_range().end - first.new_range().start,
+            lines: Vec::new(),
+        };
+
+        for op in &group {
+            for change in diff.iter_changes(op) {
+                let kind = match change.tag() {
+                    ChangeTag::Equal => LineKind::Same,
+                    ChangeTag::Insert => {
+                        result.added += 1;
+                        LineKind::Added
+                    }
+                    ChangeTag::Delete => {
+                        result.removed += 1;
+                        LineKind::Removed
+                    }
+                };
+                hunk.lines.push(DiffLine {
+                    kind,
+                    old_line: change.old_index().map(|i| i + 1),
+                    new_line: change.new_index().map(|i| i + 1),
+                    text: change.value().trim_end_matches(['\n', '\r']).to_string(),
+                });
+            }
+        }
+        result.hunks.push(hunk);
+    }
+
+    result
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Line diff of two texts, files or git HEAD versions, grouped into hunks
+/// with `context_lines` unchanged lines around each change (3 by default)
+#[tauri::command]
+pub async fn compute_diff(
+    old: DiffSource,
+    new: DiffSource,
+    algorithm: Option<DiffAlgorithm>,
+    context_lines: Option<usize>,
+) -> Result<TextDiffResult, String> {
+    tokio::task::spawn_blocking(move || {
+        let old = load_source(old)?;
+        let new = load_source(new)?;
+        Ok(line_diff(
+            &old,
+            &new,
+            algorithm.unwrap_or_default(),
+            context_lines.unwrap_or(DEFAULT_CONTEXT_LINES),
+        ))
+    })
+    .await
+    .map_err(|e| format!("Diff task failed: {}", e))?
+}
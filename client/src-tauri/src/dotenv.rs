@@ -0,0 +1,481 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// ============================================================================
+// DOTENV TYPES
+// ============================================================================
+
+/// Shown instead of secret values unless they are asked for
+const MASK: &str = "********";
+
+/// Parts of a variable name that mark its value as secret
+const SECRET_MARKERS: &[&str] = &[
+    "SECRET",
+    "TOKEN",
+    "PASSWORD",
+    "PASSWD",
+    "PRIVATE",
+    "CREDENTIAL",
+    "API_KEY",
+    "APIKEY",
+    "ACCESS_KEY",
+    "AUTH",
+];
+
+/// Folders never searched for .env files
+const SKIPPED_DIRS: &[&str] = &[".git", "node_modules", "target", "dist", "build", ".venv"];
+
+/// How deep list_env_files looks below the workspace root
+const MAX_SEARCH_DEPTH: usize = 4;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EnvEntry {
+    pub key: String,
+    /// As written (before `${VAR}` expansion); masked for secrets unless revealed
+    pub value: String,
+    /// 1-based line number
+    pub line: usize,
+    pub secret: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EnvProblem {
+    /// None for problems that concern the whole file
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EnvFile {
+    pub path: String,
+    pub exists: bool,
+    pub entries: Vec<EnvEntry>,
+    pub problems: Vec<EnvProblem>,
+}
+
+/// A parsed assignment
+struct Assignment {
+    key: String,
+    value: String,
+    /// 0-based index into the file's lines
+    index: usize,
+    /// Single-quoted, so `$` is not expanded
+    literal: bool,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let upper = key.to_ascii_uppercase();
+    SECRET_MARKERS.iter().any(|marker| upper.contains(marker))
+}
+
+/// Value of the text after `=`, and whether it is literal (single-quoted)
+fn parse_value(raw: &str) -> Result<(String, bool), String> {
+    if let Some(rest) = raw.strip_prefix('\'') {
+        let end = rest
+            .find('\'')
+            .ok_or_else(|| "Unterminated single quote".to_string())?;
+        return Ok((rest[..end].to_string(), true));
+    }
+
+    if let Some(rest) = raw.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Ok((value, false)),
+                '\\' => match chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    // Kept escaped so expansion leaves it alone
+                    Some('$') => value.push_str("\\$"),
+                    Some(other) => {
+                        value.push('\\');
+                        value.push(other);
+                    }
+                    None => value.push('\\'),
+                },
+                _ => value.push(c),
+            }
+        }
+        return Err("Unterminated double quote".to_string());
+    }
+
+    // Unquoted: a `#` after whitespace starts a comment
+    let end = raw
+        .char_indices()
+        .find(|(i, c)| *c == '#' && raw[..*i].ends_with([' ', '\t']))
+        .map(|(i, _)| i)
+        .unwrap_or(raw.len());
+    Ok((raw[..end].trim_end().to_string(), false))
+}
+
+/// The key of an assignment line and whether it starts with `export`
+fn split_assignment(line: &str) -> Option<(&str, &str, bool)> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return None;
+    }
+    let (body, exported) = match trimmed.strip_prefix("export ") {
+        Some(rest) => (rest.trim_start(), true),
+        None => (trimmed, false),
+    };
+    let (key, rest) = body.split_once('=').unwrap_or((body, ""));
+    Some((key.trim(), rest.trim_start(), exported))
+}
+
+fn parse(text: &str) -> (Vec<Assignment>, Vec<EnvProblem>) {
+    let mut assignments: Vec<Assignment> = Vec::new();
+    let mut problems = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let Some((key, rest, _)) = split_assignment(line) else {
+            continue;
+        };
+        let problem = |message: String| EnvProblem {
+            line: Some(index + 1),
+            message,
+        };
+
+        if !line.contains('=') {
+            problems.push(problem("Expected KEY=VALUE".to_string()));
+            continue;
+        }
+        if !is_valid_key(key) {
+            problems.push(problem(format!("Invalid variable name: {}", key)));
+            continue;
+        }
+        let (value, literal) = match parse_value(rest) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                problems.push(problem(e));
+                continue;
+            }
+        };
+        if let Some(previous) = assignments.iter().find(|a| a.key == key) {
+            problems.push(problem(format!(
+                "{} is already set on line {}; this value wins",
+                key,
+                previous.index + 1
+            )));
+        }
+
+        assignments.push(Assignment {
+            key: key.to_string(),
+            value,
+            index,
+            literal,
+        });
+    }
+
+    (assignments, problems)
+}
+
+/// Replace `$NAME` and `${NAME}` with earlier variables or the app's own
+/// environment; unknown names become empty, `\$` stays a plain `$`
+fn expand(value: &str, variables: &HashMap<String, String>) -> String {
+    let lookup = |name: &str| {
+        variables
+            .get(name)
+            .cloned()
+            .or_else(|| std::env::var(name).ok())
+            .unwrap_or_default()
+    };
+
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find(['$', '\\']) {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        if rest[start..].starts_with("\\$") {
+            result.push('$');
+            rest = &after[1..];
+        } else if rest[start..].starts_with('\\') {
+            result.push('\\');
+            rest = after;
+        } else if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => {
+                    result.push_str(&lookup(&braced[..end]));
+                    rest = &braced[end + 1..];
+                }
+                None => {
+                    result.push('$');
+                    rest = after;
+                }
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            if end == 0 {
+                result.push('$');
+            } else {
+                result.push_str(&lookup(&after[..end]));
+            }
+            rest = &after[end..];
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// How a value is written back: bare when safe, single-quoted when it has
+/// no quote or newline, double-quoted with escapes otherwise
+fn format_value(value: &str) -> String {
+    let bare = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./:@+,=".contains(c));
+    if bare {
+        return value.to_string();
+    }
+    if !value.contains(['\'', '\n', '\r']) {
+        return format!("'{}'", value);
+    }
+
+    let mut quoted = String::from('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '$' => quoted.push_str("\\$"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn read_text(path: &Path) -> Result<Option<String>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+fn env_file(path: &Path, reveal: bool) -> Result<EnvFile, String> {
+    let text = read_text(path)?;
+    let (assignments, problems) = parse(text.as_deref().unwrap_or_default());
+
+    let entries = assignments
+        .into_iter()
+        .map(|a| {
+            let secret = is_secret_key(&a.key);
+            EnvEntry {
+                value: if secret && !reveal && !a.value.is_empty() {
+                    MASK.to_string()
+                } else {
+                    a.value
+                },
+                key: a.key,
+                line: a.index + 1,
+                secret,
+            }
+        })
+        .collect();
+
+    Ok(EnvFile {
+        path: path.to_string_lossy().to_string(),
+        exists: text.is_some(),
+        entries,
+        problems,
+    })
+}
+
+/// Rewrite the file's lines through `edit`, keeping its line endings
+fn edit_lines(path: &Path, edit: impl FnOnce(&mut Vec<String>)) -> Result<(), String> {
+    let text = read_text(path)?.unwrap_or_default();
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    edit(&mut lines);
+
+    let mut output = lines.join(newline);
+    if !output.is_empty() {
+        output.push_str(newline);
+    }
+    crate::atomic_write(path, output.as_bytes())
+}
+
+/// Variables of the given .env files, in order so later files override
+/// earlier ones, with `${VAR}` references expanded. Used to start terminals
+/// and tasks with a chosen set of variables.
+pub(crate) fn load_env_sets(paths: &[String]) -> Result<Vec<(String, String)>, String> {
+    let mut variables: HashMap<String, String> = HashMap::new();
+    let mut order: Vec<String> = Vec::new();
+
+    for path in paths {
+        let text = read_text(Path::new(path))?
+            .ok_or_else(|| format!("Environment file not found: {}", path))?;
+        for assignment in parse(&text).0 {
+            let value = if assignment.literal {
+                assignment.value
+            } else {
+                expand(&assignment.value, &variables)
+            };
+            if !variables.contains_key(&assignment.key) {
+                order.push(assignment.key.clone());
+            }
+            variables.insert(assignment.key, value);
+        }
+    }
+
+    Ok(order
+        .into_iter()
+        .filter_map(|key| variables.remove(&key).map(|value| (key, value)))
+        .collect())
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// `.env` and `.env.*` files in the workspace (a few levels deep), sorted
+#[tauri::command]
+pub async fn list_env_files(root_path: String) -> Result<Vec<String>, String> {
+    let root = PathBuf::from(&root_path);
+    if !root.is_dir() {
+        return Err(format!("Path is not a directory: {}", root_path));
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let mut files: Vec<String> = walkdir::WalkDir::new(&root)
+            .max_depth(MAX_SEARCH_DEPTH)
+            .into_iter()
+            .filter_entry(|e| {
+                !(e.file_type().is_dir()
+                    && e.depth() > 0
+                    && SKIPPED_DIRS.contains(&e.file_name().to_string_lossy().as_ref()))
+            })
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| {
+                let name = e.file_name().to_string_lossy();
+                name == ".env" || name.starts_with(".env.") || name.ends_with(".env")
+            })
+            .map(|e| e.path().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        files
+    })
+    .await
+    .map_err(|e| format!("Search task failed: {}", e))
+}
+
+/// Variables of a .env file and any parse problems. Values of secret-looking
+/// variables (tokens, passwords, keys) are masked unless `reveal` is set.
+#[tauri::command]
+pub async fn read_env_file(path: String, reveal: Option<bool>) -> Result<EnvFile, String> {
+    env_file(Path::new(&path), reveal.unwrap_or(false))
+}
+
+/// Set a variable, replacing its effective assignment in place (comments and
+/// other lines are kept) or appending it; creates the file if needed
+#[tauri::command]
+pub async fn set_env_var(path: String, key: String, value: String) -> Result<EnvFile, String> {
+    if !is_valid_key(&key) {
+        return Err(format!("Invalid variable name: {}", key));
+    }
+    let file = Path::new(&path);
+
+    edit_lines(file, |lines| {
+        let existing = lines
+            .iter()
+            .rposition(|line| split_assignment(line).is_some_and(|(k, _, _)| k == key));
+        match existing {
+            Some(index) => {
+                let exported = split_assignment(&lines[index]).is_some_and(|(_, _, e)| e);
+                lines[index] = format!(
+                    "{}{}={}",
+                    if exported { "export " } else { "" },
+                    key,
+                    format_value(&value)
+                );
+            }
+            None => lines.push(format!("{}={}", key, format_value(&value))),
+        }
+    })?;
+
+    env_file(file, false)
+}
+
+/// Remove every assignment of a variable
+#[tauri::command]
+pub async fn remove_env_var(path: String, key: String) -> Result<EnvFile, String> {
+    let file = Path::new(&path);
+    if read_text(file)?.is_none() {
+        return Err(format!("Environment file not found: {}", path));
+    }
+
+    edit_lines(file, |lines| {
+        lines.retain(|line| split_assignment(line).is_none_or(|(k, _, _)| k != key));
+    })?;
+
+    env_file(file, false)
+}
+
+/// Parse problems of a .env file, plus the variables of `example_path`
+/// (by default a `.env.example` next to it) that it leaves unset or empty
+#[tauri::command]
+pub async fn validate_env_file(
+    path: String,
+    example_path: Option<String>,
+) -> Result<Vec<EnvProblem>, String> {
+    let file = Path::new(&path);
+    let text = read_text(file)?.ok_or_else(|| format!("Environment file not found: {}", path))?;
+    let (assignments, mut problems) = parse(&text);
+
+    let example = match example_path {
+        Some(example) => Some(PathBuf::from(example)),
+        None => file
+            .parent()
+            .map(|dir| dir.join(".env.example"))
+            .filter(|example| example.is_file() && example != file),
+    };
+    if let Some(example) = example {
+        let example_text = read_text(&example)?
+            .ok_or_else(|| format!("Environment file not found: {}", example.display()))?;
+        for expected in parse(&example_text).0 {
+            let set = assignments
+                .iter()
+                .rev()
+                .find(|a| a.key == expected.key)
+                .is_some_and(|a| !a.value.is_empty());
+            if !set {
+                problems.push(EnvProblem {
+                    line: None,
+                    message: format!(
+                        "{} is listed in {} but not set",
+                        expected.key,
+                        example
+                            .file_name()
+                            .map(|n| n.to_string_lossy().to_string())
+                            .unwrap_or_default()
+                    ),
+                });
+            }
+        }
+    }
+
+    Ok(problems)
+}
@@ -0,0 +1,549 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{watch, Semaphore};
+
+use crate::transfer::PROGRESS_INTERVAL;
+
+// ============================================================================
+// DOWNLOAD TYPES
+// ============================================================================
+
+/// Event emitted to the window that started a download whenever it advances
+/// or changes state
+pub const DOWNLOAD_EVENT: &str = "download://progress";
+
+/// Downloads past this many wait in the queue
+const MAX_CONCURRENT_DOWNLOADS: usize = 3;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadState {
+    /// Waiting for a free download slot
+    Queued,
+    Downloading,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DownloadStatus {
+    pub download_id: String,
+    pub url: String,
+    pub path: String,
+    pub state: DownloadState,
+    pub received_bytes: u64,
+    /// From Content-Length, when the server sent one
+    pub total_bytes: Option<u64>,
+    /// Why the download failed
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Control {
+    Run,
+    Pause,
+    Cancel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HashAlgorithm {
+    Sha256,
+    Sha512,
+}
+
+/// Expected digest of the finished file
+#[derive(Debug, Clone)]
+struct Checksum {
+    algorithm: HashAlgorithm,
+    /// Lowercase hex
+    digest: String,
+}
+
+struct ManagedDownload {
+    window_label: String,
+    target: PathBuf,
+    checksum: Option<Checksum>,
+    status: DownloadStatus,
+    control: watch::Sender<Control>,
+    /// A task is fetching the file or waiting for a slot
+    task_running: bool,
+}
+
+/// Downloads of this run, keyed by the id the frontend chose
+pub struct DownloadManager {
+    slots: Arc<Semaphore>,
+    downloads: Mutex<HashMap<String, ManagedDownload>>,
+}
+
+impl Default for DownloadManager {
+    fn default() -> Self {
+        DownloadManager {
+            slots: Arc::new(Semaphore::new(MAX_CONCURRENT_DOWNLOADS)),
+            downloads: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl DownloadManager {
+    /// Cancel and forget every download started by a window (e.g. when it is
+    /// closed); partial files of stopped downloads are removed
+    pub fn close_window(&self, label: &str) {
+        let Ok(mut downloads) = self.downloads.lock() else {
+            return;
+        };
+        downloads.retain(|_, download| {
+            if download.window_label != label {
+                return true;
+            }
+            if download.task_running {
+                let _ = download.control.send(Control::Cancel);
+            } else if download.status.state != DownloadState::Completed {
+                let _ = std::fs::remove_file(part_path(&download.target));
+            }
+            false
+        });
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Where the file is written until it is complete; kept between pause and
+/// resume so the download can continue where it stopped
+fn part_path(target: &Path) -> PathBuf {
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    target.with_file_name(format!(".{}.part", name))
+}
+
+/// `sha256:<hex>`, `sha512:<hex>` or a bare hex digest (told apart by length)
+fn parse_checksum(text: &str) -> Result<Checksum, String> {
+    let text = text.trim();
+    let (algorithm, digest) = match text.split_once(':') {
+        Some((name, digest)) => match name.to_ascii_lowercase().as_str() {
+            "sha256" => (HashAlgorithm::Sha256, digest),
+            "sha512" => (HashAlgorithm::Sha512, digest),
+            _ => return Err(format!("Unsupported checksum algorithm: {}", name)),
+        },
+        None => match text.len() {
+            64 => (HashAlgorithm::Sha256, text),
+            128 => (HashAlgorithm::Sha512, text),
+            _ => return Err(format!("Invalid checksum: {}", text)),
+        },
+    };
+
+    let expected_len = match algorithm {
+        HashAlgorithm::Sha256 => 64,
+        HashAlgorithm::Sha512 => 128,
+    };
+    if digest.len() != expected_len || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid checksum: {}", text));
+    }
+    Ok(Checksum {
+        algorithm,
+        digest: digest.to_ascii_lowercase(),
+    })
+}
+
+fn file_digest(path: &Path, algorithm: HashAlgorithm) -> Result<String, String> {
+    fn hash<D: Digest>(mut file: std::fs::File) -> std::io::Result<String> {
+        let mut hasher = D::new();
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect())
+    }
+
+    let file = std::fs::File::open(path).map_err(|e| format!("Failed to read download: {}", e))?;
+    match algorithm {
+        HashAlgorithm::Sha256 => hash::<Sha256>(file),
+        HashAlgorithm::Sha512 => hash::<Sha512>(file),
+    }
+    .map_err(|e| format!("Failed to read download: {}", e))
+}
+
+/// Change a download's status and tell the window that started it
+fn update(app: &AppHandle, download_id: &str, change: impl FnOnce(&mut ManagedDownload)) {
+    let manager = app.state::<DownloadManager>();
+    let Ok(mut downloads) = manager.downloads.lock() else {
+        return;
+    };
+    let Some(download) = downloads.get_mut(download_id) else {
+        return;
+    };
+    change(download);
+    let _ = app.emit_to(
+        download.window_label.as_str(),
+        DOWNLOAD_EVENT,
+        download.status.clone(),
+    );
+}
+
+/// Resolves once the download is paused or cancelled
+async fn stopped(control: &mut watch::Receiver<Control>) {
+    if control.wait_for(|c| *c != Control::Run).await.is_err() {
+        // The download was forgotten; nothing will resume it
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Fetch the rest of the file into its part file; Ok(false) when stopped
+async fn fetch(
+    app: &AppHandle,
+    download_id: &str,
+    url: &str,
+    part: &Path,
+    control: &mut watch::Receiver<Control>,
+) -> Result<bool, String> {
+    let offset = tokio::fs::metadata(part)
+        .await
+        .map(|m| m.len())
+        .unwrap_or(0);
+
+    let client = reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+    }
+
+    let mut response = tokio::select! {
+        _ = stopped(control) => return Ok(false),
+        response = request.send() => response.map_err(|e| format!("Download failed: {}", e))?,
+    };
+    let status = response.status();
+    if offset > 0 && status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+        // The part file already holds the whole file
+        return Ok(true);
+    }
+    if !status.is_success() {
+        return Err(format!("Download failed: HTTP {}", status));
+    }
+
+    // A server that ignores the range sends the whole file again
+    let resumed = offset > 0 && status == reqwest::StatusCode::PARTIAL_CONTENT;
+    let mut file = if resumed {
+        tokio::fs::OpenOptions::new().append(true).open(part).await
+    } else {
+        tokio::fs::File::create(part).await
+    }
+    .map_err(|e| format!("Failed to create file: {}", e))?;
+
+    let mut received_bytes = if resumed { offset } else { 0 };
+    let total_bytes = response.content_length().map(|len| len + received_bytes);
+    update(app, download_id, |download| {
+        download.status.state = DownloadState::Downloading;
+        download.status.received_bytes = received_bytes;
+        download.status.total_bytes = total_bytes;
+    });
+
+    let mut last_progress = Instant::now();
+    loop {
+        let chunk = tokio::select! {
+            _ = stopped(control) => {
+                file.flush()
+                    .await
+                    .map_err(|e| format!("Failed to write file: {}", e))?;
+                return Ok(false);
+            }
+            chunk = response.chunk() => chunk,
+        };
+        let Some(chunk) = chunk.map_err(|e| format!("Download failed: {}", e))? else {
+            break;
+        };
+
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("Failed to write file: {}", e))?;
+        received_bytes += chunk.len() as u64;
+
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            update(app, download_id, |download| {
+                download.status.received_bytes = received_bytes;
+            });
+        }
+    }
+
+    file.sync_all()
+        .await
+        .map_err(|e| format!("Failed to write file: {}", e))?;
+    update(app, download_id, |download| {
+        download.status.received_bytes = received_bytes;
+        download.status.total_bytes = Some(received_bytes);
+    });
+    Ok(true)
+}
+
+/// Check the finished part file and move it into place
+async fn finish(part: PathBuf, target: PathBuf, checksum: Option<Checksum>) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        if let Some(checksum) = checksum {
+            let actual = file_digest(&part, checksum.algorithm)?;
+            if actual != checksum.digest {
+                let _ = std::fs::remove_file(&part);
+                return Err(format!(
+                    "Checksum mismatch: expected {}, got {}",
+                    checksum.digest, actual
+                ));
+            }
+        }
+        std::fs::rename(&part, &target).map_err(|e| format!("Failed to save file: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Download task failed: {}", e))?
+}
+
+/// Wait for a slot, fetch the file and record how the download ended
+async fn run_download(app: AppHandle, download_id: String, mut control: watch::Receiver<Control>) {
+    let details = {
+        let manager = app.state::<DownloadManager>();
+        let Ok(downloads) = manager.downloads.lock() else {
+            return;
+        };
+        downloads.get(&download_id).map(|download| {
+            (
+                manager.slots.clone(),
+                download.status.url.clone(),
+                download.target.clone(),
+                download.checksum.clone(),
+            )
+        })
+    };
+    let Some((slots, url, target, checksum)) = details else {
+        return;
+    };
+    let part = part_path(&target);
+
+    let permit = tokio::select! {
+        _ = stopped(&mut control) => None,
+        permit = slots.acquire_owned() => permit.ok(),
+    };
+    let result = match permit {
+        Some(_permit) => match fetch(&app, &download_id, &url, &part, &mut control).await {
+            Ok(true) => finish(part.clone(), target, checksum).await.map(|_| true),
+            other => other,
+        },
+        None => Ok(false),
+    };
+
+    let control = *control.borrow();
+    if matches!(result, Ok(false)) && control == Control::Cancel {
+        let _ = tokio::fs::remove_file(&part).await;
+    }
+    update(&app, &download_id, |download| {
+        download.task_running = false;
+        download.status.state = match &result {
+            Ok(true) => DownloadState::Completed,
+            Ok(false) if control == Control::Cancel => DownloadState::Cancelled,
+            Ok(false) => DownloadState::Paused,
+            Err(_) => DownloadState::Failed,
+        };
+        download.status.error = result.err();
+    });
+}
+
+/// Start (or restart) the task of a download that has none running
+fn spawn_download(app: &AppHandle, download: &mut ManagedDownload) {
+    let (control, receiver) = watch::channel(Control::Run);
+    download.control = control;
+    download.task_running = true;
+    download.status.state = DownloadState::Queued;
+    download.status.error = None;
+    let _ = app.emit_to(
+        download.window_label.as_str(),
+        DOWNLOAD_EVENT,
+        download.status.clone(),
+    );
+
+    tokio::spawn(run_download(
+        app.clone(),
+        download.status.download_id.clone(),
+        receiver,
+    ));
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Download `url` into `path` in the background and return right away.
+///
+/// Progress and state changes arrive as `download://progress` events. At most
+/// three downloads run at once; the rest wait as queued. The file only
+/// appears at `path` once it is complete and, when `checksum` is given
+/// (`sha256:<hex>` or `sha512:<hex>`), matches it.
+#[tauri::command]
+pub async fn download_file(
+    app: AppHandle,
+    window: Window,
+    manager: State<'_, DownloadManager>,
+    download_id: String,
+    url: String,
+    path: String,
+    checksum: Option<String>,
+) -> Result<DownloadStatus, String> {
+    let parsed = url::Url::parse(&url).map_err(|e| format!("Invalid URL: {}", e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("Unsupported URL scheme: {}", parsed.scheme()));
+    }
+    let target = PathBuf::from(&path);
+    if target.file_name().is_none() {
+        return Err("Path has no file name".to_string());
+    }
+    if !target.parent().is_some_and(|dir| dir.is_dir()) {
+        return Err(format!("Directory does not exist: {}", path));
+    }
+    let checksum = checksum
+        .filter(|c| !c.trim().is_empty())
+        .map(|c| parse_checksum(&c))
+        .transpose()?;
+
+    let mut downloads = manager
+        .downloads
+        .lock()
+        .map_err(|_| "Download manager is poisoned".to_string())?;
+    if downloads
+        .get(&download_id)
+        .is_some_and(|download| download.task_running)
+    {
+        return Err(format!("Download already running: {}", download_id));
+    }
+
+    let mut download = ManagedDownload {
+        window_label: window.label().to_string(),
+        target,
+        checksum,
+        status: DownloadStatus {
+            download_id: download_id.clone(),
+            url,
+            path,
+            state: DownloadState::Queued,
+            received_bytes: 0,
+            total_bytes: None,
+            error: None,
+        },
+        control: watch::channel(Control::Run).0,
+        task_running: false,
+    };
+    spawn_download(&app, &mut download);
+    let status = download.status.clone();
+    downloads.insert(download_id, download);
+    Ok(status)
+}
+
+/// Stop a queued or running download, keeping what was received so far
+#[tauri::command]
+pub async fn pause_download(
+    manager: State<'_, DownloadManager>,
+    download_id: String,
+) -> Result<(), String> {
+    let downloads = manager
+        .downloads
+        .lock()
+        .map_err(|_| "Download manager is poisoned".to_string())?;
+    let download = downloads
+        .get(&download_id)
+        .filter(|download| download.task_running)
+        .ok_or_else(|| format!("No running download: {}", download_id))?;
+
+    let _ = download.control.send(Control::Pause);
+    Ok(())
+}
+
+/// Continue a paused or failed download from where it stopped, when the
+/// server supports range requests (from the start otherwise)
+#[tauri::command]
+pub async fn resume_download(
+    app: AppHandle,
+    manager: State<'_, DownloadManager>,
+    download_id: String,
+) -> Result<DownloadStatus, String> {
+    let mut downloads = manager
+        .downloads
+        .lock()
+        .map_err(|_| "Download manager is poisoned".to_string())?;
+    let download = downloads
+        .get_mut(&download_id)
+        .ok_or_else(|| format!("Download not found: {}", download_id))?;
+    if download.task_running {
+        return Err(format!("Download is still running: {}", download_id));
+    }
+    if !matches!(
+        download.status.state,
+        DownloadState::Paused | DownloadState::Failed
+    ) {
+        return Err(format!("Download cannot be resumed: {}", download_id));
+    }
+
+    spawn_download(&app, download);
+    Ok(download.status.clone())
+}
+
+/// Stop a download and remove its partial file
+#[tauri::command]
+pub async fn cancel_download(
+    app: AppHandle,
+    manager: State<'_, DownloadManager>,
+    download_id: String,
+) -> Result<(), String> {
+    let mut downloads = manager
+        .downloads
+        .lock()
+        .map_err(|_| "Download manager is poisoned".to_string())?;
+    let download = downloads
+        .get_mut(&download_id)
+        .ok_or_else(|| format!("Download not found: {}", download_id))?;
+
+    if download.task_running {
+        let _ = download.control.send(Control::Cancel);
+    } else if download.status.state != DownloadState::Completed {
+        let _ = std::fs::remove_file(part_path(&download.target));
+        download.status.state = DownloadState::Cancelled;
+        let _ = app.emit_to(
+            download.window_label.as_str(),
+            DOWNLOAD_EVENT,
+            download.status.clone(),
+        );
+    }
+    Ok(())
+}
+
+/// Every download of this run, in no particular order
+#[tauri::command]
+pub async fn list_downloads(
+    manager: State<'_, DownloadManager>,
+) -> Result<Vec<DownloadStatus>, String> {
+    Ok(manager
+        .downloads
+        .lock()
+        .map_err(|_| "Download manager is poisoned".to_string())?
+        .values()
+        .map(|download| download.status.clone())
+        .collect())
+}
@@ -0,0 +1,321 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::backup::FileBackup;
+use crate::ignore::{self, IgnoreFilter};
+use crate::search::candidate_files;
+
+// ============================================================================
+// ENCODING TYPES
+// ============================================================================
+
+/// Larger files are reported but never converted
+const MAX_CONVERT_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Bytes inspected to tell text from binary
+const SNIFF_LEN: usize = 8192;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TextEncoding {
+    Utf8,
+    /// UTF-8 with a byte order mark
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    /// ISO-8859-1; also what any non-UTF-8 8-bit text is reported as
+    Latin1,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    /// Both kinds in one file
+    Mixed,
+    /// A single line, nothing to convert
+    None,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TextFormat {
+    pub path: String,
+    /// None when the file is skipped
+    pub encoding: Option<TextEncoding>,
+    pub line_ending: LineEnding,
+    pub lf_count: usize,
+    pub crlf_count: usize,
+    /// Why the file cannot be converted (binary, too large)
+    pub skipped: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversionSummary {
+    /// Files rewritten
+    pub converted: Vec<String>,
+    /// Files already in the requested format
+    pub unchanged: usize,
+    /// Files left alone (binary, too large), with the reason
+    pub skipped: Vec<(String, String)>,
+    /// Files that could not be converted or written, with the reason
+    pub failed: Vec<(String, String)>,
+    /// Pass to restore_backup to restore the original files
+    pub backup_id: Option<String>,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// UTF-16 without a BOM: most code units of ASCII-heavy text have one zero byte
+fn sniff_utf16(bytes: &[u8]) -> Option<TextEncoding> {
+    let sample = &bytes[..bytes.len().min(SNIFF_LEN) & !1];
+    if sample.len() < 2 {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let even_zeros = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_zeros = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|b| **b == 0)
+        .count();
+
+    if odd_zeros * 10 >= pairs * 4 && even_zeros * 10 < pairs {
+        Some(TextEncoding::Utf16Le)
+    } else if even_zeros * 10 >= pairs * 4 && odd_zeros * 10 < pairs {
+        Some(TextEncoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+fn decode_utf16(bytes: &[u8], little_endian: bool) -> Option<String> {
+    if bytes.len() % 2 == 1 {
+        return None;
+    }
+    let units = bytes.chunks_exact(2).map(|pair| {
+        let pair = [pair[0], pair[1]];
+        if little_endian {
+            u16::from_le_bytes(pair)
+        } else {
+            u16::from_be_bytes(pair)
+        }
+    });
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .ok()
+}
+
+/// Encoding and decoded text of a file, or None for binary content
+pub(crate) fn decode(bytes: &[u8]) -> Option<(TextEncoding, String)> {
+    if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        let text = String::from_utf8(rest.to_vec()).ok()?;
+        return Some((TextEncoding::Utf8Bom, text));
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return Some((TextEncoding::Utf16Le, decode_utf16(rest, true)?));
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        return Some((TextEncoding::Utf16Be, decode_utf16(rest, false)?));
+    }
+    if let Some(encoding) = sniff_utf16(bytes) {
+        let text = decode_utf16(bytes, encoding == TextEncoding::Utf16Le)?;
+        return Some((encoding, text));
+    }
+
+    let sniff = &bytes[..bytes.len().min(SNIFF_LEN)];
+    if sniff.contains(&0) {
+        return None;
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Some((TextEncoding::Utf8, text.to_string())),
+        Err(_) => {
+            // Control characters other than whitespace mean binary, not Latin-1
+            let controls = sniff
+                .iter()
+                .filter(|b| **b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0C | 0x1B))
+                .count();
+            if controls * 100 > sniff.len() {
+                return None;
+            }
+            Some((
+                TextEncoding::Latin1,
+                bytes.iter().map(|b| *b as char).collect(),
+            ))
+        }
+    }
+}
+
+fn encode(text: &str, encoding: TextEncoding) -> Result<Vec<u8>, String> {
+    Ok(match encoding {
+        TextEncoding::Utf8 => text.as_bytes().to_vec(),
+        TextEncoding::Utf8Bom => [&[0xEF, 0xBB, 0xBF], text.as_bytes()].concat(),
+        TextEncoding::Utf16Le => [0xFF, 0xFE]
+            .into_iter()
+            .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect(),
+        TextEncoding::Utf16Be => [0xFE, 0xFF]
+            .into_iter()
+            .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+            .collect(),
+        TextEncoding::Latin1 => text
+            .chars()
+            .map(|c| u8::try_from(c).map_err(|_| format!("'{}' cannot be written as Latin-1", c)))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+fn line_endings(text: &str) -> (LineEnding, usize, usize) {
+    let crlf = text.matches("\r\n").count();
+    let lf = text.matches('\n').count() - crlf;
+    let kind = match (lf, crlf) {
+        (0, 0) => LineEnding::None,
+        (_, 0) => LineEnding::Lf,
+        (0, _) => LineEnding::Crlf,
+        _ => LineEnding::Mixed,
+    };
+    (kind, lf, crlf)
+}
+
+/// Files named directly plus the non-ignored files under named directories
+fn expand_paths(paths: &[String]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths.iter().map(PathBuf::from) {
+        if !path.is_dir() {
+            files.push(path);
+            continue;
+        }
+        let Ok(filter) = IgnoreFilter::load(&ignore::workspace_root(&path, None)) else {
+            continue;
+        };
+        files.extend(candidate_files(&path, &filter, &None, &None).map(|e| e.into_path()));
+    }
+    files
+}
+
+/// Read and decode a file, or the reason it is skipped
+fn read_text(path: &Path) -> Result<(Vec<u8>, TextEncoding, String), String> {
+    let metadata = std::fs::metadata(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    if metadata.len() > MAX_CONVERT_SIZE {
+        return Err("File is too large".to_string());
+    }
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let (encoding, text) = decode(&bytes).ok_or_else(|| "Binary file".to_string())?;
+    Ok((bytes, encoding, text))
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Encoding and line endings of files; directories are expanded to the
+/// files under them that the workspace does not ignore
+#[tauri::command]
+pub async fn detect_text_format(paths: Vec<String>) -> Result<Vec<TextFormat>, String> {
+    tokio::task::spawn_blocking(move || {
+        expand_paths(&paths)
+            .into_iter()
+            .map(|path| {
+                let display = path.to_string_lossy().to_string();
+                match read_text(&path) {
+                    Ok((_, encoding, text)) => {
+                        let (line_ending, lf_count, crlf_count) = line_endings(&text);
+                        TextFormat {
+                            path: display,
+                            encoding: Some(encoding),
+                            line_ending,
+                            lf_count,
+                            crlf_count,
+                            skipped: None,
+                        }
+                    }
+                    Err(reason) => TextFormat {
+                        path: display,
+                        encoding: None,
+                        line_ending: LineEnding::None,
+                        lf_count: 0,
+                        crlf_count: 0,
+                        skipped: Some(reason),
+                    },
+                }
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| format!("Detection task failed: {}", e))
+}
+
+/// Convert files in place to an encoding and/or line ending (`lf` or
+/// `crlf`), leaving unset ones as they are.
+///
+/// Directories are expanded like detect_text_format. Every rewritten file
+/// is backed up first so the conversion can be undone with restore_backup.
+#[tauri::command]
+pub async fn convert_text_format(
+    app: AppHandle,
+    paths: Vec<String>,
+    encoding: Option<TextEncoding>,
+    line_ending: Option<LineEnding>,
+) -> Result<ConversionSummary, String> {
+    if let Some(target @ (LineEnding::Mixed | LineEnding::None)) = line_ending {
+        return Err(format!("Cannot convert line endings to {:?}", target));
+    }
+    if encoding.is_none() && line_ending.is_none() {
+        return Err("Nothing to convert to".to_string());
+    }
+    let mut backup = FileBackup::new(&app)?;
+
+    tokio::task::spawn_blocking(move || {
+        let mut summary = ConversionSummary {
+            converted: Vec::new(),
+            unchanged: 0,
+            skipped: Vec::new(),
+            failed: Vec::new(),
+            backup_id: None,
+        };
+
+        for path in expand_paths(&paths) {
+            let display = path.to_string_lossy().to_string();
+            let (bytes, current, mut text) = match read_text(&path) {
+                Ok(decoded) => decoded,
+                Err(reason) => {
+                    summary.skipped.push((display, reason));
+                    continue;
+                }
+            };
+
+            if let Some(target) = line_ending {
+                let normalized = text.replace("\r\n", "\n");
+                text = match target {
+                    LineEnding::Crlf => normalized.replace('\n', "\r\n"),
+                    _ => normalized,
+                };
+            }
+            let converted = match encode(&text, encoding.unwrap_or(current)) {
+                Ok(converted) => converted,
+                Err(e) => {
+                    summary.failed.push((display, e));
+                    continue;
+                }
+            };
+
+            if converted == bytes {
+                summary.unchanged += 1;
+                continue;
+            }
+            match backup.write(&path, &bytes, &converted) {
+                Ok(()) => summary.converted.push(display),
+                Err(e) => summary.failed.push((display, e)),
+            }
+        }
+
+        summary.backup_id = backup.id();
+        summary
+    })
+    .await
+    .map_err(|e| format!("Conversion task failed: {}", e))
+}
@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+// ============================================================================
+// FORMATTER TYPES
+// ============================================================================
+
+/// Per-project overrides, keyed by language id
+const PROJECT_CONFIG_PATH: &str = ".codecollab/formatters.json";
+
+const DEFAULT_TIMEOUT_MS: u64 = 10_000;
+
+/// Placeholder replaced by the file path in formatter arguments
+const FILE_PLACEHOLDER: &str = "{file}";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FormatterConfig {
+    /// Program followed by its arguments; must read stdin and write stdout
+    pub command: Vec<String>,
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FormatResult {
+    pub content: String,
+    pub changed: bool,
+    /// Program that produced the result
+    pub formatter: String,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn default_formatter(language: &str) -> Option<FormatterConfig> {
+    let command: &[&str] = match language {
+        "rust" => &["rustfmt", "--edition", "2021", "--emit", "stdout"],
+        "python" => &[
+            "black",
+            "--quiet",
+            "--stdin-filename",
+            FILE_PLACEHOLDER,
+            "-",
+        ],
+        "javascript" | "javascriptreact" | "typescript" | "typescriptreact" | "json" | "jsonc"
+        | "html" | "css" | "scss" | "less" | "markdown" | "yaml" | "graphql" | "vue" => {
+            &["prettier", "--stdin-filepath", FILE_PLACEHOLDER]
+        }
+        _ => return None,
+    };
+
+    Some(FormatterConfig {
+        command: command.iter().map(|s| s.to_string()).collect(),
+        timeout_ms: None,
+    })
+}
+
+fn project_formatter(
+    project_root: &Path,
+    language: &str,
+) -> Result<Option<FormatterConfig>, String> {
+    let config_path = project_root.join(PROJECT_CONFIG_PATH);
+    if !config_path.exists() {
+        return Ok(None);
+    }
+
+    let json = std::fs::read(&config_path)
+        .map_err(|e| format!("Failed to read {}: {}", config_path.display(), e))?;
+    let mut formatters: HashMap<String, FormatterConfig> = serde_json::from_slice(&json)
+        .map_err(|e| format!("Invalid {}: {}", config_path.display(), e))?;
+
+    Ok(formatters.remove(language))
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Format buffer content with the formatter for the file's language.
+///
+/// The content is piped through the formatter; the file on disk is not touched.
+/// Formatters can be overridden per project in `.codecollab/formatters.json`.
+#[tauri::command]
+pub async fn format_file(
+    path: String,
+    content: String,
+    project_root: Option<String>,
+) -> Result<FormatResult, String> {
+    let path_buf = PathBuf::from(&path);
+    let language = collab_protocol::detect_language_with_content(&path, &content);
+
+    let project_root = project_root.map(PathBuf::from);
+    let config = match &project_root {
+        Some(root) => project_formatter(root, &language)?,
+        None => None,
+    }
+    .or_else(|| default_formatter(&language))
+    .filter(|config| !config.command.is_empty())
+    .ok_or_else(|| format!("No formatter configured for {}", language))?;
+
+    let program = config.command[0].clone();
+    let args: Vec<String> = config.command[1..]
+        .iter()
+        .map(|arg| arg.replace(FILE_PLACEHOLDER, &path))
+        .collect();
+    let working_dir = project_root
+        .or_else(|| path_buf.parent().map(Path::to_path_buf))
+        .filter(|dir| dir.is_dir());
+
+    let mut command = Command::new(&program);
+    command
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(dir) = working_dir {
+        command.current_dir(dir);
+    }
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    let mut stdin = child.stdin.take().ok_or("Formatter has no stdin")?;
+    let input = content.clone();
+    let writer = tokio::spawn(async move {
+        let _ = stdin.write_all(input.as_bytes()).await;
+    });
+
+    let timeout = Duration::from_millis(config.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS));
+    let output = tokio::time::timeout(timeout, child.wait_with_output())
+        .await
+        .map_err(|_| format!("{} timed out after {} ms", program, timeout.as_millis()))?
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    let _ = writer.await;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} failed: {}", program, stderr.trim()));
+    }
+
+    let formatted = String::from_utf8(output.stdout)
+        .map_err(|_| format!("{} produced invalid UTF-8", program))?;
+
+    Ok(FormatResult {
+        changed: formatted != content,
+        content: formatted,
+        formatter: program,
+    })
+}
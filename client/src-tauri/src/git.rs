@@ -0,0 +1,506 @@
+use git2::{Diff, DiffFormat, DiffOptions, IndexAddOption, Repository, Status, StatusOptions};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// ============================================================================
+// GIT TYPES
+// ============================================================================
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RepoInfo {
+    /// Working directory of the repository
+    pub root: String,
+    /// Current branch name (None when HEAD is detached or unborn)
+    pub branch: Option<String>,
+    pub head_oid: Option<String>,
+    pub is_detached: bool,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    Typechange,
+    Untracked,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileStatus {
+    /// Absolute path of the file
+    pub path: String,
+    /// Change staged in the index
+    pub staged: Option<ChangeKind>,
+    /// Change in the working tree not yet staged
+    pub unstaged: Option<ChangeKind>,
+    pub conflicted: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DiffLine {
+    /// '+', '-' or ' '
+    pub origin: char,
+    pub content: String,
+    pub old_lineno: Option<u32>,
+    pub new_lineno: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DiffHunk {
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileDiff {
+    pub path: String,
+    pub old_path: Option<String>,
+    pub is_binary: bool,
+    pub hunks: Vec<DiffHunk>,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn open_repo(path: &str) -> Result<Repository, String> {
+    Repository::discover(path).map_err(|e| format!("Not a git repository: {}", e.message()))
+}
+
+fn workdir(repo: &Repository) -> Result<PathBuf, String> {
+    repo.workdir()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| "Bare repositories are not supported".to_string())
+}
+
+/// Convert an absolute (or already relative) path to one relative to the repo root
+fn relative_path(root: &Path, path: &str) -> Result<PathBuf, String> {
+    let path = Path::new(path);
+    if path.is_relative() {
+        return Ok(path.to_path_buf());
+    }
+    if let Ok(relative) = path.strip_prefix(root) {
+        return Ok(relative.to_path_buf());
+    }
+
+    // Fall back to canonical paths to see through symlinked roots
+    let canonical_root = root.canonicalize().map_err(|e| e.to_string())?;
+    let canonical_path = path
+        .canonicalize()
+        .or_else(|_| {
+            // Deleted files cannot be canonicalized, resolve their parent instead
+            let parent = path.parent().ok_or(std::io::ErrorKind::NotFound)?;
+            let name = path.file_name().ok_or(std::io::ErrorKind::NotFound)?;
+            parent.canonicalize().map(|p| p.join(name))
+        })
+        .map_err(|e| format!("Failed to resolve {}: {}", path.display(), e))?;
+
+    canonical_path
+        .strip_prefix(&canonical_root)
+        .map(Path::to_path_buf)
+        .map_err(|_| format!("{} is outside the repository", path.display()))
+}
+
+/// Status of a single file for the file tree; None when it is unchanged,
+/// ignored or a folder
+pub(crate) fn path_status(repo: &Repository, path: &Path) -> Option<FileStatus> {
+    let root = repo.workdir()?;
+    let relative = relative_path(root, &path.to_string_lossy()).ok()?;
+    let status = repo.status_file(&relative).ok()?;
+
+    let (staged, unstaged) = (staged_kind(status), unstaged_kind(status));
+    let conflicted = status.is_conflicted();
+    if status.is_ignored() || (staged.is_none() && unstaged.is_none() && !conflicted) {
+        return None;
+    }
+    Some(FileStatus {
+        path: path.to_string_lossy().to_string(),
+        staged,
+        unstaged,
+        conflicted,
+    })
+}
+
+/// Content of a file as committed at HEAD; None when HEAD does not have it
+/// (an untracked file or nothing committed yet)
+pub(crate) fn head_content(path: &str) -> Result<Option<Vec<u8>>, String> {
+    let repo = open_repo(path)?;
+    let relative = relative_path(&workdir(&repo)?, path)?;
+    let Some(tree) = repo.head().ok().and_then(|h| h.peel_to_tree().ok()) else {
+        return Ok(None);
+    };
+    let Ok(entry) = tree.get_path(&relative) else {
+        return Ok(None);
+    };
+    let blob = entry
+        .to_object(&repo)
+        .and_then(|object| object.peel_to_blob())
+        .map_err(|e| format!("Failed to read {} at HEAD: {}", path, e.message()))?;
+    Ok(Some(blob.content().to_vec()))
+}
+
+/// Author of each given line (1-based) of a file's current content, from
+/// git blame; lines that are not committed yet are left out
+pub(crate) fn line_authors(
+    repo: &Repository,
+    path: &Path,
+    content: &str,
+    lines: &[usize],
+) -> HashMap<usize, String> {
+    let mut authors = HashMap::new();
+    let Some(root) = repo.workdir() else {
+        return authors;
+    };
+    let Ok(relative) = relative_path(root, &path.to_string_lossy()) else {
+        return authors;
+    };
+    let Ok(committed) = repo.blame_file(&relative, None) else {
+        return authors;
+    };
+    // Blame the content as it is now, so line numbers match the editor
+    let Ok(blame) = committed.blame_buffer(content.as_bytes()) else {
+        return authors;
+    };
+
+    for &line in lines {
+        let Some(hunk) = blame.get_line(line) else {
+            continue;
+        };
+        if hunk.final_commit_id().is_zero() {
+            continue;
+        }
+        let signature = hunk.final_signature();
+        if let Some(name) = signature.name() {
+            authors.insert(line, name.to_string());
+        }
+    }
+    authors
+}
+
+async fn run_blocking<T, F>(f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| format!("Git task failed: {}", e))?
+}
+
+fn staged_kind(status: Status) -> Option<ChangeKind> {
+    if status.is_index_new() {
+        Some(ChangeKind::Added)
+    } else if status.is_index_modified() {
+        Some(ChangeKind::Modified)
+    } else if status.is_index_deleted() {
+        Some(ChangeKind::Deleted)
+    } else if status.is_index_renamed() {
+        Some(ChangeKind::Renamed)
+    } else if status.is_index_typechange() {
+        Some(ChangeKind::Typechange)
+    } else {
+        None
+    }
+}
+
+fn unstaged_kind(status: Status) -> Option<ChangeKind> {
+    if status.is_wt_new() {
+        Some(ChangeKind::Untracked)
+    } else if status.is_wt_modified() {
+        Some(ChangeKind::Modified)
+    } else if status.is_wt_deleted() {
+        Some(ChangeKind::Deleted)
+    } else if status.is_wt_renamed() {
+        Some(ChangeKind::Renamed)
+    } else if status.is_wt_typechange() {
+        Some(ChangeKind::Typechange)
+    } else {
+        None
+    }
+}
+
+fn collect_diff(root: &Path, diff: &Diff) -> Result<Vec<FileDiff>, String> {
+    let mut files: Vec<FileDiff> = Vec::new();
+
+    diff.print(DiffFormat::Patch, |delta, hunk, line| {
+        let new_path = delta.new_file().path().map(|p| root.join(p));
+        let old_path = delta.old_file().path().map(|p| root.join(p));
+        let path = new_path
+            .clone()
+            .or_else(|| old_path.clone())
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        if files.last().map(|f| f.path != path).unwrap_or(true) {
+            files.push(FileDiff {
+                path: path.clone(),
+                old_path: old_path
+                    .filter(|old| Some(old) != new_path.as_ref())
+                    .map(|old| old.to_string_lossy().to_string()),
+                is_binary: delta.flags().is_binary(),
+                hunks: Vec::new(),
+            });
+        }
+        let file = files.last_mut().expect("file entry was just pushed");
+
+        let Some(hunk) = hunk else {
+            return true;
+        };
+        let header = String::from_utf8_lossy(hunk.header())
+            .trim_end()
+            .to_string();
+        if file
+            .hunks
+            .last()
+            .map(|h| h.header != header)
+            .unwrap_or(true)
+        {
+            file.hunks.push(DiffHunk {
+                header,
+                old_start: hunk.old_start(),
+                old_lines: hunk.old_lines(),
+                new_start: hunk.new_start(),
+                new_lines: hunk.new_lines(),
+                lines: Vec::new(),
+            });
+        }
+
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            if let Some(current) = file.hunks.last_mut() {
+                current.lines.push(DiffLine {
+                    origin: line.origin(),
+                    content: String::from_utf8_lossy(line.content())
+                        .trim_end_matches(['\n', '\r'])
+                        .to_string(),
+                    old_lineno: line.old_lineno(),
+                    new_lineno: line.new_lineno(),
+                });
+            }
+        }
+
+        true
+    })
+    .map_err(|e| format!("Failed to build diff: {}", e.message()))?;
+
+    Ok(files)
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Detect the repository containing `path`, returning None outside of git
+#[tauri::command]
+pub async fn git_repo_info(path: String) -> Result<Option<RepoInfo>, String> {
+    run_blocking(move || {
+        let Ok(repo) = Repository::discover(&path) else {
+            return Ok(None);
+        };
+        let Some(root) = repo.workdir() else {
+            return Ok(None);
+        };
+
+        let head = repo.head().ok();
+        let is_detached = repo.head_detached().unwrap_or(false);
+        let branch = head
+            .as_ref()
+            .filter(|_| !is_detached)
+            .and_then(|h| h.shorthand().map(str::to_string))
+            .or_else(|| {
+                // Unborn branch: read the symbolic target of HEAD directly
+                repo.find_reference("HEAD")
+                    .ok()
+                    .and_then(|r| r.symbolic_target().map(str::to_string))
+                    .map(|t| t.trim_start_matches("refs/heads/").to_string())
+                    .filter(|_| head.is_none())
+            });
+
+        Ok(Some(RepoInfo {
+            root: root.to_string_lossy().to_string(),
+            branch,
+            head_oid: head.and_then(|h| h.target()).map(|oid| oid.to_string()),
+            is_detached,
+        }))
+    })
+    .await
+}
+
+/// Per-file status for badges in the file tree
+#[tauri::command]
+pub async fn git_status(path: String) -> Result<Vec<FileStatus>, String> {
+    run_blocking(move || {
+        let repo = open_repo(&path)?;
+        let root = workdir(&repo)?;
+
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .renames_head_to_index(true);
+
+        let statuses = repo
+            .statuses(Some(&mut options))
+            .map_err(|e| format!("Failed to read status: {}", e.message()))?;
+
+        Ok(statuses
+            .iter()
+            .filter(|entry| !entry.status().is_ignored())
+            .filter_map(|entry| {
+                let status = entry.status();
+                let path = entry.path()?;
+                Some(FileStatus {
+                    path: root.join(path).to_string_lossy().to_string(),
+                    staged: staged_kind(status),
+                    unstaged: unstaged_kind(status),
+                    conflicted: status.is_conflicted(),
+                })
+            })
+            .collect())
+    })
+    .await
+}
+
+/// Staged (index vs HEAD) or unstaged (working tree vs index) diff,
+/// optionally limited to a single file
+#[tauri::command]
+pub async fn git_diff(
+    path: String,
+    file: Option<String>,
+    staged: bool,
+) -> Result<Vec<FileDiff>, String> {
+    run_blocking(move || {
+        let repo = open_repo(&path)?;
+        let root = workdir(&repo)?;
+
+        let mut options = DiffOptions::new();
+        options.include_untracked(true).show_untracked_content(true);
+        if let Some(file) = &file {
+            options.pathspec(relative_path(&root, file)?);
+        }
+
+        let diff = if staged {
+            let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+            repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut options))
+        } else {
+            repo.diff_index_to_workdir(None, Some(&mut options))
+        }
+        .map_err(|e| format!("Failed to diff: {}", e.message()))?;
+
+        collect_diff(&root, &diff)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn git_stage(path: String, files: Vec<String>) -> Result<(), String> {
+    run_blocking(move || {
+        let repo = open_repo(&path)?;
+        let root = workdir(&repo)?;
+        let mut index = repo
+            .index()
+            .map_err(|e| format!("Failed to open index: {}", e.message()))?;
+
+        for file in &files {
+            let relative = relative_path(&root, file)?;
+            let result = if root.join(&relative).is_dir() {
+                index.add_all([&relative], IndexAddOption::DEFAULT, None)
+            } else if root.join(&relative).exists() {
+                index.add_path(&relative)
+            } else {
+                index.remove_path(&relative)
+            };
+            result.map_err(|e| format!("Failed to stage {}: {}", file, e.message()))?;
+        }
+
+        index
+            .write()
+            .map_err(|e| format!("Failed to write index: {}", e.message()))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn git_unstage(path: String, files: Vec<String>) -> Result<(), String> {
+    run_blocking(move || {
+        let repo = open_repo(&path)?;
+        let root = workdir(&repo)?;
+        let relative: Vec<PathBuf> = files
+            .iter()
+            .map(|file| relative_path(&root, file))
+            .collect::<Result<_, _>>()?;
+
+        let head = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let result = match head {
+            Some(head) => repo
+                .reset_default(Some(head.as_object()), &relative)
+                .map_err(|e| format!("Failed to unstage: {}", e.message())),
+            // Nothing committed yet: unstaging means dropping from the index
+            None => {
+                let mut index = repo
+                    .index()
+                    .map_err(|e| format!("Failed to open index: {}", e.message()))?;
+                for path in &relative {
+                    let _ = index.remove_path(path);
+                }
+                index
+                    .write()
+                    .map_err(|e| format!("Failed to write index: {}", e.message()))
+            }
+        };
+        result
+    })
+    .await
+}
+
+/// Commit the staged changes, returning the new commit id
+#[tauri::command]
+pub async fn git_commit(path: String, message: String) -> Result<String, String> {
+    if message.trim().is_empty() {
+        return Err("Commit message is empty".to_string());
+    }
+
+    run_blocking(move || {
+        let repo = open_repo(&path)?;
+        let signature = repo.signature().map_err(|e| {
+            format!(
+                "Git user.name and user.email are not configured: {}",
+                e.message()
+            )
+        })?;
+
+        let mut index = repo
+            .index()
+            .map_err(|e| format!("Failed to open index: {}", e.message()))?;
+        let tree_oid = index
+            .write_tree()
+            .map_err(|e| format!("Failed to write tree: {}", e.message()))?;
+        let tree = repo
+            .find_tree(tree_oid)
+            .map_err(|e| format!("Failed to find tree: {}", e.message()))?;
+
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+
+        let oid = repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                &message,
+                &tree,
+                &parents,
+            )
+            .map_err(|e| format!("Failed to commit: {}", e.message()))?;
+
+        Ok(oid.to_string())
+    })
+    .await
+}
@@ -0,0 +1,356 @@
+use crate::collab::{collab_connect, CollabRegistry, CollabSession};
+use collab_protocol::{is_binary_extension, ClientMessage, HostedEntry, ScanOptions};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use tauri::{AppHandle, State, Window};
+use tokio::sync::mpsc;
+
+// ============================================================================
+// HOSTING TYPES
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct HostedProject {
+    pub session: CollabSession,
+    pub name: String,
+    pub file_count: usize,
+    pub folder_count: usize,
+    /// Files left out because they are binary or too large
+    pub skipped_files: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateProjectResponse {
+    project_id: String,
+    name: String,
+}
+
+#[derive(Debug, Default)]
+struct FolderScan {
+    entries: Vec<HostedEntry>,
+    skipped_files: Vec<String>,
+}
+
+/// Serves a hosted folder over the window's collab connection.
+///
+/// Only paths that are part of the uploaded tree are ever read, so guests
+/// cannot request arbitrary files from the host's disk.
+pub struct HostSession {
+    root: PathBuf,
+    project_id: String,
+    options: ScanOptions,
+    /// Hosted tree keyed by path
+    known: HashMap<String, HostedEntry>,
+    /// Files whose content was sent; later edits on disk are pushed
+    served: HashSet<String>,
+    changes: mpsc::UnboundedReceiver<notify::Result<Event>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl HostSession {
+    /// Wait for the next change in the hosted folder
+    pub(crate) async fn next_change(&mut self) -> Option<notify::Result<Event>> {
+        self.changes.recv().await
+    }
+
+    /// Upload the whole tree, as when the room is first hosted.
+    ///
+    /// Sent again after a reconnect: the server forgot the loaded contents, so
+    /// files are requested afresh instead of being pushed on change.
+    pub(crate) fn host_message(&mut self) -> ClientMessage {
+        self.served.clear();
+        ClientMessage::HostProject {
+            project_id: self.project_id.clone(),
+            entries: self.known.values().cloned().collect(),
+        }
+    }
+
+    /// Answer a `FileRequest` from the server
+    pub(crate) async fn serve_file(&mut self, file_path: &str) -> ClientMessage {
+        let content = match self.known.get(file_path) {
+            Some(entry) if !entry.is_dir => tokio::fs::read_to_string(self.root.join(file_path))
+                .await
+                .ok(),
+            _ => None,
+        };
+        if content.is_some() {
+            self.served.insert(file_path.to_string());
+        }
+
+        ClientMessage::HostFileContent {
+            project_id: self.project_id.clone(),
+            file_path: file_path.to_string(),
+            content,
+        }
+    }
+
+    /// Turn a folder change into tree updates and content pushes
+    pub(crate) fn handle_change(&mut self, event: notify::Result<Event>) -> Vec<ClientMessage> {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                log::warn!("Hosted folder watcher error: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let mut created = Vec::new();
+        let mut deleted = Vec::new();
+        let mut messages = Vec::new();
+
+        for path in &event.paths {
+            let Some(relative) = relative_path(&self.root, path) else {
+                continue;
+            };
+            if is_excluded(&self.options, &relative) {
+                continue;
+            }
+
+            if path.exists() {
+                if !self.known.contains_key(&relative) {
+                    // New file, or a folder that may already have content (moved in)
+                    let scan = scan_folder(&self.root, path, &self.options);
+                    for entry in scan.entries {
+                        if !self.known.contains_key(&entry.path) {
+                            self.known.insert(entry.path.clone(), entry.clone());
+                            created.push(entry);
+                        }
+                    }
+                } else if self.served.contains(&relative) {
+                    messages.push(ClientMessage::HostFileContent {
+                        project_id: self.project_id.clone(),
+                        file_path: relative.clone(),
+                        content: std::fs::read_to_string(path).ok(),
+                    });
+                }
+            } else if self.known.contains_key(&relative) {
+                let below = format!("{}/", relative);
+                self.known
+                    .retain(|known, _| known != &relative && !known.starts_with(&below));
+                self.served
+                    .retain(|served| served != &relative && !served.starts_with(&below));
+                deleted.push(relative);
+            }
+        }
+
+        if !created.is_empty() || !deleted.is_empty() {
+            messages.insert(
+                0,
+                ClientMessage::HostTreeUpdate {
+                    project_id: self.project_id.clone(),
+                    created,
+                    deleted,
+                },
+            );
+        }
+        messages
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// `/`-separated path relative to the hosted root, None if outside it
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let parts: Vec<String> = relative
+        .components()
+        .map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// Apply the exclude patterns to every component, as the server scan does
+fn is_excluded(options: &ScanOptions, relative: &str) -> bool {
+    relative
+        .split('/')
+        .any(|name| options.should_exclude(relative, name))
+}
+
+/// Scan `start` (the root or a folder below it) with the server's scan rules.
+///
+/// Entries are relative to `root`; `start` itself is included unless it is the root.
+fn scan_folder(root: &Path, start: &Path, options: &ScanOptions) -> FolderScan {
+    let mut scan = FolderScan::default();
+    let mut file_count = 0;
+
+    let mut walker = walkdir::WalkDir::new(start).sort_by_file_name();
+    if options.max_depth > 0 {
+        let start_depth = start
+            .strip_prefix(root)
+            .map_or(0, |p| p.components().count());
+        walker = walker.max_depth((options.max_depth + 1).saturating_sub(start_depth).max(1));
+    }
+
+    let entries = walker
+        .into_iter()
+        .filter_entry(|entry| {
+            relative_path(root, entry.path())
+                .is_none_or(|relative| !is_excluded(options, &relative))
+        })
+        .filter_map(|entry| entry.ok());
+
+    for entry in entries {
+        let Some(relative) = relative_path(root, entry.path()) else {
+            continue;
+        };
+
+        if entry.file_type().is_dir() {
+            scan.entries.push(HostedEntry {
+                path: relative,
+                is_dir: true,
+                size: 0,
+            });
+            continue;
+        }
+        if !entry.file_type().is_file() || file_count >= options.max_files {
+            continue;
+        }
+
+        let extension = entry
+            .path()
+            .extension()
+            .map(|e| e.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if !options.should_include_extension(&extension) {
+            continue;
+        }
+
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        if is_binary_extension(&relative) || size > options.max_file_size {
+            scan.skipped_files.push(relative);
+            continue;
+        }
+
+        scan.entries.push(HostedEntry {
+            path: relative,
+            is_dir: false,
+            size,
+        });
+        file_count += 1;
+    }
+
+    scan
+}
+
+/// `ws://` / `wss://` base for an `http://` / `https://` server URL
+fn websocket_url(server_url: &str) -> String {
+    let server_url = server_url.trim_end_matches('/');
+    if let Some(rest) = server_url.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = server_url.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        server_url.to_string()
+    }
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Host a local folder in a new room on a CodeCollab server.
+///
+/// The folder is scanned here and its tree uploaded; file contents are sent
+/// when a peer first opens them, and changes on disk are kept in sync while
+/// the window stays connected. `server_url` is the HTTP base of the server.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn host_project(
+    app: AppHandle,
+    window: Window,
+    registry: State<'_, CollabRegistry>,
+    server_url: String,
+    folder_path: String,
+    name: Option<String>,
+    display_name: Option<String>,
+    options: Option<ScanOptions>,
+) -> Result<HostedProject, String> {
+    let root = PathBuf::from(&folder_path);
+    if !root.is_dir() {
+        return Err(format!("Path is not a directory: {}", folder_path));
+    }
+    let options = options.unwrap_or_default();
+
+    let scan_root = root.clone();
+    let scan_options = options.clone();
+    let scan =
+        tokio::task::spawn_blocking(move || scan_folder(&scan_root, &scan_root, &scan_options))
+            .await
+            .map_err(|e| format!("Failed to scan folder: {}", e))?;
+
+    let name = name
+        .filter(|n| !n.trim().is_empty())
+        .or_else(|| root.file_name().map(|n| n.to_string_lossy().to_string()));
+    let created: CreateProjectResponse = reqwest::Client::new()
+        .post(format!("{}/api/projects", server_url.trim_end_matches('/')))
+        .json(&serde_json::json!({ "name": name }))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to create room: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid create room response: {}", e))?;
+
+    let (change_tx, changes) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |res| {
+        let _ = change_tx.send(res);
+    })
+    .map_err(|e| format!("Failed to create watcher: {}", e))?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch directory: {}", e))?;
+
+    let host = HostSession {
+        root,
+        project_id: created.project_id.clone(),
+        options,
+        known: scan
+            .entries
+            .iter()
+            .map(|entry| (entry.path.clone(), entry.clone()))
+            .collect(),
+        served: HashSet::new(),
+        changes,
+        _watcher: watcher,
+    };
+
+    let session = collab_connect(
+        app,
+        window.label(),
+        &registry,
+        &websocket_url(&server_url),
+        created.project_id.clone(),
+        display_name,
+        None,
+        None,
+        Some(host),
+    )
+    .await?;
+
+    let folder_count = scan.entries.iter().filter(|entry| entry.is_dir).count();
+    let file_count = scan.entries.len() - folder_count;
+    registry.send(
+        window.label(),
+        ClientMessage::HostProject {
+            project_id: created.project_id,
+            entries: scan.entries,
+        },
+    )?;
+
+    Ok(HostedProject {
+        session,
+        name: created.name,
+        file_count,
+        folder_count,
+        skipped_files: scan.skipped_files,
+    })
+}
@@ -0,0 +1,363 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, State};
+
+use super::collections::{load_environments, substitute_variables};
+
+// ============================================================================
+// AUTH TYPES
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyLocation {
+    Header,
+    Query,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OAuth2Grant {
+    ClientCredentials,
+    AuthorizationCode,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OAuth2Config {
+    pub grant: OAuth2Grant,
+    pub token_url: String,
+    /// Authorization endpoint, only used by the authorization code grant
+    pub auth_url: Option<String>,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub scope: Option<String>,
+    pub redirect_uri: Option<String>,
+}
+
+/// How a request authenticates; added to the request when it is sent
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HttpAuth {
+    Basic {
+        username: String,
+        password: String,
+    },
+    Bearer {
+        token: String,
+    },
+    ApiKey {
+        key: String,
+        value: String,
+        location: ApiKeyLocation,
+    },
+    #[serde(rename = "oauth2")]
+    OAuth2(OAuth2Config),
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    token_type: Option<String>,
+    expires_in: Option<u64>,
+    refresh_token: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct CachedToken {
+    access_token: String,
+    token_type: String,
+    refresh_token: Option<String>,
+    expires_at: Option<Instant>,
+}
+
+impl CachedToken {
+    fn is_fresh(&self) -> bool {
+        self.expires_at
+            .is_none_or(|at| Instant::now() + TOKEN_EXPIRY_MARGIN < at)
+    }
+}
+
+/// Refresh tokens this long before they expire
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// OAuth2 tokens kept in memory, keyed by token endpoint, client and scope
+#[derive(Default)]
+pub struct OAuth2Tokens {
+    tokens: Mutex<HashMap<String, CachedToken>>,
+}
+
+impl OAuth2Tokens {
+    fn get(&self, key: &str) -> Option<CachedToken> {
+        self.tokens.lock().ok()?.get(key).cloned()
+    }
+
+    fn insert(&self, key: String, token: CachedToken) {
+        if let Ok(mut tokens) = self.tokens.lock() {
+            tokens.insert(key, token);
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        if let Ok(mut tokens) = self.tokens.lock() {
+            tokens.remove(key);
+        }
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn cache_key(config: &OAuth2Config) -> String {
+    format!(
+        "{}|{}|{}",
+        config.token_url,
+        config.client_id,
+        config.scope.as_deref().unwrap_or_default()
+    )
+}
+
+/// Environment variables, then `{{secret:name}}` references from the keychain
+fn resolve(text: &str, variables: &HashMap<String, String>) -> String {
+    crate::secrets::expand_references(&substitute_variables(text, variables))
+}
+
+impl OAuth2Config {
+    fn substitute(&mut self, variables: &HashMap<String, String>) {
+        self.token_url = resolve(&self.token_url, variables);
+        self.client_id = resolve(&self.client_id, variables);
+        for field in [
+            &mut self.auth_url,
+            &mut self.client_secret,
+            &mut self.scope,
+            &mut self.redirect_uri,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *field = resolve(field, variables);
+        }
+    }
+}
+
+impl HttpAuth {
+    /// Substitute environment variables and keychain secrets in every field
+    pub(crate) fn substitute(&mut self, variables: &HashMap<String, String>) {
+        let sub = |text: &mut String| *text = resolve(text, variables);
+        match self {
+            HttpAuth::Basic { username, password } => {
+                sub(username);
+                sub(password);
+            }
+            HttpAuth::Bearer { token } => sub(token),
+            HttpAuth::ApiKey { key, value, .. } => {
+                sub(key);
+                sub(value);
+            }
+            HttpAuth::OAuth2(config) => config.substitute(variables),
+        }
+    }
+}
+
+/// Config with the active environment applied, as send_http_request sees it
+fn resolve_config(app: &AppHandle, mut config: OAuth2Config) -> Result<OAuth2Config, String> {
+    let variables = load_environments(app)?.active_variables();
+    config.substitute(&variables);
+    Ok(config)
+}
+
+/// Call the token endpoint with the given grant parameters
+async fn request_token(
+    client: &reqwest::Client,
+    config: &OAuth2Config,
+    params: &[(&str, &str)],
+) -> Result<CachedToken, String> {
+    let mut form: Vec<(&str, &str)> = params.to_vec();
+    form.push(("client_id", &config.client_id));
+    if let Some(secret) = &config.client_secret {
+        form.push(("client_secret", secret));
+    }
+
+    let response = client
+        .post(&config.token_url)
+        .header("Accept", "application/json")
+        .form(&form)
+        .send()
+        .await
+        .map_err(|e| format!("Token request failed: {}", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Token request failed ({}): {}", status, body));
+    }
+
+    let token: TokenResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid token response: {}", e))?;
+
+    Ok(CachedToken {
+        access_token: token.access_token,
+        token_type: token.token_type.unwrap_or_else(|| "Bearer".to_string()),
+        refresh_token: token.refresh_token,
+        expires_at: token
+            .expires_in
+            .map(|secs| Instant::now() + Duration::from_secs(secs)),
+    })
+}
+
+/// A valid access token: cached, refreshed, or (client credentials) newly issued
+async fn oauth2_token(
+    client: &reqwest::Client,
+    tokens: &OAuth2Tokens,
+    config: &OAuth2Config,
+) -> Result<CachedToken, String> {
+    let key = cache_key(config);
+    let cached = tokens.get(&key);
+
+    if let Some(token) = cached.as_ref().filter(|t| t.is_fresh()) {
+        return Ok(token.clone());
+    }
+
+    if let Some(refresh_token) = cached.and_then(|t| t.refresh_token) {
+        match request_token(
+            client,
+            config,
+            &[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", &refresh_token),
+            ],
+        )
+        .await
+        {
+            Ok(mut token) => {
+                // Servers may keep the refresh token and omit it from the answer
+                token.refresh_token.get_or_insert(refresh_token);
+                tokens.insert(key, token.clone());
+                return Ok(token);
+            }
+            Err(e) => {
+                log::warn!("OAuth2 token refresh failed: {}", e);
+                tokens.remove(&key);
+            }
+        }
+    }
+
+    match config.grant {
+        OAuth2Grant::ClientCredentials => {
+            let mut params = vec![("grant_type", "client_credentials")];
+            if let Some(scope) = &config.scope {
+                params.push(("scope", scope));
+            }
+            let token = request_token(client, config, &params).await?;
+            tokens.insert(key, token.clone());
+            Ok(token)
+        }
+        OAuth2Grant::AuthorizationCode => Err(
+            "OAuth2 authorization required: sign in with oauth2_authorization_url first"
+                .to_string(),
+        ),
+    }
+}
+
+/// Add the credentials to a request
+pub(crate) async fn apply_auth(
+    builder: reqwest::RequestBuilder,
+    auth: &HttpAuth,
+    client: &reqwest::Client,
+    tokens: &OAuth2Tokens,
+) -> Result<reqwest::RequestBuilder, String> {
+    Ok(match auth {
+        HttpAuth::Basic { username, password } => builder.basic_auth(username, Some(password)),
+        HttpAuth::Bearer { token } => builder.bearer_auth(token),
+        HttpAuth::ApiKey {
+            key,
+            value,
+            location: ApiKeyLocation::Header,
+        } => builder.header(key, value),
+        HttpAuth::ApiKey {
+            key,
+            value,
+            location: ApiKeyLocation::Query,
+        } => builder.query(&[(key, value)]),
+        HttpAuth::OAuth2(config) => {
+            let token = oauth2_token(client, tokens, config).await?;
+            builder.header(
+                "Authorization",
+                format!("{} {}", token.token_type, token.access_token),
+            )
+        }
+    })
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Build the URL to open in a browser to start the authorization code flow
+#[tauri::command]
+pub fn oauth2_authorization_url(
+    app: AppHandle,
+    config: OAuth2Config,
+    state: String,
+) -> Result<String, String> {
+    let config = resolve_config(&app, config)?;
+    let auth_url = config
+        .auth_url
+        .as_deref()
+        .ok_or("OAuth2 config has no authorization URL")?;
+    let mut url =
+        url::Url::parse(auth_url).map_err(|e| format!("Invalid authorization URL: {}", e))?;
+
+    {
+        let mut query = url.query_pairs_mut();
+        query
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &config.client_id)
+            .append_pair("state", &state);
+        if let Some(redirect_uri) = &config.redirect_uri {
+            query.append_pair("redirect_uri", redirect_uri);
+        }
+        if let Some(scope) = &config.scope {
+            query.append_pair("scope", scope);
+        }
+    }
+
+    Ok(url.to_string())
+}
+
+/// Exchange an authorization code for tokens; later requests reuse and refresh them
+#[tauri::command]
+pub async fn oauth2_exchange_code(
+    app: AppHandle,
+    tokens: State<'_, OAuth2Tokens>,
+    config: OAuth2Config,
+    code: String,
+) -> Result<(), String> {
+    let config = resolve_config(&app, config)?;
+    let mut params = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code.as_str()),
+    ];
+    if let Some(redirect_uri) = &config.redirect_uri {
+        params.push(("redirect_uri", redirect_uri));
+    }
+
+    let token = request_token(&reqwest::Client::new(), &config, &params).await?;
+    tokens.insert(cache_key(&config), token);
+    Ok(())
+}
+
+/// Forget every cached OAuth2 token
+#[tauri::command]
+pub fn clear_oauth2_tokens(tokens: State<'_, OAuth2Tokens>) -> Result<(), String> {
+    tokens
+        .tokens
+        .lock()
+        .map_err(|_| "Token cache is poisoned".to_string())?
+        .clear();
+    Ok(())
+}
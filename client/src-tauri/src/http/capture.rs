@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::CryptoProvider;
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{DigitallySignedStruct, SignatureScheme};
+
+// ============================================================================
+// CAPTURE TYPES
+// ============================================================================
+
+/// Redirects followed when the request does not set max_redirects
+pub const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RedirectHop {
+    pub status: u16,
+    pub url: String,
+    /// Where the hop redirected to
+    pub location: String,
+}
+
+/// Connection phases in milliseconds.
+///
+/// DNS, connect and TLS are measured on a probe connection to the first URL
+/// opened just before the request, so they are None when the probe failed.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TimingBreakdown {
+    pub dns_ms: Option<f64>,
+    pub connect_ms: Option<f64>,
+    pub tls_ms: Option<f64>,
+    /// Until the response headers of the last hop arrived
+    pub first_byte_ms: f64,
+    pub total_ms: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TlsDetails {
+    /// e.g. "TLS 1.3"
+    pub version: String,
+    pub cert_subject: String,
+    pub cert_issuer: String,
+    /// Certificate expiry in milliseconds since epoch
+    pub cert_not_after: Option<i64>,
+}
+
+/// What the probe connection learned about the server
+#[derive(Debug, Default)]
+pub(crate) struct ConnectionProbe {
+    pub timing: TimingBreakdown,
+    pub tls: Option<TlsDetails>,
+}
+
+/// Accepts any certificate: the probe only reports what the server presents,
+/// the request itself decides whether to trust it
+#[derive(Debug)]
+struct InspectOnly(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for InspectOnly {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn tls_details(connection: &rustls::ClientConnection) -> Option<TlsDetails> {
+    let version = match connection.protocol_version()? {
+        rustls::ProtocolVersion::TLSv1_2 => "TLS 1.2".to_string(),
+        rustls::ProtocolVersion::TLSv1_3 => "TLS 1.3".to_string(),
+        other => format!("{:?}", other),
+    };
+
+    let (cert_subject, cert_issuer, cert_not_after) = connection
+        .peer_certificates()
+        .and_then(|certs| certs.first())
+        .and_then(|der| x509_parser::parse_x509_certificate(der).ok())
+        .map(|(_, cert)| {
+            (
+                cert.subject().to_string(),
+                cert.issuer().to_string(),
+                Some(cert.validity().not_after.timestamp() * 1000),
+            )
+        })
+        .unwrap_or_default();
+
+    Some(TlsDetails {
+        version,
+        cert_subject,
+        cert_issuer,
+        cert_not_after,
+    })
+}
+
+async fn run_probe(url: &reqwest::Url, probe: &mut ConnectionProbe) -> Result<(), String> {
+    let host = url.host_str().ok_or("URL has no host")?;
+    let port = url.port_or_known_default().ok_or("URL has no port")?;
+
+    let start = Instant::now();
+    let addr = tokio::net::lookup_host((host.trim_matches(['[', ']']), port))
+        .await
+        .map_err(|e| e.to_string())?
+        .next()
+        .ok_or("Host did not resolve")?;
+    probe.timing.dns_ms = Some(millis(start.elapsed()));
+
+    let start = Instant::now();
+    let tcp = TcpStream::connect(addr).await.map_err(|e| e.to_string())?;
+    probe.timing.connect_ms = Some(millis(start.elapsed()));
+
+    if url.scheme() != "https" {
+        return Ok(());
+    }
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(InspectOnly(provider)))
+        .with_no_client_auth();
+    let server_name = ServerName::try_from(host.trim_matches(['[', ']']).to_string())
+        .map_err(|e| e.to_string())?;
+
+    let start = Instant::now();
+    let tls = tokio_rustls::TlsConnector::from(Arc::new(config))
+        .connect(server_name, tcp)
+        .await
+        .map_err(|e| e.to_string())?;
+    probe.timing.tls_ms = Some(millis(start.elapsed()));
+    probe.tls = tls_details(tls.get_ref().1);
+
+    Ok(())
+}
+
+/// Time DNS, connect and TLS to the URL's host and read its TLS details.
+///
+/// Failures are logged and leave the fields empty; the request itself reports
+/// the real error.
+pub(crate) async fn probe_connection(url: &reqwest::Url, timeout: Duration) -> ConnectionProbe {
+    let mut probe = ConnectionProbe::default();
+    match tokio::time::timeout(timeout, run_probe(url, &mut probe)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => log::debug!("Connection probe to {} failed: {}", url, e),
+        Err(_) => log::debug!("Connection probe to {} timed out", url),
+    }
+    probe
+}
+
+/// Headers that must not follow a redirect to another origin
+fn strip_credentials(request: &mut reqwest::Request) {
+    let headers = request.headers_mut();
+    headers.remove(reqwest::header::AUTHORIZATION);
+    headers.remove(reqwest::header::COOKIE);
+    headers.remove(reqwest::header::PROXY_AUTHORIZATION);
+}
+
+/// Send a request, following redirects by hand so every hop is recorded.
+///
+/// Like browsers, 301/302/303 turn into a GET without body while 307/308 keep
+/// method and body; credentials are dropped when the origin changes.
+pub(crate) async fn execute_with_redirects(
+    client: &reqwest::Client,
+    mut request: reqwest::Request,
+    max_redirects: usize,
+) -> Result<(reqwest::Response, Vec<RedirectHop>), String> {
+    let mut hops = Vec::new();
+
+    loop {
+        let retry = request.try_clone();
+        let response = client
+            .execute(request)
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        let status = response.status();
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|location| response.url().join(location).ok());
+
+        let (Some(location), Some(mut next)) = (location, retry) else {
+            return Ok((response, hops));
+        };
+        if !status.is_redirection() || hops.len() >= max_redirects {
+            return Ok((response, hops));
+        }
+
+        hops.push(RedirectHop {
+            status: status.as_u16(),
+            url: response.url().to_string(),
+            location: location.to_string(),
+        });
+
+        if matches!(status.as_u16(), 301..=303) && next.method() != reqwest::Method::HEAD {
+            *next.method_mut() = reqwest::Method::GET;
+            *next.body_mut() = None;
+            next.headers_mut().remove(reqwest::header::CONTENT_TYPE);
+            next.headers_mut().remove(reqwest::header::CONTENT_LENGTH);
+        }
+        if next.url().origin() != location.origin() {
+            strip_credentials(&mut next);
+        }
+        *next.url_mut() = location;
+        request = next;
+    }
+}
@@ -0,0 +1,502 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use super::extract::Extraction;
+use super::runner::Assertion;
+use super::transport::TransportSettings;
+use super::{http_data_dir, HttpRequest};
+use crate::secrets;
+
+// ============================================================================
+// COLLECTION TYPES
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CollectionFolder {
+    pub id: String,
+    pub name: String,
+    /// None for folders at the top of the collection
+    pub parent_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SavedRequest {
+    pub id: String,
+    pub name: String,
+    /// None for requests at the top of the collection
+    pub folder_id: Option<String>,
+    pub request: HttpRequest,
+    /// Checked against the response when the collection is run
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+    /// Values taken from the response into environment variables for later requests
+    #[serde(default)]
+    pub extractions: Vec<Extraction>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    pub folders: Vec<CollectionFolder>,
+    pub requests: Vec<SavedRequest>,
+    /// When the collection was last saved (milliseconds since epoch)
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EnvironmentVariable {
+    pub key: String,
+    pub value: String,
+    pub enabled: bool,
+    /// The value is kept in the OS keychain and left empty on disk
+    #[serde(default)]
+    pub secret: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Environment {
+    pub id: String,
+    pub name: String,
+    pub variables: Vec<EnvironmentVariable>,
+    /// Proxy and TLS settings for requests sent while this environment is active
+    #[serde(default)]
+    pub transport: TransportSettings,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Environments {
+    pub active_id: Option<String>,
+    pub environments: Vec<Environment>,
+}
+
+impl Environments {
+    fn active(&self) -> Option<&Environment> {
+        self.environments
+            .iter()
+            .find(|env| Some(&env.id) == self.active_id.as_ref())
+    }
+
+    /// Enabled variables of the active environment, with secret values
+    /// read from the keychain
+    pub fn active_variables(&self) -> HashMap<String, String> {
+        self.active()
+            .map(|env| {
+                env.variables
+                    .iter()
+                    .filter(|var| var.enabled && !var.key.is_empty())
+                    .filter_map(|var| {
+                        if !var.secret {
+                            return Some((var.key.clone(), var.value.clone()));
+                        }
+                        match secrets::read_secret(&secrets::environment_key(&env.id, &var.key)) {
+                            Ok(value) => value.map(|value| (var.key.clone(), value)),
+                            Err(e) => {
+                                log::warn!("Secret variable {} is unavailable: {}", var.key, e);
+                                None
+                            }
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Transport settings of the active environment
+    pub fn active_transport(&self) -> TransportSettings {
+        self.active()
+            .map(|env| env.transport.clone())
+            .unwrap_or_default()
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn collections_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(http_data_dir(app)?.join("collections"))
+}
+
+/// Collection ids end up in file names, so only generated-looking ids are accepted
+fn collection_path(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+    if id.is_empty()
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("Invalid collection id: {}", id));
+    }
+    Ok(collections_dir(app)?.join(format!("{}.json", id)))
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+
+    let json =
+        serde_json::to_vec_pretty(value).map_err(|e| format!("Failed to serialize: {}", e))?;
+    crate::atomic_write(path, &json)
+}
+
+pub(crate) fn read_collection(app: &AppHandle, id: &str) -> Result<Collection, String> {
+    let path = collection_path(app, id)?;
+    let json = std::fs::read(&path).map_err(|_| format!("Collection not found: {}", id))?;
+
+    serde_json::from_slice(&json).map_err(|e| format!("Failed to parse collection: {}", e))
+}
+
+pub(crate) fn write_collection(app: &AppHandle, collection: &mut Collection) -> Result<(), String> {
+    collection.updated_at = chrono::Utc::now().timestamp_millis();
+    write_json(&collection_path(app, &collection.id)?, collection)
+}
+
+/// Drop folders whose parent is gone and move orphaned requests to the top
+fn normalize_collection(collection: &mut Collection) {
+    loop {
+        let ids: Vec<String> = collection.folders.iter().map(|f| f.id.clone()).collect();
+        let before = collection.folders.len();
+        collection
+            .folders
+            .retain(|f| f.parent_id.as_ref().is_none_or(|p| ids.contains(p)));
+        if collection.folders.len() == before {
+            break;
+        }
+    }
+
+    for request in &mut collection.requests {
+        if let Some(folder_id) = &request.folder_id {
+            if !collection.folders.iter().any(|f| &f.id == folder_id) {
+                request.folder_id = None;
+            }
+        }
+    }
+}
+
+/// Load the environments, treating a missing file as none defined
+pub(crate) fn load_environments(app: &AppHandle) -> Result<Environments, String> {
+    let path = http_data_dir(app)?.join("environments.json");
+    if !path.exists() {
+        return Ok(Environments::default());
+    }
+
+    let json = std::fs::read(&path).map_err(|e| format!("Failed to read environments: {}", e))?;
+    serde_json::from_slice(&json).map_err(|e| format!("Failed to parse environments: {}", e))
+}
+
+fn save_environments(app: &AppHandle, environments: &Environments) -> Result<(), String> {
+    write_json(&http_data_dir(app)?.join("environments.json"), environments)
+}
+
+/// Move the values of secret variables into the keychain, and drop the
+/// keychain entries of variables that are no longer secret. A secret
+/// variable sent with an empty value keeps its stored secret.
+fn store_secret_variables(
+    environment: &mut Environment,
+    previous: Option<&Environment>,
+) -> Result<(), String> {
+    for var in environment.variables.iter_mut().filter(|var| var.secret) {
+        if !var.value.is_empty() {
+            secrets::write_secret(
+                &secrets::environment_key(&environment.id, &var.key),
+                &var.value,
+            )?;
+            var.value.clear();
+        }
+    }
+
+    for old in previous.into_iter().flat_map(|env| &env.variables) {
+        let still_secret = environment
+            .variables
+            .iter()
+            .any(|var| var.secret && var.key == old.key);
+        if old.secret && !still_secret {
+            secrets::remove_secret(&secrets::environment_key(&environment.id, &old.key))?;
+        }
+    }
+    Ok(())
+}
+
+/// Set variables on the active environment, adding the ones it lacks.
+///
+/// Returns false without saving anything when no environment is active.
+pub(crate) fn store_active_variables(
+    app: &AppHandle,
+    values: &HashMap<String, String>,
+) -> Result<bool, String> {
+    let mut environments = load_environments(app)?;
+    let active_id = environments.active_id.clone();
+    let Some(environment) = environments
+        .environments
+        .iter_mut()
+        .find(|env| Some(&env.id) == active_id.as_ref())
+    else {
+        return Ok(false);
+    };
+
+    for (key, value) in values {
+        match environment.variables.iter_mut().find(|var| &var.key == key) {
+            Some(var) if var.secret => {
+                secrets::write_secret(&secrets::environment_key(&environment.id, key), value)?;
+                var.enabled = true;
+            }
+            Some(var) => {
+                var.value = value.clone();
+                var.enabled = true;
+            }
+            None => environment.variables.push(EnvironmentVariable {
+                key: key.clone(),
+                value: value.clone(),
+                enabled: true,
+                secret: false,
+            }),
+        }
+    }
+
+    save_environments(app, &environments)?;
+    Ok(true)
+}
+
+/// Replace `{{name}}` with the variable's value; unknown variables are left as written
+pub(crate) fn substitute_variables(text: &str, variables: &HashMap<String, String>) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+
+        match after.find("}}") {
+            Some(end) => {
+                let name = after[..end].trim();
+                match variables.get(name) {
+                    Some(value) => result.push_str(value),
+                    None => result.push_str(&rest[start..start + 2 + end + 2]),
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                result.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Apply the variables to the URL, headers, body and auth of a request
+pub(crate) fn apply_environment(request: &mut HttpRequest, variables: &HashMap<String, String>) {
+    // Auth can refer to keychain secrets even without an active environment
+    if let Some(auth) = &mut request.auth {
+        auth.substitute(variables);
+    }
+    if variables.is_empty() {
+        return;
+    }
+
+    request.url = substitute_variables(&request.url, variables);
+    for header in &mut request.headers {
+        header.key = substitute_variables(&header.key, variables);
+        header.value = substitute_variables(&header.value, variables);
+    }
+    if let Some(body) = &request.body {
+        request.body = Some(substitute_variables(body, variables));
+    }
+    if let Some(graphql) = &mut request.graphql {
+        graphql.substitute(variables);
+    }
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// List saved collections, sorted by name
+#[tauri::command]
+pub async fn list_collections(app: AppHandle) -> Result<Vec<Collection>, String> {
+    let dir = collections_dir(&app)?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries =
+        std::fs::read_dir(&dir).map_err(|e| format!("Failed to read collections: {}", e))?;
+
+    let mut collections = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+
+        match std::fs::read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_slice::<Collection>(&json).map_err(|e| e.to_string()))
+        {
+            Ok(collection) => collections.push(collection),
+            Err(e) => log::warn!("Skipping unreadable collection {}: {}", path.display(), e),
+        }
+    }
+
+    collections.sort_by_key(|c| c.name.to_lowercase());
+    Ok(collections)
+}
+
+/// Create or replace a collection (an empty id creates a new one).
+///
+/// Used to rename it and to organize its folders; folders whose parent is
+/// missing are dropped and their requests moved to the top level.
+#[tauri::command]
+pub async fn save_collection(
+    app: AppHandle,
+    mut collection: Collection,
+) -> Result<Collection, String> {
+    if collection.id.is_empty() {
+        collection.id = uuid::Uuid::new_v4().to_string();
+    }
+    if collection.name.trim().is_empty() {
+        return Err("Collection name cannot be empty".to_string());
+    }
+
+    normalize_collection(&mut collection);
+    write_collection(&app, &mut collection)?;
+    Ok(collection)
+}
+
+#[tauri::command]
+pub async fn delete_collection(app: AppHandle, collection_id: String) -> Result<(), String> {
+    let path = collection_path(&app, &collection_id)?;
+    std::fs::remove_file(&path).map_err(|e| format!("Failed to delete collection: {}", e))?;
+    super::diff::forget_responses(&app, &collection_id, None);
+    Ok(())
+}
+
+/// Add a request to a collection or update it in place (an empty id adds a new one)
+#[tauri::command]
+pub async fn save_request(
+    app: AppHandle,
+    collection_id: String,
+    mut request: SavedRequest,
+) -> Result<SavedRequest, String> {
+    let mut collection = read_collection(&app, &collection_id)?;
+
+    if let Some(folder_id) = &request.folder_id {
+        if !collection.folders.iter().any(|f| &f.id == folder_id) {
+            return Err(format!("Folder not found: {}", folder_id));
+        }
+    }
+
+    if request.id.is_empty() {
+        request.id = uuid::Uuid::new_v4().to_string();
+    }
+    match collection.requests.iter_mut().find(|r| r.id == request.id) {
+        Some(existing) => *existing = request.clone(),
+        None => collection.requests.push(request.clone()),
+    }
+
+    write_collection(&app, &mut collection)?;
+    Ok(request)
+}
+
+#[tauri::command]
+pub async fn delete_request(
+    app: AppHandle,
+    collection_id: String,
+    request_id: String,
+) -> Result<(), String> {
+    let mut collection = read_collection(&app, &collection_id)?;
+
+    let before = collection.requests.len();
+    collection.requests.retain(|r| r.id != request_id);
+    if collection.requests.len() == before {
+        return Err(format!("Request not found: {}", request_id));
+    }
+
+    write_collection(&app, &mut collection)?;
+    super::diff::forget_responses(&app, &collection_id, Some(&request_id));
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_environments(app: AppHandle) -> Result<Environments, String> {
+    load_environments(&app)
+}
+
+/// Create or replace an environment (an empty id creates a new one)
+#[tauri::command]
+pub async fn save_environment(
+    app: AppHandle,
+    mut environment: Environment,
+) -> Result<Environment, String> {
+    if environment.name.trim().is_empty() {
+        return Err("Environment name cannot be empty".to_string());
+    }
+    if environment.id.is_empty() {
+        environment.id = uuid::Uuid::new_v4().to_string();
+    }
+
+    let mut environments = load_environments(&app)?;
+    let previous = environments
+        .environments
+        .iter()
+        .find(|e| e.id == environment.id);
+    store_secret_variables(&mut environment, previous)?;
+
+    match environments
+        .environments
+        .iter_mut()
+        .find(|e| e.id == environment.id)
+    {
+        Some(existing) => *existing = environment.clone(),
+        None => environments.environments.push(environment.clone()),
+    }
+
+    save_environments(&app, &environments)?;
+    Ok(environment)
+}
+
+#[tauri::command]
+pub async fn delete_environment(app: AppHandle, environment_id: String) -> Result<(), String> {
+    let mut environments = load_environments(&app)?;
+    for environment in environments
+        .environments
+        .iter()
+        .filter(|e| e.id == environment_id)
+    {
+        for var in environment.variables.iter().filter(|var| var.secret) {
+            secrets::remove_secret(&secrets::environment_key(&environment.id, &var.key))?;
+        }
+    }
+    environments.environments.retain(|e| e.id != environment_id);
+    if environments.active_id.as_ref() == Some(&environment_id) {
+        environments.active_id = None;
+    }
+
+    save_environments(&app, &environments)
+}
+
+/// Switch the environment used by send_http_request (None to send requests as written)
+#[tauri::command]
+pub async fn set_active_environment(
+    app: AppHandle,
+    environment_id: Option<String>,
+) -> Result<(), String> {
+    let mut environments = load_environments(&app)?;
+
+    if let Some(id) = &environment_id {
+        if !environments.environments.iter().any(|e| &e.id == id) {
+            return Err(format!("Environment not found: {}", id));
+        }
+    }
+
+    environments.active_id = environment_id;
+    save_environments(&app, &environments)
+}
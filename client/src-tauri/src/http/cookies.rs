@@ -0,0 +1,245 @@
+use cookie_store::{CookieDomain, CookieExpiration, CookieStore};
+use reqwest_cookie_store::CookieStoreMutex;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
+use tauri::{AppHandle, State};
+
+// ============================================================================
+// COOKIE TYPES
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CookieInfo {
+    pub domain: String,
+    pub path: String,
+    pub name: String,
+    pub value: String,
+    /// Expiry in milliseconds since epoch, None for session cookies
+    pub expires_at: Option<i64>,
+    pub secure: bool,
+    pub http_only: bool,
+    /// Sent only to `domain` itself rather than its subdomains too
+    pub host_only: bool,
+}
+
+/// Cookies shared by every request that does not opt out, persisted in app data
+#[derive(Default)]
+pub struct CookieJar {
+    store: OnceLock<Arc<CookieStoreMutex>>,
+}
+
+impl CookieJar {
+    /// The shared store, loaded from disk on first use
+    pub(crate) fn store(&self, app: &AppHandle) -> Arc<CookieStoreMutex> {
+        self.store
+            .get_or_init(|| {
+                let store = match load_store(app) {
+                    Ok(store) => store,
+                    Err(e) => {
+                        // A corrupt jar should not break every request
+                        log::warn!("Starting with an empty cookie jar: {}", e);
+                        CookieStore::default()
+                    }
+                };
+                Arc::new(CookieStoreMutex::new(store))
+            })
+            .clone()
+    }
+
+    /// Write the persistent cookies to disk; session cookies only live in memory
+    pub(crate) fn persist(&self, app: &AppHandle) -> Result<(), String> {
+        let mut json = Vec::new();
+        {
+            let store = self.store(app);
+            let store = store
+                .lock()
+                .map_err(|_| "Cookie jar is poisoned".to_string())?;
+            cookie_store::serde::json::save(&store, &mut json)
+                .map_err(|e| format!("Failed to serialize cookies: {}", e))?;
+        }
+
+        let path = jar_path(app)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+        crate::atomic_write(&path, &json)
+    }
+
+    fn update<T>(
+        &self,
+        app: &AppHandle,
+        f: impl FnOnce(&mut CookieStore) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let result = {
+            let store = self.store(app);
+            let mut store = store
+                .lock()
+                .map_err(|_| "Cookie jar is poisoned".to_string())?;
+            f(&mut store)?
+        };
+        self.persist(app)?;
+        Ok(result)
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn jar_path(app: &AppHandle) -> Result<std::path::PathBuf, String> {
+    Ok(super::http_data_dir(app)?.join("cookies.json"))
+}
+
+fn load_store(app: &AppHandle) -> Result<CookieStore, String> {
+    let path = jar_path(app)?;
+    if !path.exists() {
+        return Ok(CookieStore::default());
+    }
+
+    let file = std::fs::File::open(&path).map_err(|e| format!("Failed to read cookies: {}", e))?;
+    cookie_store::serde::json::load(std::io::BufReader::new(file))
+        .map_err(|e| format!("Failed to parse cookies: {}", e))
+}
+
+fn cookie_info(cookie: &cookie_store::Cookie<'static>) -> CookieInfo {
+    let (domain, host_only) = match &cookie.domain {
+        CookieDomain::HostOnly(domain) => (domain.clone(), true),
+        CookieDomain::Suffix(domain) => (domain.clone(), false),
+        _ => (String::new(), true),
+    };
+    let expires_at = match &cookie.expires {
+        CookieExpiration::AtUtc(at) => Some((at.unix_timestamp_nanos() / 1_000_000) as i64),
+        CookieExpiration::SessionEnd => None,
+    };
+
+    CookieInfo {
+        domain,
+        path: cookie.path.to_string(),
+        name: cookie.name().to_string(),
+        value: cookie.value().to_string(),
+        expires_at,
+        secure: cookie.secure().unwrap_or(false),
+        http_only: cookie.http_only().unwrap_or(false),
+        host_only,
+    }
+}
+
+/// Cookies stored for the domain, or the ones it would receive as a subdomain
+fn matches_domain(cookie: &CookieInfo, domain: &str) -> bool {
+    cookie.domain == domain
+        || (!cookie.host_only
+            && domain
+                .strip_suffix(cookie.domain.as_str())
+                .is_some_and(|rest| rest.ends_with('.')))
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// List unexpired cookies, optionally only those for one domain
+#[tauri::command]
+pub async fn list_cookies(
+    app: AppHandle,
+    jar: State<'_, CookieJar>,
+    domain: Option<String>,
+) -> Result<Vec<CookieInfo>, String> {
+    let store = jar.store(&app);
+    let store = store
+        .lock()
+        .map_err(|_| "Cookie jar is poisoned".to_string())?;
+
+    let mut cookies: Vec<CookieInfo> = store
+        .iter_unexpired()
+        .map(cookie_info)
+        .filter(|cookie| domain.as_deref().is_none_or(|d| matches_domain(cookie, d)))
+        .collect();
+
+    cookies.sort_by(|a, b| (&a.domain, &a.path, &a.name).cmp(&(&b.domain, &b.path, &b.name)));
+    Ok(cookies)
+}
+
+/// Add a cookie or replace the one with the same domain, path and name
+#[tauri::command]
+pub async fn set_cookie(
+    app: AppHandle,
+    jar: State<'_, CookieJar>,
+    cookie: CookieInfo,
+) -> Result<(), String> {
+    if cookie.domain.is_empty() || cookie.name.is_empty() {
+        return Err("Cookie needs a domain and a name".to_string());
+    }
+
+    let path = if cookie.path.starts_with('/') {
+        cookie.path.clone()
+    } else {
+        "/".to_string()
+    };
+    let mut header = format!("{}={}; Path={}", cookie.name, cookie.value, path);
+    if !cookie.host_only {
+        header.push_str(&format!("; Domain={}", cookie.domain));
+    }
+    if let Some(expires_at) = cookie.expires_at {
+        let max_age = (expires_at - chrono::Utc::now().timestamp_millis()) / 1000;
+        header.push_str(&format!("; Max-Age={}", max_age.max(0)));
+    }
+    if cookie.secure {
+        header.push_str("; Secure");
+    }
+    if cookie.http_only {
+        header.push_str("; HttpOnly");
+    }
+
+    let scheme = if cookie.secure { "https" } else { "http" };
+    let url = url::Url::parse(&format!("{}://{}{}", scheme, cookie.domain, path))
+        .map_err(|e| format!("Invalid cookie domain: {}", e))?;
+
+    jar.update(&app, |store| {
+        store
+            .parse(&header, &url)
+            .map(|_| ())
+            .map_err(|e| format!("Invalid cookie: {}", e))
+    })
+}
+
+#[tauri::command]
+pub async fn delete_cookie(
+    app: AppHandle,
+    jar: State<'_, CookieJar>,
+    domain: String,
+    path: String,
+    name: String,
+) -> Result<(), String> {
+    jar.update(&app, |store| {
+        store
+            .remove(&domain, &path, &name)
+            .map(|_| ())
+            .ok_or_else(|| format!("Cookie not found: {}", name))
+    })
+}
+
+/// Remove every cookie, or only those for one domain
+#[tauri::command]
+pub async fn clear_cookies(
+    app: AppHandle,
+    jar: State<'_, CookieJar>,
+    domain: Option<String>,
+) -> Result<(), String> {
+    jar.update(&app, |store| {
+        let Some(domain) = domain else {
+            store.clear();
+            return Ok(());
+        };
+
+        let doomed: Vec<CookieInfo> = store
+            .iter_any()
+            .map(cookie_info)
+            .filter(|cookie| matches_domain(cookie, &domain))
+            .collect();
+        for cookie in doomed {
+            store.remove(&cookie.domain, &cookie.path, &cookie.name);
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,429 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+use super::{http_data_dir, HttpResponse};
+
+// ============================================================================
+// DIFF TYPES
+// ============================================================================
+
+/// Headers that differ on every response and would only add noise
+const VOLATILE_HEADERS: &[&str] = &["date", "age", "expires"];
+
+/// Above this many line pairs the text diff stops looking for common lines
+/// and reports the differing block as removed and added
+const MAX_TEXT_DIFF_CELLS: usize = 4_000_000;
+
+/// The last response of a saved request, kept to diff the next one against
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StoredResponse {
+    /// Milliseconds since epoch
+    pub saved_at: i64,
+    pub response: HttpResponse,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A JSON value that differs, at a path like `$.items[0].name`
+#[derive(Debug, Serialize, Clone)]
+pub struct JsonChange {
+    pub path: String,
+    pub kind: ChangeKind,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LineKind {
+    Same,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DiffLine {
+    pub kind: LineKind,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HeaderChange {
+    pub name: String,
+    pub kind: ChangeKind,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BodyDiff {
+    /// Both bodies are JSON
+    Json { changes: Vec<JsonChange> },
+    /// Line diff of the body text
+    Text { lines: Vec<DiffLine> },
+    /// A body is binary; only whether it changed is known
+    Binary { changed: bool },
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ResponseDiff {
+    /// (before, after) when the status changed
+    pub status: Option<(u16, u16)>,
+    pub headers: Vec<HeaderChange>,
+    pub body: BodyDiff,
+    /// Nothing changed in the status, headers or body
+    pub identical: bool,
+    /// When the previous response was stored, for diffs against a stored one
+    pub previous_saved_at: Option<i64>,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Ids end up in file names, so only generated-looking ids are accepted
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Directory of the stored responses of a collection
+fn responses_dir(app: &AppHandle, collection_id: &str) -> Result<PathBuf, String> {
+    if !valid_id(collection_id) {
+        return Err(format!("Invalid collection id: {}", collection_id));
+    }
+    Ok(http_data_dir(app)?.join("responses").join(collection_id))
+}
+
+fn stored_response_path(
+    app: &AppHandle,
+    collection_id: &str,
+    request_id: &str,
+) -> Result<PathBuf, String> {
+    if !valid_id(request_id) {
+        return Err(format!("Invalid request id: {}", request_id));
+    }
+    Ok(responses_dir(app, collection_id)?.join(format!("{}.json", request_id)))
+}
+
+pub(crate) fn read_stored_response(
+    app: &AppHandle,
+    collection_id: &str,
+    request_id: &str,
+) -> Result<Option<StoredResponse>, String> {
+    let path = stored_response_path(app, collection_id, request_id)?;
+    let Ok(json) = std::fs::read(&path) else {
+        return Ok(None);
+    };
+    serde_json::from_slice(&json)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse stored response: {}", e))
+}
+
+/// Store a response as the last one of a saved request, returning its diff
+/// against the one it replaces
+pub(crate) fn record_response(
+    app: &AppHandle,
+    collection_id: &str,
+    request_id: &str,
+    response: HttpResponse,
+) -> Result<Option<ResponseDiff>, String> {
+    let path = stored_response_path(app, collection_id, request_id)?;
+    let diff = read_stored_response(app, collection_id, request_id)?.map(|previous| {
+        let mut diff = diff(&previous.response, &response);
+        diff.previous_saved_at = Some(previous.saved_at);
+        diff
+    });
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+    let stored = StoredResponse {
+        saved_at: chrono::Utc::now().timestamp_millis(),
+        response,
+    };
+    let json =
+        serde_json::to_vec_pretty(&stored).map_err(|e| format!("Failed to serialize: {}", e))?;
+    crate::atomic_write(&path, &json)?;
+
+    Ok(diff)
+}
+
+/// Drop the stored responses of a deleted request, or of a whole collection
+pub(crate) fn forget_responses(app: &AppHandle, collection_id: &str, request_id: Option<&str>) {
+    let removed = match request_id {
+        Some(request_id) => stored_response_path(app, collection_id, request_id)
+            .and_then(|path| std::fs::remove_file(path).map_err(|e| e.to_string())),
+        None => responses_dir(app, collection_id)
+            .and_then(|dir| std::fs::remove_dir_all(dir).map_err(|e| e.to_string())),
+    };
+    if let Err(e) = removed {
+        log::debug!("No stored responses removed for {}: {}", collection_id, e);
+    }
+}
+
+/// Path of an object member, bracket-quoted when it is not a plain identifier
+fn member_path(parent: &str, key: &str) -> String {
+    let plain = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        format!("{}.{}", parent, key)
+    } else {
+        format!(
+            "{}['{}']",
+            parent,
+            key.replace('\\', "\\\\").replace('\'', "\\'")
+        )
+    }
+}
+
+fn diff_json(path: &str, before: &Value, after: &Value, changes: &mut Vec<JsonChange>) {
+    match (before, after) {
+        (Value::Object(a), Value::Object(b)) => {
+            let keys: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+            for key in keys {
+                let path = member_path(path, key);
+                match (a.get(key), b.get(key)) {
+                    (Some(x), Some(y)) => diff_json(&path, x, y, changes),
+                    (Some(x), None) => changes.push(JsonChange {
+                        path,
+                        kind: ChangeKind::Removed,
+                        before: Some(x.clone()),
+                        after: None,
+                    }),
+                    (None, Some(y)) => changes.push(JsonChange {
+                        path,
+                        kind: ChangeKind::Added,
+                        before: None,
+                        after: Some(y.clone()),
+                    }),
+                    (None, None) => {}
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                let path = format!("{}[{}]", path, i);
+                match (a.get(i), b.get(i)) {
+                    (Some(x), Some(y)) => diff_json(&path, x, y, changes),
+                    (Some(x), None) => changes.push(JsonChange {
+                        path,
+                        kind: ChangeKind::Removed,
+                        before: Some(x.clone()),
+                        after: None,
+                    }),
+                    (None, Some(y)) => changes.push(JsonChange {
+                        path,
+                        kind: ChangeKind::Added,
+                        before: None,
+                        after: Some(y.clone()),
+                    }),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ if before != after => changes.push(JsonChange {
+            path: path.to_string(),
+            kind: ChangeKind::Changed,
+            before: Some(before.clone()),
+            after: Some(after.clone()),
+        }),
+        _ => {}
+    }
+}
+
+fn line(kind: LineKind, text: &str) -> DiffLine {
+    DiffLine {
+        kind,
+        text: text.to_string(),
+    }
+}
+
+/// Line diff by longest common subsequence, after trimming the common
+/// prefix and suffix
+fn diff_text(before: &str, after: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = before.lines().collect();
+    let b: Vec<&str> = after.lines().collect();
+
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut lines: Vec<DiffLine> = a[..prefix]
+        .iter()
+        .map(|l| line(LineKind::Same, l))
+        .collect();
+
+    if a_mid.len() * b_mid.len() > MAX_TEXT_DIFF_CELLS {
+        lines.extend(a_mid.iter().map(|l| line(LineKind::Removed, l)));
+        lines.extend(b_mid.iter().map(|l| line(LineKind::Added, l)));
+    } else {
+        // lcs[i][j]: common lines of a_mid[i..] and b_mid[j..]
+        let (n, m) = (a_mid.len(), b_mid.len());
+        let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if a_mid[i] == b_mid[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && a_mid[i] == b_mid[j] {
+                lines.push(line(LineKind::Same, a_mid[i]));
+                i += 1;
+                j += 1;
+            } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+                lines.push(line(LineKind::Removed, a_mid[i]));
+                i += 1;
+            } else {
+                lines.push(line(LineKind::Added, b_mid[j]));
+                j += 1;
+            }
+        }
+    }
+
+    lines.extend(
+        a[a.len() - suffix..]
+            .iter()
+            .map(|l| line(LineKind::Same, l)),
+    );
+    lines
+}
+
+fn diff_headers(before: &HttpResponse, after: &HttpResponse) -> Vec<HeaderChange> {
+    let lower = |response: &HttpResponse| -> Vec<(String, String)> {
+        response
+            .headers
+            .iter()
+            .map(|(name, value)| (name.to_ascii_lowercase(), value.clone()))
+            .filter(|(name, _)| !VOLATILE_HEADERS.contains(&name.as_str()))
+            .collect()
+    };
+    let (a, b) = (lower(before), lower(after));
+    let find = |headers: &[(String, String)], name: &str| {
+        headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+    };
+
+    let names: BTreeSet<&String> = a.iter().chain(&b).map(|(name, _)| name).collect();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let (before, after) = (find(&a, name), find(&b, name));
+            let kind = match (&before, &after) {
+                (Some(x), Some(y)) if x == y => return None,
+                (Some(_), Some(_)) => ChangeKind::Changed,
+                (Some(_), None) => ChangeKind::Removed,
+                (None, _) => ChangeKind::Added,
+            };
+            Some(HeaderChange {
+                name: name.clone(),
+                kind,
+                before,
+                after,
+            })
+        })
+        .collect()
+}
+
+fn diff_body(before: &HttpResponse, after: &HttpResponse) -> BodyDiff {
+    if before.is_binary || after.is_binary {
+        let changed = before.is_binary != after.is_binary
+            || before.size_bytes != after.size_bytes
+            || before.body != after.body;
+        return BodyDiff::Binary { changed };
+    }
+
+    let parse = |body: &str| serde_json::from_str::<Value>(body).ok();
+    match (parse(&before.body), parse(&after.body)) {
+        (Some(a), Some(b)) => {
+            let mut changes = Vec::new();
+            diff_json("$", &a, &b, &mut changes);
+            BodyDiff::Json { changes }
+        }
+        _ => BodyDiff::Text {
+            lines: diff_text(&before.body, &after.body),
+        },
+    }
+}
+
+/// Compare two responses
+pub(crate) fn diff(before: &HttpResponse, after: &HttpResponse) -> ResponseDiff {
+    let status = (before.status != after.status).then_some((before.status, after.status));
+    let headers = diff_headers(before, after);
+    let body = diff_body(before, after);
+
+    let body_changed = match &body {
+        BodyDiff::Json { changes } => !changes.is_empty(),
+        BodyDiff::Text { lines } => lines.iter().any(|l| l.kind != LineKind::Same),
+        BodyDiff::Binary { changed } => *changed,
+    };
+    ResponseDiff {
+        identical: status.is_none() && headers.is_empty() && !body_changed,
+        status,
+        headers,
+        body,
+        previous_saved_at: None,
+    }
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Compare two responses: status, headers (except volatile ones like Date)
+/// and body, structurally when both bodies are JSON
+#[tauri::command]
+pub fn diff_responses(previous: HttpResponse, current: HttpResponse) -> ResponseDiff {
+    diff(&previous, &current)
+}
+
+/// The last response stored for a saved request
+#[tauri::command]
+pub async fn get_previous_response(
+    app: AppHandle,
+    collection_id: String,
+    request_id: String,
+) -> Result<Option<StoredResponse>, String> {
+    read_stored_response(&app, &collection_id, &request_id)
+}
+
+/// Store a saved request's response and diff it against the previous one;
+/// None on the first run
+#[tauri::command]
+pub async fn record_saved_response(
+    app: AppHandle,
+    collection_id: String,
+    request_id: String,
+    response: HttpResponse,
+) -> Result<Option<ResponseDiff>, String> {
+    record_response(&app, &collection_id, &request_id, response)
+}
@@ -0,0 +1,266 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State, Window};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::oneshot;
+
+use super::capture::execute_with_redirects;
+use super::{
+    auth, cookies, prepare_request, response_headers, save_cookies, status_text, HttpRequest,
+};
+
+// ============================================================================
+// DOWNLOAD TYPES
+// ============================================================================
+
+/// Event emitted to the window while a response body is written to disk
+pub const DOWNLOAD_PROGRESS_EVENT: &str = "http://download-progress";
+
+/// Least time between two progress events of one download
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DownloadProgress {
+    pub download_id: String,
+    pub received_bytes: u64,
+    /// From Content-Length, when the server sent one
+    pub total_bytes: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DownloadResult {
+    pub status: u16,
+    pub status_text: String,
+    pub headers: HashMap<String, String>,
+    pub path: String,
+    pub size_bytes: u64,
+    pub time_ms: u64,
+    /// The download was cancelled; nothing was written to `path`
+    pub cancelled: bool,
+}
+
+struct RunningDownload {
+    window_label: String,
+    cancel: oneshot::Sender<()>,
+}
+
+/// Downloads in progress, keyed by the id the frontend chose
+#[derive(Default)]
+pub struct DownloadRegistry {
+    running: Mutex<HashMap<String, RunningDownload>>,
+}
+
+impl DownloadRegistry {
+    /// Cancel every download started by a window (e.g. when it is closed)
+    pub fn close_window(&self, label: &str) {
+        let Ok(mut running) = self.running.lock() else {
+            return;
+        };
+
+        let ids: Vec<String> = running
+            .iter()
+            .filter(|(_, download)| download.window_label == label)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in ids {
+            if let Some(download) = running.remove(&id) {
+                let _ = download.cancel.send(());
+            }
+        }
+    }
+
+    fn remove(&self, download_id: &str) {
+        if let Ok(mut running) = self.running.lock() {
+            running.remove(download_id);
+        }
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Stream the body into `temp_path`; Ok(false) when cancelled
+async fn write_body(
+    window: &Window,
+    download_id: &str,
+    response: &mut reqwest::Response,
+    temp_path: &Path,
+    cancel: &mut oneshot::Receiver<()>,
+) -> Result<(bool, u64), String> {
+    let mut file = tokio::fs::File::create(temp_path)
+        .await
+        .map_err(|e| format!("Failed to create file: {}", e))?;
+
+    let total_bytes = response.content_length();
+    let mut received_bytes = 0u64;
+    let mut last_progress = Instant::now();
+
+    loop {
+        let chunk = tokio::select! {
+            _ = &mut *cancel => return Ok((false, received_bytes)),
+            chunk = response.chunk() => chunk,
+        };
+        let Some(chunk) = chunk.map_err(|e| format!("Failed to read response body: {}", e))? else {
+            break;
+        };
+
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("Failed to write file: {}", e))?;
+        received_bytes += chunk.len() as u64;
+
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            let _ = window.emit_to(
+                window.label(),
+                DOWNLOAD_PROGRESS_EVENT,
+                DownloadProgress {
+                    download_id: download_id.to_string(),
+                    received_bytes,
+                    total_bytes,
+                },
+            );
+        }
+    }
+
+    file.sync_all()
+        .await
+        .map_err(|e| format!("Failed to write file: {}", e))?;
+
+    let _ = window.emit_to(
+        window.label(),
+        DOWNLOAD_PROGRESS_EVENT,
+        DownloadProgress {
+            download_id: download_id.to_string(),
+            received_bytes,
+            total_bytes: total_bytes.or(Some(received_bytes)),
+        },
+    );
+
+    Ok((true, received_bytes))
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Send a request and stream the whole response body into a file.
+///
+/// `download_id` is chosen by the caller so the download can be cancelled
+/// with cancel_http_download while this command is still running. The file is
+/// only replaced once the body has been fully received.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn save_response_to_file(
+    app: AppHandle,
+    window: Window,
+    jar: State<'_, cookies::CookieJar>,
+    tokens: State<'_, auth::OAuth2Tokens>,
+    registry: State<'_, DownloadRegistry>,
+    mut request: HttpRequest,
+    path: String,
+    download_id: String,
+) -> Result<DownloadResult, String> {
+    let target = PathBuf::from(&path);
+    let dir = target
+        .parent()
+        .filter(|dir| dir.is_dir())
+        .ok_or_else(|| format!("Directory does not exist: {}", path))?;
+    let file_name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| "Path has no file name".to_string())?;
+    let temp_path = dir.join(format!(".{}.{}.part", file_name, uuid::Uuid::new_v4()));
+
+    let (cancel_tx, mut cancel) = oneshot::channel();
+    {
+        let mut running = registry
+            .running
+            .lock()
+            .map_err(|_| "Download registry is poisoned".to_string())?;
+        if running.contains_key(&download_id) {
+            return Err(format!("Download already running: {}", download_id));
+        }
+        running.insert(
+            download_id.clone(),
+            RunningDownload {
+                window_label: window.label().to_string(),
+                cancel: cancel_tx,
+            },
+        );
+    }
+
+    let result = async {
+        let (client, built) = prepare_request(&app, &jar, &tokens, &mut request, true).await?;
+        let start = Instant::now();
+
+        let sent = execute_with_redirects(&client, built, request.redirect_limit());
+        let mut response = tokio::select! {
+            _ = &mut cancel => None,
+            response = sent => Some(response?.0),
+        };
+        let Some(response) = response.as_mut() else {
+            return Ok(DownloadResult {
+                status: 0,
+                status_text: String::new(),
+                headers: HashMap::new(),
+                path: path.clone(),
+                size_bytes: 0,
+                time_ms: start.elapsed().as_millis() as u64,
+                cancelled: true,
+            });
+        };
+        save_cookies(&app, &jar, &request);
+
+        let written = write_body(&window, &download_id, response, &temp_path, &mut cancel).await;
+        let (completed, size_bytes) = match written {
+            Ok(written) => written,
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&temp_path).await;
+                return Err(e);
+            }
+        };
+
+        if completed {
+            tokio::fs::rename(&temp_path, &target)
+                .await
+                .map_err(|e| format!("Failed to save file: {}", e))?;
+        } else {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+        }
+
+        Ok(DownloadResult {
+            status: response.status().as_u16(),
+            status_text: status_text(response.status()),
+            headers: response_headers(response),
+            path: path.clone(),
+            size_bytes,
+            time_ms: start.elapsed().as_millis() as u64,
+            cancelled: !completed,
+        })
+    }
+    .await;
+
+    registry.remove(&download_id);
+    result
+}
+
+#[tauri::command]
+pub async fn cancel_http_download(
+    registry: State<'_, DownloadRegistry>,
+    download_id: String,
+) -> Result<(), String> {
+    let download = registry
+        .running
+        .lock()
+        .map_err(|_| "Download registry is poisoned".to_string())?
+        .remove(&download_id)
+        .ok_or_else(|| format!("No running download: {}", download_id))?;
+
+    let _ = download.cancel.send(());
+    Ok(())
+}
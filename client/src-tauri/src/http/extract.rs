@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::AppHandle;
+
+use super::collections::store_active_variables;
+use super::{jsonpath, HttpResponse};
+
+// ============================================================================
+// EXTRACTION TYPES
+// ============================================================================
+
+/// Where in the response a value is taken from
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExtractionSource {
+    /// First value the path selects in the JSON body
+    JsonPath { path: String },
+    /// Header value (case-insensitive name)
+    Header { name: String },
+    /// Capture group of the first match in the body; group 1 by default, or
+    /// the whole match when the pattern has no groups
+    Regex {
+        pattern: String,
+        group: Option<usize>,
+    },
+}
+
+/// Store a value from the response in an environment variable
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Extraction {
+    pub variable: String,
+    pub source: ExtractionSource,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ExtractionResult {
+    pub variable: String,
+    /// None when nothing was found; the variable is left unchanged
+    pub value: Option<String>,
+    pub error: Option<String>,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn extract_one(response: &HttpResponse, source: &ExtractionSource) -> Result<String, String> {
+    match source {
+        ExtractionSource::JsonPath { path } => {
+            let json: serde_json::Value = serde_json::from_str(&response.body)
+                .map_err(|e| format!("Body is not JSON: {}", e))?;
+            jsonpath::query_first(&json, path)?
+                .map(jsonpath::value_to_string)
+                .ok_or_else(|| format!("Nothing at {}", path))
+        }
+        ExtractionSource::Header { name } => response
+            .headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+            .ok_or_else(|| format!("No {} header", name)),
+        ExtractionSource::Regex { pattern, group } => {
+            let re = regex::Regex::new(pattern).map_err(|e| format!("Invalid regex: {}", e))?;
+            let captures = re
+                .captures(&response.body)
+                .ok_or_else(|| format!("No match for {}", pattern))?;
+            let group = group.unwrap_or(if captures.len() > 1 { 1 } else { 0 });
+            captures
+                .get(group)
+                .map(|m| m.as_str().to_string())
+                .ok_or_else(|| format!("Group {} did not match", group))
+        }
+    }
+}
+
+/// Run extractions against a response
+pub(crate) fn extract(
+    response: &HttpResponse,
+    extractions: &[Extraction],
+) -> Vec<ExtractionResult> {
+    extractions
+        .iter()
+        .filter(|extraction| !extraction.variable.is_empty())
+        .map(|extraction| {
+            let (value, error) = match extract_one(response, &extraction.source) {
+                Ok(value) => (Some(value), None),
+                Err(e) => (None, Some(e)),
+            };
+            ExtractionResult {
+                variable: extraction.variable.clone(),
+                value,
+                error,
+            }
+        })
+        .collect()
+}
+
+/// The values that were found, by variable
+pub(crate) fn found_values(results: &[ExtractionResult]) -> HashMap<String, String> {
+    results
+        .iter()
+        .filter_map(|r| Some((r.variable.clone(), r.value.clone()?)))
+        .collect()
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Run a request's extractions against a response and store the values found
+/// in the active environment
+#[tauri::command]
+pub async fn apply_extractions(
+    app: AppHandle,
+    response: HttpResponse,
+    extractions: Vec<Extraction>,
+) -> Result<Vec<ExtractionResult>, String> {
+    let results = extract(&response, &extractions);
+    let values = found_values(&results);
+
+    if !values.is_empty() && !store_active_variables(&app, &values)? {
+        return Err("No active environment to store extracted variables in".to_string());
+    }
+    Ok(results)
+}
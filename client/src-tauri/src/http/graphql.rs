@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, State};
+
+use super::collections::substitute_variables;
+use super::{auth, cookies, execute_request, http_data_dir, HttpHeader, HttpRequest};
+
+// ============================================================================
+// GRAPHQL TYPES
+// ============================================================================
+
+/// Body of a GraphQL request, sent instead of `body`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GraphqlBody {
+    pub query: String,
+    pub variables: Option<serde_json::Value>,
+    pub operation_name: Option<String>,
+}
+
+impl GraphqlBody {
+    /// Replace `{{variables}}` in the query and in every string of the variables
+    pub(crate) fn substitute(&mut self, variables: &HashMap<String, String>) {
+        self.query = substitute_variables(&self.query, variables);
+        if let Some(json) = &mut self.variables {
+            substitute_json(json, variables);
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GraphqlLocation {
+    pub line: u32,
+    pub column: u32,
+}
+
+/// An entry of the response's `errors` list
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GraphqlError {
+    pub message: String,
+    #[serde(default)]
+    pub locations: Vec<GraphqlLocation>,
+    /// Field names and list indices leading to the failed field
+    #[serde(default)]
+    pub path: Vec<serde_json::Value>,
+    pub extensions: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphqlSchema {
+    pub url: String,
+    /// When the schema was fetched (milliseconds since epoch)
+    pub fetched_at: i64,
+    /// The `__schema` object of the introspection result
+    pub schema: serde_json::Value,
+}
+
+const INTROSPECTION_QUERY: &str = r#"
+query IntrospectionQuery {
+  __schema {
+    queryType { name }
+    mutationType { name }
+    subscriptionType { name }
+    types { ...FullType }
+    directives {
+      name
+      description
+      locations
+      args { ...InputValue }
+    }
+  }
+}
+
+fragment FullType on __Type {
+  kind
+  name
+  description
+  fields(includeDeprecated: true) {
+    name
+    description
+    args { ...InputValue }
+    type { ...TypeRef }
+    isDeprecated
+    deprecationReason
+  }
+  inputFields { ...InputValue }
+  interfaces { ...TypeRef }
+  enumValues(includeDeprecated: true) {
+    name
+    description
+    isDeprecated
+    deprecationReason
+  }
+  possibleTypes { ...TypeRef }
+}
+
+fragment InputValue on __InputValue {
+  name
+  description
+  type { ...TypeRef }
+  defaultValue
+}
+
+fragment TypeRef on __Type {
+  kind
+  name
+  ofType {
+    kind
+    name
+    ofType {
+      kind
+      name
+      ofType {
+        kind
+        name
+        ofType {
+          kind
+          name
+          ofType {
+            kind
+            name
+            ofType {
+              kind
+              name
+              ofType { kind name }
+            }
+          }
+        }
+      }
+    }
+  }
+}
+"#;
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn has_header(request: &HttpRequest, name: &str) -> bool {
+    request
+        .headers
+        .iter()
+        .any(|h| h.enabled && h.key.eq_ignore_ascii_case(name))
+}
+
+fn set_default_header(request: &mut HttpRequest, name: &str, value: &str) {
+    if !has_header(request, name) {
+        request.headers.push(HttpHeader {
+            key: name.to_string(),
+            value: value.to_string(),
+            enabled: true,
+        });
+    }
+}
+
+/// Substitute environment variables in every string of a JSON value
+fn substitute_json(value: &mut serde_json::Value, variables: &HashMap<String, String>) {
+    match value {
+        serde_json::Value::String(s) => *s = substitute_variables(s, variables),
+        serde_json::Value::Array(items) => {
+            items
+                .iter_mut()
+                .for_each(|item| substitute_json(item, variables));
+        }
+        serde_json::Value::Object(map) => {
+            map.values_mut()
+                .for_each(|item| substitute_json(item, variables));
+        }
+        _ => {}
+    }
+}
+
+/// Turn the GraphQL body into a regular request.
+///
+/// GET requests carry the operation in the query string, everything else
+/// posts it as JSON.
+pub(crate) fn encode_request(request: &mut HttpRequest) -> Result<(), String> {
+    let Some(graphql) = request.graphql.clone() else {
+        return Ok(());
+    };
+
+    if request.method.eq_ignore_ascii_case("GET") {
+        let mut url = url::Url::parse(&request.url).map_err(|e| format!("Invalid URL: {}", e))?;
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("query", &graphql.query);
+            if let Some(variables) = &graphql.variables {
+                query.append_pair("variables", &variables.to_string());
+            }
+            if let Some(operation_name) = &graphql.operation_name {
+                query.append_pair("operationName", operation_name);
+            }
+        }
+        request.url = url.to_string();
+        request.body = None;
+    } else {
+        let body = serde_json::json!({
+            "query": graphql.query,
+            "variables": graphql.variables,
+            "operationName": graphql.operation_name,
+        });
+        request.body = Some(body.to_string());
+        set_default_header(request, "Content-Type", "application/json");
+    }
+
+    set_default_header(
+        request,
+        "Accept",
+        "application/graphql-response+json, application/json",
+    );
+    Ok(())
+}
+
+/// The `errors` list of a GraphQL response, if it has one
+pub(crate) fn parse_errors(body: &str) -> Option<Vec<GraphqlError>> {
+    #[derive(Deserialize)]
+    struct Envelope {
+        errors: Option<Vec<GraphqlError>>,
+    }
+
+    serde_json::from_str::<Envelope>(body)
+        .ok()?
+        .errors
+        .filter(|errors| !errors.is_empty())
+}
+
+fn schema_cache_path(app: &AppHandle, url: &str) -> Result<std::path::PathBuf, String> {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    url.hash(&mut hasher);
+    Ok(http_data_dir(app)?
+        .join("graphql-schemas")
+        .join(format!("{:016x}.json", hasher.finish())))
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Fetch the schema of a GraphQL endpoint, or return the cached one.
+///
+/// `request` supplies the URL, headers and auth; its body is replaced by the
+/// introspection query. Pass `refresh` to ignore the cache.
+#[tauri::command]
+pub async fn run_introspection(
+    app: AppHandle,
+    jar: State<'_, cookies::CookieJar>,
+    tokens: State<'_, auth::OAuth2Tokens>,
+    mut request: HttpRequest,
+    refresh: Option<bool>,
+) -> Result<GraphqlSchema, String> {
+    let cache_path = schema_cache_path(&app, &request.url)?;
+    if !refresh.unwrap_or(false) {
+        if let Some(cached) = std::fs::read(&cache_path)
+            .ok()
+            .and_then(|json| serde_json::from_slice::<GraphqlSchema>(&json).ok())
+        {
+            return Ok(cached);
+        }
+    }
+
+    let url = request.url.clone();
+    request.method = "POST".to_string();
+    request.graphql = Some(GraphqlBody {
+        query: INTROSPECTION_QUERY.to_string(),
+        variables: None,
+        operation_name: Some("IntrospectionQuery".to_string()),
+    });
+
+    let response = execute_request(&app, &jar, &tokens, request).await?;
+    if let Some(errors) = &response.graphql_errors {
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        return Err(format!("Introspection failed: {}", messages.join("; ")));
+    }
+    if !(200..300).contains(&response.status) {
+        return Err(format!(
+            "Introspection failed: {} {}",
+            response.status, response.status_text
+        ));
+    }
+
+    let mut result: serde_json::Value = serde_json::from_str(&response.body)
+        .map_err(|e| format!("Invalid introspection response: {}", e))?;
+    let schema = result
+        .pointer_mut("/data/__schema")
+        .map(serde_json::Value::take)
+        .ok_or("Introspection response has no schema")?;
+
+    let schema = GraphqlSchema {
+        url,
+        fetched_at: chrono::Utc::now().timestamp_millis(),
+        schema,
+    };
+
+    if let Some(parent) = cache_path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+    let json = serde_json::to_vec(&schema).map_err(|e| format!("Failed to serialize: {}", e))?;
+    crate::atomic_write(&cache_path, &json)?;
+
+    Ok(schema)
+}
@@ -0,0 +1,522 @@
+use base64::Engine;
+use prost::Message;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, MethodDescriptor};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use tonic::metadata::{MetadataKey, MetadataMap, MetadataValue};
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+
+use super::collections::{load_environments, substitute_variables};
+use super::HttpHeader;
+
+// ============================================================================
+// GRPC TYPES
+// ============================================================================
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Deadline of a call that does not set one
+const DEFAULT_DEADLINE_MS: u64 = 30000;
+
+/// Where service descriptions come from
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GrpcSchemaSource {
+    /// Ask the server through gRPC server reflection
+    Reflection,
+    /// Compile .proto files with `protoc` (must be on PATH); include_dirs
+    /// defaults to the folders of the files
+    ProtoFiles {
+        paths: Vec<String>,
+        #[serde(default)]
+        include_dirs: Vec<String>,
+    },
+    /// A descriptor set written by `protoc --include_imports --descriptor_set_out`
+    DescriptorSet { path: String },
+}
+
+#[derive(Debug, Serialize)]
+pub struct GrpcMethod {
+    pub name: String,
+    pub input_type: String,
+    pub output_type: String,
+    pub client_streaming: bool,
+    pub server_streaming: bool,
+    /// The input message with every field at its default, as a starting point
+    pub input_template: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GrpcService {
+    pub name: String,
+    pub methods: Vec<GrpcMethod>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GrpcRequest {
+    /// Server address, e.g. `http://localhost:50051`; https uses TLS
+    pub target: String,
+    /// Fully qualified service name, e.g. `helloworld.Greeter`
+    pub service: String,
+    pub method: String,
+    /// The request message as protobuf JSON
+    pub body: String,
+    /// Sent as metadata; keys ending in `-bin` take base64 values
+    #[serde(default)]
+    pub metadata: Vec<HttpHeader>,
+    pub deadline_ms: Option<u64>,
+    pub source: GrpcSchemaSource,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GrpcResponse {
+    /// gRPC status code, 0 for OK
+    pub code: i32,
+    /// e.g. "Ok", "NotFound"
+    pub code_name: String,
+    /// Status message of a failed call
+    pub message: Option<String>,
+    /// The response message as pretty-printed protobuf JSON; None when the call failed
+    pub body: Option<String>,
+    /// Response headers and trailers; binary values are base64
+    pub metadata: HashMap<String, String>,
+    pub time_ms: u64,
+}
+
+/// Encodes and decodes messages described at runtime
+struct DynamicCodec {
+    output: MessageDescriptor,
+}
+
+struct DynamicEncoder;
+
+struct DynamicDecoder(MessageDescriptor);
+
+impl Codec for DynamicCodec {
+    type Encode = DynamicMessage;
+    type Decode = DynamicMessage;
+    type Encoder = DynamicEncoder;
+    type Decoder = DynamicDecoder;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        DynamicEncoder
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        DynamicDecoder(self.output.clone())
+    }
+}
+
+impl Encoder for DynamicEncoder {
+    type Item = DynamicMessage;
+    type Error = tonic::Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        item.encode(dst)
+            .map_err(|e| tonic::Status::internal(format!("Failed to encode request: {}", e)))
+    }
+}
+
+impl Decoder for DynamicDecoder {
+    type Item = DynamicMessage;
+    type Error = tonic::Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        DynamicMessage::decode(self.0.clone(), src)
+            .map(Some)
+            .map_err(|e| tonic::Status::internal(format!("Failed to decode response: {}", e)))
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+async fn connect(target: &str) -> Result<Channel, String> {
+    let mut endpoint = Endpoint::from_shared(target.to_string())
+        .map_err(|e| format!("Invalid target {}: {}", target, e))?
+        .connect_timeout(CONNECT_TIMEOUT);
+    if target.starts_with("https://") {
+        endpoint = endpoint
+            .tls_config(ClientTlsConfig::new().with_webpki_roots())
+            .map_err(|e| format!("Failed to configure TLS: {}", e))?;
+    }
+
+    endpoint
+        .connect()
+        .await
+        .map_err(|e| format!("Failed to connect to {}: {}", target, e))
+}
+
+fn metadata_map(metadata: &[HttpHeader]) -> Result<MetadataMap, String> {
+    let mut map = MetadataMap::new();
+
+    for entry in metadata {
+        if !entry.enabled || entry.key.is_empty() {
+            continue;
+        }
+        let key = entry.key.to_lowercase();
+        if key.ends_with("-bin") {
+            let value = base64::engine::general_purpose::STANDARD
+                .decode(&entry.value)
+                .map_err(|e| format!("Invalid base64 for metadata {}: {}", entry.key, e))?;
+            let key = MetadataKey::from_bytes(key.as_bytes())
+                .map_err(|e| format!("Invalid metadata key {}: {}", entry.key, e))?;
+            map.append_bin(key, MetadataValue::from_bytes(&value));
+        } else {
+            let key = MetadataKey::from_bytes(key.as_bytes())
+                .map_err(|e| format!("Invalid metadata key {}: {}", entry.key, e))?;
+            let value = entry
+                .value
+                .parse()
+                .map_err(|e| format!("Invalid value for metadata {}: {}", entry.key, e))?;
+            map.append(key, value);
+        }
+    }
+
+    Ok(map)
+}
+
+fn metadata_to_map(metadata: &MetadataMap) -> HashMap<String, String> {
+    metadata
+        .iter()
+        .map(|entry| match entry {
+            tonic::metadata::KeyAndValueRef::Ascii(key, value) => (
+                key.to_string(),
+                value.to_str().unwrap_or_default().to_string(),
+            ),
+            tonic::metadata::KeyAndValueRef::Binary(key, value) => (
+                key.to_string(),
+                value
+                    .to_bytes()
+                    .map(|bytes| base64::engine::general_purpose::STANDARD.encode(bytes))
+                    .unwrap_or_default(),
+            ),
+        })
+        .collect()
+}
+
+/// Server reflection for one protocol version: the v1 and v1alpha types are
+/// identical but distinct, and servers may implement either
+macro_rules! reflection {
+    ($module:ident, $pb:path) => {
+        mod $module {
+            use super::*;
+            use pb::server_reflection_client::ServerReflectionClient;
+            use pb::server_reflection_request::MessageRequest;
+            use pb::server_reflection_response::MessageResponse;
+            use $pb as pb;
+
+            async fn ask(
+                client: &mut ServerReflectionClient<Channel>,
+                metadata: &MetadataMap,
+                request: MessageRequest,
+            ) -> Result<MessageResponse, tonic::Status> {
+                let mut call = tonic::Request::new(futures_util::stream::iter([
+                    pb::ServerReflectionRequest {
+                        host: String::new(),
+                        message_request: Some(request),
+                    },
+                ]));
+                *call.metadata_mut() = metadata.clone();
+
+                let response = client
+                    .server_reflection_info(call)
+                    .await?
+                    .into_inner()
+                    .message()
+                    .await?
+                    .and_then(|r| r.message_response)
+                    .ok_or_else(|| tonic::Status::internal("Empty reflection response"))?;
+                match response {
+                    MessageResponse::ErrorResponse(e) => {
+                        Err(tonic::Status::new(e.error_code.into(), e.error_message))
+                    }
+                    other => Ok(other),
+                }
+            }
+
+            /// Files describing the server's services, and the files those import
+            pub(super) async fn files(
+                channel: Channel,
+                metadata: &MetadataMap,
+            ) -> Result<Vec<prost_types::FileDescriptorProto>, tonic::Status> {
+                let mut client = ServerReflectionClient::new(channel);
+
+                let services = match ask(
+                    &mut client,
+                    metadata,
+                    MessageRequest::ListServices(String::new()),
+                )
+                .await?
+                {
+                    MessageResponse::ListServicesResponse(list) => list.service,
+                    _ => return Err(tonic::Status::internal("Unexpected reflection response")),
+                };
+
+                let mut files: HashMap<String, prost_types::FileDescriptorProto> = HashMap::new();
+                let mut pending: Vec<MessageRequest> = services
+                    .into_iter()
+                    .map(|s| MessageRequest::FileContainingSymbol(s.name))
+                    .collect();
+                while let Some(request) = pending.pop() {
+                    let MessageResponse::FileDescriptorResponse(response) =
+                        ask(&mut client, metadata, request).await?
+                    else {
+                        return Err(tonic::Status::internal("Unexpected reflection response"));
+                    };
+                    for bytes in response.file_descriptor_proto {
+                        let file = prost_types::FileDescriptorProto::decode(bytes.as_slice())
+                            .map_err(|e| tonic::Status::internal(e.to_string()))?;
+                        files.insert(file.name().to_string(), file);
+                    }
+
+                    // Some servers only send the file itself: fetch missing imports by name
+                    let missing: HashSet<String> = files
+                        .values()
+                        .flat_map(|f| f.dependency.iter())
+                        .filter(|d| !files.contains_key(*d) && !d.starts_with("google/protobuf/"))
+                        .cloned()
+                        .collect();
+                    pending.extend(missing.into_iter().map(MessageRequest::FileByFilename));
+                }
+
+                Ok(files.into_values().collect())
+            }
+        }
+    };
+}
+
+reflection!(reflection_v1, tonic_reflection::pb::v1);
+reflection!(reflection_v1alpha, tonic_reflection::pb::v1alpha);
+
+/// Compile .proto files into a descriptor set with protoc
+fn compile_protos(paths: &[String], include_dirs: &[String]) -> Result<Vec<u8>, String> {
+    let out = std::env::temp_dir().join(format!("grpc-{}.protoset", uuid::Uuid::new_v4()));
+
+    let mut includes: Vec<PathBuf> = include_dirs.iter().map(PathBuf::from).collect();
+    if includes.is_empty() {
+        includes = paths
+            .iter()
+            .filter_map(|p| Path::new(p).parent().map(Path::to_path_buf))
+            .collect();
+        includes.dedup();
+    }
+
+    let mut command = std::process::Command::new("protoc");
+    command.arg("--include_imports");
+    command.arg(format!("--descriptor_set_out={}", out.display()));
+    for dir in &includes {
+        command.arg(format!("--proto_path={}", dir.display()));
+    }
+    command.args(paths);
+
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run protoc (is it installed?): {}", e))?;
+    let result = if output.status.success() {
+        std::fs::read(&out).map_err(|e| format!("Failed to read descriptor set: {}", e))
+    } else {
+        Err(format!(
+            "protoc failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    };
+    let _ = std::fs::remove_file(&out);
+    result
+}
+
+/// Build the descriptor pool for a target from the chosen source
+async fn load_pool(
+    target: &str,
+    source: &GrpcSchemaSource,
+    metadata: &MetadataMap,
+) -> Result<DescriptorPool, String> {
+    let mut pool = DescriptorPool::global();
+
+    match source {
+        GrpcSchemaSource::Reflection => {
+            let channel = connect(target).await?;
+            let files = match reflection_v1::files(channel.clone(), metadata).await {
+                Err(status) if status.code() == tonic::Code::Unimplemented => {
+                    reflection_v1alpha::files(channel, metadata).await
+                }
+                result => result,
+            }
+            .map_err(|e| format!("Server reflection failed: {}", e.message()))?;
+            pool.add_file_descriptor_protos(files)
+                .map_err(|e| format!("Invalid descriptors from server: {}", e))?;
+        }
+        GrpcSchemaSource::ProtoFiles {
+            paths,
+            include_dirs,
+        } => {
+            let bytes = compile_protos(paths, include_dirs)?;
+            pool.decode_file_descriptor_set(bytes.as_slice())
+                .map_err(|e| format!("Invalid descriptor set: {}", e))?;
+        }
+        GrpcSchemaSource::DescriptorSet { path } => {
+            let bytes =
+                std::fs::read(path).map_err(|e| format!("Failed to read descriptor set: {}", e))?;
+            pool.decode_file_descriptor_set(bytes.as_slice())
+                .map_err(|e| format!("Invalid descriptor set: {}", e))?;
+        }
+    }
+
+    Ok(pool)
+}
+
+/// Protobuf JSON with every field shown, including defaults
+fn to_pretty_json(message: &DynamicMessage) -> Result<String, String> {
+    let mut out = Vec::new();
+    let mut serializer = serde_json::Serializer::pretty(&mut out);
+    message
+        .serialize_with_options(
+            &mut serializer,
+            &prost_reflect::SerializeOptions::new().skip_default_fields(false),
+        )
+        .map_err(|e| format!("Failed to serialize message: {}", e))?;
+    String::from_utf8(out).map_err(|e| format!("Failed to serialize message: {}", e))
+}
+
+fn find_method(
+    pool: &DescriptorPool,
+    service: &str,
+    method: &str,
+) -> Result<MethodDescriptor, String> {
+    pool.get_service_by_name(service)
+        .ok_or_else(|| format!("Unknown service: {}", service))?
+        .methods()
+        .find(|m| m.name() == method)
+        .ok_or_else(|| format!("Unknown method: {}/{}", service, method))
+}
+
+/// Substitute the active environment into the parts of a request the user types
+fn apply_environment(app: &AppHandle, request: &mut GrpcRequest) -> Result<(), String> {
+    let variables = load_environments(app)?.active_variables();
+    if variables.is_empty() {
+        return Ok(());
+    }
+
+    request.target = substitute_variables(&request.target, &variables);
+    request.body = substitute_variables(&request.body, &variables);
+    for entry in &mut request.metadata {
+        entry.key = substitute_variables(&entry.key, &variables);
+        entry.value = substitute_variables(&entry.value, &variables);
+    }
+    Ok(())
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// List the services and methods a target offers
+#[tauri::command]
+pub async fn list_grpc_services(
+    app: AppHandle,
+    target: String,
+    source: GrpcSchemaSource,
+    metadata: Option<Vec<HttpHeader>>,
+) -> Result<Vec<GrpcService>, String> {
+    let variables = load_environments(&app)?.active_variables();
+    let target = substitute_variables(&target, &variables);
+    let metadata = metadata_map(&metadata.unwrap_or_default())?;
+    let pool = load_pool(&target, &source, &metadata).await?;
+
+    let mut services: Vec<GrpcService> = pool
+        .services()
+        .filter(|s| !s.full_name().starts_with("grpc.reflection."))
+        .map(|service| GrpcService {
+            name: service.full_name().to_string(),
+            methods: service
+                .methods()
+                .map(|method| GrpcMethod {
+                    name: method.name().to_string(),
+                    input_type: method.input().full_name().to_string(),
+                    output_type: method.output().full_name().to_string(),
+                    client_streaming: method.is_client_streaming(),
+                    server_streaming: method.is_server_streaming(),
+                    input_template: to_pretty_json(&DynamicMessage::new(method.input()))
+                        .unwrap_or_default(),
+                })
+                .collect(),
+        })
+        .collect();
+    services.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(services)
+}
+
+/// Make a unary gRPC call with a JSON request body.
+///
+/// A call the server rejects is not an error: its status comes back in the
+/// response. Errors are for requests that could not be made at all.
+#[tauri::command]
+pub async fn send_grpc_request(
+    app: AppHandle,
+    mut request: GrpcRequest,
+) -> Result<GrpcResponse, String> {
+    apply_environment(&app, &mut request)?;
+    let metadata = metadata_map(&request.metadata)?;
+
+    let pool = load_pool(&request.target, &request.source, &metadata).await?;
+    let method = find_method(&pool, &request.service, &request.method)?;
+    if method.is_client_streaming() || method.is_server_streaming() {
+        return Err(format!(
+            "{}/{} is a streaming method; only unary calls are supported",
+            request.service, request.method
+        ));
+    }
+
+    let mut deserializer = serde_json::Deserializer::from_str(&request.body);
+    let message = DynamicMessage::deserialize(method.input(), &mut deserializer)
+        .map_err(|e| format!("Invalid request body: {}", e))?;
+
+    let path = format!("/{}/{}", request.service, request.method)
+        .parse()
+        .map_err(|e| format!("Invalid method path: {}", e))?;
+    let deadline = Duration::from_millis(request.deadline_ms.unwrap_or(DEFAULT_DEADLINE_MS));
+
+    let mut call = tonic::Request::new(message);
+    *call.metadata_mut() = metadata;
+    call.set_timeout(deadline);
+
+    let channel = connect(&request.target).await?;
+    let mut grpc = tonic::client::Grpc::new(channel);
+    grpc.ready()
+        .await
+        .map_err(|e| format!("Connection not ready: {}", e))?;
+
+    let start = Instant::now();
+    let codec = DynamicCodec {
+        output: method.output(),
+    };
+    let result = tokio::time::timeout(deadline, grpc.unary(call, path, codec))
+        .await
+        .unwrap_or_else(|_| Err(tonic::Status::deadline_exceeded("Deadline exceeded")));
+    let time_ms = start.elapsed().as_millis() as u64;
+
+    Ok(match result {
+        Ok(response) => GrpcResponse {
+            code: tonic::Code::Ok as i32,
+            code_name: format!("{:?}", tonic::Code::Ok),
+            message: None,
+            body: Some(to_pretty_json(response.get_ref())?),
+            metadata: metadata_to_map(response.metadata()),
+            time_ms,
+        },
+        Err(status) => GrpcResponse {
+            code: status.code() as i32,
+            code_name: format!("{:?}", status.code()),
+            message: Some(status.message().to_string()),
+            body: None,
+            metadata: metadata_to_map(status.metadata()),
+            time_ms,
+        },
+    })
+}
@@ -0,0 +1,106 @@
+//! The subset of JSONPath used by assertions and variable extraction:
+//! `$`, `.name`, `['name']`, `[index]` (negative counts from the end) and
+//! the `*` / `[*]` wildcard.
+
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(i64),
+    Wildcard,
+}
+
+fn parse(path: &str) -> Result<Vec<Segment>, String> {
+    let invalid = |reason: &str| format!("Invalid JSONPath {}: {}", path, reason);
+
+    let mut rest = path
+        .trim()
+        .strip_prefix('$')
+        .ok_or_else(|| invalid("must start with $"))?;
+    let mut segments = Vec::new();
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            let name = &after[..end];
+            if name.is_empty() {
+                return Err(invalid("empty name after ."));
+            }
+            segments.push(if name == "*" {
+                Segment::Wildcard
+            } else {
+                Segment::Key(name.to_string())
+            });
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').ok_or_else(|| invalid("unclosed ["))?;
+            let inner = after[..end].trim();
+            let quoted = inner
+                .strip_prefix('\'')
+                .and_then(|s| s.strip_suffix('\''))
+                .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')));
+
+            segments.push(match quoted {
+                Some(name) => Segment::Key(name.to_string()),
+                None if inner == "*" => Segment::Wildcard,
+                None => Segment::Index(
+                    inner
+                        .parse()
+                        .map_err(|_| invalid(&format!("bad index [{}]", inner)))?,
+                ),
+            });
+            rest = &after[end + 1..];
+        } else {
+            return Err(invalid(&format!("unexpected {}", rest)));
+        }
+    }
+
+    Ok(segments)
+}
+
+/// Every value the path selects, in document order
+pub fn query<'a>(value: &'a Value, path: &str) -> Result<Vec<&'a Value>, String> {
+    let mut nodes = vec![value];
+
+    for segment in parse(path)? {
+        nodes = nodes
+            .into_iter()
+            .flat_map(|node| -> Vec<&Value> {
+                match (&segment, node) {
+                    (Segment::Key(key), Value::Object(map)) => map.get(key).into_iter().collect(),
+                    (Segment::Index(index), Value::Array(items)) => {
+                        let index = if *index < 0 {
+                            items.len() as i64 + index
+                        } else {
+                            *index
+                        };
+                        usize::try_from(index)
+                            .ok()
+                            .and_then(|i| items.get(i))
+                            .into_iter()
+                            .collect()
+                    }
+                    (Segment::Wildcard, Value::Array(items)) => items.iter().collect(),
+                    (Segment::Wildcard, Value::Object(map)) => map.values().collect(),
+                    _ => Vec::new(),
+                }
+            })
+            .collect();
+    }
+
+    Ok(nodes)
+}
+
+/// The first value the path selects
+pub fn query_first<'a>(value: &'a Value, path: &str) -> Result<Option<&'a Value>, String> {
+    Ok(query(value, path)?.into_iter().next())
+}
+
+/// Text form of a value: strings without quotes, everything else as JSON
+pub fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
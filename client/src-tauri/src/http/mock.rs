@@ -0,0 +1,363 @@
+use axum::body::Body;
+use axum::extract::{Request, State as AxumState};
+use axum::http::{HeaderName, HeaderValue, Method, StatusCode};
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tauri::{Emitter, State, Window};
+use tokio::sync::oneshot;
+
+use super::collections::substitute_variables;
+use super::HttpHeader;
+
+// ============================================================================
+// MOCK SERVER TYPES
+// ============================================================================
+
+/// Every request a mock server answers
+pub const MOCK_REQUEST_EVENT: &str = "http://mock-request";
+
+/// Largest request body read for templates
+const MAX_REQUEST_BODY: usize = 10 * 1024 * 1024;
+
+/// A canned response.
+///
+/// `path` segments starting with `:` capture a parameter and a final `*`
+/// matches the rest of the path. The body and header values are templates:
+/// `{{params.id}}`, `{{query.page}}`, `{{headers.x-token}}`, `{{body}}`,
+/// `{{method}}`, `{{path}}`, `{{uuid}}` and `{{now}}` are filled in per request.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MockRoute {
+    /// HTTP method, or `*` for any
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    #[serde(default)]
+    pub headers: Vec<HttpHeader>,
+    #[serde(default)]
+    pub body: String,
+    /// Wait this long before answering
+    pub delay_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MockServerInfo {
+    pub server_id: String,
+    pub port: u16,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MockRequestLog {
+    pub server_id: String,
+    pub method: String,
+    /// Path and query
+    pub path: String,
+    pub status: u16,
+    /// Index of the route that answered, None when nothing matched
+    pub route_index: Option<usize>,
+    /// Milliseconds since epoch
+    pub timestamp: i64,
+}
+
+/// What the request handler needs
+struct MockState {
+    server_id: String,
+    window: Window,
+    routes: RwLock<Vec<MockRoute>>,
+}
+
+struct RunningMock {
+    window_label: String,
+    state: Arc<MockState>,
+    shutdown: oneshot::Sender<()>,
+}
+
+/// Running mock servers, keyed by server id
+#[derive(Default)]
+pub struct MockServerRegistry {
+    servers: Mutex<HashMap<String, RunningMock>>,
+}
+
+impl MockServerRegistry {
+    /// Stop every mock server started by a window (e.g. when it is closed)
+    pub fn close_window(&self, label: &str) {
+        let Ok(mut servers) = self.servers.lock() else {
+            return;
+        };
+
+        let ids: Vec<String> = servers
+            .iter()
+            .filter(|(_, server)| server.window_label == label)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in ids {
+            if let Some(server) = servers.remove(&id) {
+                let _ = server.shutdown.send(());
+            }
+        }
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Match a request path against a route pattern, returning the captured parameters
+fn match_path(pattern: &str, path: &str) -> Option<HashMap<String, String>> {
+    let mut params = HashMap::new();
+    let mut segments = path.trim_matches('/').split('/').filter(|s| !s.is_empty());
+
+    for part in pattern
+        .trim_matches('/')
+        .split('/')
+        .filter(|s| !s.is_empty())
+    {
+        if part == "*" {
+            let rest: Vec<&str> = segments.by_ref().collect();
+            params.insert("*".to_string(), rest.join("/"));
+            return Some(params);
+        }
+        let segment = segments.next()?;
+        match part.strip_prefix(':') {
+            Some(name) => {
+                let value = percent_encoding::percent_decode_str(segment).decode_utf8_lossy();
+                params.insert(name.to_string(), value.into_owned());
+            }
+            None if part == segment => {}
+            None => return None,
+        }
+    }
+
+    segments.next().is_none().then_some(params)
+}
+
+/// Template variables for a request
+fn template_variables(
+    method: &Method,
+    uri: &axum::http::Uri,
+    headers: &axum::http::HeaderMap,
+    params: HashMap<String, String>,
+    body: &str,
+) -> HashMap<String, String> {
+    let mut variables: HashMap<String, String> = params
+        .into_iter()
+        .map(|(name, value)| (format!("params.{}", name), value))
+        .collect();
+
+    if let Some(query) = uri.query() {
+        for (name, value) in url::form_urlencoded::parse(query.as_bytes()) {
+            variables.insert(format!("query.{}", name), value.into_owned());
+        }
+    }
+    for (name, value) in headers {
+        if let Ok(value) = value.to_str() {
+            variables.insert(format!("headers.{}", name.as_str()), value.to_string());
+        }
+    }
+
+    variables.insert("body".to_string(), body.to_string());
+    variables.insert("method".to_string(), method.to_string());
+    variables.insert("path".to_string(), uri.path().to_string());
+    variables.insert("uuid".to_string(), uuid::Uuid::new_v4().to_string());
+    variables.insert("now".to_string(), chrono::Utc::now().to_rfc3339());
+    variables
+}
+
+fn with_cors(mut response: Response) -> Response {
+    let headers = response.headers_mut();
+    for (name, value) in [
+        ("access-control-allow-origin", "*"),
+        ("access-control-allow-methods", "*"),
+        ("access-control-allow-headers", "*"),
+        ("access-control-expose-headers", "*"),
+    ] {
+        headers
+            .entry(name)
+            .or_insert_with(|| HeaderValue::from_static(value));
+    }
+    response
+}
+
+fn plain_response(status: StatusCode, body: String) -> Response {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response
+}
+
+async fn handle(AxumState(state): AxumState<Arc<MockState>>, request: Request) -> Response {
+    let (parts, body) = request.into_parts();
+    let body = axum::body::to_bytes(body, MAX_REQUEST_BODY)
+        .await
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .unwrap_or_default();
+
+    let matched = state.routes.read().ok().and_then(|routes| {
+        routes.iter().enumerate().find_map(|(index, route)| {
+            let method_matches =
+                route.method == "*" || route.method.eq_ignore_ascii_case(parts.method.as_str());
+            if !method_matches {
+                return None;
+            }
+            match_path(&route.path, parts.uri.path()).map(|params| (index, route.clone(), params))
+        })
+    });
+
+    let (route_index, response) = match matched {
+        Some((index, route, params)) => {
+            let variables =
+                template_variables(&parts.method, &parts.uri, &parts.headers, params, &body);
+            if let Some(delay) = route.delay_ms.filter(|ms| *ms > 0) {
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+            }
+
+            let status = StatusCode::from_u16(route.status).unwrap_or(StatusCode::OK);
+            let mut response =
+                plain_response(status, substitute_variables(&route.body, &variables));
+            for header in route.headers.iter().filter(|h| h.enabled) {
+                let name = HeaderName::from_bytes(header.key.as_bytes());
+                let value = HeaderValue::from_str(&substitute_variables(&header.value, &variables));
+                if let (Ok(name), Ok(value)) = (name, value) {
+                    response.headers_mut().append(name, value);
+                }
+            }
+            (Some(index), response)
+        }
+        // Answer CORS preflights no route claimed
+        None if parts.method == Method::OPTIONS => {
+            (None, plain_response(StatusCode::NO_CONTENT, String::new()))
+        }
+        None => (
+            None,
+            plain_response(
+                StatusCode::NOT_FOUND,
+                format!("No mock route for {} {}", parts.method, parts.uri.path()),
+            ),
+        ),
+    };
+
+    let _ = state.window.emit_to(
+        state.window.label(),
+        MOCK_REQUEST_EVENT,
+        MockRequestLog {
+            server_id: state.server_id.clone(),
+            method: parts.method.to_string(),
+            path: parts
+                .uri
+                .path_and_query()
+                .map(|p| p.to_string())
+                .unwrap_or_default(),
+            status: response.status().as_u16(),
+            route_index,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        },
+    );
+
+    with_cors(response)
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Serve mock routes on a local port (0 or None picks a free one).
+///
+/// Routes are tried in order and the first match answers; every request is
+/// reported as an `http://mock-request` event on the calling window.
+#[tauri::command]
+pub async fn start_mock_server(
+    window: Window,
+    registry: State<'_, MockServerRegistry>,
+    port: Option<u16>,
+    routes: Vec<MockRoute>,
+) -> Result<MockServerInfo, String> {
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port.unwrap_or(0)))
+        .await
+        .map_err(|e| format!("Failed to listen on port {}: {}", port.unwrap_or(0), e))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to read local address: {}", e))?
+        .port();
+
+    let server_id = uuid::Uuid::new_v4().to_string();
+    let state = Arc::new(MockState {
+        server_id: server_id.clone(),
+        window: window.clone(),
+        routes: RwLock::new(routes),
+    });
+    let router = axum::Router::new()
+        .fallback(handle)
+        .with_state(state.clone());
+
+    let (shutdown, shutdown_rx) = oneshot::channel();
+    registry
+        .servers
+        .lock()
+        .map_err(|_| "Mock server registry is poisoned".to_string())?
+        .insert(
+            server_id.clone(),
+            RunningMock {
+                window_label: window.label().to_string(),
+                state,
+                shutdown,
+            },
+        );
+
+    tokio::spawn(async move {
+        let served = axum::serve(listener, router)
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            })
+            .await;
+        if let Err(e) = served {
+            log::warn!("Mock server on port {} stopped: {}", port, e);
+        }
+    });
+
+    Ok(MockServerInfo {
+        server_id,
+        port,
+        url: format!("http://127.0.0.1:{}", port),
+    })
+}
+
+/// Replace the routes of a running mock server without restarting it
+#[tauri::command]
+pub async fn update_mock_routes(
+    registry: State<'_, MockServerRegistry>,
+    server_id: String,
+    routes: Vec<MockRoute>,
+) -> Result<(), String> {
+    let servers = registry
+        .servers
+        .lock()
+        .map_err(|_| "Mock server registry is poisoned".to_string())?;
+    let server = servers
+        .get(&server_id)
+        .ok_or_else(|| format!("No mock server: {}", server_id))?;
+
+    *server
+        .state
+        .routes
+        .write()
+        .map_err(|_| "Mock routes are poisoned".to_string())? = routes;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn stop_mock_server(
+    registry: State<'_, MockServerRegistry>,
+    server_id: String,
+) -> Result<(), String> {
+    let server = registry
+        .servers
+        .lock()
+        .map_err(|_| "Mock server registry is poisoned".to_string())?
+        .remove(&server_id)
+        .ok_or_else(|| format!("No mock server: {}", server_id))?;
+
+    let _ = server.shutdown.send(());
+    Ok(())
+}
@@ -0,0 +1,378 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, State};
+
+pub mod auth;
+pub mod capture;
+pub mod collections;
+pub mod cookies;
+pub mod diff;
+pub mod download;
+pub mod extract;
+pub mod graphql;
+pub mod grpc;
+pub mod jsonpath;
+pub mod mock;
+pub mod openapi;
+pub mod retry;
+pub mod runner;
+pub mod sse;
+pub mod transport;
+pub mod websocket;
+
+// ============================================================================
+// HTTP REQUEST TYPES
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HttpHeader {
+    pub key: String,
+    pub value: String,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<HttpHeader>,
+    pub body: Option<String>,
+    pub timeout_ms: Option<u64>,
+    /// Send without the shared cookie jar: no cookies sent, none stored
+    #[serde(default)]
+    pub skip_cookie_jar: bool,
+    #[serde(default)]
+    pub auth: Option<auth::HttpAuth>,
+    /// Defaults to true; false returns the first redirect response as is
+    #[serde(default)]
+    pub follow_redirects: Option<bool>,
+    /// Defaults to capture::DEFAULT_MAX_REDIRECTS
+    #[serde(default)]
+    pub max_redirects: Option<usize>,
+    /// Sent instead of `body` when set
+    #[serde(default)]
+    pub graphql: Option<graphql::GraphqlBody>,
+    /// Proxy and TLS settings; unset fields come from the active environment
+    #[serde(default)]
+    pub transport: Option<transport::TransportSettings>,
+    /// Resend on connection errors or chosen statuses; None sends once
+    #[serde(default)]
+    pub retry: Option<retry::RetryPolicy>,
+}
+
+impl HttpRequest {
+    fn redirect_limit(&self) -> usize {
+        if self.follow_redirects == Some(false) {
+            0
+        } else {
+            self.max_redirects.unwrap_or(capture::DEFAULT_MAX_REDIRECTS)
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub status_text: String,
+    pub headers: HashMap<String, String>,
+    /// Text of the body, cut at MAX_PREVIEW_SIZE; empty for binary bodies
+    pub body: String,
+    pub time_ms: u64,
+    /// Bytes read, or the announced length when the body was cut
+    pub size_bytes: usize,
+    pub content_type: Option<String>,
+    pub is_binary: bool,
+    /// The body was larger than MAX_PREVIEW_SIZE; use save_response_to_file to get all of it
+    pub truncated: bool,
+    /// URL of the last hop
+    pub url: String,
+    pub redirects: Vec<capture::RedirectHop>,
+    pub timing: capture::TimingBreakdown,
+    /// None for plain HTTP or when the TLS probe failed
+    pub tls: Option<capture::TlsDetails>,
+    /// The `errors` of a GraphQL response; None for other requests
+    pub graphql_errors: Option<Vec<graphql::GraphqlError>>,
+    /// Every attempt when the request was retried by its policy, empty otherwise;
+    /// time_ms and timing cover all of them
+    #[serde(default)]
+    pub attempts: Vec<retry::RetryAttempt>,
+}
+
+/// Most of a response body held in memory for display
+const MAX_PREVIEW_SIZE: usize = 5 * 1024 * 1024;
+
+const DEFAULT_TIMEOUT_MS: u64 = 30000;
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Where collections, environments and cookies are kept
+fn http_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?
+        .join("http"))
+}
+
+/// Apply the active environment, transport settings and auth, and build the
+/// client and request ready to send.
+///
+/// The client does not follow redirects; see capture::execute_with_redirects.
+///
+/// With `streaming` the timeout applies to each read rather than the whole
+/// exchange, so long downloads are not cut off.
+pub(crate) async fn prepare_request(
+    app: &AppHandle,
+    jar: &cookies::CookieJar,
+    tokens: &auth::OAuth2Tokens,
+    request: &mut HttpRequest,
+    streaming: bool,
+) -> Result<(reqwest::Client, reqwest::Request), String> {
+    // App-wide defaults for whatever the request leaves unset
+    let defaults = crate::settings::load(app).http;
+    request.timeout_ms.get_or_insert(defaults.timeout_ms);
+    request.follow_redirects.get_or_insert(defaults.follow_redirects);
+    request.max_redirects.get_or_insert(defaults.max_redirects);
+
+    let environments = collections::load_environments(app)?;
+    let variables = environments.active_variables();
+    collections::apply_environment(request, &variables);
+    graphql::encode_request(request)?;
+
+    let mut transport_settings = request
+        .transport
+        .clone()
+        .unwrap_or_default()
+        .or(&environments.active_transport());
+    transport_settings.substitute(&variables);
+
+    let timeout =
+        std::time::Duration::from_millis(request.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS));
+
+    let mut client_builder = transport::apply(
+        reqwest::Client::builder().redirect(reqwest::redirect::Policy::none()),
+        &transport_settings,
+    )?;
+    client_builder = if streaming {
+        client_builder.read_timeout(timeout)
+    } else {
+        client_builder.timeout(timeout)
+    };
+    if !request.skip_cookie_jar {
+        client_builder = client_builder.cookie_provider(jar.store(app));
+    }
+    let client = client_builder
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let method = request.method.to_uppercase();
+    let mut req_builder = match method.as_str() {
+        "GET" => client.get(&request.url),
+        "POST" => client.post(&request.url),
+        "PUT" => client.put(&request.url),
+        "PATCH" => client.patch(&request.url),
+        "DELETE" => client.delete(&request.url),
+        "HEAD" => client.head(&request.url),
+        "OPTIONS" => client.request(reqwest::Method::OPTIONS, &request.url),
+        _ => return Err(format!("Unsupported HTTP method: {}", method)),
+    };
+
+    // Add headers
+    for header in &request.headers {
+        if header.enabled && !header.key.is_empty() {
+            req_builder = req_builder.header(&header.key, &header.value);
+        }
+    }
+
+    // Add body for methods that support it
+    if let Some(body) = &request.body {
+        if !body.is_empty() && matches!(method.as_str(), "POST" | "PUT" | "PATCH") {
+            let has_content_type = request
+                .headers
+                .iter()
+                .any(|h| h.enabled && h.key.to_lowercase() == "content-type");
+
+            if !has_content_type {
+                req_builder = req_builder.header("Content-Type", "application/json");
+            }
+
+            req_builder = req_builder.body(body.clone());
+        }
+    }
+
+    if let Some(request_auth) = &request.auth {
+        req_builder = auth::apply_auth(req_builder, request_auth, &client, tokens).await?;
+    }
+
+    let built = req_builder
+        .build()
+        .map_err(|e| format!("Invalid request: {}", e))?;
+    Ok((client, built))
+}
+
+/// Persist cookies the response may have set
+pub(crate) fn save_cookies(app: &AppHandle, jar: &cookies::CookieJar, request: &HttpRequest) {
+    if !request.skip_cookie_jar {
+        if let Err(e) = jar.persist(app) {
+            log::warn!("Failed to save cookies: {}", e);
+        }
+    }
+}
+
+pub(crate) fn status_text(status: reqwest::StatusCode) -> String {
+    status.canonical_reason().unwrap_or("Unknown").to_string()
+}
+
+pub(crate) fn response_headers(response: &reqwest::Response) -> HashMap<String, String> {
+    let mut headers = HashMap::new();
+    for (key, value) in response.headers().iter() {
+        if let Ok(v) = value.to_str() {
+            headers.insert(key.to_string(), v.to_string());
+        }
+    }
+    headers
+}
+
+/// Content types shown as text; anything else is treated as binary
+fn is_text_content_type(content_type: &str) -> bool {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+
+    mime.starts_with("text/")
+        || mime.ends_with("+json")
+        || mime.ends_with("+xml")
+        || matches!(
+            mime.as_str(),
+            "application/json"
+                | "application/xml"
+                | "application/javascript"
+                | "application/ecmascript"
+                | "application/x-www-form-urlencoded"
+                | "application/graphql"
+                | "application/yaml"
+                | "application/x-yaml"
+                | "application/x-ndjson"
+                | "image/svg+xml"
+        )
+}
+
+/// Send a request after substituting `{{variables}}` from the active environment.
+///
+/// The body is streamed and only the first MAX_PREVIEW_SIZE bytes are kept;
+/// binary bodies are measured but not returned.
+pub(crate) async fn execute_request(
+    app: &AppHandle,
+    jar: &cookies::CookieJar,
+    tokens: &auth::OAuth2Tokens,
+    mut request: HttpRequest,
+) -> Result<HttpResponse, String> {
+    let (client, built) = prepare_request(app, jar, tokens, &mut request, false).await?;
+
+    let timeout =
+        std::time::Duration::from_millis(request.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS));
+    let probe = capture::probe_connection(built.url(), timeout).await;
+
+    let start = std::time::Instant::now();
+
+    let (mut response, redirects, attempts) = retry::execute_with_retries(
+        &client,
+        built,
+        request.redirect_limit(),
+        request.retry.as_ref(),
+    )
+    .await?;
+    let first_byte = start.elapsed();
+
+    save_cookies(app, jar, &request);
+
+    let status = response.status().as_u16();
+    let status_text = status_text(response.status());
+    let headers = response_headers(&response);
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    let content_length = response.content_length();
+    let url = response.url().to_string();
+
+    let mut preview = Vec::new();
+    let mut truncated = false;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?
+    {
+        let room = MAX_PREVIEW_SIZE - preview.len();
+        preview.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        if chunk.len() > room {
+            // Dropping the response closes the connection instead of downloading the rest
+            truncated = true;
+            break;
+        }
+    }
+
+    let elapsed = start.elapsed();
+    let timing = capture::TimingBreakdown {
+        first_byte_ms: first_byte.as_secs_f64() * 1000.0,
+        total_ms: elapsed.as_secs_f64() * 1000.0,
+        ..probe.timing
+    };
+
+    let is_binary = match &content_type {
+        Some(content_type) => !is_text_content_type(content_type),
+        None => crate::looks_binary(&preview),
+    };
+    let size_bytes = match (truncated, content_length) {
+        (true, Some(length)) => length as usize,
+        _ => preview.len(),
+    };
+    let body = if is_binary {
+        String::new()
+    } else {
+        String::from_utf8_lossy(&preview).into_owned()
+    };
+    let graphql_errors = request
+        .graphql
+        .as_ref()
+        .and_then(|_| graphql::parse_errors(&body));
+
+    Ok(HttpResponse {
+        status,
+        status_text,
+        headers,
+        body,
+        time_ms: elapsed.as_millis() as u64,
+        size_bytes,
+        content_type,
+        is_binary,
+        truncated,
+        url,
+        redirects,
+        timing,
+        tls: probe.tls,
+        graphql_errors,
+        attempts,
+    })
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+#[tauri::command]
+pub async fn send_http_request(
+    app: AppHandle,
+    jar: State<'_, cookies::CookieJar>,
+    tokens: State<'_, auth::OAuth2Tokens>,
+    request: HttpRequest,
+) -> Result<HttpResponse, String> {
+    execute_request(&app, &jar, &tokens, request).await
+}
@@ -0,0 +1,492 @@
+use serde_json::{json, Map, Value};
+use tauri::AppHandle;
+
+use super::auth::{ApiKeyLocation, HttpAuth, OAuth2Config, OAuth2Grant};
+use super::collections::{write_collection, Collection, CollectionFolder, SavedRequest};
+use super::{HttpHeader, HttpRequest};
+
+// ============================================================================
+// OPENAPI TYPES
+// ============================================================================
+
+const METHODS: [&str; 7] = ["get", "post", "put", "patch", "delete", "head", "options"];
+
+/// How deep example bodies follow nested and recursive schemas
+const MAX_SCHEMA_DEPTH: usize = 8;
+
+/// The document being imported and where it came from, to resolve references
+/// and relative server URLs
+struct Spec<'a> {
+    root: &'a Value,
+    source_url: Option<url::Url>,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+async fn load_document(source: &str) -> Result<Value, String> {
+    let text = if source.starts_with("http://") || source.starts_with("https://") {
+        reqwest::get(source)
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Failed to download {}: {}", source, e))?
+            .text()
+            .await
+            .map_err(|e| format!("Failed to download {}: {}", source, e))?
+    } else {
+        std::fs::read_to_string(source).map_err(|e| format!("Failed to read {}: {}", source, e))?
+    };
+
+    // YAML is a superset of JSON, so one parser reads both
+    serde_yaml::from_str(&text).map_err(|e| format!("Failed to parse OpenAPI document: {}", e))
+}
+
+impl Spec<'_> {
+    /// Follow a local `$ref` (`#/components/...`); anything else is returned as is
+    fn resolve<'v>(&'v self, value: &'v Value) -> &'v Value {
+        let mut value = value;
+        // Bounded so a reference cycle cannot loop forever
+        for _ in 0..MAX_SCHEMA_DEPTH {
+            let Some(target) = value
+                .get("$ref")
+                .and_then(Value::as_str)
+                .and_then(|r| r.strip_prefix('#'))
+                .and_then(|pointer| self.root.pointer(pointer))
+            else {
+                break;
+            };
+            value = target;
+        }
+        value
+    }
+
+    fn base_url(&self, path_item: &Value, operation: &Value) -> String {
+        let server = [operation, path_item, self.root]
+            .into_iter()
+            .filter_map(|v| v.get("servers")?.as_array()?.first())
+            .next();
+        let Some(server) = server else {
+            return "{{baseUrl}}".to_string();
+        };
+
+        let mut url = server
+            .get("url")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        if let Some(variables) = server.get("variables").and_then(Value::as_object) {
+            for (name, variable) in variables {
+                let default = variable
+                    .get("default")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                url = url.replace(&format!("{{{}}}", name), default);
+            }
+        }
+
+        if !url.contains("://") {
+            if let Some(joined) = self.source_url.as_ref().and_then(|s| s.join(&url).ok()) {
+                url = joined.to_string();
+            } else if url.is_empty() || url.starts_with('/') {
+                url = format!("{{{{baseUrl}}}}{}", url);
+            }
+        }
+        url.trim_end_matches('/').to_string()
+    }
+
+    /// An example value for a schema, from its examples or built from its shape
+    fn example(&self, schema: &Value, depth: usize) -> Value {
+        let schema = self.resolve(schema);
+        if depth > MAX_SCHEMA_DEPTH {
+            return Value::Null;
+        }
+
+        for key in ["example", "default", "const"] {
+            if let Some(value) = schema.get(key) {
+                return value.clone();
+            }
+        }
+        if let Some(first) = schema.get("examples").and_then(|e| e.as_array()?.first()) {
+            return first.clone();
+        }
+        if let Some(first) = schema.get("enum").and_then(|e| e.as_array()?.first()) {
+            return first.clone();
+        }
+
+        if let Some(parts) = schema.get("allOf").and_then(Value::as_array) {
+            let mut merged = Map::new();
+            for part in parts {
+                if let Value::Object(fields) = self.example(part, depth + 1) {
+                    merged.extend(fields);
+                }
+            }
+            return Value::Object(merged);
+        }
+        for key in ["oneOf", "anyOf"] {
+            if let Some(first) = schema.get(key).and_then(|e| e.as_array()?.first()) {
+                return self.example(first, depth + 1);
+            }
+        }
+
+        // 3.1 allows a list of types; take the first that is not null
+        let schema_type = match schema.get("type") {
+            Some(Value::String(t)) => Some(t.as_str()),
+            Some(Value::Array(types)) => types
+                .iter()
+                .filter_map(Value::as_str)
+                .find(|t| *t != "null"),
+            _ => None,
+        };
+        match schema_type {
+            Some("object") | None if schema.get("properties").is_some() => {
+                let fields = schema
+                    .get("properties")
+                    .and_then(Value::as_object)
+                    .map(|properties| {
+                        properties
+                            .iter()
+                            .map(|(name, property)| {
+                                (name.clone(), self.example(property, depth + 1))
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Value::Object(fields)
+            }
+            Some("object") => json!({}),
+            Some("array") => match schema.get("items") {
+                Some(items) => json!([self.example(items, depth + 1)]),
+                None => json!([]),
+            },
+            Some("string") => json!(match schema.get("format").and_then(Value::as_str) {
+                Some("date-time") => "2024-01-01T00:00:00Z",
+                Some("date") => "2024-01-01",
+                Some("email") => "user@example.com",
+                Some("uuid") => "00000000-0000-0000-0000-000000000000",
+                Some("uri") | Some("url") => "https://example.com",
+                _ => "string",
+            }),
+            Some("integer") => json!(0),
+            Some("number") => json!(0.0),
+            Some("boolean") => json!(false),
+            _ => Value::Null,
+        }
+    }
+
+    /// The example of a media type: `example`, the first of `examples`, or one
+    /// built from the schema
+    fn media_example(&self, media: &Value) -> Value {
+        if let Some(example) = media.get("example") {
+            return example.clone();
+        }
+        if let Some(first) = media
+            .get("examples")
+            .and_then(Value::as_object)
+            .and_then(|examples| examples.values().next())
+        {
+            return self
+                .resolve(first)
+                .get("value")
+                .cloned()
+                .unwrap_or(Value::Null);
+        }
+        media
+            .get("schema")
+            .map(|schema| self.example(schema, 0))
+            .unwrap_or(Value::Null)
+    }
+
+    /// Path-level parameters overridden by operation-level ones of the same name and location
+    fn parameters<'v>(&'v self, path_item: &'v Value, operation: &'v Value) -> Vec<&'v Value> {
+        let mut parameters: Vec<&Value> = Vec::new();
+        for list in [path_item, operation]
+            .into_iter()
+            .filter_map(|v| v.get("parameters")?.as_array())
+        {
+            for parameter in list.iter().map(|p| self.resolve(p)) {
+                let key = (parameter.get("name"), parameter.get("in"));
+                parameters.retain(|p| (p.get("name"), p.get("in")) != key);
+                parameters.push(parameter);
+            }
+        }
+        parameters
+    }
+
+    /// Auth for the first security requirement that maps onto a supported scheme
+    fn auth(&self, operation: &Value) -> Option<HttpAuth> {
+        let requirements = operation
+            .get("security")
+            .or_else(|| self.root.get("security"))?
+            .as_array()?;
+
+        requirements
+            .iter()
+            .filter_map(Value::as_object)
+            .flat_map(|requirement| requirement.iter())
+            .find_map(|(name, scopes)| {
+                let scheme = self.resolve(self.root.pointer(&format!(
+                    "/components/securitySchemes/{}",
+                    name.replace('~', "~0").replace('/', "~1")
+                ))?);
+                let scopes: Vec<&str> = scopes
+                    .as_array()
+                    .map(|s| s.iter().filter_map(Value::as_str).collect())
+                    .unwrap_or_default();
+                security_scheme_auth(scheme, &scopes)
+            })
+    }
+}
+
+fn str_field<'v>(value: &'v Value, key: &str) -> Option<&'v str> {
+    value.get(key).and_then(Value::as_str)
+}
+
+fn security_scheme_auth(scheme: &Value, scopes: &[&str]) -> Option<HttpAuth> {
+    let scope = (!scopes.is_empty()).then(|| scopes.join(" "));
+
+    match str_field(scheme, "type")? {
+        "http" => match str_field(scheme, "scheme")?.to_lowercase().as_str() {
+            "basic" => Some(HttpAuth::Basic {
+                username: "{{username}}".to_string(),
+                password: "{{password}}".to_string(),
+            }),
+            "bearer" => Some(HttpAuth::Bearer {
+                token: "{{token}}".to_string(),
+            }),
+            _ => None,
+        },
+        "apiKey" => Some(HttpAuth::ApiKey {
+            key: str_field(scheme, "name")?.to_string(),
+            value: "{{apiKey}}".to_string(),
+            location: match str_field(scheme, "in")? {
+                "header" => ApiKeyLocation::Header,
+                "query" => ApiKeyLocation::Query,
+                _ => return None,
+            },
+        }),
+        "oauth2" => {
+            let flows = scheme.get("flows")?;
+            let (grant, flow) = match (
+                flows.get("clientCredentials"),
+                flows.get("authorizationCode"),
+            ) {
+                (Some(flow), _) => (OAuth2Grant::ClientCredentials, flow),
+                (None, Some(flow)) => (OAuth2Grant::AuthorizationCode, flow),
+                (None, None) => return None,
+            };
+            Some(HttpAuth::OAuth2(OAuth2Config {
+                grant,
+                token_url: str_field(flow, "tokenUrl")?.to_string(),
+                auth_url: str_field(flow, "authorizationUrl").map(|u| u.to_string()),
+                client_id: "{{clientId}}".to_string(),
+                client_secret: Some("{{clientSecret}}".to_string()),
+                scope,
+                redirect_uri: None,
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// Text of a scalar example for URLs and headers
+fn example_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn form_body(example: &Value) -> String {
+    let mut form = url::form_urlencoded::Serializer::new(String::new());
+    if let Some(fields) = example.as_object() {
+        for (name, value) in fields {
+            form.append_pair(name, &example_text(value));
+        }
+    }
+    form.finish()
+}
+
+/// Turn one operation into a saved request
+fn build_request(
+    spec: &Spec,
+    path: &str,
+    method: &str,
+    path_item: &Value,
+    operation: &Value,
+) -> SavedRequest {
+    // OpenAPI path templates `{id}` become environment variables `{{id}}`
+    let mut url = format!(
+        "{}{}",
+        spec.base_url(path_item, operation),
+        path.replace('{', "{{").replace('}', "}}")
+    );
+    let mut query = Vec::new();
+    let mut headers = Vec::new();
+
+    for parameter in spec.parameters(path_item, operation) {
+        let Some(name) = str_field(parameter, "name") else {
+            continue;
+        };
+        let required = parameter.get("required").and_then(Value::as_bool) == Some(true);
+        let example = parameter
+            .get("example")
+            .cloned()
+            .or_else(|| {
+                parameter
+                    .get("schema")
+                    .map(|schema| spec.example(schema, 0))
+                    .filter(|v| !v.is_null() && v != "string")
+            })
+            .map(|v| example_text(&v))
+            .unwrap_or_else(|| format!("{{{{{}}}}}", name));
+
+        match str_field(parameter, "in") {
+            Some("query") if required || parameter.get("example").is_some() => {
+                query.push(format!("{}={}", name, example));
+            }
+            Some("header") => headers.push(HttpHeader {
+                key: name.to_string(),
+                value: example,
+                enabled: required,
+            }),
+            _ => {}
+        }
+    }
+    if !query.is_empty() {
+        url = format!("{}?{}", url, query.join("&"));
+    }
+
+    let mut body = None;
+    let content = operation
+        .get("requestBody")
+        .map(|b| spec.resolve(b))
+        .and_then(|b| b.get("content"))
+        .and_then(Value::as_object);
+    if let Some(content) = content {
+        let media_type = content
+            .keys()
+            .find(|t| t.contains("json"))
+            .or_else(|| content.keys().next());
+        if let Some(media_type) = media_type {
+            let example = spec.media_example(&content[media_type]);
+            body = match media_type.as_str() {
+                t if t.contains("json") => serde_json::to_string_pretty(&example).ok(),
+                "application/x-www-form-urlencoded" => Some(form_body(&example)),
+                _ => example.as_str().map(|s| s.to_string()),
+            };
+            headers.push(HttpHeader {
+                key: "Content-Type".to_string(),
+                value: media_type.clone(),
+                enabled: true,
+            });
+        }
+    }
+
+    let name = str_field(operation, "summary")
+        .or_else(|| str_field(operation, "operationId"))
+        .map(|n| n.to_string())
+        .unwrap_or_else(|| format!("{} {}", method.to_uppercase(), path));
+
+    SavedRequest {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        folder_id: None,
+        request: HttpRequest {
+            method: method.to_uppercase(),
+            url,
+            headers,
+            body,
+            auth: spec.auth(operation),
+            ..Default::default()
+        },
+        assertions: Vec::new(),
+        extractions: Vec::new(),
+    }
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Create a collection from an OpenAPI 3.x document (JSON or YAML, file path or URL).
+///
+/// Operations are grouped in folders by their first tag. Path parameters,
+/// required query parameters and credentials become `{{variables}}` to fill
+/// in from an environment.
+#[tauri::command]
+pub async fn import_openapi(
+    app: AppHandle,
+    source: String,
+    name: Option<String>,
+) -> Result<Collection, String> {
+    let document = load_document(&source).await?;
+    let version = str_field(&document, "openapi").unwrap_or_default();
+    if !version.starts_with('3') {
+        return Err(match document.get("swagger") {
+            Some(_) => {
+                "Swagger 2.0 documents are not supported; convert to OpenAPI 3 first".to_string()
+            }
+            None => "Not an OpenAPI 3 document".to_string(),
+        });
+    }
+
+    let spec = Spec {
+        root: &document,
+        source_url: url::Url::parse(&source)
+            .ok()
+            .filter(|u| u.scheme().starts_with("http")),
+    };
+    let mut collection = Collection {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name
+            .filter(|n| !n.trim().is_empty())
+            .or_else(|| {
+                document
+                    .pointer("/info/title")?
+                    .as_str()
+                    .map(|t| t.to_string())
+            })
+            .unwrap_or_else(|| "Imported API".to_string()),
+        ..Default::default()
+    };
+
+    let paths = document
+        .get("paths")
+        .and_then(Value::as_object)
+        .ok_or("The document has no paths")?;
+    for (path, path_item) in paths {
+        let path_item = spec.resolve(path_item);
+        for method in METHODS {
+            let Some(operation) = path_item.get(method) else {
+                continue;
+            };
+            let mut request = build_request(&spec, path, method, path_item, operation);
+
+            if let Some(tag) = operation
+                .get("tags")
+                .and_then(|t| t.as_array()?.first()?.as_str())
+            {
+                let folder_id = match collection.folders.iter().find(|f| f.name == tag) {
+                    Some(folder) => folder.id.clone(),
+                    None => {
+                        let folder = CollectionFolder {
+                            id: uuid::Uuid::new_v4().to_string(),
+                            name: tag.to_string(),
+                            parent_id: None,
+                        };
+                        let id = folder.id.clone();
+                        collection.folders.push(folder);
+                        id
+                    }
+                };
+                request.folder_id = Some(folder_id);
+            }
+            collection.requests.push(request);
+        }
+    }
+
+    write_collection(&app, &mut collection)?;
+    Ok(collection)
+}
@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use super::capture::{execute_with_redirects, RedirectHop};
+
+// ============================================================================
+// RETRY TYPES
+// ============================================================================
+
+/// Longest wait between attempts, whatever the backoff or Retry-After says
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// When and how often to resend a failed request
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Attempts after the first one
+    pub max_retries: u32,
+    /// Wait before the first retry, multiplied by backoff_multiplier for each one after
+    pub backoff_ms: u64,
+    pub backoff_multiplier: f64,
+    /// Response statuses that are retried
+    pub retry_on_status: Vec<u16>,
+    /// Retry when no response arrives (refused connection, timeout, reset)
+    pub retry_on_connection_error: bool,
+    /// Also retry POST and PATCH, which may not be safe to send twice
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            backoff_ms: 500,
+            backoff_multiplier: 2.0,
+            retry_on_status: vec![429, 502, 503, 504],
+            retry_on_connection_error: true,
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetryAttempt {
+    /// 1 for the first try
+    pub attempt: u32,
+    /// None when no response arrived
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub time_ms: u64,
+    /// Wait before the next attempt; None for the last one
+    pub delay_ms: Option<u64>,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn is_idempotent(method: &reqwest::Method) -> bool {
+    matches!(
+        *method,
+        reqwest::Method::GET
+            | reqwest::Method::HEAD
+            | reqwest::Method::OPTIONS
+            | reqwest::Method::PUT
+            | reqwest::Method::DELETE
+            | reqwest::Method::TRACE
+    )
+}
+
+/// Seconds from a Retry-After header; HTTP dates are ignored
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+impl RetryPolicy {
+    fn backoff(&self, retry: u32) -> Duration {
+        let factor = self.backoff_multiplier.max(1.0).powi(retry as i32);
+        Duration::from_secs_f64(self.backoff_ms as f64 / 1000.0 * factor).min(MAX_RETRY_DELAY)
+    }
+}
+
+/// Send a request, retrying by the policy.
+///
+/// Without a policy, or for a non-idempotent method the policy does not
+/// cover, it is sent once and no attempts are recorded. The error of the
+/// last attempt is returned when all of them fail.
+pub(crate) async fn execute_with_retries(
+    client: &reqwest::Client,
+    request: reqwest::Request,
+    max_redirects: usize,
+    policy: Option<&RetryPolicy>,
+) -> Result<(reqwest::Response, Vec<RedirectHop>, Vec<RetryAttempt>), String> {
+    let policy = policy
+        .filter(|p| p.max_retries > 0)
+        .filter(|p| p.retry_non_idempotent || is_idempotent(request.method()));
+    let Some(policy) = policy else {
+        let (response, hops) = execute_with_redirects(client, request, max_redirects).await?;
+        return Ok((response, hops, Vec::new()));
+    };
+
+    let mut attempts = Vec::new();
+    let mut request = request;
+    let mut retry = 0;
+    loop {
+        let next = request.try_clone();
+        let start = Instant::now();
+        let result = execute_with_redirects(client, request, max_redirects).await;
+
+        let status = result.as_ref().ok().map(|(r, _)| r.status().as_u16());
+        let retryable = match &result {
+            Ok((response, _)) => policy.retry_on_status.contains(&response.status().as_u16()),
+            Err(_) => policy.retry_on_connection_error,
+        };
+        let delay = (retryable && next.is_some() && retry < policy.max_retries).then(|| {
+            let after = result.as_ref().ok().and_then(|(r, _)| retry_after(r));
+            after
+                .unwrap_or_else(|| policy.backoff(retry))
+                .min(MAX_RETRY_DELAY)
+        });
+
+        attempts.push(RetryAttempt {
+            attempt: retry + 1,
+            status,
+            error: result.as_ref().err().cloned(),
+            time_ms: start.elapsed().as_millis() as u64,
+            delay_ms: delay.map(|d| d.as_millis() as u64),
+        });
+
+        let (Some(delay), Some(next)) = (delay, next) else {
+            return match result {
+                Ok((response, hops)) => Ok((response, hops, attempts)),
+                Err(e) if attempts.len() > 1 => {
+                    Err(format!("{} (after {} attempts)", e, attempts.len()))
+                }
+                Err(e) => Err(e),
+            };
+        };
+        tokio::time::sleep(delay).await;
+        request = next;
+        retry += 1;
+    }
+}
@@ -0,0 +1,276 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, State};
+
+use super::collections::{
+    apply_environment, read_collection, store_active_variables, Collection, SavedRequest,
+};
+use super::diff::{self, ResponseDiff};
+use super::extract::{self, ExtractionResult};
+use super::{auth, cookies, execute_request, jsonpath, HttpResponse};
+
+// ============================================================================
+// ASSERTION TYPES
+// ============================================================================
+
+/// A check run against a response
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Assertion {
+    StatusEquals {
+        status: u16,
+    },
+    /// Header present and containing `value` (case-insensitive header name)
+    HeaderContains {
+        header: String,
+        value: String,
+    },
+    /// JSON body value at `path` equals `equals` or matches the `matches`
+    /// regex; with neither it only has to exist
+    JsonPath {
+        path: String,
+        equals: Option<serde_json::Value>,
+        matches: Option<String>,
+    },
+    ResponseTimeUnder {
+        ms: u64,
+    },
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AssertionResult {
+    pub assertion: Assertion,
+    pub passed: bool,
+    /// What was found, to show next to a failure
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RequestReport {
+    pub request_id: String,
+    pub name: String,
+    /// None when the request could not be sent
+    pub status: Option<u16>,
+    pub time_ms: u64,
+    pub error: Option<String>,
+    pub assertions: Vec<AssertionResult>,
+    pub extractions: Vec<ExtractionResult>,
+    /// Changes since the previous run; None on the first one
+    pub diff: Option<ResponseDiff>,
+    /// Every assertion passed and every extraction found a value
+    pub passed: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CollectionReport {
+    pub collection_id: String,
+    pub requests: Vec<RequestReport>,
+    pub passed: usize,
+    pub failed: usize,
+    pub total_ms: u64,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn check_json_path(
+    response: &HttpResponse,
+    path: &str,
+    equals: Option<&serde_json::Value>,
+    matches: Option<&str>,
+) -> (bool, String) {
+    if response.truncated {
+        return (false, "Body was too large to check".to_string());
+    }
+    let json: serde_json::Value = match serde_json::from_str(&response.body) {
+        Ok(json) => json,
+        Err(e) => return (false, format!("Body is not JSON: {}", e)),
+    };
+    let value = match jsonpath::query_first(&json, path) {
+        Ok(Some(value)) => value,
+        Ok(None) => return (false, format!("Nothing at {}", path)),
+        Err(e) => return (false, e),
+    };
+
+    let found = format!("Found {}", value);
+    if let Some(expected) = equals {
+        return (value == expected, found);
+    }
+    if let Some(pattern) = matches {
+        return match regex::Regex::new(pattern) {
+            Ok(re) => (re.is_match(&jsonpath::value_to_string(value)), found),
+            Err(e) => (false, format!("Invalid regex: {}", e)),
+        };
+    }
+    (true, found)
+}
+
+/// Run assertions against a response
+pub(crate) fn evaluate(response: &HttpResponse, assertions: &[Assertion]) -> Vec<AssertionResult> {
+    assertions
+        .iter()
+        .map(|assertion| {
+            let (passed, message) = match assertion {
+                Assertion::StatusEquals { status } => (
+                    response.status == *status,
+                    format!("Status was {}", response.status),
+                ),
+                Assertion::HeaderContains { header, value } => {
+                    match response
+                        .headers
+                        .iter()
+                        .find(|(name, _)| name.eq_ignore_ascii_case(header))
+                    {
+                        Some((_, actual)) => (
+                            actual.contains(value.as_str()),
+                            format!("Header was {}", actual),
+                        ),
+                        None => (false, format!("No {} header", header)),
+                    }
+                }
+                Assertion::JsonPath {
+                    path,
+                    equals,
+                    matches,
+                } => check_json_path(response, path, equals.as_ref(), matches.as_deref()),
+                Assertion::ResponseTimeUnder { ms } => (
+                    response.time_ms < *ms,
+                    format!("Took {} ms", response.time_ms),
+                ),
+            };
+
+            AssertionResult {
+                assertion: assertion.clone(),
+                passed,
+                message,
+            }
+        })
+        .collect()
+}
+
+/// Requests in a folder and its subfolders, or the whole collection, in saved order
+fn requests_to_run<'a>(
+    collection: &'a Collection,
+    folder_id: Option<&str>,
+) -> Vec<&'a SavedRequest> {
+    let Some(folder_id) = folder_id else {
+        return collection.requests.iter().collect();
+    };
+
+    let mut folders = vec![folder_id.to_string()];
+    let mut i = 0;
+    while i < folders.len() {
+        let parent = folders[i].clone();
+        folders.extend(
+            collection
+                .folders
+                .iter()
+                .filter(|f| f.parent_id.as_ref() == Some(&parent))
+                .map(|f| f.id.clone()),
+        );
+        i += 1;
+    }
+
+    collection
+        .requests
+        .iter()
+        .filter(|r| r.folder_id.as_ref().is_some_and(|f| folders.contains(f)))
+        .collect()
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Check a response the frontend already has against a request's assertions
+#[tauri::command]
+pub fn check_assertions(
+    response: HttpResponse,
+    assertions: Vec<Assertion>,
+) -> Vec<AssertionResult> {
+    evaluate(&response, &assertions)
+}
+
+/// Send every request of a collection (or of one folder) in order and check its assertions.
+///
+/// Values extracted from a response are used by the requests after it and
+/// stored in the active environment. Each response is kept and diffed
+/// against the one from the previous run. A request that cannot be sent fails but
+/// does not stop the run.
+#[tauri::command]
+pub async fn run_collection(
+    app: AppHandle,
+    jar: State<'_, cookies::CookieJar>,
+    tokens: State<'_, auth::OAuth2Tokens>,
+    collection_id: String,
+    folder_id: Option<String>,
+) -> Result<CollectionReport, String> {
+    let collection = read_collection(&app, &collection_id)?;
+    let start = std::time::Instant::now();
+
+    // Extracted values, applied before the environment so they also work
+    // without an active environment
+    let mut extracted = HashMap::new();
+    let mut reports = Vec::new();
+    for saved in requests_to_run(&collection, folder_id.as_deref()) {
+        let mut request = saved.request.clone();
+        apply_environment(&mut request, &extracted);
+
+        let report = match execute_request(&app, &jar, &tokens, request).await {
+            Ok(response) => {
+                let assertions = evaluate(&response, &saved.assertions);
+                let extractions = extract::extract(&response, &saved.extractions);
+
+                let values = extract::found_values(&extractions);
+                if !values.is_empty() {
+                    if let Err(e) = store_active_variables(&app, &values) {
+                        log::warn!("Failed to store extracted variables: {}", e);
+                    }
+                    extracted.extend(values);
+                }
+
+                let (status, time_ms) = (response.status, response.time_ms);
+                let diff = diff::record_response(&app, &collection_id, &saved.id, response)
+                    .unwrap_or_else(|e| {
+                        log::warn!("Failed to store response of {}: {}", saved.name, e);
+                        None
+                    });
+
+                RequestReport {
+                    request_id: saved.id.clone(),
+                    name: saved.name.clone(),
+                    status: Some(status),
+                    time_ms,
+                    error: None,
+                    passed: assertions.iter().all(|a| a.passed)
+                        && extractions.iter().all(|e| e.value.is_some()),
+                    assertions,
+                    extractions,
+                    diff,
+                }
+            }
+            Err(e) => RequestReport {
+                request_id: saved.id.clone(),
+                name: saved.name.clone(),
+                status: None,
+                time_ms: 0,
+                error: Some(e),
+                assertions: Vec::new(),
+                extractions: Vec::new(),
+                diff: None,
+                passed: false,
+            },
+        };
+        reports.push(report);
+    }
+
+    let passed = reports.iter().filter(|r| r.passed).count();
+    Ok(CollectionReport {
+        collection_id,
+        failed: reports.len() - passed,
+        passed,
+        requests: reports,
+        total_ms: start.elapsed().as_millis() as u64,
+    })
+}
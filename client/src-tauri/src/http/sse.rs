@@ -0,0 +1,454 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+use tokio::sync::oneshot;
+
+use super::capture::execute_with_redirects;
+use super::{
+    auth, cookies, prepare_request, response_headers, save_cookies, status_text, HttpHeader,
+    HttpRequest,
+};
+
+// ============================================================================
+// SSE TYPES
+// ============================================================================
+
+/// Every event received on a stream
+pub const SSE_EVENT: &str = "http://sse-event";
+
+/// Connection drops and reconnect attempts
+pub const SSE_STATUS_EVENT: &str = "http://sse-status";
+
+/// Emitted once when a stream ends for good
+pub const SSE_CLOSED_EVENT: &str = "http://sse-closed";
+
+/// Reconnect delay until the server sends a `retry:` field
+const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+
+/// Consecutive failed reconnects before the stream is given up
+const MAX_RECONNECT_ATTEMPTS: u32 = 10;
+
+/// Read timeout when the request does not set one; streams are often idle
+const DEFAULT_IDLE_TIMEOUT_MS: u64 = 5 * 60 * 1000;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SseEvent {
+    pub stream_id: String,
+    /// Last event id seen on the stream, sent back as Last-Event-ID on reconnect
+    pub id: Option<String>,
+    /// The `event:` field, "message" when absent
+    pub event: String,
+    pub data: String,
+    /// Milliseconds since epoch
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SseStatus {
+    pub stream_id: String,
+    pub connected: bool,
+    /// Current reconnect attempt, None while connected
+    pub reconnect_attempt: Option<u32>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SseClosed {
+    pub stream_id: String,
+    /// None when closed with close_sse
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SseStreamInfo {
+    pub stream_id: String,
+    pub status: u16,
+    pub status_text: String,
+    pub headers: HashMap<String, String>,
+}
+
+struct RunningStream {
+    window_label: String,
+    cancel: oneshot::Sender<()>,
+}
+
+/// Open event streams, keyed by stream id
+#[derive(Default)]
+pub struct SseRegistry {
+    streams: Mutex<HashMap<String, RunningStream>>,
+}
+
+impl SseRegistry {
+    /// Close every stream opened by a window (e.g. when it is closed)
+    pub fn close_window(&self, label: &str) {
+        let Ok(mut streams) = self.streams.lock() else {
+            return;
+        };
+
+        let ids: Vec<String> = streams
+            .iter()
+            .filter(|(_, stream)| stream.window_label == label)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in ids {
+            if let Some(stream) = streams.remove(&id) {
+                let _ = stream.cancel.send(());
+            }
+        }
+    }
+
+    fn remove(&self, stream_id: &str) {
+        if let Ok(mut streams) = self.streams.lock() {
+            streams.remove(stream_id);
+        }
+    }
+}
+
+/// Incremental `text/event-stream` parser
+#[derive(Default)]
+struct EventStreamParser {
+    buffer: Vec<u8>,
+    /// The previous chunk ended in CR, so a leading LF belongs to that line
+    after_cr: bool,
+    started: bool,
+    data: String,
+    event: String,
+    last_event_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+/// A dispatched event, before it is tied to a stream
+struct ParsedEvent {
+    id: Option<String>,
+    event: String,
+    data: String,
+}
+
+impl EventStreamParser {
+    fn feed(&mut self, chunk: &[u8]) -> Vec<ParsedEvent> {
+        let mut events = Vec::new();
+
+        for &byte in chunk {
+            if std::mem::take(&mut self.after_cr) && byte == b'\n' {
+                continue;
+            }
+            if byte == b'\r' || byte == b'\n' {
+                self.after_cr = byte == b'\r';
+                let line = std::mem::take(&mut self.buffer);
+                if let Some(event) = self.process_line(&String::from_utf8_lossy(&line)) {
+                    events.push(event);
+                }
+            } else {
+                self.buffer.push(byte);
+            }
+        }
+
+        events
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<ParsedEvent> {
+        let line = if self.started {
+            line
+        } else {
+            self.started = true;
+            line.strip_prefix('\u{feff}').unwrap_or(line)
+        };
+
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => self.last_event_id = Some(value.to_string()),
+            "retry" => {
+                if let Ok(ms) = value.parse::<u64>() {
+                    self.retry = Some(Duration::from_millis(ms));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<ParsedEvent> {
+        let event = std::mem::take(&mut self.event);
+        let mut data = std::mem::take(&mut self.data);
+        if data.is_empty() {
+            return None;
+        }
+        data.pop();
+
+        Some(ParsedEvent {
+            id: self.last_event_id.clone(),
+            event: if event.is_empty() {
+                "message".to_string()
+            } else {
+                event
+            },
+            data,
+        })
+    }
+
+    /// Forget a half-received event when the connection drops
+    fn reset_connection(&mut self) {
+        self.buffer.clear();
+        self.after_cr = false;
+        self.started = false;
+        self.data.clear();
+        self.event.clear();
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Connect to the stream, sending Last-Event-ID when the parser has one
+async fn open_stream(
+    app: &AppHandle,
+    request: &HttpRequest,
+    last_event_id: Option<&str>,
+) -> Result<reqwest::Response, String> {
+    let mut request = request.clone();
+    if !request
+        .headers
+        .iter()
+        .any(|h| h.enabled && h.key.eq_ignore_ascii_case("accept"))
+    {
+        request.headers.push(HttpHeader {
+            key: "Accept".to_string(),
+            value: "text/event-stream".to_string(),
+            enabled: true,
+        });
+    }
+    if let Some(id) = last_event_id.filter(|id| !id.is_empty()) {
+        request.headers.push(HttpHeader {
+            key: "Last-Event-ID".to_string(),
+            value: id.to_string(),
+            enabled: true,
+        });
+    }
+    if request.timeout_ms.is_none() {
+        request.timeout_ms = Some(DEFAULT_IDLE_TIMEOUT_MS);
+    }
+
+    let jar = app.state::<cookies::CookieJar>();
+    let tokens = app.state::<auth::OAuth2Tokens>();
+    let (client, built) = prepare_request(app, &jar, &tokens, &mut request, true).await?;
+    let (response, _) = execute_with_redirects(&client, built, request.redirect_limit()).await?;
+    save_cookies(app, &jar, &request);
+
+    Ok(response)
+}
+
+/// Check the response really is an event stream
+fn check_stream(response: &reqwest::Response) -> Result<(), String> {
+    if !response.status().is_success() {
+        return Err(format!(
+            "Server answered {} {}",
+            response.status().as_u16(),
+            status_text(response.status())
+        ));
+    }
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if !content_type.to_lowercase().starts_with("text/event-stream") {
+        return Err(format!("Not an event stream: {}", content_type));
+    }
+    Ok(())
+}
+
+fn emit_status(window: &Window, status: SseStatus) {
+    let _ = window.emit_to(window.label(), SSE_STATUS_EVENT, status);
+}
+
+/// Read events until the connection drops; Ok(false) when cancelled
+async fn read_events(
+    window: &Window,
+    stream_id: &str,
+    response: &mut reqwest::Response,
+    parser: &mut EventStreamParser,
+    cancel: &mut oneshot::Receiver<()>,
+) -> Result<bool, String> {
+    loop {
+        let chunk = tokio::select! {
+            _ = &mut *cancel => return Ok(false),
+            chunk = response.chunk() => chunk,
+        };
+        let Some(chunk) = chunk.map_err(|e| format!("Stream failed: {}", e))? else {
+            return Ok(true);
+        };
+
+        for event in parser.feed(&chunk) {
+            let _ = window.emit_to(
+                window.label(),
+                SSE_EVENT,
+                SseEvent {
+                    stream_id: stream_id.to_string(),
+                    id: event.id,
+                    event: event.event,
+                    data: event.data,
+                    timestamp: chrono::Utc::now().timestamp_millis(),
+                },
+            );
+        }
+    }
+}
+
+/// Pump the stream, reconnecting with Last-Event-ID after drops, until it is
+/// cancelled, the server answers 204 or reconnecting keeps failing
+async fn run_stream(
+    app: AppHandle,
+    window: Window,
+    stream_id: String,
+    request: HttpRequest,
+    mut response: reqwest::Response,
+    mut cancel: oneshot::Receiver<()>,
+) {
+    let mut parser = EventStreamParser::default();
+
+    let reason = 'stream: loop {
+        let dropped =
+            match read_events(&window, &stream_id, &mut response, &mut parser, &mut cancel).await {
+                Ok(false) => break None,
+                Ok(true) => "Server closed the stream".to_string(),
+                Err(e) => e,
+            };
+        log::debug!("Event stream {} dropped: {}", stream_id, dropped);
+        parser.reset_connection();
+
+        let mut attempt = 0;
+        let mut error = dropped;
+        response = loop {
+            attempt += 1;
+            if attempt > MAX_RECONNECT_ATTEMPTS {
+                break 'stream Some(error);
+            }
+            emit_status(
+                &window,
+                SseStatus {
+                    stream_id: stream_id.clone(),
+                    connected: false,
+                    reconnect_attempt: Some(attempt),
+                    error: Some(error.clone()),
+                },
+            );
+
+            let delay = parser.retry.unwrap_or(DEFAULT_RETRY);
+            tokio::select! {
+                _ = &mut cancel => break 'stream None,
+                _ = tokio::time::sleep(delay) => {}
+            }
+
+            let opened = tokio::select! {
+                _ = &mut cancel => break 'stream None,
+                opened = open_stream(&app, &request, parser.last_event_id.as_deref()) => opened,
+            };
+            match opened {
+                // 204 tells the client to stop reconnecting
+                Ok(response) if response.status() == reqwest::StatusCode::NO_CONTENT => {
+                    break 'stream Some("Server ended the stream".to_string());
+                }
+                Ok(response) => match check_stream(&response) {
+                    Ok(()) => break response,
+                    Err(e) => break 'stream Some(e),
+                },
+                Err(e) => error = e,
+            }
+        };
+
+        emit_status(
+            &window,
+            SseStatus {
+                stream_id: stream_id.clone(),
+                connected: true,
+                reconnect_attempt: None,
+                error: None,
+            },
+        );
+    };
+
+    app.state::<SseRegistry>().remove(&stream_id);
+    let _ = window.emit_to(
+        window.label(),
+        SSE_CLOSED_EVENT,
+        SseClosed { stream_id, reason },
+    );
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Open a Server-Sent Events stream.
+///
+/// Returns once the server has answered; events then arrive as
+/// `http://sse-event` on the calling window. Dropped connections are retried
+/// with Last-Event-ID, honouring the server's `retry:` delay.
+#[tauri::command]
+pub async fn open_sse(
+    app: AppHandle,
+    window: Window,
+    registry: State<'_, SseRegistry>,
+    request: HttpRequest,
+) -> Result<SseStreamInfo, String> {
+    let response = open_stream(&app, &request, None).await?;
+    check_stream(&response)?;
+
+    let stream_id = uuid::Uuid::new_v4().to_string();
+    let (cancel_tx, cancel) = oneshot::channel();
+    registry
+        .streams
+        .lock()
+        .map_err(|_| "SSE registry is poisoned".to_string())?
+        .insert(
+            stream_id.clone(),
+            RunningStream {
+                window_label: window.label().to_string(),
+                cancel: cancel_tx,
+            },
+        );
+
+    let info = SseStreamInfo {
+        stream_id: stream_id.clone(),
+        status: response.status().as_u16(),
+        status_text: status_text(response.status()),
+        headers: response_headers(&response),
+    };
+    tokio::spawn(run_stream(
+        app, window, stream_id, request, response, cancel,
+    ));
+
+    Ok(info)
+}
+
+#[tauri::command]
+pub async fn close_sse(registry: State<'_, SseRegistry>, stream_id: String) -> Result<(), String> {
+    let stream = registry
+        .streams
+        .lock()
+        .map_err(|_| "SSE registry is poisoned".to_string())?
+        .remove(&stream_id)
+        .ok_or_else(|| format!("No open event stream: {}", stream_id))?;
+
+    let _ = stream.cancel.send(());
+    Ok(())
+}
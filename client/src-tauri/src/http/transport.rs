@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::collections::substitute_variables;
+
+// ============================================================================
+// TRANSPORT TYPES
+// ============================================================================
+
+/// Proxy and TLS trust settings, set on an environment and overridden field
+/// by field on a request
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TransportSettings {
+    /// e.g. `http://proxy:8080` or `socks5://localhost:1080`; credentials go in the URL
+    pub proxy_url: Option<String>,
+    /// Comma-separated hosts, domains and CIDR ranges that bypass the proxy
+    pub no_proxy: Option<String>,
+    /// Use the system/environment proxy (HTTP_PROXY...) when no proxy_url is set;
+    /// otherwise requests go direct
+    pub use_system_proxy: Option<bool>,
+    /// PEM file of extra CA certificates to trust
+    pub ca_bundle_path: Option<String>,
+    /// Client certificate for mutual TLS: a PEM certificate (with client_key_path)
+    /// or a PKCS#12 file (.p12/.pfx, with client_cert_password)
+    pub client_cert_path: Option<String>,
+    /// PKCS#8 PEM private key of a PEM client certificate
+    pub client_key_path: Option<String>,
+    pub client_cert_password: Option<String>,
+    /// Skip certificate and hostname checks; only for servers you control
+    pub accept_invalid_certs: Option<bool>,
+}
+
+impl TransportSettings {
+    /// These settings with every unset field taken from `fallback`
+    pub(crate) fn or(self, fallback: &TransportSettings) -> TransportSettings {
+        let fallback = fallback.clone();
+        TransportSettings {
+            proxy_url: self.proxy_url.or(fallback.proxy_url),
+            no_proxy: self.no_proxy.or(fallback.no_proxy),
+            use_system_proxy: self.use_system_proxy.or(fallback.use_system_proxy),
+            ca_bundle_path: self.ca_bundle_path.or(fallback.ca_bundle_path),
+            client_cert_path: self.client_cert_path.or(fallback.client_cert_path),
+            client_key_path: self.client_key_path.or(fallback.client_key_path),
+            client_cert_password: self.client_cert_password.or(fallback.client_cert_password),
+            accept_invalid_certs: self.accept_invalid_certs.or(fallback.accept_invalid_certs),
+        }
+    }
+
+    /// Substitute environment variables in every field
+    pub(crate) fn substitute(&mut self, variables: &HashMap<String, String>) {
+        for field in [
+            &mut self.proxy_url,
+            &mut self.no_proxy,
+            &mut self.ca_bundle_path,
+            &mut self.client_cert_path,
+            &mut self.client_key_path,
+            &mut self.client_cert_password,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *field = substitute_variables(field, variables);
+        }
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn read_file(path: &str, what: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("Failed to read {} {}: {}", what, path, e))
+}
+
+fn client_identity(
+    cert_path: &str,
+    settings: &TransportSettings,
+) -> Result<reqwest::Identity, String> {
+    let cert = read_file(cert_path, "client certificate")?;
+    let is_pkcs12 = Path::new(cert_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("p12") || ext.eq_ignore_ascii_case("pfx"));
+
+    if is_pkcs12 {
+        let password = settings.client_cert_password.as_deref().unwrap_or_default();
+        return reqwest::Identity::from_pkcs12_der(&cert, password)
+            .map_err(|e| format!("Invalid client certificate: {}", e));
+    }
+
+    let key_path = settings
+        .client_key_path
+        .as_deref()
+        .ok_or("A PEM client certificate needs client_key_path")?;
+    let key = read_file(key_path, "client key")?;
+    reqwest::Identity::from_pkcs8_pem(&cert, &key)
+        .map_err(|e| format!("Invalid client certificate or key: {}", e))
+}
+
+/// Configure proxy and TLS trust on a client builder
+pub(crate) fn apply(
+    mut builder: reqwest::ClientBuilder,
+    settings: &TransportSettings,
+) -> Result<reqwest::ClientBuilder, String> {
+    let proxy_url = settings.proxy_url.as_deref().filter(|url| !url.is_empty());
+    if let Some(proxy_url) = proxy_url {
+        let proxy = reqwest::Proxy::all(proxy_url)
+            .map_err(|e| format!("Invalid proxy URL {}: {}", proxy_url, e))?
+            .no_proxy(
+                settings
+                    .no_proxy
+                    .as_deref()
+                    .and_then(reqwest::NoProxy::from_string),
+            );
+        builder = builder.proxy(proxy);
+    } else if !settings.use_system_proxy.unwrap_or(false) {
+        // Direct by default so localhost requests never go through a system proxy
+        builder = builder.no_proxy();
+    }
+
+    if let Some(path) = settings.ca_bundle_path.as_deref().filter(|p| !p.is_empty()) {
+        let certs = reqwest::Certificate::from_pem_bundle(&read_file(path, "CA bundle")?)
+            .map_err(|e| format!("Invalid CA bundle {}: {}", path, e))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    if let Some(path) = settings
+        .client_cert_path
+        .as_deref()
+        .filter(|p| !p.is_empty())
+    {
+        builder = builder.identity(client_identity(path, settings)?);
+    }
+
+    if settings.accept_invalid_certs.unwrap_or(false) {
+        builder = builder
+            .danger_accept_invalid_certs(true)
+            .danger_accept_invalid_hostnames(true);
+    }
+
+    Ok(builder)
+}
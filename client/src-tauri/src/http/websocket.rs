@@ -0,0 +1,415 @@
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use super::collections::{load_environments, substitute_variables};
+use super::HttpHeader;
+
+// ============================================================================
+// WEBSOCKET TYPES
+// ============================================================================
+
+/// Every frame sent or received on a console connection
+pub const WS_MESSAGE_EVENT: &str = "http://ws-message";
+
+/// Emitted once when a console connection ends, whoever closed it
+pub const WS_CLOSED_EVENT: &str = "http://ws-closed";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait for the server to answer our close frame
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Frames kept per connection for ws_history
+const MAX_HISTORY: usize = 1000;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum WsDirection {
+    Sent,
+    Received,
+}
+
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum WsFrameKind {
+    Text,
+    Binary,
+    Ping,
+    Pong,
+    Close,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct WsMessage {
+    pub connection_id: String,
+    pub direction: WsDirection,
+    pub kind: WsFrameKind,
+    /// Text as is, binary/ping/pong payloads base64 encoded, the reason for close frames
+    pub data: String,
+    pub size_bytes: usize,
+    /// Close code, for close frames only
+    pub close_code: Option<u16>,
+    /// Milliseconds since epoch
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WsConnectionInfo {
+    pub connection_id: String,
+    pub url: String,
+    /// The subprotocol the server picked, if any
+    pub protocol: Option<String>,
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct WsClosed {
+    pub connection_id: String,
+    pub code: Option<u16>,
+    pub reason: String,
+}
+
+/// A frame to send, from the frontend
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsOutgoing {
+    Text {
+        data: String,
+    },
+    /// `data` is base64
+    Binary {
+        data: String,
+    },
+    Ping {
+        data: Option<String>,
+    },
+}
+
+struct WsConnection {
+    window_label: String,
+    outgoing: mpsc::UnboundedSender<Message>,
+    history: Arc<Mutex<VecDeque<WsMessage>>>,
+}
+
+/// Open console connections, keyed by connection id
+#[derive(Default)]
+pub struct WsRegistry {
+    connections: Mutex<HashMap<String, WsConnection>>,
+}
+
+impl WsRegistry {
+    /// Close every connection opened by a window (e.g. when it is closed)
+    pub fn close_window(&self, label: &str) {
+        let Ok(connections) = self.connections.lock() else {
+            return;
+        };
+
+        for connection in connections.values() {
+            if connection.window_label == label {
+                let _ = connection.outgoing.send(Message::Close(None));
+            }
+        }
+    }
+
+    fn send(&self, connection_id: &str, message: Message) -> Result<(), String> {
+        let connections = self
+            .connections
+            .lock()
+            .map_err(|_| "WebSocket registry is poisoned".to_string())?;
+        let connection = connections
+            .get(connection_id)
+            .ok_or_else(|| format!("No WebSocket connection: {}", connection_id))?;
+
+        connection
+            .outgoing
+            .send(message)
+            .map_err(|_| "Connection is closed".to_string())
+    }
+
+    fn remove(&self, connection_id: &str) {
+        if let Ok(mut connections) = self.connections.lock() {
+            connections.remove(connection_id);
+        }
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn encode(data: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(data)
+}
+
+fn frame(connection_id: &str, direction: WsDirection, message: &Message) -> Option<WsMessage> {
+    let (kind, data, size_bytes, close_code) = match message {
+        Message::Text(text) => (WsFrameKind::Text, text.clone(), text.len(), None),
+        Message::Binary(data) => (WsFrameKind::Binary, encode(data), data.len(), None),
+        Message::Ping(data) => (WsFrameKind::Ping, encode(data), data.len(), None),
+        Message::Pong(data) => (WsFrameKind::Pong, encode(data), data.len(), None),
+        Message::Close(close) => (
+            WsFrameKind::Close,
+            close
+                .as_ref()
+                .map(|c| c.reason.to_string())
+                .unwrap_or_default(),
+            0,
+            close.as_ref().map(|c| u16::from(c.code)),
+        ),
+        Message::Frame(_) => return None,
+    };
+
+    Some(WsMessage {
+        connection_id: connection_id.to_string(),
+        direction,
+        kind,
+        data,
+        size_bytes,
+        close_code,
+        timestamp: chrono::Utc::now().timestamp_millis(),
+    })
+}
+
+fn record(
+    window: &Window,
+    history: &Mutex<VecDeque<WsMessage>>,
+    connection_id: &str,
+    direction: WsDirection,
+    message: &Message,
+) {
+    let Some(entry) = frame(connection_id, direction, message) else {
+        return;
+    };
+
+    if let Ok(mut history) = history.lock() {
+        if history.len() >= MAX_HISTORY {
+            history.pop_front();
+        }
+        history.push_back(entry.clone());
+    }
+    let _ = window.emit_to(window.label(), WS_MESSAGE_EVENT, entry);
+}
+
+/// Forward frames both ways until either side closes
+async fn run_socket(
+    window: Window,
+    connection_id: String,
+    mut socket: Socket,
+    mut outgoing: mpsc::UnboundedReceiver<Message>,
+    history: Arc<Mutex<VecDeque<WsMessage>>>,
+) {
+    let mut closed = WsClosed {
+        connection_id: connection_id.clone(),
+        code: None,
+        reason: String::new(),
+    };
+    let mut close_deadline = None;
+
+    loop {
+        tokio::select! {
+            message = outgoing.recv(), if close_deadline.is_none() => {
+                // The registry entry is gone: nobody can send any more
+                let message = message.unwrap_or(Message::Close(None));
+                let closing = matches!(message, Message::Close(_));
+                if let Err(e) = socket.send(message.clone()).await {
+                    closed.reason = format!("Failed to send: {}", e);
+                    break;
+                }
+                record(&window, &history, &connection_id, WsDirection::Sent, &message);
+                if closing {
+                    close_deadline = Some(tokio::time::Instant::now() + CLOSE_TIMEOUT);
+                }
+            }
+            incoming = socket.next() => {
+                let message = match incoming {
+                    Some(Ok(message)) => message,
+                    Some(Err(e)) => {
+                        closed.reason = e.to_string();
+                        break;
+                    }
+                    None => break,
+                };
+                record(&window, &history, &connection_id, WsDirection::Received, &message);
+                if let Message::Close(close) = &message {
+                    if let Some(close) = close {
+                        closed.code = Some(u16::from(close.code));
+                        closed.reason = close.reason.to_string();
+                    }
+                    // Keep reading so tungstenite can finish the handshake
+                    close_deadline.get_or_insert(tokio::time::Instant::now() + CLOSE_TIMEOUT);
+                }
+            }
+            _ = tokio::time::sleep_until(close_deadline.unwrap_or_else(tokio::time::Instant::now)),
+                if close_deadline.is_some() => break,
+        }
+    }
+
+    window.state::<WsRegistry>().remove(&connection_id);
+    let _ = window.emit_to(window.label(), WS_CLOSED_EVENT, closed);
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Open a WebSocket connection for the testing console.
+///
+/// The URL and headers go through the active environment. Frames are
+/// reported as `http://ws-message` events on the calling window and the end
+/// of the connection as `http://ws-closed`.
+#[tauri::command]
+pub async fn ws_connect(
+    app: AppHandle,
+    window: Window,
+    registry: State<'_, WsRegistry>,
+    url: String,
+    headers: Option<Vec<HttpHeader>>,
+    protocols: Option<Vec<String>>,
+) -> Result<WsConnectionInfo, String> {
+    let variables = load_environments(&app)?.active_variables();
+    let url = substitute_variables(&url, &variables);
+
+    let mut request = url
+        .as_str()
+        .into_client_request()
+        .map_err(|e| format!("Invalid WebSocket URL: {}", e))?;
+    for header in headers.unwrap_or_default() {
+        if !header.enabled || header.key.is_empty() {
+            continue;
+        }
+        let name = HeaderName::from_bytes(substitute_variables(&header.key, &variables).as_bytes())
+            .map_err(|e| format!("Invalid header name {}: {}", header.key, e))?;
+        let value = HeaderValue::from_str(&substitute_variables(&header.value, &variables))
+            .map_err(|e| format!("Invalid value for header {}: {}", header.key, e))?;
+        request.headers_mut().append(name, value);
+    }
+    let protocols = protocols.unwrap_or_default();
+    if !protocols.is_empty() {
+        let value = HeaderValue::from_str(&protocols.join(", "))
+            .map_err(|e| format!("Invalid subprotocol: {}", e))?;
+        request
+            .headers_mut()
+            .insert("Sec-WebSocket-Protocol", value);
+    }
+
+    let (socket, response) =
+        tokio::time::timeout(CONNECT_TIMEOUT, tokio_tungstenite::connect_async(request))
+            .await
+            .map_err(|_| format!("Timed out connecting to {}", url))?
+            .map_err(|e| format!("Failed to connect to {}: {}", url, e))?;
+
+    let response_headers: HashMap<String, String> = response
+        .headers()
+        .iter()
+        .filter_map(|(key, value)| Some((key.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    let protocol = response
+        .headers()
+        .get("Sec-WebSocket-Protocol")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+
+    let connection_id = uuid::Uuid::new_v4().to_string();
+    let (outgoing, outgoing_rx) = mpsc::unbounded_channel();
+    let history = Arc::new(Mutex::new(VecDeque::new()));
+    registry
+        .connections
+        .lock()
+        .map_err(|_| "WebSocket registry is poisoned".to_string())?
+        .insert(
+            connection_id.clone(),
+            WsConnection {
+                window_label: window.label().to_string(),
+                outgoing,
+                history: history.clone(),
+            },
+        );
+
+    tokio::spawn(run_socket(
+        window,
+        connection_id.clone(),
+        socket,
+        outgoing_rx,
+        history,
+    ));
+
+    Ok(WsConnectionInfo {
+        connection_id,
+        url,
+        protocol,
+        status: response.status().as_u16(),
+        headers: response_headers,
+    })
+}
+
+#[tauri::command]
+pub async fn ws_send(
+    registry: State<'_, WsRegistry>,
+    connection_id: String,
+    message: WsOutgoing,
+) -> Result<(), String> {
+    let decode = |data: &str| {
+        base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| format!("Invalid base64 data: {}", e))
+    };
+
+    let message = match message {
+        WsOutgoing::Text { data } => Message::Text(data),
+        WsOutgoing::Binary { data } => Message::Binary(decode(&data)?),
+        WsOutgoing::Ping { data } => Message::Ping(decode(data.as_deref().unwrap_or_default())?),
+    };
+    registry.send(&connection_id, message)
+}
+
+/// Start the close handshake; `http://ws-closed` follows once it completes
+#[tauri::command]
+pub async fn ws_close(
+    registry: State<'_, WsRegistry>,
+    connection_id: String,
+    code: Option<u16>,
+    reason: Option<String>,
+) -> Result<(), String> {
+    let frame = CloseFrame {
+        code: CloseCode::from(code.unwrap_or(1000)),
+        reason: reason.unwrap_or_default().into(),
+    };
+    registry.send(&connection_id, Message::Close(Some(frame)))
+}
+
+/// Frames exchanged so far on an open connection, oldest first
+#[tauri::command]
+pub async fn ws_history(
+    registry: State<'_, WsRegistry>,
+    connection_id: String,
+) -> Result<Vec<WsMessage>, String> {
+    let connections = registry
+        .connections
+        .lock()
+        .map_err(|_| "WebSocket registry is poisoned".to_string())?;
+    let connection = connections
+        .get(&connection_id)
+        .ok_or_else(|| format!("No WebSocket connection: {}", connection_id))?;
+
+    let history = connection
+        .history
+        .lock()
+        .map_err(|_| "WebSocket history is poisoned".to_string())?;
+    Ok(history.iter().cloned().collect())
+}
@@ -0,0 +1,177 @@
+use git2::Repository;
+use globset::GlobSet;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::search::build_glob_set;
+
+// ============================================================================
+// IGNORE TYPES
+// ============================================================================
+
+/// Workspace ignore preferences, next to the formatter overrides
+const PREFERENCES_PATH: &str = ".codecollab/ignore.json";
+
+/// Names hidden whatever the preferences say
+const ALWAYS_IGNORED: &[&str] = &[".git"];
+
+/// Names hidden unless the workspace clears them
+const DEFAULT_IGNORED_NAMES: &[&str] = &[
+    "node_modules",
+    "target",
+    "__pycache__",
+    ".next",
+    "dist",
+    "build",
+];
+
+/// What the file tree and search leave out of a workspace
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct IgnorePreferences {
+    /// Show dotfiles and dot-directories (`.git` stays hidden)
+    pub show_hidden: bool,
+    /// Hide what the repository's .gitignore files ignore
+    pub respect_gitignore: bool,
+    /// File and directory names hidden anywhere in the tree
+    pub ignored_names: Vec<String>,
+    /// Extra globs relative to the workspace root, e.g. `**/*.min.js`
+    pub exclude: Vec<String>,
+}
+
+impl Default for IgnorePreferences {
+    fn default() -> Self {
+        IgnorePreferences {
+            show_hidden: false,
+            respect_gitignore: true,
+            ignored_names: DEFAULT_IGNORED_NAMES
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            exclude: Vec::new(),
+        }
+    }
+}
+
+/// Ignore preferences of one workspace, ready to test paths against
+pub struct IgnoreFilter {
+    root: PathBuf,
+    preferences: IgnorePreferences,
+    exclude: Option<GlobSet>,
+    repo: Option<Repository>,
+}
+
+impl IgnoreFilter {
+    /// Load the preferences of the workspace at `root`
+    pub fn load(root: &Path) -> Result<Self, String> {
+        let preferences = read_preferences(root)?;
+        let exclude = build_glob_set(&preferences.exclude)?;
+        let repo = preferences
+            .respect_gitignore
+            .then(|| Repository::discover(root).ok())
+            .flatten()
+            .filter(|repo| repo.workdir().is_some());
+
+        Ok(IgnoreFilter {
+            root: root.to_path_buf(),
+            preferences,
+            exclude,
+            repo,
+        })
+    }
+
+    /// Whether a file or directory is left out of listings and search
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy())
+            .unwrap_or_default();
+        if ALWAYS_IGNORED.contains(&name.as_ref())
+            || (!self.preferences.show_hidden && name.starts_with('.'))
+            || self.preferences.ignored_names.iter().any(|n| *n == name)
+        {
+            return true;
+        }
+
+        if let Some(exclude) = &self.exclude {
+            if exclude.is_match(path.strip_prefix(&self.root).unwrap_or(path)) {
+                return true;
+            }
+        }
+
+        let Some(repo) = &self.repo else {
+            return false;
+        };
+        let Some(relative) = repo.workdir().and_then(|w| path.strip_prefix(w).ok()) else {
+            return false;
+        };
+        // libgit2 only applies directory-only patterns (`build/`) with a trailing slash
+        let relative = if is_dir {
+            relative.join("")
+        } else {
+            relative.to_path_buf()
+        };
+        repo.is_path_ignored(&relative).unwrap_or(false)
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn read_preferences(root: &Path) -> Result<IgnorePreferences, String> {
+    let path = root.join(PREFERENCES_PATH);
+    if !path.exists() {
+        return Ok(IgnorePreferences::default());
+    }
+
+    let json =
+        std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_slice(&json).map_err(|e| format!("Invalid {}: {}", path.display(), e))
+}
+
+/// Workspace a path belongs to: the given root, else the nearest folder with
+/// ignore preferences, else the git work tree, else the path itself
+pub fn workspace_root(path: &Path, root: Option<&str>) -> PathBuf {
+    if let Some(root) = root {
+        return PathBuf::from(root);
+    }
+    if let Some(dir) = path
+        .ancestors()
+        .find(|dir| dir.join(PREFERENCES_PATH).is_file())
+    {
+        return dir.to_path_buf();
+    }
+    Repository::discover(path)
+        .ok()
+        .and_then(|repo| repo.workdir().map(Path::to_path_buf))
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Ignore preferences of a workspace, or the defaults when it has none
+#[tauri::command]
+pub async fn get_ignore_preferences(root_path: String) -> Result<IgnorePreferences, String> {
+    read_preferences(Path::new(&root_path))
+}
+
+/// Save ignore preferences to the workspace's `.codecollab/ignore.json`
+#[tauri::command]
+pub async fn set_ignore_preferences(
+    root_path: String,
+    preferences: IgnorePreferences,
+) -> Result<(), String> {
+    build_glob_set(&preferences.exclude)?;
+
+    let path = Path::new(&root_path).join(PREFERENCES_PATH);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let json = serde_json::to_vec_pretty(&preferences)
+        .map_err(|e| format!("Failed to serialize ignore preferences: {}", e))?;
+    crate::atomic_write(&path, &json)
+}
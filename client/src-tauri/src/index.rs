@@ -0,0 +1,514 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager, State};
+use walkdir::WalkDir;
+
+use crate::ignore::IgnoreFilter;
+use crate::search::{candidate_files, read_text_file};
+use crate::watcher::{FsChangeEvent, FsChangeKind};
+
+// ============================================================================
+// INDEX TYPES
+// ============================================================================
+
+/// Event emitted when a workspace index has been (re)built
+pub const INDEX_READY_EVENT: &str = "index://ready";
+
+const DEFAULT_QUERY_LIMIT: usize = 50;
+
+/// Symbols are only extracted from files up to this size
+const MAX_SYMBOL_FILE_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SymbolKind {
+    Function,
+    Class,
+    Struct,
+    Enum,
+    Interface,
+    Trait,
+    Type,
+    Module,
+    Constant,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// 1-based line number
+    pub line: usize,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexQueryKind {
+    #[default]
+    Files,
+    Symbols,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IndexMatch {
+    pub path: String,
+    /// Path relative to the indexed root
+    pub relative_path: String,
+    pub score: i64,
+    /// Character positions of the matched query characters, in the file's
+    /// relative path for file queries and in the symbol name for symbol ones
+    pub positions: Vec<usize>,
+    /// The matched symbol, for symbol queries
+    pub symbol: Option<Symbol>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct IndexStats {
+    pub root: String,
+    pub files: usize,
+    pub symbols: usize,
+    pub elapsed_ms: u64,
+}
+
+struct IndexedFile {
+    relative: String,
+    symbols: Vec<Symbol>,
+}
+
+/// File names and symbols of one workspace root
+struct WorkspaceIndex {
+    root: PathBuf,
+    filter: IgnoreFilter,
+    files: HashMap<PathBuf, IndexedFile>,
+}
+
+/// In-memory indexes keyed by root, shared by every window. They are kept
+/// fresh from the watcher's change events, so a root must also be watched
+/// (open_folder and workspace roots are).
+#[derive(Default)]
+pub struct IndexRegistry {
+    indexes: Mutex<HashMap<PathBuf, Arc<Mutex<WorkspaceIndex>>>>,
+}
+
+impl IndexRegistry {
+    fn get(&self, root: &Path) -> Result<Arc<Mutex<WorkspaceIndex>>, String> {
+        self.indexes
+            .lock()
+            .map_err(|_| "Index registry is poisoned".to_string())?
+            .get(root)
+            .cloned()
+            .ok_or_else(|| format!("Workspace is not indexed: {}", root.display()))
+    }
+
+    /// Index of the root containing `path`, if any
+    fn containing(&self, path: &Path) -> Option<Arc<Mutex<WorkspaceIndex>>> {
+        self.indexes
+            .lock()
+            .ok()?
+            .iter()
+            .filter(|(root, _)| path.starts_with(root))
+            .max_by_key(|(root, _)| root.as_os_str().len())
+            .map(|(_, index)| index.clone())
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// ctags-like definitions per language; the `name` group is the symbol
+fn symbol_patterns() -> &'static [(&'static [&'static str], SymbolKind, Regex)] {
+    static PATTERNS: OnceLock<Vec<(&'static [&'static str], SymbolKind, Regex)>> = OnceLock::new();
+    const JS: &[&str] = &[
+        "javascript",
+        "javascriptreact",
+        "typescript",
+        "typescriptreact",
+        "vue",
+    ];
+    const TS: &[&str] = &["typescript", "typescriptreact", "vue"];
+
+    PATTERNS.get_or_init(|| {
+        let table: &[(&[&str], SymbolKind, &str)] = &[
+            (&["rust"], SymbolKind::Function, r"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:const\s+)?(?:async\s+)?(?:unsafe\s+)?(?:extern\s+\S+\s+)?fn\s+(?P<name>\w+)"),
+            (&["rust"], SymbolKind::Struct, r"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:struct|union)\s+(?P<name>\w+)"),
+            (&["rust"], SymbolKind::Enum, r"^\s*(?:pub(?:\([^)]*\))?\s+)?enum\s+(?P<name>\w+)"),
+            (&["rust"], SymbolKind::Trait, r"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:unsafe\s+)?trait\s+(?P<name>\w+)"),
+            (&["rust"], SymbolKind::Type, r"^\s*(?:pub(?:\([^)]*\))?\s+)?type\s+(?P<name>\w+)"),
+            (&["rust"], SymbolKind::Module, r"^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+(?P<name>\w+)"),
+            (&["rust"], SymbolKind::Constant, r"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:const|static)\s+(?:mut\s+)?(?P<name>[A-Z_][A-Z0-9_]*)\s*:"),
+            (&["rust"], SymbolKind::Function, r"^\s*macro_rules!\s*(?P<name>\w+)"),
+            (JS, SymbolKind::Function, r"^\s*(?:export\s+)?(?:default\s+)?(?:async\s+)?function\s*\*?\s*(?P<name>[\w$]+)"),
+            (JS, SymbolKind::Function, r"^\s*(?:export\s+)?(?:const|let|var)\s+(?P<name>[\w$]+)\s*(?::[^=]+)?=\s*(?:async\s+)?(?:function\b|\([^)]*\)\s*(?::[^=]+)?=>|[\w$]+\s*=>)"),
+            (JS, SymbolKind::Class, r"^\s*(?:export\s+)?(?:default\s+)?(?:abstract\s+)?class\s+(?P<name>[\w$]+)"),
+            (TS, SymbolKind::Interface, r"^\s*(?:export\s+)?(?:declare\s+)?interface\s+(?P<name>[\w$]+)"),
+            (TS, SymbolKind::Type, r"^\s*(?:export\s+)?(?:declare\s+)?type\s+(?P<name>[\w$]+)\s*(?:<[^=]*>)?\s*="),
+            (TS, SymbolKind::Enum, r"^\s*(?:export\s+)?(?:declare\s+)?(?:const\s+)?enum\s+(?P<name>[\w$]+)"),
+            (&["python"], SymbolKind::Function, r"^\s*(?:async\s+)?def\s+(?P<name>\w+)"),
+            (&["python"], SymbolKind::Class, r"^\s*class\s+(?P<name>\w+)"),
+            (&["go"], SymbolKind::Function, r"^func\s+(?:\([^)]*\)\s*)?(?P<name>\w+)"),
+            (&["go"], SymbolKind::Struct, r"^type\s+(?P<name>\w+)\s+struct\b"),
+            (&["go"], SymbolKind::Interface, r"^type\s+(?P<name>\w+)\s+interface\b"),
+            (&["java", "csharp", "kotlin", "scala", "php", "swift", "dart"], SymbolKind::Class, r"^\s*(?:(?:public|private|protected|internal|abstract|final|sealed|static|open|data|partial)\s+)*class\s+(?P<name>\w+)"),
+            (&["java", "csharp", "kotlin", "php", "swift", "dart"], SymbolKind::Interface, r"^\s*(?:(?:public|private|protected|internal|abstract|sealed)\s+)*(?:interface|protocol)\s+(?P<name>\w+)"),
+            (&["java", "csharp", "kotlin", "swift", "dart"], SymbolKind::Enum, r"^\s*(?:(?:public|private|protected|internal)\s+)*enum\s+(?:class\s+)?(?P<name>\w+)"),
+            (&["kotlin", "swift", "php", "scala"], SymbolKind::Function, r"^\s*(?:(?:public|private|protected|internal|override|static|suspend|open)\s+)*(?:fun|func|function|def)\s+(?P<name>\w+)"),
+            (&["c", "cpp"], SymbolKind::Struct, r"^\s*(?:typedef\s+)?(?:struct|union)\s+(?P<name>\w+)\s*\{"),
+            (&["c", "cpp"], SymbolKind::Enum, r"^\s*(?:typedef\s+)?enum\s+(?:class\s+)?(?P<name>\w+)"),
+            (&["cpp"], SymbolKind::Class, r"^\s*class\s+(?P<name>\w+)\s*(?:final\s*)?[:{]"),
+            (&["c", "cpp"], SymbolKind::Function, r"^[A-Za-z_][\w\s\*&:<>,]*?[\s\*&](?P<name>[A-Za-z_]\w*)\s*\([^;]*$"),
+            (&["ruby"], SymbolKind::Function, r"^\s*def\s+(?:self\.)?(?P<name>\w+[?!]?)"),
+            (&["ruby"], SymbolKind::Class, r"^\s*class\s+(?P<name>\w+)"),
+            (&["ruby"], SymbolKind::Module, r"^\s*module\s+(?P<name>\w+)"),
+            (&["shellscript"], SymbolKind::Function, r"^\s*(?:function\s+)?(?P<name>[\w-]+)\s*\(\)\s*\{?"),
+        ];
+
+        table
+            .iter()
+            .map(|(languages, kind, pattern)| {
+                (
+                    *languages,
+                    *kind,
+                    Regex::new(pattern).expect("valid symbol pattern"),
+                )
+            })
+            .collect()
+    })
+}
+
+fn extract_symbols(path: &Path, content: &str) -> Vec<Symbol> {
+    let language = collab_protocol::detect_language_with_content(&path.to_string_lossy(), content);
+    let patterns: Vec<_> = symbol_patterns()
+        .iter()
+        .filter(|(languages, _, _)| languages.contains(&language.as_str()))
+        .collect();
+    if patterns.is_empty() {
+        return Vec::new();
+    }
+
+    let mut symbols = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        // The first matching pattern wins, so `export const f = () =>` is
+        // not also reported as something else
+        let found = patterns.iter().find_map(|(_, kind, regex)| {
+            regex
+                .captures(line)
+                .and_then(|c| c.name("name"))
+                .map(|name| (*kind, name.as_str()))
+        });
+        if let Some((kind, name)) = found {
+            if !matches!(name, "if" | "for" | "while" | "switch" | "return" | "else") {
+                symbols.push(Symbol {
+                    name: name.to_string(),
+                    kind,
+                    line: index + 1,
+                });
+            }
+        }
+    }
+    symbols
+}
+
+fn index_file(root: &Path, path: &Path) -> IndexedFile {
+    let symbols = std::fs::metadata(path)
+        .ok()
+        .filter(|m| m.len() <= MAX_SYMBOL_FILE_SIZE)
+        .and_then(|_| read_text_file(path))
+        .map(|content| extract_symbols(path, &content))
+        .unwrap_or_default();
+
+    IndexedFile {
+        relative: path
+            .strip_prefix(root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/"),
+        symbols,
+    }
+}
+
+fn build(root: &Path) -> Result<WorkspaceIndex, String> {
+    let filter = IgnoreFilter::load(root)?;
+    let files = candidate_files(root, &filter, &None, &None)
+        .map(|entry| {
+            let path = entry.into_path();
+            let file = index_file(root, &path);
+            (path, file)
+        })
+        .collect();
+
+    Ok(WorkspaceIndex {
+        root: root.to_path_buf(),
+        filter,
+        files,
+    })
+}
+
+fn is_boundary(previous: Option<char>, current: char) -> bool {
+    match previous {
+        None => true,
+        Some(p) => {
+            matches!(p, '/' | '\\' | '_' | '-' | '.' | ' ')
+                || (p.is_lowercase() && current.is_uppercase())
+        }
+    }
+}
+
+/// Fuzzy match every query character in order; higher scores for
+/// consecutive runs, word starts and short candidates. Returns the score
+/// and the matched character positions.
+pub(crate) fn fuzzy_score(query: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if query.is_empty() {
+        return None;
+    }
+
+    let chars: Vec<char> = candidate.chars().collect();
+    let mut positions: Vec<usize> = Vec::with_capacity(query.len());
+    let mut score = 0i64;
+    let mut next = 0;
+
+    let matches = |i: usize, wanted: char| chars[i].to_lowercase().eq(std::iter::once(wanted));
+    for &wanted in &query {
+        // Continue a run, else prefer the next word start, else any occurrence
+        let at = if next < chars.len() && !positions.is_empty() && matches(next, wanted) {
+            next
+        } else {
+            (next..chars.len())
+                .find(|&i| {
+                    matches(i, wanted) && is_boundary(i.checked_sub(1).map(|p| chars[p]), chars[i])
+                })
+                .or_else(|| (next..chars.len()).find(|&i| matches(i, wanted)))?
+        };
+
+        score += 1;
+        if positions.last().is_some_and(|last| at == last + 1) {
+            score += 8;
+        }
+        if is_boundary(at.checked_sub(1).map(|p| chars[p]), chars[at]) {
+            score += 10;
+        }
+        positions.push(at);
+        next = at + 1;
+    }
+
+    // Matches inside the file name beat matches spread over directories
+    let name_start = candidate
+        .rfind('/')
+        .map_or(0, |i| candidate[..=i].chars().count());
+    if positions[0] >= name_start {
+        score += 20;
+    }
+    score -= (chars.len() / 8) as i64;
+
+    Some((score, positions))
+}
+
+fn query_files(index: &WorkspaceIndex, query: &str, limit: usize) -> Vec<IndexMatch> {
+    let mut matches: Vec<IndexMatch> = index
+        .files
+        .iter()
+        .filter_map(|(path, file)| {
+            let (score, positions) = fuzzy_score(query, &file.relative)?;
+            Some(IndexMatch {
+                path: path.to_string_lossy().to_string(),
+                relative_path: file.relative.clone(),
+                score,
+                positions,
+                symbol: None,
+            })
+        })
+        .collect();
+    rank(&mut matches, limit);
+    matches
+}
+
+fn query_symbols(index: &WorkspaceIndex, query: &str, limit: usize) -> Vec<IndexMatch> {
+    let mut matches: Vec<IndexMatch> = index
+        .files
+        .iter()
+        .flat_map(|(path, file)| {
+            file.symbols.iter().filter_map(move |symbol| {
+                let (score, positions) = fuzzy_score(query, &symbol.name)?;
+                Some(IndexMatch {
+                    path: path.to_string_lossy().to_string(),
+                    relative_path: file.relative.clone(),
+                    score,
+                    positions,
+                    symbol: Some(symbol.clone()),
+                })
+            })
+        })
+        .collect();
+    rank(&mut matches, limit);
+    matches
+}
+
+/// Best scores first, shorter paths breaking ties
+fn rank(matches: &mut Vec<IndexMatch>, limit: usize) {
+    matches.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(a.relative_path.len().cmp(&b.relative_path.len()))
+            .then(a.relative_path.cmp(&b.relative_path))
+    });
+    matches.truncate(limit);
+}
+
+/// Files matching a query from the index of the root, when it has one
+pub(crate) fn indexed_files(
+    registry: &IndexRegistry,
+    root: &Path,
+    query: &str,
+    limit: usize,
+) -> Option<Vec<IndexMatch>> {
+    let index = registry.get(root).ok()?;
+    let index = index.lock().ok()?;
+    Some(query_files(&index, query, limit))
+}
+
+impl WorkspaceIndex {
+    fn remove(&mut self, path: &Path) {
+        self.files.remove(path);
+        self.files.retain(|file, _| !file.starts_with(path));
+    }
+
+    fn add(&mut self, path: &Path) {
+        if path.is_dir() {
+            if self.filter.is_ignored(path, true) {
+                return;
+            }
+            let files: Vec<PathBuf> = WalkDir::new(path)
+                .into_iter()
+                .filter_entry(|e| {
+                    e.depth() == 0 || !self.filter.is_ignored(e.path(), e.file_type().is_dir())
+                })
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .map(|e| e.into_path())
+                .collect();
+            for file in files {
+                let indexed = index_file(&self.root, &file);
+                self.files.insert(file, indexed);
+            }
+        } else if path.is_file() && !self.filter.is_ignored(path, false) {
+            let indexed = index_file(&self.root, path);
+            self.files.insert(path.to_path_buf(), indexed);
+        }
+    }
+
+    fn stats(&self, started: Instant) -> IndexStats {
+        IndexStats {
+            root: self.root.to_string_lossy().to_string(),
+            files: self.files.len(),
+            symbols: self.files.values().map(|f| f.symbols.len()).sum(),
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+/// Apply a watcher change to the index of the root it belongs to
+pub(crate) fn on_fs_change(app: &AppHandle, change: &FsChangeEvent) {
+    let Some(registry) = app.try_state::<IndexRegistry>() else {
+        return;
+    };
+    let path = PathBuf::from(&change.path);
+    let Some(index) = registry.containing(&path) else {
+        return;
+    };
+    let Ok(mut index) = index.lock() else {
+        return;
+    };
+
+    match change.kind {
+        FsChangeKind::Created | FsChangeKind::Modified => {
+            index.remove(&path);
+            index.add(&path);
+        }
+        FsChangeKind::Deleted => index.remove(&path),
+        FsChangeKind::Renamed => {
+            if let Some(old_path) = &change.old_path {
+                index.remove(Path::new(old_path));
+            }
+            index.add(&path);
+        }
+    }
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Build (or rebuild) the in-memory index of a workspace root: every file
+/// the workspace does not ignore plus the symbols defined in its source
+/// files. Emits `index://ready` with the same stats once done.
+#[tauri::command]
+pub async fn build_workspace_index(
+    app: AppHandle,
+    registry: State<'_, IndexRegistry>,
+    root_path: String,
+) -> Result<IndexStats, String> {
+    let root = PathBuf::from(&root_path);
+    if !root.is_dir() {
+        return Err(format!("Path is not a directory: {}", root_path));
+    }
+
+    let started = Instant::now();
+    let index = tokio::task::spawn_blocking(move || build(&root))
+        .await
+        .map_err(|e| format!("Index task failed: {}", e))??;
+    let stats = index.stats(started);
+
+    registry
+        .indexes
+        .lock()
+        .map_err(|_| "Index registry is poisoned".to_string())?
+        .insert(index.root.clone(), Arc::new(Mutex::new(index)));
+
+    let _ = app.emit(INDEX_READY_EVENT, stats.clone());
+    Ok(stats)
+}
+
+/// Fuzzy search the index of a root for files (by relative path) or symbols
+/// (by name), best matches first
+#[tauri::command]
+pub async fn query_index(
+    registry: State<'_, IndexRegistry>,
+    root_path: String,
+    query: String,
+    kind: Option<IndexQueryKind>,
+    limit: Option<usize>,
+) -> Result<Vec<IndexMatch>, String> {
+    let index = registry.get(Path::new(&root_path))?;
+    let index = index
+        .lock()
+        .map_err(|_| "Workspace index is poisoned".to_string())?;
+    let limit = limit.unwrap_or(DEFAULT_QUERY_LIMIT);
+
+    Ok(match kind.unwrap_or_default() {
+        IndexQueryKind::Files => query_files(&index, &query, limit),
+        IndexQueryKind::Symbols => query_symbols(&index, &query, limit),
+    })
+}
+
+/// Drop the index of a root, e.g. when its folder is closed
+#[tauri::command]
+pub async fn drop_workspace_index(
+    registry: State<'_, IndexRegistry>,
+    root_path: String,
+) -> Result<(), String> {
+    registry
+        .indexes
+        .lock()
+        .map_err(|_| "Index registry is poisoned".to_string())?
+        .remove(Path::new(&root_path));
+    Ok(())
+}
@@ -1,8 +1,41 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use tauri::Manager;
 use walkdir::WalkDir;
 
+mod archive;
+mod autosave;
+mod backup;
+mod clipboard;
+mod collab;
+mod diff;
+mod dotenv;
+mod downloads;
+mod encoding;
+mod format;
+mod git;
+mod host;
+mod http;
+mod ignore;
+mod index;
+mod lsp;
+mod preview;
+mod recent;
+mod replace;
+mod search;
+mod secrets;
+mod session;
+mod settings;
+mod tasks;
+mod templates;
+mod terminal;
+mod todos;
+mod transfer;
+mod trash_bin;
+mod watcher;
+mod workspace;
+
 // ============================================================================
 // FILE SYSTEM TYPES
 // ============================================================================
@@ -15,6 +48,14 @@ pub struct FileNode {
     pub is_dir: bool,
     pub children: Option<Vec<FileNode>>,
     pub extension: Option<String>,
+    /// Size, modification time (ms since epoch) and read-only flag; only
+    /// filled in on request (read_directory with_stats, stat_paths)
+    #[serde(default)]
+    pub size_bytes: Option<u64>,
+    #[serde(default)]
+    pub mtime_ms: Option<u64>,
+    #[serde(default)]
+    pub readonly: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -22,43 +63,83 @@ pub struct FileContent {
     pub path: String,
     pub content: String,
     pub language: String,
+    /// Hash of the content as read, passed back to write_file for conflict detection
+    pub hash: String,
+    /// Modification time as read (milliseconds since epoch)
+    pub mtime_ms: Option<u64>,
 }
 
-// ============================================================================
-// HTTP REQUEST TYPES
-// ============================================================================
+#[derive(Debug, Serialize)]
+pub struct PathStat {
+    pub path: String,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    /// 0 for directories
+    pub size_bytes: u64,
+    /// Milliseconds since epoch
+    pub mtime_ms: Option<u64>,
+    pub created_ms: Option<u64>,
+    pub readonly: bool,
+    /// Unix permission bits (e.g. 0o644); None on Windows
+    pub mode: Option<u32>,
+    /// None when the path is unchanged, ignored, a folder or not in a repository
+    pub git_status: Option<git::FileStatus>,
+}
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct HttpHeader {
-    pub key: String,
-    pub value: String,
-    pub enabled: bool,
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileMetadata {
+    pub path: String,
+    pub size_bytes: u64,
+    pub is_binary: bool,
+    /// Exact for small files, extrapolated from a sample for large ones
+    pub line_count_estimate: u64,
+    pub language: String,
+    pub mtime_ms: Option<u64>,
+    /// Whether read_file will accept this file
+    pub fits_in_memory: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct HttpRequest {
-    pub method: String,
-    pub url: String,
-    pub headers: Vec<HttpHeader>,
-    pub body: Option<String>,
-    pub timeout_ms: Option<u64>,
+pub struct FileChunk {
+    pub path: String,
+    /// Byte offset the chunk starts at (moved forward to a UTF-8 boundary)
+    pub offset: u64,
+    pub content: String,
+    /// Offset to request the next chunk from, None at end of file
+    pub next_offset: Option<u64>,
+    pub total_size: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct HttpResponse {
-    pub status: u16,
-    pub status_text: String,
-    pub headers: HashMap<String, String>,
-    pub body: String,
-    pub time_ms: u64,
-    pub size_bytes: usize,
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum WriteResult {
+    /// The file was written
+    Written {
+        path: String,
+        hash: String,
+        mtime_ms: Option<u64>,
+        /// Where the overwritten disk version was saved (forced writes only)
+        backup_path: Option<String>,
+    },
+    /// The file changed on disk since it was read; nothing was written
+    Conflict {
+        path: String,
+        disk_content: String,
+        disk_hash: String,
+        disk_mtime_ms: Option<u64>,
+    },
 }
 
 // ============================================================================
 // HELPER FUNCTIONS
 // ============================================================================
 
-fn read_directory_recursive(path: &PathBuf, depth: u32) -> Result<Vec<FileNode>, String> {
+fn read_directory_recursive(
+    path: &PathBuf,
+    depth: u32,
+    filter: &ignore::IgnoreFilter,
+    with_stats: bool,
+) -> Result<Vec<FileNode>, String> {
     if depth == 0 {
         return Ok(vec![]);
     }
@@ -73,20 +154,10 @@ fn read_directory_recursive(path: &PathBuf, depth: u32) -> Result<Vec<FileNode>,
         let entry_path = entry.path();
         let file_name = entry.file_name().to_string_lossy().to_string();
 
-        // Skip hidden files and common ignored directories
-        if file_name.starts_with('.')
-            || file_name == "node_modules"
-            || file_name == "target"
-            || file_name == ".git"
-            || file_name == "__pycache__"
-            || file_name == ".next"
-            || file_name == "dist"
-            || file_name == "build"
-        {
+        let is_dir = entry_path.is_dir();
+        if filter.is_ignored(&entry_path, is_dir) {
             continue;
         }
-
-        let is_dir = entry_path.is_dir();
         let extension = if is_dir {
             None
         } else {
@@ -96,13 +167,15 @@ fn read_directory_recursive(path: &PathBuf, depth: u32) -> Result<Vec<FileNode>,
         };
 
         let children = if is_dir && depth > 1 {
-            Some(read_directory_recursive(&entry_path, depth - 1)?)
+            Some(read_directory_recursive(&entry_path, depth - 1, filter, with_stats)?)
         } else if is_dir {
             Some(vec![]) // Empty placeholder for lazy loading
         } else {
             None
         };
 
+        let metadata = with_stats.then(|| entry.metadata().ok()).flatten();
+
         nodes.push(FileNode {
             id: uuid::Uuid::new_v4().to_string(),
             name: file_name,
@@ -110,6 +183,9 @@ fn read_directory_recursive(path: &PathBuf, depth: u32) -> Result<Vec<FileNode>,
             is_dir,
             children,
             extension,
+            size_bytes: metadata.as_ref().map(|m| if is_dir { 0 } else { m.len() }),
+            mtime_ms: metadata.as_ref().and_then(|m| epoch_ms(m.modified())),
+            readonly: metadata.as_ref().map(|m| m.permissions().readonly()),
         });
     }
 
@@ -123,42 +199,9 @@ fn read_directory_recursive(path: &PathBuf, depth: u32) -> Result<Vec<FileNode>,
     Ok(nodes)
 }
 
-fn get_language_from_extension(ext: &str) -> String {
-    match ext.to_lowercase().as_str() {
-        "rs" => "rust",
-        "js" | "mjs" | "cjs" => "javascript",
-        "ts" | "mts" | "cts" => "typescript",
-        "jsx" => "javascript",
-        "tsx" => "typescript",
-        "json" => "json",
-        "html" | "htm" => "html",
-        "css" => "css",
-        "scss" | "sass" => "scss",
-        "md" | "markdown" => "markdown",
-        "py" => "python",
-        "go" => "go",
-        "java" => "java",
-        "c" => "c",
-        "cpp" | "cc" | "cxx" => "cpp",
-        "h" | "hpp" => "cpp",
-        "sh" | "bash" | "zsh" => "shell",
-        "yaml" | "yml" => "yaml",
-        "toml" => "toml",
-        "xml" => "xml",
-        "sql" => "sql",
-        "graphql" | "gql" => "graphql",
-        _ => "plaintext",
-    }
-    .to_string()
-}
-
-// ============================================================================
-// TAURI COMMANDS
-// ============================================================================
-
-#[tauri::command]
-async fn open_folder(path: String) -> Result<FileNode, String> {
-    let path_buf = PathBuf::from(&path);
+/// A folder and ten levels of its contents, as the root of the file tree
+fn folder_node(path: &str, name: Option<&str>) -> Result<FileNode, String> {
+    let path_buf = PathBuf::from(path);
 
     if !path_buf.exists() {
         return Err(format!("Path does not exist: {}", path));
@@ -168,27 +211,197 @@ async fn open_folder(path: String) -> Result<FileNode, String> {
         return Err(format!("Path is not a directory: {}", path));
     }
 
-    let root_name = path_buf
-        .file_name()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_else(|| path.clone());
+    let root_name = name.map(str::to_string).unwrap_or_else(|| {
+        path_buf
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string())
+    });
 
-    let children = read_directory_recursive(&path_buf, 10)?;
+    let filter = ignore::IgnoreFilter::load(&path_buf)?;
+    let children = read_directory_recursive(&path_buf, 10, &filter, false)?;
 
     Ok(FileNode {
         id: uuid::Uuid::new_v4().to_string(),
         name: root_name,
-        path: path.clone(),
+        path: path.to_string(),
         is_dir: true,
         children: Some(children),
         extension: None,
+        size_bytes: None,
+        mtime_ms: None,
+        readonly: None,
+    })
+}
+
+/// Files and folders under `root` whose name contains `query` (case-insensitive)
+fn find_files(root: &Path, query: &str, limit: usize) -> Result<Vec<FileNode>, String> {
+    let query_lower = query.to_lowercase();
+    let mut results = Vec::new();
+    let filter = ignore::IgnoreFilter::load(root)?;
+
+    for entry in WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !filter.is_ignored(e.path(), e.file_type().is_dir()))
+        .filter_map(|e| e.ok())
+    {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+
+        if file_name.to_lowercase().contains(&query_lower) {
+            let is_dir = path.is_dir();
+            let extension = if is_dir {
+                None
+            } else {
+                path.extension().map(|e| e.to_string_lossy().to_string())
+            };
+
+            results.push(FileNode {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: file_name,
+                path: path.to_string_lossy().to_string(),
+                is_dir,
+                children: None,
+                extension,
+                size_bytes: None,
+                mtime_ms: None,
+                readonly: None,
+            });
+
+            if results.len() >= limit {
+                break;
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+/// Hash file content for change detection (stable for the lifetime of the app)
+fn content_hash(bytes: &[u8]) -> String {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+fn file_mtime_ms(path: &Path) -> Option<u64> {
+    epoch_ms(std::fs::metadata(path).and_then(|m| m.modified()))
+}
+
+fn epoch_ms(time: std::io::Result<std::time::SystemTime>) -> Option<u64> {
+    time.ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+}
+
+fn stat_one(path: &Path, repo: Option<&git2::Repository>) -> Result<PathStat, String> {
+    let symlink = std::fs::symlink_metadata(path)
+        .map_err(|e| format!("Failed to read metadata of {}: {}", path.display(), e))?;
+    // Follow links for everything else; a dangling one is described by itself
+    let metadata = std::fs::metadata(path).unwrap_or_else(|_| symlink.clone());
+
+    #[cfg(unix)]
+    let mode = {
+        use std::os::unix::fs::PermissionsExt;
+        Some(metadata.permissions().mode() & 0o7777)
+    };
+    #[cfg(not(unix))]
+    let mode = None;
+
+    Ok(PathStat {
+        path: path.to_string_lossy().to_string(),
+        is_dir: metadata.is_dir(),
+        is_symlink: symlink.file_type().is_symlink(),
+        size_bytes: if metadata.is_dir() { 0 } else { metadata.len() },
+        mtime_ms: epoch_ms(metadata.modified()),
+        created_ms: epoch_ms(metadata.created()),
+        readonly: metadata.permissions().readonly(),
+        mode,
+        git_status: repo.and_then(|repo| git::path_status(repo, path)),
     })
 }
 
+/// Write a file via a temp file in the same directory and an atomic rename
+fn atomic_write(path: &Path, content: &[u8]) -> Result<(), String> {
+    use std::io::Write;
+
+    let dir = path
+        .parent()
+        .ok_or_else(|| "Cannot get parent directory".to_string())?;
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| "Path has no file name".to_string())?;
+    let temp_path = dir.join(format!(".{}.{}.tmp", file_name, uuid::Uuid::new_v4()));
+
+    let result = (|| {
+        let mut file = std::fs::File::create(&temp_path)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+
+        // Keep the original permissions when replacing an existing file
+        if let Ok(metadata) = std::fs::metadata(path) {
+            std::fs::set_permissions(&temp_path, metadata.permissions())?;
+        }
+
+        std::fs::rename(&temp_path, path)
+    })();
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(format!("Failed to write file: {}", e));
+    }
+
+    Ok(())
+}
+
+/// Largest file read_file loads in one go; bigger files go through read_file_range
+const MAX_READ_FILE_SIZE: u64 = 50 * 1024 * 1024;
+
+/// Largest chunk returned by read_file_range
+const MAX_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Most name matches search_files returns
+const MAX_FILE_SEARCH_RESULTS: usize = 50;
+
+/// Bytes inspected for binary detection and line count estimation
+const SAMPLE_SIZE: usize = 1024 * 1024;
+
+fn looks_binary(sample: &[u8]) -> bool {
+    sample[..sample.len().min(8192)].contains(&0)
+}
+
+fn read_sample(path: &Path) -> Result<Vec<u8>, String> {
+    use std::io::Read;
+
+    let mut sample = Vec::with_capacity(SAMPLE_SIZE);
+    std::fs::File::open(path)
+        .and_then(|f| f.take(SAMPLE_SIZE as u64).read_to_end(&mut sample))
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(sample)
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
 #[tauri::command]
-async fn read_directory(path: String) -> Result<Vec<FileNode>, String> {
+async fn open_folder(path: String) -> Result<FileNode, String> {
+    folder_node(&path, None)
+}
+
+/// List one level of a folder; with_stats also fills in size, mtime and
+/// the read-only flag of each entry
+#[tauri::command]
+async fn read_directory(
+    path: String,
+    root_path: Option<String>,
+    with_stats: Option<bool>,
+) -> Result<Vec<FileNode>, String> {
     let path_buf = PathBuf::from(&path);
-    read_directory_recursive(&path_buf, 1)
+    let root = ignore::workspace_root(&path_buf, root_path.as_deref());
+    let filter = ignore::IgnoreFilter::load(&root)?;
+    read_directory_recursive(&path_buf, 1, &filter, with_stats.unwrap_or(false))
 }
 
 #[tauri::command]
@@ -203,27 +416,181 @@ async fn read_file(path: String) -> Result<FileContent, String> {
         return Err(format!("Path is not a file: {}", path));
     }
 
-    let content =
-        std::fs::read_to_string(&path_buf).map_err(|e| format!("Failed to read file: {}", e))?;
+    let size = std::fs::metadata(&path_buf)
+        .map_err(|e| format!("Failed to read metadata: {}", e))?
+        .len();
+    if size > MAX_READ_FILE_SIZE {
+        return Err(format!(
+            "File is too large to open at once ({} bytes), use read_file_range",
+            size
+        ));
+    }
 
-    let extension = path_buf
-        .extension()
-        .map(|e| e.to_string_lossy().to_string())
-        .unwrap_or_default();
+    let bytes = std::fs::read(&path_buf).map_err(|e| format!("Failed to read file: {}", e))?;
+    if looks_binary(&bytes) {
+        return Err(format!("File appears to be binary: {}, use read_file_base64", path));
+    }
+    let content = String::from_utf8(bytes)
+        .map_err(|_| format!("File is not valid UTF-8 text: {}", path))?;
 
-    let language = get_language_from_extension(&extension);
+    let language = collab_protocol::detect_language_with_content(&path, &content);
+    let hash = content_hash(content.as_bytes());
+    let mtime_ms = file_mtime_ms(&path_buf);
 
     Ok(FileContent {
         path,
         content,
         language,
+        hash,
+        mtime_ms,
     })
 }
 
 #[tauri::command]
-async fn write_file(path: String, content: String) -> Result<(), String> {
-    std::fs::write(&path, &content).map_err(|e| format!("Failed to write file: {}", e))?;
-    Ok(())
+async fn get_file_metadata(path: String) -> Result<FileMetadata, String> {
+    let path_buf = PathBuf::from(&path);
+
+    if !path_buf.is_file() {
+        return Err(format!("Path is not a file: {}", path));
+    }
+
+    let size_bytes = std::fs::metadata(&path_buf)
+        .map_err(|e| format!("Failed to read metadata: {}", e))?
+        .len();
+    let sample = read_sample(&path_buf)?;
+    let is_binary = looks_binary(&sample);
+
+    let sample_lines = sample.iter().filter(|&&b| b == b'\n').count() as u64;
+    let line_count_estimate = if is_binary || sample.is_empty() {
+        0
+    } else if sample.len() as u64 >= size_bytes {
+        sample_lines + u64::from(!sample.ends_with(b"\n"))
+    } else {
+        (sample_lines as f64 * size_bytes as f64 / sample.len() as f64).round() as u64
+    };
+
+    let language = if is_binary {
+        collab_protocol::detect_language(&path)
+    } else {
+        collab_protocol::detect_language_with_content(&path, &String::from_utf8_lossy(&sample))
+    };
+
+    Ok(FileMetadata {
+        language,
+        mtime_ms: file_mtime_ms(&path_buf),
+        fits_in_memory: !is_binary && size_bytes <= MAX_READ_FILE_SIZE,
+        path,
+        size_bytes,
+        is_binary,
+        line_count_estimate,
+    })
+}
+
+/// Read up to `max_bytes` of a file starting at `offset`.
+///
+/// Chunk boundaries are adjusted so no UTF-8 character is split; keep calling
+/// with `next_offset` until it is `None`.
+#[tauri::command]
+async fn read_file_range(path: String, offset: u64, max_bytes: u64) -> Result<FileChunk, String> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut file =
+        std::fs::File::open(&path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let total_size = file
+        .metadata()
+        .map_err(|e| format!("Failed to read metadata: {}", e))?
+        .len();
+
+    if offset >= total_size {
+        return Ok(FileChunk {
+            path,
+            offset: total_size,
+            content: String::new(),
+            next_offset: None,
+            total_size,
+        });
+    }
+
+    // At least one full character must fit so every call makes progress
+    let max_bytes = max_bytes.clamp(4, MAX_CHUNK_SIZE);
+    let mut buffer = Vec::with_capacity(max_bytes as usize);
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.take(max_bytes).read_to_end(&mut buffer))
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    // Skip continuation bytes of a character that started before the offset
+    let start = buffer
+        .iter()
+        .take(3)
+        .take_while(|&&b| b & 0xC0 == 0x80)
+        .count();
+
+    // Leave an incomplete trailing character for the next chunk
+    let mut end = buffer.len();
+    if offset + (end as u64) < total_size {
+        if let Err(e) = std::str::from_utf8(&buffer[start..end]) {
+            if e.error_len().is_none() {
+                end = start + e.valid_up_to();
+            }
+        }
+    }
+
+    let chunk_end = offset + end as u64;
+    Ok(FileChunk {
+        content: String::from_utf8_lossy(&buffer[start..end]).to_string(),
+        offset: offset + start as u64,
+        next_offset: (chunk_end < total_size).then_some(chunk_end),
+        path,
+        total_size,
+    })
+}
+
+/// Write a file atomically.
+///
+/// When `expected_hash` (from read_file) is given and the file changed on disk
+/// since, a `Conflict` is returned instead of writing. With `force`, the write
+/// goes ahead and the disk version is kept next to the file as `<name>.bak`.
+#[tauri::command]
+async fn write_file(
+    path: String,
+    content: String,
+    expected_hash: Option<String>,
+    force: Option<bool>,
+) -> Result<WriteResult, String> {
+    let path_buf = PathBuf::from(&path);
+    let mut backup_path = None;
+
+    if let (Some(expected_hash), true) = (expected_hash, path_buf.is_file()) {
+        let disk_bytes =
+            std::fs::read(&path_buf).map_err(|e| format!("Failed to read file: {}", e))?;
+        let disk_hash = content_hash(&disk_bytes);
+
+        if disk_hash != expected_hash {
+            if !force.unwrap_or(false) {
+                return Ok(WriteResult::Conflict {
+                    path,
+                    disk_content: String::from_utf8_lossy(&disk_bytes).to_string(),
+                    disk_hash,
+                    disk_mtime_ms: file_mtime_ms(&path_buf),
+                });
+            }
+
+            let mut backup = path_buf.clone().into_os_string();
+            backup.push(".bak");
+            std::fs::write(&backup, &disk_bytes)
+                .map_err(|e| format!("Failed to write backup: {}", e))?;
+            backup_path = Some(PathBuf::from(backup).to_string_lossy().to_string());
+        }
+    }
+
+    atomic_write(&path_buf, content.as_bytes())?;
+
+    Ok(WriteResult::Written {
+        hash: content_hash(content.as_bytes()),
+        mtime_ms: file_mtime_ms(&path_buf),
+        path,
+        backup_path,
+    })
 }
 
 #[tauri::command]
@@ -253,6 +620,9 @@ async fn create_file(dir_path: String, name: String) -> Result<FileNode, String>
         is_dir: false,
         children: None,
         extension,
+        size_bytes: None,
+        mtime_ms: None,
+        readonly: None,
     })
 }
 
@@ -274,9 +644,13 @@ async fn create_directory(parent_path: String, name: String) -> Result<FileNode,
         is_dir: true,
         children: Some(vec![]),
         extension: None,
+        size_bytes: None,
+        mtime_ms: None,
+        readonly: None,
     })
 }
 
+/// Permanently delete a file or folder (see move_to_trash for the recoverable default)
 #[tauri::command]
 async fn delete_path(path: String) -> Result<(), String> {
     let path_buf = PathBuf::from(&path);
@@ -327,151 +701,87 @@ async fn rename_path(old_path: String, new_name: String) -> Result<FileNode, Str
         is_dir,
         children: None,
         extension,
+        size_bytes: None,
+        mtime_ms: None,
+        readonly: None,
     })
 }
 
+/// Find files by name. Uses the workspace index when the root has one
+/// (fuzzy, ranked, no disk walk), else walks the folder for substring matches.
 #[tauri::command]
-async fn search_files(root_path: String, query: String) -> Result<Vec<FileNode>, String> {
-    let query_lower = query.to_lowercase();
-    let mut results = Vec::new();
+async fn search_files(
+    index: tauri::State<'_, index::IndexRegistry>,
+    root_path: String,
+    query: String,
+) -> Result<Vec<FileNode>, String> {
+    let root = Path::new(&root_path);
+    let Some(matches) = index::indexed_files(&index, root, &query, MAX_FILE_SEARCH_RESULTS) else {
+        return find_files(root, &query, MAX_FILE_SEARCH_RESULTS);
+    };
 
-    for entry in WalkDir::new(&root_path)
+    Ok(matches
         .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| {
-            let name = e.file_name().to_string_lossy().to_lowercase();
-            !name.starts_with('.')
-                && name != "node_modules"
-                && name != "target"
-                && name != "__pycache__"
-                && name != ".next"
-        })
-    {
-        let path = entry.path();
-        let file_name = entry.file_name().to_string_lossy().to_string();
-
-        if file_name.to_lowercase().contains(&query_lower) {
-            let is_dir = path.is_dir();
-            let extension = if is_dir {
-                None
-            } else {
-                path.extension().map(|e| e.to_string_lossy().to_string())
-            };
-
-            results.push(FileNode {
+        .map(|m| {
+            let path = Path::new(&m.path);
+            FileNode {
                 id: uuid::Uuid::new_v4().to_string(),
-                name: file_name,
-                path: path.to_string_lossy().to_string(),
-                is_dir,
+                name: path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                extension: path.extension().map(|e| e.to_string_lossy().to_string()),
+                path: m.path,
+                is_dir: false,
                 children: None,
-                extension,
-            });
-
-            if results.len() >= 50 {
-                break;
+                size_bytes: None,
+                mtime_ms: None,
+                readonly: None,
             }
-        }
-    }
-
-    Ok(results)
+        })
+        .collect())
 }
 
+/// Size, timestamps, permissions and git status of a file or folder
 #[tauri::command]
-fn get_file_language(path: String) -> String {
+async fn stat_path(path: String) -> Result<PathStat, String> {
     let path_buf = PathBuf::from(&path);
-    let ext = path_buf
-        .extension()
-        .map(|e| e.to_string_lossy().to_string())
-        .unwrap_or_default();
-
-    get_language_from_extension(&ext)
+    let repo = git2::Repository::discover(&path_buf).ok();
+    stat_one(&path_buf, repo.as_ref())
 }
 
+/// stat_path for many paths at once, e.g. the visible rows of the file tree;
+/// paths that no longer exist are left out
 #[tauri::command]
-async fn send_http_request(request: HttpRequest) -> Result<HttpResponse, String> {
-    // Build client that accepts invalid certs and works with localhost
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_millis(
-            request.timeout_ms.unwrap_or(30000),
-        ))
-        .danger_accept_invalid_certs(true)
-        .no_proxy() // Important for localhost requests
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
-
-    let method = request.method.to_uppercase();
-    let mut req_builder = match method.as_str() {
-        "GET" => client.get(&request.url),
-        "POST" => client.post(&request.url),
-        "PUT" => client.put(&request.url),
-        "PATCH" => client.patch(&request.url),
-        "DELETE" => client.delete(&request.url),
-        "HEAD" => client.head(&request.url),
-        "OPTIONS" => client.request(reqwest::Method::OPTIONS, &request.url),
-        _ => return Err(format!("Unsupported HTTP method: {}", method)),
-    };
-
-    // Add headers
-    for header in &request.headers {
-        if header.enabled && !header.key.is_empty() {
-            req_builder = req_builder.header(&header.key, &header.value);
+async fn stat_paths(paths: Vec<String>) -> Result<Vec<PathStat>, String> {
+    let mut repo: Option<git2::Repository> = None;
+    let mut stats = Vec::new();
+
+    for path in paths.iter().map(PathBuf::from) {
+        let in_repo = repo
+            .as_ref()
+            .and_then(|r| r.workdir())
+            .is_some_and(|workdir| path.starts_with(workdir));
+        if !in_repo {
+            repo = git2::Repository::discover(&path).ok();
         }
-    }
-
-    // Add body for methods that support it
-    if let Some(body) = &request.body {
-        if !body.is_empty() && matches!(method.as_str(), "POST" | "PUT" | "PATCH") {
-            let has_content_type = request
-                .headers
-                .iter()
-                .any(|h| h.enabled && h.key.to_lowercase() == "content-type");
-
-            if !has_content_type {
-                req_builder = req_builder.header("Content-Type", "application/json");
-            }
-
-            req_builder = req_builder.body(body.clone());
+        if let Ok(stat) = stat_one(&path, repo.as_ref()) {
+            stats.push(stat);
         }
     }
 
-    let start = std::time::Instant::now();
-
-    let response = req_builder
-        .send()
-        .await
-        .map_err(|e| format!("Request failed: {}", e))?;
-
-    let elapsed = start.elapsed().as_millis() as u64;
-
-    let status = response.status().as_u16();
-    let status_text = response
-        .status()
-        .canonical_reason()
-        .unwrap_or("Unknown")
-        .to_string();
+    Ok(stats)
+}
 
-    let mut headers = HashMap::new();
-    for (key, value) in response.headers().iter() {
-        if let Ok(v) = value.to_str() {
-            headers.insert(key.to_string(), v.to_string());
+#[tauri::command]
+fn get_file_language(path: String) -> String {
+    // The first bytes are enough for a #! line or a leading modeline
+    match read_sample(Path::new(&path)) {
+        Ok(sample) if !looks_binary(&sample) => {
+            collab_protocol::detect_language_with_content(&path, &String::from_utf8_lossy(&sample))
         }
+        _ => collab_protocol::detect_language(&path),
     }
-
-    let body = response
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read response body: {}", e))?;
-
-    let size_bytes = body.len();
-
-    Ok(HttpResponse {
-        status,
-        status_text,
-        headers,
-        body,
-        time_ms: elapsed,
-        size_bytes,
-    })
 }
 
 // ============================================================================
@@ -486,6 +796,64 @@ pub fn run() {
         .plugin(tauri_plugin_http::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_websocket::init())
+        .manage(collab::CollabRegistry::default())
+        .manage(watcher::WatcherRegistry::default())
+        .manage(workspace::WorkspaceRegistry::default())
+        .manage(terminal::TerminalRegistry::default())
+        .manage(lsp::LspRegistry::default())
+        .manage(tasks::TaskRegistry::default())
+        .manage(recent::RecentFolders::default())
+        .manage(settings::SettingsStore::default())
+        .manage(autosave::AutosaveJournal::default())
+        .manage(index::IndexRegistry::default())
+        .manage(clipboard::ClipboardState::default())
+        .manage(downloads::DownloadManager::default())
+        .manage(http::cookies::CookieJar::default())
+        .manage(http::auth::OAuth2Tokens::default())
+        .manage(http::download::DownloadRegistry::default())
+        .manage(http::websocket::WsRegistry::default())
+        .manage(http::sse::SseRegistry::default())
+        .manage(http::mock::MockServerRegistry::default())
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                window
+                    .state::<watcher::WatcherRegistry>()
+                    .unwatch_window(window.label());
+                window
+                    .state::<workspace::WorkspaceRegistry>()
+                    .close_window(window.label());
+                window
+                    .state::<terminal::TerminalRegistry>()
+                    .close_window(window.label());
+                window
+                    .state::<collab::CollabRegistry>()
+                    .close_window(window.label());
+                window
+                    .state::<tasks::TaskRegistry>()
+                    .close_window(window.label());
+                window
+                    .state::<downloads::DownloadManager>()
+                    .close_window(window.label());
+                window
+                    .state::<http::download::DownloadRegistry>()
+                    .close_window(window.label());
+                window
+                    .state::<http::websocket::WsRegistry>()
+                    .close_window(window.label());
+                window
+                    .state::<http::sse::SseRegistry>()
+                    .close_window(window.label());
+                window
+                    .state::<http::mock::MockServerRegistry>()
+                    .close_window(window.label());
+                window
+                    .state::<settings::SettingsStore>()
+                    .close_window(window.label());
+                window
+                    .state::<autosave::AutosaveJournal>()
+                    .flush_now(window.app_handle());
+            }
+        })
         .setup(|app| {
             if cfg!(debug_assertions) {
                 app.handle().plugin(
@@ -500,14 +868,146 @@ pub fn run() {
             open_folder,
             read_directory,
             read_file,
+            read_file_range,
+            preview::read_file_base64,
+            get_file_metadata,
             write_file,
             create_file,
             create_directory,
             delete_path,
             rename_path,
             search_files,
+            search::search_in_files,
+            todos::scan_todos,
+            index::build_workspace_index,
+            index::query_index,
+            index::drop_workspace_index,
+            replace::replace_in_files,
+            backup::restore_backup,
+            encoding::detect_text_format,
+            encoding::convert_text_format,
+            ignore::get_ignore_preferences,
+            ignore::set_ignore_preferences,
+            collab::connect_to_project,
+            collab::disconnect,
+            collab::send_collab_message,
+            host::host_project,
+            format::format_file,
+            git::git_repo_info,
+            git::git_status,
+            git::git_diff,
+            git::git_stage,
+            git::git_unstage,
+            git::git_commit,
+            diff::compute_diff,
+            downloads::download_file,
+            downloads::pause_download,
+            downloads::resume_download,
+            downloads::cancel_download,
+            downloads::list_downloads,
+            dotenv::list_env_files,
+            dotenv::read_env_file,
+            dotenv::set_env_var,
+            dotenv::remove_env_var,
+            dotenv::validate_env_file,
+            lsp::lsp_start,
+            lsp::lsp_stop,
+            lsp::lsp_did_open,
+            lsp::lsp_did_change,
+            lsp::lsp_did_close,
+            lsp::lsp_hover,
+            lsp::lsp_completion,
+            lsp::lsp_definition,
+            settings::get_setting,
+            settings::set_setting,
+            settings::reset_setting,
+            settings::subscribe_settings,
+            settings::unsubscribe_settings,
+            secrets::store_secret,
+            secrets::get_secret,
+            secrets::delete_secret,
+            autosave::buffer_changed,
+            autosave::buffer_saved,
+            autosave::recover_unsaved,
+            autosave::restore_unsaved,
+            session::save_workspace_state,
+            session::load_workspace_state,
+            recent::record_recent_folder,
+            recent::list_recent_folders,
+            recent::pin_recent_folder,
+            recent::remove_recent_folder,
+            recent::prune_recent_folders,
+            templates::list_project_templates,
+            templates::create_project_from_template,
+            templates::save_project_template,
+            tasks::run_command,
+            tasks::cancel_run,
+            terminal::create_terminal,
+            terminal::write_terminal,
+            terminal::resize_terminal,
+            terminal::kill_terminal,
+            transfer::move_path,
+            transfer::copy_path,
+            archive::compress_paths,
+            archive::extract_archive,
+            clipboard::copy_to_clipboard,
+            clipboard::cut_to_clipboard,
+            clipboard::read_clipboard_files,
+            clipboard::paste_from_clipboard,
+            clipboard::clear_clipboard,
+            trash_bin::move_to_trash,
+            trash_bin::list_trash,
+            trash_bin::restore_from_trash,
+            watcher::watch_directory,
+            watcher::unwatch_directory,
+            workspace::open_workspace,
+            workspace::add_workspace_root,
+            workspace::remove_workspace_root,
+            workspace::list_workspace_roots,
+            workspace::search_workspace_files,
+            workspace::save_workspace_file,
+            workspace::open_workspace_file,
             get_file_language,
-            send_http_request,
+            stat_path,
+            stat_paths,
+            http::send_http_request,
+            http::auth::oauth2_authorization_url,
+            http::auth::oauth2_exchange_code,
+            http::auth::clear_oauth2_tokens,
+            http::collections::list_collections,
+            http::collections::save_collection,
+            http::collections::delete_collection,
+            http::collections::save_request,
+            http::collections::delete_request,
+            http::collections::list_environments,
+            http::collections::save_environment,
+            http::collections::delete_environment,
+            http::collections::set_active_environment,
+            http::cookies::list_cookies,
+            http::cookies::set_cookie,
+            http::cookies::delete_cookie,
+            http::cookies::clear_cookies,
+            http::download::save_response_to_file,
+            http::download::cancel_http_download,
+            http::runner::check_assertions,
+            http::runner::run_collection,
+            http::extract::apply_extractions,
+            http::diff::diff_responses,
+            http::diff::get_previous_response,
+            http::diff::record_saved_response,
+            http::openapi::import_openapi,
+            http::mock::start_mock_server,
+            http::mock::update_mock_routes,
+            http::mock::stop_mock_server,
+            http::graphql::run_introspection,
+            http::websocket::ws_connect,
+            http::websocket::ws_send,
+            http::websocket::ws_close,
+            http::websocket::ws_history,
+            http::sse::open_sse,
+            http::sse::close_sse,
+            http::grpc::list_grpc_services,
+            http::grpc::send_grpc_request,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,531 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::oneshot;
+
+// ============================================================================
+// LSP TYPES
+// ============================================================================
+
+/// Event carrying `textDocument/publishDiagnostics` notifications
+pub const LSP_DIAGNOSTICS_EVENT: &str = "lsp://diagnostics";
+
+/// Event emitted when a language server process exits
+pub const LSP_EXIT_EVENT: &str = "lsp://exit";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const INITIALIZE_TIMEOUT: Duration = Duration::from_secs(60);
+
+type PendingRequests = Mutex<HashMap<u64, oneshot::Sender<Result<Value, String>>>>;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LspServerInfo {
+    pub language: String,
+    pub workspace_root: String,
+    pub command: Vec<String>,
+    /// Capabilities reported by the server in its initialize result
+    pub capabilities: Value,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LspDiagnostics {
+    pub language: String,
+    pub workspace_root: String,
+    pub path: Option<String>,
+    pub uri: String,
+    pub diagnostics: Value,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LspExit {
+    pub language: String,
+    pub workspace_root: String,
+}
+
+/// A running language server speaking JSON-RPC over stdio
+struct LspServer {
+    info: LspServerInfo,
+    /// Set once the initialize handshake completed
+    capabilities: OnceLock<Value>,
+    stdin: tokio::sync::Mutex<ChildStdin>,
+    child: tokio::sync::Mutex<Child>,
+    next_id: AtomicU64,
+    pending: PendingRequests,
+}
+
+impl LspServer {
+    fn info(&self) -> LspServerInfo {
+        LspServerInfo {
+            capabilities: self.capabilities.get().cloned().unwrap_or(Value::Null),
+            ..self.info.clone()
+        }
+    }
+
+    async fn send(&self, message: Value) -> Result<(), String> {
+        let body = message.to_string();
+        let frame = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+
+        let mut stdin = self.stdin.lock().await;
+        stdin
+            .write_all(frame.as_bytes())
+            .await
+            .and(stdin.flush().await)
+            .map_err(|e| format!("Failed to write to language server: {}", e))
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<(), String> {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+            .await
+    }
+
+    async fn request(
+        &self,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+    ) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .map_err(|_| "Pending request map is poisoned".to_string())?
+            .insert(id, tx);
+
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .await?;
+
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err("Language server exited".to_string()),
+            Err(_) => {
+                if let Ok(mut pending) = self.pending.lock() {
+                    pending.remove(&id);
+                }
+                Err(format!("Language server timed out on {}", method))
+            }
+        }
+    }
+}
+
+/// Running language servers keyed by language and workspace root
+#[derive(Default)]
+pub struct LspRegistry {
+    servers: Mutex<HashMap<String, Arc<LspServer>>>,
+}
+
+impl LspRegistry {
+    fn get(&self, workspace_root: &str, language: &str) -> Result<Arc<LspServer>, String> {
+        self.servers
+            .lock()
+            .map_err(|_| "LSP registry is poisoned".to_string())?
+            .get(&server_key(workspace_root, language))
+            .cloned()
+            .ok_or_else(|| {
+                format!(
+                    "No {} language server running for {}",
+                    language, workspace_root
+                )
+            })
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn server_key(workspace_root: &str, language: &str) -> String {
+    format!("{}@{}", language, workspace_root)
+}
+
+/// Default language server command for an editor language id
+fn default_command(language: &str) -> Option<Vec<String>> {
+    let command: &[&str] = match language {
+        "rust" => &["rust-analyzer"],
+        "typescript" | "javascript" => &["typescript-language-server", "--stdio"],
+        "python" => &["pyright-langserver", "--stdio"],
+        _ => return None,
+    };
+    Some(command.iter().map(|s| s.to_string()).collect())
+}
+
+fn path_to_uri(path: &str) -> Result<String, String> {
+    url::Url::from_file_path(path)
+        .map(|u| u.to_string())
+        .map_err(|_| format!("Not an absolute path: {}", path))
+}
+
+fn uri_to_path(uri: &str) -> Option<String> {
+    url::Url::parse(uri)
+        .ok()?
+        .to_file_path()
+        .ok()
+        .map(|p| p.to_string_lossy().to_string())
+}
+
+fn position_params(path: &str, line: u32, character: u32) -> Result<Value, String> {
+    Ok(json!({
+        "textDocument": { "uri": path_to_uri(path)? },
+        "position": { "line": line, "character": character },
+    }))
+}
+
+async fn read_message<R: tokio::io::AsyncBufRead + Unpin>(reader: &mut R) -> Option<Value> {
+    let mut content_length = None;
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await.ok()? == 0 {
+            return None;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            content_length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let mut body = vec![0u8; content_length?];
+    reader.read_exact(&mut body).await.ok()?;
+    serde_json::from_slice(&body).ok()
+}
+
+/// Dispatch messages coming from the server until its stdout closes
+fn spawn_reader(app: AppHandle, server: Arc<LspServer>, stdout: tokio::process::ChildStdout) {
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(stdout);
+
+        while let Some(message) = read_message(&mut reader).await {
+            let id = message.get("id").cloned();
+            let method = message.get("method").and_then(Value::as_str);
+
+            match (id, method) {
+                // Response to one of our requests
+                (Some(id), None) => {
+                    let Some(id) = id.as_u64() else { continue };
+                    let sender = server.pending.lock().ok().and_then(|mut p| p.remove(&id));
+                    if let Some(sender) = sender {
+                        let result = match message.get("error") {
+                            Some(error) => Err(error
+                                .get("message")
+                                .and_then(Value::as_str)
+                                .unwrap_or("Language server error")
+                                .to_string()),
+                            None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+                        };
+                        let _ = sender.send(result);
+                    }
+                }
+                // Request from the server: acknowledge so it does not stall
+                (Some(id), Some(method)) => {
+                    let result = match method {
+                        "workspace/configuration" => {
+                            let items = message["params"]["items"]
+                                .as_array()
+                                .map(|items| items.len())
+                                .unwrap_or(0);
+                            Value::Array(vec![Value::Null; items])
+                        }
+                        _ => Value::Null,
+                    };
+                    let _ = server
+                        .send(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+                        .await;
+                }
+                (None, Some("textDocument/publishDiagnostics")) => {
+                    let params = &message["params"];
+                    let uri = params["uri"].as_str().unwrap_or_default().to_string();
+                    let _ = app.emit(
+                        LSP_DIAGNOSTICS_EVENT,
+                        LspDiagnostics {
+                            language: server.info.language.clone(),
+                            workspace_root: server.info.workspace_root.clone(),
+                            path: uri_to_path(&uri),
+                            diagnostics: params["diagnostics"].clone(),
+                            uri,
+                        },
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        // Fail any in-flight requests and tell the UI the server is gone
+        if let Ok(mut pending) = server.pending.lock() {
+            pending.clear();
+        }
+        let _ = app.emit(
+            LSP_EXIT_EVENT,
+            LspExit {
+                language: server.info.language.clone(),
+                workspace_root: server.info.workspace_root.clone(),
+            },
+        );
+    });
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Start (or reuse) the language server for `language` in a workspace.
+///
+/// `command` overrides the default server binary and arguments.
+#[tauri::command]
+pub async fn lsp_start(
+    app: AppHandle,
+    registry: State<'_, LspRegistry>,
+    workspace_root: String,
+    language: String,
+    command: Option<Vec<String>>,
+) -> Result<LspServerInfo, String> {
+    if let Ok(server) = registry.get(&workspace_root, &language) {
+        return Ok(server.info());
+    }
+
+    if !Path::new(&workspace_root).is_dir() {
+        return Err(format!("Path is not a directory: {}", workspace_root));
+    }
+
+    let command = command
+        .filter(|c| !c.is_empty())
+        .or_else(|| default_command(&language))
+        .ok_or_else(|| format!("No language server configured for {}", language))?;
+
+    let mut child = Command::new(&command[0])
+        .args(&command[1..])
+        .current_dir(&workspace_root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", command[0], e))?;
+
+    let stdin = child.stdin.take().ok_or("Language server has no stdin")?;
+    let stdout = child.stdout.take().ok_or("Language server has no stdout")?;
+
+    let server = Arc::new(LspServer {
+        info: LspServerInfo {
+            language: language.clone(),
+            workspace_root: workspace_root.clone(),
+            command,
+            capabilities: Value::Null,
+        },
+        capabilities: OnceLock::new(),
+        stdin: tokio::sync::Mutex::new(stdin),
+        child: tokio::sync::Mutex::new(child),
+        next_id: AtomicU64::new(1),
+        pending: Mutex::new(HashMap::new()),
+    });
+    spawn_reader(app, server.clone(), stdout);
+
+    let root_uri = path_to_uri(&workspace_root)?;
+    let initialized = server
+        .request(
+            "initialize",
+            json!({
+                "processId": std::process::id(),
+                "rootUri": root_uri,
+                "workspaceFolders": [{ "uri": root_uri, "name": workspace_root }],
+                "capabilities": {
+                    "textDocument": {
+                        "synchronization": { "didSave": true },
+                        "publishDiagnostics": { "relatedInformation": true },
+                        "hover": { "contentFormat": ["markdown", "plaintext"] },
+                        "completion": { "completionItem": { "snippetSupport": true } },
+                        "definition": { "linkSupport": true },
+                    },
+                    "workspace": { "configuration": true, "workspaceFolders": true },
+                },
+            }),
+            INITIALIZE_TIMEOUT,
+        )
+        .await;
+
+    let result = match initialized {
+        Ok(result) => result,
+        Err(e) => {
+            // The reader task keeps the server alive, so kill the process explicitly
+            let _ = server.child.lock().await.kill().await;
+            return Err(e);
+        }
+    };
+    server.notify("initialized", json!({})).await?;
+
+    let _ = server
+        .capabilities
+        .set(result.get("capabilities").cloned().unwrap_or(Value::Null));
+
+    registry
+        .servers
+        .lock()
+        .map_err(|_| "LSP registry is poisoned".to_string())?
+        .insert(server_key(&workspace_root, &language), server.clone());
+
+    Ok(server.info())
+}
+
+#[tauri::command]
+pub async fn lsp_did_open(
+    registry: State<'_, LspRegistry>,
+    workspace_root: String,
+    language: String,
+    path: String,
+    text: String,
+    version: i32,
+) -> Result<(), String> {
+    let server = registry.get(&workspace_root, &language)?;
+    server
+        .notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": path_to_uri(&path)?,
+                    "languageId": language,
+                    "version": version,
+                    "text": text,
+                }
+            }),
+        )
+        .await
+}
+
+/// Send the full new content of a document (full document sync)
+#[tauri::command]
+pub async fn lsp_did_change(
+    registry: State<'_, LspRegistry>,
+    workspace_root: String,
+    language: String,
+    path: String,
+    text: String,
+    version: i32,
+) -> Result<(), String> {
+    let server = registry.get(&workspace_root, &language)?;
+    server
+        .notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": path_to_uri(&path)?, "version": version },
+                "contentChanges": [{ "text": text }],
+            }),
+        )
+        .await
+}
+
+#[tauri::command]
+pub async fn lsp_did_close(
+    registry: State<'_, LspRegistry>,
+    workspace_root: String,
+    language: String,
+    path: String,
+) -> Result<(), String> {
+    let server = registry.get(&workspace_root, &language)?;
+    server
+        .notify(
+            "textDocument/didClose",
+            json!({ "textDocument": { "uri": path_to_uri(&path)? } }),
+        )
+        .await
+}
+
+/// Raw LSP `Hover` result (or null)
+#[tauri::command]
+pub async fn lsp_hover(
+    registry: State<'_, LspRegistry>,
+    workspace_root: String,
+    language: String,
+    path: String,
+    line: u32,
+    character: u32,
+) -> Result<Value, String> {
+    let server = registry.get(&workspace_root, &language)?;
+    server
+        .request(
+            "textDocument/hover",
+            position_params(&path, line, character)?,
+            REQUEST_TIMEOUT,
+        )
+        .await
+}
+
+/// Raw LSP `CompletionList` / `CompletionItem[]` result
+#[tauri::command]
+pub async fn lsp_completion(
+    registry: State<'_, LspRegistry>,
+    workspace_root: String,
+    language: String,
+    path: String,
+    line: u32,
+    character: u32,
+) -> Result<Value, String> {
+    let server = registry.get(&workspace_root, &language)?;
+    server
+        .request(
+            "textDocument/completion",
+            position_params(&path, line, character)?,
+            REQUEST_TIMEOUT,
+        )
+        .await
+}
+
+/// Raw LSP `Location` / `Location[]` / `LocationLink[]` result
+#[tauri::command]
+pub async fn lsp_definition(
+    registry: State<'_, LspRegistry>,
+    workspace_root: String,
+    language: String,
+    path: String,
+    line: u32,
+    character: u32,
+) -> Result<Value, String> {
+    let server = registry.get(&workspace_root, &language)?;
+    server
+        .request(
+            "textDocument/definition",
+            position_params(&path, line, character)?,
+            REQUEST_TIMEOUT,
+        )
+        .await
+}
+
+/// Shut a language server down gracefully, killing it if it does not comply
+#[tauri::command]
+pub async fn lsp_stop(
+    registry: State<'_, LspRegistry>,
+    workspace_root: String,
+    language: String,
+) -> Result<(), String> {
+    let server = registry
+        .servers
+        .lock()
+        .map_err(|_| "LSP registry is poisoned".to_string())?
+        .remove(&server_key(&workspace_root, &language));
+    let Some(server) = server else {
+        return Ok(());
+    };
+
+    let _ = server
+        .request("shutdown", Value::Null, REQUEST_TIMEOUT)
+        .await;
+    let _ = server.notify("exit", Value::Null).await;
+
+    let mut child = server.child.lock().await;
+    if tokio::time::timeout(Duration::from_secs(2), child.wait())
+        .await
+        .is_err()
+    {
+        let _ = child.kill().await;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use base64::Engine;
+use serde::Serialize;
+use std::io::Read;
+use std::path::Path;
+
+// ============================================================================
+// PREVIEW TYPES
+// ============================================================================
+
+/// Largest file whose content is returned for preview
+const DEFAULT_MAX_PREVIEW_SIZE: u64 = 20 * 1024 * 1024;
+
+/// Hard cap on max_bytes, whatever the caller asks for
+const MAX_PREVIEW_SIZE: u64 = 100 * 1024 * 1024;
+
+/// Bytes read to sniff the file type
+const SNIFF_LEN: usize = 64;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PreviewKind {
+    Image,
+    Font,
+    Pdf,
+    Audio,
+    Video,
+    Archive,
+    /// Binary content the frontend has no viewer for
+    Binary,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BinaryFileContent {
+    pub path: String,
+    pub mime_type: String,
+    pub kind: PreviewKind,
+    pub size_bytes: u64,
+    /// Base64 of the whole file; None when it is larger than the limit
+    pub data_base64: Option<String>,
+    pub too_large: bool,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// MIME type from the file's leading bytes
+fn sniff_mime(head: &[u8]) -> Option<&'static str> {
+    let riff = |form: &[u8]| head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == form;
+    let mime = match head {
+        [0x89, b'P', b'N', b'G', ..] => "image/png",
+        [0xFF, 0xD8, 0xFF, ..] => "image/jpeg",
+        [b'G', b'I', b'F', b'8', ..] => "image/gif",
+        [b'B', b'M', ..] => "image/bmp",
+        [0x00, 0x00, 0x01, 0x00, ..] => "image/x-icon",
+        [b'%', b'P', b'D', b'F', b'-', ..] => "application/pdf",
+        [b'w', b'O', b'F', b'F', ..] => "font/woff",
+        [b'w', b'O', b'F', b'2', ..] => "font/woff2",
+        [0x00, 0x01, 0x00, 0x00, ..] => "font/ttf",
+        [b'O', b'T', b'T', b'O', ..] => "font/otf",
+        [b'P', b'K', 0x03, 0x04, ..] => "application/zip",
+        [0x1F, 0x8B, ..] => "application/gzip",
+        [b'I', b'D', b'3', ..] => "audio/mpeg",
+        [b'O', b'g', b'g', b'S', ..] => "audio/ogg",
+        [b'f', b'L', b'a', b'C', ..] => "audio/flac",
+        [0x1A, 0x45, 0xDF, 0xA3, ..] => "video/webm",
+        _ if riff(b"WEBP") => "image/webp",
+        _ if riff(b"WAVE") => "audio/wav",
+        _ if riff(b"AVI ") => "video/x-msvideo",
+        [_, _, _, _, b'f', b't', b'y', b'p', brand @ ..] => match brand.get(..4) {
+            Some(b"avif") => "image/avif",
+            Some(b"heic") | Some(b"heix") => "image/heic",
+            Some(b"qt  ") => "video/quicktime",
+            Some(b"M4A ") => "audio/mp4",
+            _ => "video/mp4",
+        },
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// MIME type from the extension, for formats without a signature (SVG) or
+/// that could not be sniffed
+fn mime_from_extension(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    let mime = match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        "avif" => "image/avif",
+        "pdf" => "application/pdf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "flac" => "audio/flac",
+        "mp4" | "m4v" => "video/mp4",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        "zip" => "application/zip",
+        "gz" | "tgz" => "application/gzip",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+fn preview_kind(mime: &str) -> PreviewKind {
+    match mime.split('/').next().unwrap_or_default() {
+        "image" => PreviewKind::Image,
+        "font" => PreviewKind::Font,
+        "audio" => PreviewKind::Audio,
+        "video" => PreviewKind::Video,
+        _ if mime == "application/pdf" => PreviewKind::Pdf,
+        _ if mime == "application/zip" || mime == "application/gzip" => PreviewKind::Archive,
+        _ => PreviewKind::Binary,
+    }
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Read a file as base64 with its MIME type, for previewing images, fonts,
+/// PDFs and other binary files read_file refuses.
+///
+/// Files over `max_bytes` (20 MB by default) come back with `too_large`
+/// set and no data, so the frontend can still show what they are.
+#[tauri::command]
+pub async fn read_file_base64(
+    path: String,
+    max_bytes: Option<u64>,
+) -> Result<BinaryFileContent, String> {
+    let path_buf = Path::new(&path);
+    if !path_buf.is_file() {
+        return Err(format!("Path is not a file: {}", path));
+    }
+
+    let size_bytes = std::fs::metadata(path_buf)
+        .map_err(|e| format!("Failed to read metadata: {}", e))?
+        .len();
+    let limit = max_bytes
+        .unwrap_or(DEFAULT_MAX_PREVIEW_SIZE)
+        .min(MAX_PREVIEW_SIZE);
+
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    std::fs::File::open(path_buf)
+        .and_then(|f| f.take(SNIFF_LEN as u64).read_to_end(&mut head))
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let mime_type = sniff_mime(&head)
+        .or_else(|| mime_from_extension(path_buf))
+        .unwrap_or("application/octet-stream");
+
+    let too_large = size_bytes > limit;
+    let data_base64 = if too_large {
+        None
+    } else {
+        let bytes = std::fs::read(path_buf).map_err(|e| format!("Failed to read file: {}", e))?;
+        Some(base64::engine::general_purpose::STANDARD.encode(bytes))
+    };
+
+    Ok(BinaryFileContent {
+        kind: preview_kind(mime_type),
+        mime_type: mime_type.to_string(),
+        path,
+        size_bytes,
+        data_base64,
+        too_large,
+    })
+}
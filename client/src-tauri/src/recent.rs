@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+// ============================================================================
+// RECENT FOLDER TYPES
+// ============================================================================
+
+/// Unpinned folders kept; pinned ones never fall off the list
+const MAX_RECENT_FOLDERS: usize = 20;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecentFolder {
+    pub path: String,
+    /// Folder name, for display
+    pub name: String,
+    /// Milliseconds since epoch
+    pub last_opened: i64,
+    pub open_count: u32,
+    #[serde(default)]
+    pub pinned: bool,
+    /// The folder no longer exists (checked when listing)
+    #[serde(default, skip_deserializing)]
+    pub missing: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+struct RecentFoldersFile {
+    folders: Vec<RecentFolder>,
+}
+
+/// Serializes updates to the recent folders file between windows
+#[derive(Default)]
+pub struct RecentFolders {
+    lock: Mutex<()>,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn recent_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?
+        .join("recent_folders.json"))
+}
+
+fn load(app: &AppHandle) -> Result<RecentFoldersFile, String> {
+    let path = recent_path(app)?;
+    if !path.exists() {
+        return Ok(RecentFoldersFile::default());
+    }
+
+    let json = std::fs::read(&path).map_err(|e| format!("Failed to read recent folders: {}", e))?;
+    Ok(serde_json::from_slice(&json).unwrap_or_else(|e| {
+        // A corrupt list should never block the welcome screen
+        log::warn!("Ignoring unreadable recent folders: {}", e);
+        RecentFoldersFile::default()
+    }))
+}
+
+fn save(app: &AppHandle, recent: &RecentFoldersFile) -> Result<(), String> {
+    let path = recent_path(app)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    }
+
+    let json = serde_json::to_vec_pretty(recent)
+        .map_err(|e| format!("Failed to serialize recent folders: {}", e))?;
+    crate::atomic_write(&path, &json)
+}
+
+/// Run an update of the recent folders file while holding the lock
+fn update<T>(
+    app: &AppHandle,
+    store: &RecentFolders,
+    f: impl FnOnce(&mut RecentFoldersFile) -> Result<T, String>,
+) -> Result<T, String> {
+    let _guard = store
+        .lock
+        .lock()
+        .map_err(|_| "Recent folders lock is poisoned".to_string())?;
+
+    let mut recent = load(app)?;
+    let result = f(&mut recent)?;
+    save(app, &recent)?;
+    Ok(result)
+}
+
+/// Same folder whatever the trailing separator
+fn normalize(path: &str) -> String {
+    let trimmed = path.trim_end_matches(['/', '\\']);
+    if trimmed.is_empty() {
+        path.to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Pinned first, then most recently opened
+fn sort(folders: &mut [RecentFolder]) {
+    folders.sort_by(|a, b| {
+        b.pinned
+            .cmp(&a.pinned)
+            .then(b.last_opened.cmp(&a.last_opened))
+    });
+}
+
+/// Every word of the query appears in the folder's path (which ends in its name)
+fn matches_query(folder: &RecentFolder, query: &str) -> bool {
+    let haystack = folder.path.to_lowercase();
+    query
+        .to_lowercase()
+        .split_whitespace()
+        .all(|word| haystack.contains(word))
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Record a folder as just opened, moving it to the top of the list
+#[tauri::command]
+pub async fn record_recent_folder(
+    app: AppHandle,
+    store: State<'_, RecentFolders>,
+    path: String,
+) -> Result<RecentFolder, String> {
+    if !Path::new(&path).is_dir() {
+        return Err(format!("Path is not a directory: {}", path));
+    }
+    let path = normalize(&path);
+
+    update(&app, &store, |recent| {
+        let now = chrono::Utc::now().timestamp_millis();
+        let folder = match recent.folders.iter().position(|f| f.path == path) {
+            Some(index) => {
+                let mut folder = recent.folders.remove(index);
+                folder.last_opened = now;
+                folder.open_count += 1;
+                folder
+            }
+            None => RecentFolder {
+                name: Path::new(&path)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.clone()),
+                path: path.clone(),
+                last_opened: now,
+                open_count: 1,
+                pinned: false,
+                missing: false,
+            },
+        };
+        recent.folders.insert(0, folder.clone());
+
+        // Drop the oldest unpinned folders past the limit
+        let mut unpinned = 0;
+        recent.folders.retain(|f| {
+            unpinned += usize::from(!f.pinned);
+            f.pinned || unpinned <= MAX_RECENT_FOLDERS
+        });
+        Ok(folder)
+    })
+}
+
+/// Recent folders, pinned first, optionally filtered by a quick-open query
+/// matched against the path; `missing` marks folders that no longer exist
+#[tauri::command]
+pub async fn list_recent_folders(
+    app: AppHandle,
+    query: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<RecentFolder>, String> {
+    let mut folders = load(&app)?.folders;
+    if let Some(query) = query.as_deref().filter(|q| !q.trim().is_empty()) {
+        folders.retain(|folder| matches_query(folder, query));
+    }
+    sort(&mut folders);
+    if let Some(limit) = limit {
+        folders.truncate(limit);
+    }
+
+    for folder in &mut folders {
+        folder.missing = !Path::new(&folder.path).is_dir();
+    }
+    Ok(folders)
+}
+
+#[tauri::command]
+pub async fn pin_recent_folder(
+    app: AppHandle,
+    store: State<'_, RecentFolders>,
+    path: String,
+    pinned: bool,
+) -> Result<(), String> {
+    let path = normalize(&path);
+    update(&app, &store, |recent| {
+        let folder = recent
+            .folders
+            .iter_mut()
+            .find(|f| f.path == path)
+            .ok_or_else(|| format!("Not a recent folder: {}", path))?;
+        folder.pinned = pinned;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn remove_recent_folder(
+    app: AppHandle,
+    store: State<'_, RecentFolders>,
+    path: String,
+) -> Result<(), String> {
+    let path = normalize(&path);
+    update(&app, &store, |recent| {
+        recent.folders.retain(|f| f.path != path);
+        Ok(())
+    })
+}
+
+/// Forget unpinned folders that no longer exist, returning their paths.
+///
+/// Pinned folders are kept (they may be on a drive that is not mounted)
+/// and show up as `missing` instead.
+#[tauri::command]
+pub async fn prune_recent_folders(
+    app: AppHandle,
+    store: State<'_, RecentFolders>,
+) -> Result<Vec<String>, String> {
+    update(&app, &store, |recent| {
+        let (stale, kept): (Vec<RecentFolder>, Vec<RecentFolder>) = recent
+            .folders
+            .drain(..)
+            .partition(|f| !f.pinned && !Path::new(&f.path).is_dir());
+        recent.folders = kept;
+        Ok(stale.into_iter().map(|f| f.path).collect())
+    })
+}
@@ -0,0 +1,189 @@
+use regex::{NoExpand, Regex};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+use crate::backup::FileBackup;
+use crate::ignore::IgnoreFilter;
+use crate::search::{
+    build_glob_set, build_matcher, candidate_files, read_text_file, SearchOptions, MAX_LINE_PREVIEW,
+};
+
+// ============================================================================
+// REPLACE TYPES
+// ============================================================================
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReplaceOptions {
+    /// What to find; `max_results` is not used, every match is replaced
+    #[serde(flatten)]
+    pub search: SearchOptions,
+    /// Replacement text; with `is_regex` it may refer to capture groups as
+    /// `$1` or `${name}` (`$$` for a literal dollar)
+    pub replacement: String,
+    /// Only report the planned changes, writing nothing
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Files to leave untouched, e.g. unticked in the preview
+    #[serde(default)]
+    pub excluded_files: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LineReplacement {
+    /// 1-based line number
+    pub line_number: usize,
+    /// Line before and after the replacement (truncated for very long lines)
+    pub before: String,
+    pub after: String,
+    pub replacements: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileReplacement {
+    pub path: String,
+    pub lines: Vec<LineReplacement>,
+    pub replacements: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplaceSummary {
+    pub dry_run: bool,
+    pub files_changed: usize,
+    pub total_replacements: usize,
+    pub files: Vec<FileReplacement>,
+    /// Pass to restore_backup to restore the original files
+    pub backup_id: Option<String>,
+    /// Files that could not be written, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn preview(line: &str) -> String {
+    line.chars().take(MAX_LINE_PREVIEW).collect()
+}
+
+/// Replace matches line by line, like content search reports them, keeping
+/// line endings as they are. Returns the new content and the changed lines.
+fn replace_content(
+    matcher: &Regex,
+    replacement: &str,
+    expand: bool,
+    content: &str,
+) -> Option<(String, Vec<LineReplacement>)> {
+    let mut output = String::with_capacity(content.len());
+    let mut lines = Vec::new();
+
+    for (index, raw) in content.split_inclusive('\n').enumerate() {
+        let body = raw.trim_end_matches(['\n', '\r']);
+        let ending = &raw[body.len()..];
+
+        let count = matcher.find_iter(body).count();
+        if count == 0 {
+            output.push_str(raw);
+            continue;
+        }
+
+        let replaced: Cow<str> = if expand {
+            matcher.replace_all(body, replacement)
+        } else {
+            matcher.replace_all(body, NoExpand(replacement))
+        };
+        output.push_str(&replaced);
+        output.push_str(ending);
+
+        lines.push(LineReplacement {
+            line_number: index + 1,
+            before: preview(body),
+            after: preview(&replaced),
+            replacements: count,
+        });
+    }
+
+    (!lines.is_empty()).then_some((output, lines))
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Find and replace across the files under `root_path`, using the same
+/// matching and ignore rules as search_in_files.
+///
+/// With `dry_run` every planned change is returned and nothing is written.
+/// Otherwise each changed file is first copied into a backup (see
+/// restore_backup) and then written back atomically; a file that fails is
+/// reported in `failed` without stopping the rest.
+#[tauri::command]
+pub async fn replace_in_files(
+    app: AppHandle,
+    root_path: String,
+    options: ReplaceOptions,
+) -> Result<ReplaceSummary, String> {
+    let matcher = build_matcher(&options.search)?;
+    let include = build_glob_set(&options.search.include)?;
+    let exclude = build_glob_set(&options.search.exclude)?;
+
+    let root = PathBuf::from(&root_path);
+    if !root.is_dir() {
+        return Err(format!("Path is not a directory: {}", root_path));
+    }
+    let filter = IgnoreFilter::load(&root)?;
+    let backup = FileBackup::new(&app)?;
+
+    tokio::task::spawn_blocking(move || {
+        let excluded: Vec<PathBuf> = options.excluded_files.iter().map(PathBuf::from).collect();
+        let mut summary = ReplaceSummary {
+            dry_run: options.dry_run,
+            files_changed: 0,
+            total_replacements: 0,
+            files: Vec::new(),
+            backup_id: None,
+            failed: Vec::new(),
+        };
+        let mut backup = (!options.dry_run).then_some(backup);
+
+        for entry in candidate_files(&root, &filter, &include, &exclude) {
+            let path = entry.path();
+            if excluded.iter().any(|e| e == path) {
+                continue;
+            }
+            let Some(content) = read_text_file(path) else {
+                continue;
+            };
+            let Some((replaced, lines)) = replace_content(
+                &matcher,
+                &options.replacement,
+                options.search.is_regex,
+                &content,
+            ) else {
+                continue;
+            };
+
+            if let Some(backup) = &mut backup {
+                if let Err(e) = backup.write(path, content.as_bytes(), replaced.as_bytes()) {
+                    summary.failed.push((path.to_string_lossy().to_string(), e));
+                    continue;
+                }
+            }
+
+            let replacements = lines.iter().map(|l| l.replacements).sum();
+            summary.files_changed += 1;
+            summary.total_replacements += replacements;
+            summary.files.push(FileReplacement {
+                path: path.to_string_lossy().to_string(),
+                lines,
+                replacements,
+            });
+        }
+
+        summary.backup_id = backup.and_then(|b| b.id());
+        Ok(summary)
+    })
+    .await
+    .map_err(|e| format!("Replace task failed: {}", e))?
+}
@@ -0,0 +1,255 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+use tauri::{AppHandle, Emitter};
+use walkdir::{DirEntry, WalkDir};
+
+use crate::ignore::IgnoreFilter;
+
+// ============================================================================
+// CONTENT SEARCH TYPES
+// ============================================================================
+
+/// Event emitted for every file that contains at least one match
+pub const SEARCH_RESULT_EVENT: &str = "search://result";
+
+/// Event emitted once the search has finished
+pub const SEARCH_DONE_EVENT: &str = "search://done";
+
+const DEFAULT_MAX_RESULTS: usize = 2000;
+const MAX_FILE_SIZE: u64 = 2 * 1024 * 1024;
+pub(crate) const MAX_LINE_PREVIEW: usize = 500;
+const BINARY_SNIFF_LEN: usize = 8192;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SearchOptions {
+    pub query: String,
+    #[serde(default)]
+    pub is_regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Only search files matching one of these globs (relative to the root)
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Skip files matching any of these globs (relative to the root)
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Stop after this many matching lines
+    pub max_results: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LineMatch {
+    /// 1-based line number
+    pub line_number: usize,
+    /// Line text (truncated for very long lines)
+    pub line_text: String,
+    /// Character ranges of the matches within the line
+    pub ranges: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileMatches {
+    pub search_id: String,
+    pub path: String,
+    pub matches: Vec<LineMatch>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchSummary {
+    pub search_id: String,
+    pub files_searched: usize,
+    pub files_matched: usize,
+    pub total_matches: usize,
+    /// Whether the result cap was hit before the search completed
+    pub truncated: bool,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+pub(crate) fn build_glob_set(patterns: &[String]) -> Result<Option<GlobSet>, String> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| format!("Invalid glob '{}': {}", pattern, e))?;
+        builder.add(glob);
+    }
+
+    builder
+        .build()
+        .map(Some)
+        .map_err(|e| format!("Invalid glob set: {}", e))
+}
+
+pub(crate) fn build_matcher(options: &SearchOptions) -> Result<Regex, String> {
+    if options.query.is_empty() {
+        return Err("Search query is empty".to_string());
+    }
+
+    let pattern = if options.is_regex {
+        options.query.clone()
+    } else {
+        regex::escape(&options.query)
+    };
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|e| format!("Invalid regex: {}", e))
+}
+
+/// Read a file as text, skipping large and binary files
+pub(crate) fn read_text_file(path: &Path) -> Option<String> {
+    let metadata = std::fs::metadata(path).ok()?;
+    if metadata.len() > MAX_FILE_SIZE {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(metadata.len() as usize);
+    std::fs::File::open(path).ok()?.read_to_end(&mut bytes).ok()?;
+
+    let sniff = &bytes[..bytes.len().min(BINARY_SNIFF_LEN)];
+    if sniff.contains(&0) {
+        return None;
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+/// Files under `root` that are not ignored and pass the include/exclude globs
+pub(crate) fn candidate_files<'a>(
+    root: &'a Path,
+    filter: &'a IgnoreFilter,
+    include: &'a Option<GlobSet>,
+    exclude: &'a Option<GlobSet>,
+) -> impl Iterator<Item = DirEntry> + 'a {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !filter.is_ignored(e.path(), e.file_type().is_dir()))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(move |e| {
+            let relative = e.path().strip_prefix(root).unwrap_or(e.path());
+            include.as_ref().is_none_or(|include| include.is_match(relative))
+                && !exclude.as_ref().is_some_and(|exclude| exclude.is_match(relative))
+        })
+}
+
+fn byte_to_char_offset(line: &str, byte_offset: usize) -> usize {
+    line[..byte_offset].chars().count()
+}
+
+fn search_file(matcher: &Regex, content: &str, remaining: usize) -> Vec<LineMatch> {
+    let mut matches = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        if matches.len() >= remaining {
+            break;
+        }
+
+        let ranges: Vec<(usize, usize)> = matcher
+            .find_iter(line)
+            .map(|m| {
+                (
+                    byte_to_char_offset(line, m.start()),
+                    byte_to_char_offset(line, m.end()),
+                )
+            })
+            .collect();
+
+        if ranges.is_empty() {
+            continue;
+        }
+
+        matches.push(LineMatch {
+            line_number: index + 1,
+            line_text: line.chars().take(MAX_LINE_PREVIEW).collect(),
+            ranges,
+        });
+    }
+
+    matches
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Search file contents under `root_path`.
+///
+/// Matches are streamed per file as `search://result` events tagged with the
+/// search id; a final `search://done` event carries the same summary that is
+/// returned from the command.
+#[tauri::command]
+pub async fn search_in_files(
+    app: AppHandle,
+    root_path: String,
+    options: SearchOptions,
+    search_id: Option<String>,
+) -> Result<SearchSummary, String> {
+    let search_id = search_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let matcher = build_matcher(&options)?;
+    let include = build_glob_set(&options.include)?;
+    let exclude = build_glob_set(&options.exclude)?;
+    let max_results = options.max_results.unwrap_or(DEFAULT_MAX_RESULTS).max(1);
+
+    let root = std::path::PathBuf::from(&root_path);
+    if !root.is_dir() {
+        return Err(format!("Path is not a directory: {}", root_path));
+    }
+    let filter = IgnoreFilter::load(&root)?;
+
+    let summary = tokio::task::spawn_blocking(move || {
+        let mut summary = SearchSummary {
+            search_id: search_id.clone(),
+            files_searched: 0,
+            files_matched: 0,
+            total_matches: 0,
+            truncated: false,
+        };
+
+        for entry in candidate_files(&root, &filter, &include, &exclude) {
+            let Some(content) = read_text_file(entry.path()) else {
+                continue;
+            };
+            summary.files_searched += 1;
+
+            let remaining = max_results - summary.total_matches;
+            let matches = search_file(&matcher, &content, remaining);
+            if matches.is_empty() {
+                continue;
+            }
+
+            summary.files_matched += 1;
+            summary.total_matches += matches.len();
+
+            let _ = app.emit(
+                SEARCH_RESULT_EVENT,
+                FileMatches {
+                    search_id: search_id.clone(),
+                    path: entry.path().to_string_lossy().to_string(),
+                    matches,
+                },
+            );
+
+            if summary.total_matches >= max_results {
+                summary.truncated = true;
+                break;
+            }
+        }
+
+        let _ = app.emit(SEARCH_DONE_EVENT, summary.clone());
+        summary
+    })
+    .await
+    .map_err(|e| format!("Search task failed: {}", e))?;
+
+    Ok(summary)
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+// ============================================================================
+// SECRET TYPES
+// ============================================================================
+
+/// Keychain service every secret is stored under (the app identifier)
+const SERVICE: &str = "com.codecollab.dev";
+
+/// Start of a `{{secret:name}}` reference in request and auth fields
+const REFERENCE_PREFIX: &str = "{{secret:";
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn entry(key: &str) -> Result<keyring::Entry, String> {
+    if key.trim().is_empty() {
+        return Err("Secret key cannot be empty".to_string());
+    }
+    keyring::Entry::new(SERVICE, key).map_err(|e| format!("Failed to open keychain: {}", e))
+}
+
+/// Keychain key of a variable marked secret in an environment
+pub(crate) fn environment_key(environment_id: &str, variable: &str) -> String {
+    format!("env:{}:{}", environment_id, variable)
+}
+
+pub(crate) fn write_secret(key: &str, value: &str) -> Result<(), String> {
+    entry(key)?
+        .set_password(value)
+        .map_err(|e| format!("Failed to store secret: {}", e))
+}
+
+/// The stored value, or None when there is no secret under `key`
+pub(crate) fn read_secret(key: &str) -> Result<Option<String>, String> {
+    match entry(key)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read secret: {}", e)),
+    }
+}
+
+/// Remove a secret; removing one that does not exist is not an error
+pub(crate) fn remove_secret(key: &str) -> Result<(), String> {
+    match entry(key)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to delete secret: {}", e)),
+    }
+}
+
+/// Replace `{{secret:name}}` references with the stored secrets. Unknown or
+/// unreadable secrets are left as written, like unknown variables.
+pub(crate) fn expand_references(text: &str) -> String {
+    if !text.contains(REFERENCE_PREFIX) {
+        return text.to_string();
+    }
+
+    let mut values = HashMap::new();
+    for (start, _) in text.match_indices(REFERENCE_PREFIX) {
+        let rest = &text[start + REFERENCE_PREFIX.len()..];
+        let Some(end) = rest.find("}}") else {
+            continue;
+        };
+        let name = rest[..end].trim();
+        match read_secret(name) {
+            Ok(Some(value)) => {
+                values.insert(format!("secret:{}", name), value);
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!("Secret {} is unavailable: {}", name, e),
+        }
+    }
+    crate::http::collections::substitute_variables(text, &values)
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Store a secret (an API token, a password) in the OS keychain. Requests and
+/// auth settings can then refer to it as `{{secret:key}}`.
+#[tauri::command]
+pub async fn store_secret(key: String, value: String) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || write_secret(&key, &value))
+        .await
+        .map_err(|e| format!("Keychain task failed: {}", e))?
+}
+
+/// The secret stored under `key`, or None when there is none
+#[tauri::command]
+pub async fn get_secret(key: String) -> Result<Option<String>, String> {
+    tokio::task::spawn_blocking(move || read_secret(&key))
+        .await
+        .map_err(|e| format!("Keychain task failed: {}", e))?
+}
+
+#[tauri::command]
+pub async fn delete_secret(key: String) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || remove_secret(&key))
+        .await
+        .map_err(|e| format!("Keychain task failed: {}", e))?
+}
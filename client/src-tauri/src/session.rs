@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, Window};
+
+use crate::http::HttpRequest;
+
+// ============================================================================
+// WORKSPACE SESSION TYPES
+// ============================================================================
+
+/// Bumped when the session layout changes incompatibly
+const SESSION_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct CursorPosition {
+    pub line: u32,
+    pub column: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct OpenTab {
+    pub path: String,
+    pub is_active: bool,
+    pub cursor: Option<CursorPosition>,
+    pub scroll_top: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HttpRequestDraft {
+    pub id: String,
+    pub name: String,
+    pub request: HttpRequest,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct WorkspaceState {
+    pub version: u32,
+    /// Folder opened in the file tree
+    pub folder: Option<String>,
+    /// Root folders of a multi-root workspace (see open_workspace)
+    pub roots: Vec<crate::workspace::WorkspaceRoot>,
+    /// Paths of expanded directories in the file tree
+    pub expanded_nodes: Vec<String>,
+    pub open_tabs: Vec<OpenTab>,
+    pub http_drafts: Vec<HttpRequestDraft>,
+    /// When the state was saved (milliseconds since epoch)
+    pub saved_at: i64,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// One session file per window so each window restores its own layout
+fn session_path(app: &AppHandle, window: &Window) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?
+        .join("sessions");
+
+    let label: String = window
+        .label()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    Ok(dir.join(format!("{}.json", label)))
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+#[tauri::command]
+pub async fn save_workspace_state(
+    app: AppHandle,
+    window: Window,
+    mut state: WorkspaceState,
+) -> Result<(), String> {
+    let path = session_path(&app, &window)?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create session directory: {}", e))?;
+    }
+
+    state.version = SESSION_VERSION;
+    state.saved_at = chrono::Utc::now().timestamp_millis();
+
+    let json = serde_json::to_vec_pretty(&state)
+        .map_err(|e| format!("Failed to serialize workspace state: {}", e))?;
+
+    crate::atomic_write(&path, &json)
+}
+
+/// Load the last saved state for the calling window, if any
+#[tauri::command]
+pub async fn load_workspace_state(
+    app: AppHandle,
+    window: Window,
+) -> Result<Option<WorkspaceState>, String> {
+    let path = session_path(&app, &window)?;
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let json =
+        std::fs::read(&path).map_err(|e| format!("Failed to read workspace state: {}", e))?;
+
+    match serde_json::from_slice::<WorkspaceState>(&json) {
+        Ok(state) if state.version <= SESSION_VERSION => Ok(Some(state)),
+        Ok(_) => Ok(None),
+        Err(e) => {
+            // A corrupt session should never block startup
+            log::warn!("Ignoring unreadable workspace state: {}", e);
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+
+// ============================================================================
+// SETTINGS TYPES
+// ============================================================================
+
+/// Event emitted to subscribed windows whenever a setting changes
+pub const SETTINGS_CHANGED_EVENT: &str = "settings://changed";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct EditorSettings {
+    pub font_size: u32,
+    pub font_family: String,
+    pub tab_size: u32,
+    pub insert_spaces: bool,
+    pub word_wrap: bool,
+    pub format_on_save: bool,
+    /// Save edited files after this many milliseconds; None turns auto-save off
+    pub auto_save_delay_ms: Option<u64>,
+    pub theme: String,
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        EditorSettings {
+            font_size: 14,
+            font_family: "JetBrains Mono, monospace".to_string(),
+            tab_size: 4,
+            insert_spaces: true,
+            word_wrap: false,
+            format_on_save: false,
+            auto_save_delay_ms: None,
+            theme: "dark".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollabSettings {
+    /// Server the join dialog connects to by default
+    pub server_url: String,
+    pub display_name: Option<String>,
+}
+
+impl Default for CollabSettings {
+    fn default() -> Self {
+        CollabSettings {
+            server_url: "ws://localhost:5000".to_string(),
+            display_name: None,
+        }
+    }
+}
+
+/// Used by HTTP requests that do not set these themselves
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpDefaults {
+    pub timeout_ms: u64,
+    pub follow_redirects: bool,
+    pub max_redirects: usize,
+}
+
+impl Default for HttpDefaults {
+    fn default() -> Self {
+        HttpDefaults {
+            timeout_ms: 30000,
+            follow_redirects: true,
+            max_redirects: crate::http::capture::DEFAULT_MAX_REDIRECTS,
+        }
+    }
+}
+
+/// Every app setting; keys are dotted paths such as `editor.font_size`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub editor: EditorSettings,
+    pub collab: CollabSettings,
+    pub http: HttpDefaults,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SettingChange {
+    pub key: String,
+    pub value: Value,
+}
+
+/// Serializes writes to the settings file and tracks which windows want
+/// change events for which keys
+#[derive(Default)]
+pub struct SettingsStore {
+    lock: Mutex<()>,
+    /// Key prefixes per window label; an empty list means every key
+    subscriptions: Mutex<HashMap<String, Vec<String>>>,
+}
+
+impl SettingsStore {
+    /// Forget a window's subscriptions (e.g. when it is closed)
+    pub fn close_window(&self, label: &str) {
+        if let Ok(mut subscriptions) = self.subscriptions.lock() {
+            subscriptions.remove(label);
+        }
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?
+        .join("settings.json"))
+}
+
+/// Saved settings, with defaults for anything missing or unreadable
+pub(crate) fn load(app: &AppHandle) -> Settings {
+    let Ok(path) = settings_path(app) else {
+        return Settings::default();
+    };
+    let Ok(json) = std::fs::read(&path) else {
+        return Settings::default();
+    };
+    serde_json::from_slice(&json).unwrap_or_else(|e| {
+        // A bad settings file should never keep the app from starting
+        log::warn!("Ignoring unreadable settings: {}", e);
+        Settings::default()
+    })
+}
+
+fn save(app: &AppHandle, settings: &Settings) -> Result<(), String> {
+    let path = settings_path(app)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    }
+    let json = serde_json::to_vec_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    crate::atomic_write(&path, &json)
+}
+
+fn validate(settings: &Settings) -> Result<(), String> {
+    let editor = &settings.editor;
+    if !(6..=72).contains(&editor.font_size) {
+        return Err("editor.font_size must be between 6 and 72".to_string());
+    }
+    if !(1..=16).contains(&editor.tab_size) {
+        return Err("editor.tab_size must be between 1 and 16".to_string());
+    }
+    if editor.auto_save_delay_ms.is_some_and(|delay| delay < 100) {
+        return Err("editor.auto_save_delay_ms must be at least 100".to_string());
+    }
+
+    let url = url::Url::parse(&settings.collab.server_url)
+        .map_err(|e| format!("collab.server_url is not a valid URL: {}", e))?;
+    if !matches!(url.scheme(), "ws" | "wss" | "http" | "https") {
+        return Err("collab.server_url must be a ws, wss, http or https URL".to_string());
+    }
+
+    if !(1..=600_000).contains(&settings.http.timeout_ms) {
+        return Err("http.timeout_ms must be between 1 and 600000".to_string());
+    }
+    if settings.http.max_redirects > 50 {
+        return Err("http.max_redirects must be at most 50".to_string());
+    }
+    Ok(())
+}
+
+/// Value at a dotted key, or None when the key is not part of the schema
+fn value_at<'a>(root: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.')
+        .try_fold(root, |value, part| value.as_object()?.get(part))
+}
+
+fn value_at_mut<'a>(root: &'a mut Value, key: &str) -> Option<&'a mut Value> {
+    key.split('.')
+        .try_fold(root, |value, part| value.as_object_mut()?.get_mut(part))
+}
+
+/// Apply `update` to the settings as JSON, check the result against the
+/// schema and save it, then notify the windows subscribed to `key`
+fn update(
+    app: &AppHandle,
+    store: &SettingsStore,
+    key: &str,
+    update: impl FnOnce(&mut Value) -> Result<(), String>,
+) -> Result<Value, String> {
+    let value = {
+        let _guard = store
+            .lock
+            .lock()
+            .map_err(|_| "Settings lock is poisoned".to_string())?;
+
+        let mut json = serde_json::to_value(load(app))
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        if value_at(&json, key).is_none() {
+            return Err(format!("Unknown setting: {}", key));
+        }
+        update(&mut json)?;
+
+        let settings: Settings = serde_json::from_value(json)
+            .map_err(|e| format!("Invalid value for {}: {}", key, e))?;
+        validate(&settings)?;
+        save(app, &settings)?;
+
+        let json = serde_json::to_value(&settings)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        value_at(&json, key).cloned().unwrap_or(Value::Null)
+    };
+
+    let change = SettingChange {
+        key: key.to_string(),
+        value: value.clone(),
+    };
+    let labels: Vec<String> = store
+        .subscriptions
+        .lock()
+        .map_err(|_| "Settings subscriptions are poisoned".to_string())?
+        .iter()
+        .filter(|(_, prefixes)| {
+            prefixes.is_empty()
+                || prefixes.iter().any(|prefix| {
+                    key == prefix
+                        || key.starts_with(&format!("{}.", prefix))
+                        || prefix.starts_with(&format!("{}.", key))
+                })
+        })
+        .map(|(label, _)| label.clone())
+        .collect();
+    for label in labels {
+        let _ = app.emit_to(label.as_str(), SETTINGS_CHANGED_EVENT, change.clone());
+    }
+
+    Ok(value)
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Value of a setting such as `editor.font_size` or a whole group such as
+/// `editor`; every setting when no key is given
+#[tauri::command]
+pub async fn get_setting(app: AppHandle, key: Option<String>) -> Result<Value, String> {
+    let json = serde_json::to_value(load(&app))
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    match key {
+        None => Ok(json),
+        Some(key) => value_at(&json, &key)
+            .cloned()
+            .ok_or_else(|| format!("Unknown setting: {}", key)),
+    }
+}
+
+/// Change a setting (or a whole group) after checking it against the
+/// schema, returning the stored value. Subscribed windows receive a
+/// `settings://changed` event.
+#[tauri::command]
+pub async fn set_setting(
+    app: AppHandle,
+    store: State<'_, SettingsStore>,
+    key: String,
+    value: Value,
+) -> Result<Value, String> {
+    update(&app, &store, &key, |json| {
+        if let Some(slot) = value_at_mut(json, &key) {
+            *slot = value;
+        }
+        Ok(())
+    })
+}
+
+/// Put a setting (or a whole group) back to its default
+#[tauri::command]
+pub async fn reset_setting(
+    app: AppHandle,
+    store: State<'_, SettingsStore>,
+    key: String,
+) -> Result<Value, String> {
+    let defaults = serde_json::to_value(Settings::default())
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    let default = value_at(&defaults, &key)
+        .cloned()
+        .ok_or_else(|| format!("Unknown setting: {}", key))?;
+
+    update(&app, &store, &key, |json| {
+        if let Some(slot) = value_at_mut(json, &key) {
+            *slot = default;
+        }
+        Ok(())
+    })
+}
+
+/// Send the calling window `settings://changed` events for the given keys
+/// (or groups), replacing its previous subscription; an empty list
+/// subscribes to every setting. Returns the current settings.
+#[tauri::command]
+pub async fn subscribe_settings(
+    app: AppHandle,
+    window: Window,
+    store: State<'_, SettingsStore>,
+    keys: Vec<String>,
+) -> Result<Value, String> {
+    store
+        .subscriptions
+        .lock()
+        .map_err(|_| "Settings subscriptions are poisoned".to_string())?
+        .insert(window.label().to_string(), keys);
+
+    serde_json::to_value(load(&app)).map_err(|e| format!("Failed to serialize settings: {}", e))
+}
+
+#[tauri::command]
+pub async fn unsubscribe_settings(
+    window: Window,
+    store: State<'_, SettingsStore>,
+) -> Result<(), String> {
+    store.close_window(window.label());
+    Ok(())
+}
@@ -0,0 +1,277 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::sync::oneshot;
+
+// ============================================================================
+// TASK RUNNER TYPES
+// ============================================================================
+
+/// Event carrying one line of task output, emitted to the window that started it
+pub const RUN_OUTPUT_EVENT: &str = "run://output";
+
+/// Event emitted when a task finishes or is cancelled
+pub const RUN_EXIT_EVENT: &str = "run://exit";
+
+/// Programs that may run without asking the user first, when given by bare
+/// name and so found on PATH
+const ALLOWED_PROGRAMS: &[&str] = &[
+    "cargo", "npm", "pnpm", "yarn", "bun", "npx", "node", "deno", "python", "python3", "pip", "go",
+    "make", "rustc", "tsc", "pytest",
+];
+
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RunOutput {
+    pub run_id: String,
+    pub stream: OutputStream,
+    pub line: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RunExit {
+    pub run_id: String,
+    /// None when the process was killed by a signal or could not be waited on
+    pub exit_code: Option<i32>,
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RunStart {
+    Started {
+        run_id: String,
+    },
+    /// The program is not allowlisted; ask the user and call again with `confirmed`
+    ConfirmationRequired {
+        program: String,
+        command_line: String,
+    },
+}
+
+struct RunningTask {
+    window_label: String,
+    cancel: oneshot::Sender<()>,
+}
+
+/// Running tasks and the programs the user approved this session, by the
+/// program string exactly as it was given
+#[derive(Default)]
+pub struct TaskRegistry {
+    running: Mutex<HashMap<String, RunningTask>>,
+    approved: Mutex<HashSet<String>>,
+}
+
+impl TaskRegistry {
+    /// Kill every task started by a window (e.g. when it is closed)
+    pub fn close_window(&self, label: &str) {
+        let Ok(mut running) = self.running.lock() else {
+            return;
+        };
+
+        let ids: Vec<String> = running
+            .iter()
+            .filter(|(_, task)| task.window_label == label)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in ids {
+            if let Some(task) = running.remove(&id) {
+                let _ = task.cancel.send(());
+            }
+        }
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Whether a program may run without confirmation. Only bare names count:
+/// `./npm` or `/tmp/x/cargo` are whatever sits at that path, not the tools
+/// on PATH.
+fn is_allowlisted(program: &str) -> bool {
+    if program.contains(['/', '\\']) {
+        return false;
+    }
+    let name = program.to_lowercase();
+    let name = [".exe", ".cmd", ".bat"]
+        .iter()
+        .find_map(|ext| name.strip_suffix(ext))
+        .unwrap_or(&name);
+    ALLOWED_PROGRAMS.contains(&name)
+}
+
+fn spawn_line_forwarder<R>(
+    app: AppHandle,
+    label: String,
+    run_id: String,
+    stream: OutputStream,
+    reader: R,
+) where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let _ = app.emit_to(
+                label.as_str(),
+                RUN_OUTPUT_EVENT,
+                RunOutput {
+                    run_id: run_id.clone(),
+                    stream,
+                    line,
+                },
+            );
+        }
+    });
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Run a project task in `cwd`, streaming its output as `run://output` events.
+///
+/// Programs outside the allowlist require the user's confirmation; once
+/// confirmed they are allowed for the rest of the session. Variables from
+/// `env_files` (.env files, later ones overriding earlier ones) are added to
+/// the task's environment.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn run_command(
+    app: AppHandle,
+    window: Window,
+    registry: State<'_, TaskRegistry>,
+    cwd: String,
+    program: String,
+    args: Vec<String>,
+    confirmed: Option<bool>,
+    env_files: Option<Vec<String>>,
+) -> Result<RunStart, String> {
+    let cwd_path = PathBuf::from(&cwd);
+    if !cwd_path.is_dir() {
+        return Err(format!("Path is not a directory: {}", cwd));
+    }
+    if program.trim().is_empty() {
+        return Err("No program given".to_string());
+    }
+
+    {
+        let mut approved = registry
+            .approved
+            .lock()
+            .map_err(|_| "Task registry is poisoned".to_string())?;
+
+        if !is_allowlisted(&program) && !approved.contains(&program) {
+            if !confirmed.unwrap_or(false) {
+                return Ok(RunStart::ConfirmationRequired {
+                    command_line: std::iter::once(program.as_str())
+                        .chain(args.iter().map(String::as_str))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    program,
+                });
+            }
+            approved.insert(program.clone());
+        }
+    }
+
+    let env = crate::dotenv::load_env_sets(&env_files.unwrap_or_default())?;
+    let mut child = Command::new(&program)
+        .args(&args)
+        .envs(env)
+        .current_dir(&cwd_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    let run_id = uuid::Uuid::new_v4().to_string();
+    let label = window.label().to_string();
+
+    if let Some(stdout) = child.stdout.take() {
+        spawn_line_forwarder(
+            app.clone(),
+            label.clone(),
+            run_id.clone(),
+            OutputStream::Stdout,
+            stdout,
+        );
+    }
+    if let Some(stderr) = child.stderr.take() {
+        spawn_line_forwarder(
+            app.clone(),
+            label.clone(),
+            run_id.clone(),
+            OutputStream::Stderr,
+            stderr,
+        );
+    }
+
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+    registry
+        .running
+        .lock()
+        .map_err(|_| "Task registry is poisoned".to_string())?
+        .insert(
+            run_id.clone(),
+            RunningTask {
+                window_label: label.clone(),
+                cancel: cancel_tx,
+            },
+        );
+
+    let exit_run_id = run_id.clone();
+    tokio::spawn(async move {
+        let (status, cancelled) = tokio::select! {
+            status = child.wait() => (status.ok(), false),
+            _ = cancel_rx => {
+                let _ = child.kill().await;
+                (None, true)
+            }
+        };
+
+        if let Ok(mut running) = app.state::<TaskRegistry>().running.lock() {
+            running.remove(&exit_run_id);
+        }
+
+        let _ = app.emit_to(
+            label.as_str(),
+            RUN_EXIT_EVENT,
+            RunExit {
+                run_id: exit_run_id,
+                exit_code: status.and_then(|s| s.code()),
+                cancelled,
+            },
+        );
+    });
+
+    Ok(RunStart::Started { run_id })
+}
+
+/// Kill a running task
+#[tauri::command]
+pub async fn cancel_run(registry: State<'_, TaskRegistry>, run_id: String) -> Result<(), String> {
+    let task = registry
+        .running
+        .lock()
+        .map_err(|_| "Task registry is poisoned".to_string())?
+        .remove(&run_id)
+        .ok_or_else(|| format!("No running task: {}", run_id))?;
+
+    let _ = task.cancel.send(());
+    Ok(())
+}
@@ -0,0 +1,361 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::ignore::IgnoreFilter;
+use crate::search::candidate_files;
+use crate::FileNode;
+
+// ============================================================================
+// TEMPLATE TYPES
+// ============================================================================
+
+/// User templates live under the app data directory, one folder each
+const TEMPLATES_DIR: &str = "templates";
+
+/// Description of a user template, at the top of its folder
+const TEMPLATE_MANIFEST: &str = "template.json";
+
+/// A built-in template: relative path and content of every file
+struct BuiltinTemplate {
+    id: &'static str,
+    name: &'static str,
+    description: &'static str,
+    files: &'static [(&'static str, &'static str)],
+}
+
+const BUILTIN_TEMPLATES: &[BuiltinTemplate] = &[
+    BuiltinTemplate {
+        id: "rust",
+        name: "Rust binary",
+        description: "A Cargo project with a hello world main.rs",
+        files: &[
+            (
+                "Cargo.toml",
+                "[package]\nname = \"{{package_name}}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n",
+            ),
+            (
+                "src/main.rs",
+                "fn main() {\n    println!(\"Hello from {{project_name}}!\");\n}\n",
+            ),
+            (".gitignore", "/target\n"),
+            ("README.md", "# {{project_name}}\n\n```sh\ncargo run\n```\n"),
+        ],
+    },
+    BuiltinTemplate {
+        id: "node",
+        name: "Node.js",
+        description: "A package.json with an ES module entry point",
+        files: &[
+            (
+                "package.json",
+                "{\n  \"name\": \"{{package_name}}\",\n  \"version\": \"0.1.0\",\n  \"type\": \"module\",\n  \"main\": \"index.js\",\n  \"scripts\": {\n    \"start\": \"node index.js\"\n  }\n}\n",
+            ),
+            ("index.js", "console.log(\"Hello from {{project_name}}!\");\n"),
+            (".gitignore", "node_modules/\n"),
+            ("README.md", "# {{project_name}}\n\n```sh\nnpm start\n```\n"),
+        ],
+    },
+    BuiltinTemplate {
+        id: "python",
+        name: "Python",
+        description: "A main.py with a requirements.txt",
+        files: &[
+            (
+                "main.py",
+                "def main():\n    print(\"Hello from {{project_name}}!\")\n\n\nif __name__ == \"__main__\":\n    main()\n",
+            ),
+            ("requirements.txt", ""),
+            (".gitignore", "__pycache__/\n.venv/\n"),
+            (
+                "README.md",
+                "# {{project_name}}\n\n```sh\npython -m venv .venv\npip install -r requirements.txt\npython main.py\n```\n",
+            ),
+        ],
+    },
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectTemplate {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Placeholders the template uses besides the built-in ones, with
+    /// their default values
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    #[serde(skip_deserializing)]
+    pub builtin: bool,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn templates_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?
+        .join(TEMPLATES_DIR))
+}
+
+fn builtin_info(template: &BuiltinTemplate) -> ProjectTemplate {
+    ProjectTemplate {
+        id: template.id.to_string(),
+        name: template.name.to_string(),
+        description: template.description.to_string(),
+        variables: HashMap::new(),
+        builtin: true,
+    }
+}
+
+fn user_templates(app: &AppHandle) -> Result<Vec<(PathBuf, ProjectTemplate)>, String> {
+    let dir = templates_dir(app)?;
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+
+    let mut templates = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let Ok(json) = std::fs::read(path.join(TEMPLATE_MANIFEST)) else {
+            continue;
+        };
+        match serde_json::from_slice::<ProjectTemplate>(&json) {
+            Ok(mut template) => {
+                // The folder name is the id, whatever the manifest says
+                template.id = entry.file_name().to_string_lossy().to_string();
+                templates.push((path, template));
+            }
+            Err(e) => log::warn!("Ignoring template {}: {}", path.display(), e),
+        }
+    }
+    Ok(templates)
+}
+
+/// Lowercase words joined by `separator`, e.g. "My App" -> "my-app"
+fn slug(name: &str, separator: char) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(&separator.to_string())
+}
+
+/// Values of the built-in placeholders, overridden by the caller's
+fn placeholders(
+    project_name: &str,
+    defaults: &HashMap<String, String>,
+    variables: HashMap<String, String>,
+) -> Vec<(String, String)> {
+    let mut values: HashMap<String, String> = defaults.clone();
+    values.insert("project_name".to_string(), project_name.to_string());
+    values.insert("package_name".to_string(), slug(project_name, '-'));
+    values.insert("crate_name".to_string(), slug(project_name, '_'));
+    values.insert(
+        "year".to_string(),
+        chrono::Utc::now().format("%Y").to_string(),
+    );
+    values.extend(variables);
+
+    values
+        .into_iter()
+        .map(|(key, value)| (format!("{{{{{}}}}}", key), value))
+        .collect()
+}
+
+fn substitute(text: &str, placeholders: &[(String, String)]) -> String {
+    placeholders
+        .iter()
+        .fold(text.to_string(), |text, (placeholder, value)| {
+            text.replace(placeholder, value)
+        })
+}
+
+/// Write one template file below `root`, refusing paths that leave it
+fn write_template_file(root: &Path, relative: &str, content: &[u8]) -> Result<(), String> {
+    let relative = Path::new(relative);
+    if relative
+        .components()
+        .any(|c| !matches!(c, std::path::Component::Normal(_)))
+    {
+        return Err(format!(
+            "Invalid template file path: {}",
+            relative.display()
+        ));
+    }
+
+    let target = root.join(relative);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    std::fs::write(&target, content)
+        .map_err(|e| format!("Failed to write {}: {}", target.display(), e))
+}
+
+fn generate(
+    app: &AppHandle,
+    template_id: &str,
+    root: &Path,
+    project_name: &str,
+    variables: HashMap<String, String>,
+) -> Result<(), String> {
+    if let Some(builtin) = BUILTIN_TEMPLATES.iter().find(|t| t.id == template_id) {
+        let placeholders = placeholders(project_name, &HashMap::new(), variables);
+        for (path, content) in builtin.files {
+            write_template_file(
+                root,
+                &substitute(path, &placeholders),
+                substitute(content, &placeholders).as_bytes(),
+            )?;
+        }
+        return Ok(());
+    }
+
+    let (dir, template) = user_templates(app)?
+        .into_iter()
+        .find(|(_, t)| t.id == template_id)
+        .ok_or_else(|| format!("Unknown template: {}", template_id))?;
+    let placeholders = placeholders(project_name, &template.variables, variables);
+
+    for entry in walkdir::WalkDir::new(&dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let relative = entry
+            .path()
+            .strip_prefix(&dir)
+            .unwrap_or(entry.path())
+            .to_string_lossy()
+            .replace('\\', "/");
+        if relative == TEMPLATE_MANIFEST {
+            continue;
+        }
+
+        let bytes = std::fs::read(entry.path())
+            .map_err(|e| format!("Failed to read template file: {}", e))?;
+        // Only text files get placeholders replaced; anything else is copied as is
+        let content = match String::from_utf8(bytes) {
+            Ok(text) => substitute(&text, &placeholders).into_bytes(),
+            Err(e) => e.into_bytes(),
+        };
+        write_template_file(root, &substitute(&relative, &placeholders), &content)?;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Built-in templates followed by the user's own
+#[tauri::command]
+pub async fn list_project_templates(app: AppHandle) -> Result<Vec<ProjectTemplate>, String> {
+    let mut templates: Vec<ProjectTemplate> = BUILTIN_TEMPLATES.iter().map(builtin_info).collect();
+    templates.extend(user_templates(&app)?.into_iter().map(|(_, t)| t));
+    Ok(templates)
+}
+
+/// Create `parent_dir/project_name` from a template and return its tree.
+///
+/// `{{project_name}}`, `{{package_name}}` (kebab-case), `{{crate_name}}`
+/// (snake_case), `{{year}}` and the template's own variables are replaced
+/// in file contents and paths; `variables` overrides any of them. Nothing
+/// is left behind if generation fails.
+#[tauri::command]
+pub async fn create_project_from_template(
+    app: AppHandle,
+    template_id: String,
+    parent_dir: String,
+    project_name: String,
+    variables: Option<HashMap<String, String>>,
+) -> Result<FileNode, String> {
+    let project_name = project_name.trim();
+    if project_name.is_empty() || project_name.contains(['/', '\\']) || project_name == ".." {
+        return Err(format!("Invalid project name: {}", project_name));
+    }
+    let parent = Path::new(&parent_dir);
+    if !parent.is_dir() {
+        return Err(format!("Path is not a directory: {}", parent_dir));
+    }
+    let root = parent.join(project_name);
+    if root.exists() {
+        return Err(format!("Path already exists: {}", root.display()));
+    }
+
+    std::fs::create_dir(&root).map_err(|e| format!("Failed to create project folder: {}", e))?;
+    if let Err(e) = generate(
+        &app,
+        &template_id,
+        &root,
+        project_name,
+        variables.unwrap_or_default(),
+    ) {
+        let _ = std::fs::remove_dir_all(&root);
+        return Err(e);
+    }
+
+    crate::folder_node(&root.to_string_lossy(), None)
+}
+
+/// Save a folder as a user template, copying the files the workspace does
+/// not ignore. Placeholders such as `{{project_name}}` can then be added to
+/// the copies in the templates folder.
+#[tauri::command]
+pub async fn save_project_template(
+    app: AppHandle,
+    source_dir: String,
+    name: String,
+    description: Option<String>,
+) -> Result<ProjectTemplate, String> {
+    let source = PathBuf::from(&source_dir);
+    if !source.is_dir() {
+        return Err(format!("Path is not a directory: {}", source_dir));
+    }
+    let id = slug(&name, '-');
+    if id.is_empty() || BUILTIN_TEMPLATES.iter().any(|t| t.id == id) {
+        return Err(format!("Invalid template name: {}", name));
+    }
+    let dir = templates_dir(&app)?.join(&id);
+    if dir.exists() {
+        return Err(format!("A template named {} already exists", name));
+    }
+
+    let template = ProjectTemplate {
+        id,
+        name,
+        description: description.unwrap_or_default(),
+        variables: HashMap::new(),
+        builtin: false,
+    };
+
+    let copy = || -> Result<(), String> {
+        let filter = IgnoreFilter::load(&source)?;
+        for entry in candidate_files(&source, &filter, &None, &None) {
+            let relative = entry.path().strip_prefix(&source).unwrap_or(entry.path());
+            let target = dir.join(relative);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create directory: {}", e))?;
+            }
+            std::fs::copy(entry.path(), &target)
+                .map_err(|e| format!("Failed to copy {}: {}", entry.path().display(), e))?;
+        }
+
+        let json = serde_json::to_vec_pretty(&template)
+            .map_err(|e| format!("Failed to serialize template: {}", e))?;
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+        crate::atomic_write(&dir.join(TEMPLATE_MANIFEST), &json)
+    };
+    if let Err(e) = copy() {
+        let _ = std::fs::remove_dir_all(&dir);
+        return Err(e);
+    }
+
+    Ok(template)
+}
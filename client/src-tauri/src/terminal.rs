@@ -0,0 +1,278 @@
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+
+// ============================================================================
+// TERMINAL TYPES
+// ============================================================================
+
+/// Event carrying terminal output, emitted to the window owning the session
+pub const TERMINAL_OUTPUT_EVENT: &str = "terminal://output";
+
+/// Event emitted once the shell of a session exits
+pub const TERMINAL_EXIT_EVENT: &str = "terminal://exit";
+
+const READ_BUFFER_SIZE: usize = 8192;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TerminalOutput {
+    pub id: String,
+    pub data: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TerminalExit {
+    pub id: String,
+    /// None when the session was killed or the status is unknown
+    pub exit_code: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TerminalInfo {
+    pub id: String,
+    pub shell: String,
+    pub cwd: String,
+}
+
+struct TerminalSession {
+    window_label: String,
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+}
+
+/// Running terminal sessions keyed by id
+#[derive(Default)]
+pub struct TerminalRegistry {
+    sessions: Mutex<HashMap<String, TerminalSession>>,
+}
+
+impl TerminalRegistry {
+    fn remove(&self, id: &str) -> Option<TerminalSession> {
+        self.sessions.lock().ok()?.remove(id)
+    }
+
+    /// Kill every session owned by a window (e.g. when it is closed)
+    pub fn close_window(&self, label: &str) {
+        let Ok(mut sessions) = self.sessions.lock() else {
+            return;
+        };
+
+        sessions.retain(|_, session| {
+            if session.window_label == label {
+                let _ = session.child.kill();
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn default_shell() -> String {
+    if cfg!(windows) {
+        std::env::var("COMSPEC").unwrap_or_else(|_| "powershell.exe".to_string())
+    } else {
+        std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string())
+    }
+}
+
+fn pty_size(rows: u16, cols: u16) -> PtySize {
+    PtySize {
+        rows: rows.max(1),
+        cols: cols.max(1),
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+/// Forward PTY output to the owning window until the shell exits
+fn spawn_reader(app: AppHandle, id: String, label: String, mut reader: Box<dyn Read + Send>) {
+    std::thread::spawn(move || {
+        let mut buffer = [0u8; READ_BUFFER_SIZE];
+        // Bytes of a UTF-8 character split across two reads
+        let mut pending: Vec<u8> = Vec::new();
+
+        loop {
+            let read = match reader.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => read,
+            };
+            pending.extend_from_slice(&buffer[..read]);
+
+            let valid = match std::str::from_utf8(&pending) {
+                Ok(_) => pending.len(),
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(_) => pending.len(),
+            };
+            let data = String::from_utf8_lossy(&pending[..valid]).to_string();
+            pending.drain(..valid);
+
+            let _ = app.emit_to(
+                label.as_str(),
+                TERMINAL_OUTPUT_EVENT,
+                TerminalOutput {
+                    id: id.clone(),
+                    data,
+                },
+            );
+        }
+
+        let exit_code = app
+            .state::<TerminalRegistry>()
+            .remove(&id)
+            .and_then(|mut session| session.child.wait().ok())
+            .map(|status| status.exit_code());
+
+        let _ = app.emit_to(
+            label.as_str(),
+            TERMINAL_EXIT_EVENT,
+            TerminalExit { id, exit_code },
+        );
+    });
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Start a shell in `cwd` (the opened project folder) and stream its output.
+///
+/// Variables from `env_files` (.env files, later ones overriding earlier
+/// ones) are added to the shell's environment.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn create_terminal(
+    app: AppHandle,
+    window: Window,
+    registry: State<'_, TerminalRegistry>,
+    cwd: String,
+    shell: Option<String>,
+    rows: u16,
+    cols: u16,
+    env_files: Option<Vec<String>>,
+) -> Result<TerminalInfo, String> {
+    let cwd_path = PathBuf::from(&cwd);
+    if !cwd_path.is_dir() {
+        return Err(format!("Path is not a directory: {}", cwd));
+    }
+
+    let shell = shell
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(default_shell);
+    let env = crate::dotenv::load_env_sets(&env_files.unwrap_or_default())?;
+
+    let pair = native_pty_system()
+        .openpty(pty_size(rows, cols))
+        .map_err(|e| format!("Failed to open pty: {}", e))?;
+
+    let mut command = CommandBuilder::new(&shell);
+    command.cwd(&cwd_path);
+    command.env("TERM", "xterm-256color");
+    for (key, value) in env {
+        command.env(key, value);
+    }
+
+    let child = pair
+        .slave
+        .spawn_command(command)
+        .map_err(|e| format!("Failed to start {}: {}", shell, e))?;
+    // The child holds its own handle; keeping ours would prevent EOF on exit
+    drop(pair.slave);
+
+    let reader = pair
+        .master
+        .try_clone_reader()
+        .map_err(|e| format!("Failed to read from pty: {}", e))?;
+    let writer = pair
+        .master
+        .take_writer()
+        .map_err(|e| format!("Failed to write to pty: {}", e))?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let label = window.label().to_string();
+
+    registry
+        .sessions
+        .lock()
+        .map_err(|_| "Terminal registry is poisoned".to_string())?
+        .insert(
+            id.clone(),
+            TerminalSession {
+                window_label: label.clone(),
+                master: pair.master,
+                writer,
+                child,
+            },
+        );
+
+    spawn_reader(app, id.clone(), label, reader);
+
+    Ok(TerminalInfo { id, shell, cwd })
+}
+
+/// Send keystrokes / pasted text to a session
+#[tauri::command]
+pub async fn write_terminal(
+    registry: State<'_, TerminalRegistry>,
+    id: String,
+    data: String,
+) -> Result<(), String> {
+    let mut sessions = registry
+        .sessions
+        .lock()
+        .map_err(|_| "Terminal registry is poisoned".to_string())?;
+    let session = sessions
+        .get_mut(&id)
+        .ok_or_else(|| format!("Terminal not found: {}", id))?;
+
+    session
+        .writer
+        .write_all(data.as_bytes())
+        .and_then(|_| session.writer.flush())
+        .map_err(|e| format!("Failed to write to terminal: {}", e))
+}
+
+#[tauri::command]
+pub async fn resize_terminal(
+    registry: State<'_, TerminalRegistry>,
+    id: String,
+    rows: u16,
+    cols: u16,
+) -> Result<(), String> {
+    let sessions = registry
+        .sessions
+        .lock()
+        .map_err(|_| "Terminal registry is poisoned".to_string())?;
+    let session = sessions
+        .get(&id)
+        .ok_or_else(|| format!("Terminal not found: {}", id))?;
+
+    session
+        .master
+        .resize(pty_size(rows, cols))
+        .map_err(|e| format!("Failed to resize terminal: {}", e))
+}
+
+#[tauri::command]
+pub async fn kill_terminal(
+    registry: State<'_, TerminalRegistry>,
+    id: String,
+) -> Result<(), String> {
+    let mut session = registry
+        .remove(&id)
+        .ok_or_else(|| format!("Terminal not found: {}", id))?;
+
+    session
+        .child
+        .kill()
+        .map_err(|e| format!("Failed to kill terminal: {}", e))
+}
@@ -0,0 +1,195 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::ignore::IgnoreFilter;
+use crate::search::{candidate_files, read_text_file, MAX_LINE_PREVIEW};
+
+// ============================================================================
+// TODO TYPES
+// ============================================================================
+
+const DEFAULT_MAX_ITEMS: usize = 5000;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TodoKind {
+    Todo,
+    Fixme,
+    Hack,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TodoItem {
+    pub kind: TodoKind,
+    /// 1-based line number
+    pub line: usize,
+    /// Comment text after the marker
+    pub text: String,
+    /// From `TODO(name)`, or else the last author of the line per git blame
+    pub author: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TodoFile {
+    pub path: String,
+    pub items: Vec<TodoItem>,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct TodoCounts {
+    pub todo: usize,
+    pub fixme: usize,
+    pub hack: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TodoReport {
+    /// Files with at least one item, sorted by path
+    pub files: Vec<TodoFile>,
+    pub counts: TodoCounts,
+    /// Items per author, most first; items without one are not counted
+    pub by_author: Vec<(String, usize)>,
+    pub total: usize,
+    /// The scan stopped at `max_items`
+    pub truncated: bool,
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// A marker right after a comment opener, with an optional `(author)`
+fn todo_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r"(?://+|#+|/\*+|^\s*\*|--|<!--|;+)\s*(TODO|FIXME|HACK)\b(?:\(([^)]*)\))?:?\s*(.*)",
+        )
+        .expect("todo regex is valid")
+    })
+}
+
+fn scan_file(content: &str) -> Vec<TodoItem> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let captures = todo_regex().captures(line)?;
+            let kind = match &captures[1] {
+                "TODO" => TodoKind::Todo,
+                "FIXME" => TodoKind::Fixme,
+                _ => TodoKind::Hack,
+            };
+            let text = captures[3]
+                .trim_end_matches("*/")
+                .trim_end_matches("-->")
+                .trim();
+            Some(TodoItem {
+                kind,
+                line: index + 1,
+                text: text.chars().take(MAX_LINE_PREVIEW).collect(),
+                author: captures
+                    .get(2)
+                    .map(|m| m.as_str().trim().to_string())
+                    .filter(|name| !name.is_empty()),
+            })
+        })
+        .collect()
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// TODO, FIXME and HACK comments in the files under `root_path` that the
+/// workspace does not ignore, grouped by file. Items without a
+/// `TODO(name)` author get one from git blame unless `blame` is false.
+#[tauri::command]
+pub async fn scan_todos(
+    root_path: String,
+    blame: Option<bool>,
+    max_items: Option<usize>,
+) -> Result<TodoReport, String> {
+    let root = PathBuf::from(&root_path);
+    if !root.is_dir() {
+        return Err(format!("Path is not a directory: {}", root_path));
+    }
+    let filter = IgnoreFilter::load(&root)?;
+    let max_items = max_items.unwrap_or(DEFAULT_MAX_ITEMS).max(1);
+
+    tokio::task::spawn_blocking(move || {
+        let repo = blame
+            .unwrap_or(true)
+            .then(|| git2::Repository::discover(&root).ok())
+            .flatten();
+
+        let mut report = TodoReport {
+            files: Vec::new(),
+            counts: TodoCounts::default(),
+            by_author: Vec::new(),
+            total: 0,
+            truncated: false,
+        };
+
+        for entry in candidate_files(&root, &filter, &None, &None) {
+            let Some(content) = read_text_file(entry.path()) else {
+                continue;
+            };
+            let mut items = scan_file(&content);
+            if items.is_empty() {
+                continue;
+            }
+            if report.total + items.len() > max_items {
+                items.truncate(max_items - report.total);
+                report.truncated = true;
+            }
+
+            if let Some(repo) = &repo {
+                let lines: Vec<usize> = items
+                    .iter()
+                    .filter(|item| item.author.is_none())
+                    .map(|item| item.line)
+                    .collect();
+                if !lines.is_empty() {
+                    let mut authors =
+                        crate::git::line_authors(repo, entry.path(), &content, &lines);
+                    for item in items.iter_mut().filter(|item| item.author.is_none()) {
+                        item.author = authors.remove(&item.line);
+                    }
+                }
+            }
+
+            report.total += items.len();
+            report.files.push(TodoFile {
+                path: entry.path().to_string_lossy().to_string(),
+                items,
+            });
+            if report.truncated {
+                break;
+            }
+        }
+
+        let mut by_author: HashMap<String, usize> = HashMap::new();
+        for item in report.files.iter().flat_map(|file| &file.items) {
+            match item.kind {
+                TodoKind::Todo => report.counts.todo += 1,
+                TodoKind::Fixme => report.counts.fixme += 1,
+                TodoKind::Hack => report.counts.hack += 1,
+            }
+            if let Some(author) = &item.author {
+                *by_author.entry(author.clone()).or_default() += 1;
+            }
+        }
+        report.by_author = by_author.into_iter().collect();
+        report
+            .by_author
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        report.files.sort_by(|a, b| a.path.cmp(&b.path));
+        report
+    })
+    .await
+    .map_err(|e| format!("Scan task failed: {}", e))
+}
@@ -0,0 +1,326 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use walkdir::WalkDir;
+
+use crate::FileNode;
+
+// ============================================================================
+// MOVE / COPY TYPES
+// ============================================================================
+
+/// Event emitted while a move or copy is in progress
+pub const TRANSFER_PROGRESS_EVENT: &str = "fs://transfer-progress";
+
+pub(crate) const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What to do when the destination already exists
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OverwritePolicy {
+    /// Replace existing files (directories are merged)
+    Overwrite,
+    /// Keep existing files (directories are merged)
+    Skip,
+    /// Pick a free name such as "file (1).txt"
+    #[default]
+    Rename,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TransferMode {
+    Copy,
+    Move,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TransferProgress {
+    pub operation_id: String,
+    pub processed: usize,
+    pub total: usize,
+    pub skipped: usize,
+    pub current_path: String,
+    pub done: bool,
+}
+
+struct Transfer {
+    app: AppHandle,
+    mode: TransferMode,
+    policy: OverwritePolicy,
+    progress: TransferProgress,
+    last_emit: Instant,
+}
+
+impl Transfer {
+    fn report(&mut self, path: &Path, force: bool) {
+        self.progress.current_path = path.to_string_lossy().to_string();
+        if force || self.last_emit.elapsed() >= PROGRESS_INTERVAL {
+            self.last_emit = Instant::now();
+            let _ = self
+                .app
+                .emit(TRANSFER_PROGRESS_EVENT, self.progress.clone());
+        }
+    }
+
+    /// Copy or move a single file, applying the overwrite policy
+    fn transfer_file(&mut self, source: &Path, target: &Path) -> Result<(), String> {
+        if target.exists() {
+            match self.policy {
+                OverwritePolicy::Skip => {
+                    self.progress.skipped += 1;
+                    self.progress.processed += 1;
+                    self.report(source, false);
+                    return Ok(());
+                }
+                OverwritePolicy::Overwrite | OverwritePolicy::Rename => {
+                    if target.is_dir() {
+                        std::fs::remove_dir_all(target)
+                    } else {
+                        std::fs::remove_file(target)
+                    }
+                    .map_err(|e| format!("Failed to replace {}: {}", target.display(), e))?;
+                }
+            }
+        }
+
+        match self.mode {
+            TransferMode::Copy => {
+                std::fs::copy(source, target)
+                    .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+            }
+            TransferMode::Move => {
+                // Renames fail across devices, fall back to copy + delete
+                if std::fs::rename(source, target).is_err() {
+                    std::fs::copy(source, target)
+                        .map_err(|e| format!("Failed to move {}: {}", source.display(), e))?;
+                    std::fs::remove_file(source)
+                        .map_err(|e| format!("Failed to remove {}: {}", source.display(), e))?;
+                }
+            }
+        }
+
+        self.progress.processed += 1;
+        self.report(source, false);
+        Ok(())
+    }
+
+    /// Recursively transfer a directory, merging into an existing target
+    fn transfer_dir(&mut self, source: &Path, target: &Path) -> Result<(), String> {
+        if target.is_file() {
+            match self.policy {
+                OverwritePolicy::Skip => return Ok(()),
+                _ => std::fs::remove_file(target)
+                    .map_err(|e| format!("Failed to replace {}: {}", target.display(), e))?,
+            }
+        }
+        std::fs::create_dir_all(target)
+            .map_err(|e| format!("Failed to create directory: {}", e))?;
+
+        let entries =
+            std::fs::read_dir(source).map_err(|e| format!("Failed to read directory: {}", e))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+            let entry_target = target.join(entry.file_name());
+            let file_type = entry
+                .file_type()
+                .map_err(|e| format!("Failed to read entry: {}", e))?;
+
+            // Symlinks are skipped rather than followed out of the tree
+            if file_type.is_dir() {
+                self.transfer_dir(&entry.path(), &entry_target)?;
+            } else if file_type.is_file() {
+                self.transfer_file(&entry.path(), &entry_target)?;
+            }
+        }
+
+        // Skipped files stay behind, so only remove the directory once it is empty
+        if self.mode == TransferMode::Move {
+            let _ = std::fs::remove_dir(source);
+        }
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Find a free "name (n).ext" variant of a path
+pub(crate) fn unique_target(target: &Path) -> PathBuf {
+    let parent = target.parent().unwrap_or(Path::new(""));
+    let stem = target
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = target
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|n| parent.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .expect("unbounded name search")
+}
+
+fn count_files(path: &Path) -> usize {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .count()
+}
+
+pub(crate) fn file_node(path: &Path) -> FileNode {
+    let is_dir = path.is_dir();
+    FileNode {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        path: path.to_string_lossy().to_string(),
+        is_dir,
+        children: if is_dir { Some(vec![]) } else { None },
+        extension: if is_dir {
+            None
+        } else {
+            path.extension().map(|e| e.to_string_lossy().to_string())
+        },
+        size_bytes: None,
+        mtime_ms: None,
+        readonly: None,
+    }
+}
+
+pub(crate) async fn run_transfer(
+    app: AppHandle,
+    mode: TransferMode,
+    source_path: String,
+    dest_dir: String,
+    policy: Option<OverwritePolicy>,
+    operation_id: Option<String>,
+) -> Result<FileNode, String> {
+    let source = PathBuf::from(&source_path);
+    let dest_dir = PathBuf::from(&dest_dir);
+
+    if !source.exists() {
+        return Err(format!("Path does not exist: {}", source_path));
+    }
+    if !dest_dir.is_dir() {
+        return Err(format!(
+            "Destination is not a directory: {}",
+            dest_dir.display()
+        ));
+    }
+
+    let file_name = source
+        .file_name()
+        .ok_or_else(|| "Cannot get file name".to_string())?;
+    let mut target = dest_dir.join(file_name);
+
+    let canonical_source = source
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve path: {}", e))?;
+    let canonical_dest = dest_dir
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve path: {}", e))?;
+    if source.is_dir() && canonical_dest.starts_with(&canonical_source) {
+        return Err("Cannot move or copy a folder into itself".to_string());
+    }
+
+    // Source and target are the same path: only a renamed copy makes sense
+    let policy = policy.unwrap_or_default();
+    let same_location = canonical_source.parent() == Some(canonical_dest.as_path());
+    if same_location && (mode == TransferMode::Move || policy != OverwritePolicy::Rename) {
+        return Ok(file_node(&source));
+    }
+
+    if policy == OverwritePolicy::Rename && target.exists() {
+        target = unique_target(&target);
+    }
+
+    tokio::task::spawn_blocking(move || {
+        // Fast path: a plain rename when nothing is in the way
+        if mode == TransferMode::Move
+            && !target.exists()
+            && std::fs::rename(&source, &target).is_ok()
+        {
+            return Ok(file_node(&target));
+        }
+
+        let mut transfer = Transfer {
+            app,
+            mode,
+            policy,
+            progress: TransferProgress {
+                operation_id: operation_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+                processed: 0,
+                total: count_files(&source),
+                skipped: 0,
+                current_path: String::new(),
+                done: false,
+            },
+            last_emit: Instant::now(),
+        };
+
+        let result = if source.is_dir() {
+            transfer.transfer_dir(&source, &target)
+        } else {
+            transfer.transfer_file(&source, &target)
+        };
+
+        transfer.progress.done = true;
+        transfer.report(&source, true);
+
+        result.map(|_| file_node(&target))
+    })
+    .await
+    .map_err(|e| format!("Transfer task failed: {}", e))?
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Move a file or folder into `dest_dir`, working across devices
+#[tauri::command]
+pub async fn move_path(
+    app: AppHandle,
+    source_path: String,
+    dest_dir: String,
+    policy: Option<OverwritePolicy>,
+    operation_id: Option<String>,
+) -> Result<FileNode, String> {
+    run_transfer(
+        app,
+        TransferMode::Move,
+        source_path,
+        dest_dir,
+        policy,
+        operation_id,
+    )
+    .await
+}
+
+/// Copy a file or folder (recursively) into `dest_dir`
+#[tauri::command]
+pub async fn copy_path(
+    app: AppHandle,
+    source_path: String,
+    dest_dir: String,
+    policy: Option<OverwritePolicy>,
+    operation_id: Option<String>,
+) -> Result<FileNode, String> {
+    run_transfer(
+        app,
+        TransferMode::Copy,
+        source_path,
+        dest_dir,
+        policy,
+        operation_id,
+    )
+    .await
+}
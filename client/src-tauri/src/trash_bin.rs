@@ -0,0 +1,140 @@
+use serde::Serialize;
+use std::path::PathBuf;
+
+// ============================================================================
+// TRASH TYPES
+// ============================================================================
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TrashEntry {
+    /// Platform specific identifier, passed back to restore_from_trash
+    pub id: String,
+    pub name: String,
+    pub original_path: String,
+    /// When the item was trashed (seconds since epoch)
+    pub time_deleted: i64,
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Move a file or folder to the OS trash (the default delete action)
+#[tauri::command]
+pub async fn move_to_trash(path: String) -> Result<(), String> {
+    let path_buf = PathBuf::from(&path);
+
+    if !path_buf.exists() {
+        return Err(format!("Path does not exist: {}", path));
+    }
+
+    tokio::task::spawn_blocking(move || trash::delete(&path_buf))
+        .await
+        .map_err(|e| format!("Trash task failed: {}", e))?
+        .map_err(|e| format!("Failed to move to trash: {}", e))
+}
+
+/// List trashed items, optionally only those that came from under `root_path`
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+#[tauri::command]
+pub async fn list_trash(root_path: Option<String>) -> Result<Vec<TrashEntry>, String> {
+    let items = tokio::task::spawn_blocking(trash::os_limited::list)
+        .await
+        .map_err(|e| format!("Trash task failed: {}", e))?
+        .map_err(|e| format!("Failed to list trash: {}", e))?;
+
+    let root = root_path.map(PathBuf::from);
+    let mut entries: Vec<TrashEntry> = items
+        .into_iter()
+        .filter(|item| match &root {
+            Some(root) => item.original_path().starts_with(root),
+            None => true,
+        })
+        .map(|item| TrashEntry {
+            id: item.id.to_string_lossy().to_string(),
+            name: item.name.to_string_lossy().to_string(),
+            original_path: item.original_path().to_string_lossy().to_string(),
+            time_deleted: item.time_deleted,
+        })
+        .collect();
+
+    // Most recently deleted first
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.time_deleted));
+
+    Ok(entries)
+}
+
+/// Restore trashed items to their original location
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+#[tauri::command]
+pub async fn restore_from_trash(ids: Vec<String>) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        let items: Vec<_> = trash::os_limited::list()
+            .map_err(|e| format!("Failed to list trash: {}", e))?
+            .into_iter()
+            .filter(|item| {
+                ids.iter()
+                    .any(|id| item.id.to_string_lossy() == id.as_str())
+            })
+            .collect();
+
+        if items.is_empty() {
+            return Err("No matching items in trash".to_string());
+        }
+
+        trash::os_limited::restore_all(items).map_err(|e| match e {
+            trash::Error::RestoreCollision { path, .. } => format!(
+                "Cannot restore, a file already exists at {}",
+                path.display()
+            ),
+            e => format!("Failed to restore from trash: {}", e),
+        })
+    })
+    .await
+    .map_err(|e| format!("Trash task failed: {}", e))?
+}
+
+/// Listing the trash is not supported by the OS APIs on macOS
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+#[tauri::command]
+pub async fn list_trash(_root_path: Option<String>) -> Result<Vec<TrashEntry>, String> {
+    Err("Listing the trash is not supported on this platform".to_string())
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+#[tauri::command]
+pub async fn restore_from_trash(_ids: Vec<String>) -> Result<(), String> {
+    Err("Restoring from the trash is not supported on this platform".to_string())
+}
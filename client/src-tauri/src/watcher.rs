@@ -0,0 +1,183 @@
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, State, Window};
+
+// ============================================================================
+// FILE WATCHER TYPES
+// ============================================================================
+
+/// Event emitted to the subscribing window for every file system change
+pub const FS_CHANGE_EVENT: &str = "fs://change";
+
+const IGNORED_COMPONENTS: &[&str] = &[".git", "node_modules", "target", "__pycache__", ".next"];
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FsChangeKind {
+    Created,
+    Modified,
+    Deleted,
+    Renamed,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FsChangeEvent {
+    /// Watched root the change belongs to
+    pub root: String,
+    pub kind: FsChangeKind,
+    /// Affected path (the new path for renames)
+    pub path: String,
+    /// Previous path, only set for renames
+    pub old_path: Option<String>,
+}
+
+/// Active watchers keyed by window label and watched root
+#[derive(Default)]
+pub struct WatcherRegistry {
+    watchers: Mutex<HashMap<(String, PathBuf), RecommendedWatcher>>,
+}
+
+impl WatcherRegistry {
+    /// Drop every watcher owned by a window (e.g. when it is closed)
+    pub fn unwatch_window(&self, label: &str) {
+        if let Ok(mut watchers) = self.watchers.lock() {
+            watchers.retain(|(owner, _), _| owner != label);
+        }
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn is_ignored_path(root: &Path, path: &Path) -> bool {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .any(|c| IGNORED_COMPONENTS.contains(&c.as_os_str().to_string_lossy().as_ref()))
+}
+
+fn to_change_events(root: &Path, event: &Event) -> Vec<FsChangeEvent> {
+    let root_str = root.to_string_lossy().to_string();
+    let change = |kind: FsChangeKind, path: &Path, old_path: Option<&Path>| FsChangeEvent {
+        root: root_str.clone(),
+        kind,
+        path: path.to_string_lossy().to_string(),
+        old_path: old_path.map(|p| p.to_string_lossy().to_string()),
+    };
+
+    let kind = match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            return match event.paths.as_slice() {
+                [from, to] if !is_ignored_path(root, to) => {
+                    vec![change(FsChangeKind::Renamed, to, Some(from))]
+                }
+                _ => Vec::new(),
+            };
+        }
+        // Unpaired rename halves look like a delete/create to the file tree
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => FsChangeKind::Deleted,
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => FsChangeKind::Created,
+        EventKind::Modify(_) => FsChangeKind::Modified,
+        EventKind::Create(_) => FsChangeKind::Created,
+        EventKind::Remove(_) => FsChangeKind::Deleted,
+        _ => return Vec::new(),
+    };
+
+    event
+        .paths
+        .iter()
+        .filter(|path| !is_ignored_path(root, path))
+        .map(|path| change(kind, path, None))
+        .collect()
+}
+
+/// Start watching a folder for a window (a no-op when it already is)
+pub(crate) fn start_watching(
+    app: AppHandle,
+    registry: &WatcherRegistry,
+    label: &str,
+    root: PathBuf,
+) -> Result<(), String> {
+    if !root.is_dir() {
+        return Err(format!("Path is not a directory: {}", root.display()));
+    }
+
+    let key = (label.to_string(), root.clone());
+    let mut watchers = registry
+        .watchers
+        .lock()
+        .map_err(|_| "Watcher registry is poisoned".to_string())?;
+
+    if watchers.contains_key(&key) {
+        return Ok(());
+    }
+
+    let label = label.to_string();
+    let event_root = root.clone();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        let event = match res {
+            Ok(event) => event,
+            Err(e) => {
+                log::warn!("File watcher error: {}", e);
+                return;
+            }
+        };
+
+        for change in to_change_events(&event_root, &event) {
+            crate::index::on_fs_change(&app, &change);
+            let _ = app.emit_to(label.as_str(), FS_CHANGE_EVENT, change);
+        }
+    })
+    .map_err(|e| format!("Failed to create watcher: {}", e))?;
+
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch directory: {}", e))?;
+
+    watchers.insert(key, watcher);
+    Ok(())
+}
+
+pub(crate) fn stop_watching(
+    registry: &WatcherRegistry,
+    label: &str,
+    root: PathBuf,
+) -> Result<(), String> {
+    registry
+        .watchers
+        .lock()
+        .map_err(|_| "Watcher registry is poisoned".to_string())?
+        .remove(&(label.to_string(), root));
+
+    Ok(())
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Start watching a folder; changes are emitted as `fs://change` to the calling window only
+#[tauri::command]
+pub async fn watch_directory(
+    app: AppHandle,
+    window: Window,
+    registry: State<'_, WatcherRegistry>,
+    path: String,
+) -> Result<(), String> {
+    start_watching(app, &registry, window.label(), PathBuf::from(&path))
+}
+
+/// Stop watching a folder for the calling window
+#[tauri::command]
+pub async fn unwatch_directory(
+    window: Window,
+    registry: State<'_, WatcherRegistry>,
+    path: String,
+) -> Result<(), String> {
+    stop_watching(&registry, window.label(), PathBuf::from(&path))
+}
@@ -0,0 +1,311 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, State, Window};
+
+use crate::watcher::{self, WatcherRegistry};
+use crate::FileNode;
+
+// ============================================================================
+// WORKSPACE TYPES
+// ============================================================================
+
+/// Bumped when the workspace file layout changes incompatibly
+const WORKSPACE_FILE_VERSION: u32 = 1;
+
+/// Most name matches returned per root
+const MAX_RESULTS_PER_ROOT: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WorkspaceRoot {
+    /// Absolute path of the folder
+    pub path: String,
+    /// Shown in the file tree instead of the folder name when set
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// A `.codecollab-workspace` file; root paths inside its folder are stored
+/// relative to it so the file can be committed with the project
+#[derive(Debug, Serialize, Deserialize)]
+struct WorkspaceFile {
+    version: u32,
+    roots: Vec<WorkspaceRoot>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RootFileMatches {
+    pub root: String,
+    pub results: Vec<FileNode>,
+}
+
+/// Root folders open in each window, keyed by window label
+#[derive(Default)]
+pub struct WorkspaceRegistry {
+    roots: Mutex<HashMap<String, Vec<WorkspaceRoot>>>,
+}
+
+impl WorkspaceRegistry {
+    /// Forget the roots of a window (e.g. when it is closed)
+    pub fn close_window(&self, label: &str) {
+        if let Ok(mut roots) = self.roots.lock() {
+            roots.remove(label);
+        }
+    }
+
+    fn roots_of(&self, label: &str) -> Result<Vec<WorkspaceRoot>, String> {
+        Ok(self
+            .roots
+            .lock()
+            .map_err(|_| "Workspace registry is poisoned".to_string())?
+            .get(label)
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Same folder whatever the trailing separator
+fn normalize(path: &str) -> String {
+    let trimmed = path.trim_end_matches(['/', '\\']);
+    if trimmed.is_empty() {
+        path.to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+fn root_node(root: &WorkspaceRoot) -> Result<FileNode, String> {
+    crate::folder_node(&root.path, root.name.as_deref())
+}
+
+/// Replace a window's roots, watching the new ones and unwatching the rest
+fn set_roots(
+    app: &AppHandle,
+    window: &Window,
+    registry: &WorkspaceRegistry,
+    watchers: &WatcherRegistry,
+    roots: Vec<WorkspaceRoot>,
+) -> Result<Vec<FileNode>, String> {
+    // Build every tree first so a bad root leaves the workspace unchanged
+    let nodes = roots.iter().map(root_node).collect::<Result<Vec<_>, _>>()?;
+
+    let label = window.label();
+    let previous = std::mem::replace(
+        registry
+            .roots
+            .lock()
+            .map_err(|_| "Workspace registry is poisoned".to_string())?
+            .entry(label.to_string())
+            .or_default(),
+        roots.clone(),
+    );
+
+    for old in previous
+        .iter()
+        .filter(|old| !roots.iter().any(|r| r.path == old.path))
+    {
+        watcher::stop_watching(watchers, label, PathBuf::from(&old.path))?;
+    }
+    for root in &roots {
+        watcher::start_watching(app.clone(), watchers, label, PathBuf::from(&root.path))?;
+    }
+
+    Ok(nodes)
+}
+
+// ============================================================================
+// TAURI COMMANDS
+// ============================================================================
+
+/// Open several root folders in the calling window, replacing its current
+/// ones. Returns one tree per root; each root is watched and its
+/// `fs://change` events carry the root they belong to.
+#[tauri::command]
+pub async fn open_workspace(
+    app: AppHandle,
+    window: Window,
+    registry: State<'_, WorkspaceRegistry>,
+    watchers: State<'_, WatcherRegistry>,
+    roots: Vec<WorkspaceRoot>,
+) -> Result<Vec<FileNode>, String> {
+    let mut unique: Vec<WorkspaceRoot> = Vec::new();
+    for mut root in roots {
+        root.path = normalize(&root.path);
+        if !unique.iter().any(|r| r.path == root.path) {
+            unique.push(root);
+        }
+    }
+
+    set_roots(&app, &window, &registry, &watchers, unique)
+}
+
+/// Add a root folder to the calling window's workspace and start watching it
+#[tauri::command]
+pub async fn add_workspace_root(
+    app: AppHandle,
+    window: Window,
+    registry: State<'_, WorkspaceRegistry>,
+    watchers: State<'_, WatcherRegistry>,
+    path: String,
+    name: Option<String>,
+) -> Result<FileNode, String> {
+    let root = WorkspaceRoot {
+        path: normalize(&path),
+        name,
+    };
+    let mut roots = registry.roots_of(window.label())?;
+    if roots.iter().any(|r| r.path == root.path) {
+        return Err(format!("Already in the workspace: {}", root.path));
+    }
+
+    let node = root_node(&root)?;
+    watcher::start_watching(app, &watchers, window.label(), PathBuf::from(&root.path))?;
+    roots.push(root);
+    registry
+        .roots
+        .lock()
+        .map_err(|_| "Workspace registry is poisoned".to_string())?
+        .insert(window.label().to_string(), roots);
+
+    Ok(node)
+}
+
+/// Remove a root folder from the calling window's workspace and stop watching it
+#[tauri::command]
+pub async fn remove_workspace_root(
+    window: Window,
+    registry: State<'_, WorkspaceRegistry>,
+    watchers: State<'_, WatcherRegistry>,
+    path: String,
+) -> Result<(), String> {
+    let path = normalize(&path);
+    {
+        let mut all = registry
+            .roots
+            .lock()
+            .map_err(|_| "Workspace registry is poisoned".to_string())?;
+        let roots = all.entry(window.label().to_string()).or_default();
+        let before = roots.len();
+        roots.retain(|r| r.path != path);
+        if roots.len() == before {
+            return Err(format!("Not in the workspace: {}", path));
+        }
+    }
+
+    watcher::stop_watching(&watchers, window.label(), PathBuf::from(&path))
+}
+
+#[tauri::command]
+pub async fn list_workspace_roots(
+    window: Window,
+    registry: State<'_, WorkspaceRegistry>,
+) -> Result<Vec<WorkspaceRoot>, String> {
+    registry.roots_of(window.label())
+}
+
+/// Find files by name in every root of the workspace, or in one of them.
+///
+/// Each root applies its own ignore preferences. Content search stays per
+/// root through search_in_files.
+#[tauri::command]
+pub async fn search_workspace_files(
+    window: Window,
+    registry: State<'_, WorkspaceRegistry>,
+    query: String,
+    root: Option<String>,
+) -> Result<Vec<RootFileMatches>, String> {
+    let root = root.as_deref().map(normalize);
+    let roots: Vec<WorkspaceRoot> = registry
+        .roots_of(window.label())?
+        .into_iter()
+        .filter(|r| root.iter().all(|only| *only == r.path))
+        .collect();
+    if let (Some(root), true) = (&root, roots.is_empty()) {
+        return Err(format!("Not in the workspace: {}", root));
+    }
+
+    roots
+        .into_iter()
+        .map(|r| {
+            Ok(RootFileMatches {
+                results: crate::find_files(Path::new(&r.path), &query, MAX_RESULTS_PER_ROOT)?,
+                root: r.path,
+            })
+        })
+        .collect()
+}
+
+/// Save the calling window's roots to a `.codecollab-workspace` file
+#[tauri::command]
+pub async fn save_workspace_file(
+    window: Window,
+    registry: State<'_, WorkspaceRegistry>,
+    path: String,
+) -> Result<(), String> {
+    let file_path = PathBuf::from(&path);
+    let dir = file_path.parent().unwrap_or(Path::new(""));
+
+    let roots = registry
+        .roots_of(window.label())?
+        .into_iter()
+        .map(|root| WorkspaceRoot {
+            path: match Path::new(&root.path).strip_prefix(dir) {
+                Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+                Ok(relative) => relative.to_string_lossy().to_string(),
+                Err(_) => root.path,
+            },
+            name: root.name,
+        })
+        .collect();
+
+    let json = serde_json::to_vec_pretty(&WorkspaceFile {
+        version: WORKSPACE_FILE_VERSION,
+        roots,
+    })
+    .map_err(|e| format!("Failed to serialize workspace: {}", e))?;
+    crate::atomic_write(&file_path, &json)
+}
+
+/// Open the roots listed in a `.codecollab-workspace` file in the calling window
+#[tauri::command]
+pub async fn open_workspace_file(
+    app: AppHandle,
+    window: Window,
+    registry: State<'_, WorkspaceRegistry>,
+    watchers: State<'_, WatcherRegistry>,
+    path: String,
+) -> Result<Vec<FileNode>, String> {
+    let file_path = PathBuf::from(&path);
+    let json = std::fs::read(&file_path).map_err(|e| format!("Failed to read workspace: {}", e))?;
+    let file: WorkspaceFile =
+        serde_json::from_slice(&json).map_err(|e| format!("Invalid workspace file: {}", e))?;
+    if file.version > WORKSPACE_FILE_VERSION {
+        return Err(format!(
+            "Workspace file version {} is newer than this app supports",
+            file.version
+        ));
+    }
+
+    let dir = file_path.parent().unwrap_or(Path::new(""));
+    let roots = file
+        .roots
+        .into_iter()
+        .map(|root| {
+            let path = match root.path.as_str() {
+                "." => dir.to_path_buf(),
+                relative => dir.join(relative),
+            };
+            WorkspaceRoot {
+                path: normalize(&path.to_string_lossy()),
+                name: root.name,
+            }
+        })
+        .collect();
+
+    set_roots(&app, &window, &registry, &watchers, roots)
+}
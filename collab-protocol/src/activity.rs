@@ -0,0 +1,60 @@
+//! Entries of the per-project activity feed sent in `ServerMessage::ActivityFeed`.
+
+use serde::{Deserialize, Serialize};
+
+use super::PeerId;
+
+/// Kind of activity recorded in the feed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActivityKind {
+    /// Peer joined the project
+    Joined,
+    /// Peer left the project
+    Left,
+    /// Peer opened a file
+    FileOpened { file_path: String },
+    /// A file was created
+    FileCreated { path: String },
+    /// A folder was created
+    FolderCreated { path: String },
+    /// A file or folder was deleted
+    Deleted { path: String },
+    /// A file or folder was renamed
+    Renamed { old_name: String, new_name: String },
+    /// A file or folder was moved
+    Moved { node_id: String },
+    /// File content was replaced wholesale
+    FileUpdated { path: String },
+    /// Chat message was sent (truncated preview)
+    Chat { preview: String },
+}
+
+/// A single entry in the activity feed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityEntry {
+    /// When the activity happened (milliseconds since epoch)
+    pub timestamp: i64,
+    /// Peer responsible for the activity (if known)
+    pub peer_id: Option<PeerId>,
+    /// Display name of the peer (if known)
+    pub peer_name: Option<String>,
+    /// What happened
+    pub kind: ActivityKind,
+}
+
+impl ActivityEntry {
+    pub fn new(kind: ActivityKind) -> Self {
+        Self {
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            peer_id: None,
+            peer_name: None,
+            kind,
+        }
+    }
+
+    pub fn with_peer(mut self, peer_id: impl Into<String>, peer_name: Option<String>) -> Self {
+        self.peer_id = Some(peer_id.into());
+        self.peer_name = peer_name;
+        self
+    }
+}
@@ -0,0 +1,355 @@
+//! Language detection for files opened or hosted in a project.
+//!
+//! The server tags files in the shared tree with it and the desktop client
+//! uses it when it opens a file, so both report the same editor language id.
+//! Well-known file names and extensions are checked first; with the file's
+//! content at hand, editor modelines and `#!` lines settle the rest.
+
+/// Language reported when nothing matches
+pub const PLAINTEXT: &str = "plaintext";
+
+/// How many lines at each end of a file are searched for a modeline
+const MODELINE_LINES: usize = 5;
+
+/// Extensions (lowercase, without the dot) and their language ids
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("rs", "rust"),
+    ("js", "javascript"),
+    ("mjs", "javascript"),
+    ("cjs", "javascript"),
+    ("jsx", "javascriptreact"),
+    ("ts", "typescript"),
+    ("mts", "typescript"),
+    ("cts", "typescript"),
+    ("tsx", "typescriptreact"),
+    ("py", "python"),
+    ("pyw", "python"),
+    ("rb", "ruby"),
+    ("go", "go"),
+    ("java", "java"),
+    ("c", "c"),
+    ("cpp", "cpp"),
+    ("cc", "cpp"),
+    ("cxx", "cpp"),
+    ("c++", "cpp"),
+    ("h", "cpp"),
+    ("hpp", "cpp"),
+    ("hxx", "cpp"),
+    ("cs", "csharp"),
+    ("php", "php"),
+    ("swift", "swift"),
+    ("kt", "kotlin"),
+    ("kts", "kotlin"),
+    ("scala", "scala"),
+    ("html", "html"),
+    ("htm", "html"),
+    ("css", "css"),
+    ("scss", "scss"),
+    ("sass", "scss"),
+    ("less", "less"),
+    ("json", "json"),
+    ("jsonc", "jsonc"),
+    ("xml", "xml"),
+    ("yaml", "yaml"),
+    ("yml", "yaml"),
+    ("toml", "toml"),
+    ("md", "markdown"),
+    ("markdown", "markdown"),
+    ("sql", "sql"),
+    ("sh", "shellscript"),
+    ("bash", "shellscript"),
+    ("zsh", "shellscript"),
+    ("ps1", "powershell"),
+    ("psm1", "powershell"),
+    ("dockerfile", "dockerfile"),
+    ("graphql", "graphql"),
+    ("gql", "graphql"),
+    ("vue", "vue"),
+    ("svelte", "svelte"),
+    ("lua", "lua"),
+    ("r", "r"),
+    ("dart", "dart"),
+    ("elm", "elm"),
+    ("ex", "elixir"),
+    ("exs", "elixir"),
+    ("erl", "erlang"),
+    ("hrl", "erlang"),
+    ("hs", "haskell"),
+    ("lhs", "haskell"),
+    ("clj", "clojure"),
+    ("cljs", "clojure"),
+    ("cljc", "clojure"),
+    ("fs", "fsharp"),
+    ("fsx", "fsharp"),
+    ("fsi", "fsharp"),
+    ("ml", "ocaml"),
+    ("mli", "ocaml"),
+    ("nim", "nim"),
+    ("zig", "zig"),
+    ("v", "v"),
+    ("sol", "solidity"),
+    ("move", "move"),
+    ("proto", "protobuf"),
+    ("tf", "terraform"),
+    ("tfvars", "terraform"),
+    ("ini", "ini"),
+    ("conf", "ini"),
+    ("cfg", "ini"),
+    ("env", "dotenv"),
+    ("txt", "plaintext"),
+    ("log", "log"),
+    ("csv", "csv"),
+    ("diff", "diff"),
+    ("patch", "diff"),
+    ("makefile", "makefile"),
+    ("mk", "makefile"),
+    ("cmake", "cmake"),
+    ("lock", "plaintext"),
+    ("pl", "perl"),
+    ("pm", "perl"),
+];
+
+/// Whole file names (lowercase) that say more than their extension
+const FILE_NAMES: &[(&str, &str)] = &[
+    ("makefile", "makefile"),
+    ("gnumakefile", "makefile"),
+    ("dockerfile", "dockerfile"),
+    ("containerfile", "dockerfile"),
+    ("cmakelists.txt", "cmake"),
+    ("gemfile", "ruby"),
+    ("rakefile", "ruby"),
+    ("vagrantfile", "ruby"),
+    ("podfile", "ruby"),
+    ("jenkinsfile", "groovy"),
+    (".bashrc", "shellscript"),
+    (".bash_profile", "shellscript"),
+    (".zshrc", "shellscript"),
+    (".profile", "shellscript"),
+    (".env", "dotenv"),
+    ("cargo.lock", "toml"),
+    ("pipfile", "toml"),
+];
+
+/// Interpreters named on a `#!` line (without version suffixes) and their language ids
+const INTERPRETERS: &[(&str, &str)] = &[
+    ("sh", "shellscript"),
+    ("bash", "shellscript"),
+    ("zsh", "shellscript"),
+    ("dash", "shellscript"),
+    ("ksh", "shellscript"),
+    ("ash", "shellscript"),
+    ("fish", "shellscript"),
+    ("python", "python"),
+    ("pypy", "python"),
+    ("node", "javascript"),
+    ("nodejs", "javascript"),
+    ("deno", "typescript"),
+    ("bun", "typescript"),
+    ("ts-node", "typescript"),
+    ("tsx", "typescript"),
+    ("ruby", "ruby"),
+    ("perl", "perl"),
+    ("php", "php"),
+    ("lua", "lua"),
+    ("rscript", "r"),
+    ("pwsh", "powershell"),
+    ("elixir", "elixir"),
+    ("escript", "erlang"),
+    ("runhaskell", "haskell"),
+    ("make", "makefile"),
+];
+
+/// Language of a file extension (without the dot)
+pub fn language_from_extension(extension: &str) -> Option<&'static str> {
+    let extension = extension.to_lowercase();
+    EXTENSIONS
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, language)| *language)
+}
+
+/// Language of a well-known file name like `Makefile` or `Dockerfile.dev`
+pub fn language_from_file_name(name: &str) -> Option<&'static str> {
+    let name = name.to_lowercase();
+    if name.starts_with("dockerfile.") || name.ends_with(".dockerfile") {
+        return Some("dockerfile");
+    }
+    if name.starts_with(".env.") {
+        return Some("dotenv");
+    }
+    FILE_NAMES
+        .iter()
+        .find(|(file_name, _)| *file_name == name)
+        .map(|(_, language)| *language)
+}
+
+/// Language of the interpreter on a `#!` first line, e.g. `#!/usr/bin/env python3`
+pub fn language_from_shebang(first_line: &str) -> Option<&'static str> {
+    let mut words = first_line.strip_prefix("#!")?.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        // `env -S cmd args` and other options come before the program
+        program = words.find(|word| !word.starts_with('-'))?;
+    }
+
+    let program = program.to_lowercase();
+    let name = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    INTERPRETERS
+        .iter()
+        .find(|(interpreter, _)| *interpreter == name)
+        .map(|(_, language)| *language)
+}
+
+/// A language named in a modeline, as an id or an extension (`python`, `sh`, `js`)
+fn language_from_name(name: &str) -> Option<&'static str> {
+    let name = name.trim().to_lowercase();
+    let alias = match name.as_str() {
+        "c++" => Some("cpp"),
+        "shell" | "shell-script" | "sh" => Some("shellscript"),
+        "js" | "js2" => Some("javascript"),
+        "text" => Some(PLAINTEXT),
+        _ => None,
+    };
+    alias
+        .or_else(|| {
+            EXTENSIONS
+                .iter()
+                .map(|(_, language)| *language)
+                .find(|language| *language == name)
+        })
+        .or_else(|| language_from_extension(&name))
+}
+
+/// Language from a Vim (`vim: set ft=python:`) or Emacs (`-*- mode: ruby -*-`) modeline
+fn language_from_modeline(line: &str) -> Option<&'static str> {
+    if let Some(start) = line.find("-*-") {
+        let rest = &line[start + 3..];
+        let settings = &rest[..rest.find("-*-")?];
+        if !settings.contains(':') {
+            return language_from_name(settings);
+        }
+        return settings.split(';').find_map(|setting| {
+            let (key, value) = setting.split_once(':')?;
+            key.trim()
+                .eq_ignore_ascii_case("mode")
+                .then(|| language_from_name(value))
+                .flatten()
+        });
+    }
+
+    // Vim only takes markers at the start of a line or after whitespace
+    let start = ["vim:", "vi:", "ex:"]
+        .iter()
+        .filter_map(|marker| {
+            line.match_indices(marker)
+                .find(|(i, _)| line[..*i].chars().last().is_none_or(char::is_whitespace))
+                .map(|(i, _)| i + marker.len())
+        })
+        .min()?;
+    line[start..]
+        .split(|c: char| c == ':' || c.is_whitespace())
+        .find_map(|setting| {
+            let value = setting
+                .strip_prefix("ft=")
+                .or_else(|| setting.strip_prefix("filetype="))
+                .or_else(|| setting.strip_prefix("syntax="))?;
+            language_from_name(value)
+        })
+}
+
+/// Language of a path by its file name or extension, None when neither is known
+fn language_from_path(path: &str) -> Option<&'static str> {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    language_from_file_name(name).or_else(|| {
+        let (_, extension) = name.rsplit_once('.')?;
+        language_from_extension(extension)
+    })
+}
+
+/// Detect the language of a file from its path alone
+pub fn detect_language(path: &str) -> String {
+    language_from_path(path).unwrap_or(PLAINTEXT).to_string()
+}
+
+/// Detect the language of a file from its path and content.
+///
+/// A modeline wins over the path, since someone wrote it on purpose; a
+/// `#!` line is only used when the path says nothing.
+pub fn detect_language_with_content(path: &str, content: &str) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let tail = lines
+        .len()
+        .saturating_sub(MODELINE_LINES)
+        .max(MODELINE_LINES);
+    let modeline = lines
+        .iter()
+        .take(MODELINE_LINES)
+        .chain(lines.iter().skip(tail))
+        .find_map(|line| language_from_modeline(line));
+
+    modeline
+        .or_else(|| language_from_path(path))
+        .or_else(|| lines.first().and_then(|line| language_from_shebang(line)))
+        .unwrap_or(PLAINTEXT)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_by_file_name() {
+        assert_eq!(detect_language("Makefile"), "makefile");
+        assert_eq!(detect_language("docker/Dockerfile"), "dockerfile");
+        assert_eq!(detect_language("Dockerfile.dev"), "dockerfile");
+        assert_eq!(detect_language("CMakeLists.txt"), "cmake");
+        assert_eq!(detect_language("src/main.rs"), "rust");
+        assert_eq!(detect_language("README"), "plaintext");
+    }
+
+    #[test]
+    fn test_detect_by_shebang() {
+        assert_eq!(
+            detect_language_with_content("bin/deploy", "#!/bin/bash\necho hi\n"),
+            "shellscript"
+        );
+        assert_eq!(
+            detect_language_with_content("tool", "#!/usr/bin/env python3.11\n"),
+            "python"
+        );
+        assert_eq!(
+            detect_language_with_content("tool", "#!/usr/bin/env -S node --experimental\n"),
+            "javascript"
+        );
+        // The extension wins over the interpreter
+        assert_eq!(
+            detect_language_with_content("notes.txt", "#!/bin/sh\n"),
+            "plaintext"
+        );
+    }
+
+    #[test]
+    fn test_detect_by_modeline() {
+        assert_eq!(
+            detect_language_with_content("config", "# vim: set ft=yaml:\nkey: 1\n"),
+            "yaml"
+        );
+        assert_eq!(
+            detect_language_with_content("build", "# -*- mode: python -*-\n"),
+            "python"
+        );
+        assert_eq!(
+            detect_language_with_content("script.txt", "echo hi\n# vim: ft=sh\n"),
+            "shellscript"
+        );
+        assert_eq!(
+            detect_language_with_content("notes", "-*- ruby -*-\n"),
+            "ruby"
+        );
+        assert_eq!(
+            detect_language_with_content("notes", "regex: ft=python\n"),
+            "plaintext"
+        );
+    }
+}
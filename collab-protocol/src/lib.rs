@@ -0,0 +1,24 @@
+//! Wire protocol shared by the CodeCollab server and the desktop client.
+//!
+//! Both sides depend on this crate so message definitions can never drift:
+//! bincode encodes enum variants by position, so any difference between the
+//! two copies would silently corrupt messages.
+
+pub mod activity;
+pub mod language;
+pub mod protocol;
+pub mod scan;
+
+pub use activity::{ActivityEntry, ActivityKind};
+pub use language::{detect_language, detect_language_with_content};
+pub use protocol::*;
+pub use scan::{is_binary_extension, ScanOptions};
+
+/// Unique identifier for a project/document
+pub type ProjectId = String;
+
+/// Unique identifier for a peer/user
+pub type PeerId = String;
+
+/// Unique identifier for a file within a project
+pub type FileId = String;
@@ -8,6 +8,7 @@ use bytes::{Buf, BufMut, Bytes, BytesMut};
 use serde::{Deserialize, Serialize};
 use std::io::{self, Cursor};
 
+use super::activity::ActivityEntry;
 use super::{PeerId, ProjectId};
 
 /// Protocol version for compatibility checking
@@ -42,22 +43,49 @@ pub enum MessageType {
     CloseFile = 0x31,
     FileContent = 0x32,
     FileRequest = 0x33,
+    HostProject = 0x34,
+    HostTreeUpdate = 0x35,
+    RequestFileVersion = 0x36,
+    FileVersion = 0x37,
 
     // Presence & Cursors (high-frequency, separate channel)
     PresenceUpdate = 0x40,
     PresenceBroadcast = 0x41,
     CursorUpdate = 0x42,
     CursorBroadcast = 0x43,
+    Reaction = 0x44,
+    ReactionBroadcast = 0x45,
+    SetDisplayName = 0x46,
+    DisplayNameChanged = 0x47,
 
     // Chat
     ChatMessage = 0x50,
     ChatHistory = 0x51,
+    ChatDelete = 0x52,
+    ChatDeleted = 0x53,
 
     // Voice (signaling only - actual audio via LiveKit)
     VoiceJoin = 0x60,
     VoiceLeave = 0x61,
     VoiceToken = 0x62,
 
+    // Activity feed
+    RequestActivityFeed = 0x70,
+    ActivityFeed = 0x71,
+
+    // Project list (lobby socket)
+    ProjectCreated = 0x80,
+    ProjectDeleted = 0x81,
+    PeerCountChanged = 0x82,
+
+    // Annotations (review comments and highlights on file text)
+    AnnotationAdd = 0x90,
+    AnnotationAdded = 0x91,
+    AnnotationResolve = 0x92,
+    AnnotationResolved = 0x93,
+    RequestAnnotations = 0x94,
+    Annotations = 0x95,
+
     // Admin/Debug
     Ping = 0xF0,
     Pong = 0xF1,
@@ -84,15 +112,36 @@ impl TryFrom<u8> for MessageType {
             0x31 => Ok(MessageType::CloseFile),
             0x32 => Ok(MessageType::FileContent),
             0x33 => Ok(MessageType::FileRequest),
+            0x34 => Ok(MessageType::HostProject),
+            0x35 => Ok(MessageType::HostTreeUpdate),
+            0x36 => Ok(MessageType::RequestFileVersion),
+            0x37 => Ok(MessageType::FileVersion),
             0x40 => Ok(MessageType::PresenceUpdate),
             0x41 => Ok(MessageType::PresenceBroadcast),
             0x42 => Ok(MessageType::CursorUpdate),
             0x43 => Ok(MessageType::CursorBroadcast),
+            0x44 => Ok(MessageType::Reaction),
+            0x45 => Ok(MessageType::ReactionBroadcast),
+            0x46 => Ok(MessageType::SetDisplayName),
+            0x47 => Ok(MessageType::DisplayNameChanged),
             0x50 => Ok(MessageType::ChatMessage),
             0x51 => Ok(MessageType::ChatHistory),
+            0x52 => Ok(MessageType::ChatDelete),
+            0x53 => Ok(MessageType::ChatDeleted),
             0x60 => Ok(MessageType::VoiceJoin),
             0x61 => Ok(MessageType::VoiceLeave),
             0x62 => Ok(MessageType::VoiceToken),
+            0x70 => Ok(MessageType::RequestActivityFeed),
+            0x71 => Ok(MessageType::ActivityFeed),
+            0x80 => Ok(MessageType::ProjectCreated),
+            0x81 => Ok(MessageType::ProjectDeleted),
+            0x82 => Ok(MessageType::PeerCountChanged),
+            0x90 => Ok(MessageType::AnnotationAdd),
+            0x91 => Ok(MessageType::AnnotationAdded),
+            0x92 => Ok(MessageType::AnnotationResolve),
+            0x93 => Ok(MessageType::AnnotationResolved),
+            0x94 => Ok(MessageType::RequestAnnotations),
+            0x95 => Ok(MessageType::Annotations),
             0xF0 => Ok(MessageType::Ping),
             0xF1 => Ok(MessageType::Pong),
             0xF2 => Ok(MessageType::Stats),
@@ -144,6 +193,11 @@ pub enum ClientMessage {
         client_id: Option<PeerId>,
         client_name: String,
         session_token: Option<String>,
+        /// Account token from the server's login endpoint, if signed in
+        auth_token: Option<String>,
+        /// Color the client would like (`#rrggbb`), kept unless another peer
+        /// in the project has it
+        preferred_color: Option<String>,
     },
 
     /// Graceful disconnect
@@ -155,6 +209,8 @@ pub enum ClientMessage {
     JoinProject {
         project_id: ProjectId,
         request_state: bool, // Request full state on join
+        /// Token of a share link, joining with the link's role and scope
+        share_token: Option<String>,
     },
 
     /// Leave a project/room
@@ -225,6 +281,91 @@ pub enum ClientMessage {
     Ping {
         timestamp: u64,
     },
+
+    /// Request recent project activity
+    RequestActivityFeed {
+        project_id: ProjectId,
+        /// Maximum number of entries (server default if omitted)
+        limit: Option<u32>,
+    },
+
+    /// Ephemeral emoji reaction/ping pointing at a line of code
+    Reaction {
+        project_id: ProjectId,
+        file_path: String,
+        /// Line number (1-based)
+        line: u32,
+        emoji: String,
+    },
+
+    /// Change own display name in every joined project
+    SetDisplayName {
+        name: String,
+    },
+
+    /// Become the host of a joined project, replacing its file tree with the
+    /// host's local folder (contents are served on demand)
+    HostProject {
+        project_id: ProjectId,
+        entries: Vec<HostedEntry>,
+    },
+
+    /// Files or folders appeared or disappeared in the hosted folder
+    HostTreeUpdate {
+        project_id: ProjectId,
+        created: Vec<HostedEntry>,
+        /// Relative paths; deleting a folder removes everything below it
+        deleted: Vec<String>,
+    },
+
+    /// Host's answer to a `FileRequest`, or a push after the file changed on
+    /// disk (`None` if the file could not be read)
+    HostFileContent {
+        project_id: ProjectId,
+        file_path: String,
+        content: Option<String>,
+    },
+
+    /// Remove a chat message from the project's history (the author, or
+    /// whoever may manage the project)
+    ChatDelete {
+        project_id: ProjectId,
+        message_id: String,
+    },
+
+    /// Comment on or highlight a range of a file's text
+    AnnotationAdd {
+        project_id: ProjectId,
+        file_path: String,
+        /// Start of the range, in characters
+        start: u32,
+        /// End of the range (exclusive), in characters
+        end: u32,
+        kind: AnnotationKind,
+        body: String,
+    },
+
+    /// Mark an annotation resolved, or open it again
+    AnnotationResolve {
+        project_id: ProjectId,
+        file_path: String,
+        annotation_id: String,
+        resolved: bool,
+    },
+
+    /// Request the annotations of a file
+    RequestAnnotations {
+        project_id: ProjectId,
+        file_path: String,
+    },
+
+    /// Request one file's content as of earlier document heads
+    RequestFileVersion {
+        project_id: ProjectId,
+        file_path: String,
+        /// Automerge change hashes, hex encoded
+        heads: Vec<String>,
+    },
 }
 
 /// Messages sent from server to client
@@ -256,8 +397,14 @@ pub enum ServerMessage {
         project_id: ProjectId,
         /// List of other peers in the project
         peers: Vec<PeerInfo>,
-        /// Full document state if requested (Automerge binary)
+        /// Document state if requested (Automerge binary)
         document_state: Option<Vec<u8>>,
+        /// The file tree ready to render: a JSON array of nested nodes
+        file_tree: Option<String>,
+        /// Heads (hex) `document_state` continues from when it only holds
+        /// the changes made since the peer's previous session; None when it
+        /// is the full document
+        document_since: Option<Vec<String>>,
     },
 
     /// Notification that a peer joined
@@ -336,6 +483,8 @@ pub enum ServerMessage {
         peer_name: String,
         content: String,
         timestamp: i64,
+        /// Identifier for `ChatDelete`
+        message_id: Option<String>,
     },
 
     /// Chat history response
@@ -364,6 +513,115 @@ pub enum ServerMessage {
         active_peers: u32,
         uptime_seconds: u64,
     },
+
+    /// Notification that a peer opened a file
+    FileOpened {
+        project_id: ProjectId,
+        peer_id: PeerId,
+        peer_name: String,
+        peer_color: String,
+        file_path: String,
+    },
+
+    /// Notification that a peer closed a file
+    FileClosed {
+        project_id: ProjectId,
+        peer_id: PeerId,
+        file_path: String,
+    },
+
+    /// Recent project activity, newest first
+    ActivityFeed {
+        project_id: ProjectId,
+        entries: Vec<ActivityEntry>,
+    },
+
+    /// Reaction broadcast from another peer (not persisted)
+    ReactionBroadcast {
+        project_id: ProjectId,
+        peer_id: PeerId,
+        peer_name: String,
+        peer_color: String,
+        file_path: String,
+        line: u32,
+        emoji: String,
+        /// How long clients should display the reaction
+        ttl_ms: u64,
+    },
+
+    /// A peer's display name was assigned or changed (also sent to the peer itself)
+    DisplayNameChanged {
+        project_id: ProjectId,
+        peer_id: PeerId,
+        name: String,
+        avatar_seed: String,
+        /// The peer's color in the project
+        color: Option<String>,
+    },
+
+    /// Ask the host to send the content of a file a peer opened
+    FileRequest {
+        project_id: ProjectId,
+        file_path: String,
+    },
+
+    /// A chat message was removed from the history
+    ChatDeleted {
+        project_id: ProjectId,
+        message_id: String,
+        /// Peer that removed it
+        deleted_by: PeerId,
+    },
+
+    /// A project was created (lobby socket)
+    ProjectCreated {
+        project_id: ProjectId,
+        name: String,
+        owner_id: Option<String>,
+        created_at: i64,
+    },
+
+    /// A project was deleted (lobby socket)
+    ProjectDeleted { project_id: ProjectId },
+
+    /// Peers connected to a project changed (lobby socket)
+    PeerCountChanged {
+        project_id: ProjectId,
+        peer_count: u32,
+    },
+
+    /// An annotation was added to a file
+    AnnotationAdded {
+        project_id: ProjectId,
+        annotation: AnnotationInfo,
+    },
+
+    /// An annotation was resolved or opened again
+    AnnotationResolved {
+        project_id: ProjectId,
+        file_path: String,
+        annotation_id: String,
+        resolved: bool,
+        /// Peer that changed it
+        resolved_by: PeerId,
+    },
+
+    /// Annotations of a file, in text order
+    Annotations {
+        project_id: ProjectId,
+        file_path: String,
+        annotations: Vec<AnnotationInfo>,
+    },
+
+    /// A file's content as of the requested heads
+    FileVersion {
+        project_id: ProjectId,
+        file_path: String,
+        heads: Vec<String>,
+        /// None if the file did not exist at those heads
+        content: Option<String>,
+        language: Option<String>,
+    },
 }
 
 /// Presence status
@@ -384,6 +642,17 @@ pub struct PeerInfo {
     pub status: PresenceStatus,
     pub active_file: Option<String>,
     pub joined_at: i64,
+    pub avatar_seed: String,
+}
+
+/// A file or folder of a hosted project
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostedEntry {
+    /// Path relative to the hosted folder, `/`-separated
+    pub path: String,
+    pub is_dir: bool,
+    /// File size in bytes (0 for folders)
+    pub size: u64,
 }
 
 /// Chat history item
@@ -393,6 +662,35 @@ pub struct ChatHistoryItem {
     pub peer_name: String,
     pub content: String,
     pub timestamp: i64,
+    /// Identifier for `ChatDelete`
+    pub message_id: Option<String>,
+}
+
+/// What an annotation is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnnotationKind {
+    /// A review comment, starting a thread
+    Comment,
+    /// A highlight, optionally with a note
+    Highlight,
+}
+
+/// A comment or highlight on a range of a file's text. The range follows the
+/// text through concurrent edits.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnnotationInfo {
+    pub annotation_id: String,
+    pub file_path: String,
+    /// Start of the range, in characters
+    pub start: u32,
+    /// End of the range (exclusive), in characters
+    pub end: u32,
+    pub kind: AnnotationKind,
+    pub author_id: PeerId,
+    pub author_name: String,
+    pub body: String,
+    pub created_at: i64,
+    pub resolved: bool,
 }
 
 /// Error codes for server responses
@@ -433,6 +731,17 @@ impl SyncProtocol {
             ClientMessage::VoiceJoin { .. } => MessageType::VoiceJoin,
             ClientMessage::VoiceLeave { .. } => MessageType::VoiceLeave,
             ClientMessage::Ping { .. } => MessageType::Ping,
+            ClientMessage::RequestActivityFeed { .. } => MessageType::RequestActivityFeed,
+            ClientMessage::Reaction { .. } => MessageType::Reaction,
+            ClientMessage::SetDisplayName { .. } => MessageType::SetDisplayName,
+            ClientMessage::HostProject { .. } => MessageType::HostProject,
+            ClientMessage::HostTreeUpdate { .. } => MessageType::HostTreeUpdate,
+            ClientMessage::HostFileContent { .. } => MessageType::FileContent,
+            ClientMessage::ChatDelete { .. } => MessageType::ChatDelete,
+            ClientMessage::AnnotationAdd { .. } => MessageType::AnnotationAdd,
+            ClientMessage::AnnotationResolve { .. } => MessageType::AnnotationResolve,
+            ClientMessage::RequestAnnotations { .. } => MessageType::RequestAnnotations,
+            ClientMessage::RequestFileVersion { .. } => MessageType::RequestFileVersion,
         };
 
         let payload = bincode::serialize(msg)?;
@@ -474,6 +783,20 @@ impl SyncProtocol {
             ServerMessage::VoiceToken { .. } => MessageType::VoiceToken,
             ServerMessage::Pong { .. } => MessageType::Pong,
             ServerMessage::Stats { .. } => MessageType::Stats,
+            ServerMessage::FileOpened { .. } => MessageType::OpenFile,
+            ServerMessage::FileClosed { .. } => MessageType::CloseFile,
+            ServerMessage::ActivityFeed { .. } => MessageType::ActivityFeed,
+            ServerMessage::ReactionBroadcast { .. } => MessageType::ReactionBroadcast,
+            ServerMessage::DisplayNameChanged { .. } => MessageType::DisplayNameChanged,
+            ServerMessage::FileRequest { .. } => MessageType::FileRequest,
+            ServerMessage::ChatDeleted { .. } => MessageType::ChatDeleted,
+            ServerMessage::ProjectCreated { .. } => MessageType::ProjectCreated,
+            ServerMessage::ProjectDeleted { .. } => MessageType::ProjectDeleted,
+            ServerMessage::PeerCountChanged { .. } => MessageType::PeerCountChanged,
+            ServerMessage::AnnotationAdded { .. } => MessageType::AnnotationAdded,
+            ServerMessage::AnnotationResolved { .. } => MessageType::AnnotationResolved,
+            ServerMessage::Annotations { .. } => MessageType::Annotations,
+            ServerMessage::FileVersion { .. } => MessageType::FileVersion,
         };
 
         let payload = bincode::serialize(msg)?;
@@ -596,6 +919,8 @@ mod tests {
             client_id: Some("client-123".to_string()),
             client_name: "Test User".to_string(),
             session_token: None,
+            auth_token: None,
+            preferred_color: Some("#3b82f6".to_string()),
         };
 
         let encoded = SyncProtocol::encode_client(&msg).unwrap();
@@ -606,11 +931,13 @@ mod tests {
                 protocol_version,
                 client_id,
                 client_name,
+                preferred_color,
                 ..
             } => {
                 assert_eq!(protocol_version, PROTOCOL_VERSION);
                 assert_eq!(client_id, Some("client-123".to_string()));
                 assert_eq!(client_name, "Test User");
+                assert_eq!(preferred_color, Some("#3b82f6".to_string()));
             }
             _ => panic!("Wrong message type"),
         }
@@ -695,9 +1022,164 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_reaction_roundtrip() {
+        let msg = ClientMessage::Reaction {
+            project_id: "proj".to_string(),
+            file_path: "/src/main.rs".to_string(),
+            line: 7,
+            emoji: "🎉".to_string(),
+        };
+
+        let encoded = SyncProtocol::encode_client(&msg).unwrap();
+        assert_eq!(encoded[1], MessageType::Reaction as u8);
+
+        match SyncProtocol::decode_client(&encoded).unwrap() {
+            ClientMessage::Reaction { line, emoji, .. } => {
+                assert_eq!(line, 7);
+                assert_eq!(emoji, "🎉");
+            }
+            _ => panic!("Wrong message type"),
+        }
+    }
+
+    #[test]
+    fn test_chat_delete_roundtrip() {
+        let msg = ServerMessage::ChatDeleted {
+            project_id: "proj".to_string(),
+            message_id: "0190a1b2c3d4e5f6".to_string(),
+            deleted_by: "peer-1".to_string(),
+        };
+
+        let encoded = SyncProtocol::encode_server(&msg).unwrap();
+        assert_eq!(encoded[1], MessageType::ChatDeleted as u8);
+
+        match SyncProtocol::decode_server(&encoded).unwrap() {
+            ServerMessage::ChatDeleted { message_id, .. } => {
+                assert_eq!(message_id, "0190a1b2c3d4e5f6");
+            }
+            _ => panic!("Wrong message type"),
+        }
+    }
+
+    #[test]
+    fn test_annotation_roundtrip() {
+        let msg = ClientMessage::AnnotationAdd {
+            project_id: "proj".to_string(),
+            file_path: "src/main.rs".to_string(),
+            start: 3,
+            end: 7,
+            kind: AnnotationKind::Comment,
+            body: "Rename this".to_string(),
+        };
+        let encoded = SyncProtocol::encode_client(&msg).unwrap();
+        assert_eq!(encoded[1], MessageType::AnnotationAdd as u8);
+        match SyncProtocol::decode_client(&encoded).unwrap() {
+            ClientMessage::AnnotationAdd {
+                start, end, kind, ..
+            } => {
+                assert_eq!((start, end), (3, 7));
+                assert_eq!(kind, AnnotationKind::Comment);
+            }
+            _ => panic!("Wrong message type"),
+        }
+
+        let annotation = AnnotationInfo {
+            annotation_id: "a1".to_string(),
+            file_path: "src/main.rs".to_string(),
+            start: 3,
+            end: 7,
+            kind: AnnotationKind::Highlight,
+            author_id: "peer-1".to_string(),
+            author_name: "Alice".to_string(),
+            body: String::new(),
+            created_at: 1_700_000_000,
+            resolved: false,
+        };
+        let msg = ServerMessage::Annotations {
+            project_id: "proj".to_string(),
+            file_path: "src/main.rs".to_string(),
+            annotations: vec![annotation.clone()],
+        };
+        let encoded = SyncProtocol::encode_server(&msg).unwrap();
+        assert_eq!(encoded[1], MessageType::Annotations as u8);
+        match SyncProtocol::decode_server(&encoded).unwrap() {
+            ServerMessage::Annotations { annotations, .. } => {
+                assert_eq!(annotations, vec![annotation]);
+            }
+            _ => panic!("Wrong message type"),
+        }
+    }
+
+    #[test]
+    fn test_file_version_roundtrip() {
+        let msg = ServerMessage::FileVersion {
+            project_id: "proj".to_string(),
+            file_path: "src/main.rs".to_string(),
+            heads: vec!["ab".repeat(32)],
+            content: Some("fn main() {}".to_string()),
+            language: Some("rust".to_string()),
+        };
+        let encoded = SyncProtocol::encode_server(&msg).unwrap();
+        assert_eq!(encoded[1], MessageType::FileVersion as u8);
+        match SyncProtocol::decode_server(&encoded).unwrap() {
+            ServerMessage::FileVersion { heads, content, .. } => {
+                assert_eq!(heads, vec!["ab".repeat(32)]);
+                assert_eq!(content.as_deref(), Some("fn main() {}"));
+            }
+            _ => panic!("Wrong message type"),
+        }
+    }
+
+    #[test]
+    fn test_lobby_roundtrip() {
+        let msg = ServerMessage::PeerCountChanged {
+            project_id: "proj".to_string(),
+            peer_count: 3,
+        };
+
+        let encoded = SyncProtocol::encode_server(&msg).unwrap();
+        assert_eq!(encoded[1], MessageType::PeerCountChanged as u8);
+
+        match SyncProtocol::decode_server(&encoded).unwrap() {
+            ServerMessage::PeerCountChanged { peer_count, .. } => assert_eq!(peer_count, 3),
+            _ => panic!("Wrong message type"),
+        }
+    }
+
+    #[test]
+    fn test_host_project_roundtrip() {
+        let msg = ClientMessage::HostProject {
+            project_id: "proj".to_string(),
+            entries: vec![
+                HostedEntry {
+                    path: "src".to_string(),
+                    is_dir: true,
+                    size: 0,
+                },
+                HostedEntry {
+                    path: "src/main.rs".to_string(),
+                    is_dir: false,
+                    size: 42,
+                },
+            ],
+        };
+
+        let encoded = SyncProtocol::encode_client(&msg).unwrap();
+        assert_eq!(encoded[1], MessageType::HostProject as u8);
+
+        match SyncProtocol::decode_client(&encoded).unwrap() {
+            ClientMessage::HostProject { entries, .. } => {
+                assert_eq!(entries.len(), 2);
+                assert_eq!(entries[1].path, "src/main.rs");
+            }
+            _ => panic!("Wrong message type"),
+        }
+    }
+
     #[test]
     fn test_version_mismatch() {
-        let mut data = SyncProtocol::encode_client(&ClientMessage::Ping { timestamp: 0 }).unwrap();
+        let data = SyncProtocol::encode_client(&ClientMessage::Ping { timestamp: 0 }).unwrap();
         // Corrupt version
         let mut bytes = data.to_vec();
         bytes[0] = 0xFF;
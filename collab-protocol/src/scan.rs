@@ -0,0 +1,161 @@
+//! Directory scanning rules for hosted projects.
+//!
+//! The server applies them when it scans a folder on its own disk; the desktop
+//! client applies the same rules when it hosts a folder remotely.
+
+use serde::{Deserialize, Serialize};
+
+/// Options for directory scanning
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanOptions {
+    /// Maximum file size to include content (bytes)
+    pub max_file_size: u64,
+    /// File extensions to include (empty = all)
+    pub include_extensions: Vec<String>,
+    /// File/folder patterns to exclude
+    pub exclude_patterns: Vec<String>,
+    /// Whether to read file contents during scan
+    pub read_contents: bool,
+    /// Maximum depth to scan (0 = unlimited)
+    pub max_depth: usize,
+    /// Maximum number of files to scan
+    pub max_files: usize,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            max_file_size: 10 * 1024 * 1024, // 10MB
+            include_extensions: Vec::new(),
+            exclude_patterns: vec![
+                ".git".to_string(),
+                "node_modules".to_string(),
+                "target".to_string(),
+                ".next".to_string(),
+                "__pycache__".to_string(),
+                ".pytest_cache".to_string(),
+                "dist".to_string(),
+                "build".to_string(),
+                ".DS_Store".to_string(),
+                "*.pyc".to_string(),
+                "*.pyo".to_string(),
+                "*.so".to_string(),
+                "*.dylib".to_string(),
+                "*.dll".to_string(),
+                "*.exe".to_string(),
+            ],
+            read_contents: false, // On-demand loading by default
+            max_depth: 20,
+            max_files: 10000,
+        }
+    }
+}
+
+impl ScanOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_file_size(mut self, size: u64) -> Self {
+        self.max_file_size = size;
+        self
+    }
+
+    pub fn with_read_contents(mut self, read: bool) -> Self {
+        self.read_contents = read;
+        self
+    }
+
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    pub fn with_exclude_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude_patterns.push(pattern.into());
+        self
+    }
+
+    /// Check if a path should be excluded based on patterns
+    pub fn should_exclude(&self, path: &str, name: &str) -> bool {
+        for pattern in &self.exclude_patterns {
+            if let Some(suffix) = pattern.strip_prefix('*') {
+                // Wildcard pattern (e.g., "*.pyc")
+                if name.ends_with(suffix) {
+                    return true;
+                }
+            } else if name == pattern || path.contains(pattern) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Check if a file extension should be included
+    pub fn should_include_extension(&self, extension: &str) -> bool {
+        if self.include_extensions.is_empty() {
+            return true;
+        }
+        self.include_extensions.iter().any(|ext| ext == extension)
+    }
+}
+
+/// Check if a file is likely binary based on extension
+pub fn is_binary_extension(path: &str) -> bool {
+    let ext = path
+        .rsplit('.')
+        .next()
+        .unwrap_or("")
+        .to_lowercase();
+
+    matches!(
+        ext.as_str(),
+        "png" | "jpg" | "jpeg" | "gif" | "bmp" | "ico" | "webp" | "svg"
+            | "mp3" | "mp4" | "wav" | "ogg" | "webm" | "avi" | "mov"
+            | "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx"
+            | "zip" | "tar" | "gz" | "rar" | "7z" | "bz2"
+            | "exe" | "dll" | "so" | "dylib" | "bin"
+            | "ttf" | "otf" | "woff" | "woff2" | "eot"
+            | "sqlite" | "db" | "sqlite3"
+            | "pyc" | "pyo" | "class" | "o" | "obj"
+            | "wasm"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_binary() {
+        assert!(is_binary_extension("image.png"));
+        assert!(is_binary_extension("archive.zip"));
+        assert!(!is_binary_extension("code.rs"));
+        assert!(!is_binary_extension("readme.md"));
+    }
+
+    #[test]
+    fn test_scan_options_exclude() {
+        let opts = ScanOptions::default();
+
+        assert!(opts.should_exclude("/project/node_modules/foo", "foo"));
+        assert!(opts.should_exclude("/project/.git/config", "config"));
+        assert!(opts.should_exclude("/project/file.pyc", "file.pyc"));
+        assert!(!opts.should_exclude("/project/src/main.rs", "main.rs"));
+    }
+
+    #[test]
+    fn test_scan_options_builder() {
+        let opts = ScanOptions::new()
+            .with_max_file_size(1024 * 1024)
+            .with_read_contents(true)
+            .with_max_depth(10)
+            .with_exclude_pattern("*.log");
+
+        assert_eq!(opts.max_file_size, 1024 * 1024);
+        assert!(opts.read_contents);
+        assert_eq!(opts.max_depth, 10);
+        assert!(opts.exclude_patterns.contains(&"*.log".to_string()));
+    }
+}
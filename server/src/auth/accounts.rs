@@ -0,0 +1,380 @@
+//! Account registration, login and token handling.
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use std::sync::{Arc, OnceLock};
+use tracing::info;
+
+use super::{AuthClaims, AuthConfig, AuthError, ExternalIdentity, UserInfo};
+use crate::storage::{DocumentStore, UserAccount};
+
+/// Shortest password accepted at registration
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Longest username accepted at registration
+const MAX_USERNAME_LENGTH: usize = 32;
+
+/// Hash of a random password, verified against when a login has no real
+/// hash to check
+fn dummy_hash() -> &'static str {
+    static HASH: OnceLock<String> = OnceLock::new();
+    HASH.get_or_init(|| {
+        let password = SaltString::generate(&mut OsRng);
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_str().as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .unwrap_or_default()
+    })
+}
+
+/// Service for user accounts and their tokens
+pub struct AuthService {
+    storage: Arc<DocumentStore>,
+    config: AuthConfig,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+}
+
+impl AuthService {
+    pub fn new(storage: Arc<DocumentStore>, config: AuthConfig) -> Self {
+        Self {
+            storage,
+            encoding: EncodingKey::from_secret(&config.jwt_secret),
+            decoding: DecodingKey::from_secret(&config.jwt_secret),
+            config,
+        }
+    }
+
+    /// Register a new account and sign it in
+    pub fn register(
+        &self,
+        username: &str,
+        password: &str,
+        display_name: Option<&str>,
+    ) -> Result<(UserInfo, String), AuthError> {
+        let username = validate_username(username)?;
+        if password.chars().count() < MIN_PASSWORD_LENGTH {
+            return Err(AuthError::WeakPassword(MIN_PASSWORD_LENGTH));
+        }
+
+        let salt = SaltString::generate(&mut OsRng);
+        let password_hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| AuthError::Hashing(e.to_string()))?
+            .to_string();
+
+        let display_name = display_name
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .unwrap_or(&username)
+            .to_string();
+        let user = UserAccount {
+            user_id: uuid::Uuid::new_v4().to_string(),
+            username,
+            display_name,
+            password_hash,
+            created_at: chrono::Utc::now().timestamp(),
+        };
+
+        let created = self
+            .storage
+            .create_user(&user)
+            .map_err(|e| AuthError::Storage(e.to_string()))?;
+        if !created {
+            return Err(AuthError::UsernameTaken);
+        }
+
+        info!("User registered: {} ({})", user.username, user.user_id);
+        let token = self.issue_token(&user)?;
+        Ok((user.into(), token))
+    }
+
+    /// Check a username and password and issue a token
+    pub fn login(&self, username: &str, password: &str) -> Result<(UserInfo, String), AuthError> {
+        let user = self
+            .storage
+            .find_user_by_username(username.trim())
+            .map_err(|e| AuthError::Storage(e.to_string()))?;
+
+        // Unknown usernames, and accounts created through OAuth which have no
+        // password, are checked against a dummy hash so that their logins
+        // take as long as any other and do not reveal which accounts exist
+        let hash = user
+            .as_ref()
+            .and_then(|user| PasswordHash::new(&user.password_hash).ok());
+        let dummy =
+            PasswordHash::new(dummy_hash()).map_err(|e| AuthError::Hashing(e.to_string()))?;
+        let verified = Argon2::default()
+            .verify_password(password.as_bytes(), hash.as_ref().unwrap_or(&dummy))
+            .is_ok()
+            && hash.is_some();
+        let user = user
+            .filter(|_| verified)
+            .ok_or(AuthError::InvalidCredentials)?;
+
+        let token = self.issue_token(&user)?;
+        Ok((user.into(), token))
+    }
+
+    /// Sign in with an identity from an OAuth provider.
+    ///
+    /// With `link_to` the identity is added to that account. Otherwise the
+    /// account it is linked to is signed in, or a new one is created for it.
+    pub fn oauth_login(
+        &self,
+        identity: &ExternalIdentity,
+        link_to: Option<&str>,
+    ) -> Result<(UserInfo, String), AuthError> {
+        let linked = self
+            .storage
+            .find_identity(&identity.provider, &identity.external_id)
+            .map_err(|e| AuthError::Storage(e.to_string()))?;
+
+        let user = match (link_to, linked) {
+            (Some(user_id), _) => {
+                let user = self.account(user_id)?.ok_or(AuthError::InvalidToken)?;
+                self.link(identity, &user)?;
+                user
+            }
+            (None, Some(user_id)) => self.account(&user_id)?.ok_or(AuthError::InvalidToken)?,
+            (None, None) => {
+                let user = self.create_oauth_account(identity)?;
+                self.link(identity, &user)?;
+                user
+            }
+        };
+
+        let token = self.issue_token(&user)?;
+        Ok((user.into(), token))
+    }
+
+    fn account(&self, user_id: &str) -> Result<Option<UserAccount>, AuthError> {
+        self.storage
+            .get_user(user_id)
+            .map_err(|e| AuthError::Storage(e.to_string()))
+    }
+
+    fn link(&self, identity: &ExternalIdentity, user: &UserAccount) -> Result<(), AuthError> {
+        let linked = self
+            .storage
+            .link_identity(&identity.provider, &identity.external_id, &user.user_id)
+            .map_err(|e| AuthError::Storage(e.to_string()))?;
+        if !linked {
+            return Err(AuthError::IdentityTaken(identity.provider.clone()));
+        }
+        info!(
+            "Linked {} identity {} to user {}",
+            identity.provider, identity.external_id, user.user_id
+        );
+        Ok(())
+    }
+
+    /// Create a passwordless account named after the identity, adding a
+    /// number if the name is taken
+    fn create_oauth_account(&self, identity: &ExternalIdentity) -> Result<UserAccount, AuthError> {
+        let base: String = identity
+            .username
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+            .take(MAX_USERNAME_LENGTH - 4)
+            .collect();
+        let base = if base.len() < 3 {
+            format!("{}-user", identity.provider)
+        } else {
+            base
+        };
+
+        for attempt in 1..=100 {
+            let username = if attempt == 1 {
+                base.clone()
+            } else {
+                format!("{}-{}", base, attempt)
+            };
+            let user = UserAccount {
+                user_id: uuid::Uuid::new_v4().to_string(),
+                username,
+                display_name: identity
+                    .display_name
+                    .clone()
+                    .unwrap_or_else(|| identity.username.clone()),
+                password_hash: String::new(),
+                created_at: chrono::Utc::now().timestamp(),
+            };
+            let created = self
+                .storage
+                .create_user(&user)
+                .map_err(|e| AuthError::Storage(e.to_string()))?;
+            if created {
+                info!(
+                    "User registered through {}: {} ({})",
+                    identity.provider, user.username, user.user_id
+                );
+                return Ok(user);
+            }
+        }
+
+        Err(AuthError::UsernameTaken)
+    }
+
+    /// Check a token's signature and expiry
+    pub fn verify_token(&self, token: &str) -> Result<AuthClaims, AuthError> {
+        decode::<AuthClaims>(token, &self.decoding, &Validation::new(Algorithm::HS256))
+            .map(|data| data.claims)
+            .map_err(|_| AuthError::InvalidToken)
+    }
+
+    /// Look up an account
+    pub fn user(&self, user_id: &str) -> Result<Option<UserInfo>, AuthError> {
+        self.storage
+            .get_user(user_id)
+            .map(|user| user.map(UserInfo::from))
+            .map_err(|e| AuthError::Storage(e.to_string()))
+    }
+
+    fn issue_token(&self, user: &UserAccount) -> Result<String, AuthError> {
+        let now = chrono::Utc::now().timestamp();
+        let claims = AuthClaims {
+            sub: user.user_id.clone(),
+            name: user.username.clone(),
+            iat: now,
+            exp: now + self.config.token_ttl_seconds as i64,
+        };
+        encode(&Header::new(Algorithm::HS256), &claims, &self.encoding)
+            .map_err(|_| AuthError::InvalidToken)
+    }
+}
+
+fn validate_username(username: &str) -> Result<String, AuthError> {
+    let username = username.trim();
+    if username.len() < 3 || username.len() > MAX_USERNAME_LENGTH {
+        return Err(AuthError::InvalidUsername(format!(
+            "must be 3 to {} characters",
+            MAX_USERNAME_LENGTH
+        )));
+    }
+    if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        return Err(AuthError::InvalidUsername(
+            "only letters, digits, '_', '-' and '.' are allowed".to_string(),
+        ));
+    }
+    Ok(username.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StorageConfig;
+    use tempfile::tempdir;
+
+    fn test_service() -> AuthService {
+        let dir = tempdir().unwrap();
+        let config = StorageConfig::new(dir.path().join("test.sled").to_string_lossy().to_string());
+        let storage = Arc::new(DocumentStore::open(config).unwrap());
+        AuthService::new(storage, AuthConfig::new("secret"))
+    }
+
+    #[test]
+    fn test_register_and_login() {
+        let auth = test_service();
+
+        let (user, token) = auth.register("alice", "correct horse", None).unwrap();
+        assert_eq!(user.display_name, "alice");
+        assert_eq!(auth.verify_token(&token).unwrap().sub, user.user_id);
+
+        assert!(matches!(
+            auth.register("Alice", "another password", None),
+            Err(AuthError::UsernameTaken)
+        ));
+
+        let (logged_in, _) = auth.login("ALICE", "correct horse").unwrap();
+        assert_eq!(logged_in.user_id, user.user_id);
+        assert!(matches!(
+            auth.login("alice", "wrong password"),
+            Err(AuthError::InvalidCredentials)
+        ));
+        assert!(matches!(
+            auth.login("bob", "correct horse"),
+            Err(AuthError::InvalidCredentials)
+        ));
+    }
+
+    #[test]
+    fn test_registration_rules() {
+        let auth = test_service();
+
+        assert!(matches!(
+            auth.register("al", "long enough", None),
+            Err(AuthError::InvalidUsername(_))
+        ));
+        assert!(matches!(
+            auth.register("al ice", "long enough", None),
+            Err(AuthError::InvalidUsername(_))
+        ));
+        assert!(matches!(
+            auth.register("alice", "short", None),
+            Err(AuthError::WeakPassword(_))
+        ));
+    }
+
+    #[test]
+    fn test_token_from_other_secret() {
+        let auth = test_service();
+        let (_, token) = auth.register("alice", "correct horse", None).unwrap();
+
+        let other = AuthService::new(auth.storage.clone(), AuthConfig::new("other"));
+        assert!(matches!(
+            other.verify_token(&token),
+            Err(AuthError::InvalidToken)
+        ));
+        assert!(matches!(
+            auth.verify_token("garbage"),
+            Err(AuthError::InvalidToken)
+        ));
+    }
+
+    #[test]
+    fn test_oauth_login() {
+        let auth = test_service();
+        let identity = ExternalIdentity {
+            provider: "github".to_string(),
+            external_id: "42".to_string(),
+            username: "alice".to_string(),
+            display_name: Some("Alice A.".to_string()),
+        };
+
+        // A password account already has the name, so a number is added
+        let (password_user, _) = auth.register("alice", "correct horse", None).unwrap();
+        let (oauth_user, _) = auth.oauth_login(&identity, None).unwrap();
+        assert_eq!(oauth_user.username, "alice-2");
+        assert_eq!(oauth_user.display_name, "Alice A.");
+
+        // The same identity signs in to the same account, without a password
+        let (again, _) = auth.oauth_login(&identity, None).unwrap();
+        assert_eq!(again.user_id, oauth_user.user_id);
+        assert!(matches!(
+            auth.login("alice-2", ""),
+            Err(AuthError::InvalidCredentials)
+        ));
+
+        // It cannot be linked to another account, but a new one can
+        assert!(matches!(
+            auth.oauth_login(&identity, Some(&password_user.user_id)),
+            Err(AuthError::IdentityTaken(_))
+        ));
+        let google = ExternalIdentity {
+            provider: "google".to_string(),
+            external_id: "abc".to_string(),
+            ..identity
+        };
+        let (linked, _) = auth
+            .oauth_login(&google, Some(&password_user.user_id))
+            .unwrap();
+        assert_eq!(linked.user_id, password_user.user_id);
+    }
+}
@@ -0,0 +1,50 @@
+//! HTTP middleware that resolves account tokens.
+
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+use super::AuthService;
+
+/// The account behind a request's bearer token
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub user_id: String,
+    pub username: String,
+}
+
+/// Attach the authenticated user to the request as an `AuthUser` extension.
+///
+/// Requests without a token pass through anonymously. Bearer values that are
+/// not JWTs (such as WebSocket session tokens) are left for the handler; a
+/// JWT that fails verification is rejected.
+pub async fn authenticate(
+    State(auth): State<Arc<AuthService>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, String)> {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| token.matches('.').count() == 2)
+        .map(str::to_string);
+
+    if let Some(token) = token {
+        let claims = auth
+            .verify_token(&token)
+            .map_err(|e| (StatusCode::UNAUTHORIZED, e.to_string()))?;
+        request.extensions_mut().insert(AuthUser {
+            user_id: claims.sub,
+            username: claims.name,
+        });
+    }
+
+    Ok(next.run(request).await)
+}
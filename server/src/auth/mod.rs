@@ -0,0 +1,131 @@
+//! Auth module for user accounts.
+//!
+//! This module handles:
+//! - Registration and login with argon2 password hashing
+//! - JWT issuance and verification
+//! - Middleware that attaches the authenticated user to HTTP requests
+//! - OAuth2 sign-in (GitHub, Google) and linking those identities to accounts
+//!
+//! Accounts are optional: peers without a token keep working anonymously.
+
+mod accounts;
+mod middleware;
+pub mod oauth;
+
+pub use accounts::AuthService;
+pub use middleware::{authenticate, AuthUser};
+pub use oauth::{ExternalIdentity, OAuthService};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::storage::UserAccount;
+
+/// Errors that can occur during authentication
+#[derive(Error, Debug)]
+pub enum AuthError {
+    #[error("Invalid username: {0}")]
+    InvalidUsername(String),
+
+    #[error("Password must be at least {0} characters")]
+    WeakPassword(usize),
+
+    #[error("Username is already taken")]
+    UsernameTaken,
+
+    #[error("Invalid username or password")]
+    InvalidCredentials,
+
+    #[error("Invalid or expired token")]
+    InvalidToken,
+
+    #[error("Unknown sign-in provider: {0}")]
+    UnknownProvider(String),
+
+    #[error("Sign-in failed: {0}")]
+    OAuth(String),
+
+    #[error("This {0} account is already linked to another user")]
+    IdentityTaken(String),
+
+    #[error("Password hashing failed: {0}")]
+    Hashing(String),
+
+    #[error("Storage error: {0}")]
+    Storage(String),
+}
+
+/// Configuration for the auth service
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    /// Secret tokens are signed with
+    pub jwt_secret: Vec<u8>,
+    /// Token lifetime in seconds
+    pub token_ttl_seconds: u64,
+}
+
+impl AuthConfig {
+    pub fn new(jwt_secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            jwt_secret: jwt_secret.into(),
+            token_ttl_seconds: 7 * 24 * 60 * 60, // 7 days default
+        }
+    }
+
+    /// Create from environment variables (`JWT_SECRET`, `JWT_TTL_SECONDS`)
+    pub fn from_env() -> Option<Self> {
+        let config = std::env::var("JWT_SECRET")
+            .ok()
+            .filter(|secret| !secret.is_empty())
+            .map(Self::new)?;
+
+        Some(
+            match std::env::var("JWT_TTL_SECONDS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+            {
+                Some(seconds) => config.with_ttl(seconds),
+                None => config,
+            },
+        )
+    }
+
+    /// Set token TTL
+    pub fn with_ttl(mut self, seconds: u64) -> Self {
+        self.token_ttl_seconds = seconds;
+        self
+    }
+}
+
+/// Claims of an account token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthClaims {
+    /// Subject (user ID)
+    pub sub: String,
+    /// Username
+    pub name: String,
+    /// Issued at timestamp
+    pub iat: i64,
+    /// Expiration timestamp
+    pub exp: i64,
+}
+
+/// Public view of a user account
+#[derive(Debug, Clone, Serialize)]
+pub struct UserInfo {
+    pub user_id: String,
+    pub username: String,
+    pub display_name: String,
+    pub created_at: i64,
+}
+
+impl From<UserAccount> for UserInfo {
+    fn from(user: UserAccount) -> Self {
+        Self {
+            user_id: user.user_id,
+            username: user.username,
+            display_name: user.display_name,
+            created_at: user.created_at,
+        }
+    }
+}
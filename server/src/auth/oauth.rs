@@ -0,0 +1,562 @@
+//! OAuth2 sign-in with GitHub and Google.
+//!
+//! The authorization-code flow with PKCE: `start` hands out the provider's
+//! authorize URL with a random `state`, and `finish` takes the code the
+//! provider redirects back with, exchanges it for an access token and fetches
+//! the user's identity. Pending states are kept in memory, expire after a few
+//! minutes and can be used only once.
+//!
+//! Each state is bound to the browser that started the sign-in: `start` also
+//! hands out a nonce, sent to the browser as an HttpOnly cookie scoped to the
+//! callback, and `finish` refuses a state that comes without it. A callback
+//! link crafted from someone else's sign-in therefore cannot log a victim
+//! into the attacker's account.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use dashmap::DashMap;
+use reqwest::Url;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use super::AuthError;
+
+/// How long a sign-in may take between start and callback
+const STATE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Cookie carrying a sign-in's browser nonce to the callback
+pub const NONCE_COOKIE: &str = "collab_oauth_nonce";
+
+/// Sent to providers that require one (GitHub)
+const USER_AGENT: &str = "collab-server";
+
+/// Endpoints and credentials of an OAuth provider
+#[derive(Debug, Clone)]
+pub struct ProviderConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    pub auth_url: String,
+    pub token_url: String,
+    pub userinfo_url: String,
+    pub scopes: Vec<String>,
+}
+
+impl ProviderConfig {
+    pub fn github(client_id: impl Into<String>, client_secret: impl Into<String>) -> Self {
+        Self {
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            auth_url: "https://github.com/login/oauth/authorize".to_string(),
+            token_url: "https://github.com/login/oauth/access_token".to_string(),
+            userinfo_url: "https://api.github.com/user".to_string(),
+            scopes: vec!["read:user".to_string()],
+        }
+    }
+
+    pub fn google(client_id: impl Into<String>, client_secret: impl Into<String>) -> Self {
+        Self {
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            auth_url: "https://accounts.google.com/o/oauth2/v2/auth".to_string(),
+            token_url: "https://oauth2.googleapis.com/token".to_string(),
+            userinfo_url: "https://openidconnect.googleapis.com/v1/userinfo".to_string(),
+            scopes: vec!["openid".to_string(), "profile".to_string()],
+        }
+    }
+
+    /// Read `<PREFIX>_CLIENT_ID` and `<PREFIX>_CLIENT_SECRET`
+    fn credentials_from_env(prefix: &str) -> Option<(String, String)> {
+        let id = std::env::var(format!("{}_CLIENT_ID", prefix)).ok()?;
+        let secret = std::env::var(format!("{}_CLIENT_SECRET", prefix)).ok()?;
+        if id.is_empty() || secret.is_empty() {
+            return None;
+        }
+        Some((id, secret))
+    }
+}
+
+/// Configuration for OAuth sign-in
+#[derive(Debug, Clone, Default)]
+pub struct OAuthConfig {
+    /// Providers by name (`github`, `google`)
+    pub providers: HashMap<String, ProviderConfig>,
+    /// The server's `/auth/callback` URL, as registered with the providers
+    pub redirect_url: String,
+    /// Origins a finished sign-in may redirect to with its token
+    pub allowed_origins: Vec<String>,
+}
+
+impl OAuthConfig {
+    /// Create from environment variables (`GITHUB_CLIENT_ID`,
+    /// `GITHUB_CLIENT_SECRET`, `GOOGLE_CLIENT_ID`, `GOOGLE_CLIENT_SECRET`,
+    /// `OAUTH_REDIRECT_URL`, `OAUTH_ALLOWED_ORIGINS`)
+    pub fn from_env() -> Self {
+        let mut providers = HashMap::new();
+        if let Some((id, secret)) = ProviderConfig::credentials_from_env("GITHUB") {
+            providers.insert("github".to_string(), ProviderConfig::github(id, secret));
+        }
+        if let Some((id, secret)) = ProviderConfig::credentials_from_env("GOOGLE") {
+            providers.insert("google".to_string(), ProviderConfig::google(id, secret));
+        }
+
+        let redirect_url = std::env::var("OAUTH_REDIRECT_URL")
+            .unwrap_or_else(|_| "http://localhost:5000/auth/callback".to_string());
+        let allowed_origins = std::env::var("OAUTH_ALLOWED_ORIGINS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|origin| origin.trim().trim_end_matches('/').to_string())
+            .filter(|origin| !origin.is_empty())
+            .collect();
+
+        Self {
+            providers,
+            redirect_url,
+            allowed_origins,
+        }
+    }
+}
+
+/// A user as identified by an OAuth provider
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalIdentity {
+    /// Provider name (`github`, `google`)
+    pub provider: String,
+    /// The provider's stable ID for the user
+    pub external_id: String,
+    /// Suggested username for a new account
+    pub username: String,
+    pub display_name: Option<String>,
+}
+
+/// A sign-in waiting for the provider's callback
+#[derive(Debug, Clone)]
+struct PendingLogin {
+    provider: String,
+    code_verifier: String,
+    /// Held by the browser that started the sign-in
+    nonce: String,
+    link_user_id: Option<String>,
+    redirect_to: Option<String>,
+    created_at: Instant,
+}
+
+/// A sign-in completed with the provider
+#[derive(Debug, Clone)]
+pub struct CompletedLogin {
+    pub identity: ExternalIdentity,
+    /// Account to link the identity to, if the sign-in was started by one
+    pub link_user_id: Option<String>,
+    pub redirect_to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubUser {
+    id: u64,
+    login: String,
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleUser {
+    sub: String,
+    name: Option<String>,
+    given_name: Option<String>,
+}
+
+/// Service for OAuth sign-in flows
+pub struct OAuthService {
+    config: OAuthConfig,
+    pending: DashMap<String, PendingLogin>,
+    http: reqwest::Client,
+}
+
+impl OAuthService {
+    pub fn new(config: OAuthConfig) -> Self {
+        Self {
+            config,
+            pending: DashMap::new(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Names of the configured providers
+    pub fn providers(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.config.providers.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Begin a sign-in and return the provider's authorize URL and the
+    /// nonce the browser must bring back to the callback.
+    ///
+    /// `link_user_id` links the identity to an existing account instead of
+    /// signing in; `redirect_to` must be on an allowed origin.
+    pub fn start(
+        &self,
+        provider: &str,
+        link_user_id: Option<String>,
+        redirect_to: Option<String>,
+    ) -> Result<(String, String), AuthError> {
+        let config = self
+            .config
+            .providers
+            .get(provider)
+            .ok_or_else(|| AuthError::UnknownProvider(provider.to_string()))?;
+        if let Some(target) = &redirect_to {
+            if !self.is_allowed_redirect(target) {
+                return Err(AuthError::OAuth(
+                    "redirect target is not allowed".to_string(),
+                ));
+            }
+        }
+
+        self.cleanup_expired();
+        let state = random_token();
+        let code_verifier = random_token();
+        let nonce = random_token();
+        let url = Url::parse_with_params(
+            &config.auth_url,
+            &[
+                ("response_type", "code"),
+                ("client_id", config.client_id.as_str()),
+                ("redirect_uri", self.config.redirect_url.as_str()),
+                ("scope", config.scopes.join(" ").as_str()),
+                ("state", state.as_str()),
+                ("code_challenge", pkce_challenge(&code_verifier).as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|e| AuthError::OAuth(e.to_string()))?;
+
+        self.pending.insert(
+            state,
+            PendingLogin {
+                provider: provider.to_string(),
+                code_verifier,
+                nonce: nonce.clone(),
+                link_user_id,
+                redirect_to,
+                created_at: Instant::now(),
+            },
+        );
+        Ok((url.into(), nonce))
+    }
+
+    /// Finish a sign-in from the provider's callback, given the nonce the
+    /// browser brought along
+    pub async fn finish(
+        &self,
+        code: &str,
+        state: &str,
+        nonce: Option<&str>,
+    ) -> Result<CompletedLogin, AuthError> {
+        let pending = self.take_pending(state, nonce)?;
+        let config = self
+            .config
+            .providers
+            .get(&pending.provider)
+            .ok_or_else(|| AuthError::UnknownProvider(pending.provider.clone()))?;
+
+        let access_token = self
+            .exchange_code(config, code, &pending.code_verifier)
+            .await?;
+        let identity = self
+            .fetch_identity(&pending.provider, config, &access_token)
+            .await?;
+        info!(
+            "OAuth sign-in through {} for {}",
+            identity.provider, identity.username
+        );
+
+        Ok(CompletedLogin {
+            identity,
+            link_user_id: pending.link_user_id,
+            redirect_to: pending.redirect_to,
+        })
+    }
+
+    /// Check a redirect target against the allowed origins
+    pub fn is_allowed_redirect(&self, target: &str) -> bool {
+        let Ok(url) = Url::parse(target) else {
+            return false;
+        };
+        let origin = url.origin().ascii_serialization();
+        self.config.allowed_origins.contains(&origin)
+    }
+
+    /// `Set-Cookie` value handing a sign-in's nonce to the browser, or
+    /// clearing it once the sign-in is over
+    pub fn nonce_cookie(&self, nonce: Option<&str>) -> String {
+        let callback = Url::parse(&self.config.redirect_url).ok();
+        let path = callback.as_ref().map_or("/", |url| url.path());
+        let secure = callback.as_ref().is_some_and(|url| url.scheme() == "https");
+        format!(
+            "{}={}; Path={}; Max-Age={}; HttpOnly; SameSite=Lax{}",
+            NONCE_COOKIE,
+            nonce.unwrap_or_default(),
+            path,
+            nonce.map_or(0, |_| STATE_TTL.as_secs()),
+            if secure { "; Secure" } else { "" }
+        )
+    }
+
+    /// Remove and return a pending sign-in, if it has not expired and the
+    /// nonce is the one handed out with it
+    fn take_pending(&self, state: &str, nonce: Option<&str>) -> Result<PendingLogin, AuthError> {
+        let (_, pending) = self
+            .pending
+            .remove(state)
+            .ok_or_else(|| AuthError::OAuth("unknown or used state".to_string()))?;
+        if nonce != Some(pending.nonce.as_str()) {
+            return Err(AuthError::OAuth(
+                "sign-in was started in another browser".to_string(),
+            ));
+        }
+        if pending.created_at.elapsed() > STATE_TTL {
+            return Err(AuthError::OAuth("sign-in took too long".to_string()));
+        }
+        Ok(pending)
+    }
+
+    fn cleanup_expired(&self) {
+        self.pending
+            .retain(|_, pending| pending.created_at.elapsed() <= STATE_TTL);
+    }
+
+    async fn exchange_code(
+        &self,
+        config: &ProviderConfig,
+        code: &str,
+        code_verifier: &str,
+    ) -> Result<String, AuthError> {
+        let response: TokenResponse = self
+            .http
+            .post(&config.token_url)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", self.config.redirect_url.as_str()),
+                ("client_id", config.client_id.as_str()),
+                ("client_secret", config.client_secret.as_str()),
+                ("code_verifier", code_verifier),
+            ])
+            .send()
+            .await
+            .map_err(|e| AuthError::OAuth(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| AuthError::OAuth(e.to_string()))?;
+
+        match response.access_token {
+            Some(token) => Ok(token),
+            None => Err(AuthError::OAuth(
+                response
+                    .error_description
+                    .or(response.error)
+                    .unwrap_or_else(|| "no access token".to_string()),
+            )),
+        }
+    }
+
+    async fn fetch_identity(
+        &self,
+        provider: &str,
+        config: &ProviderConfig,
+        access_token: &str,
+    ) -> Result<ExternalIdentity, AuthError> {
+        let response = self
+            .http
+            .get(&config.userinfo_url)
+            .bearer_auth(access_token)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .header(reqwest::header::USER_AGENT, USER_AGENT)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| AuthError::OAuth(e.to_string()))?;
+
+        match provider {
+            "github" => {
+                let user: GitHubUser = response
+                    .json()
+                    .await
+                    .map_err(|e| AuthError::OAuth(e.to_string()))?;
+                Ok(ExternalIdentity {
+                    provider: provider.to_string(),
+                    external_id: user.id.to_string(),
+                    username: user.login,
+                    display_name: user.name,
+                })
+            }
+            "google" => {
+                let user: GoogleUser = response
+                    .json()
+                    .await
+                    .map_err(|e| AuthError::OAuth(e.to_string()))?;
+                Ok(ExternalIdentity {
+                    provider: provider.to_string(),
+                    external_id: user.sub,
+                    username: user.given_name.unwrap_or_default().to_lowercase(),
+                    display_name: user.name,
+                })
+            }
+            other => Err(AuthError::UnknownProvider(other.to_string())),
+        }
+    }
+}
+
+/// PKCE S256 code challenge for a verifier
+pub fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// The sign-in nonce among the cookies of a `Cookie` header
+pub fn cookie_nonce(cookies: &str) -> Option<&str> {
+    cookies
+        .split(';')
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == NONCE_COOKIE)
+        .map(|(_, value)| value)
+}
+
+/// Random URL-safe value for states, nonces and PKCE verifiers
+fn random_token() -> String {
+    URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_service() -> OAuthService {
+        let mut providers = HashMap::new();
+        providers.insert(
+            "github".to_string(),
+            ProviderConfig::github("client-id", "client-secret"),
+        );
+        OAuthService::new(OAuthConfig {
+            providers,
+            redirect_url: "http://localhost:8080/auth/callback".to_string(),
+            allowed_origins: vec!["http://localhost:3000".to_string()],
+        })
+    }
+
+    fn query(url: &str) -> HashMap<String, String> {
+        Url::parse(url)
+            .unwrap()
+            .query_pairs()
+            .into_owned()
+            .collect()
+    }
+
+    #[test]
+    fn test_pkce_challenge() {
+        // Example from RFC 7636, appendix B
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn test_authorize_url() {
+        let oauth = test_service();
+        let (url, nonce) = oauth.start("github", None, None).unwrap();
+        assert!(url.starts_with("https://github.com/login/oauth/authorize?"));
+
+        let params = query(&url);
+        assert_eq!(params["client_id"], "client-id");
+        assert_eq!(
+            params["redirect_uri"],
+            "http://localhost:8080/auth/callback"
+        );
+        assert_eq!(params["code_challenge_method"], "S256");
+
+        let pending = oauth.take_pending(&params["state"], Some(&nonce)).unwrap();
+        assert_eq!(pending.provider, "github");
+        assert_eq!(
+            pkce_challenge(&pending.code_verifier),
+            params["code_challenge"]
+        );
+
+        assert!(matches!(
+            oauth.start("gitlab", None, None),
+            Err(AuthError::UnknownProvider(_))
+        ));
+    }
+
+    fn start(oauth: &OAuthService) -> (String, String) {
+        let (url, nonce) = oauth.start("github", None, None).unwrap();
+        (query(&url)["state"].clone(), nonce)
+    }
+
+    #[test]
+    fn test_state_single_use_and_expiry() {
+        let oauth = test_service();
+        let (state, nonce) = start(&oauth);
+        assert!(oauth.take_pending(&state, Some(&nonce)).is_ok());
+        assert!(matches!(
+            oauth.take_pending(&state, Some(&nonce)),
+            Err(AuthError::OAuth(_))
+        ));
+
+        let (state, nonce) = start(&oauth);
+        oauth.pending.get_mut(&state).unwrap().created_at -= STATE_TTL + Duration::from_secs(1);
+        assert!(matches!(
+            oauth.take_pending(&state, Some(&nonce)),
+            Err(AuthError::OAuth(_))
+        ));
+    }
+
+    #[test]
+    fn test_state_bound_to_browser() {
+        let oauth = test_service();
+        let (state, _) = start(&oauth);
+        let (_, other_nonce) = start(&oauth);
+        assert!(matches!(
+            oauth.take_pending(&state, Some(&other_nonce)),
+            Err(AuthError::OAuth(_))
+        ));
+        let (state, _) = start(&oauth);
+        assert!(matches!(
+            oauth.take_pending(&state, None),
+            Err(AuthError::OAuth(_))
+        ));
+
+        let (_, nonce) = start(&oauth);
+        assert_eq!(
+            oauth.nonce_cookie(Some(&nonce)),
+            format!(
+                "{}={}; Path=/auth/callback; Max-Age=600; HttpOnly; SameSite=Lax",
+                NONCE_COOKIE, nonce
+            )
+        );
+        let header = format!("theme=dark; {}={}", NONCE_COOKIE, nonce);
+        assert_eq!(cookie_nonce(&header), Some(nonce.as_str()));
+        assert_eq!(cookie_nonce("theme=dark"), None);
+    }
+
+    #[test]
+    fn test_redirect_allowlist() {
+        let oauth = test_service();
+        assert!(oauth.is_allowed_redirect("http://localhost:3000/login/done"));
+        assert!(!oauth.is_allowed_redirect("http://localhost:3001/"));
+        assert!(!oauth.is_allowed_redirect("https://evil.example/"));
+        assert!(!oauth.is_allowed_redirect("not a url"));
+        assert!(matches!(
+            oauth.start("github", None, Some("https://evil.example/".to_string())),
+            Err(AuthError::OAuth(_))
+        ));
+    }
+}
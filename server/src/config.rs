@@ -0,0 +1,590 @@
+//! Server configuration.
+//!
+//! Settings are layered, each source overriding the ones before it:
+//! 1. Built-in defaults
+//! 2. `collab.toml`, or the file named by `COLLAB_CONFIG`
+//! 3. The individual environment variables the server has always read
+//!    (`PORT`, `STORAGE_PATH`, `PRESENCE_IDLE_TIMEOUT_SECS`, ...)
+//! 4. `COLLAB_`-prefixed variables named after the file's keys, with `__`
+//!    between section and key (`COLLAB_LIMITS__MAX_PROJECTS=200`)
+//!
+//! Secrets for accounts, sign-in and TLS are still read from the environment
+//! by their own modules.
+
+use figment::{
+    providers::{Env, Format, Serialized, Toml},
+    Figment,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::git::GitConfig;
+use crate::notify::NotifyConfig;
+use crate::origins::{self, OriginPolicy};
+use crate::storage::StorageConfig;
+use crate::sync::{chat::ChatConfig, presence::PresenceConfig, SyncServerConfig};
+use crate::throttle::ConnectionLimits;
+use crate::voice::LiveKitConfig;
+
+/// File read when `COLLAB_CONFIG` is not set
+const DEFAULT_CONFIG_FILE: &str = "collab.toml";
+
+/// Environment variables that predate the config file, and the setting each
+/// one maps to
+const LEGACY_ENV: &[(&str, &str)] = &[
+    ("PORT", "server.port"),
+    ("STORAGE_PATH", "storage.path"),
+    ("SHARE_LINK_SECRET", "sync.share_secret"),
+    ("PRESENCE_IDLE_TIMEOUT_SECS", "presence.idle_timeout_secs"),
+    ("PRESENCE_AWAY_TIMEOUT_SECS", "presence.away_timeout_secs"),
+    (
+        "PRESENCE_CURSOR_RETENTION_SECS",
+        "presence.cursor_retention_secs",
+    ),
+    ("ALLOWED_ORIGINS", "cors.allowed_origins"),
+    ("ALLOWED_ORIGINS_FILE", "cors.allowed_origins_file"),
+    ("CORS_PERMISSIVE", "cors.permissive"),
+    ("LIVEKIT_API_KEY", "voice.api_key"),
+    ("LIVEKIT_API_SECRET", "voice.api_secret"),
+    ("LIVEKIT_URL", "voice.url"),
+];
+
+/// Errors that can occur while loading the configuration
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Could not load configuration: {0}")]
+    Load(#[from] Box<figment::Error>),
+
+    #[error("Invalid configuration:\n  - {}", .0.join("\n  - "))]
+    Invalid(Vec<String>),
+
+    #[error("Could not read {0}: {1}")]
+    Io(String, std::io::Error),
+}
+
+/// All server settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub server: ServerSettings,
+    pub storage: StorageSettings,
+    pub sync: SyncSettings,
+    pub presence: PresenceSettings,
+    pub limits: LimitSettings,
+    pub cors: CorsSettings,
+    pub voice: VoiceSettings,
+    pub chat: ChatConfig,
+    pub notifications: NotifyConfig,
+    pub git: GitConfig,
+}
+
+/// HTTP listener
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSettings {
+    /// Port to listen on
+    pub port: u16,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self { port: 5000 }
+    }
+}
+
+/// Document database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageSettings {
+    /// Path to the Sled database directory
+    pub path: String,
+    /// Whether to compress stored documents
+    pub compression: bool,
+    /// Cache size in bytes
+    pub cache_size: u64,
+    /// Flush interval in milliseconds (0 = immediate)
+    pub flush_interval_ms: u64,
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        let defaults = StorageConfig::default();
+        Self {
+            path: defaults.path,
+            compression: defaults.compression,
+            cache_size: defaults.cache_size,
+            flush_interval_ms: defaults.flush_interval_ms,
+        }
+    }
+}
+
+/// Sync server timing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SyncSettings {
+    /// Seconds between document auto-saves
+    pub save_interval_secs: u64,
+    /// Milliseconds between presence broadcasts
+    pub presence_interval_ms: u64,
+    /// Seconds between cleanups of stale data
+    pub cleanup_interval_secs: u64,
+    /// Seconds a room stays in memory after its last peer leaves
+    pub room_linger_secs: u64,
+    /// Seconds a disconnected session can be resumed
+    pub session_timeout_secs: u64,
+    /// Activity feed entries kept per project
+    pub activity_capacity: usize,
+    /// Most audit log entries kept across all projects
+    pub audit_max_entries: usize,
+    /// Days audit log entries are kept
+    pub audit_retention_days: u64,
+    /// Secret share links are signed with; random (and lost on restart) if
+    /// not set
+    pub share_secret: Option<String>,
+}
+
+impl Default for SyncSettings {
+    fn default() -> Self {
+        let defaults = SyncServerConfig::default();
+        Self {
+            save_interval_secs: defaults.save_interval.as_secs(),
+            presence_interval_ms: defaults.presence_interval.as_millis() as u64,
+            cleanup_interval_secs: defaults.cleanup_interval.as_secs(),
+            room_linger_secs: defaults.room_linger.as_secs(),
+            session_timeout_secs: defaults.session_timeout.as_secs(),
+            activity_capacity: defaults.activity_capacity,
+            audit_max_entries: defaults.audit_capacity,
+            audit_retention_days: defaults.audit_retention.as_secs() / (24 * 60 * 60),
+            share_secret: None,
+        }
+    }
+}
+
+/// Presence thresholds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PresenceSettings {
+    /// Seconds of inactivity before a peer is shown as idle
+    pub idle_timeout_secs: u64,
+    /// Seconds of inactivity before a peer is shown as away
+    pub away_timeout_secs: u64,
+    /// Seconds a disconnected peer's cursor stays visible
+    pub cursor_retention_secs: u64,
+}
+
+impl Default for PresenceSettings {
+    fn default() -> Self {
+        let defaults = PresenceConfig::default();
+        Self {
+            idle_timeout_secs: defaults.idle_timeout.as_secs(),
+            away_timeout_secs: defaults.away_timeout.as_secs(),
+            cursor_retention_secs: defaults.cursor_retention.as_secs(),
+        }
+    }
+}
+
+/// Capacity limits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitSettings {
+    /// Projects open at the same time
+    pub max_projects: usize,
+    /// Peers in one project
+    pub max_peers_per_project: usize,
+    /// Largest WebSocket message accepted, in bytes
+    pub max_message_size: usize,
+    /// Open WebSocket connections across all clients
+    pub max_connections: usize,
+    /// Open WebSocket connections from one IP
+    pub max_connections_per_ip: usize,
+    /// New WebSocket connections one IP may open per minute
+    pub connections_per_minute: u32,
+    /// Take the client IP from `X-Forwarded-For`; only enable behind a proxy
+    /// that sets it
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for LimitSettings {
+    fn default() -> Self {
+        let defaults = SyncServerConfig::default();
+        let connections = ConnectionLimits::default();
+        Self {
+            max_projects: defaults.max_projects,
+            max_peers_per_project: defaults.max_peers_per_project,
+            max_message_size: 16 * 1024 * 1024,
+            max_connections: connections.max_connections,
+            max_connections_per_ip: connections.max_connections_per_ip,
+            connections_per_minute: connections.connections_per_minute,
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+/// Origins browsers may connect from (see [`OriginPolicy`])
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsSettings {
+    /// Allowed origins; a comma-separated string is accepted too
+    #[serde(deserialize_with = "string_or_list")]
+    pub allowed_origins: Vec<String>,
+    /// File with more allowed origins, one per line
+    pub allowed_origins_file: Option<String>,
+    /// Allow every origin (development only)
+    pub permissive: bool,
+}
+
+/// LiveKit voice chat
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VoiceSettings {
+    /// LiveKit server URL
+    pub url: String,
+    /// LiveKit API key; voice chat is disabled without one
+    pub api_key: Option<String>,
+    /// LiveKit API secret
+    pub api_secret: Option<String>,
+    /// Lifetime of voice tokens in seconds
+    pub token_ttl_secs: u64,
+}
+
+impl Default for VoiceSettings {
+    fn default() -> Self {
+        Self {
+            url: "wss://localhost:7880".to_string(),
+            api_key: None,
+            api_secret: None,
+            token_ttl_secs: 6 * 60 * 60,
+        }
+    }
+}
+
+impl Settings {
+    /// Load and validate the settings from the config file and environment
+    pub fn load() -> Result<Self, ConfigError> {
+        let file = match std::env::var("COLLAB_CONFIG") {
+            Ok(path) if !path.is_empty() => Toml::file_exact(path),
+            _ => Toml::file(DEFAULT_CONFIG_FILE),
+        };
+        Self::from_figment(Figment::from(Serialized::defaults(Settings::default())).merge(file))
+    }
+
+    /// Apply the environment on top of `base` and validate the result
+    fn from_figment(base: Figment) -> Result<Self, ConfigError> {
+        let settings: Self = base
+            .merge(Env::raw().filter_map(|key| {
+                LEGACY_ENV
+                    .iter()
+                    .find(|(name, _)| key == *name)
+                    .map(|(_, path)| (*path).into())
+            }))
+            .merge(Env::prefixed("COLLAB_").ignore(&["CONFIG"]).split("__"))
+            .extract()
+            .map_err(Box::new)?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Check values that would otherwise fail later, or silently misbehave
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut errors = Vec::new();
+
+        if self.server.port == 0 {
+            errors.push("server.port must not be 0".to_string());
+        }
+        if self.storage.path.trim().is_empty() {
+            errors.push("storage.path must not be empty".to_string());
+        }
+        if self.sync.save_interval_secs == 0 {
+            errors.push("sync.save_interval_secs must be at least 1".to_string());
+        }
+        if self.sync.presence_interval_ms == 0 {
+            errors.push("sync.presence_interval_ms must be at least 1".to_string());
+        }
+        if self.sync.cleanup_interval_secs == 0 {
+            errors.push("sync.cleanup_interval_secs must be at least 1".to_string());
+        }
+        if self.sync.share_secret.as_deref() == Some("") {
+            errors.push("sync.share_secret must not be empty when set".to_string());
+        }
+        if self.presence.away_timeout_secs <= self.presence.idle_timeout_secs {
+            errors.push(format!(
+                "presence.away_timeout_secs ({}) must be greater than presence.idle_timeout_secs ({})",
+                self.presence.away_timeout_secs, self.presence.idle_timeout_secs
+            ));
+        }
+        if self.limits.max_projects == 0 {
+            errors.push("limits.max_projects must be at least 1".to_string());
+        }
+        if self.limits.max_peers_per_project == 0 {
+            errors.push("limits.max_peers_per_project must be at least 1".to_string());
+        }
+        if self.limits.max_connections == 0 {
+            errors.push("limits.max_connections must be at least 1".to_string());
+        }
+        if self.limits.max_connections_per_ip == 0 {
+            errors.push("limits.max_connections_per_ip must be at least 1".to_string());
+        }
+        if self.limits.connections_per_minute == 0 {
+            errors.push("limits.connections_per_minute must be at least 1".to_string());
+        }
+        if self.limits.max_message_size < 1024 {
+            errors.push("limits.max_message_size must be at least 1024 bytes".to_string());
+        }
+        if let Some(origin) = self
+            .cors
+            .allowed_origins
+            .iter()
+            .find(|origin| !origin.contains("://"))
+        {
+            errors.push(format!(
+                "cors.allowed_origins: '{}' is not an origin (scheme://host[:port])",
+                origin
+            ));
+        }
+        if self.chat.max_length == 0 {
+            errors.push("chat.max_length must be at least 1".to_string());
+        }
+        if self.chat.messages_per_minute == 0 {
+            errors.push("chat.messages_per_minute must be at least 1".to_string());
+        }
+        if self.notifications.timeout_secs == 0 {
+            errors.push("notifications.timeout_secs must be at least 1".to_string());
+        }
+        if let Some(smtp) = &self.notifications.smtp {
+            if smtp.from.parse::<lettre::message::Mailbox>().is_err() {
+                errors.push(format!(
+                    "notifications.smtp.from: '{}' is not an email address",
+                    smtp.from
+                ));
+            }
+            if smtp.username.is_some() != smtp.password.is_some() {
+                errors.push(
+                    "notifications.smtp.username and notifications.smtp.password must be set together"
+                        .to_string(),
+                );
+            }
+        }
+        if self.git.root.is_empty() {
+            errors.push("git.root must not be empty".to_string());
+        }
+        if self.git.author_name.is_empty() || self.git.author_email.is_empty() {
+            errors.push("git.author_name and git.author_email must not be empty".to_string());
+        }
+        if self.git.import_timeout_secs == 0 {
+            errors.push("git.import_timeout_secs must be greater than 0".to_string());
+        }
+        if self.voice.api_key.is_some() != self.voice.api_secret.is_some() {
+            errors.push("voice.api_key and voice.api_secret must be set together".to_string());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+
+    /// Storage settings
+    pub fn storage_config(&self) -> StorageConfig {
+        StorageConfig {
+            path: self.storage.path.clone(),
+            compression: self.storage.compression,
+            cache_size: self.storage.cache_size,
+            flush_interval_ms: self.storage.flush_interval_ms,
+        }
+    }
+
+    /// WebSocket connection limits
+    pub fn connection_limits(&self) -> ConnectionLimits {
+        ConnectionLimits {
+            max_connections: self.limits.max_connections,
+            max_connections_per_ip: self.limits.max_connections_per_ip,
+            connections_per_minute: self.limits.connections_per_minute,
+        }
+    }
+
+    /// Presence thresholds
+    pub fn presence_config(&self) -> PresenceConfig {
+        PresenceConfig::default()
+            .with_idle_timeout(Duration::from_secs(self.presence.idle_timeout_secs))
+            .with_away_timeout(Duration::from_secs(self.presence.away_timeout_secs))
+            .with_cursor_retention(Duration::from_secs(self.presence.cursor_retention_secs))
+    }
+
+    /// Sync server settings, including presence and limits
+    pub fn sync_server_config(&self) -> SyncServerConfig {
+        let mut config = SyncServerConfig {
+            max_projects: self.limits.max_projects,
+            max_peers_per_project: self.limits.max_peers_per_project,
+            save_interval: Duration::from_secs(self.sync.save_interval_secs),
+            presence_interval: Duration::from_millis(self.sync.presence_interval_ms),
+            cleanup_interval: Duration::from_secs(self.sync.cleanup_interval_secs),
+            room_linger: Duration::from_secs(self.sync.room_linger_secs),
+            session_timeout: Duration::from_secs(self.sync.session_timeout_secs),
+            activity_capacity: self.sync.activity_capacity,
+            audit_capacity: self.sync.audit_max_entries,
+            audit_retention: Duration::from_secs(self.sync.audit_retention_days * 24 * 60 * 60),
+            chat: self.chat.clone(),
+            ..SyncServerConfig::default()
+        }
+        .with_presence(self.presence_config());
+
+        match &self.sync.share_secret {
+            Some(secret) => config = config.with_share_secret(secret.as_bytes()),
+            None => warn!("SHARE_LINK_SECRET not set - share links will stop working on restart"),
+        }
+        config
+    }
+
+    /// Origin allowlist, merging the configured list and file.
+    ///
+    /// With neither set, every origin is allowed as before, and a warning is
+    /// logged.
+    pub fn origin_policy(&self) -> Result<OriginPolicy, ConfigError> {
+        if self.cors.permissive {
+            warn!("CORS_PERMISSIVE is set: requests from any origin are allowed");
+            return Ok(OriginPolicy::permissive());
+        }
+
+        let mut allowed = self.cors.allowed_origins.clone();
+        if let Some(path) = self
+            .cors
+            .allowed_origins_file
+            .as_ref()
+            .filter(|p| !p.is_empty())
+        {
+            let text =
+                std::fs::read_to_string(path).map_err(|e| ConfigError::Io(path.clone(), e))?;
+            allowed.extend(origins::parse_list(&text));
+        }
+
+        if allowed.is_empty() {
+            warn!("ALLOWED_ORIGINS is not set: requests from any origin are allowed");
+            return Ok(OriginPolicy::permissive());
+        }
+
+        let policy = OriginPolicy::allow(allowed);
+        info!("Allowed origins: {}", policy.allowed().join(", "));
+        Ok(policy)
+    }
+
+    /// LiveKit settings, or None if voice chat is not configured
+    pub fn livekit_config(&self) -> Option<LiveKitConfig> {
+        let (Some(key), Some(secret)) = (&self.voice.api_key, &self.voice.api_secret) else {
+            return None;
+        };
+        Some(LiveKitConfig::new(key, secret, &self.voice.url).with_ttl(self.voice.token_ttl_secs))
+    }
+}
+
+/// Accept a list, or a comma-separated string as environment variables give
+fn string_or_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrList {
+        String(String),
+        List(Vec<String>),
+    }
+
+    Ok(match StringOrList::deserialize(deserializer)? {
+        StringOrList::String(text) => origins::parse_list(&text),
+        StringOrList::List(list) => list,
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::result_large_err)] // figment::Jail's closures return figment::Error
+mod tests {
+    use super::*;
+
+    /// Settings from a TOML string, with an empty environment
+    fn load(toml: &str) -> Result<Settings, ConfigError> {
+        let mut result = None;
+        figment::Jail::expect_with(|jail| {
+            jail.clear_env();
+            result = Some(Settings::from_figment(
+                Figment::from(Serialized::defaults(Settings::default())).merge(Toml::string(toml)),
+            ));
+            Ok(())
+        });
+        result.unwrap()
+    }
+
+    #[test]
+    fn test_defaults_match_components() {
+        let settings = Settings::default();
+        settings.validate().unwrap();
+
+        let sync = settings.sync_server_config();
+        let defaults = SyncServerConfig::default();
+        assert_eq!(sync.max_projects, defaults.max_projects);
+        assert_eq!(sync.save_interval, defaults.save_interval);
+        assert_eq!(sync.presence.away_timeout, defaults.presence.away_timeout);
+        assert_eq!(
+            settings.storage_config().path,
+            StorageConfig::default().path
+        );
+        assert!(settings.livekit_config().is_none());
+    }
+
+    #[test]
+    fn test_layering() {
+        figment::Jail::expect_with(|jail| {
+            jail.clear_env();
+            jail.create_file(
+                "collab.toml",
+                r#"
+                [server]
+                port = 8080
+
+                [limits]
+                max_projects = 10
+
+                [cors]
+                allowed_origins = ["http://localhost:3000"]
+                "#,
+            )?;
+            jail.set_env("PORT", "9000");
+            jail.set_env("COLLAB_LIMITS__MAX_PROJECTS", "20");
+            jail.set_env("ALLOWED_ORIGINS", "https://a.example, https://b.example");
+            jail.set_env("LIVEKIT_API_KEY", "key");
+            jail.set_env("LIVEKIT_API_SECRET", "secret");
+
+            let settings = Settings::load().unwrap();
+            assert_eq!(settings.server.port, 9000);
+            assert_eq!(settings.limits.max_projects, 20);
+            assert_eq!(
+                settings.cors.allowed_origins,
+                vec!["https://a.example", "https://b.example"]
+            );
+            assert_eq!(settings.livekit_config().unwrap().api_key, "key");
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let error = load(
+            r#"
+            [presence]
+            idle_timeout_secs = 300
+            away_timeout_secs = 60
+
+            [voice]
+            api_key = "key"
+            "#,
+        )
+        .unwrap_err();
+        let ConfigError::Invalid(errors) = error else {
+            panic!("expected validation errors, got {}", error);
+        };
+        assert_eq!(errors.len(), 2);
+
+        assert!(matches!(
+            load("[limits]\nmax_project = 1"),
+            Err(ConfigError::Load(_))
+        ));
+    }
+}
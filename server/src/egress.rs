@@ -0,0 +1,108 @@
+//! Checks on hosts the server connects to on behalf of clients.
+//!
+//! Webhooks and git imports take URLs from whoever sets them up. Without
+//! these checks they could make the server reach into the network it runs
+//! in, so only hosts whose every address is public are accepted.
+
+use std::net::IpAddr;
+
+/// Whether an address is reachable on the public internet
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                || a >= 240
+                // Carrier-grade NAT and benchmarking ranges
+                || (a == 100 && b & 0xc0 == 64)
+                || (a == 198 && b & 0xfe == 18))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(ip));
+            }
+            let [first, second, ..] = ip.segments();
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local, link-local and documentation ranges
+                || first & 0xfe00 == 0xfc00
+                || first & 0xffc0 == 0xfe80
+                || (first == 0x2001 && second == 0x0db8))
+        }
+    }
+}
+
+/// Check that a host, given as a name or an address, only has public
+/// addresses
+pub async fn check_public_host(host: &str) -> Result<(), String> {
+    resolve_public_host(host).await.map(|_| ())
+}
+
+/// Addresses of a host, given as a name or an address, if they are all
+/// public. Connecting to these rather than resolving the host again keeps a
+/// DNS change in between from pointing the connection elsewhere.
+pub async fn resolve_public_host(host: &str) -> Result<Vec<IpAddr>, String> {
+    let addresses: Vec<IpAddr> = match host.trim_matches(['[', ']']).parse() {
+        Ok(ip) => vec![ip],
+        Err(_) => tokio::net::lookup_host((host, 0))
+            .await
+            .map_err(|e| format!("Cannot resolve '{}': {}", host, e))?
+            .map(|address| address.ip())
+            .collect(),
+    };
+    if addresses.is_empty() {
+        return Err(format!("Cannot resolve '{}'", host));
+    }
+    if addresses.iter().copied().all(is_public) {
+        Ok(addresses)
+    } else {
+        Err(format!("'{}' is not a public host", host))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_public() {
+        for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_public(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_public_host() {
+        assert!(check_public_host("127.0.0.1").await.is_err());
+        assert!(check_public_host("[::1]").await.is_err());
+        assert!(check_public_host("localhost").await.is_err());
+        assert!(check_public_host("93.184.216.34").await.is_ok());
+        assert_eq!(
+            resolve_public_host("93.184.216.34").await.unwrap(),
+            vec![IpAddr::from([93, 184, 216, 34])]
+        );
+    }
+}
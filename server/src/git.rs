@@ -0,0 +1,975 @@
+//! Export of projects to git repositories, and import of new projects from
+//! them.
+//!
+//! A project's repository is the directory named after the project under the
+//! server's git root, a new one or one already there, bare or with a working
+//! tree. Projects cannot choose another directory, so none can reach into
+//! another's repository. An export writes
+//! the project's files as the document has them into a commit on the
+//! project's branch, checks them out if the repository has a working tree
+//! and pushes the branch if the project has a remote. Folders come along
+//! through the files in them; git cannot keep empty ones. Nothing is
+//! committed when the files are the same as in the branch's last commit.
+//! Remotes must be on public hosts, checked when they are set and again
+//! before each push.
+//!
+//! Exports happen when asked for over HTTP and, for projects that opt in, on
+//! the server's schedule.
+//!
+//! An import makes a shallow clone of a remote branch into a scratch
+//! directory, reads its files with the same scanner hosted folders use and
+//! hands them over to become a new project. Symbolic links are left out, so a
+//! repository cannot pull other files of the server into its project. Only
+//! public hosts may be imported from, and a clone that grows too large or
+//! takes too long is abandoned.
+
+use collab_protocol::HostedEntry;
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{
+    Cred, ErrorCode, FetchOptions, Oid, PushOptions, RemoteCallbacks, Repository,
+    RepositoryInitOptions, Signature,
+};
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::egress;
+use crate::room::{scan_directory_tree, ScanOptions};
+use crate::storage::{DocumentStore, ProjectGit};
+use crate::sync::{SyncError, SyncServer};
+
+/// Git export settings for the whole server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GitConfig {
+    /// Directory project repositories live under
+    pub root: String,
+    /// Seconds between exports of projects that commit on a schedule; 0
+    /// turns scheduled exports off
+    pub commit_interval_secs: u64,
+    /// Author and committer of export commits
+    pub author_name: String,
+    pub author_email: String,
+    /// Most bytes an import may download
+    pub import_max_bytes: u64,
+    /// Most objects an import may download
+    pub import_max_objects: usize,
+    /// Seconds an import may take; also the longest git waits on a remote
+    pub import_timeout_secs: u64,
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            root: "./data/git".to_string(),
+            commit_interval_secs: 0,
+            author_name: "CodeCollab".to_string(),
+            author_email: "collab@localhost".to_string(),
+            import_max_bytes: 100 * 1024 * 1024,
+            import_max_objects: 50_000,
+            import_timeout_secs: 120,
+        }
+    }
+}
+
+/// Errors exporting a project
+#[derive(Error, Debug)]
+pub enum GitError {
+    #[error("Git failed: {0}")]
+    Git(#[from] git2::Error),
+
+    #[error("Invalid repository: {0}")]
+    InvalidRepository(String),
+
+    #[error("Project is not exported to git")]
+    NotConfigured,
+
+    #[error("Remote refused the push: {0}")]
+    PushRejected(String),
+
+    #[error("{0}")]
+    Sync(#[from] SyncError),
+
+    #[error("Storage error: {0}")]
+    Storage(String),
+
+    #[error("Export task failed: {0}")]
+    Task(String),
+
+    #[error("Import failed: {0}")]
+    Import(String),
+}
+
+/// Outcome of an export
+#[derive(Debug, Clone, Serialize)]
+pub struct GitCommit {
+    /// The branch's last commit
+    pub commit: String,
+    /// Whether that commit was made by this export
+    pub created: bool,
+    /// Whether the branch was pushed to the remote
+    pub pushed: bool,
+    /// Why pushing failed; the commit is kept and pushed next time
+    pub push_error: Option<String>,
+}
+
+/// How far an import has got, streamed to whoever started it
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum ImportProgress {
+    /// Objects received from the remote so far
+    Cloning {
+        received: usize,
+        total: usize,
+    },
+    /// Reading the files out of the clone
+    Scanning,
+    /// Files read and going into the new project
+    Importing {
+        files: usize,
+        folders: usize,
+        skipped: usize,
+    },
+    /// The project is ready to join
+    Done {
+        project_id: String,
+        name: String,
+        ws_url: String,
+    },
+    Failed {
+        message: String,
+    },
+}
+
+/// Files and folders read from a repository, by path from its root
+#[derive(Debug, Default)]
+pub struct ImportedFiles {
+    pub entries: Vec<HostedEntry>,
+    pub contents: Vec<(String, String)>,
+    /// Files left out for being binary, too large or not UTF-8
+    pub skipped: Vec<String>,
+}
+
+/// Root name the scanner gives a clone; stripped from the paths it returns
+const IMPORT_ROOT: &str = "import";
+
+/// A file or folder of the tree being committed
+enum TreeEntry {
+    File(Oid),
+    Folder(BTreeMap<String, TreeEntry>),
+}
+
+/// Commits projects to their git repositories
+pub struct GitBridge {
+    storage: Arc<DocumentStore>,
+    config: GitConfig,
+}
+
+impl GitBridge {
+    pub fn new(storage: Arc<DocumentStore>, config: GitConfig) -> Self {
+        Self { storage, config }
+    }
+
+    /// Directory of a project's repository. IDs that are not a single plain
+    /// path component are refused, so no repository sits inside another.
+    pub fn repo_path(&self, project_id: &str) -> Result<PathBuf, GitError> {
+        let plain = !project_id.is_empty()
+            && !project_id.starts_with('.')
+            && !project_id.contains(['/', '\\']);
+        if plain {
+            Ok(Path::new(&self.config.root).join(project_id))
+        } else {
+            Err(GitError::InvalidRepository(format!(
+                "'{}' cannot name a repository",
+                project_id
+            )))
+        }
+    }
+
+    /// Create a project's repository, or check the one already there.
+    /// Returns the branch's last commit, if it has one.
+    pub fn attach(&self, settings: &ProjectGit) -> Result<Option<String>, GitError> {
+        let repo = self.open_or_init(settings)?;
+        Ok(branch_commit(&repo, &settings.branch).map(|commit| commit.id().to_string()))
+    }
+
+    /// The branch's last commit, if the repository and the commit exist
+    pub fn head(&self, settings: &ProjectGit) -> Result<Option<String>, GitError> {
+        let Ok(repo) = Repository::open(self.repo_path(&settings.project_id)?) else {
+            return Ok(None);
+        };
+        Ok(branch_commit(&repo, &settings.branch).map(|commit| commit.id().to_string()))
+    }
+
+    /// Commit files to the project's branch, unless they are the same as in
+    /// its last commit. Blocks on disk access.
+    pub fn commit(
+        &self,
+        settings: &ProjectGit,
+        files: &[(String, String)],
+        message: &str,
+    ) -> Result<GitCommit, GitError> {
+        let repo = self.open_or_init(settings)?;
+        let tree = repo.find_tree(write_tree(&repo, files)?)?;
+        let parent = branch_commit(&repo, &settings.branch);
+        if let Some(parent) = parent
+            .as_ref()
+            .filter(|parent| parent.tree_id() == tree.id())
+        {
+            return Ok(GitCommit {
+                commit: parent.id().to_string(),
+                created: false,
+                pushed: false,
+                push_error: None,
+            });
+        }
+
+        let signature = Signature::now(&self.config.author_name, &self.config.author_email)?;
+        let reference = branch_ref(&settings.branch);
+        let parents: Vec<_> = parent.iter().collect();
+        let commit = repo.commit(
+            Some(&reference),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )?;
+
+        // Bring the working tree along if the branch is checked out
+        let head = repo.find_reference("HEAD")?;
+        if !repo.is_bare() && head.symbolic_target() == Some(reference.as_str()) {
+            repo.checkout_head(Some(CheckoutBuilder::new().force()))?;
+        }
+
+        Ok(GitCommit {
+            commit: commit.to_string(),
+            created: true,
+            pushed: false,
+            push_error: None,
+        })
+    }
+
+    /// Push the project's branch to its remote. Blocks on the network.
+    pub fn push(&self, settings: &ProjectGit) -> Result<(), GitError> {
+        let Some(remote) = &settings.remote else {
+            return Err(GitError::InvalidRepository(
+                "Project has no remote to push to".to_string(),
+            ));
+        };
+        let repo = self.open_or_init(settings)?;
+
+        // libgit2 asks again after rejected credentials; once is enough
+        let asked = Cell::new(false);
+        let rejected = RefCell::new(None);
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(|_, username, _| {
+            if asked.replace(true) {
+                return Err(git2::Error::from_str("Remote rejected the credentials"));
+            }
+            match &remote.token {
+                Some(token) => Cred::userpass_plaintext(
+                    remote.username.as_deref().or(username).unwrap_or("git"),
+                    token,
+                ),
+                None => Cred::default(),
+            }
+        });
+        callbacks.push_update_reference(|reference, status| {
+            if let Some(status) = status {
+                *rejected.borrow_mut() = Some(format!("{}: {}", reference, status));
+            }
+            Ok(())
+        });
+        let mut options = PushOptions::new();
+        options.remote_callbacks(callbacks);
+
+        let reference = branch_ref(&settings.branch);
+        repo.remote_anonymous(&remote.url)?.push(
+            &[format!("{}:{}", reference, reference)],
+            Some(&mut options),
+        )?;
+        drop(options);
+
+        match rejected.into_inner() {
+            Some(reason) => Err(GitError::PushRejected(reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Commit a project's current files and push them if it has a remote
+    pub async fn export(
+        self: &Arc<Self>,
+        sync_server: &SyncServer,
+        project_id: &str,
+        message: Option<String>,
+    ) -> Result<GitCommit, GitError> {
+        let settings = self
+            .storage
+            .get_git(project_id)
+            .map_err(|e| GitError::Storage(e.to_string()))?
+            .ok_or(GitError::NotConfigured)?;
+        let files = sync_server.project_files(project_id)?;
+        let message = message.unwrap_or_else(|| format!("Snapshot of {} files", files.len()));
+
+        // The remote's host may resolve elsewhere since it was set
+        let remote_check = match &settings.remote {
+            Some(remote) => Some(validate_public_remote(&remote.url).await),
+            None => None,
+        };
+
+        let bridge = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut commit = bridge.commit(&settings, &files, &message)?;
+            match remote_check {
+                Some(Ok(())) => match bridge.push(&settings) {
+                    Ok(()) => commit.pushed = true,
+                    Err(e) => commit.push_error = Some(e.to_string()),
+                },
+                Some(Err(e)) => commit.push_error = Some(e),
+                None => {}
+            }
+            Ok(commit)
+        })
+        .await
+        .map_err(|e| GitError::Task(e.to_string()))?
+    }
+
+    /// Export the projects that commit on a schedule, until the server
+    /// shuts down
+    pub async fn run(self: Arc<Self>, sync_server: Arc<SyncServer>) {
+        if self.config.commit_interval_secs == 0 {
+            return;
+        }
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(self.config.commit_interval_secs));
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let projects = match self.storage.list_git() {
+                Ok(projects) => projects,
+                Err(e) => {
+                    warn!("Failed to list projects exported to git: {}", e);
+                    continue;
+                }
+            };
+            for settings in projects.iter().filter(|settings| settings.auto_commit) {
+                match self.export(&sync_server, &settings.project_id, None).await {
+                    Ok(commit) => {
+                        if commit.created {
+                            info!("Committed {} as {}", settings.project_id, commit.commit);
+                        }
+                        if let Some(e) = commit.push_error {
+                            warn!("Failed to push {}: {}", settings.project_id, e);
+                        }
+                    }
+                    Err(e) => warn!("Failed to export {}: {}", settings.project_id, e),
+                }
+            }
+        }
+    }
+
+    /// Clone a branch of a repository, the remote's default one if none is
+    /// given, and read its files. The clone is removed afterwards. Callers
+    /// check the URL with [`validate_public_remote`] first.
+    pub async fn import(
+        &self,
+        url: String,
+        branch: Option<String>,
+        options: ScanOptions,
+        progress: mpsc::UnboundedSender<ImportProgress>,
+    ) -> Result<ImportedFiles, GitError> {
+        let timeout = Duration::from_secs(self.config.import_timeout_secs);
+        let deadline = Instant::now() + timeout;
+        let config = self.config.clone();
+        let task = tokio::task::spawn_blocking(move || {
+            let dir = std::env::temp_dir().join(format!("collab-import-{}", uuid::Uuid::new_v4()));
+            let result = clone_shallow(
+                &url,
+                branch.as_deref(),
+                &dir,
+                &config,
+                deadline,
+                |received, total| {
+                    let _ = progress.send(ImportProgress::Cloning { received, total });
+                },
+            )
+            .and_then(|()| {
+                let _ = progress.send(ImportProgress::Scanning);
+                read_clone(&dir, &options)
+            });
+
+            // Removed here, so a clone outliving the timeout is still cleaned up
+            if let Err(e) = std::fs::remove_dir_all(&dir) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    warn!("Failed to remove clone at {}: {}", dir.display(), e);
+                }
+            }
+            let files = result?;
+            let _ = progress.send(ImportProgress::Importing {
+                files: files.contents.len(),
+                folders: files.entries.iter().filter(|entry| entry.is_dir).count(),
+                skipped: files.skipped.len(),
+            });
+            Ok(files)
+        });
+
+        match tokio::time::timeout(timeout, task).await {
+            Ok(files) => files.map_err(|e| GitError::Task(e.to_string()))?,
+            Err(_) => Err(GitError::Import(format!(
+                "Import took longer than {} seconds",
+                self.config.import_timeout_secs
+            ))),
+        }
+    }
+
+    fn open_or_init(&self, settings: &ProjectGit) -> Result<Repository, GitError> {
+        let path = self.repo_path(&settings.project_id)?;
+        match Repository::open(&path) {
+            Ok(repo) if repo.is_bare() != settings.bare => {
+                Err(GitError::InvalidRepository(format!(
+                    "{} is {} bare repository",
+                    path.display(),
+                    if repo.is_bare() { "a" } else { "not a" }
+                )))
+            }
+            Ok(repo) => Ok(repo),
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(Repository::init_opts(
+                &path,
+                RepositoryInitOptions::new()
+                    .bare(settings.bare)
+                    .initial_head(&settings.branch)
+                    .mkpath(true),
+            )?),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Make git give up on remotes that stop answering. Call once at startup,
+/// before any repository is touched.
+pub fn set_network_timeouts(config: &GitConfig) -> Result<(), GitError> {
+    let millis = i32::try_from(config.import_timeout_secs.saturating_mul(1000)).unwrap_or(i32::MAX);
+    // SAFETY: libgit2 reads these settings without locking; nothing else
+    // uses git while the server starts up
+    unsafe {
+        git2::opts::set_server_connect_timeout_in_milliseconds(millis)?;
+        git2::opts::set_server_timeout_in_milliseconds(millis)?;
+    }
+    Ok(())
+}
+
+/// Check a repository before importing from or pushing to it: a network
+/// remote on a public host
+pub async fn validate_public_remote(url: &str) -> Result<(), String> {
+    validate_remote(url)?;
+    let host = remote_host(url).ok_or_else(|| format!("'{}' has no host", url))?;
+    egress::check_public_host(&host).await
+}
+
+/// Host of a network remote, in URL or scp-like form
+fn remote_host(url: &str) -> Option<String> {
+    if url.contains("://") {
+        let url = reqwest::Url::parse(url).ok()?;
+        return url.host_str().map(str::to_string);
+    }
+    let (host, _) = url.split_once(':')?;
+    host.rsplit('@').next().map(str::to_string)
+}
+
+/// Name for a project imported from a repository: the last part of its URL
+pub fn repository_name(url: &str) -> String {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(".git");
+    if name.is_empty() {
+        "Imported project".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Clone only the last commit of a branch, reporting objects received and
+/// the total expected. The transfer is abandoned once it passes the
+/// configured size or the deadline. Blocks on the network.
+fn clone_shallow(
+    url: &str,
+    branch: Option<&str>,
+    into: &Path,
+    config: &GitConfig,
+    deadline: Instant,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<(), GitError> {
+    let mut reported = None;
+    let abandoned = Cell::new(None);
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.transfer_progress(|stats| {
+        let (received, total) = (stats.received_objects(), stats.total_objects());
+        let reason = if stats.received_bytes() as u64 > config.import_max_bytes {
+            Some(format!(
+                "Repository is larger than {} bytes",
+                config.import_max_bytes
+            ))
+        } else if total > config.import_max_objects {
+            Some(format!(
+                "Repository has more than {} objects",
+                config.import_max_objects
+            ))
+        } else if Instant::now() > deadline {
+            Some(format!(
+                "Clone took longer than {} seconds",
+                config.import_timeout_secs
+            ))
+        } else {
+            None
+        };
+        if reason.is_some() {
+            abandoned.set(reason);
+            return false;
+        }
+
+        // Called for every chunk; report every 64 objects and the last one
+        if reported != Some(received) && (received % 64 == 0 || received == total) {
+            reported = Some(received);
+            progress(received, total);
+        }
+        true
+    });
+    let mut fetch = FetchOptions::new();
+    fetch.remote_callbacks(callbacks).depth(1);
+
+    let mut builder = RepoBuilder::new();
+    builder.fetch_options(fetch);
+    if let Some(branch) = branch {
+        builder.branch(branch);
+    }
+    let cloned = builder.clone(url, into);
+    drop(builder);
+    match (cloned, abandoned.into_inner()) {
+        (_, Some(reason)) => Err(GitError::Import(reason)),
+        (Err(e), None) => Err(e.into()),
+        (Ok(_), None) => Ok(()),
+    }
+}
+
+/// Read the files of a clone, without its history or symbolic links
+fn read_clone(dir: &Path, options: &ScanOptions) -> Result<ImportedFiles, GitError> {
+    let import_error = |e: std::io::Error| GitError::Import(e.to_string());
+    std::fs::remove_dir_all(dir.join(".git")).map_err(import_error)?;
+    remove_links(dir).map_err(import_error)?;
+
+    let (tree, scan) = scan_directory_tree(dir, IMPORT_ROOT, options)
+        .map_err(|e| GitError::Import(e.to_string()))?;
+    let prefix = format!("{}/", IMPORT_ROOT);
+    let relative = |path: &str| path.strip_prefix(&prefix).map(str::to_string);
+
+    let mut files = ImportedFiles {
+        skipped: scan
+            .skipped_files
+            .iter()
+            .filter_map(|path| relative(path))
+            .collect(),
+        ..Default::default()
+    };
+    for node in tree.all_nodes() {
+        // The root has no prefix and becomes the project itself
+        let Some(path) = relative(&node.path) else {
+            continue;
+        };
+        if node.is_directory() {
+            files.entries.push(HostedEntry {
+                path,
+                is_dir: true,
+                size: 0,
+            });
+            continue;
+        }
+        let content = std::fs::read(dir.join(&path))
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok());
+        match content {
+            Some(content) => {
+                files.entries.push(HostedEntry {
+                    path: path.clone(),
+                    is_dir: false,
+                    size: content.len() as u64,
+                });
+                files.contents.push((path, content));
+            }
+            None => files.skipped.push(path),
+        }
+    }
+    Ok(files)
+}
+
+/// Delete the symbolic links under a directory
+fn remove_links(dir: &Path) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            std::fs::remove_file(entry.path())?;
+        } else if file_type.is_dir() {
+            remove_links(&entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Check a remote before a project saves it. Only network remotes are
+/// allowed, so a project cannot push into other directories of the server.
+pub fn validate_remote(url: &str) -> Result<(), String> {
+    let network = ["https://", "http://", "ssh://", "git://"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme));
+    // scp-like syntax: user@host:path
+    let scp = url
+        .split_once(':')
+        .is_some_and(|(host, _)| host.contains('@') && !host.contains('/'));
+    if network || scp {
+        Ok(())
+    } else {
+        Err(format!("'{}' is not a network remote", url))
+    }
+}
+
+/// Check a branch name before a project saves it
+pub fn validate_branch(branch: &str) -> Result<(), String> {
+    if git2::Reference::is_valid_name(&branch_ref(branch)) {
+        Ok(())
+    } else {
+        Err(format!("'{}' is not a valid branch name", branch))
+    }
+}
+
+fn branch_ref(branch: &str) -> String {
+    format!("refs/heads/{}", branch)
+}
+
+fn branch_commit<'r>(repo: &'r Repository, branch: &str) -> Option<git2::Commit<'r>> {
+    repo.find_reference(&branch_ref(branch))
+        .ok()?
+        .peel_to_commit()
+        .ok()
+}
+
+/// Write the files as blobs and trees, returning the root tree
+fn write_tree(repo: &Repository, files: &[(String, String)]) -> Result<Oid, GitError> {
+    let mut root = BTreeMap::new();
+    'files: for (path, content) in files {
+        let (folders, name) = path.rsplit_once('/').unwrap_or(("", path));
+        let mut entries = &mut root;
+        for folder in folders.split('/').filter(|folder| !folder.is_empty()) {
+            let entry = entries
+                .entry(folder.to_string())
+                .or_insert_with(|| TreeEntry::Folder(BTreeMap::new()));
+            // A file and a folder at one path; the file committed first wins
+            let TreeEntry::Folder(children) = entry else {
+                continue 'files;
+            };
+            entries = children;
+        }
+        let blob = repo.blob(content.as_bytes())?;
+        entries
+            .entry(name.to_string())
+            .or_insert(TreeEntry::File(blob));
+    }
+    write_folder(repo, &root)
+}
+
+fn write_folder(repo: &Repository, entries: &BTreeMap<String, TreeEntry>) -> Result<Oid, GitError> {
+    let mut builder = repo.treebuilder(None)?;
+    for (name, entry) in entries {
+        match entry {
+            TreeEntry::File(blob) => builder.insert(name, *blob, 0o100644)?,
+            TreeEntry::Folder(children) => {
+                builder.insert(name, write_folder(repo, children)?, 0o040000)?
+            }
+        };
+    }
+    Ok(builder.write()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{GitRemote, StorageConfig};
+
+    fn test_bridge() -> (Arc<GitBridge>, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = DocumentStore::open(StorageConfig::new(
+            dir.path().join("test.sled").to_string_lossy().to_string(),
+        ))
+        .unwrap();
+        let config = GitConfig {
+            root: dir.path().join("git").to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        (Arc::new(GitBridge::new(Arc::new(storage), config)), dir)
+    }
+
+    fn settings(bare: bool) -> ProjectGit {
+        ProjectGit {
+            project_id: "proj".to_string(),
+            bare,
+            branch: "main".to_string(),
+            remote: None,
+            auto_commit: false,
+        }
+    }
+
+    fn files(entries: &[(&str, &str)]) -> Vec<(String, String)> {
+        entries
+            .iter()
+            .map(|(path, content)| (path.to_string(), content.to_string()))
+            .collect()
+    }
+
+    /// Path and content of every file in a commit
+    fn committed(repo: &Repository, commit: &str) -> Vec<(String, String)> {
+        let tree = repo
+            .find_commit(Oid::from_str(commit).unwrap())
+            .unwrap()
+            .tree()
+            .unwrap();
+        let mut files = Vec::new();
+        tree.walk(git2::TreeWalkMode::PreOrder, |folder, entry| {
+            if let Ok(blob) = entry
+                .to_object(repo)
+                .and_then(|object| object.peel_to_blob())
+            {
+                let content = String::from_utf8(blob.content().to_vec()).unwrap();
+                files.push((format!("{}{}", folder, entry.name().unwrap()), content));
+            }
+            git2::TreeWalkResult::Ok
+        })
+        .unwrap();
+        files
+    }
+
+    #[test]
+    fn test_commit_to_bare_repository() {
+        let (bridge, _dir) = test_bridge();
+        let settings = settings(true);
+        assert_eq!(bridge.attach(&settings).unwrap(), None);
+
+        let snapshot = files(&[("README.md", "# Demo"), ("src/main.rs", "fn main() {}")]);
+        let first = bridge.commit(&settings, &snapshot, "First").unwrap();
+        assert!(first.created);
+        assert_eq!(bridge.head(&settings).unwrap(), Some(first.commit.clone()));
+
+        let repo = Repository::open(bridge.repo_path(&settings.project_id).unwrap()).unwrap();
+        assert!(repo.is_bare());
+        assert_eq!(committed(&repo, &first.commit), snapshot);
+
+        // The same files make no new commit
+        let again = bridge.commit(&settings, &snapshot, "Again").unwrap();
+        assert!(!again.created);
+        assert_eq!(again.commit, first.commit);
+
+        let second = bridge
+            .commit(
+                &settings,
+                &files(&[("src/main.rs", "fn main() {}")]),
+                "Second",
+            )
+            .unwrap();
+        assert!(second.created);
+        let commit = repo
+            .find_commit(Oid::from_str(&second.commit).unwrap())
+            .unwrap();
+        assert_eq!(commit.message(), Some("Second"));
+        assert_eq!(commit.parent_id(0).unwrap().to_string(), first.commit);
+    }
+
+    #[test]
+    fn test_commit_checks_out_working_tree() {
+        let (bridge, _dir) = test_bridge();
+        let settings = settings(false);
+        let path = bridge.repo_path(&settings.project_id).unwrap();
+
+        bridge
+            .commit(
+                &settings,
+                &files(&[("a.txt", "a"), ("docs/b.md", "b")]),
+                "First",
+            )
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(path.join("docs/b.md")).unwrap(),
+            "b"
+        );
+
+        bridge
+            .commit(&settings, &files(&[("a.txt", "changed")]), "Second")
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(path.join("a.txt")).unwrap(),
+            "changed"
+        );
+        assert!(!path.join("docs/b.md").exists());
+
+        // Attaching it as a bare repository is refused
+        assert!(matches!(
+            bridge.attach(&ProjectGit {
+                bare: true,
+                ..settings
+            }),
+            Err(GitError::InvalidRepository(_))
+        ));
+    }
+
+    #[test]
+    fn test_repositories_stay_under_root() {
+        let (bridge, _dir) = test_bridge();
+        assert!(bridge.repo_path("../elsewhere").is_err());
+        assert!(bridge.repo_path("/").is_err());
+        assert!(bridge.repo_path("..").is_err());
+        assert!(bridge.repo_path("").is_err());
+        // Nothing nests inside another project's repository
+        assert!(bridge.repo_path("proj/.git").is_err());
+        assert!(bridge.repo_path("proj\\sub").is_err());
+        assert!(bridge.repo_path("proj").unwrap().ends_with("git/proj"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_settings() {
+        assert!(validate_remote("https://github.com/team/proj.git").is_ok());
+        assert!(validate_remote("ssh://git@example.com/proj.git").is_ok());
+        assert!(validate_remote("git@github.com:team/proj.git").is_ok());
+        assert!(validate_remote("/srv/other/repo.git").is_err());
+        assert!(validate_remote("file:///srv/other/repo.git").is_err());
+
+        // Remotes are pushed to from the server, so only public hosts
+        for url in [
+            "http://169.254.169.254/latest/meta-data",
+            "http://10.1.2.3/team/proj.git",
+            "ssh://localhost/srv/proj.git",
+            "git@192.168.1.10:team/proj.git",
+        ] {
+            assert!(validate_public_remote(url).await.is_err(), "{}", url);
+        }
+        assert!(validate_public_remote("ssh://git@93.184.216.34/team/proj.git")
+            .await
+            .is_ok());
+
+        assert!(validate_branch("main").is_ok());
+        assert!(validate_branch("release/1.0").is_ok());
+        assert!(validate_branch("bad..name").is_err());
+        assert!(validate_branch("").is_err());
+    }
+
+    #[test]
+    fn test_push_to_remote() {
+        let (bridge, dir) = test_bridge();
+        let remote_path = dir.path().join("remote.git");
+        let remote = Repository::init_bare(&remote_path).unwrap();
+
+        let settings = ProjectGit {
+            remote: Some(GitRemote {
+                url: remote_path.to_string_lossy().to_string(),
+                username: None,
+                token: None,
+            }),
+            ..settings(true)
+        };
+        let commit = bridge
+            .commit(&settings, &files(&[("main.rs", "")]), "First")
+            .unwrap();
+        bridge.push(&settings).unwrap();
+
+        let pushed = remote.find_reference("refs/heads/main").unwrap();
+        assert_eq!(pushed.target().unwrap().to_string(), commit.commit);
+    }
+
+    #[test]
+    fn test_import_reads_files_without_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        let repo = Repository::init(&source).unwrap();
+        std::fs::create_dir_all(source.join("src/util")).unwrap();
+        std::fs::write(source.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(source.join("src/util/mod.rs"), "").unwrap();
+        std::fs::write(source.join("logo.png"), [0x89, 0x50]).unwrap();
+        std::fs::write(source.join("data.txt"), [0xff, 0xfe]).unwrap();
+        std::os::unix::fs::symlink("/etc/hostname", source.join("hostname")).unwrap();
+
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@localhost").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "First", &tree, &[])
+            .unwrap();
+
+        // The local transport cannot clone shallow, so read the checkout
+        let mut files = read_clone(&source, &ScanOptions::default()).unwrap();
+        assert!(!source.join(".git").exists());
+        assert!(!source.join("hostname").exists());
+
+        files.contents.sort();
+        assert_eq!(
+            files.contents,
+            self::files(&[("src/main.rs", "fn main() {}"), ("src/util/mod.rs", "")])
+        );
+        let mut folders: Vec<_> = files
+            .entries
+            .iter()
+            .filter(|entry| entry.is_dir)
+            .map(|entry| entry.path.as_str())
+            .collect();
+        folders.sort();
+        assert_eq!(folders, ["src", "src/util"]);
+        files.skipped.sort();
+        assert_eq!(files.skipped, ["data.txt", "logo.png"]);
+    }
+
+    #[tokio::test]
+    async fn test_validate_import() {
+        assert_eq!(
+            remote_host("https://user@example.com:8443/team/app.git").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            remote_host("git@example.com:team/app").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            remote_host("ssh://git@[::1]/app.git").as_deref(),
+            Some("[::1]")
+        );
+
+        // Nothing inside the server's own network
+        for url in [
+            "https://127.0.0.1/app.git",
+            "http://localhost:3000/app.git",
+            "git://10.0.0.5/app.git",
+            "ssh://git@[::1]/app.git",
+            "git@169.254.169.254:app.git",
+            "/srv/repos/app.git",
+        ] {
+            assert!(validate_public_remote(url).await.is_err(), "{}", url);
+        }
+        assert!(validate_public_remote("https://93.184.216.34/app.git")
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn test_repository_name() {
+        assert_eq!(repository_name("https://example.com/team/app.git"), "app");
+        assert_eq!(repository_name("git@example.com:team/app"), "app");
+        assert_eq!(repository_name("git@example.com:app.git/"), "app");
+        assert_eq!(repository_name("https://example.com/"), "example.com");
+    }
+}
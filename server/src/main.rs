@@ -8,36 +8,70 @@
 
 use axum::{
     extract::{
-        ws::{Message, WebSocket, WebSocketUpgrade},
-        Path, State,
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, Host, Path, Query, State,
     },
-    http::{Method, StatusCode},
-    response::IntoResponse,
-    routing::get,
-    Json, Router,
+    http::{header, HeaderMap, StatusCode},
+    middleware,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Redirect, Response,
+    },
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use collab_protocol::{
+    ActivityEntry, ClientMessage, ErrorCode, PeerInfo, PresenceStatus, ServerMessage,
+    SyncProtocol, PROTOCOL_VERSION,
 };
-use futures_util::{SinkExt, StreamExt};
+use futures_util::{SinkExt, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::{net::SocketAddr, sync::Arc};
-use tokio::sync::mpsc;
-use tower_http::cors::{Any, CorsLayer};
-use tracing::{debug, error, info, warn};
-
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+use tracing::{debug, error, info, warn, Instrument};
+
+mod auth;
+mod config;
+mod egress;
+mod git;
+mod notify;
+mod origins;
 mod room;
 mod storage;
 mod sync;
+mod telemetry;
+mod throttle;
+mod tls;
 mod voice;
 
-use room::RoomManager;
-use storage::{DocumentMetadata, DocumentStore, StorageConfig};
+use auth::{oauth::OAuthConfig, AuthConfig, AuthService, AuthUser, OAuthService};
+use git::{GitBridge, GitCommit, GitError, ImportProgress};
+use notify::{Notifier, NotifyConfig, ProjectEvent};
+use room::{NestedNode, RoomManager, ScanOptions};
+use storage::{
+    AuditRecord, DocumentMetadata, DocumentStore, GitRemote, NotificationSink, ProjectAcl,
+    ProjectEventKind, ProjectGit, ProjectNotifications, ProjectRole, ShareLink, ShareRole,
+};
 use sync::{
-    presence::generate_peer_color,
-    protocol::{
-        ClientMessage, ErrorCode, PeerInfo, PresenceStatus, ServerMessage,
-        SyncProtocol, PROTOCOL_VERSION,
-    }, SyncServer, SyncServerConfig,
+    presence::{
+            is_valid_reaction, peer_color, ANONYMOUS_NAME, REACTION_TTL,
+        },
+    files::{if_none_match, normalize_path, FileDelete, FileWrite},
+    lanes::PeerSender,
+    sharing::DEFAULT_LINK_TTL,
+    SyncError, SyncServer,
 };
-use voice::{LiveKitConfig, LiveKitService, VoicePermissions};
+use throttle::{ConnectionLimiter, ConnectionPermit, LimitExceeded};
+use voice::{LiveKitService, VoicePermissions};
+
+/// Number of activity entries returned when the client doesn't ask for a limit
+const DEFAULT_ACTIVITY_LIMIT: usize = 50;
+
+/// Number of audit log entries returned when the admin doesn't ask for a limit
+const DEFAULT_AUDIT_LIMIT: usize = 100;
 
 // ============================================================================
 // APPLICATION STATE
@@ -51,23 +85,89 @@ pub struct AppState {
     room_manager: Arc<RoomManager>,
     /// Voice chat service
     voice_service: Arc<LiveKitService>,
+    /// User accounts
+    auth: Arc<AuthService>,
+    /// GitHub/Google sign-in
+    oauth: Arc<OAuthService>,
+    /// Webhook, Slack and email notifications about project events
+    notifier: Arc<Notifier>,
+    /// Export of projects to git repositories
+    git: Arc<GitBridge>,
+    /// Token for the admin API, which is disabled without one
+    admin_token: Option<String>,
+    /// Renders `/metrics`; None if the recorder could not be installed
+    metrics: Option<metrics_exporter_prometheus::PrometheusHandle>,
+    /// Whether clients connect over TLS, so advertised URLs use `wss://`
+    secure: bool,
+    /// Largest WebSocket message accepted, in bytes
+    max_message_size: usize,
+    /// Open WebSocket connections, per client IP
+    connections: Arc<ConnectionLimiter>,
+    /// Whether the client IP is taken from `X-Forwarded-For`
+    trust_forwarded_for: bool,
     /// Server start time
     started_at: std::time::Instant,
 }
 
 impl AppState {
-    pub async fn new(storage: DocumentStore) -> Self {
-        let config = SyncServerConfig::default();
-        let sync_server = Arc::new(SyncServer::new(storage, config));
+    pub async fn new(storage: DocumentStore, settings: &config::Settings, secure: bool) -> Self {
+        let sync_server = Arc::new(SyncServer::new(storage, settings.sync_server_config()));
         let room_manager = Arc::new(RoomManager::new());
 
-        // Try to configure voice service from environment
-        let voice_service = match LiveKitConfig::from_env() {
-            Ok(config) => {
-                info!("LiveKit configured from environment");
+        let auth_config = AuthConfig::from_env().unwrap_or_else(|| {
+            warn!("JWT_SECRET not set - sign-ins will not survive a restart");
+            AuthConfig::new(rand::random::<[u8; 32]>().to_vec())
+        });
+        let auth = Arc::new(AuthService::new(sync_server.storage().clone(), auth_config));
+
+        let oauth = Arc::new(OAuthService::new(OAuthConfig::from_env()));
+        if oauth.providers().is_empty() {
+            info!("No OAuth providers configured - sign-in with GitHub/Google disabled");
+        } else {
+            info!("OAuth providers: {}", oauth.providers().join(", "));
+        }
+
+        if let Err(e) = git::set_network_timeouts(&settings.git) {
+            warn!("Failed to set git network timeouts: {}", e);
+        }
+        let git = Arc::new(GitBridge::new(
+            sync_server.storage().clone(),
+            settings.git.clone(),
+        ));
+
+        let storage = sync_server.storage().clone();
+        let notifier =
+            Notifier::new(storage.clone(), &settings.notifications).unwrap_or_else(|e| {
+                warn!("Notifications disabled: {}", e);
+                Notifier::new(storage, &NotifyConfig::default())
+                    .expect("Failed to create notification client")
+            });
+        if notifier.email_enabled() {
+            info!("SMTP configured - email notifications enabled");
+        }
+
+        let admin_token = std::env::var("ADMIN_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty());
+        if admin_token.is_none() {
+            info!("ADMIN_TOKEN not set - admin API disabled");
+        }
+
+        let metrics = match telemetry::install() {
+            Ok(handle) => Some(handle),
+            Err(e) => {
+                warn!("Metrics disabled: {}", e);
+                None
+            }
+        };
+
+        // Try to configure voice service from the settings
+        let voice_service = match settings.livekit_config() {
+            Some(config) => {
+                info!("LiveKit configured");
                 Arc::new(LiveKitService::new(config).unwrap_or_else(|_| LiveKitService::unconfigured()))
             }
-            Err(_) => {
+            None => {
                 warn!("LiveKit not configured - voice chat will be disabled");
                 Arc::new(LiveKitService::unconfigured())
             }
@@ -77,6 +177,16 @@ impl AppState {
             sync_server,
             room_manager,
             voice_service,
+            auth,
+            oauth,
+            notifier: Arc::new(notifier),
+            git,
+            admin_token,
+            metrics,
+            secure,
+            max_message_size: settings.limits.max_message_size,
+            connections: Arc::new(ConnectionLimiter::new(settings.connection_limits())),
+            trust_forwarded_for: settings.limits.trust_forwarded_for,
             started_at: std::time::Instant::now(),
         }
     }
@@ -101,6 +211,31 @@ struct CreateProjectRequest {
     name: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct ForkProjectRequest {
+    /// Name of the copy; the original's with " (fork)" if not given
+    name: Option<String>,
+    /// Keep the original's change history instead of starting afresh
+    #[serde(default)]
+    preserve_history: bool,
+    /// Copy the chat too
+    #[serde(default)]
+    include_chat: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImportGitRequest {
+    /// Repository to clone; only network remotes are accepted
+    url: String,
+    /// Branch to import; the remote's default branch if not given
+    branch: Option<String>,
+    /// Name of the project; the repository's if not given
+    name: Option<String>,
+    /// Which files to take from the repository
+    #[serde(default)]
+    scan: ScanOptions,
+}
+
 #[derive(Debug, Serialize)]
 struct CreateProjectResponse {
     project_id: String,
@@ -115,23 +250,246 @@ struct ProjectInfo {
     peer_count: usize,
     has_host: bool,
     created_at: i64,
+    updated_at: i64,
+    owner_id: Option<String>,
+    archived: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProjectListQuery {
+    /// Only projects owned by the signed-in user
+    #[serde(default)]
+    mine: bool,
+    /// Only projects owned by this user
+    owner: Option<String>,
+    /// List archived projects instead of active ones
+    #[serde(default)]
+    archived: bool,
+    /// Only projects whose name contains this, ignoring case
+    q: Option<String>,
+    #[serde(default)]
+    sort: ProjectSort,
+    /// Page number, from 1
+    page: Option<usize>,
+    per_page: Option<usize>,
+}
+
+/// Order of a project listing
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ProjectSort {
+    /// Most recently changed first
+    #[default]
+    UpdatedAt,
+    /// Alphabetical
+    Name,
+    /// Most connected peers first
+    Peers,
+}
+
+/// Projects per page when the client doesn't ask for a size
+const DEFAULT_PROJECTS_PER_PAGE: usize = 50;
+
+/// Largest page of projects returned
+const MAX_PROJECTS_PER_PAGE: usize = 200;
+
+#[derive(Debug, Deserialize)]
+struct RegisterRequest {
+    username: String,
+    password: String,
+    display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginRequest {
+    username: String,
+    password: String,
+}
+
+#[derive(Debug, Serialize)]
+struct AuthResponse {
+    user: auth::UserInfo,
+    /// Sent as `Authorization: Bearer <token>` and in Hello
+    token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OAuthStartQuery {
+    /// Where to send the browser with the token once signed in
+    redirect_to: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct OAuthStartResponse {
+    authorize_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OAuthCallbackQuery {
+    code: Option<String>,
+    state: String,
+    error: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
 struct ProjectListResponse {
     projects: Vec<ProjectInfo>,
+    /// Projects matching the filters, across all pages
     total: usize,
+    page: usize,
+    per_page: usize,
+    /// Page to request next, if there are more projects
+    next_page: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ActivityQuery {
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct ActivityFeedResponse {
+    project_id: String,
+    entries: Vec<ActivityEntry>,
 }
 
 #[derive(Debug, Serialize)]
 struct ProjectDetailResponse {
     project_id: String,
     name: String,
+    archived: bool,
     peers: Vec<PeerInfo>,
     file_count: usize,
     folder_count: usize,
 }
 
+#[derive(Debug, Serialize)]
+struct FileResponse {
+    project_id: String,
+    path: String,
+    content: String,
+    language: String,
+    version: u64,
+    etag: String,
+}
+
+#[derive(Debug, Serialize)]
+struct TreeResponse {
+    project_id: String,
+    tree: Vec<NestedNode>,
+    etag: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WriteFileRequest {
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateShareLinkRequest {
+    role: ShareRole,
+    /// Files or folders the link is limited to
+    #[serde(default)]
+    paths: Vec<String>,
+    expires_in_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct ShareLinkResponse {
+    #[serde(flatten)]
+    link: ShareLink,
+    /// Passed as `share_token` when joining the project
+    token: String,
+    ws_url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ShareLinkListResponse {
+    project_id: String,
+    links: Vec<ShareLink>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LobbyQuery {
+    /// Account token, to include projects only members may see
+    token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NotificationsRequest {
+    sinks: Vec<NotificationSink>,
+    /// Events to notify about; all of them if empty
+    #[serde(default)]
+    events: Vec<ProjectEventKind>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitSettingsRequest {
+    #[serde(default)]
+    bare: bool,
+    /// Defaults to `main`
+    branch: Option<String>,
+    remote: Option<GitRemote>,
+    #[serde(default)]
+    auto_commit: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitCommitRequest {
+    message: Option<String>,
+}
+
+/// A project's git settings, without the remote's credentials
+#[derive(Debug, Serialize)]
+struct GitResponse {
+    project_id: String,
+    bare: bool,
+    branch: String,
+    remote_url: Option<String>,
+    auto_commit: bool,
+    /// The branch's last commit
+    head: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuditQuery {
+    /// Only entries for this project
+    project_id: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DisconnectQuery {
+    /// Sent to the peer with its Goodbye
+    reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct AdminSaveResponse {
+    saved: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct SetRoleRequest {
+    role: ProjectRole,
+}
+
+#[derive(Debug, Serialize)]
+struct AclMember {
+    user_id: String,
+    /// None if the account no longer exists
+    username: Option<String>,
+    role: ProjectRole,
+}
+
+#[derive(Debug, Serialize)]
+struct AclResponse {
+    project_id: String,
+    /// True if the project has no access list and everyone may edit it
+    open: bool,
+    members: Vec<AclMember>,
+}
+
 // ============================================================================
 // HTTP HANDLERS
 // ============================================================================
@@ -150,14 +508,31 @@ async fn health_check(State(state): State<Arc<AppState>>) -> impl IntoResponse {
     })
 }
 
+/// Prometheus metrics
+async fn metrics_handler(State(state): State<Arc<AppState>>) -> Result<String, StatusCode> {
+    let handle = state.metrics.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+
+    // Gauges are read at scrape time
+    let stats = state.sync_server.stats();
+    let storage = state.sync_server.storage().stats();
+    metrics::gauge!(telemetry::ROOMS_ACTIVE).set(stats.active_projects as f64);
+    metrics::gauge!(telemetry::PEERS_CONNECTED).set(stats.active_peers as f64);
+    metrics::gauge!(telemetry::WS_CONNECTIONS).set(state.connections.connections() as f64);
+    metrics::gauge!(telemetry::STORAGE_SIZE).set(storage.total_size_bytes as f64);
+    metrics::gauge!(telemetry::STORED_DOCUMENTS).set(storage.document_count as f64);
+
+    Ok(handle.render())
+}
+
 /// Create a new project/room
 async fn create_project(
     State(state): State<Arc<AppState>>,
+    host: Option<Host>,
+    headers: HeaderMap,
+    user: Option<Extension<AuthUser>>,
     Json(payload): Json<CreateProjectRequest>,
 ) -> Result<Json<CreateProjectResponse>, (axum::http::StatusCode, String)> {
-    // Generate a safe project ID from UUID
-    let full_uuid = uuid::Uuid::new_v4().to_string();
-    let project_id: String = full_uuid.chars().take(8).collect();
+    let project_id = new_project_id();
 
     let short_id: String = project_id.chars().take(4).collect();
     let name = payload
@@ -166,35 +541,229 @@ async fn create_project(
 
     info!("Creating project: {} ({})", name, project_id);
 
-    // Create room in room manager
-    state.room_manager.create_room(&project_id, &name).await;
-
-    // Save metadata
-    let metadata = DocumentMetadata::new(&project_id, &name);
+    // Save metadata, owned by the signed-in user if any
+    let mut metadata = DocumentMetadata::new(&project_id, &name);
+    if let Some(Extension(user)) = &user {
+        metadata = metadata.with_owner(&user.user_id);
+    }
     if let Err(e) = state.sync_server.storage().save_metadata(&metadata) {
         error!("Failed to save project metadata: {}", e);
         // Continue anyway - room is created in memory
     }
+    register_project(&state, &metadata).await;
 
     info!("Created project successfully: {} ({})", name, project_id);
-
     let response = CreateProjectResponse {
-        project_id: project_id.clone(),
+        ws_url: ws_url(&state, host, &headers, &project_id),
+        project_id,
         name,
-        ws_url: format!("/ws/{}", project_id),
     };
 
     Ok(Json(response))
 }
 
-/// List all projects
-async fn list_projects(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+/// Copy a project into a new one owned by the caller, who must be able to
+/// view the original.
+///
+/// The copy starts a fresh history unless `preserve_history` is set, and
+/// takes the chat along only with `include_chat`.
+async fn fork_project(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    host: Option<Host>,
+    headers: HeaderMap,
+    user: Option<Extension<AuthUser>>,
+    Json(payload): Json<ForkProjectRequest>,
+) -> Result<Json<CreateProjectResponse>, (StatusCode, String)> {
+    require_role(&state, user.as_deref(), &project_id, ProjectRole::Viewer)?;
+    let source = state
+        .sync_server
+        .storage()
+        .get_metadata(&project_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Project not found: {}", project_id)))?;
+
+    let fork_id = new_project_id();
+    let name = payload
+        .name
+        .unwrap_or_else(|| format!("{} (fork)", source.name));
+    let mut metadata = DocumentMetadata::new(&fork_id, &name);
+    if let Some(Extension(user)) = &user {
+        metadata = metadata.with_owner(&user.user_id);
+    }
+
+    let forked = state
+        .sync_server
+        .fork_project(
+            &project_id,
+            &metadata,
+            payload.preserve_history,
+            payload.include_chat,
+        )
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !forked {
+        return Err((StatusCode::NOT_FOUND, format!("Project not found: {}", project_id)));
+    }
+    register_project(&state, &metadata).await;
+
+    info!("Forked project {} into {} ({})", project_id, name, fork_id);
+    Ok(Json(CreateProjectResponse {
+        ws_url: ws_url(&state, host, &headers, &fork_id),
+        project_id: fork_id,
+        name,
+    }))
+}
+
+/// Create a project from a shallow clone of a repository on a public host,
+/// owned by the signed-in caller.
+///
+/// The response is a stream of server-sent events following the import; the
+/// last one says whether it is done, with the new project, or failed. The
+/// project only exists once the import is done.
+async fn import_git(
+    State(state): State<Arc<AppState>>,
+    host: Option<Host>,
+    headers: HeaderMap,
+    user: Option<Extension<AuthUser>>,
+    Json(payload): Json<ImportGitRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, (StatusCode, String)> {
+    let Some(Extension(user)) = user else {
+        return Err((StatusCode::UNAUTHORIZED, "Sign in to import a project".to_string()));
+    };
+    git::validate_public_remote(&payload.url)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    if let Some(branch) = &payload.branch {
+        git::validate_branch(branch).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+
+    let project_id = new_project_id();
+    let name = payload
+        .name
+        .unwrap_or_else(|| git::repository_name(&payload.url));
+    let metadata = DocumentMetadata::new(&project_id, &name).with_owner(&user.user_id);
+    let ws_url = ws_url(&state, host, &headers, &project_id);
+
+    info!("Importing project {} ({}) from {}", name, project_id, payload.url);
+    let (progress, events) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let url = payload.url;
+        let imported = state
+            .git
+            .import(url.clone(), payload.branch, payload.scan, progress.clone())
+            .await
+            .and_then(|files| {
+                state
+                    .sync_server
+                    .import_project(&metadata, &files.entries, &files.contents)
+                    .map_err(GitError::from)
+            });
+        let last = match imported {
+            Ok(()) => {
+                register_project(&state, &metadata).await;
+                info!("Imported project {} ({}) from {}", name, project_id, url);
+                ImportProgress::Done {
+                    project_id,
+                    name,
+                    ws_url,
+                }
+            }
+            Err(e) => {
+                warn!("Failed to import {}: {}", url, e);
+                ImportProgress::Failed {
+                    message: e.to_string(),
+                }
+            }
+        };
+        let _ = progress.send(last);
+    });
+
+    let events = tokio_stream::wrappers::UnboundedReceiverStream::new(events)
+        .map(|progress| Event::default().json_data(progress));
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Short random ID for a new project
+fn new_project_id() -> String {
+    uuid::Uuid::new_v4().to_string().chars().take(8).collect()
+}
+
+/// Open a room for a project whose metadata was just saved, give its owner
+/// access and tell everyone it exists
+async fn register_project(state: &AppState, metadata: &DocumentMetadata) {
+    state
+        .room_manager
+        .create_room(&metadata.project_id, &metadata.name)
+        .await;
+
+    if let Some(owner_id) = &metadata.owner_id {
+        let acl = ProjectAcl::new(&metadata.project_id, owner_id.clone());
+        if let Err(e) = state.sync_server.storage().save_acl(&acl) {
+            error!("Failed to save project access list: {}", e);
+        }
+    }
+
+    state.sync_server.announce(ServerMessage::ProjectCreated {
+        project_id: metadata.project_id.clone(),
+        name: metadata.name.clone(),
+        owner_id: metadata.owner_id.clone(),
+        created_at: metadata.created_at,
+    });
+    state.sync_server.emit_event(ProjectEvent::ProjectCreated {
+        project_id: metadata.project_id.clone(),
+        name: metadata.name.clone(),
+    });
+}
+
+/// List projects visible to the caller, a page at a time.
+///
+/// Filters: `mine=true` (the signed-in user's), `owner`, `q` (name
+/// substring), `archived=true` (archived instead of active). Sorted by `sort` (`updated_at`, `name` or `peers`).
+async fn list_projects(
+    State(state): State<Arc<AppState>>,
+    user: Option<Extension<AuthUser>>,
+    Query(query): Query<ProjectListQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let user_id = user.map(|Extension(user)| user.user_id);
+    let owner = match (query.mine, &user_id) {
+        (false, _) => None,
+        (true, Some(user_id)) => Some(user_id.clone()),
+        (true, None) => {
+            return Err((StatusCode::UNAUTHORIZED, "Sign in to list your projects".to_string()))
+        }
+    };
+    let owner = owner.or(query.owner);
+    let name_filter = query
+        .q
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+        .map(str::to_lowercase);
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query
+        .per_page
+        .unwrap_or(DEFAULT_PROJECTS_PER_PAGE)
+        .clamp(1, MAX_PROJECTS_PER_PAGE);
     let storage = state.sync_server.storage();
 
-    match storage.list_documents() {
+    Ok(match storage.list_documents() {
         Ok(docs) => {
-            let projects: Vec<ProjectInfo> = docs
+            let mut projects: Vec<ProjectInfo> = docs
                 .into_iter()
+                .filter(|meta| meta.archived == query.archived)
+                .filter(|meta| owner.is_none() || meta.owner_id == owner)
+                .filter(|meta| {
+                    name_filter
+                        .as_ref()
+                        .is_none_or(|q| meta.name.to_lowercase().contains(q))
+                })
+                .filter(|meta| {
+                    matches!(
+                        state.sync_server.project_role(&meta.project_id, user_id.as_deref()),
+                        Ok(Some(_))
+                    )
+                })
                 .map(|meta| {
                     let peer_count = state
                         .sync_server
@@ -209,34 +778,74 @@ async fn list_projects(State(state): State<Arc<AppState>>) -> impl IntoResponse
                         peer_count,
                         has_host: false, // Would need to check room state
                         created_at: meta.created_at,
+                        updated_at: meta.updated_at,
+                        owner_id: meta.owner_id,
+                        archived: meta.archived,
                     }
                 })
                 .collect();
 
+            // The project ID breaks ties so pages are stable
+            match query.sort {
+                ProjectSort::UpdatedAt => projects.sort_by(|a, b| {
+                    b.updated_at
+                        .cmp(&a.updated_at)
+                        .then_with(|| a.project_id.cmp(&b.project_id))
+                }),
+                ProjectSort::Name => projects.sort_by(|a, b| {
+                    a.name
+                        .to_lowercase()
+                        .cmp(&b.name.to_lowercase())
+                        .then_with(|| a.project_id.cmp(&b.project_id))
+                }),
+                ProjectSort::Peers => projects.sort_by(|a, b| {
+                    b.peer_count
+                        .cmp(&a.peer_count)
+                        .then_with(|| a.project_id.cmp(&b.project_id))
+                }),
+            }
+
             let total = projects.len();
-            Json(ProjectListResponse { projects, total })
+            let projects: Vec<ProjectInfo> = projects
+                .into_iter()
+                .skip((page - 1).saturating_mul(per_page))
+                .take(per_page)
+                .collect();
+            let next_page = (page.saturating_mul(per_page) < total).then_some(page + 1);
+            Json(ProjectListResponse {
+                projects,
+                total,
+                page,
+                per_page,
+                next_page,
+            })
         }
         Err(e) => {
             error!("Failed to list projects: {}", e);
             Json(ProjectListResponse {
                 projects: vec![],
                 total: 0,
+                page,
+                per_page,
+                next_page: None,
             })
         }
-    }
+    })
 }
 
 /// Get project details
 async fn get_project(
     State(state): State<Arc<AppState>>,
     Path(project_id): Path<String>,
-) -> Result<impl IntoResponse, StatusCode> {
+    user: Option<Extension<AuthUser>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
     let storage = state.sync_server.storage();
 
     let metadata = storage
         .get_metadata(&project_id)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Project not found: {}", project_id)))?;
+    require_role(&state, user.as_deref(), &project_id, ProjectRole::Viewer)?;
 
     let peers: Vec<PeerInfo> = state
         .sync_server
@@ -257,6 +866,7 @@ async fn get_project(
                     },
                     active_file: presence.active_file,
                     joined_at: presence.joined_at,
+                    avatar_seed: presence.avatar_seed,
                 })
                 .collect()
         })
@@ -273,69 +883,1251 @@ async fn get_project(
     Ok(Json(ProjectDetailResponse {
         project_id: metadata.project_id,
         name: metadata.name,
+        archived: metadata.archived,
         peers,
         file_count,
         folder_count,
     }))
 }
 
-// ============================================================================
-// WEBSOCKET HANDLER
-// ============================================================================
+/// Archive a project: its room is closed and it can't be joined until it is
+/// unarchived (owners only)
+async fn archive_project(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    set_archived(&state, user.as_deref(), &project_id, true)
+}
 
-/// WebSocket upgrade handler
-async fn ws_handler(
-    ws: WebSocketUpgrade,
+/// Delete a project and everything stored about it (owners only)
+async fn delete_project(
+    State(state): State<Arc<AppState>>,
     Path(project_id): Path<String>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_role(&state, user.as_deref(), &project_id, ProjectRole::Owner)?;
+    let deleted = state
+        .sync_server
+        .delete_project(&project_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !deleted {
+        return Err((StatusCode::NOT_FOUND, format!("Project not found: {}", project_id)));
+    }
+    state.room_manager.remove_room(&project_id).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Make an archived project joinable again (owners only)
+async fn unarchive_project(
     State(state): State<Arc<AppState>>,
-) -> impl IntoResponse {
-    info!("WebSocket upgrade request for project: {}", project_id);
-    ws.on_upgrade(move |socket| handle_websocket(socket, project_id, state))
+    Path(project_id): Path<String>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    set_archived(&state, user.as_deref(), &project_id, false)
 }
 
-/// Handle WebSocket connection
-async fn handle_websocket(socket: WebSocket, project_id: String, state: Arc<AppState>) {
-    let (mut ws_sender, mut ws_receiver) = socket.split();
+fn set_archived(
+    state: &AppState,
+    user: Option<&AuthUser>,
+    project_id: &str,
+    archived: bool,
+) -> Result<Json<DocumentMetadata>, (StatusCode, String)> {
+    require_role(state, user, project_id, ProjectRole::Owner)?;
+    state
+        .sync_server
+        .set_archived(project_id, archived)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Project not found: {}", project_id)))
+}
 
-    // Generate peer identifiers
-    let peer_id = uuid::Uuid::new_v4().to_string();
-    let peer_color = generate_peer_color();
-    let session_token = generate_session_token();
+/// Get recent project activity, newest first
+async fn get_project_activity(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    user: Option<Extension<AuthUser>>,
+    Query(query): Query<ActivityQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    require_role(&state, user.as_deref(), &project_id, ProjectRole::Viewer)?;
 
-    info!(
-        "New WebSocket connection: peer={}, project={}",
-        peer_id, project_id
-    );
+    let limit = query.limit.unwrap_or(DEFAULT_ACTIVITY_LIMIT);
+    let entries = state.sync_server.activity().recent(&project_id, limit);
 
-    // Create channel for sending messages to this peer
-    let (tx, mut rx) = mpsc::unbounded_channel::<ServerMessage>();
+    Ok(Json(ActivityFeedResponse {
+        project_id,
+        entries,
+    }))
+}
 
-    // Register peer with sync server
-    if let Err(e) = state.sync_server.register_peer(
-        &peer_id,
-        "Anonymous", // Will be updated on Hello
-        &peer_color,
-        &session_token,
-        tx.clone(),
-    ) {
-        error!("Failed to register peer: {}", e);
-        return;
+/// Check that the caller has at least the given role in a project.
+///
+/// Everyone may edit projects without an access list.
+fn require_role(
+    state: &AppState,
+    user: Option<&AuthUser>,
+    project_id: &str,
+    needed: ProjectRole,
+) -> Result<ProjectRole, (StatusCode, String)> {
+    let role = state
+        .sync_server
+        .project_role(project_id, user.map(|user| user.user_id.as_str()))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    match role {
+        Some(role) if role >= needed => Ok(role),
+        Some(_) => Err((
+            StatusCode::FORBIDDEN,
+            match needed {
+                ProjectRole::Owner => "Only the project owner can do this",
+                _ => "Viewers cannot change this project",
+            }
+            .to_string(),
+        )),
+        None if user.is_none() => Err((
+            StatusCode::UNAUTHORIZED,
+            "Sign in to access this project".to_string(),
+        )),
+        None => Err((
+            StatusCode::FORBIDDEN,
+            "You do not have access to this project".to_string(),
+        )),
     }
+}
 
-    // Send welcome message
-    let welcome = ServerMessage::Welcome {
-        protocol_version: PROTOCOL_VERSION,
-        peer_id: peer_id.clone(),
-        color: peer_color.clone(),
-        session_token: session_token.clone(),
-        server_time: chrono::Utc::now().timestamp(),
-    };
+/// Resolve the project and file path of a file request, checking the
+/// caller's role
+fn file_target(
+    state: &AppState,
+    user: Option<&AuthUser>,
+    project_id: &str,
+    path: &str,
+    needed: ProjectRole,
+) -> Result<String, (StatusCode, String)> {
+    let path = normalize_path(path)
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("Invalid file path: {}", path)))?;
+    project_target(state, user, project_id, needed)?;
+    Ok(path)
+}
 
-    if let Err(e) = send_server_message(&mut ws_sender, &welcome).await {
-        error!("Failed to send welcome: {}", e);
-        state.sync_server.unregister_peer(&peer_id);
-        return;
+/// Check that the project of a file or tree request exists and that the
+/// caller has the role needed
+fn project_target(
+    state: &AppState,
+    user: Option<&AuthUser>,
+    project_id: &str,
+    needed: ProjectRole,
+) -> Result<(), (StatusCode, String)> {
+    let exists = state
+        .sync_server
+        .storage()
+        .get_metadata(project_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .is_some();
+    if !exists {
+        return Err((StatusCode::NOT_FOUND, format!("Project not found: {}", project_id)));
+    }
+    require_role(state, user, project_id, needed)?;
+    Ok(())
+}
+
+fn file_error(err: SyncError) -> (StatusCode, String) {
+    match err {
+        SyncError::Unauthorized(msg) => (StatusCode::CONFLICT, msg),
+        err => {
+            error!("File request failed: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        }
     }
+}
+
+fn precondition_failed(current: Option<String>) -> axum::response::Response {
+    let mut headers = HeaderMap::new();
+    if let Some(tag) = current.and_then(|tag| tag.parse().ok()) {
+        headers.insert(header::ETAG, tag);
+    }
+    (
+        StatusCode::PRECONDITION_FAILED,
+        headers,
+        "File has changed since it was read",
+    )
+        .into_response()
+}
+
+fn if_match_header(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::IF_MATCH)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// Whether the request's `If-None-Match` says the client already has `etag`
+fn not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|header| if_none_match(header, etag))
+}
+
+/// Read a project's file tree as nested nodes.
+///
+/// The ETag changes with every change to the document, so a client polling
+/// with `If-None-Match` gets 304 until something happens.
+async fn get_tree(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    user: Option<Extension<AuthUser>>,
+    headers: HeaderMap,
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    project_target(&state, user.as_deref(), &project_id, ProjectRole::Viewer)?;
+
+    let (tree, etag) = state
+        .sync_server
+        .read_tree(&project_id)
+        .await
+        .map_err(file_error)?;
+
+    if not_modified(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+    Ok((
+        [(header::ETAG, etag.clone())],
+        Json(TreeResponse {
+            project_id,
+            tree,
+            etag,
+        }),
+    )
+        .into_response())
+}
+
+/// Read a project file.
+///
+/// Answers with the bare content when the client accepts `text/plain`, and
+/// with 304 when `If-None-Match` carries the file's current ETag.
+async fn get_file(
+    State(state): State<Arc<AppState>>,
+    Path((project_id, path)): Path<(String, String)>,
+    user: Option<Extension<AuthUser>>,
+    headers: HeaderMap,
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    let path = file_target(&state, user.as_deref(), &project_id, &path, ProjectRole::Viewer)?;
+
+    let (file, etag) = state
+        .sync_server
+        .read_file(&project_id, &path)
+        .await
+        .map_err(file_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("File not found: {}", path)))?;
+
+    if not_modified(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+    let plain = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/plain"));
+    if plain {
+        return Ok((
+            [
+                (header::ETAG, etag),
+                (header::CONTENT_TYPE, "text/plain; charset=utf-8".to_string()),
+            ],
+            file.content,
+        )
+            .into_response());
+    }
+    Ok((
+        [(header::ETAG, etag.clone())],
+        Json(FileResponse {
+            project_id,
+            path: file.path,
+            content: file.content,
+            language: file.language,
+            version: file.version,
+            etag,
+        }),
+    )
+        .into_response())
+}
+
+/// Create or overwrite a project file.
+///
+/// With `If-Match` the write only happens if the file still has that ETag.
+async fn put_file(
+    State(state): State<Arc<AppState>>,
+    Path((project_id, path)): Path<(String, String)>,
+    user: Option<Extension<AuthUser>>,
+    headers: HeaderMap,
+    Json(payload): Json<WriteFileRequest>,
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    let path = file_target(&state, user.as_deref(), &project_id, &path, ProjectRole::Editor)?;
+
+    let result = state
+        .sync_server
+        .write_file(&project_id, &path, &payload.content, if_match_header(&headers))
+        .await
+        .map_err(file_error)?;
+
+    Ok(match result {
+        FileWrite::Created(etag) => (StatusCode::CREATED, [(header::ETAG, etag)]).into_response(),
+        FileWrite::Updated(etag) => (StatusCode::NO_CONTENT, [(header::ETAG, etag)]).into_response(),
+        FileWrite::PreconditionFailed(current) => precondition_failed(current),
+    })
+}
+
+/// Delete a project file or folder
+async fn delete_file(
+    State(state): State<Arc<AppState>>,
+    Path((project_id, path)): Path<(String, String)>,
+    user: Option<Extension<AuthUser>>,
+    headers: HeaderMap,
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    let path = file_target(&state, user.as_deref(), &project_id, &path, ProjectRole::Editor)?;
+
+    let result = state
+        .sync_server
+        .delete_file(&project_id, &path, if_match_header(&headers))
+        .await
+        .map_err(file_error)?;
+
+    Ok(match result {
+        FileDelete::Deleted => StatusCode::NO_CONTENT.into_response(),
+        FileDelete::NotFound => {
+            (StatusCode::NOT_FOUND, format!("File not found: {}", path)).into_response()
+        }
+        FileDelete::PreconditionFailed(current) => precondition_failed(current),
+    })
+}
+
+/// Check that the caller may manage the project's share links.
+///
+/// The project's owner may, when signed in. Otherwise the caller identifies
+/// with the session token of its WebSocket connection (`Authorization: Bearer
+/// <token>`) and must have joined the project without a share link, as its
+/// host if it is hosted, or else as its owner or, in a project nobody owns,
+/// as the peer that created it. Returns the caller's name.
+fn share_manager(
+    state: &AppState,
+    headers: &HeaderMap,
+    user: Option<Extension<AuthUser>>,
+    project_id: &str,
+) -> Result<String, (StatusCode, String)> {
+    if let Some(Extension(user)) = user {
+        let role = state
+            .sync_server
+            .project_role(project_id, Some(&user.user_id))
+            .ok()
+            .flatten();
+        if role == Some(ProjectRole::Owner) {
+            return Ok(user.username);
+        }
+    }
+
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, "Missing session token".to_string()))?;
+
+    state
+        .sync_server
+        .share_manager(token.trim(), project_id)
+        .ok_or_else(|| {
+            (
+                StatusCode::FORBIDDEN,
+                "Only the project host can manage share links".to_string(),
+            )
+        })
+}
+
+/// WebSocket URL of a project as seen by the client: `wss://` when served
+/// over TLS, here or by a proxy that sets `X-Forwarded-Proto`
+fn ws_url(state: &AppState, host: Option<Host>, headers: &HeaderMap, project_id: &str) -> String {
+    let path = format!("/ws/{}", project_id);
+    let Some(Host(host)) = host else {
+        return path;
+    };
+    let forwarded_https = headers
+        .get("x-forwarded-proto")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|proto| proto.eq_ignore_ascii_case("https"));
+    let scheme = if state.secure || forwarded_https {
+        "wss"
+    } else {
+        "ws"
+    };
+    format!("{}://{}{}", scheme, host, path)
+}
+
+/// Create a share link for a project
+async fn create_share_link(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    host: Option<Host>,
+    headers: HeaderMap,
+    user: Option<Extension<AuthUser>>,
+    Json(payload): Json<CreateShareLinkRequest>,
+) -> Result<Json<ShareLinkResponse>, (StatusCode, String)> {
+    let created_by = share_manager(&state, &headers, user, &project_id)?;
+
+    let ttl = payload
+        .expires_in_secs
+        .map(std::time::Duration::from_secs)
+        .unwrap_or(DEFAULT_LINK_TTL);
+    let (link, token) = state
+        .sync_server
+        .create_share_link(&project_id, payload.role, &payload.paths, ttl, Some(created_by))
+        .map_err(|e| match e {
+            SyncError::InvalidMessage(msg) => (StatusCode::BAD_REQUEST, msg),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+
+    Ok(Json(ShareLinkResponse {
+        ws_url: ws_url(&state, host, &headers, &project_id),
+        link,
+        token,
+    }))
+}
+
+/// List the active share links of a project
+async fn list_share_links(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    headers: HeaderMap,
+    user: Option<Extension<AuthUser>>,
+) -> Result<Json<ShareLinkListResponse>, (StatusCode, String)> {
+    share_manager(&state, &headers, user, &project_id)?;
+
+    let links = state
+        .sync_server
+        .list_share_links(&project_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(ShareLinkListResponse { project_id, links }))
+}
+
+/// Revoke a share link, disconnecting the peers that joined through it
+async fn revoke_share_link(
+    State(state): State<Arc<AppState>>,
+    Path((project_id, link_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    user: Option<Extension<AuthUser>>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    share_manager(&state, &headers, user, &project_id)?;
+
+    let revoked = state
+        .sync_server
+        .revoke_share_link(&project_id, &link_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if revoked {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err((StatusCode::NOT_FOUND, format!("Share link not found: {}", link_id)))
+    }
+}
+
+/// Notification settings of a project
+async fn get_notifications(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    headers: HeaderMap,
+    user: Option<Extension<AuthUser>>,
+) -> Result<Json<ProjectNotifications>, (StatusCode, String)> {
+    share_manager(&state, &headers, user, &project_id)?;
+
+    let settings = state
+        .sync_server
+        .storage()
+        .get_notifications(&project_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(settings.unwrap_or(ProjectNotifications {
+        project_id,
+        sinks: Vec::new(),
+        events: Vec::new(),
+    })))
+}
+
+/// Replace the notification settings of a project
+async fn put_notifications(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    headers: HeaderMap,
+    user: Option<Extension<AuthUser>>,
+    Json(payload): Json<NotificationsRequest>,
+) -> Result<Json<ProjectNotifications>, (StatusCode, String)> {
+    share_manager(&state, &headers, user, &project_id)?;
+
+    for sink in &payload.sinks {
+        state
+            .notifier
+            .validate_sink(sink)
+            .await
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+
+    let settings = ProjectNotifications {
+        project_id,
+        sinks: payload.sinks,
+        events: payload.events,
+    };
+    state
+        .sync_server
+        .storage()
+        .save_notifications(&settings)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(settings))
+}
+
+fn git_response(settings: ProjectGit, head: Option<String>) -> GitResponse {
+    GitResponse {
+        project_id: settings.project_id,
+        bare: settings.bare,
+        branch: settings.branch,
+        remote_url: settings.remote.map(|remote| remote.url),
+        auto_commit: settings.auto_commit,
+        head,
+    }
+}
+
+fn git_error(err: GitError) -> (StatusCode, String) {
+    match err {
+        GitError::NotConfigured => (StatusCode::NOT_FOUND, err.to_string()),
+        GitError::InvalidRepository(msg) => (StatusCode::BAD_REQUEST, msg),
+        GitError::Sync(SyncError::Unauthorized(msg)) => (StatusCode::CONFLICT, msg),
+        GitError::Sync(SyncError::DocumentNotFound(_)) => {
+            (StatusCode::NOT_FOUND, "Project has no saved document yet".to_string())
+        }
+        err => {
+            error!("Git export failed: {}", err);
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        }
+    }
+}
+
+/// Show the git repository a project is exported to
+async fn get_git(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    headers: HeaderMap,
+    user: Option<Extension<AuthUser>>,
+) -> Result<Json<GitResponse>, (StatusCode, String)> {
+    share_manager(&state, &headers, user, &project_id)?;
+
+    let settings = state
+        .sync_server
+        .storage()
+        .get_git(&project_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| git_error(GitError::NotConfigured))?;
+    let head = state.git.head(&settings).map_err(git_error)?;
+
+    Ok(Json(git_response(settings, head)))
+}
+
+/// Export a project to a git repository under the server's git root. The
+/// repository is created unless one is already at the path.
+async fn put_git(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    headers: HeaderMap,
+    user: Option<Extension<AuthUser>>,
+    Json(payload): Json<GitSettingsRequest>,
+) -> Result<Json<GitResponse>, (StatusCode, String)> {
+    share_manager(&state, &headers, user, &project_id)?;
+
+    let exists = state
+        .sync_server
+        .storage()
+        .get_metadata(&project_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .is_some();
+    if !exists {
+        return Err((StatusCode::NOT_FOUND, format!("Project not found: {}", project_id)));
+    }
+
+    let branch = payload.branch.unwrap_or_else(|| "main".to_string());
+    git::validate_branch(&branch).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    if let Some(remote) = &payload.remote {
+        git::validate_public_remote(&remote.url)
+            .await
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    }
+
+    let settings = ProjectGit {
+        project_id,
+        bare: payload.bare,
+        branch,
+        remote: payload.remote,
+        auto_commit: payload.auto_commit,
+    };
+    let head = state.git.attach(&settings).map_err(git_error)?;
+    state
+        .sync_server
+        .storage()
+        .save_git(&settings)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(git_response(settings, head)))
+}
+
+/// Stop exporting a project to git; the repository stays where it is
+async fn delete_git(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    headers: HeaderMap,
+    user: Option<Extension<AuthUser>>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    share_manager(&state, &headers, user, &project_id)?;
+
+    let removed = state
+        .sync_server
+        .storage()
+        .remove_git(&project_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if removed {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(git_error(GitError::NotConfigured))
+    }
+}
+
+/// Commit a project's files to its git repository, and push them if it has
+/// a remote
+async fn commit_git(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    user: Option<Extension<AuthUser>>,
+    payload: Option<Json<GitCommitRequest>>,
+) -> Result<Json<GitCommit>, (StatusCode, String)> {
+    project_target(&state, user.as_deref(), &project_id, ProjectRole::Editor)?;
+
+    let message = payload.and_then(|Json(payload)| payload.message);
+    let commit = state
+        .git
+        .export(&state.sync_server, &project_id, message)
+        .await
+        .map_err(git_error)?;
+    Ok(Json(commit))
+}
+
+/// Describe a project's access list with the members' usernames
+fn acl_response(state: &AppState, project_id: String, acl: Option<ProjectAcl>) -> AclResponse {
+    let Some(acl) = acl else {
+        return AclResponse {
+            project_id,
+            open: true,
+            members: Vec::new(),
+        };
+    };
+
+    let members = std::iter::once((acl.owner_id.clone(), ProjectRole::Owner))
+        .chain(acl.editors.iter().map(|id| (id.clone(), ProjectRole::Editor)))
+        .chain(acl.viewers.iter().map(|id| (id.clone(), ProjectRole::Viewer)))
+        .map(|(user_id, role)| AclMember {
+            username: state.auth.user(&user_id).ok().flatten().map(|user| user.username),
+            user_id,
+            role,
+        })
+        .collect();
+    AclResponse {
+        project_id,
+        open: false,
+        members,
+    }
+}
+
+fn acl_error(err: SyncError) -> (StatusCode, String) {
+    match err {
+        SyncError::InvalidMessage(msg) => (StatusCode::BAD_REQUEST, msg),
+        SyncError::Unauthorized(msg) => (StatusCode::CONFLICT, msg),
+        err => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
+    }
+}
+
+/// Get a project's access list
+async fn get_acl(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<Json<AclResponse>, (StatusCode, String)> {
+    require_role(&state, user.as_deref(), &project_id, ProjectRole::Viewer)?;
+    let acl = state.sync_server.project_acl(&project_id).map_err(acl_error)?;
+    Ok(Json(acl_response(&state, project_id, acl)))
+}
+
+/// Give a user the editor or viewer role in a project (owner only)
+async fn grant_access(
+    State(state): State<Arc<AppState>>,
+    Path((project_id, user_id)): Path<(String, String)>,
+    user: Option<Extension<AuthUser>>,
+    Json(payload): Json<SetRoleRequest>,
+) -> Result<Json<AclResponse>, (StatusCode, String)> {
+    require_role(&state, user.as_deref(), &project_id, ProjectRole::Owner)?;
+    if state.auth.user(&user_id).map_err(auth_error)?.is_none() {
+        return Err((StatusCode::NOT_FOUND, format!("User not found: {}", user_id)));
+    }
+
+    let acl = state
+        .sync_server
+        .set_project_role(&project_id, &user_id, Some(payload.role))
+        .map_err(acl_error)?;
+    Ok(Json(acl_response(&state, project_id, Some(acl))))
+}
+
+/// Take away a user's access to a project (owner only)
+async fn revoke_access(
+    State(state): State<Arc<AppState>>,
+    Path((project_id, user_id)): Path<(String, String)>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_role(&state, user.as_deref(), &project_id, ProjectRole::Owner)?;
+    state
+        .sync_server
+        .set_project_role(&project_id, &user_id, None)
+        .map_err(acl_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Check the `X-Admin-Token` header against `ADMIN_TOKEN`
+fn require_admin(state: &AppState, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    use sha2::{Digest, Sha256};
+
+    let Some(expected) = &state.admin_token else {
+        return Err((StatusCode::NOT_FOUND, "Admin API is disabled".to_string()));
+    };
+    let given = headers
+        .get("x-admin-token")
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, "Missing admin token".to_string()))?;
+
+    // Compare digests so the time taken says nothing about the token
+    if Sha256::digest(given.as_bytes()) != Sha256::digest(expected.as_bytes()) {
+        return Err((StatusCode::FORBIDDEN, "Invalid admin token".to_string()));
+    }
+    Ok(())
+}
+
+/// List open rooms with their peers
+async fn admin_list_rooms(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<sync::server::RoomReport>>, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+    Ok(Json(state.sync_server.room_reports()))
+}
+
+/// Save all open documents now
+async fn admin_save_all(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<AdminSaveResponse>, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+    let saved = state
+        .sync_server
+        .force_save(None)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(AdminSaveResponse { saved }))
+}
+
+/// Save one open document now
+async fn admin_save_room(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<AdminSaveResponse>, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+    let saved = state
+        .sync_server
+        .force_save(Some(&project_id))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if saved == 0 {
+        return Err((StatusCode::NOT_FOUND, format!("Room not open: {}", project_id)));
+    }
+    Ok(Json(AdminSaveResponse { saved }))
+}
+
+/// Save a room's document and remove everyone from it
+async fn admin_close_room(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+    let closed = state
+        .sync_server
+        .close_room(&project_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if closed {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err((StatusCode::NOT_FOUND, format!("Room not open: {}", project_id)))
+    }
+}
+
+/// Compare a project's in-memory document with the stored one
+async fn admin_dump_project(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<sync::server::ProjectDump>, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+    state
+        .sync_server
+        .dump_project(&project_id)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// List the broken invariants in a room's document
+async fn admin_validate_room(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<sync::document::IntegrityIssue>>, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+    check_integrity(&state, &project_id, false)
+}
+
+/// Repair a room's document and list what was fixed
+async fn admin_repair_room(
+    State(state): State<Arc<AppState>>,
+    Path(project_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<sync::document::IntegrityIssue>>, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+    check_integrity(&state, &project_id, true)
+}
+
+fn check_integrity(
+    state: &AppState,
+    project_id: &str,
+    repair: bool,
+) -> Result<Json<Vec<sync::document::IntegrityIssue>>, (StatusCode, String)> {
+    state
+        .sync_server
+        .check_integrity(project_id, repair)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Room not open: {}", project_id)))
+}
+
+/// Joins, leaves and opened files, newest first
+async fn admin_audit_log(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AuditQuery>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<AuditRecord>>, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+    let limit = query.limit.unwrap_or(DEFAULT_AUDIT_LIMIT);
+    state
+        .sync_server
+        .audit_log(query.project_id.as_deref(), limit)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Disconnect a peer
+async fn admin_disconnect_peer(
+    State(state): State<Arc<AppState>>,
+    Path(peer_id): Path<String>,
+    Query(query): Query<DisconnectQuery>,
+    headers: HeaderMap,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+    if state.sync_server.disconnect_peer(&peer_id, query.reason) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err((StatusCode::NOT_FOUND, format!("Peer not found: {}", peer_id)))
+    }
+}
+
+fn auth_error(err: auth::AuthError) -> (StatusCode, String) {
+    use auth::AuthError;
+    let status = match err {
+        AuthError::InvalidUsername(_) | AuthError::WeakPassword(_) => StatusCode::BAD_REQUEST,
+        AuthError::UsernameTaken | AuthError::IdentityTaken(_) => StatusCode::CONFLICT,
+        AuthError::InvalidCredentials | AuthError::InvalidToken => StatusCode::UNAUTHORIZED,
+        AuthError::UnknownProvider(_) => StatusCode::NOT_FOUND,
+        AuthError::OAuth(_) => StatusCode::BAD_REQUEST,
+        AuthError::Hashing(_) | AuthError::Storage(_) => {
+            error!("Auth request failed: {}", err);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+    (status, err.to_string())
+}
+
+/// Register a user account
+async fn register(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<RegisterRequest>,
+) -> Result<(StatusCode, Json<AuthResponse>), (StatusCode, String)> {
+    let auth = state.auth.clone();
+    let (user, token) = tokio::task::spawn_blocking(move || {
+        auth.register(
+            &payload.username,
+            &payload.password,
+            payload.display_name.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map_err(auth_error)?;
+
+    Ok((StatusCode::CREATED, Json(AuthResponse { user, token })))
+}
+
+/// Sign in and get a token
+async fn login(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<LoginRequest>,
+) -> Result<Json<AuthResponse>, (StatusCode, String)> {
+    let auth = state.auth.clone();
+    let (user, token) =
+        tokio::task::spawn_blocking(move || auth.login(&payload.username, &payload.password))
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .map_err(auth_error)?;
+
+    Ok(Json(AuthResponse { user, token }))
+}
+
+/// Get the signed-in user
+async fn current_user(
+    State(state): State<Arc<AppState>>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<Json<auth::UserInfo>, (StatusCode, String)> {
+    let Some(Extension(user)) = user else {
+        return Err((StatusCode::UNAUTHORIZED, "Not signed in".to_string()));
+    };
+    state
+        .auth
+        .user(&user.user_id)
+        .map_err(auth_error)?
+        .map(Json)
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, "Account no longer exists".to_string()))
+}
+
+/// The signed-in user's open connections and the projects they joined
+async fn current_user_sessions(
+    State(state): State<Arc<AppState>>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<Json<Vec<sync::server::PeerReport>>, (StatusCode, String)> {
+    let Some(Extension(user)) = user else {
+        return Err((StatusCode::UNAUTHORIZED, "Not signed in".to_string()));
+    };
+    Ok(Json(state.sync_server.user_sessions(&user.user_id)))
+}
+
+/// A connected peer and the projects it has joined; for administrators and
+/// the account the peer signed in with
+async fn get_peer(
+    State(state): State<Arc<AppState>>,
+    Path(peer_id): Path<String>,
+    headers: HeaderMap,
+    user: Option<Extension<AuthUser>>,
+) -> Result<Json<sync::server::PeerReport>, (StatusCode, String)> {
+    let report = state.sync_server.peer_report(&peer_id);
+    let is_self = match (&report, &user) {
+        (Some(report), Some(Extension(user))) => {
+            report.user_id.as_deref() == Some(user.user_id.as_str())
+        }
+        _ => false,
+    };
+    if !is_self {
+        require_admin(&state, &headers)?;
+    }
+    report
+        .map(Json)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Peer not connected: {}", peer_id)))
+}
+
+/// Start signing in with an OAuth provider.
+///
+/// A signed-in caller links the provider's identity to their account instead.
+async fn start_oauth(
+    State(state): State<Arc<AppState>>,
+    Path(provider): Path<String>,
+    Query(query): Query<OAuthStartQuery>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<Response, (StatusCode, String)> {
+    let link_user_id = user.map(|Extension(user)| user.user_id);
+    let (authorize_url, nonce) = state
+        .oauth
+        .start(&provider, link_user_id, query.redirect_to)
+        .map_err(auth_error)?;
+    // The callback only completes in the browser holding this cookie
+    let cookie = state.oauth.nonce_cookie(Some(&nonce));
+    Ok((
+        [(header::SET_COOKIE, cookie)],
+        Json(OAuthStartResponse { authorize_url }),
+    )
+        .into_response())
+}
+
+/// Provider callback: finish the sign-in and hand out a token.
+///
+/// Redirects to the `redirect_to` given at the start with the token in the
+/// URL fragment, or returns it as JSON.
+async fn oauth_callback(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<OAuthCallbackQuery>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let code = match (query.code, query.error) {
+        (_, Some(error)) => return Err((StatusCode::BAD_REQUEST, format!("Sign-in failed: {}", error))),
+        (Some(code), None) => code,
+        (None, None) => return Err((StatusCode::BAD_REQUEST, "Missing code".to_string())),
+    };
+    let nonce = headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .find_map(auth::oauth::cookie_nonce);
+    let login = state
+        .oauth
+        .finish(&code, &query.state, nonce)
+        .await
+        .map_err(auth_error)?;
+
+    let auth = state.auth.clone();
+    let identity = login.identity;
+    let link_user_id = login.link_user_id;
+    let (user, token) =
+        tokio::task::spawn_blocking(move || auth.oauth_login(&identity, link_user_id.as_deref()))
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .map_err(auth_error)?;
+
+    let cookie = [(header::SET_COOKIE, state.oauth.nonce_cookie(None))];
+    Ok(match login.redirect_to {
+        Some(target) => {
+            (cookie, Redirect::to(&format!("{}#token={}", target, token))).into_response()
+        }
+        None => (cookie, Json(AuthResponse { user, token })).into_response(),
+    })
+}
+
+// ============================================================================
+// WEBSOCKET HANDLER
+// ============================================================================
+
+/// WebSocket upgrade handler
+async fn ws_handler(
+    ws: WebSocketUpgrade,
+    Path(project_id): Path<String>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    info!("WebSocket upgrade request for project: {}", project_id);
+
+    let permit = match acquire_connection(&state, remote, &headers) {
+        Ok(permit) => permit,
+        Err(e) => return refuse_connection(ws, e),
+    };
+    let ip = client_ip(&state, remote, &headers);
+
+    ws.max_message_size(state.max_message_size)
+        .on_upgrade(move |socket| handle_websocket(socket, project_id, ip, state, permit))
+}
+
+/// Project list updates for the landing page.
+///
+/// The socket only sends: ProjectCreated, ProjectDeleted and PeerCountChanged
+/// for the projects the caller may see in `/api/projects`. Signed-in users
+/// pass their token as `?token=`, since browsers cannot set headers on
+/// WebSocket requests.
+async fn lobby_handler(
+    ws: WebSocketUpgrade,
+    Query(query): Query<LobbyQuery>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    let user_id = match query.token.as_deref().map(|token| state.auth.verify_token(token)) {
+        None => None,
+        Some(Ok(claims)) => Some(claims.sub),
+        Some(Err(e)) => return (StatusCode::UNAUTHORIZED, e.to_string()).into_response(),
+    };
+
+    let permit = match acquire_connection(&state, remote, &headers) {
+        Ok(permit) => permit,
+        Err(e) => return refuse_connection(ws, e),
+    };
+
+    ws.on_upgrade(move |socket| handle_lobby(socket, state, user_id, permit))
+}
+
+/// Forward project list updates to a lobby socket until either side closes
+async fn handle_lobby(
+    socket: WebSocket,
+    state: Arc<AppState>,
+    user_id: Option<String>,
+    _permit: ConnectionPermit,
+) {
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+    let mut updates = state.sync_server.subscribe_lobby();
+
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(msg) => {
+                    if !lobby_visible(&state, &msg, user_id.as_deref()) {
+                        continue;
+                    }
+                    if send_server_message(&mut ws_sender, &msg).await.is_err() {
+                        break;
+                    }
+                }
+                // The client's list is out of date; have it reconnect and
+                // fetch the list again
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(missed)) => {
+                    debug!("Lobby socket missed {} updates", missed);
+                    let _ = ws_sender
+                        .send(Message::Close(Some(CloseFrame {
+                            code: close_code::AGAIN,
+                            reason: "Missed project list updates".into(),
+                        })))
+                        .await;
+                    break;
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            },
+            incoming = ws_receiver.next() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Nothing to handle; pings are answered by the WebSocket layer
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// Whether a lobby update concerns a project the user may see
+fn lobby_visible(state: &AppState, msg: &ServerMessage, user_id: Option<&str>) -> bool {
+    match msg {
+        ServerMessage::ProjectCreated { project_id, .. }
+        | ServerMessage::PeerCountChanged { project_id, .. } => matches!(
+            state.sync_server.project_role(project_id, user_id),
+            Ok(Some(_))
+        ),
+        // Deleted projects have no access list left to check
+        _ => true,
+    }
+}
+
+/// Take a connection slot for the client
+fn acquire_connection(
+    state: &AppState,
+    remote: SocketAddr,
+    headers: &HeaderMap,
+) -> Result<ConnectionPermit, LimitExceeded> {
+    let ip = client_ip(state, remote, headers);
+    state.connections.try_acquire(ip).inspect_err(|e| {
+        warn!("Refused WebSocket connection from {}: {}", ip, e);
+        metrics::counter!(telemetry::CONNECTIONS_REJECTED, "reason" => e.reason()).increment(1);
+    })
+}
+
+/// Response to a connection refused by the connection limits
+fn refuse_connection(ws: WebSocketUpgrade, e: LimitExceeded) -> Response {
+    match e {
+        // Cheap to refuse before upgrading
+        LimitExceeded::RateLimited { retry_after } => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.as_secs().max(1).to_string())],
+            e.to_string(),
+        )
+            .into_response(),
+        // Browsers cannot read an upgrade error, so tell them why in a close
+        // frame
+        e => ws.on_upgrade(move |socket| refuse_websocket(socket, e)),
+    }
+}
+
+/// Address of the client: the socket's peer, or the address the proxy in
+/// front of the server appended to `X-Forwarded-For`
+fn client_ip(state: &AppState, remote: SocketAddr, headers: &HeaderMap) -> std::net::IpAddr {
+    if state.trust_forwarded_for {
+        let forwarded = headers
+            .get("x-forwarded-for")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse().ok());
+        if let Some(ip) = forwarded {
+            return ip;
+        }
+    }
+    remote.ip()
+}
+
+/// Close a connection refused by the connection limits
+async fn refuse_websocket(mut socket: WebSocket, reason: LimitExceeded) {
+    let _ = socket
+        .send(Message::Close(Some(CloseFrame {
+            code: close_code::AGAIN,
+            reason: reason.to_string().into(),
+        })))
+        .await;
+}
+
+/// Handle WebSocket connection
+async fn handle_websocket(
+    socket: WebSocket,
+    project_id: String,
+    ip: std::net::IpAddr,
+    state: Arc<AppState>,
+    _permit: ConnectionPermit,
+) {
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+
+    // Generate peer identifiers
+    let peer_id = uuid::Uuid::new_v4().to_string();
+    let peer_color = peer_color(&peer_id);
+    let session_token = generate_session_token();
+
+    info!(
+        "New WebSocket connection: peer={}, project={}",
+        peer_id, project_id
+    );
+
+    // Create channel for sending messages to this peer
+    let (tx, mut rx) = sync::lanes::channel();
+
+    // Register peer with sync server
+    if let Err(e) = state.sync_server.register_peer(
+        &peer_id,
+        ANONYMOUS_NAME, // Will be updated on Hello, or generated on join
+        &peer_color,
+        &session_token,
+        tx.clone(),
+    ) {
+        error!("Failed to register peer: {}", e);
+        return;
+    }
+    if let Some(peer) = state.sync_server.get_peer(&peer_id) {
+        peer.write().ip = Some(ip);
+    }
+
+    // Send welcome message
+    let welcome = ServerMessage::Welcome {
+        protocol_version: PROTOCOL_VERSION,
+        peer_id: peer_id.clone(),
+        color: peer_color.clone(),
+        session_token: session_token.clone(),
+        server_time: chrono::Utc::now().timestamp(),
+    };
+
+    if let Err(e) = send_server_message(&mut ws_sender, &welcome).await {
+        error!("Failed to send welcome: {}", e);
+        state.sync_server.unregister_peer(&peer_id);
+        return;
+    }
+
+    // Everything this connection logs carries the peer and project
+    let span = tracing::info_span!("ws", peer_id = %peer_id, project_id = %project_id);
 
     // Clone values for tasks
     let peer_id_recv = peer_id.clone();
@@ -344,73 +2136,106 @@ async fn handle_websocket(socket: WebSocket, project_id: String, state: Arc<AppS
     let state_recv = state.clone();
 
     // Task to forward messages from channel to WebSocket
-    let send_task = tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
-            match SyncProtocol::encode_server(&msg) {
-                Ok(bytes) => {
-                    if ws_sender.send(Message::Binary(bytes.to_vec())).await.is_err() {
-                        break;
+    let send_task = tokio::spawn(
+        async move {
+            while let Some(msg) = rx.recv().await {
+                match SyncProtocol::encode_server(&msg) {
+                    Ok(bytes) => {
+                        metrics::counter!(telemetry::MESSAGES_SENT, "type" => telemetry::message_type(&bytes))
+                            .increment(1);
+                        metrics::counter!(telemetry::WS_BYTES_SENT).increment(bytes.len() as u64);
+                        if ws_sender.send(Message::Binary(bytes.to_vec())).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        warn!("Failed to encode message: {}", e);
                     }
                 }
-                Err(e) => {
-                    warn!("Failed to encode message: {}", e);
+
+                // The server only says goodbye when it drops the connection
+                if matches!(msg, ServerMessage::Goodbye { .. }) {
+                    let _ = ws_sender.close().await;
+                    break;
                 }
             }
+            debug!("Send task ended for peer {}", peer_id_send);
         }
-        debug!("Send task ended for peer {}", peer_id_send);
-    });
+        .instrument(span.clone()),
+    );
 
     // Task to handle incoming WebSocket messages
-    let recv_task = tokio::spawn(async move {
-        while let Some(Ok(msg)) = ws_receiver.next().await {
-            match msg {
-                Message::Binary(data) => {
-                    // Try to decode as binary protocol
-                    match SyncProtocol::decode_client(&data) {
-                        Ok(client_msg) => {
-                            handle_client_message(
-                                client_msg,
-                                &peer_id_recv,
-                                &project_id_recv,
-                                &state_recv,
-                                &tx,
+    let recv_task = tokio::spawn(
+        async move {
+            while let Some(Ok(msg)) = ws_receiver.next().await {
+                match msg {
+                    Message::Binary(data) => {
+                        metrics::counter!(telemetry::MESSAGES_RECEIVED, "type" => telemetry::message_type(&data))
+                            .increment(1);
+                        metrics::counter!(telemetry::WS_BYTES_RECEIVED).increment(data.len() as u64);
+
+                        // Try to decode as binary protocol
+                        match SyncProtocol::decode_client(&data) {
+                            Ok(client_msg) => {
+                                let (id, message_span) =
+                                    message_span(&telemetry::message_type(&data), data.len());
+                                telemetry::with_correlation_id(
+                                    id,
+                                    handle_client_message(
+                                        client_msg,
+                                        &peer_id_recv,
+                                        &project_id_recv,
+                                        &state_recv,
+                                        &tx,
+                                    ),
+                                )
+                                .instrument(message_span)
+                                .await;
+                            }
+                            Err(e) => {
+                                warn!("Failed to decode binary message: {}", e);
+                            }
+                        }
+                    }
+                    Message::Text(text) => {
+                        metrics::counter!(telemetry::MESSAGES_RECEIVED, "type" => "Json").increment(1);
+                        metrics::counter!(telemetry::WS_BYTES_RECEIVED).increment(text.len() as u64);
+
+                        // Also support JSON for compatibility/debugging
+                        if let Ok(client_msg) = serde_json::from_str::<ClientMessage>(&text) {
+                            let (id, message_span) = message_span("Json", text.len());
+                            telemetry::with_correlation_id(
+                                id,
+                                handle_client_message(
+                                    client_msg,
+                                    &peer_id_recv,
+                                    &project_id_recv,
+                                    &state_recv,
+                                    &tx,
+                                ),
                             )
+                            .instrument(message_span)
                             .await;
-                        }
-                        Err(e) => {
-                            warn!("Failed to decode binary message: {}", e);
+                        } else {
+                            // Try legacy JSON format
+                            handle_legacy_json(&text, &peer_id_recv, &project_id_recv, &state_recv, &tx)
+                                .await;
                         }
                     }
-                }
-                Message::Text(text) => {
-                    // Also support JSON for compatibility/debugging
-                    if let Ok(client_msg) = serde_json::from_str::<ClientMessage>(&text) {
-                        handle_client_message(
-                            client_msg,
-                            &peer_id_recv,
-                            &project_id_recv,
-                            &state_recv,
-                            &tx,
-                        )
-                        .await;
-                    } else {
-                        // Try legacy JSON format
-                        handle_legacy_json(&text, &peer_id_recv, &project_id_recv, &state_recv, &tx)
-                            .await;
+                    Message::Ping(_) => {
+                        // Pong is handled automatically
                     }
+                    Message::Close(_) => {
+                        info!("WebSocket closed by client: {}", peer_id_recv);
+                        break;
+                    }
+                    _ => {}
                 }
-                Message::Ping(_) => {
-                    // Pong is handled automatically
-                }
-                Message::Close(_) => {
-                    info!("WebSocket closed by client: {}", peer_id_recv);
-                    break;
-                }
-                _ => {}
             }
+            debug!("Receive task ended for peer {}", peer_id_recv);
         }
-        debug!("Receive task ended for peer {}", peer_id_recv);
-    });
+        .instrument(span),
+    );
 
     // Wait for either task to complete
     tokio::select! {
@@ -423,23 +2248,63 @@ async fn handle_websocket(socket: WebSocket, project_id: String, state: Arc<AppS
     info!("Peer {} disconnected from project {}", peer_id, project_id);
 }
 
+/// Correlation ID and span for one incoming message
+fn message_span(message_type: &str, size: usize) -> (String, tracing::Span) {
+    let id = telemetry::new_correlation_id();
+    let span = tracing::info_span!(
+        "message",
+        r#type = %message_type,
+        size,
+        correlation_id = %id
+    );
+    (id, span)
+}
+
 /// Handle a decoded client message
 async fn handle_client_message(
     msg: ClientMessage,
     peer_id: &str,
     project_id: &str,
     state: &Arc<AppState>,
-    tx: &mpsc::UnboundedSender<ServerMessage>,
+    tx: &PeerSender,
 ) {
     match msg {
         ClientMessage::Hello {
             client_name,
             session_token,
+            auth_token,
+            preferred_color,
             ..
         } => {
-            // Update peer name if provided
+            // Update peer name if provided (a unique one is picked on join)
+            if !client_name.trim().is_empty() {
+                if let Some(peer) = state.sync_server.get_peer(peer_id) {
+                    peer.write().name = client_name.trim().to_string();
+                }
+            }
+
+            // Kept on join unless another peer in the project has the color
             if let Some(peer) = state.sync_server.get_peer(peer_id) {
-                peer.write().name = client_name.clone();
+                peer.write().preferred_color = preferred_color;
+            }
+
+            // Signed-in peers are tied to their account, named after it by default
+            if let Some(token) = auth_token {
+                match state.auth.verify_token(&token) {
+                    Ok(claims) => {
+                        let user = state.auth.user(&claims.sub).ok().flatten();
+                        if let Some(peer) = state.sync_server.get_peer(peer_id) {
+                            let mut peer = peer.write();
+                            peer.user_id = Some(claims.sub);
+                            if let Some(user) = user.filter(|_| client_name.trim().is_empty()) {
+                                peer.name = user.display_name;
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        let _ = tx.send(error_reply(ErrorCode::Unauthorized, e.to_string(), None));
+                    }
+                }
             }
 
             // Check for session restoration
@@ -447,6 +2312,10 @@ async fn handle_client_message(
                 if let Some(existing_peer_id) = state.sync_server.restore_session(&token) {
                     info!("Session restored for peer {} -> {}", existing_peer_id, peer_id);
                 }
+                // Projects joined again only get the changes made since
+                if let Some(peer) = state.sync_server.get_peer(peer_id) {
+                    peer.write().resumed_token = Some(token);
+                }
             }
 
             debug!("Hello from peer {}: {}", peer_id, client_name);
@@ -455,21 +2324,39 @@ async fn handle_client_message(
         ClientMessage::JoinProject {
             project_id: req_project_id,
             request_state,
+            share_token,
         } => {
             match state
                 .sync_server
-                .join_project(peer_id, &req_project_id, request_state)
+                .join_project_with_link(
+                    peer_id,
+                    &req_project_id,
+                    request_state,
+                    share_token.as_deref(),
+                )
                 .await
             {
                 Ok(response) => {
                     let _ = tx.send(response);
+                    match state.sync_server.chat_history(&req_project_id) {
+                        Ok(messages) if !messages.is_empty() => {
+                            let _ = tx.send(ServerMessage::ChatHistory {
+                                project_id: req_project_id,
+                                messages,
+                            });
+                        }
+                        Ok(_) => {}
+                        Err(e) => warn!("Failed to load chat history: {}", e),
+                    }
                 }
                 Err(e) => {
-                    let _ = tx.send(ServerMessage::Error {
-                        code: ErrorCode::ServerError,
-                        message: e.to_string(),
-                        project_id: Some(req_project_id),
-                    });
+                    let code = match e {
+                        SyncError::Unauthorized(_) | SyncError::Archived(_) => {
+                            ErrorCode::Unauthorized
+                        }
+                        _ => ErrorCode::ServerError,
+                    };
+                    let _ = tx.send(error_reply(code, e.to_string(), Some(req_project_id)));
                 }
             }
         }
@@ -502,6 +2389,13 @@ async fn handle_client_message(
                 Ok(None) => {
                     // No response needed
                 }
+                Err(SyncError::Unauthorized(message)) => {
+                    let _ = tx.send(error_reply(
+                        ErrorCode::Unauthorized,
+                        message,
+                        Some(req_project_id),
+                    ));
+                }
                 Err(e) => {
                     warn!("Sync error: {}", e);
                 }
@@ -527,6 +2421,48 @@ async fn handle_client_message(
             project_id: req_project_id,
             file_path,
         } => {
+            // Only members may open files, and peers that joined through a
+            // share link stay within its scope
+            if let Err(e) = state
+                .sync_server
+                .check_file_access(peer_id, &req_project_id, &file_path)
+            {
+                let _ = tx.send(error_reply(
+                    ErrorCode::Unauthorized,
+                    e.to_string(),
+                    Some(req_project_id),
+                ));
+                return;
+            }
+            state
+                .sync_server
+                .audit_file_opened(peer_id, &req_project_id, &file_path);
+
+            // Track who has this file open and let the others know
+            if let Some(project_presence) = state.sync_server.presence().get(&req_project_id) {
+                if project_presence.open_file(peer_id, &file_path).is_ok() {
+                    if let Some(peer) = state.sync_server.get_peer(peer_id) {
+                        let peer = peer.read();
+                        let opened_msg = ServerMessage::FileOpened {
+                            project_id: req_project_id.clone(),
+                            peer_id: peer_id.to_string(),
+                            peer_name: peer.name.clone(),
+                            peer_color: peer.color.clone(),
+                            file_path: file_path.clone(),
+                        };
+                        state.sync_server.broadcast_to_project(&req_project_id, peer_id, opened_msg);
+                    }
+                }
+            }
+
+            // Rooms hosted from a peer's machine are served by asking the host
+            if state
+                .sync_server
+                .open_hosted_file(peer_id, &req_project_id, &file_path)
+            {
+                return;
+            }
+
             match state
                 .room_manager
                 .load_file_content(&req_project_id, &file_path)
@@ -550,8 +2486,20 @@ async fn handle_client_message(
             }
         }
 
-        ClientMessage::CloseFile { .. } => {
-            // Track file close for presence
+        ClientMessage::CloseFile {
+            project_id: req_project_id,
+            file_path,
+        } => {
+            if let Some(project_presence) = state.sync_server.presence().get(&req_project_id) {
+                if project_presence.close_file(peer_id, &file_path).is_ok() {
+                    let closed_msg = ServerMessage::FileClosed {
+                        project_id: req_project_id.clone(),
+                        peer_id: peer_id.to_string(),
+                        file_path,
+                    };
+                    state.sync_server.broadcast_to_project(&req_project_id, peer_id, closed_msg);
+                }
+            }
         }
 
         ClientMessage::CursorUpdate {
@@ -584,6 +2532,97 @@ async fn handle_client_message(
             }
         }
 
+        ClientMessage::Reaction {
+            project_id: req_project_id,
+            file_path,
+            line,
+            emoji,
+        } => {
+            if !is_valid_reaction(&emoji) {
+                let _ = tx.send(error_reply(
+                    ErrorCode::InvalidMessage,
+                    "Invalid reaction".to_string(),
+                    Some(req_project_id),
+                ));
+                return;
+            }
+            if let Err(e) = state
+                .sync_server
+                .check_file_access(peer_id, &req_project_id, &file_path)
+            {
+                let _ = tx.send(error_reply(
+                    ErrorCode::Unauthorized,
+                    e.to_string(),
+                    Some(req_project_id),
+                ));
+                return;
+            }
+
+            // Reactions are relayed only, never stored in the document
+            if let Some(peer) = state.sync_server.get_peer(peer_id) {
+                let peer = peer.read();
+                let reaction_msg = ServerMessage::ReactionBroadcast {
+                    project_id: req_project_id.clone(),
+                    peer_id: peer_id.to_string(),
+                    peer_name: peer.name.clone(),
+                    peer_color: peer.color.clone(),
+                    file_path,
+                    line,
+                    emoji: emoji.trim().to_string(),
+                    ttl_ms: REACTION_TTL.as_millis() as u64,
+                };
+                state.sync_server.broadcast_to_project(&req_project_id, peer_id, reaction_msg);
+            }
+        }
+
+        ClientMessage::HostProject {
+            project_id: req_project_id,
+            entries,
+        } => {
+            if let Err(e) = state
+                .sync_server
+                .host_project(peer_id, &req_project_id, &entries)
+            {
+                let _ = tx.send(host_error(e, req_project_id));
+            }
+        }
+
+        ClientMessage::HostTreeUpdate {
+            project_id: req_project_id,
+            created,
+            deleted,
+        } => {
+            if let Err(e) = state.sync_server.update_hosted_tree(
+                peer_id,
+                &req_project_id,
+                &created,
+                &deleted,
+            ) {
+                let _ = tx.send(host_error(e, req_project_id));
+            }
+        }
+
+        ClientMessage::HostFileContent {
+            project_id: req_project_id,
+            file_path,
+            content,
+        } => {
+            if let Err(e) = state.sync_server.provide_hosted_file(
+                peer_id,
+                &req_project_id,
+                &file_path,
+                content.as_deref(),
+            ) {
+                let _ = tx.send(host_error(e, req_project_id));
+            }
+        }
+
+        ClientMessage::SetDisplayName { name } => {
+            if let Err(e) = state.sync_server.set_display_name(peer_id, &name) {
+                let _ = tx.send(error_reply(ErrorCode::InvalidMessage, e.to_string(), None));
+            }
+        }
+
         ClientMessage::PresenceUpdate {
             project_id: req_project_id,
             status,
@@ -618,25 +2657,113 @@ async fn handle_client_message(
             project_id: req_project_id,
             content,
         } => {
-            // Get peer info and broadcast chat message
-            if let Some(peer) = state.sync_server.get_peer(peer_id) {
-                let peer = peer.read();
-                let timestamp = chrono::Utc::now().timestamp();
+            // Broadcast to all peers including sender so they see their message
+            match state
+                .sync_server
+                .post_chat(peer_id, &req_project_id, &content)
+            {
+                Ok(message) => debug!(
+                    "Chat message in {}: {} says {}",
+                    req_project_id, message.peer_name, message.content
+                ),
+                Err(e) => {
+                    let _ = tx.send(chat_error(e, req_project_id));
+                }
+            }
+        }
 
-                let chat_msg = ServerMessage::ChatBroadcast {
-                    project_id: req_project_id.clone(),
-                    peer_id: peer_id.to_string(),
-                    peer_name: peer.name.clone(),
-                    content: content.clone(),
-                    timestamp,
-                };
-                // Broadcast to all peers including sender so they see their message
-                state.sync_server.broadcast_to_project(&req_project_id, "", chat_msg);
+        ClientMessage::ChatDelete {
+            project_id: req_project_id,
+            message_id,
+        } => {
+            if let Err(e) = state
+                .sync_server
+                .delete_chat(peer_id, &req_project_id, &message_id)
+            {
+                let _ = tx.send(chat_error(e, req_project_id));
+            }
+        }
 
-                debug!(
-                    "Chat message in {}: {} says {}",
-                    req_project_id, peer.name, content
-                );
+        ClientMessage::AnnotationAdd {
+            project_id: req_project_id,
+            file_path,
+            start,
+            end,
+            kind,
+            body,
+        } => {
+            if let Err(e) = state.sync_server.add_annotation(
+                peer_id,
+                &req_project_id,
+                &file_path,
+                start..end,
+                kind,
+                &body,
+            ) {
+                let _ = tx.send(sync_error(e, req_project_id));
+            }
+        }
+
+        ClientMessage::AnnotationResolve {
+            project_id: req_project_id,
+            file_path,
+            annotation_id,
+            resolved,
+        } => {
+            if let Err(e) = state.sync_server.resolve_annotation(
+                peer_id,
+                &req_project_id,
+                &file_path,
+                &annotation_id,
+                resolved,
+            ) {
+                let _ = tx.send(sync_error(e, req_project_id));
+            }
+        }
+
+        ClientMessage::RequestAnnotations {
+            project_id: req_project_id,
+            file_path,
+        } => {
+            match state
+                .sync_server
+                .annotations(peer_id, &req_project_id, &file_path)
+            {
+                Ok(annotations) => {
+                    let _ = tx.send(ServerMessage::Annotations {
+                        project_id: req_project_id,
+                        file_path,
+                        annotations,
+                    });
+                }
+                Err(e) => {
+                    let _ = tx.send(sync_error(e, req_project_id));
+                }
+            }
+        }
+
+        ClientMessage::RequestFileVersion {
+            project_id: req_project_id,
+            file_path,
+            heads,
+        } => {
+            match state
+                .sync_server
+                .file_version(peer_id, &req_project_id, &file_path, &heads)
+            {
+                Ok(file) => {
+                    let language = file.as_ref().map(|file| file.language.clone());
+                    let _ = tx.send(ServerMessage::FileVersion {
+                        project_id: req_project_id,
+                        file_path,
+                        heads,
+                        content: file.map(|file| file.content),
+                        language,
+                    });
+                }
+                Err(e) => {
+                    let _ = tx.send(sync_error(e, req_project_id));
+                }
             }
         }
 
@@ -667,11 +2794,11 @@ async fn handle_client_message(
                     }
                 }
             } else {
-                let _ = tx.send(ServerMessage::Error {
-                    code: ErrorCode::ServerError,
-                    message: "Voice chat is not configured".to_string(),
-                    project_id: Some(req_project_id),
-                });
+                let _ = tx.send(error_reply(
+                    ErrorCode::ServerError,
+                    "Voice chat is not configured".to_string(),
+                    Some(req_project_id),
+                ));
             }
         }
 
@@ -686,6 +2813,31 @@ async fn handle_client_message(
             });
         }
 
+        ClientMessage::RequestActivityFeed {
+            project_id: req_project_id,
+            limit,
+        } => {
+            let limit = limit.map(|l| l as usize).unwrap_or(DEFAULT_ACTIVITY_LIMIT);
+            match state
+                .sync_server
+                .activity_feed(peer_id, &req_project_id, limit)
+            {
+                Ok(entries) => {
+                    let _ = tx.send(ServerMessage::ActivityFeed {
+                        project_id: req_project_id,
+                        entries,
+                    });
+                }
+                Err(e) => {
+                    let _ = tx.send(error_reply(
+                        ErrorCode::Unauthorized,
+                        e.to_string(),
+                        Some(req_project_id),
+                    ));
+                }
+            }
+        }
+
         ClientMessage::Goodbye { reason } => {
             info!(
                 "Peer {} saying goodbye: {:?}",
@@ -696,13 +2848,50 @@ async fn handle_client_message(
     }
 }
 
-/// Handle legacy JSON message format for backward compatibility
+/// Error response for a rejected hosting message
+fn host_error(err: SyncError, project_id: String) -> ServerMessage {
+    let code = match err {
+        SyncError::Unauthorized(_) => ErrorCode::Unauthorized,
+        SyncError::DocumentNotFound(_) => ErrorCode::ProjectNotFound,
+        _ => ErrorCode::ServerError,
+    };
+    SyncProtocol::error_response(code, err.to_string(), Some(project_id))
+}
+
+/// Error response for a rejected chat request
+fn chat_error(err: SyncError, project_id: String) -> ServerMessage {
+    let code = match err {
+        SyncError::Unauthorized(_) => ErrorCode::Unauthorized,
+        SyncError::RateLimited => ErrorCode::RateLimited,
+        SyncError::InvalidMessage(_) => ErrorCode::InvalidMessage,
+        _ => ErrorCode::ServerError,
+    };
+    error_reply(code, err.to_string(), Some(project_id))
+}
+
+/// Error response for a rejected request on a project's files
+fn sync_error(err: SyncError, project_id: String) -> ServerMessage {
+    let code = match err {
+        SyncError::Unauthorized(_) => ErrorCode::Unauthorized,
+        SyncError::DocumentNotFound(_) => ErrorCode::ProjectNotFound,
+        SyncError::RateLimited => ErrorCode::RateLimited,
+        SyncError::InvalidMessage(_) => ErrorCode::InvalidMessage,
+        _ => ErrorCode::ServerError,
+    };
+    error_reply(code, err.to_string(), Some(project_id))
+}
+
+/// Handle legacy JSON message format for backward compatibility.
+///
+/// Deprecated: legacy clients can join and ping, but never receive the room
+/// state, so this is only built with the `legacy-json` feature.
+#[cfg(feature = "legacy-json")]
 async fn handle_legacy_json(
     text: &str,
     peer_id: &str,
     project_id: &str,
     state: &Arc<AppState>,
-    tx: &mpsc::UnboundedSender<ServerMessage>,
+    tx: &PeerSender,
 ) {
     #[derive(Deserialize)]
     struct LegacyMessage {
@@ -720,7 +2909,12 @@ async fn handle_legacy_json(
                     let name = user
                         .get("name")
                         .and_then(|v| v.as_str())
-                        .unwrap_or("Anonymous");
+                        .unwrap_or(ANONYMOUS_NAME);
+
+                    warn!(
+                        "Peer {} joined with the deprecated legacy JSON protocol",
+                        peer_id
+                    );
 
                     // Update peer name
                     if let Some(peer) = state.sync_server.get_peer(peer_id) {
@@ -729,20 +2923,8 @@ async fn handle_legacy_json(
 
                     // Join the project
                     match state.sync_server.join_project(peer_id, project_id, true).await {
-                        Ok(response) => {
-                            // Send as JSON for legacy clients
-                            let json = serde_json::json!({
-                                "type": "RoomState",
-                                "room_id": project_id,
-                                "users": [],
-                                "files": [],
-                                "folder": null,
-                                "cursors": [],
-                                "chat_history": []
-                            });
-                            if let Ok(json_str) = serde_json::to_string(&json) {
-                                // Would need to send via WebSocket directly
-                            }
+                        Ok(_) => {
+                            debug!("Legacy client {} joined project {}", peer_id, project_id);
                         }
                         Err(e) => {
                             warn!("Legacy join failed: {}", e);
@@ -763,6 +2945,58 @@ async fn handle_legacy_json(
     }
 }
 
+/// Without the `legacy-json` feature, tell clients sending anything but
+/// protocol messages as JSON to upgrade
+#[cfg(not(feature = "legacy-json"))]
+async fn handle_legacy_json(
+    _text: &str,
+    peer_id: &str,
+    _project_id: &str,
+    _state: &Arc<AppState>,
+    tx: &PeerSender,
+) {
+    debug!("Unrecognized text message from peer {}", peer_id);
+    let _ = tx.send(error_reply(
+        ErrorCode::VersionMismatch,
+        "Unrecognized message. The legacy JSON protocol is no longer supported - update the client",
+        None,
+    ));
+}
+
+/// Span for an HTTP request, carrying the ID returned in `x-request-id`
+fn request_span(request: &axum::http::Request<axum::body::Body>) -> tracing::Span {
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id = %request_id
+    )
+}
+
+/// Build a protocol error for the message being handled, tagged with its
+/// correlation ID so it can be found in the logs
+fn error_reply(
+    code: ErrorCode,
+    message: impl Into<String>,
+    project_id: Option<String>,
+) -> ServerMessage {
+    let mut message = message.into();
+    warn!("Replying with {:?}: {}", code, message);
+    if let Some(id) = telemetry::correlation_id() {
+        message = format!("{} [ref {}]", message, id);
+    }
+    ServerMessage::Error {
+        code,
+        message,
+        project_id,
+    }
+}
+
 /// Send a server message over WebSocket
 async fn send_server_message(
     sender: &mut futures_util::stream::SplitSink<WebSocket, Message>,
@@ -789,81 +3023,229 @@ fn generate_session_token() -> String {
 
 #[tokio::main]
 async fn main() {
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "collab_server=info,tower_http=info".into()),
-        )
-        .init();
-
     // Load environment variables
     dotenvy::dotenv().ok();
 
-    // Initialize storage
-    let storage_path = std::env::var("STORAGE_PATH").unwrap_or_else(|_| "./data/collab.sled".to_string());
+    // Initialize tracing
+    telemetry::init_tracing();
+
+    // Load settings from collab.toml and the environment
+    let settings = match config::Settings::load() {
+        Ok(settings) => settings,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
-    info!("Initializing storage at: {}", storage_path);
+    // TLS is optional; without it a reverse proxy is expected to terminate it
+    let tls_config = tls::TlsConfig::from_env().expect("Invalid TLS configuration");
 
-    let storage_config = StorageConfig::new(&storage_path).with_compression(true);
+    // Initialize storage
+    let storage_config = settings.storage_config();
+
+    info!("Initializing storage at: {}", storage_config.path);
 
     let storage = DocumentStore::open(storage_config).expect("Failed to open storage");
 
     info!("Storage initialized successfully");
 
     // Create application state
-    let state = Arc::new(AppState::new(storage).await);
+    let state = Arc::new(AppState::new(storage, &settings, tls_config.is_some()).await);
 
     // Start background tasks
     let sync_server = state.sync_server.clone();
     let _background_handles = sync_server.start_background_tasks();
 
-    // Set up CORS
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods([
-            Method::GET,
-            Method::POST,
-            Method::PUT,
-            Method::PATCH,
-            Method::DELETE,
-            Method::OPTIONS,
-        ])
-        .allow_headers(Any);
+    // Send notifications about project events
+    tokio::spawn(
+        state
+            .notifier
+            .clone()
+            .run(state.sync_server.subscribe_events()),
+    );
+
+    // Commit projects that asked for it to git on a schedule
+    tokio::spawn(state.git.clone().run(state.sync_server.clone()));
+
+    // Forget addresses whose connection throttling has lapsed
+    let connections = state.connections.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            connections.sweep();
+        }
+    });
+
+    // Histograms need periodic upkeep when nothing scrapes them
+    if let Some(handle) = state.metrics.clone() {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(5));
+            loop {
+                interval.tick().await;
+                handle.run_upkeep();
+            }
+        });
+    }
+
+    // Set up CORS and the origin allowlist
+    let origin_policy = Arc::new(settings.origin_policy().unwrap_or_else(|e| {
+        error!("{}", e);
+        std::process::exit(1);
+    }));
+    let cors = origin_policy.cors_layer();
 
     // Build router
     let app = Router::new()
         // Health check
         .route("/health", get(health_check))
+        .route("/metrics", get(metrics_handler))
+        // Accounts
+        .route("/api/auth/register", post(register))
+        .route("/api/auth/login", post(login))
+        .route("/api/auth/me", get(current_user))
+        .route("/api/auth/me/sessions", get(current_user_sessions))
+        .route("/api/auth/oauth/:provider", get(start_oauth))
+        .route("/auth/callback", get(oauth_callback))
         // Project management
         .route("/api/projects", get(list_projects).post(create_project))
-        .route("/api/projects/:project_id", get(get_project))
+        .route("/api/projects/import-git", post(import_git))
+        .route(
+            "/api/projects/:project_id",
+            get(get_project).delete(delete_project),
+        )
+        .route("/api/projects/:project_id/activity", get(get_project_activity))
+        .route("/api/projects/:project_id/tree", get(get_tree))
+        .route("/api/projects/:project_id/fork", post(fork_project))
+        .route("/api/projects/:project_id/archive", post(archive_project))
+        .route("/api/projects/:project_id/unarchive", post(unarchive_project))
+        .route(
+            "/api/projects/:project_id/share",
+            get(list_share_links).post(create_share_link),
+        )
+        .route(
+            "/api/projects/:project_id/share/:link_id",
+            axum::routing::delete(revoke_share_link),
+        )
+        .route(
+            "/api/projects/:project_id/notifications",
+            get(get_notifications).put(put_notifications),
+        )
+        .route(
+            "/api/projects/:project_id/git",
+            get(get_git).put(put_git).delete(delete_git),
+        )
+        .route("/api/projects/:project_id/git/commit", post(commit_git))
+        .route("/api/projects/:project_id/acl", get(get_acl))
+        .route(
+            "/api/projects/:project_id/acl/:user_id",
+            axum::routing::put(grant_access).delete(revoke_access),
+        )
+        .route(
+            "/api/projects/:project_id/files/*path",
+            get(get_file).put(put_file).delete(delete_file),
+        )
+        .route("/api/peers/:peer_id", get(get_peer))
+        // Administration (X-Admin-Token)
+        .route("/api/admin/rooms", get(admin_list_rooms))
+        .route(
+            "/api/admin/rooms/:project_id",
+            axum::routing::delete(admin_close_room),
+        )
+        .route("/api/admin/rooms/:project_id/save", post(admin_save_room))
+        .route("/api/admin/rooms/:project_id/dump", get(admin_dump_project))
+        .route(
+            "/api/admin/rooms/:project_id/integrity",
+            get(admin_validate_room).post(admin_repair_room),
+        )
+        .route("/api/admin/save", post(admin_save_all))
+        .route("/api/admin/audit", get(admin_audit_log))
+        .route(
+            "/api/admin/peers/:peer_id",
+            axum::routing::delete(admin_disconnect_peer),
+        )
         // Legacy room endpoints (for compatibility)
         .route("/api/rooms", get(list_projects).post(create_project))
         .route("/api/rooms/:project_id", get(get_project))
         // WebSocket endpoint
+        .route("/ws/lobby", get(lobby_handler))
         .route("/ws/:project_id", get(ws_handler))
         // Add state and middleware
+        .layer(middleware::from_fn_with_state(
+            state.auth.clone(),
+            auth::authenticate,
+        ))
+        .layer(middleware::from_fn_with_state(
+            origin_policy,
+            origins::check_origin,
+        ))
         .with_state(state)
+        .layer(
+            tower::ServiceBuilder::new()
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                .layer(TraceLayer::new_for_http().make_span_with(request_span))
+                .layer(PropagateRequestIdLayer::x_request_id()),
+        )
         .layer(cors);
 
     // Start server
-    let port: u16 = std::env::var("PORT")
-        .ok()
-        .and_then(|p| p.parse().ok())
-        .unwrap_or(5000);
-
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let addr = SocketAddr::from(([0, 0, 0, 0], settings.server.port));
 
     info!("🚀 CodeCollab server v{} starting", env!("CARGO_PKG_VERSION"));
     info!("   Protocol version: {}", PROTOCOL_VERSION);
-    info!("   Listening on: http://{}", addr);
-    info!("   WebSocket: ws://{}/ws/:project_id", addr);
-    info!("   Health check: http://{}/health", addr);
+    let (http, ws) = if tls_config.is_some() {
+        ("https", "wss")
+    } else {
+        ("http", "ws")
+    };
+    info!("   Listening on: {}://{}", http, addr);
+    info!("   WebSocket: {}://{}/ws/:project_id", ws, addr);
+    info!("   Health check: {}://{}/health", http, addr);
+
+    match tls_config {
+        Some(tls_config) => serve_tls(app, addr, tls_config).await,
+        None => {
+            let listener = tokio::net::TcpListener::bind(addr)
+                .await
+                .expect("Failed to bind to address");
+
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .expect("Server error");
+        }
+    }
+}
 
-    let listener = tokio::net::TcpListener::bind(addr)
+/// Serve HTTPS (HTTP/1.1 or HTTP/2 through ALPN), answering ACME challenges
+/// on a plain HTTP port while a certificate is being issued
+async fn serve_tls(app: Router, addr: SocketAddr, config: tls::TlsConfig) {
+    let challenges = tls::Challenges::default();
+    if let tls::TlsConfig::Acme(acme) = &config {
+        let http_addr = SocketAddr::from(([0, 0, 0, 0], acme.http_port));
+        let listener = tokio::net::TcpListener::bind(http_addr)
+            .await
+            .expect("Failed to bind ACME challenge port");
+        info!("   ACME challenges: http://{}", http_addr);
+
+        let router = tls::challenge_router(challenges.clone());
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, router).await {
+                error!("ACME challenge server error: {}", e);
+            }
+        });
+    }
+
+    let rustls = tls::load(&config, &challenges)
         .await
-        .expect("Failed to bind to address");
+        .expect("Failed to load TLS certificate");
+    let _renewal = tls::spawn_renewal(config, rustls.clone(), challenges);
 
-    axum::serve(listener, app).await.expect("Server error");
+    axum_server::bind_rustls(addr, rustls)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .expect("Server error");
 }
@@ -0,0 +1,789 @@
+//! Notifications about project events.
+//!
+//! The sync server publishes [`ProjectEvent`]s (a project was created or
+//! saved, a file was created or deleted, a peer joined, the host left). The
+//! [`Notifier`] sends each one to the sinks configured for the whole server
+//! and to those a project configured for itself: generic JSON webhooks,
+//! optionally signed with a shared secret, Slack-compatible incoming webhooks
+//! and email through an SMTP relay. Delivery happens in the background;
+//! webhooks that fail for a reason that may pass are retried with a growing
+//! delay, and what still fails is logged and counted, never reported to the
+//! peer that caused the event.
+//!
+//! Sinks set up by projects may only point at public hosts; their hosts are
+//! checked when they are saved and again before each delivery, which then
+//! connects only to the addresses that were checked.
+
+use hmac::{Hmac, Mac};
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Address, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, warn};
+
+use crate::egress;
+use crate::storage::{DocumentStore, NotificationSink, ProjectEventKind};
+use crate::telemetry;
+
+/// Notification settings for the whole server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotifyConfig {
+    /// Sinks told about the events of every project
+    pub sinks: Vec<NotificationSink>,
+    /// Events the server's sinks are told about; all of them if empty
+    pub events: Vec<ProjectEventKind>,
+    /// Seconds to wait for a webhook or the SMTP server
+    pub timeout_secs: u64,
+    /// Times a failed webhook is tried again
+    pub retries: u32,
+    /// Milliseconds before the first retry; each further one waits twice as
+    /// long
+    pub retry_delay_ms: u64,
+    /// Relay for email sinks; email is disabled without one
+    pub smtp: Option<SmtpConfig>,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            sinks: Vec::new(),
+            events: vec![
+                ProjectEventKind::ProjectCreated,
+                ProjectEventKind::PeerJoined,
+                ProjectEventKind::HostLeft,
+            ],
+            timeout_secs: 10,
+            retries: 3,
+            retry_delay_ms: 1000,
+            smtp: None,
+        }
+    }
+}
+
+/// SMTP relay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SmtpConfig {
+    pub host: String,
+    /// Defaults to the standard port of the security mode
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Sender, e.g. `CodeCollab <collab@example.com>`
+    pub from: String,
+}
+
+/// How the SMTP connection is secured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// TLS from the start (port 465)
+    Tls,
+    /// Upgrade with STARTTLS (port 587)
+    #[default]
+    StartTls,
+    /// Plain text, for a relay on the local machine (port 25)
+    None,
+}
+
+/// Something that happened in a project
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProjectEvent {
+    ProjectCreated {
+        project_id: String,
+        name: String,
+    },
+    PeerJoined {
+        project_id: String,
+        peer_id: String,
+        peer_name: String,
+    },
+    HostLeft {
+        project_id: String,
+        peer_id: String,
+        peer_name: String,
+    },
+    ProjectSaved {
+        project_id: String,
+    },
+    FileCreated {
+        project_id: String,
+        path: String,
+    },
+    FileDeleted {
+        project_id: String,
+        path: String,
+    },
+}
+
+impl ProjectEvent {
+    pub fn kind(&self) -> ProjectEventKind {
+        match self {
+            Self::ProjectCreated { .. } => ProjectEventKind::ProjectCreated,
+            Self::PeerJoined { .. } => ProjectEventKind::PeerJoined,
+            Self::HostLeft { .. } => ProjectEventKind::HostLeft,
+            Self::ProjectSaved { .. } => ProjectEventKind::ProjectSaved,
+            Self::FileCreated { .. } => ProjectEventKind::FileCreated,
+            Self::FileDeleted { .. } => ProjectEventKind::FileDeleted,
+        }
+    }
+
+    pub fn project_id(&self) -> &str {
+        match self {
+            Self::ProjectCreated { project_id, .. }
+            | Self::PeerJoined { project_id, .. }
+            | Self::HostLeft { project_id, .. }
+            | Self::ProjectSaved { project_id }
+            | Self::FileCreated { project_id, .. }
+            | Self::FileDeleted { project_id, .. } => project_id,
+        }
+    }
+
+    /// One-line description, naming the project `project`
+    pub fn describe(&self, project: &str) -> String {
+        match self {
+            Self::ProjectCreated { .. } => format!("Project \"{}\" was created", project),
+            Self::PeerJoined { peer_name, .. } => {
+                format!("{} joined \"{}\"", peer_name, project)
+            }
+            Self::HostLeft { peer_name, .. } => format!(
+                "{} stopped hosting \"{}\"; its files are unavailable until they reconnect",
+                peer_name, project
+            ),
+            Self::ProjectSaved { .. } => format!("\"{}\" was saved", project),
+            Self::FileCreated { path, .. } => format!("{} was created in \"{}\"", path, project),
+            Self::FileDeleted { path, .. } => format!("{} was deleted from \"{}\"", path, project),
+        }
+    }
+}
+
+/// Errors delivering a notification
+#[derive(Error, Debug)]
+pub enum NotifyError {
+    #[error("Request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Webhook answered with status {0}")]
+    Status(u16),
+
+    #[error("Email failed: {0}")]
+    Email(String),
+
+    #[error("Email is not configured on this server")]
+    NoSmtp,
+
+    #[error("Refusing to connect: {0}")]
+    Blocked(String),
+}
+
+impl NotifyError {
+    /// Whether trying again later may succeed: the webhook could not be
+    /// reached, was overloaded or failed on its side
+    fn is_transient(&self) -> bool {
+        match self {
+            Self::Http(_) => true,
+            Self::Status(status) => *status == 429 || *status >= 500,
+            Self::Email(_) | Self::NoSmtp | Self::Blocked(_) => false,
+        }
+    }
+}
+
+/// Sends project events to the configured sinks
+pub struct Notifier {
+    storage: Arc<DocumentStore>,
+    sinks: Vec<NotificationSink>,
+    events: Vec<ProjectEventKind>,
+    retries: u32,
+    retry_delay: Duration,
+    timeout: Duration,
+    http: reqwest::Client,
+    mailer: Option<(AsyncSmtpTransport<Tokio1Executor>, Mailbox)>,
+}
+
+impl Notifier {
+    pub fn new(storage: Arc<DocumentStore>, config: &NotifyConfig) -> Result<Self, NotifyError> {
+        let timeout = Duration::from_secs(config.timeout_secs);
+        let http = http_client(timeout).build()?;
+        let mailer = config
+            .smtp
+            .as_ref()
+            .map(|smtp| smtp_transport(smtp, timeout))
+            .transpose()?;
+
+        Ok(Self {
+            storage,
+            sinks: config.sinks.clone(),
+            events: config.events.clone(),
+            retries: config.retries,
+            retry_delay: Duration::from_millis(config.retry_delay_ms),
+            timeout,
+            http,
+            mailer,
+        })
+    }
+
+    /// Whether email sinks can be delivered
+    pub fn email_enabled(&self) -> bool {
+        self.mailer.is_some()
+    }
+
+    /// Check a sink before a project saves it
+    pub async fn validate_sink(&self, sink: &NotificationSink) -> Result<(), String> {
+        match sink {
+            NotificationSink::Webhook { url } | NotificationSink::Slack { url } => {
+                validate_url(url).await
+            }
+            NotificationSink::SignedWebhook { url, secret } => {
+                if secret.is_empty() {
+                    return Err("Signed webhooks need a secret".to_string());
+                }
+                validate_url(url).await
+            }
+            NotificationSink::Email { to } => {
+                if !self.email_enabled() {
+                    return Err(NotifyError::NoSmtp.to_string());
+                }
+                if to.is_empty() {
+                    return Err("Email sinks need at least one address".to_string());
+                }
+                match to
+                    .iter()
+                    .find(|address| address.parse::<Address>().is_err())
+                {
+                    Some(address) => Err(format!("'{}' is not an email address", address)),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// Deliver events as the sync server publishes them, until it shuts down
+    pub async fn run(self: Arc<Self>, mut events: broadcast::Receiver<ProjectEvent>) {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    let notifier = self.clone();
+                    tokio::spawn(async move { notifier.notify(&event).await });
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    warn!("Notifications fell behind, {} events dropped", missed);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// Send an event to the server's sinks and the project's own
+    pub async fn notify(&self, event: &ProjectEvent) {
+        let project_id = event.project_id();
+        let mut sinks = if self.events.is_empty() || self.events.contains(&event.kind()) {
+            self.sinks.clone()
+        } else {
+            Vec::new()
+        };
+        let server_sinks = sinks.len();
+        match self.storage.get_notifications(project_id) {
+            Ok(Some(settings))
+                if settings.events.is_empty() || settings.events.contains(&event.kind()) =>
+            {
+                sinks.extend(settings.sinks);
+            }
+            Ok(_) => {}
+            Err(e) => warn!(
+                "Failed to load notification settings of {}: {}",
+                project_id, e
+            ),
+        }
+        if sinks.is_empty() {
+            return;
+        }
+
+        let project = self
+            .storage
+            .get_metadata(project_id)
+            .ok()
+            .flatten()
+            .map(|meta| meta.name)
+            .unwrap_or_else(|| project_id.to_string());
+        let text = event.describe(&project);
+
+        for (index, sink) in sinks.iter().enumerate() {
+            let label = sink_label(sink);
+            // A project's webhook host may resolve elsewhere since it was saved
+            let result = match sink {
+                NotificationSink::Webhook { url }
+                | NotificationSink::SignedWebhook { url, .. }
+                | NotificationSink::Slack { url }
+                    if index >= server_sinks =>
+                {
+                    match self.pinned_client(url).await {
+                        Ok(http) => self.deliver(&http, sink, event, &text).await,
+                        Err(e) => Err(NotifyError::Blocked(e)),
+                    }
+                }
+                _ => self.deliver(&self.http, sink, event, &text).await,
+            };
+            match result {
+                Ok(()) => {
+                    debug!(
+                        "Sent {:?} notification for {} to {}",
+                        event.kind(),
+                        project_id,
+                        label
+                    );
+                    metrics::counter!(telemetry::NOTIFICATIONS_SENT, "sink" => label, "result" => "ok")
+                        .increment(1);
+                }
+                Err(e) => {
+                    warn!("Failed to notify {} about {}: {}", label, project_id, e);
+                    metrics::counter!(telemetry::NOTIFICATIONS_SENT, "sink" => label, "result" => "error")
+                        .increment(1);
+                }
+            }
+        }
+    }
+
+    /// A client for a project's webhook that only connects to the public
+    /// addresses its host has now
+    async fn pinned_client(&self, url: &str) -> Result<reqwest::Client, String> {
+        let host = webhook_host(url)?;
+        let addresses: Vec<SocketAddr> = egress::resolve_public_host(&host)
+            .await?
+            .into_iter()
+            .map(|ip| SocketAddr::new(ip, 0))
+            .collect();
+        http_client(self.timeout)
+            .resolve_to_addrs(&host, &addresses)
+            .build()
+            .map_err(|e| e.to_string())
+    }
+
+    async fn deliver(
+        &self,
+        http: &reqwest::Client,
+        sink: &NotificationSink,
+        event: &ProjectEvent,
+        text: &str,
+    ) -> Result<(), NotifyError> {
+        match sink {
+            NotificationSink::Webhook { url } => {
+                self.post(http, url, &webhook_payload(event, text), None)
+                    .await
+            }
+            NotificationSink::SignedWebhook { url, secret } => {
+                self.post(http, url, &webhook_payload(event, text), Some(secret))
+                    .await
+            }
+            NotificationSink::Slack { url } => {
+                self.post(http, url, &serde_json::json!({ "text": text }), None)
+                    .await
+            }
+            NotificationSink::Email { to } => {
+                let (mailer, from) = self.mailer.as_ref().ok_or(NotifyError::NoSmtp)?;
+                let mut message = Message::builder().from(from.clone()).subject(text);
+                for address in to {
+                    let address = address
+                        .parse::<Address>()
+                        .map_err(|e| NotifyError::Email(e.to_string()))?;
+                    message = message.to(Mailbox::new(None, address));
+                }
+                let message = message
+                    .body(text.to_string())
+                    .map_err(|e| NotifyError::Email(e.to_string()))?;
+                mailer
+                    .send(message)
+                    .await
+                    .map_err(|e| NotifyError::Email(e.to_string()))?;
+                Ok(())
+            }
+        }
+    }
+
+    /// POST a JSON body, signed if there is a secret, retrying failures
+    /// that may pass
+    async fn post(
+        &self,
+        http: &reqwest::Client,
+        url: &str,
+        body: &serde_json::Value,
+        secret: Option<&str>,
+    ) -> Result<(), NotifyError> {
+        let body = body.to_string();
+        let mut delay = self.retry_delay;
+        let mut retries = 0;
+        loop {
+            match self.post_once(http, url, &body, secret).await {
+                Err(e) if retries < self.retries && e.is_transient() => {
+                    debug!("Webhook {} failed ({}), retrying in {:?}", url, e, delay);
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+
+    async fn post_once(
+        &self,
+        http: &reqwest::Client,
+        url: &str,
+        body: &str,
+        secret: Option<&str>,
+    ) -> Result<(), NotifyError> {
+        let mut request = http
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let Some(secret) = secret {
+            let timestamp = chrono::Utc::now().timestamp();
+            request = request
+                .header(TIMESTAMP_HEADER, timestamp)
+                .header(SIGNATURE_HEADER, sign_request(secret, timestamp, body));
+        }
+        let response = request.body(body.to_string()).send().await?;
+        if !response.status().is_success() {
+            return Err(NotifyError::Status(response.status().as_u16()));
+        }
+        Ok(())
+    }
+}
+
+/// Settings shared by every client that delivers webhooks
+fn http_client(timeout: Duration) -> reqwest::ClientBuilder {
+    // A redirect could lead a webhook past the check on its host
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .redirect(reqwest::redirect::Policy::none())
+}
+
+/// Header carrying the signature of a signed webhook request
+pub const SIGNATURE_HEADER: &str = "X-Collab-Signature";
+
+/// Header carrying the time a signed webhook request was sent, in seconds
+/// since the epoch. It is covered by the signature, so receivers can reject
+/// requests that are replayed later.
+pub const TIMESTAMP_HEADER: &str = "X-Collab-Timestamp";
+
+/// Signature of a webhook body: `sha256=` and the hex HMAC-SHA256 of the
+/// body under the sink's secret, the way GitHub signs its webhooks
+fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Signature of a signed webhook request: that of `<timestamp>.<body>`
+fn sign_request(secret: &str, timestamp: i64, body: &str) -> String {
+    sign(secret, &format!("{}.{}", timestamp, body))
+}
+
+/// Host of a webhook URL, which must be http(s)
+fn webhook_host(url: &str) -> Result<String, String> {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {
+            parsed.host_str().map(str::to_string)
+        }
+        _ => None,
+    }
+    .ok_or_else(|| format!("'{}' is not an http(s) URL", url))
+}
+
+/// Check that a webhook URL is http(s) and points at a public host
+async fn validate_url(url: &str) -> Result<(), String> {
+    egress::check_public_host(&webhook_host(url)?).await
+}
+
+/// Body of a generic webhook: the event's fields, a description and a timestamp
+fn webhook_payload(event: &ProjectEvent, text: &str) -> serde_json::Value {
+    let mut payload = serde_json::to_value(event).unwrap_or_default();
+    if let Some(fields) = payload.as_object_mut() {
+        fields.insert("text".to_string(), text.into());
+        fields.insert(
+            "timestamp".to_string(),
+            chrono::Utc::now().timestamp().into(),
+        );
+    }
+    payload
+}
+
+/// Metric label of a sink
+fn sink_label(sink: &NotificationSink) -> &'static str {
+    match sink {
+        NotificationSink::Webhook { .. } | NotificationSink::SignedWebhook { .. } => "webhook",
+        NotificationSink::Slack { .. } => "slack",
+        NotificationSink::Email { .. } => "email",
+    }
+}
+
+fn smtp_transport(
+    config: &SmtpConfig,
+    timeout: Duration,
+) -> Result<(AsyncSmtpTransport<Tokio1Executor>, Mailbox), NotifyError> {
+    let from = config
+        .from
+        .parse::<Mailbox>()
+        .map_err(|e| NotifyError::Email(format!("Invalid sender '{}': {}", config.from, e)))?;
+
+    let mut builder = match config.security {
+        SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host),
+        SmtpSecurity::StartTls => {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+        }
+        SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+            &config.host,
+        )),
+    }
+    .map_err(|e| NotifyError::Email(e.to_string()))?
+    .timeout(Some(timeout));
+    if let Some(port) = config.port {
+        builder = builder.port(port);
+    }
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+
+    Ok((builder.build(), from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StorageConfig;
+
+    fn test_notifier(config: &NotifyConfig) -> (Notifier, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = DocumentStore::open(StorageConfig::new(
+            dir.path().join("test.sled").to_string_lossy().to_string(),
+        ))
+        .unwrap();
+        (Notifier::new(Arc::new(storage), config).unwrap(), dir)
+    }
+
+    #[test]
+    fn test_webhook_payload() {
+        let event = ProjectEvent::PeerJoined {
+            project_id: "proj".to_string(),
+            peer_id: "peer-1".to_string(),
+            peer_name: "Alice".to_string(),
+        };
+        let text = event.describe("Demo");
+        assert_eq!(text, "Alice joined \"Demo\"");
+
+        let payload = webhook_payload(&event, &text);
+        assert_eq!(payload["event"], "peer_joined");
+        assert_eq!(payload["project_id"], "proj");
+        assert_eq!(payload["peer_name"], "Alice");
+        assert_eq!(payload["text"], text);
+        assert!(payload["timestamp"].is_i64());
+    }
+
+    #[test]
+    fn test_sign() {
+        // The example from GitHub's webhook documentation
+        assert_eq!(
+            sign("It's a Secret to Everybody", "Hello, World!"),
+            "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17"
+        );
+        assert_eq!(
+            sign_request("s3cret", 1700000000, "{}"),
+            sign("s3cret", "1700000000.{}")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_signed_webhook_is_retried() {
+        use axum::http::{HeaderMap, StatusCode};
+        use std::sync::Mutex;
+
+        // Fails the first request, then records the timestamp, signature
+        // and body
+        let received: Arc<Mutex<Vec<(i64, String, String)>>> = Arc::default();
+        let recorder = received.clone();
+        let app = axum::Router::new().route(
+            "/hook",
+            axum::routing::post(move |headers: HeaderMap, body: String| {
+                let recorder = recorder.clone();
+                async move {
+                    let mut received = recorder.lock().unwrap();
+                    let timestamp = headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+                    let signature = headers[SIGNATURE_HEADER].to_str().unwrap().to_string();
+                    received.push((timestamp, signature, body));
+                    if received.len() == 1 {
+                        StatusCode::SERVICE_UNAVAILABLE
+                    } else {
+                        StatusCode::OK
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let sink = NotificationSink::SignedWebhook {
+            url,
+            secret: "s3cret".to_string(),
+        };
+        let (notifier, _dir) = test_notifier(&NotifyConfig {
+            sinks: vec![sink.clone()],
+            retry_delay_ms: 10,
+            ..Default::default()
+        });
+        let event = ProjectEvent::FileCreated {
+            project_id: "proj".to_string(),
+            path: "src/main.rs".to_string(),
+        };
+
+        // Not among the events the server's sinks are told about by default
+        notifier.notify(&event).await;
+        assert!(received.lock().unwrap().is_empty());
+
+        notifier
+            .deliver(&notifier.http, &sink, &event, "created")
+            .await
+            .unwrap();
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        let now = chrono::Utc::now().timestamp();
+        for (timestamp, signature, body) in received.iter() {
+            assert!((now - timestamp).abs() <= 5);
+            assert_eq!(signature, &sign_request("s3cret", *timestamp, body));
+            let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+            assert_eq!(payload["event"], "file_created");
+            assert_eq!(payload["path"], "src/main.rs");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_project_sinks_only_reach_public_hosts() {
+        use crate::storage::ProjectNotifications;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = axum::Router::new().route(
+            "/hook",
+            axum::routing::post(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async {}
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let sink = NotificationSink::Webhook { url };
+        let event = ProjectEvent::ProjectSaved {
+            project_id: "proj".to_string(),
+        };
+
+        // Saved by a project before its host started resolving locally
+        let (notifier, _dir) = test_notifier(&NotifyConfig::default());
+        notifier
+            .storage
+            .save_notifications(&ProjectNotifications {
+                project_id: "proj".to_string(),
+                sinks: vec![sink.clone()],
+                events: Vec::new(),
+            })
+            .unwrap();
+        notifier.notify(&event).await;
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+        assert!(notifier.pinned_client("http://127.0.0.1/hook").await.is_err());
+        assert!(notifier
+            .pinned_client("https://93.184.216.34/hook")
+            .await
+            .is_ok());
+
+        // The server's own sinks may be internal
+        let (notifier, _dir) = test_notifier(&NotifyConfig {
+            sinks: vec![sink],
+            events: Vec::new(),
+            ..Default::default()
+        });
+        notifier.notify(&event).await;
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    // The SMTP connection pool needs a runtime
+    #[tokio::test]
+    async fn test_validate_sink() {
+        let (notifier, _dir) = test_notifier(&NotifyConfig::default());
+        let webhook = |url: &str| NotificationSink::Webhook {
+            url: url.to_string(),
+        };
+
+        assert!(notifier
+            .validate_sink(&webhook("https://93.184.216.34/hook"))
+            .await
+            .is_ok());
+        assert!(notifier
+            .validate_sink(&webhook("ftp://93.184.216.34"))
+            .await
+            .is_err());
+        assert!(notifier.validate_sink(&webhook("not a url")).await.is_err());
+
+        // Nothing on the server's own network
+        for url in [
+            "http://127.0.0.1:8080/hook",
+            "http://localhost/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://10.0.0.5/hook",
+            "http://[::1]/hook",
+        ] {
+            assert!(notifier.validate_sink(&webhook(url)).await.is_err(), "{}", url);
+        }
+        assert!(notifier
+            .validate_sink(&NotificationSink::SignedWebhook {
+                url: "http://192.168.1.1/hook".to_string(),
+                secret: "s3cret".to_string(),
+            })
+            .await
+            .is_err());
+        assert!(notifier
+            .validate_sink(&NotificationSink::SignedWebhook {
+                url: "https://93.184.216.34/hook".to_string(),
+                secret: String::new(),
+            })
+            .await
+            .is_err());
+
+        // Email needs an SMTP relay
+        let email = NotificationSink::Email {
+            to: vec!["team@example.com".to_string()],
+        };
+        assert!(notifier.validate_sink(&email).await.is_err());
+
+        let (notifier, _dir) = test_notifier(&NotifyConfig {
+            smtp: Some(SmtpConfig {
+                host: "smtp.example.com".to_string(),
+                port: None,
+                security: SmtpSecurity::StartTls,
+                username: None,
+                password: None,
+                from: "CodeCollab <collab@example.com>".to_string(),
+            }),
+            ..Default::default()
+        });
+        assert!(notifier.validate_sink(&email).await.is_ok());
+        assert!(notifier
+            .validate_sink(&NotificationSink::Email {
+                to: vec!["nobody".to_string()]
+            })
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! Origin allowlist for browser clients.
+//!
+//! Browsers send an `Origin` header with cross-origin requests and WebSocket
+//! upgrades. Requests from origins outside the allowlist are rejected, and
+//! CORS headers are only sent for listed origins. Requests without the header
+//! (the desktop client's native socket, curl) pass through: the check only
+//! protects browser users from pages on other sites.
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tracing::warn;
+
+/// Which origins may talk to the server
+#[derive(Debug, Clone, Default)]
+pub struct OriginPolicy {
+    /// Allowed origins, normalized (`scheme://host[:port]`)
+    allowed: Vec<String>,
+    /// Allow every origin (development mode)
+    permissive: bool,
+}
+
+impl OriginPolicy {
+    /// Allow every origin
+    pub fn permissive() -> Self {
+        Self {
+            allowed: Vec::new(),
+            permissive: true,
+        }
+    }
+
+    /// Allow only the given origins
+    pub fn allow<I, S>(origins: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut allowed: Vec<String> = origins
+            .into_iter()
+            .map(|origin| normalize(origin.as_ref()))
+            .filter(|origin| !origin.is_empty())
+            .collect();
+        allowed.sort();
+        allowed.dedup();
+        Self {
+            allowed,
+            permissive: false,
+        }
+    }
+
+    /// The allowed origins, empty if permissive
+    pub fn allowed(&self) -> &[String] {
+        &self.allowed
+    }
+
+    /// Whether requests from an origin are allowed
+    pub fn is_allowed(&self, origin: &str) -> bool {
+        self.permissive || self.allowed.contains(&normalize(origin))
+    }
+
+    /// CORS layer answering preflight requests for the allowed origins
+    pub fn cors_layer(&self) -> CorsLayer {
+        let allow_origin = if self.permissive {
+            AllowOrigin::from(Any)
+        } else {
+            AllowOrigin::list(
+                self.allowed
+                    .iter()
+                    .filter_map(|origin| HeaderValue::from_str(origin).ok()),
+            )
+        };
+
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([
+                Method::GET,
+                Method::POST,
+                Method::PUT,
+                Method::PATCH,
+                Method::DELETE,
+                Method::OPTIONS,
+            ])
+            .allow_headers(Any)
+    }
+}
+
+/// Reject requests, including WebSocket upgrades, whose `Origin` is not
+/// allowed
+pub async fn check_origin(
+    State(policy): State<Arc<OriginPolicy>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, String)> {
+    if let Some(origin) = request.headers().get(header::ORIGIN) {
+        let origin = origin.to_str().unwrap_or_default();
+        if !policy.is_allowed(origin) {
+            warn!(
+                "Rejected request to {} from origin {}",
+                request.uri().path(),
+                origin
+            );
+            return Err((
+                StatusCode::FORBIDDEN,
+                format!(
+                    "Origin '{}' is not allowed. Add it to ALLOWED_ORIGINS on the server.",
+                    origin
+                ),
+            ));
+        }
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Split a list of origins separated by commas or newlines, skipping `#`
+/// comments
+pub(crate) fn parse_list(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .flat_map(|line| line.split(','))
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn normalize(origin: &str) -> String {
+    origin.trim().trim_end_matches('/').to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowlist() {
+        let policy = OriginPolicy::allow(["http://localhost:3000/", "https://Collab.Example.com"]);
+
+        assert!(policy.is_allowed("http://localhost:3000"));
+        assert!(policy.is_allowed("https://collab.example.com"));
+        assert!(!policy.is_allowed("http://localhost:3001"));
+        assert!(!policy.is_allowed("https://evil.example.com"));
+        assert!(!policy.is_allowed("null"));
+
+        assert!(OriginPolicy::permissive().is_allowed("https://evil.example.com"));
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let file = "# Web client\nhttp://localhost:3000\n\ntauri://localhost, http://tauri.localhost # desktop\n";
+        assert_eq!(
+            parse_list(file),
+            vec![
+                "http://localhost:3000",
+                "tauri://localhost",
+                "http://tauri.localhost"
+            ]
+        );
+        assert!(parse_list("").is_empty());
+    }
+}
@@ -13,7 +13,7 @@ use tokio::sync::RwLock;
 use tracing::{error, info};
 
 use super::file_tree::{FileNode, FileTree, FileTreeError};
-use super::{detect_language, is_binary_extension, FileOperation, ScanOptions, ScanResult};
+use super::{detect_language_with_content, is_binary_extension, FileOperation, ScanOptions, ScanResult};
 
 /// State of a collaboration room
 #[derive(Debug, Clone)]
@@ -236,7 +236,7 @@ impl RoomManager {
             .await
             .map_err(|e| RoomError::Io(e.to_string()))?;
 
-        let language = detect_language(file_path);
+        let language = detect_language_with_content(file_path, &content);
         let metadata = tokio::fs::metadata(&local_path)
             .await
             .map_err(|e| RoomError::Io(e.to_string()))?;
@@ -489,7 +489,7 @@ pub enum RoomError {
 }
 
 /// Scan a directory and build a file tree
-fn scan_directory_tree(
+pub fn scan_directory_tree(
     base_path: &Path,
     root_name: &str,
     options: &ScanOptions,
@@ -27,6 +27,9 @@ pub struct DocumentMetadata {
     pub size_bytes: u64,
     /// Owner/creator user ID
     pub owner_id: Option<String>,
+    /// Archived projects can't be joined, are hidden from the default
+    /// listing, and may be moved to cold storage
+    pub archived: bool,
 }
 
 impl DocumentMetadata {
@@ -40,6 +43,7 @@ impl DocumentMetadata {
             change_count: 0,
             size_bytes: 0,
             owner_id: None,
+            archived: false,
         }
     }
 
@@ -49,6 +53,221 @@ impl DocumentMetadata {
     }
 }
 
+/// A user's role in a project, from least to most capable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectRole {
+    /// Read-only access
+    Viewer,
+    /// May edit files
+    Editor,
+    /// May also grant and revoke access
+    Owner,
+}
+
+/// Who may access a project.
+///
+/// Projects without an access list are open to everyone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectAcl {
+    pub project_id: String,
+    /// User ID of the owner
+    pub owner_id: String,
+    /// User IDs of editors
+    pub editors: Vec<String>,
+    /// User IDs of viewers
+    pub viewers: Vec<String>,
+}
+
+impl ProjectAcl {
+    pub fn new(project_id: impl Into<String>, owner_id: impl Into<String>) -> Self {
+        Self {
+            project_id: project_id.into(),
+            owner_id: owner_id.into(),
+            editors: Vec::new(),
+            viewers: Vec::new(),
+        }
+    }
+
+    /// Get a user's role, None if they have no access
+    pub fn role_of(&self, user_id: &str) -> Option<ProjectRole> {
+        if self.owner_id == user_id {
+            Some(ProjectRole::Owner)
+        } else if self.editors.iter().any(|id| id == user_id) {
+            Some(ProjectRole::Editor)
+        } else if self.viewers.iter().any(|id| id == user_id) {
+            Some(ProjectRole::Viewer)
+        } else {
+            None
+        }
+    }
+
+    /// Give a user the editor or viewer role, replacing any role they had.
+    ///
+    /// Returns false for the owner, whose role cannot change.
+    pub fn grant(&mut self, user_id: &str, role: ProjectRole) -> bool {
+        if self.owner_id == user_id || role == ProjectRole::Owner {
+            return false;
+        }
+        self.revoke(user_id);
+        match role {
+            ProjectRole::Editor => self.editors.push(user_id.to_string()),
+            _ => self.viewers.push(user_id.to_string()),
+        }
+        true
+    }
+
+    /// Take away a user's access, returning whether they had any.
+    ///
+    /// The owner's access cannot be revoked.
+    pub fn revoke(&mut self, user_id: &str) -> bool {
+        let before = self.editors.len() + self.viewers.len();
+        self.editors.retain(|id| id != user_id);
+        self.viewers.retain(|id| id != user_id);
+        self.editors.len() + self.viewers.len() != before
+    }
+}
+
+/// What a share link lets its holder do in a project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShareRole {
+    /// Read-only access
+    Viewer,
+    /// May edit, within the link's path scope
+    Editor,
+}
+
+/// A share link handed out for a project, kept until revoked
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareLink {
+    /// Unique link identifier (also embedded in the signed token)
+    pub link_id: String,
+    pub project_id: String,
+    pub role: ShareRole,
+    /// Paths (files or folders) the link is limited to; empty for the whole project
+    pub paths: Vec<String>,
+    /// Unix timestamp of creation
+    pub created_at: i64,
+    /// Unix timestamp after which the link no longer works
+    pub expires_at: i64,
+    /// Peer name of whoever created the link
+    pub created_by: Option<String>,
+}
+
+/// Project events notifications can be sent for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectEventKind {
+    ProjectCreated,
+    PeerJoined,
+    HostLeft,
+    ProjectSaved,
+    FileCreated,
+    FileDeleted,
+}
+
+/// Where notifications are sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationSink {
+    /// POST a JSON description of the event
+    Webhook { url: String },
+    /// POST a Slack-compatible `{"text": ...}` message (incoming webhook)
+    Slack { url: String },
+    /// Email the addresses through the server's SMTP relay
+    Email { to: Vec<String> },
+    /// Like `Webhook`, with the body signed under `secret` so the receiver
+    /// can check it came from this server
+    SignedWebhook { url: String, secret: String },
+}
+
+/// Notification settings of a project
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectNotifications {
+    pub project_id: String,
+    pub sinks: Vec<NotificationSink>,
+    /// Events to notify about; all of them if empty
+    pub events: Vec<ProjectEventKind>,
+}
+
+/// Git repository a project is exported to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectGit {
+    pub project_id: String,
+    /// Bare repositories only get commits; others get the files checked out
+    pub bare: bool,
+    /// Branch commits go to
+    pub branch: String,
+    /// Where commits are pushed, if anywhere
+    pub remote: Option<GitRemote>,
+    /// Commit on the server's schedule, not only when asked to
+    pub auto_commit: bool,
+}
+
+/// Remote repository a project's commits are pushed to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitRemote {
+    pub url: String,
+    /// User for HTTPS remotes; many hosts accept any with a token
+    pub username: Option<String>,
+    /// Password or access token for HTTPS remotes
+    pub token: Option<String>,
+}
+
+/// A chat message kept for a project's history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatRecord {
+    /// Unique message identifier; sorts in the order messages were sent
+    pub message_id: String,
+    pub project_id: String,
+    pub peer_id: String,
+    pub peer_name: String,
+    pub content: String,
+    /// Unix timestamp the message was sent
+    pub timestamp: i64,
+}
+
+/// What a peer did, as kept in the audit log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEvent {
+    Joined,
+    Left,
+    /// The connection dropped without leaving first
+    Disconnected,
+    OpenedFile { path: String },
+}
+
+/// An entry of the append-only log of who was in which project
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Unix timestamp of the event
+    pub timestamp: i64,
+    pub project_id: String,
+    pub peer_id: String,
+    pub peer_name: String,
+    pub user_id: Option<String>,
+    /// Address the peer connected from
+    pub ip: Option<String>,
+    pub event: AuditEvent,
+}
+
+/// A registered user account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserAccount {
+    /// Unique user identifier
+    pub user_id: String,
+    /// Login name, unique regardless of case
+    pub username: String,
+    /// Name shown to other peers
+    pub display_name: String,
+    /// Argon2 hash in PHC string format
+    pub password_hash: String,
+    /// Unix timestamp of registration
+    pub created_at: i64,
+}
+
 /// Incremental change record for efficient sync
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChangeRecord {
@@ -120,6 +339,26 @@ mod tests {
         assert!(meta.created_at > 0);
     }
 
+    #[test]
+    fn test_acl_roles() {
+        let mut acl = ProjectAcl::new("project-123", "owner");
+        assert_eq!(acl.role_of("owner"), Some(ProjectRole::Owner));
+        assert_eq!(acl.role_of("alice"), None);
+
+        assert!(acl.grant("alice", ProjectRole::Viewer));
+        assert_eq!(acl.role_of("alice"), Some(ProjectRole::Viewer));
+        assert!(acl.grant("alice", ProjectRole::Editor));
+        assert_eq!(acl.role_of("alice"), Some(ProjectRole::Editor));
+        assert!(acl.viewers.is_empty());
+
+        assert!(!acl.grant("owner", ProjectRole::Viewer));
+        assert!(!acl.grant("bob", ProjectRole::Owner));
+        assert!(!acl.revoke("owner"));
+        assert!(acl.revoke("alice"));
+        assert!(!acl.revoke("alice"));
+        assert!(ProjectRole::Viewer < ProjectRole::Editor && ProjectRole::Editor < ProjectRole::Owner);
+    }
+
     #[test]
     fn test_storage_config_default() {
         let config = StorageConfig::default();
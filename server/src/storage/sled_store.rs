@@ -12,7 +12,44 @@ use std::path::Path;
 use std::sync::Arc;
 use thiserror::Error;
 
-use super::{ChangeRecord, DocumentMetadata, StorageConfig};
+use crate::telemetry;
+
+use super::{
+    AuditRecord, ChangeRecord, ChatRecord, DocumentMetadata, ProjectAcl, ProjectGit,
+    ProjectNotifications, ShareLink, StorageConfig, UserAccount,
+};
+
+/// Metadata as stored before projects could be archived
+#[derive(serde::Deserialize)]
+struct MetadataV1 {
+    project_id: String,
+    name: String,
+    created_at: i64,
+    updated_at: i64,
+    change_count: u64,
+    size_bytes: u64,
+    owner_id: Option<String>,
+}
+
+/// Decode stored metadata, including records written before `archived`
+fn decode_metadata(bytes: &[u8]) -> StorageResult<DocumentMetadata> {
+    match bincode::deserialize::<DocumentMetadata>(bytes) {
+        Ok(meta) => Ok(meta),
+        Err(e) => match bincode::deserialize::<MetadataV1>(bytes) {
+            Ok(old) => Ok(DocumentMetadata {
+                project_id: old.project_id,
+                name: old.name,
+                created_at: old.created_at,
+                updated_at: old.updated_at,
+                change_count: old.change_count,
+                size_bytes: old.size_bytes,
+                owner_id: old.owner_id,
+                archived: false,
+            }),
+            Err(_) => Err(e.into()),
+        },
+    }
+}
 
 /// Errors that can occur during storage operations
 #[derive(Error, Debug)]
@@ -44,6 +81,15 @@ const TREE_DOCUMENTS: &str = "documents";
 const TREE_METADATA: &str = "metadata";
 const TREE_CHANGES: &str = "changes";
 const TREE_SYNC_STATES: &str = "sync_states";
+const TREE_SHARE_LINKS: &str = "share_links";
+const TREE_USERS: &str = "users";
+const TREE_USERNAMES: &str = "usernames";
+const TREE_IDENTITIES: &str = "identities";
+const TREE_ACLS: &str = "acls";
+const TREE_CHAT: &str = "chat";
+const TREE_NOTIFICATIONS: &str = "notifications";
+const TREE_AUDIT: &str = "audit";
+const TREE_GIT: &str = "git";
 
 /// Sled-based document store for Automerge documents
 #[derive(Clone)]
@@ -53,6 +99,15 @@ pub struct DocumentStore {
     metadata: Tree,
     changes: Tree,
     sync_states: Tree,
+    share_links: Tree,
+    users: Tree,
+    usernames: Tree,
+    identities: Tree,
+    acls: Tree,
+    chat: Tree,
+    notifications: Tree,
+    audit: Tree,
+    git: Tree,
     config: StorageConfig,
 }
 
@@ -77,11 +132,30 @@ impl DocumentStore {
                 None
             })
             .open()?;
+        Self::with_db(db, config)
+    }
+
+    /// Open a store that lives in memory and is gone once dropped
+    #[cfg(all(test, feature = "sim"))]
+    pub fn temporary() -> StorageResult<Self> {
+        let db = sled::Config::new().temporary(true).open()?;
+        Self::with_db(db, StorageConfig::default())
+    }
 
+    fn with_db(db: sled::Db, config: StorageConfig) -> StorageResult<Self> {
         let documents = db.open_tree(TREE_DOCUMENTS)?;
         let metadata = db.open_tree(TREE_METADATA)?;
         let changes = db.open_tree(TREE_CHANGES)?;
         let sync_states = db.open_tree(TREE_SYNC_STATES)?;
+        let share_links = db.open_tree(TREE_SHARE_LINKS)?;
+        let users = db.open_tree(TREE_USERS)?;
+        let usernames = db.open_tree(TREE_USERNAMES)?;
+        let identities = db.open_tree(TREE_IDENTITIES)?;
+        let acls = db.open_tree(TREE_ACLS)?;
+        let chat = db.open_tree(TREE_CHAT)?;
+        let notifications = db.open_tree(TREE_NOTIFICATIONS)?;
+        let audit = db.open_tree(TREE_AUDIT)?;
+        let git = db.open_tree(TREE_GIT)?;
 
         Ok(Self {
             db: Arc::new(db),
@@ -89,6 +163,15 @@ impl DocumentStore {
             metadata,
             changes,
             sync_states,
+            share_links,
+            users,
+            usernames,
+            identities,
+            acls,
+            chat,
+            notifications,
+            audit,
+            git,
             config,
         })
     }
@@ -100,6 +183,7 @@ impl DocumentStore {
 
     /// Store a complete Automerge document snapshot
     pub fn save_document(&self, project_id: &str, doc_bytes: &[u8]) -> StorageResult<()> {
+        let started = std::time::Instant::now();
         let data = if self.config.compression {
             compress_data(doc_bytes)
         } else {
@@ -115,6 +199,7 @@ impl DocumentStore {
             self.save_metadata(&meta)?;
         }
 
+        metrics::histogram!(telemetry::SAVE_DURATION).record(started.elapsed().as_secs_f64());
         Ok(())
     }
 
@@ -145,8 +230,11 @@ impl DocumentStore {
         // Delete document
         self.documents.remove(key)?;
 
-        // Delete metadata
+        // Delete metadata, access list, notification and git settings
         self.metadata.remove(key)?;
+        self.acls.remove(key)?;
+        self.notifications.remove(key)?;
+        self.git.remove(key)?;
 
         // Delete all changes for this project
         let change_prefix = format!("{}:", project_id);
@@ -170,6 +258,26 @@ impl DocumentStore {
             self.sync_states.remove(key)?;
         }
 
+        // Delete share links
+        let mut to_remove = Vec::new();
+        for item in self.share_links.scan_prefix(sync_prefix.as_bytes()) {
+            let (key, _) = item?;
+            to_remove.push(key);
+        }
+        for key in to_remove {
+            self.share_links.remove(key)?;
+        }
+
+        // Delete chat history
+        let mut to_remove = Vec::new();
+        for item in self.chat.scan_prefix(sync_prefix.as_bytes()) {
+            let (key, _) = item?;
+            to_remove.push(key);
+        }
+        for key in to_remove {
+            self.chat.remove(key)?;
+        }
+
         Ok(())
     }
 
@@ -183,10 +291,7 @@ impl DocumentStore {
     /// Load document metadata
     pub fn get_metadata(&self, project_id: &str) -> StorageResult<Option<DocumentMetadata>> {
         match self.metadata.get(project_id.as_bytes())? {
-            Some(bytes) => {
-                let meta: DocumentMetadata = bincode::deserialize(&bytes)?;
-                Ok(Some(meta))
-            }
+            Some(bytes) => Ok(Some(decode_metadata(&bytes)?)),
             None => Ok(None),
         }
     }
@@ -196,12 +301,26 @@ impl DocumentStore {
         let mut docs = Vec::new();
         for item in self.metadata.iter() {
             let (_, value) = item?;
-            let meta: DocumentMetadata = bincode::deserialize(&value)?;
-            docs.push(meta);
+            docs.push(decode_metadata(&value)?);
         }
         Ok(docs)
     }
 
+    /// Save a project's access list
+    pub fn save_acl(&self, acl: &ProjectAcl) -> StorageResult<()> {
+        let bytes = bincode::serialize(acl)?;
+        self.acls.insert(acl.project_id.as_bytes(), bytes)?;
+        Ok(())
+    }
+
+    /// Load a project's access list
+    pub fn get_acl(&self, project_id: &str) -> StorageResult<Option<ProjectAcl>> {
+        match self.acls.get(project_id.as_bytes())? {
+            Some(bytes) => Ok(Some(bincode::deserialize(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
     /// Store an incremental change
     pub fn save_change(&self, project_id: &str, change: &ChangeRecord) -> StorageResult<()> {
         let key = format!("{}:{:020}", project_id, change.seq);
@@ -294,6 +413,284 @@ impl DocumentStore {
         Ok(())
     }
 
+    /// Remove the sync states `keep` rejects, returning how many went
+    pub fn retain_sync_states(&self, keep: impl Fn(&[u8]) -> bool) -> StorageResult<usize> {
+        let mut to_remove = Vec::new();
+        for item in self.sync_states.iter() {
+            let (key, value) = item?;
+            if !keep(&value) {
+                to_remove.push(key);
+            }
+        }
+        for key in &to_remove {
+            self.sync_states.remove(key)?;
+        }
+        Ok(to_remove.len())
+    }
+
+    /// Save a project's notification settings
+    pub fn save_notifications(&self, settings: &ProjectNotifications) -> StorageResult<()> {
+        let bytes = bincode::serialize(settings)?;
+        self.notifications.insert(settings.project_id.as_bytes(), bytes)?;
+        Ok(())
+    }
+
+    /// Load a project's notification settings
+    pub fn get_notifications(
+        &self,
+        project_id: &str,
+    ) -> StorageResult<Option<ProjectNotifications>> {
+        match self.notifications.get(project_id.as_bytes())? {
+            Some(bytes) => Ok(Some(bincode::deserialize(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Save the git repository a project is exported to
+    pub fn save_git(&self, settings: &ProjectGit) -> StorageResult<()> {
+        let bytes = bincode::serialize(settings)?;
+        self.git.insert(settings.project_id.as_bytes(), bytes)?;
+        Ok(())
+    }
+
+    /// Load the git repository a project is exported to
+    pub fn get_git(&self, project_id: &str) -> StorageResult<Option<ProjectGit>> {
+        match self.git.get(project_id.as_bytes())? {
+            Some(bytes) => Ok(Some(bincode::deserialize(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Every project exported to git
+    pub fn list_git(&self) -> StorageResult<Vec<ProjectGit>> {
+        let mut settings = Vec::new();
+        for item in self.git.iter() {
+            let (_, value) = item?;
+            settings.push(bincode::deserialize(&value)?);
+        }
+        Ok(settings)
+    }
+
+    /// Stop exporting a project to git, returning whether it was
+    pub fn remove_git(&self, project_id: &str) -> StorageResult<bool> {
+        Ok(self.git.remove(project_id.as_bytes())?.is_some())
+    }
+
+    /// Save a project share link
+    pub fn save_share_link(&self, link: &ShareLink) -> StorageResult<()> {
+        let key = format!("{}:{}", link.project_id, link.link_id);
+        let bytes = bincode::serialize(link)?;
+        self.share_links.insert(key.as_bytes(), bytes)?;
+        Ok(())
+    }
+
+    /// Load a project share link
+    pub fn get_share_link(&self, project_id: &str, link_id: &str) -> StorageResult<Option<ShareLink>> {
+        let key = format!("{}:{}", project_id, link_id);
+        match self.share_links.get(key.as_bytes())? {
+            Some(bytes) => Ok(Some(bincode::deserialize(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// List the share links of a project
+    pub fn list_share_links(&self, project_id: &str) -> StorageResult<Vec<ShareLink>> {
+        let prefix = format!("{}:", project_id);
+        let mut links = Vec::new();
+        for item in self.share_links.scan_prefix(prefix.as_bytes()) {
+            let (_, value) = item?;
+            links.push(bincode::deserialize(&value)?);
+        }
+        Ok(links)
+    }
+
+    /// Remove a share link, returning whether it existed
+    pub fn remove_share_link(&self, project_id: &str, link_id: &str) -> StorageResult<bool> {
+        let key = format!("{}:{}", project_id, link_id);
+        Ok(self.share_links.remove(key.as_bytes())?.is_some())
+    }
+
+    /// Append a chat message to a project's history
+    pub fn save_chat_message(&self, record: &ChatRecord) -> StorageResult<()> {
+        let key = format!("{}:{}", record.project_id, record.message_id);
+        let bytes = bincode::serialize(record)?;
+        self.chat.insert(key.as_bytes(), bytes)?;
+        Ok(())
+    }
+
+    /// Load a chat message
+    pub fn get_chat_message(
+        &self,
+        project_id: &str,
+        message_id: &str,
+    ) -> StorageResult<Option<ChatRecord>> {
+        let key = format!("{}:{}", project_id, message_id);
+        match self.chat.get(key.as_bytes())? {
+            Some(bytes) => Ok(Some(bincode::deserialize(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The most recent chat messages of a project, oldest first
+    pub fn load_chat_history(
+        &self,
+        project_id: &str,
+        limit: usize,
+    ) -> StorageResult<Vec<ChatRecord>> {
+        let prefix = format!("{}:", project_id);
+        let mut messages = Vec::new();
+        for item in self.chat.scan_prefix(prefix.as_bytes()).rev().take(limit) {
+            let (_, value) = item?;
+            messages.push(bincode::deserialize(&value)?);
+        }
+        messages.reverse();
+        Ok(messages)
+    }
+
+    /// Remove a chat message from the history, returning it if it existed
+    pub fn redact_chat_message(
+        &self,
+        project_id: &str,
+        message_id: &str,
+    ) -> StorageResult<Option<ChatRecord>> {
+        let key = format!("{}:{}", project_id, message_id);
+        match self.chat.remove(key.as_bytes())? {
+            Some(bytes) => Ok(Some(bincode::deserialize(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Remove all but the most recent `keep` chat messages of a project.
+    ///
+    /// Returns the number of messages removed.
+    pub fn trim_chat_history(&self, project_id: &str, keep: usize) -> StorageResult<usize> {
+        let prefix = format!("{}:", project_id);
+        let mut to_remove = Vec::new();
+        for item in self.chat.scan_prefix(prefix.as_bytes()).rev().skip(keep) {
+            let (key, _) = item?;
+            to_remove.push(key);
+        }
+        for key in &to_remove {
+            self.chat.remove(key)?;
+        }
+        Ok(to_remove.len())
+    }
+
+    /// Append an entry to the audit log. Entries are never changed, and
+    /// deleting a project leaves its entries in place.
+    pub fn append_audit(&self, record: &AuditRecord) -> StorageResult<()> {
+        // IDs only grow, across restarts too, so keys sort in recording order
+        let key = self.db.generate_id()?.to_be_bytes();
+        let bytes = bincode::serialize(record)?;
+        self.audit.insert(key, bytes)?;
+        Ok(())
+    }
+
+    /// The most recent audit entries, of one project or of all, newest first
+    pub fn load_audit(
+        &self,
+        project_id: Option<&str>,
+        limit: usize,
+    ) -> StorageResult<Vec<AuditRecord>> {
+        let mut records = Vec::new();
+        for item in self.audit.iter().rev() {
+            if records.len() >= limit {
+                break;
+            }
+            let (_, value) = item?;
+            let record: AuditRecord = bincode::deserialize(&value)?;
+            if project_id.is_none_or(|id| id == record.project_id) {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+
+    /// Remove audit entries recorded before `before` (a Unix timestamp) and
+    /// all but the most recent `keep`.
+    ///
+    /// Returns the number of entries removed.
+    pub fn trim_audit(&self, keep: usize, before: i64) -> StorageResult<usize> {
+        let mut excess = self.audit.len().saturating_sub(keep);
+        let mut to_remove = Vec::new();
+        for item in self.audit.iter() {
+            let (key, value) = item?;
+            let record: AuditRecord = bincode::deserialize(&value)?;
+            if excess == 0 && record.timestamp >= before {
+                break;
+            }
+            excess = excess.saturating_sub(1);
+            to_remove.push(key);
+        }
+        for key in &to_remove {
+            self.audit.remove(key)?;
+        }
+        Ok(to_remove.len())
+    }
+
+    /// Store a new user account.
+    ///
+    /// Returns false, storing nothing, if the username is already taken.
+    pub fn create_user(&self, user: &UserAccount) -> StorageResult<bool> {
+        let name_key = user.username.to_lowercase();
+        let claimed = self
+            .usernames
+            .compare_and_swap(
+                name_key.as_bytes(),
+                None as Option<&[u8]>,
+                Some(user.user_id.as_bytes()),
+            )?
+            .is_ok();
+        if !claimed {
+            return Ok(false);
+        }
+
+        let bytes = bincode::serialize(user)?;
+        self.users.insert(user.user_id.as_bytes(), bytes)?;
+        Ok(true)
+    }
+
+    /// Load a user account by ID
+    pub fn get_user(&self, user_id: &str) -> StorageResult<Option<UserAccount>> {
+        match self.users.get(user_id.as_bytes())? {
+            Some(bytes) => Ok(Some(bincode::deserialize(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Load a user account by username, ignoring case
+    pub fn find_user_by_username(&self, username: &str) -> StorageResult<Option<UserAccount>> {
+        match self.usernames.get(username.to_lowercase().as_bytes())? {
+            Some(user_id) => self.get_user(&String::from_utf8_lossy(&user_id)),
+            None => Ok(None),
+        }
+    }
+
+    /// Link an external (OAuth) identity to a user account.
+    ///
+    /// Returns false, changing nothing, if the identity is linked to another user.
+    pub fn link_identity(&self, provider: &str, external_id: &str, user_id: &str) -> StorageResult<bool> {
+        let key = format!("{}:{}", provider, external_id);
+        let result = self.identities.compare_and_swap(
+            key.as_bytes(),
+            None as Option<&[u8]>,
+            Some(user_id.as_bytes()),
+        )?;
+        Ok(match result {
+            Ok(()) => true,
+            Err(existing) => existing.current.as_deref() == Some(user_id.as_bytes()),
+        })
+    }
+
+    /// Find the user an external identity is linked to
+    pub fn find_identity(&self, provider: &str, external_id: &str) -> StorageResult<Option<String>> {
+        let key = format!("{}:{}", provider, external_id);
+        Ok(self
+            .identities
+            .get(key.as_bytes())?
+            .map(|user_id| String::from_utf8_lossy(&user_id).to_string()))
+    }
+
     /// Force flush all pending writes to disk
     pub fn flush(&self) -> StorageResult<()> {
         self.db.flush()?;
@@ -377,6 +774,9 @@ impl Drop for DocumentStore {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::storage::{
+        AuditEvent, GitRemote, NotificationSink, ProjectEventKind, ProjectRole, ShareRole,
+    };
     use tempfile::tempdir;
 
     fn test_store() -> DocumentStore {
@@ -421,6 +821,54 @@ mod tests {
         assert_eq!(loaded.owner_id, Some("user-123".to_string()));
     }
 
+    #[test]
+    fn test_metadata_before_archiving() {
+        #[derive(serde::Serialize)]
+        struct Old<'a> {
+            project_id: &'a str,
+            name: &'a str,
+            created_at: i64,
+            updated_at: i64,
+            change_count: u64,
+            size_bytes: u64,
+            owner_id: Option<&'a str>,
+        }
+
+        let store = test_store();
+        let old = Old {
+            project_id: "old-project",
+            name: "Old",
+            created_at: 1,
+            updated_at: 2,
+            change_count: 3,
+            size_bytes: 4,
+            owner_id: Some("user-123"),
+        };
+        store
+            .metadata
+            .insert("old-project", bincode::serialize(&old).unwrap())
+            .unwrap();
+
+        let loaded = store.get_metadata("old-project").unwrap().unwrap();
+        assert_eq!(loaded.owner_id.as_deref(), Some("user-123"));
+        assert!(!loaded.archived);
+        assert_eq!(store.list_documents().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_acl_save_load() {
+        let store = test_store();
+        let mut acl = ProjectAcl::new("test-project", "user-123");
+        acl.grant("user-456", ProjectRole::Viewer);
+
+        store.save_acl(&acl).unwrap();
+        assert_eq!(store.get_acl("test-project").unwrap(), Some(acl));
+        assert!(store.get_acl("other").unwrap().is_none());
+
+        store.delete_document("test-project").unwrap();
+        assert!(store.get_acl("test-project").unwrap().is_none());
+    }
+
     #[test]
     fn test_changes() {
         let store = test_store();
@@ -455,6 +903,17 @@ mod tests {
         assert_eq!(loaded.unwrap(), state);
     }
 
+    #[test]
+    fn test_retain_sync_states() {
+        let store = test_store();
+        store.save_sync_state("proj", "peer-1", &[1]).unwrap();
+        store.save_sync_state("proj", "peer-2", &[2]).unwrap();
+
+        assert_eq!(store.retain_sync_states(|state| state == [2]).unwrap(), 1);
+        assert!(store.load_sync_state("proj", "peer-1").unwrap().is_none());
+        assert!(store.load_sync_state("proj", "peer-2").unwrap().is_some());
+    }
+
     #[test]
     fn test_delete_document() {
         let store = test_store();
@@ -470,4 +929,181 @@ mod tests {
         assert!(!store.document_exists(project_id).unwrap());
         assert!(store.get_metadata(project_id).unwrap().is_none());
     }
+
+    #[test]
+    fn test_share_links() {
+        let store = test_store();
+        let link = ShareLink {
+            link_id: "link-1".to_string(),
+            project_id: "proj".to_string(),
+            role: ShareRole::Viewer,
+            paths: vec!["src".to_string()],
+            created_at: 0,
+            expires_at: 100,
+            created_by: None,
+        };
+
+        store.save_share_link(&link).unwrap();
+        assert!(store.get_share_link("proj", "link-1").unwrap().is_some());
+        assert_eq!(store.list_share_links("proj").unwrap().len(), 1);
+        assert!(store.list_share_links("other").unwrap().is_empty());
+
+        assert!(store.remove_share_link("proj", "link-1").unwrap());
+        assert!(!store.remove_share_link("proj", "link-1").unwrap());
+        assert!(store.get_share_link("proj", "link-1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_chat_history() {
+        let store = test_store();
+        for i in 0..5 {
+            store
+                .save_chat_message(&ChatRecord {
+                    message_id: format!("{:04}", i),
+                    project_id: "proj".to_string(),
+                    peer_id: "peer-1".to_string(),
+                    peer_name: "Alice".to_string(),
+                    content: format!("message {}", i),
+                    timestamp: i,
+                })
+                .unwrap();
+        }
+
+        let history = store.load_chat_history("proj", 3).unwrap();
+        let ids: Vec<_> = history.iter().map(|m| m.message_id.as_str()).collect();
+        assert_eq!(ids, ["0002", "0003", "0004"]);
+
+        assert!(store.get_chat_message("proj", "0003").unwrap().is_some());
+        let removed = store.redact_chat_message("proj", "0003").unwrap().unwrap();
+        assert_eq!(removed.content, "message 3");
+        assert!(store.redact_chat_message("proj", "0003").unwrap().is_none());
+
+        assert_eq!(store.trim_chat_history("proj", 2).unwrap(), 2);
+        let history = store.load_chat_history("proj", 10).unwrap();
+        let ids: Vec<_> = history.iter().map(|m| m.message_id.as_str()).collect();
+        assert_eq!(ids, ["0002", "0004"]);
+
+        store.delete_document("proj").unwrap();
+        assert!(store.load_chat_history("proj", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_audit_log() {
+        let store = test_store();
+        for (i, project_id) in ["proj", "other", "proj", "proj"].into_iter().enumerate() {
+            store
+                .append_audit(&AuditRecord {
+                    timestamp: 100 + i as i64,
+                    project_id: project_id.to_string(),
+                    peer_id: format!("peer-{}", i),
+                    peer_name: "Alice".to_string(),
+                    user_id: None,
+                    ip: Some("10.0.0.1".to_string()),
+                    event: AuditEvent::Joined,
+                })
+                .unwrap();
+        }
+
+        let log = store.load_audit(Some("proj"), 2).unwrap();
+        let peers: Vec<_> = log.iter().map(|r| r.peer_id.as_str()).collect();
+        assert_eq!(peers, ["peer-3", "peer-2"]);
+
+        // Deleting the project leaves its trail
+        store.delete_document("proj").unwrap();
+        assert_eq!(store.load_audit(None, 10).unwrap().len(), 4);
+
+        // Too old, then too many
+        assert_eq!(store.trim_audit(10, 101).unwrap(), 1);
+        assert_eq!(store.trim_audit(2, 0).unwrap(), 1);
+        let log = store.load_audit(None, 10).unwrap();
+        let peers: Vec<_> = log.iter().map(|r| r.peer_id.as_str()).collect();
+        assert_eq!(peers, ["peer-3", "peer-2"]);
+    }
+
+    #[test]
+    fn test_notifications() {
+        let store = test_store();
+        let settings = ProjectNotifications {
+            project_id: "proj".to_string(),
+            sinks: vec![
+                NotificationSink::Slack {
+                    url: "https://hooks.slack.com/services/T/B/X".to_string(),
+                },
+                NotificationSink::Email {
+                    to: vec!["team@example.com".to_string()],
+                },
+            ],
+            events: vec![ProjectEventKind::PeerJoined],
+        };
+
+        store.save_notifications(&settings).unwrap();
+        assert_eq!(store.get_notifications("proj").unwrap(), Some(settings));
+
+        store.delete_document("proj").unwrap();
+        assert!(store.get_notifications("proj").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_git_settings() {
+        let store = test_store();
+        let settings = ProjectGit {
+            project_id: "proj".to_string(),
+            bare: true,
+            branch: "main".to_string(),
+            remote: Some(GitRemote {
+                url: "https://example.com/team/proj.git".to_string(),
+                username: None,
+                token: Some("token".to_string()),
+            }),
+            auto_commit: false,
+        };
+
+        store.save_git(&settings).unwrap();
+        assert_eq!(store.get_git("proj").unwrap(), Some(settings.clone()));
+        assert_eq!(store.list_git().unwrap(), vec![settings]);
+
+        store.delete_document("proj").unwrap();
+        assert!(store.get_git("proj").unwrap().is_none());
+        assert!(!store.remove_git("proj").unwrap());
+    }
+
+    #[test]
+    fn test_user_accounts() {
+        let store = test_store();
+        let user = UserAccount {
+            user_id: "user-1".to_string(),
+            username: "Alice".to_string(),
+            display_name: "Alice".to_string(),
+            password_hash: "hash".to_string(),
+            created_at: 0,
+        };
+
+        assert!(store.create_user(&user).unwrap());
+        let taken = UserAccount {
+            user_id: "user-2".to_string(),
+            username: "alice".to_string(),
+            ..user.clone()
+        };
+        assert!(!store.create_user(&taken).unwrap());
+        assert!(store.get_user("user-2").unwrap().is_none());
+
+        let found = store.find_user_by_username("ALICE").unwrap().unwrap();
+        assert_eq!(found.user_id, "user-1");
+        assert!(store.find_user_by_username("bob").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_identities() {
+        let store = test_store();
+
+        assert!(store.link_identity("github", "42", "user-1").unwrap());
+        assert!(store.link_identity("github", "42", "user-1").unwrap());
+        assert!(!store.link_identity("github", "42", "user-2").unwrap());
+
+        assert_eq!(
+            store.find_identity("github", "42").unwrap().as_deref(),
+            Some("user-1")
+        );
+        assert!(store.find_identity("google", "42").unwrap().is_none());
+    }
 }
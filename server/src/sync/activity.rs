@@ -0,0 +1,353 @@
+//! Per-project activity feed for the "recent activity" panel.
+//!
+//! This module keeps a bounded ring buffer of notable events per project:
+//! - Peers joining and leaving
+//! - Files being opened
+//! - File tree operations (create, delete, rename, move, content replace)
+//! - Chat messages
+//!
+//! Entries are assembled from the `PresenceEvent` stream of each project and
+//! from `FileOperation`s and chat messages recorded explicitly by the server.
+
+use collab_protocol::{ActivityEntry, ActivityKind};
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::{Arc, Weak};
+use tokio::sync::broadcast::error::RecvError;
+
+use super::files::FileChanges;
+use super::presence::{PresenceEvent, ProjectPresence};
+use super::ProjectId;
+use crate::room::{detect_language, FileOperation};
+
+/// Default number of entries kept per project
+pub const DEFAULT_ACTIVITY_CAPACITY: usize = 200;
+
+/// Maximum length of the chat preview stored in the feed
+const CHAT_PREVIEW_LEN: usize = 80;
+
+impl From<&FileOperation> for ActivityKind {
+    fn from(op: &FileOperation) -> Self {
+        match op {
+            FileOperation::CreateFile { path, .. } => ActivityKind::FileCreated { path: path.clone() },
+            FileOperation::CreateFolder { path, .. } => {
+                ActivityKind::FolderCreated { path: path.clone() }
+            }
+            FileOperation::Delete { path, .. } => ActivityKind::Deleted { path: path.clone() },
+            FileOperation::Rename {
+                old_name, new_name, ..
+            } => ActivityKind::Renamed {
+                old_name: old_name.clone(),
+                new_name: new_name.clone(),
+            },
+            FileOperation::Move { node_id, .. } => ActivityKind::Moved {
+                node_id: node_id.clone(),
+            },
+            FileOperation::UpdateContent { path, .. } => ActivityKind::FileUpdated { path: path.clone() },
+        }
+    }
+}
+
+/// Bounded activity log across all projects
+pub struct ActivityLog {
+    /// Maximum entries kept per project
+    capacity: usize,
+    /// Map of project_id -> ring buffer of entries (oldest first)
+    projects: DashMap<ProjectId, Mutex<VecDeque<ActivityEntry>>>,
+    /// Presence channels currently being watched
+    watchers: DashMap<ProjectId, Weak<ProjectPresence>>,
+}
+
+impl ActivityLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            projects: DashMap::new(),
+            watchers: DashMap::new(),
+        }
+    }
+
+    /// Append an entry, evicting the oldest one when full
+    pub fn record(&self, project_id: &str, entry: ActivityEntry) {
+        let log = self
+            .projects
+            .entry(project_id.to_string())
+            .or_insert_with(|| Mutex::new(VecDeque::with_capacity(self.capacity)));
+
+        let mut entries = log.lock();
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Record a file tree operation performed by a peer
+    pub fn record_file_operation(&self, project_id: &str, peer_id: Option<&str>, op: &FileOperation) {
+        let mut entry = ActivityEntry::new(ActivityKind::from(op));
+        if let Some(peer_id) = peer_id {
+            let name = self.known_name(project_id, peer_id);
+            entry = entry.with_peer(peer_id, name);
+        }
+        self.record(project_id, entry);
+    }
+
+    /// Record the files a change to a project document created, deleted and
+    /// renamed. Files in the document are identified by their path.
+    pub fn record_file_changes(
+        &self,
+        project_id: &str,
+        peer_id: Option<&str>,
+        changes: &FileChanges,
+    ) {
+        let renamed_from = |path: &String| changes.renamed.iter().any(|(old, _)| old == path);
+        let renamed_to = |path: &String| changes.renamed.iter().any(|(_, new)| new == path);
+
+        for path in changes.created.iter().filter(|path| !renamed_to(path)) {
+            let op = FileOperation::CreateFile {
+                node_id: path.clone(),
+                parent_id: None,
+                name: path.rsplit('/').next().unwrap_or(path).to_string(),
+                language: detect_language(path),
+                path: path.clone(),
+                content: None,
+            };
+            self.record_file_operation(project_id, peer_id, &op);
+        }
+        for path in changes.deleted.iter().filter(|path| !renamed_from(path)) {
+            let op = FileOperation::Delete {
+                node_id: path.clone(),
+                path: path.clone(),
+            };
+            self.record_file_operation(project_id, peer_id, &op);
+        }
+        for (old, new) in &changes.renamed {
+            let op = FileOperation::Rename {
+                node_id: new.clone(),
+                old_name: old.clone(),
+                new_name: new.clone(),
+            };
+            self.record_file_operation(project_id, peer_id, &op);
+        }
+    }
+
+    /// Record a chat message (only a short preview is kept)
+    pub fn record_chat(&self, project_id: &str, peer_id: &str, peer_name: &str, content: &str) {
+        let preview: String = content.chars().take(CHAT_PREVIEW_LEN).collect();
+        let entry = ActivityEntry::new(ActivityKind::Chat { preview })
+            .with_peer(peer_id, Some(peer_name.to_string()));
+        self.record(project_id, entry);
+    }
+
+    /// Remove the entry of a deleted chat message
+    pub fn redact_chat(&self, project_id: &str, peer_id: &str, content: &str) {
+        let preview: String = content.chars().take(CHAT_PREVIEW_LEN).collect();
+        if let Some(log) = self.projects.get(project_id) {
+            let mut entries = log.lock();
+            let position = entries.iter().rposition(|entry| {
+                entry.peer_id.as_deref() == Some(peer_id)
+                    && matches!(&entry.kind, ActivityKind::Chat { preview: p } if *p == preview)
+            });
+            if let Some(position) = position {
+                entries.remove(position);
+            }
+        }
+    }
+
+    /// Record the relevant subset of presence events
+    pub fn record_presence_event(&self, event: &PresenceEvent) {
+        match event {
+            PresenceEvent::Joined {
+                project_id,
+                presence,
+            } => {
+                let entry = ActivityEntry::new(ActivityKind::Joined)
+                    .with_peer(&presence.peer_id, Some(presence.name.clone()));
+                self.record(project_id, entry);
+            }
+            PresenceEvent::Left {
+                project_id,
+                peer_id,
+            } => {
+                let name = self.known_name(project_id, peer_id);
+                let entry = ActivityEntry::new(ActivityKind::Left).with_peer(peer_id, name);
+                self.record(project_id, entry);
+            }
+            PresenceEvent::FileOpened {
+                project_id,
+                peer_id,
+                file_path,
+            } => {
+                let name = self.known_name(project_id, peer_id);
+                let entry = ActivityEntry::new(ActivityKind::FileOpened {
+                    file_path: file_path.clone(),
+                })
+                .with_peer(peer_id, name);
+                self.record(project_id, entry);
+            }
+            // High-frequency or low-signal events are not worth a feed entry
+            _ => {}
+        }
+    }
+
+    /// Start feeding a project's presence events into the log.
+    ///
+    /// Does nothing if the same presence instance is already being watched.
+    pub fn watch(self: &Arc<Self>, presence: &Arc<ProjectPresence>) {
+        let project_id = presence.project_id().to_string();
+
+        if let Some(existing) = self.watchers.get(&project_id) {
+            if existing
+                .upgrade()
+                .map(|p| Arc::ptr_eq(&p, presence))
+                .unwrap_or(false)
+            {
+                return;
+            }
+        }
+        self.watchers.insert(project_id, Arc::downgrade(presence));
+
+        let mut events = presence.subscribe();
+        let log = Arc::downgrade(self);
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => match log.upgrade() {
+                        Some(log) => log.record_presence_event(&event),
+                        None => break,
+                    },
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    /// Get the most recent entries for a project, newest first
+    pub fn recent(&self, project_id: &str, limit: usize) -> Vec<ActivityEntry> {
+        self.projects
+            .get(project_id)
+            .map(|log| log.lock().iter().rev().take(limit).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Drop the log for a project
+    pub fn remove(&self, project_id: &str) {
+        self.projects.remove(project_id);
+        self.watchers.remove(project_id);
+    }
+
+    /// Look up the last known display name of a peer from earlier entries
+    fn known_name(&self, project_id: &str, peer_id: &str) -> Option<String> {
+        let log = self.projects.get(project_id)?;
+        let entries = log.lock();
+        entries
+            .iter()
+            .rev()
+            .find(|e| e.peer_id.as_deref() == Some(peer_id) && e.peer_name.is_some())
+            .and_then(|e| e.peer_name.clone())
+    }
+}
+
+impl Default for ActivityLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_ACTIVITY_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::presence::Presence;
+
+    #[test]
+    fn test_ring_buffer_eviction() {
+        let log = ActivityLog::new(3);
+
+        for i in 0..5 {
+            log.record_chat("proj", "peer-1", "Alice", &format!("message {}", i));
+        }
+
+        let recent = log.recent("proj", 10);
+        assert_eq!(recent.len(), 3);
+        assert_eq!(
+            recent[0].kind,
+            ActivityKind::Chat {
+                preview: "message 4".to_string()
+            }
+        );
+        assert_eq!(
+            recent[2].kind,
+            ActivityKind::Chat {
+                preview: "message 2".to_string()
+            }
+        );
+        // Deleted messages disappear from the feed
+        log.redact_chat("proj", "peer-1", "message 3");
+        let recent = log.recent("proj", 10);
+        assert_eq!(recent.len(), 2);
+        assert_eq!(
+            recent[1].kind,
+            ActivityKind::Chat {
+                preview: "message 2".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_presence_events_resolve_names() {
+        let log = ActivityLog::default();
+
+        log.record_presence_event(&PresenceEvent::Joined {
+            project_id: "proj".to_string(),
+            presence: Presence::new("peer-1", "Alice", "#ff0000"),
+        });
+        log.record_presence_event(&PresenceEvent::Left {
+            project_id: "proj".to_string(),
+            peer_id: "peer-1".to_string(),
+        });
+
+        let recent = log.recent("proj", 10);
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].kind, ActivityKind::Left);
+        assert_eq!(recent[0].peer_name.as_deref(), Some("Alice"));
+    }
+
+    #[test]
+    fn test_file_operation_entry() {
+        let log = ActivityLog::default();
+        let op = FileOperation::Rename {
+            node_id: "node-1".to_string(),
+            old_name: "old.rs".to_string(),
+            new_name: "new.rs".to_string(),
+        };
+
+        log.record_file_operation("proj", Some("peer-1"), &op);
+
+        let recent = log.recent("proj", 1);
+        assert!(matches!(recent[0].kind, ActivityKind::Renamed { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_watch_presence_stream() {
+        let log = Arc::new(ActivityLog::default());
+        let presence = Arc::new(ProjectPresence::new("proj"));
+
+        log.watch(&presence);
+        log.watch(&presence);
+
+        presence.add_peer(Presence::new("peer-1", "Alice", "#ff0000")).unwrap();
+        presence.open_file("peer-1", "/main.rs").unwrap();
+
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+
+        let recent = log.recent("proj", 10);
+        assert_eq!(recent.len(), 2);
+        assert_eq!(
+            recent[0].kind,
+            ActivityKind::FileOpened {
+                file_path: "/main.rs".to_string()
+            }
+        );
+    }
+}
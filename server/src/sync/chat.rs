@@ -0,0 +1,246 @@
+//! Chat moderation.
+//!
+//! Messages are checked before they are broadcast: they must not be blank or
+//! longer than the configured limit, each peer may only send so many per
+//! minute, and with the profanity filter on, blocked words are masked.
+//! Messages are kept in storage so peers joining later get the history; a
+//! deleted message is removed from it.
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use super::PeerId;
+
+/// Window over which messages are counted
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Words masked by the profanity filter when none are configured
+const DEFAULT_BLOCKED_WORDS: &[&str] = &[
+    "arsehole",
+    "asshole",
+    "bastard",
+    "bitch",
+    "bollocks",
+    "cunt",
+    "fuck",
+    "fucking",
+    "motherfucker",
+    "shit",
+    "wanker",
+];
+
+/// Chat limits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChatConfig {
+    /// Longest message accepted, in characters
+    pub max_length: usize,
+    /// Messages one peer may send per minute
+    pub messages_per_minute: u32,
+    /// Messages kept per project
+    pub history_limit: usize,
+    /// Mask blocked words
+    pub profanity_filter: bool,
+    /// Words the filter masks; a built-in list if empty
+    pub blocked_words: Vec<String>,
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        Self {
+            max_length: 2000,
+            messages_per_minute: 30,
+            history_limit: 200,
+            profanity_filter: false,
+            blocked_words: Vec::new(),
+        }
+    }
+}
+
+/// Why a chat message was refused
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ChatError {
+    #[error("Message is empty")]
+    Empty,
+
+    #[error("Message is too long ({length} characters, at most {max})")]
+    TooLong { length: usize, max: usize },
+
+    #[error("Too many messages, wait {}s", .retry_after.as_secs().max(1))]
+    RateLimited { retry_after: Duration },
+}
+
+/// Checks chat messages against the limits
+pub struct ChatModerator {
+    config: ChatConfig,
+    /// Lowercased words the filter masks
+    blocked: HashSet<String>,
+    /// Start of the current window and messages sent in it, per peer
+    rates: DashMap<PeerId, (Instant, u32)>,
+}
+
+impl ChatModerator {
+    pub fn new(config: ChatConfig) -> Self {
+        let blocked = if config.blocked_words.is_empty() {
+            DEFAULT_BLOCKED_WORDS
+                .iter()
+                .map(|word| word.to_string())
+                .collect()
+        } else {
+            config
+                .blocked_words
+                .iter()
+                .map(|word| word.trim().to_lowercase())
+                .filter(|word| !word.is_empty())
+                .collect()
+        };
+        Self {
+            config,
+            blocked,
+            rates: DashMap::new(),
+        }
+    }
+
+    /// Check a message from a peer, returning the text to broadcast
+    pub fn check(&self, peer_id: &str, content: &str) -> Result<String, ChatError> {
+        let content = content.trim();
+        if content.is_empty() {
+            return Err(ChatError::Empty);
+        }
+        let length = content.chars().count();
+        if length > self.config.max_length {
+            return Err(ChatError::TooLong {
+                length,
+                max: self.config.max_length,
+            });
+        }
+
+        let now = Instant::now();
+        let mut rate = self.rates.entry(peer_id.to_string()).or_insert((now, 0));
+        if now.duration_since(rate.0) >= RATE_WINDOW {
+            *rate = (now, 0);
+        }
+        if rate.1 >= self.config.messages_per_minute {
+            return Err(ChatError::RateLimited {
+                retry_after: RATE_WINDOW.saturating_sub(now.duration_since(rate.0)),
+            });
+        }
+        rate.1 += 1;
+        drop(rate);
+
+        Ok(if self.config.profanity_filter {
+            self.mask(content)
+        } else {
+            content.to_string()
+        })
+    }
+
+    /// Forget the rate of a disconnected peer
+    pub fn forget(&self, peer_id: &str) {
+        self.rates.remove(peer_id);
+    }
+
+    /// Replace each blocked word with asterisks
+    fn mask(&self, content: &str) -> String {
+        let mut masked = String::with_capacity(content.len());
+        let mut word = String::new();
+        for c in content.chars().chain(std::iter::once(' ')) {
+            if c.is_alphanumeric() {
+                word.push(c);
+                continue;
+            }
+            if self.blocked.contains(&word.to_lowercase()) {
+                masked.push_str(&"*".repeat(word.chars().count()));
+            } else {
+                masked.push_str(&word);
+            }
+            word.clear();
+            masked.push(c);
+        }
+        masked.pop();
+        masked
+    }
+}
+
+/// Identifier for a new chat message; later messages sort after earlier ones
+pub fn new_message_id() -> String {
+    static SEQUENCE: AtomicU32 = AtomicU32::new(0);
+    format!(
+        "{:012x}{:08x}",
+        chrono::Utc::now().timestamp_millis(),
+        SEQUENCE.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_length_limits() {
+        let moderator = ChatModerator::new(ChatConfig {
+            max_length: 5,
+            ..Default::default()
+        });
+
+        assert_eq!(moderator.check("peer", "  hello  ").unwrap(), "hello");
+        assert_eq!(moderator.check("peer", "   "), Err(ChatError::Empty));
+        assert_eq!(
+            moderator.check("peer", "hello!"),
+            Err(ChatError::TooLong { length: 6, max: 5 })
+        );
+        // Length is counted in characters, not bytes
+        assert!(moderator.check("peer", "héllo").is_ok());
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let moderator = ChatModerator::new(ChatConfig {
+            messages_per_minute: 2,
+            ..Default::default()
+        });
+
+        moderator.check("peer-1", "one").unwrap();
+        moderator.check("peer-1", "two").unwrap();
+        assert!(matches!(
+            moderator.check("peer-1", "three"),
+            Err(ChatError::RateLimited { .. })
+        ));
+        moderator.check("peer-2", "one").unwrap();
+
+        moderator.forget("peer-1");
+        moderator.check("peer-1", "four").unwrap();
+    }
+
+    #[test]
+    fn test_profanity_filter() {
+        let moderator = ChatModerator::new(ChatConfig {
+            profanity_filter: true,
+            blocked_words: vec!["Darn".to_string()],
+            ..Default::default()
+        });
+
+        assert_eq!(
+            moderator.check("peer", "darn it, DARN!").unwrap(),
+            "**** it, ****!"
+        );
+        // Only whole words are masked
+        assert_eq!(moderator.check("peer", "darnedest").unwrap(), "darnedest");
+
+        let unfiltered = ChatModerator::new(ChatConfig {
+            blocked_words: vec!["darn".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(unfiltered.check("peer", "darn").unwrap(), "darn");
+    }
+
+    #[test]
+    fn test_message_ids_sort() {
+        let first = new_message_id();
+        assert!(new_message_id() > first);
+    }
+}
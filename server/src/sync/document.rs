@@ -8,9 +8,14 @@ use automerge::{
     transaction::Transactable, ActorId, AutoCommit, Change, ChangeHash, ObjId, ObjType, ReadDoc, ScalarValue, Value, ROOT,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use thiserror::Error;
 
+use crate::room::NestedNode;
+use collab_protocol::{detect_language, AnnotationKind};
+
 /// Errors that can occur during document operations
 #[derive(Error, Debug)]
 pub enum DocumentError {
@@ -62,6 +67,21 @@ mod keys {
     pub const CONTENT: &str = "content";
     pub const LANGUAGE: &str = "language";
     pub const VERSION: &str = "version";
+    pub const ANNOTATIONS: &str = "annotations";
+
+    // Chat message keys
+    pub const ID: &str = "id";
+    pub const AUTHOR: &str = "author";
+    pub const AUTHOR_NAME: &str = "author_name";
+    pub const TIMESTAMP: &str = "timestamp";
+
+    // Annotation keys
+    pub const START: &str = "start";
+    pub const END: &str = "end";
+    pub const KIND: &str = "kind";
+    pub const BODY: &str = "body";
+    pub const RESOLVED: &str = "resolved";
+    pub const RESOLVED_BY: &str = "resolved_by";
 
     // Metadata keys
     pub const PROJECT_NAME: &str = "project_name";
@@ -91,6 +111,34 @@ pub struct FileContent {
     pub version: u64,
 }
 
+/// A chat message kept in the document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatEntry {
+    pub id: String,
+    pub author_id: String,
+    pub author_name: String,
+    pub content: String,
+    pub timestamp: i64,
+}
+
+/// A comment or highlight on a range of a file's text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    pub id: String,
+    pub path: String,
+    /// Start of the range, in characters
+    pub start: usize,
+    /// End of the range (exclusive), in characters
+    pub end: usize,
+    pub kind: AnnotationKind,
+    pub author_id: String,
+    pub author_name: String,
+    pub body: String,
+    pub created_at: i64,
+    pub resolved: bool,
+    pub resolved_by: Option<String>,
+}
+
 /// Collaborative document with CRDT-based file tree and content
 pub struct CollabDocument {
     /// The underlying Automerge document
@@ -98,9 +146,41 @@ pub struct CollabDocument {
     /// Project identifier
     project_id: String,
     /// Cache of file tree structure for quick lookups
-    tree_cache: HashMap<String, FileTreeNode>,
+    tree_cache: RefCell<TreeCache>,
     /// Whether the cache needs rebuilding
-    cache_dirty: bool,
+    cache_dirty: Cell<bool>,
+}
+
+/// Nodes that ended up at the same path, usually after merging concurrent
+/// creates or renames
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathConflict {
+    pub path: String,
+    /// IDs of the nodes at the path; the first one is what the path resolves to
+    pub node_ids: Vec<String>,
+}
+
+/// A broken structural invariant found by `CollabDocument::validate`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IntegrityIssue {
+    /// A file node without an entry in the files map
+    MissingContent { node_id: String, path: String },
+    /// A children list naming a node that does not exist
+    DanglingChild { parent_id: String, child_id: String },
+    /// A node whose parent does not exist
+    MissingParent { node_id: String, parent_id: String },
+    /// Nodes whose parent references go round in a loop, ordered by ID
+    ParentCycle { node_ids: Vec<String> },
+}
+
+/// File tree nodes read out of the document, keyed by ID and by path
+#[derive(Default)]
+struct TreeCache {
+    nodes: HashMap<String, FileTreeNode>,
+    /// Path to node ID; with several nodes at a path, the lowest ID wins
+    paths: HashMap<String, String>,
+    conflicts: Vec<PathConflict>,
 }
 
 impl CollabDocument {
@@ -115,8 +195,8 @@ impl CollabDocument {
         Ok(Self {
             doc,
             project_id,
-            tree_cache: HashMap::new(),
-            cache_dirty: true,
+            tree_cache: RefCell::default(),
+            cache_dirty: Cell::new(true),
         })
     }
 
@@ -130,8 +210,8 @@ impl CollabDocument {
         Ok(Self {
             doc,
             project_id,
-            tree_cache: HashMap::new(),
-            cache_dirty: true,
+            tree_cache: RefCell::default(),
+            cache_dirty: Cell::new(true),
         })
     }
 
@@ -141,8 +221,8 @@ impl CollabDocument {
         Ok(Self {
             doc,
             project_id: project_id.into(),
-            tree_cache: HashMap::new(),
-            cache_dirty: true,
+            tree_cache: RefCell::default(),
+            cache_dirty: Cell::new(true),
         })
     }
 
@@ -162,6 +242,17 @@ impl CollabDocument {
         Ok(())
     }
 
+    /// Run `f` with its changes committed under `actor`, then go back to
+    /// the document's own actor
+    pub fn as_actor<R>(&mut self, actor: ActorId, f: impl FnOnce(&mut Self) -> R) -> R {
+        let own = self.doc.get_actor().clone();
+        self.doc.set_actor(actor);
+        let result = f(self);
+        // Switching back commits what `f` did under `actor`
+        self.doc.set_actor(own);
+        result
+    }
+
     /// Get the project ID
     pub fn project_id(&self) -> &str {
         &self.project_id
@@ -174,7 +265,7 @@ impl CollabDocument {
 
     /// Get a mutable reference to the Automerge document
     pub fn automerge_mut(&mut self) -> &mut AutoCommit {
-        self.cache_dirty = true;
+        self.cache_dirty.set(true);
         &mut self.doc
     }
 
@@ -188,6 +279,18 @@ impl CollabDocument {
         self.doc.save_incremental()
     }
 
+    /// Save the changes made after `heads`, in the incremental format, or
+    /// None if some of the heads are unknown
+    pub fn save_since(&mut self, heads: &[ChangeHash]) -> Option<Vec<u8>> {
+        if heads
+            .iter()
+            .any(|hash| ReadDoc::get_change_by_hash(&self.doc, hash).is_none())
+        {
+            return None;
+        }
+        Some(self.doc.save_after(heads))
+    }
+
     /// Get all changes since a set of heads
     pub fn get_changes_since(&mut self, heads: &[ChangeHash]) -> Vec<Change> {
         self.doc
@@ -202,14 +305,14 @@ impl CollabDocument {
         for change in changes {
             self.doc.apply_changes([change])?;
         }
-        self.cache_dirty = true;
+        self.cache_dirty.set(true);
         Ok(())
     }
 
     /// Merge with another document
     pub fn merge(&mut self, other: &mut AutoCommit) -> DocumentResult<()> {
         self.doc.merge(other)?;
-        self.cache_dirty = true;
+        self.cache_dirty.set(true);
         Ok(())
     }
 
@@ -224,11 +327,58 @@ impl CollabDocument {
         Ok(Self {
             doc: forked,
             project_id: self.project_id.clone(),
-            tree_cache: HashMap::new(),
-            cache_dirty: true,
+            tree_cache: RefCell::default(),
+            cache_dirty: Cell::new(true),
         })
     }
 
+    /// Copy the current files and chat into a new document for `project_id`
+    /// that starts its own history
+    pub fn snapshot(&self, project_id: impl Into<String>) -> DocumentResult<Self> {
+        let mut copy = Self::new(project_id)?;
+        let nodes: HashMap<String, FileTreeNode> = self
+            .get_all_nodes()?
+            .into_iter()
+            .map(|node| (node.id.clone(), node))
+            .collect();
+
+        // Parents before children, children in their listed order
+        let mut pending: Vec<&FileTreeNode> =
+            nodes.values().filter(|node| node.parent_id.is_none()).collect();
+        pending.sort_by(|a, b| b.path.cmp(&a.path));
+        let mut copied = HashSet::new();
+        while let Some(node) = pending.pop() {
+            if !copied.insert(node.id.as_str()) {
+                continue;
+            }
+            let parent_id = node.parent_id.as_deref();
+            if node.is_dir {
+                copy.create_folder(&node.id, &node.name, &node.path, parent_id)?;
+                pending.extend(
+                    node.children
+                        .iter()
+                        .rev()
+                        .filter_map(|id| nodes.get(id))
+                        .filter(|child| child.parent_id.as_deref() == Some(node.id.as_str())),
+                );
+            } else {
+                let content = self.get_file_content(&node.path)?;
+                let language = content
+                    .as_ref()
+                    .map_or_else(|| detect_language(&node.path), |c| c.language.clone());
+                copy.create_file(&node.id, &node.name, &node.path, parent_id, &language)?;
+                if let Some(content) = content {
+                    copy.set_file_content(&node.path, &content.content)?;
+                }
+            }
+        }
+
+        for entry in self.read_chat()? {
+            copy.append_chat_message(&entry)?;
+        }
+        Ok(copy)
+    }
+
     // =========================================================================
     // File Tree Operations (Movable Tree CRDT)
     // =========================================================================
@@ -261,6 +411,20 @@ impl CollabDocument {
             .ok_or_else(|| DocumentError::Corruption("Missing files".into()))
     }
 
+    /// Get the chat list object ID
+    fn chat_id(&self) -> DocumentResult<ObjId> {
+        self.doc
+            .get(ROOT, keys::CHAT)?
+            .and_then(|(v, id)| {
+                if matches!(v, Value::Object(ObjType::List)) {
+                    Some(id)
+                } else {
+                    None
+                }
+            })
+            .ok_or_else(|| DocumentError::Corruption("Missing chat".into()))
+    }
+
     /// Create a new folder in the file tree
     pub fn create_folder(
         &mut self,
@@ -287,7 +451,7 @@ impl CollabDocument {
             self.add_child_to_parent(parent, id)?;
         }
 
-        self.cache_dirty = true;
+        self.cache_dirty.set(true);
         Ok(())
     }
 
@@ -323,7 +487,7 @@ impl CollabDocument {
         self.doc.put(&content_id, keys::LANGUAGE, language)?;
         self.doc.put(&content_id, keys::VERSION, 1u64)?;
 
-        self.cache_dirty = true;
+        self.cache_dirty.set(true);
         Ok(())
     }
 
@@ -406,8 +570,22 @@ impl CollabDocument {
         if let Some(new_parent) = new_parent_id {
             self.add_child_to_parent(new_parent, node_id)?;
         }
+        self.cache_dirty.set(true);
+
+        // The node and everything below it now live under the new parent
+        if let Some(node) = self.get_node(node_id)? {
+            let parent_path = match new_parent_id {
+                Some(parent) => self.get_node(parent)?.map(|parent| parent.path),
+                None => None,
+            };
+            let new_path = match parent_path {
+                Some(parent_path) => format!("{}/{}", parent_path, node.name),
+                None if node.path.starts_with('/') => format!("/{}", node.name),
+                None => node.name.clone(),
+            };
+            self.repath_subtree(node_id, new_path)?;
+        }
 
-        self.cache_dirty = true;
         Ok(())
     }
 
@@ -415,94 +593,427 @@ impl CollabDocument {
     pub fn rename_node(&mut self, node_id: &str, new_name: &str) -> DocumentResult<()> {
         let tree_id = self.file_tree_id()?;
 
-        if let Some((_, node_obj)) = self.doc.get(&tree_id, node_id)? {
-            self.doc.put(&node_obj, keys::NAME, new_name)?;
-            self.doc
-                .put(&node_obj, keys::UPDATED_AT, chrono::Utc::now().timestamp())?;
-            self.cache_dirty = true;
-            Ok(())
-        } else {
-            Err(DocumentError::FileNotFound(node_id.to_string()))
+        let Some((_, node_obj)) = self.doc.get(&tree_id, node_id)? else {
+            return Err(DocumentError::FileNotFound(node_id.to_string()));
+        };
+        let old_path = self.get_string_prop(&node_obj, keys::PATH)?.unwrap_or_default();
+        let new_path = match old_path.rsplit_once('/') {
+            Some((parent, _)) => format!("{}/{}", parent, new_name),
+            None => new_name.to_string(),
+        };
+
+        self.doc.put(&node_obj, keys::NAME, new_name)?;
+        self.doc
+            .put(&node_obj, keys::UPDATED_AT, chrono::Utc::now().timestamp())?;
+        self.cache_dirty.set(true);
+
+        if !old_path.is_empty() {
+            self.repath_subtree(node_id, new_path)?;
         }
+        Ok(())
     }
 
-    /// Delete a file or folder
-    pub fn delete_node(&mut self, node_id: &str) -> DocumentResult<()> {
+    /// Give a node a new path and recalculate the paths below it from the
+    /// node names, moving file contents along
+    fn repath_subtree(&mut self, node_id: &str, new_path: String) -> DocumentResult<()> {
         let tree_id = self.file_tree_id()?;
-        let files_id = self.files_id()?;
+        self.ensure_cache()?;
+        let nodes = self.tree_cache.borrow().nodes.clone();
 
-        // Get node info before deleting
-        let (is_dir, path, parent_id) =
-            if let Some((_, node_obj)) = self.doc.get(&tree_id, node_id)? {
-                let is_dir = self
-                    .doc
-                    .get(&node_obj, keys::IS_DIR)?
-                    .and_then(|(v, _)| {
-                        if let Value::Scalar(s) = v {
-                            if let ScalarValue::Boolean(b) = s.as_ref() {
-                                return Some(*b);
-                            }
-                        }
-                        None
-                    })
-                    .unwrap_or(false);
-
-                let path = self
-                    .doc
-                    .get(&node_obj, keys::PATH)?
-                    .and_then(|(v, _)| {
-                        if let Value::Scalar(s) = v {
-                            if let ScalarValue::Str(p) = s.as_ref() {
-                                return Some(p.to_string());
-                            }
-                        }
-                        None
-                    })
-                    .unwrap_or_default();
-
-                let parent = self
-                    .doc
-                    .get(&node_obj, keys::PARENT)?
-                    .and_then(|(v, _)| {
-                        if let Value::Scalar(s) = v {
-                            if let ScalarValue::Str(p) = s.as_ref() {
-                                return Some(p.to_string());
-                            }
-                        }
-                        None
-                    });
+        // Another node at the same path keeps its content there
+        let mut holders: HashMap<&str, usize> = HashMap::new();
+        for node in nodes.values() {
+            *holders.entry(node.path.as_str()).or_default() += 1;
+        }
 
-                (is_dir, path, parent)
-            } else {
-                return Err(DocumentError::FileNotFound(node_id.to_string()));
+        let mut seen = HashSet::new();
+        let mut pending = vec![(node_id.to_string(), new_path)];
+        while let Some((id, path)) = pending.pop() {
+            let Some(node) = nodes.get(&id) else {
+                continue;
             };
+            if !seen.insert(id) {
+                continue;
+            }
+
+            if node.path != path {
+                if let Some((_, obj)) = self.doc.get(&tree_id, node.id.as_str())? {
+                    self.doc.put(&obj, keys::PATH, path.as_str())?;
+                }
+                if !node.is_dir {
+                    let shared = holders.get(node.path.as_str()).is_some_and(|&n| n > 1);
+                    self.move_file_content(&node.path, &path, shared)?;
+                }
+            }
+
+            for child in node.children.iter().filter_map(|id| nodes.get(id)) {
+                pending.push((child.id.clone(), format!("{}/{}", path, child.name)));
+            }
+        }
+
+        self.cache_dirty.set(true);
+        Ok(())
+    }
+
+    /// Move a file's content entry to another path, or copy it if `keep` is set
+    fn move_file_content(&mut self, from: &str, to: &str, keep: bool) -> DocumentResult<()> {
+        let Some(file) = self.get_file_content(from)? else {
+            return Ok(());
+        };
+        let annotations = self.get_annotations(from)?;
+        let files_id = self.files_id()?;
+
+        let content_id = self.doc.put_object(&files_id, to, ObjType::Map)?;
+        let text_id = self.doc.put_object(&content_id, keys::CONTENT, ObjType::Text)?;
+        self.doc.splice_text(&text_id, 0, 0, &file.content)?;
+        self.doc.put(&content_id, keys::LANGUAGE, file.language.as_str())?;
+        self.doc.put(&content_id, keys::VERSION, file.version + 1)?;
+        for annotation in annotations {
+            let moved = Annotation {
+                path: to.to_string(),
+                ..annotation
+            };
+            match self.write_annotation(&moved) {
+                Err(DocumentError::InvalidOperation(_)) => {}
+                result => result?,
+            }
+        }
+        if !keep {
+            self.doc.delete(&files_id, from)?;
+        }
+        Ok(())
+    }
+
+    /// Delete a file or folder along with everything below it
+    pub fn delete_node(&mut self, node_id: &str) -> DocumentResult<()> {
+        let node = self
+            .get_node(node_id)?
+            .ok_or_else(|| DocumentError::FileNotFound(node_id.to_string()))?;
 
         // Remove from parent
-        if let Some(parent) = parent_id {
-            self.remove_child_from_parent(&parent, node_id)?;
+        if let Some(parent) = &node.parent_id {
+            self.remove_child_from_parent(parent, node_id)?;
         }
 
-        // Delete file content if it's a file
-        if !is_dir && !path.is_empty() {
-            self.doc.delete(&files_id, path.as_str())?;
+        let doomed = self.subtree(node_id)?;
+        self.remove_nodes(&doomed)?;
+        Ok(())
+    }
+
+    /// A node and all its descendants, found through both the children lists
+    /// and the parent references
+    fn subtree(&self, node_id: &str) -> DocumentResult<Vec<FileTreeNode>> {
+        self.ensure_cache()?;
+        let cache = self.tree_cache.borrow();
+
+        let mut below: HashMap<&str, Vec<&str>> = HashMap::new();
+        for node in cache.nodes.values() {
+            if let Some(parent) = &node.parent_id {
+                below.entry(parent.as_str()).or_default().push(&node.id);
+            }
+            below
+                .entry(node.id.as_str())
+                .or_default()
+                .extend(node.children.iter().map(String::as_str));
         }
 
-        // Delete the tree node
-        self.doc.delete(&tree_id, node_id)?;
+        let mut seen = HashSet::new();
+        let mut nodes = Vec::new();
+        let mut pending = vec![node_id];
+        while let Some(id) = pending.pop() {
+            let Some(node) = cache.nodes.get(id) else {
+                continue;
+            };
+            if !seen.insert(id) {
+                continue;
+            }
+            nodes.push(node.clone());
+            pending.extend(below.get(id).into_iter().flatten());
+        }
+        Ok(nodes)
+    }
 
-        self.cache_dirty = true;
+    /// Delete tree nodes and the contents of their files. Contents another
+    /// node still sits on are kept.
+    fn remove_nodes(&mut self, doomed: &[FileTreeNode]) -> DocumentResult<()> {
+        let tree_id = self.file_tree_id()?;
+        let files_id = self.files_id()?;
+        self.ensure_cache()?;
+
+        let doomed_ids: HashSet<&str> = doomed.iter().map(|node| node.id.as_str()).collect();
+        let kept_paths: HashSet<String> = self
+            .tree_cache
+            .borrow()
+            .nodes
+            .values()
+            .filter(|node| !doomed_ids.contains(node.id.as_str()))
+            .map(|node| node.path.clone())
+            .collect();
+
+        for node in doomed {
+            if !node.is_dir
+                && !kept_paths.contains(&node.path)
+                && self.doc.get(&files_id, node.path.as_str())?.is_some()
+            {
+                self.doc.delete(&files_id, node.path.as_str())?;
+            }
+            self.doc.delete(&tree_id, node.id.as_str())?;
+        }
+
+        self.cache_dirty.set(true);
         Ok(())
     }
 
+    /// Repair a document holding nodes whose parent is gone, left behind by
+    /// deletes that did not take the descendants along, and file contents no
+    /// node points at. Returns how many nodes and stray contents were
+    /// removed.
+    pub fn sweep_orphans(&mut self) -> DocumentResult<usize> {
+        self.ensure_cache()?;
+        let orphans: Vec<FileTreeNode> = {
+            let cache = self.tree_cache.borrow();
+            cache
+                .nodes
+                .values()
+                .filter(|node| {
+                    // Walk up until a top-level node or a missing parent; a
+                    // cycle left by concurrent moves is not an orphan
+                    let mut current = *node;
+                    for _ in 0..cache.nodes.len() {
+                        match &current.parent_id {
+                            None => return false,
+                            Some(parent) => match cache.nodes.get(parent) {
+                                Some(parent) => current = parent,
+                                None => return true,
+                            },
+                        }
+                    }
+                    false
+                })
+                .cloned()
+                .collect()
+        };
+        self.remove_nodes(&orphans)?;
+
+        let files_id = self.files_id()?;
+        self.ensure_cache()?;
+        let stray: Vec<String> = {
+            let cache = self.tree_cache.borrow();
+            self.doc
+                .keys(&files_id)
+                .filter(|path| !cache.paths.contains_key(path))
+                .collect()
+        };
+        for path in &stray {
+            self.doc.delete(&files_id, path.as_str())?;
+        }
+
+        Ok(orphans.len() + stray.len())
+    }
+
+    /// Check the file tree's structural invariants: file nodes have content,
+    /// children lists name existing nodes, and parents exist without looping
+    pub fn validate(&self) -> DocumentResult<Vec<IntegrityIssue>> {
+        self.ensure_cache()?;
+        let files_id = self.files_id()?;
+        let cache = self.tree_cache.borrow();
+
+        let mut nodes: Vec<&FileTreeNode> = cache.nodes.values().collect();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut issues = Vec::new();
+        for node in nodes {
+            if !node.is_dir && self.doc.get(&files_id, node.path.as_str())?.is_none() {
+                issues.push(IntegrityIssue::MissingContent {
+                    node_id: node.id.clone(),
+                    path: node.path.clone(),
+                });
+            }
+            for child in node.children.iter().filter(|id| !cache.nodes.contains_key(*id)) {
+                issues.push(IntegrityIssue::DanglingChild {
+                    parent_id: node.id.clone(),
+                    child_id: child.clone(),
+                });
+            }
+            if let Some(parent) = &node.parent_id {
+                if !cache.nodes.contains_key(parent) {
+                    issues.push(IntegrityIssue::MissingParent {
+                        node_id: node.id.clone(),
+                        parent_id: parent.clone(),
+                    });
+                }
+            }
+        }
+        issues.extend(
+            parent_cycles(&cache.nodes)
+                .into_iter()
+                .map(|node_ids| IntegrityIssue::ParentCycle { node_ids }),
+        );
+        Ok(issues)
+    }
+
+    /// Fix what `validate` finds and return the issues that were fixed.
+    ///
+    /// Missing contents come back empty, dangling children are dropped,
+    /// orphans are swept, and a cycle is broken by moving its lowest ID to
+    /// the top level.
+    pub fn repair(&mut self) -> DocumentResult<Vec<IntegrityIssue>> {
+        let issues = self.validate()?;
+        let files_id = self.files_id()?;
+
+        for issue in &issues {
+            match issue {
+                IntegrityIssue::MissingContent { path, .. } => {
+                    let content_id = self.doc.put_object(&files_id, path.as_str(), ObjType::Map)?;
+                    self.doc.put_object(&content_id, keys::CONTENT, ObjType::Text)?;
+                    self.doc.put(&content_id, keys::LANGUAGE, detect_language(path))?;
+                    self.doc.put(&content_id, keys::VERSION, 1u64)?;
+                }
+                IntegrityIssue::DanglingChild { parent_id, child_id } => {
+                    self.remove_child_from_parent(parent_id, child_id)?;
+                }
+                IntegrityIssue::MissingParent { .. } => {}
+                IntegrityIssue::ParentCycle { node_ids } => {
+                    self.move_node(&node_ids[0], None)?;
+                }
+            }
+        }
+        if issues
+            .iter()
+            .any(|issue| matches!(issue, IntegrityIssue::MissingParent { .. }))
+        {
+            self.sweep_orphans()?;
+        }
+
+        self.cache_dirty.set(true);
+        Ok(issues)
+    }
+
     /// Get a file tree node by ID
     pub fn get_node(&self, node_id: &str) -> DocumentResult<Option<FileTreeNode>> {
-        let tree_id = self.file_tree_id()?;
+        self.ensure_cache()?;
+        Ok(self.tree_cache.borrow().nodes.get(node_id).cloned())
+    }
 
-        if let Some((_, node_obj)) = self.doc.get(&tree_id, node_id)? {
-            Ok(Some(self.read_tree_node(node_id, &node_obj)?))
+    /// Get a file tree node by path; with several nodes at the path, the one
+    /// with the lowest ID
+    pub fn get_node_by_path(&self, path: &str) -> DocumentResult<Option<FileTreeNode>> {
+        self.ensure_cache()?;
+        let cache = self.tree_cache.borrow();
+        Ok(cache.paths.get(path).and_then(|id| cache.nodes.get(id)).cloned())
+    }
+
+    /// The file tree as nested nodes, the way `FileTree::to_nested` gives it
+    /// to the frontend.
+    ///
+    /// The CRDT has no single root, so every node without a parent in the
+    /// tree is a root; roots are ordered by path, children keep their order.
+    pub fn to_nested_tree(&self) -> DocumentResult<Vec<NestedNode>> {
+        self.ensure_cache()?;
+        let cache = self.tree_cache.borrow();
+
+        let mut roots: Vec<&FileTreeNode> = cache
+            .nodes
+            .values()
+            .filter(|node| {
+                node.parent_id
+                    .as_ref()
+                    .is_none_or(|parent| !cache.nodes.contains_key(parent))
+            })
+            .collect();
+        roots.sort_by(|a, b| a.path.cmp(&b.path));
+
+        // Concurrent moves can link nodes into a cycle; each is shown once
+        let mut seen = HashSet::new();
+        roots
+            .into_iter()
+            .filter_map(|node| self.node_to_nested(&cache, node, &mut seen).transpose())
+            .collect()
+    }
+
+    fn node_to_nested(
+        &self,
+        cache: &TreeCache,
+        node: &FileTreeNode,
+        seen: &mut HashSet<String>,
+    ) -> DocumentResult<Option<NestedNode>> {
+        if !seen.insert(node.id.clone()) {
+            return Ok(None);
+        }
+
+        let mut children = Vec::with_capacity(node.children.len());
+        for child in node.children.iter().filter_map(|id| cache.nodes.get(id)) {
+            if let Some(nested) = self.node_to_nested(cache, child, seen)? {
+                children.push(nested);
+            }
+        }
+
+        let (language, size) = match self.get_file_content(&node.path)? {
+            Some(file) if !node.is_dir => (Some(file.language), file.content.len() as u64),
+            _ => (None, 0),
+        };
+        let extension = if node.is_dir {
+            None
         } else {
-            Ok(None)
+            std::path::Path::new(&node.path)
+                .extension()
+                .map(|e| e.to_string_lossy().to_string())
+        };
+
+        Ok(Some(NestedNode {
+            id: node.id.clone(),
+            name: node.name.clone(),
+            path: node.path.clone(),
+            is_dir: node.is_dir,
+            extension,
+            language,
+            size,
+            expanded: false,
+            children: if children.is_empty() { None } else { Some(children) },
+        }))
+    }
+
+    /// Paths held by more than one node
+    pub fn path_conflicts(&self) -> DocumentResult<Vec<PathConflict>> {
+        self.ensure_cache()?;
+        Ok(self.tree_cache.borrow().conflicts.clone())
+    }
+
+    /// Rebuild the tree cache if the document changed since it was last built
+    fn ensure_cache(&self) -> DocumentResult<()> {
+        if self.cache_dirty.get() {
+            self.rebuild_cache()?;
+        }
+        Ok(())
+    }
+
+    /// Read every file tree node out of the document into the cache
+    fn rebuild_cache(&self) -> DocumentResult<()> {
+        let tree_id = self.file_tree_id()?;
+        let mut cache = TreeCache::default();
+
+        for key in self.doc.keys(&tree_id) {
+            if let Some((Value::Object(ObjType::Map), node_obj)) = self.doc.get(&tree_id, key.clone())? {
+                let node = self.read_tree_node(&key, &node_obj)?;
+                // Keys come in order, so the lowest ID claims the path
+                if let Some(winner) = cache.paths.get(&node.path) {
+                    match cache.conflicts.iter_mut().find(|c| c.path == node.path) {
+                        Some(conflict) => conflict.node_ids.push(key.clone()),
+                        None => cache.conflicts.push(PathConflict {
+                            path: node.path.clone(),
+                            node_ids: vec![winner.clone(), key.clone()],
+                        }),
+                    }
+                } else {
+                    cache.paths.insert(node.path.clone(), key.clone());
+                }
+                cache.nodes.insert(key, node);
+            }
         }
+
+        *self.tree_cache.borrow_mut() = cache;
+        self.cache_dirty.set(false);
+        Ok(())
     }
 
     /// Read a file tree node from its object ID
@@ -544,17 +1055,11 @@ impl CollabDocument {
         })
     }
 
-    /// Get all nodes in the file tree
+    /// Get all nodes in the file tree, ordered by ID
     pub fn get_all_nodes(&self) -> DocumentResult<Vec<FileTreeNode>> {
-        let tree_id = self.file_tree_id()?;
-        let mut nodes = Vec::new();
-
-        for key in self.doc.keys(&tree_id) {
-            if let Some((Value::Object(ObjType::Map), node_obj)) = self.doc.get(&tree_id, key.clone())? {
-                nodes.push(self.read_tree_node(&key, &node_obj)?);
-            }
-        }
-
+        self.ensure_cache()?;
+        let mut nodes: Vec<FileTreeNode> = self.tree_cache.borrow().nodes.values().cloned().collect();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
         Ok(nodes)
     }
 
@@ -591,6 +1096,51 @@ impl CollabDocument {
         }
     }
 
+    /// Get a file's content as it was at `heads`, without checking out the
+    /// rest of the project. `None` if the file did not exist then.
+    pub fn get_file_at_heads(
+        &self,
+        path: &str,
+        heads: &[ChangeHash],
+    ) -> DocumentResult<Option<FileContent>> {
+        if let Some(unknown) = heads
+            .iter()
+            .find(|hash| ReadDoc::get_change_by_hash(&self.doc, hash).is_none())
+        {
+            return Err(DocumentError::InvalidOperation(format!(
+                "Unknown change: {}",
+                unknown
+            )));
+        }
+
+        let files_id = self.files_id()?;
+        let Some((Value::Object(ObjType::Map), content_obj)) =
+            self.doc.get_at(&files_id, path, heads)?
+        else {
+            return Ok(None);
+        };
+
+        let content = match self.doc.get_at(&content_obj, keys::CONTENT, heads)? {
+            Some((Value::Object(ObjType::Text), text_id)) => self.doc.text_at(&text_id, heads)?,
+            _ => String::new(),
+        };
+        let language = match self.doc.get_at(&content_obj, keys::LANGUAGE, heads)? {
+            Some((Value::Scalar(s), _)) => s.to_str().map(str::to_string),
+            _ => None,
+        };
+        let version = match self.doc.get_at(&content_obj, keys::VERSION, heads)? {
+            Some((Value::Scalar(s), _)) => s.to_u64(),
+            _ => None,
+        };
+
+        Ok(Some(FileContent {
+            path: path.to_string(),
+            content,
+            language: language.unwrap_or_else(|| "plaintext".to_string()),
+            version: version.unwrap_or(1),
+        }))
+    }
+
     /// Update file content using Text CRDT splice operation
     pub fn update_file_content(
         &mut self,
@@ -612,7 +1162,7 @@ impl CollabDocument {
                 let version = self.get_uint_prop(&content_obj, keys::VERSION)?.unwrap_or(0);
                 self.doc.put(&content_obj, keys::VERSION, version + 1)?;
 
-                self.cache_dirty = true;
+                self.cache_dirty.set(true);
                 Ok(())
             } else {
                 Err(DocumentError::Corruption(format!(
@@ -625,7 +1175,11 @@ impl CollabDocument {
         }
     }
 
-    /// Replace entire file content
+    /// Replace entire file content.
+    ///
+    /// Only the regions that differ from the current text are spliced, so
+    /// saving a whole editor buffer costs as much as the edit did. The
+    /// version only moves if something changed.
     pub fn set_file_content(&mut self, path: &str, content: &str) -> DocumentResult<()> {
         let files_id = self.files_id()?;
 
@@ -633,15 +1187,16 @@ impl CollabDocument {
             if let Some((Value::Object(ObjType::Text), text_id)) =
                 self.doc.get(&content_obj, keys::CONTENT)?
             {
-                // Get current length and replace all
-                let current_len = self.doc.text(&text_id)?.len();
-                self.doc.splice_text(&text_id, 0, current_len as isize, content)?;
+                let current = self.doc.text(&text_id)?;
+                if !self.splice_diff(&text_id, &current, content)? {
+                    return Ok(());
+                }
 
                 // Increment version
                 let version = self.get_uint_prop(&content_obj, keys::VERSION)?.unwrap_or(0);
                 self.doc.put(&content_obj, keys::VERSION, version + 1)?;
 
-                self.cache_dirty = true;
+                self.cache_dirty.set(true);
                 Ok(())
             } else {
                 Err(DocumentError::Corruption(format!(
@@ -654,6 +1209,42 @@ impl CollabDocument {
         }
     }
 
+    /// Turn `old` into `new` with one splice per changed region, returning
+    /// whether anything changed. Positions count chars, as Automerge does.
+    fn splice_diff(&mut self, text_id: &ObjId, old: &str, new: &str) -> DocumentResult<bool> {
+        let mut position = 0;
+        let mut deleted = 0;
+        let mut changed = false;
+
+        for chunk in dissimilar::diff(old, new) {
+            match chunk {
+                dissimilar::Chunk::Equal(text) => {
+                    if deleted > 0 {
+                        self.doc.splice_text(text_id, position, deleted, "")?;
+                        deleted = 0;
+                    }
+                    position += text.chars().count();
+                }
+                dissimilar::Chunk::Delete(text) => {
+                    deleted += text.chars().count() as isize;
+                    changed = true;
+                }
+                // A replacement is a delete and an insert in one splice
+                dissimilar::Chunk::Insert(text) => {
+                    self.doc.splice_text(text_id, position, deleted, text)?;
+                    deleted = 0;
+                    position += text.chars().count();
+                    changed = true;
+                }
+            }
+        }
+        if deleted > 0 {
+            self.doc.splice_text(text_id, position, deleted, "")?;
+        }
+
+        Ok(changed)
+    }
+
     /// Get a stable cursor position in a file
     pub fn get_cursor(&self, path: &str, position: usize) -> DocumentResult<Option<automerge::Cursor>> {
         let files_id = self.files_id()?;
@@ -690,6 +1281,267 @@ impl CollabDocument {
         }
     }
 
+    // =========================================================================
+    // Annotation Operations (Cursors into the Text CRDT)
+    // =========================================================================
+
+    /// The content map and text of a file
+    fn file_text(&self, path: &str) -> DocumentResult<(ObjId, ObjId)> {
+        let files_id = self.files_id()?;
+        let Some((Value::Object(ObjType::Map), content_obj)) = self.doc.get(&files_id, path)? else {
+            return Err(DocumentError::FileNotFound(path.to_string()));
+        };
+        match self.doc.get(&content_obj, keys::CONTENT)? {
+            Some((Value::Object(ObjType::Text), text_id)) => Ok((content_obj, text_id)),
+            _ => Err(DocumentError::Corruption(format!(
+                "Missing content text for file: {}",
+                path
+            ))),
+        }
+    }
+
+    /// Add an annotation to the text of `annotation.path`.
+    ///
+    /// The range is anchored to its first and last characters, so it grows
+    /// and shrinks with edits inside it and moves with edits before it.
+    pub fn add_annotation(&mut self, annotation: &Annotation) -> DocumentResult<()> {
+        if annotation.start >= annotation.end {
+            return Err(DocumentError::InvalidOperation(format!(
+                "Empty range {}..{}",
+                annotation.start, annotation.end
+            )));
+        }
+        self.write_annotation(annotation)
+    }
+
+    /// Store an annotation. A range whose text was deleted is empty; it is
+    /// anchored to the character after it, and lost at the end of the text.
+    fn write_annotation(&mut self, annotation: &Annotation) -> DocumentResult<()> {
+        let (content_obj, text_id) = self.file_text(&annotation.path)?;
+        let len = self.doc.length(&text_id);
+        if annotation.start > annotation.end || annotation.start >= len || annotation.end > len {
+            return Err(DocumentError::InvalidOperation(format!(
+                "Invalid range {}..{} in {} characters",
+                annotation.start, annotation.end, len
+            )));
+        }
+        let start = self.doc.get_cursor(&text_id, annotation.start, None)?;
+        let end = match annotation.end > annotation.start {
+            true => Some(self.doc.get_cursor(&text_id, annotation.end - 1, None)?),
+            false => None,
+        };
+
+        let annotations_id = match self.doc.get(&content_obj, keys::ANNOTATIONS)? {
+            Some((Value::Object(ObjType::Map), id)) => id,
+            _ => self
+                .doc
+                .put_object(&content_obj, keys::ANNOTATIONS, ObjType::Map)?,
+        };
+        let kind = match annotation.kind {
+            AnnotationKind::Comment => "comment",
+            AnnotationKind::Highlight => "highlight",
+        };
+
+        let obj = self
+            .doc
+            .put_object(&annotations_id, annotation.id.as_str(), ObjType::Map)?;
+        self.doc.put(&obj, keys::START, start.to_string())?;
+        if let Some(end) = end {
+            self.doc.put(&obj, keys::END, end.to_string())?;
+        }
+        self.doc.put(&obj, keys::KIND, kind)?;
+        self.doc.put(&obj, keys::AUTHOR, annotation.author_id.as_str())?;
+        self.doc
+            .put(&obj, keys::AUTHOR_NAME, annotation.author_name.as_str())?;
+        self.doc.put(&obj, keys::BODY, annotation.body.as_str())?;
+        self.doc.put(&obj, keys::CREATED_AT, annotation.created_at)?;
+        self.doc.put(&obj, keys::RESOLVED, annotation.resolved)?;
+        if let Some(by) = &annotation.resolved_by {
+            self.doc.put(&obj, keys::RESOLVED_BY, by.as_str())?;
+        }
+        Ok(())
+    }
+
+    /// Mark an annotation resolved or open, returning whether it was found
+    pub fn resolve_annotation(
+        &mut self,
+        path: &str,
+        id: &str,
+        resolved: bool,
+        by: &str,
+    ) -> DocumentResult<bool> {
+        let (content_obj, _) = self.file_text(path)?;
+        let Some((Value::Object(ObjType::Map), annotations_id)) =
+            self.doc.get(&content_obj, keys::ANNOTATIONS)?
+        else {
+            return Ok(false);
+        };
+        let Some((Value::Object(ObjType::Map), obj)) = self.doc.get(&annotations_id, id)? else {
+            return Ok(false);
+        };
+        self.doc.put(&obj, keys::RESOLVED, resolved)?;
+        self.doc.put(&obj, keys::RESOLVED_BY, by)?;
+        Ok(true)
+    }
+
+    /// Annotations of a file with their ranges in the current text, in text
+    /// order
+    pub fn get_annotations(&self, path: &str) -> DocumentResult<Vec<Annotation>> {
+        let (content_obj, text_id) = self.file_text(path)?;
+        let Some((Value::Object(ObjType::Map), annotations_id)) =
+            self.doc.get(&content_obj, keys::ANNOTATIONS)?
+        else {
+            return Ok(Vec::new());
+        };
+
+        let mut annotations = Vec::new();
+        for id in self.doc.keys(&annotations_id) {
+            let Some((Value::Object(ObjType::Map), obj)) = self.doc.get(&annotations_id, id.as_str())? else {
+                continue;
+            };
+            let Some(start) = self
+                .get_string_prop(&obj, keys::START)?
+                .and_then(|cursor| automerge::Cursor::try_from(cursor).ok())
+            else {
+                continue;
+            };
+            let end = self
+                .get_string_prop(&obj, keys::END)?
+                .and_then(|cursor| automerge::Cursor::try_from(cursor).ok());
+
+            // A cursor on a deleted character resolves to where it was, so
+            // the end only takes in its character while that is still there
+            let len = self.doc.length(&text_id);
+            let start = self.doc.get_cursor_position(&text_id, &start, None)?;
+            let end = match end {
+                Some(end) => {
+                    let last = self.doc.get_cursor_position(&text_id, &end, None)?;
+                    if last < len && self.doc.get_cursor(&text_id, last, None)? == end {
+                        last + 1
+                    } else {
+                        last
+                    }
+                }
+                None => start,
+            };
+
+            let kind = match self.get_string_prop(&obj, keys::KIND)?.as_deref() {
+                Some("highlight") => AnnotationKind::Highlight,
+                _ => AnnotationKind::Comment,
+            };
+            annotations.push(Annotation {
+                id,
+                path: path.to_string(),
+                start,
+                end: end.max(start),
+                kind,
+                author_id: self.get_string_prop(&obj, keys::AUTHOR)?.unwrap_or_default(),
+                author_name: self
+                    .get_string_prop(&obj, keys::AUTHOR_NAME)?
+                    .unwrap_or_default(),
+                body: self.get_string_prop(&obj, keys::BODY)?.unwrap_or_default(),
+                created_at: self.get_int_prop(&obj, keys::CREATED_AT)?.unwrap_or(0),
+                resolved: self.get_bool_prop(&obj, keys::RESOLVED)?.unwrap_or(false),
+                resolved_by: self.get_string_prop(&obj, keys::RESOLVED_BY)?,
+            });
+        }
+
+        annotations.sort_by(|a, b| {
+            (a.start, a.created_at, &a.id).cmp(&(b.start, b.created_at, &b.id))
+        });
+        Ok(annotations)
+    }
+
+    // =========================================================================
+    // Chat Operations (List CRDT)
+    // =========================================================================
+
+    /// Append a chat message to the end of the chat list
+    pub fn append_chat_message(&mut self, entry: &ChatEntry) -> DocumentResult<()> {
+        let chat_id = self.chat_id()?;
+        let len = self.doc.length(&chat_id);
+
+        let message_id = self.doc.insert_object(&chat_id, len, ObjType::Map)?;
+        self.doc.put(&message_id, keys::ID, entry.id.as_str())?;
+        self.doc
+            .put(&message_id, keys::AUTHOR, entry.author_id.as_str())?;
+        self.doc
+            .put(&message_id, keys::AUTHOR_NAME, entry.author_name.as_str())?;
+        self.doc
+            .put(&message_id, keys::CONTENT, entry.content.as_str())?;
+        self.doc
+            .put(&message_id, keys::TIMESTAMP, entry.timestamp)?;
+        Ok(())
+    }
+
+    /// Get up to `limit` chat messages sent before the `before` timestamp (or
+    /// the latest ones), oldest first.
+    ///
+    /// Messages appended concurrently by peers that were offline end up in
+    /// list order decided by Automerge, so they are sorted by timestamp.
+    pub fn get_chat_messages(
+        &self,
+        limit: usize,
+        before: Option<i64>,
+    ) -> DocumentResult<Vec<ChatEntry>> {
+        let mut messages = self.read_chat()?;
+        messages.retain(|entry| before.is_none_or(|before| entry.timestamp < before));
+        messages.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+
+        let skip = messages.len().saturating_sub(limit);
+        Ok(messages.split_off(skip))
+    }
+
+    /// Remove a chat message, returning whether it was there
+    pub fn delete_chat_message(&mut self, id: &str) -> DocumentResult<bool> {
+        let chat_id = self.chat_id()?;
+        let len = self.doc.length(&chat_id);
+        for i in 0..len {
+            if let Some((Value::Object(ObjType::Map), message_obj)) = self.doc.get(&chat_id, i)? {
+                if self.get_string_prop(&message_obj, keys::ID)?.as_deref() == Some(id) {
+                    self.doc.delete(&chat_id, i)?;
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Remove the oldest chat messages beyond the latest `keep`
+    pub fn trim_chat_messages(&mut self, keep: usize) -> DocumentResult<()> {
+        let chat_id = self.chat_id()?;
+        let excess = self.doc.length(&chat_id).saturating_sub(keep);
+        for _ in 0..excess {
+            self.doc.delete(&chat_id, 0)?;
+        }
+        Ok(())
+    }
+
+    /// Read every chat message in list order
+    fn read_chat(&self) -> DocumentResult<Vec<ChatEntry>> {
+        let chat_id = self.chat_id()?;
+        let len = self.doc.length(&chat_id);
+        let mut messages = Vec::with_capacity(len);
+        for i in 0..len {
+            if let Some((Value::Object(ObjType::Map), message_obj)) = self.doc.get(&chat_id, i)? {
+                messages.push(ChatEntry {
+                    id: self
+                        .get_string_prop(&message_obj, keys::ID)?
+                        .unwrap_or_default(),
+                    author_id: self
+                        .get_string_prop(&message_obj, keys::AUTHOR)?
+                        .unwrap_or_default(),
+                    author_name: self
+                        .get_string_prop(&message_obj, keys::AUTHOR_NAME)?
+                        .unwrap_or_default(),
+                    content: self.get_string_prop(&message_obj, keys::CONTENT)?.unwrap_or_default(),
+                    timestamp: self.get_int_prop(&message_obj, keys::TIMESTAMP)?.unwrap_or(0),
+                });
+            }
+        }
+        Ok(messages)
+    }
+
     // =========================================================================
     // Helper methods for reading properties
     // =========================================================================
@@ -731,6 +1583,47 @@ impl CollabDocument {
     }
 }
 
+/// The Automerge actor for changes the server makes on behalf of
+/// `identity`, a user ID or the peer ID of a guest. The same identity always
+/// gets the same actor, so the history can be traced back to people; clients
+/// keep random actors of their own, leaving the server the only writer
+/// under these.
+pub fn actor_for(identity: &str) -> ActorId {
+    let digest = Sha256::digest(format!("collab-actor:{}", identity).as_bytes());
+    ActorId::from(&digest[..16])
+}
+
+/// Sets of nodes whose parent references form a loop, each ordered by ID
+fn parent_cycles(nodes: &HashMap<String, FileTreeNode>) -> Vec<Vec<String>> {
+    let mut ids: Vec<&str> = nodes.keys().map(String::as_str).collect();
+    ids.sort();
+
+    let mut done: HashSet<&str> = HashSet::new();
+    let mut cycles = Vec::new();
+    for start in ids {
+        let mut trail: Vec<&str> = Vec::new();
+        let mut current = Some(start);
+        while let Some(id) = current {
+            if done.contains(id) {
+                break;
+            }
+            if let Some(pos) = trail.iter().position(|seen| *seen == id) {
+                let mut cycle: Vec<String> = trail[pos..].iter().map(|id| id.to_string()).collect();
+                cycle.sort();
+                cycles.push(cycle);
+                break;
+            }
+            trail.push(id);
+            current = nodes
+                .get(id)
+                .and_then(|node| node.parent_id.as_deref())
+                .filter(|parent| nodes.contains_key(*parent));
+        }
+        done.extend(trail);
+    }
+    cycles
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -849,6 +1742,128 @@ mod tests {
         assert!(folder.children.is_empty());
     }
 
+    #[test]
+    fn test_delete_subtree() {
+        let mut doc = CollabDocument::new("test").unwrap();
+        doc.create_folder("src", "src", "src", None).unwrap();
+        doc.create_folder("util", "util", "src/util", Some("src")).unwrap();
+        doc.create_file("main", "main.rs", "src/main.rs", Some("src"), "rust")
+            .unwrap();
+        doc.create_file("fmt", "fmt.rs", "src/util/fmt.rs", Some("util"), "rust")
+            .unwrap();
+        doc.create_file("readme", "README.md", "README.md", None, "markdown")
+            .unwrap();
+
+        doc.delete_node("src").unwrap();
+
+        let ids: Vec<String> = doc.get_all_nodes().unwrap().into_iter().map(|n| n.id).collect();
+        assert_eq!(ids, vec!["readme"]);
+        assert!(doc.get_file_content("src/main.rs").unwrap().is_none());
+        assert!(doc.get_file_content("src/util/fmt.rs").unwrap().is_none());
+        assert!(doc.get_file_content("README.md").unwrap().is_some());
+        assert_eq!(doc.sweep_orphans().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_sweep_orphans() {
+        let mut doc = CollabDocument::new("test").unwrap();
+        doc.create_folder("src", "src", "src", None).unwrap();
+        doc.create_folder("util", "util", "src/util", Some("src")).unwrap();
+        doc.create_file("fmt", "fmt.rs", "src/util/fmt.rs", Some("util"), "rust")
+            .unwrap();
+        doc.create_file("readme", "README.md", "README.md", None, "markdown")
+            .unwrap();
+
+        // The way deletes used to leave things: only the folder itself goes,
+        // and a file's tree node goes without its content
+        let tree_id = doc.file_tree_id().unwrap();
+        doc.automerge_mut().delete(&tree_id, "src").unwrap();
+        doc.automerge_mut().delete(&tree_id, "readme").unwrap();
+        assert_eq!(doc.get_all_nodes().unwrap().len(), 2);
+
+        // Two dangling nodes, taking their content along, and the README
+        // content nothing points at
+        assert_eq!(doc.sweep_orphans().unwrap(), 3);
+        assert!(doc.get_all_nodes().unwrap().is_empty());
+        assert!(doc.get_file_content("src/util/fmt.rs").unwrap().is_none());
+        assert!(doc.get_file_content("README.md").unwrap().is_none());
+        assert_eq!(doc.sweep_orphans().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_validate_and_repair() {
+        let mut doc = CollabDocument::new("test").unwrap();
+        doc.create_folder("src", "src", "src", None).unwrap();
+        doc.create_file("main", "main.rs", "src/main.rs", Some("src"), "rust")
+            .unwrap();
+        doc.create_file("lib", "lib.rs", "src/lib.rs", Some("src"), "rust")
+            .unwrap();
+        doc.create_folder("a", "a", "a", None).unwrap();
+        doc.create_folder("b", "b", "a/b", Some("a")).unwrap();
+        assert!(doc.validate().unwrap().is_empty());
+
+        // Content lost, a child deleted without its parent knowing, and two
+        // folders moved into each other concurrently
+        let tree_id = doc.file_tree_id().unwrap();
+        let files_id = doc.files_id().unwrap();
+        doc.automerge_mut().delete(&files_id, "src/main.rs").unwrap();
+        doc.automerge_mut().delete(&tree_id, "lib").unwrap();
+        let (_, a) = doc.automerge().get(&tree_id, "a").unwrap().unwrap();
+        doc.automerge_mut().put(&a, keys::PARENT, "b").unwrap();
+        doc.add_child_to_parent("b", "a").unwrap();
+
+        let issues = doc.validate().unwrap();
+        assert_eq!(
+            issues,
+            [
+                IntegrityIssue::MissingContent {
+                    node_id: "main".to_string(),
+                    path: "src/main.rs".to_string(),
+                },
+                IntegrityIssue::DanglingChild {
+                    parent_id: "src".to_string(),
+                    child_id: "lib".to_string(),
+                },
+                IntegrityIssue::ParentCycle {
+                    node_ids: vec!["a".to_string(), "b".to_string()],
+                },
+            ]
+        );
+
+        assert_eq!(doc.repair().unwrap(), issues);
+        assert!(doc.validate().unwrap().is_empty());
+        let main = doc.get_file_content("src/main.rs").unwrap().unwrap();
+        assert_eq!((main.content.as_str(), main.language.as_str()), ("", "rust"));
+        assert_eq!(doc.get_node("src").unwrap().unwrap().children, ["main"]);
+        let a = doc.get_node("a").unwrap().unwrap();
+        assert_eq!((a.parent_id, a.path.as_str()), (None, "a"));
+        assert_eq!(doc.get_node("b").unwrap().unwrap().path, "a/b");
+    }
+
+    #[test]
+    fn test_as_actor() {
+        let mut doc = CollabDocument::new("test").unwrap();
+        let own = doc.automerge().get_actor().clone();
+        let alice = actor_for("user-alice");
+        assert_eq!(alice, actor_for("user-alice"));
+        assert_ne!(alice, actor_for("user-bob"));
+
+        let heads = doc.get_heads();
+        doc.as_actor(alice.clone(), |doc| {
+            doc.create_file("main", "main.rs", "main.rs", None, "rust")
+        })
+        .unwrap();
+        doc.set_file_content("main.rs", "fn main() {}").unwrap();
+
+        let actors: Vec<ActorId> = doc
+            .get_changes_since(&heads)
+            .iter()
+            .map(|change| change.actor_id().clone())
+            .collect();
+        assert_eq!(actors, [alice, own.clone()]);
+        assert_eq!(doc.automerge().get_actor(), &own);
+    }
+
     #[test]
     fn test_rename_node() {
         let mut doc = CollabDocument::new("test").unwrap();
@@ -882,6 +1897,209 @@ mod tests {
         assert_eq!(file.parent_id, Some("folder2".to_string()));
     }
 
+    #[test]
+    fn test_tree_cache_invalidation() {
+        let mut doc = CollabDocument::new("test").unwrap();
+        doc.create_folder("src", "src", "src", None).unwrap();
+        assert_eq!(doc.get_node_by_path("src").unwrap().unwrap().id, "src");
+        assert!(!doc.cache_dirty.get());
+
+        // Every tree mutation is seen by the next lookup
+        doc.create_file("main", "main.rs", "src/main.rs", Some("src"), "rust")
+            .unwrap();
+        assert!(doc.cache_dirty.get());
+        assert_eq!(doc.get_node("src").unwrap().unwrap().children, vec!["main"]);
+        assert_eq!(
+            doc.get_node_by_path("src/main.rs").unwrap().unwrap().id,
+            "main"
+        );
+
+        doc.rename_node("main", "lib.rs").unwrap();
+        assert_eq!(doc.get_node("main").unwrap().unwrap().name, "lib.rs");
+
+        doc.delete_node("main").unwrap();
+        assert!(doc.get_node("main").unwrap().is_none());
+        assert!(doc.get_node_by_path("src/main.rs").unwrap().is_none());
+        assert!(doc.get_node("src").unwrap().unwrap().children.is_empty());
+
+        // Changes merged in from another peer too
+        let mut other = CollabDocument::load("test", &doc.save()).unwrap();
+        other.create_folder("docs", "docs", "docs", None).unwrap();
+        doc.apply_changes(other.get_changes_since(&[])).unwrap();
+        assert!(doc.get_node_by_path("docs").unwrap().is_some());
+
+        other.create_folder("tests", "tests", "tests", None).unwrap();
+        doc.merge(other.automerge_mut()).unwrap();
+        let ids: Vec<String> = doc
+            .get_all_nodes()
+            .unwrap()
+            .into_iter()
+            .map(|n| n.id)
+            .collect();
+        assert_eq!(ids, vec!["docs", "src", "tests"]);
+    }
+
+    #[test]
+    fn test_rename_updates_paths() {
+        let mut doc = CollabDocument::new("test").unwrap();
+        doc.create_folder("src", "src", "src", None).unwrap();
+        doc.create_folder("bin", "bin", "src/bin", Some("src")).unwrap();
+        doc.create_file("tool", "tool.rs", "src/bin/tool.rs", Some("bin"), "rust")
+            .unwrap();
+        doc.set_file_content("src/bin/tool.rs", "fn main() {}").unwrap();
+
+        doc.rename_node("src", "lib").unwrap();
+
+        assert!(doc.get_node_by_path("src").unwrap().is_none());
+        assert_eq!(doc.get_node_by_path("lib").unwrap().unwrap().id, "src");
+        assert_eq!(doc.get_node_by_path("lib/bin").unwrap().unwrap().id, "bin");
+        let tool = doc.get_node_by_path("lib/bin/tool.rs").unwrap().unwrap();
+        assert_eq!(tool.id, "tool");
+        assert_eq!(tool.name, "tool.rs");
+
+        // The content moved along with the file
+        assert!(doc.get_file_content("src/bin/tool.rs").unwrap().is_none());
+        let file = doc.get_file_content("lib/bin/tool.rs").unwrap().unwrap();
+        assert_eq!(file.content, "fn main() {}");
+        assert_eq!(file.language, "rust");
+
+        doc.rename_node("tool", "cli.rs").unwrap();
+        assert!(doc.get_file_content("lib/bin/cli.rs").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_move_updates_paths() {
+        let mut doc = CollabDocument::new("test").unwrap();
+        doc.create_folder("src", "src", "src", None).unwrap();
+        doc.create_folder("lib", "lib", "lib", None).unwrap();
+        doc.create_folder("util", "util", "src/util", Some("src")).unwrap();
+        doc.create_file("fmt", "fmt.rs", "src/util/fmt.rs", Some("util"), "rust")
+            .unwrap();
+        doc.set_file_content("src/util/fmt.rs", "pub fn fmt() {}").unwrap();
+
+        // A folder moves with everything below it
+        doc.move_node("util", Some("lib")).unwrap();
+        assert_eq!(doc.get_node("util").unwrap().unwrap().path, "lib/util");
+        assert_eq!(doc.get_node("fmt").unwrap().unwrap().path, "lib/util/fmt.rs");
+        assert!(doc.get_file_content("src/util/fmt.rs").unwrap().is_none());
+        assert_eq!(
+            doc.get_file_content("lib/util/fmt.rs").unwrap().unwrap().content,
+            "pub fn fmt() {}"
+        );
+
+        // And out to the top level
+        doc.move_node("fmt", None).unwrap();
+        assert_eq!(doc.get_node_by_path("fmt.rs").unwrap().unwrap().id, "fmt");
+        assert!(doc.get_node("util").unwrap().unwrap().children.is_empty());
+        assert!(doc.get_file_content("fmt.rs").unwrap().is_some());
+
+        // Content edits after the move land on the new path
+        doc.update_file_content("fmt.rs", 0, 0, "// moved\n").unwrap();
+        assert!(doc
+            .get_file_content("fmt.rs")
+            .unwrap()
+            .unwrap()
+            .content
+            .starts_with("// moved"));
+    }
+
+    #[test]
+    fn test_path_conflicts_after_merge() {
+        let mut doc1 = CollabDocument::new("test").unwrap();
+        let mut doc2 = CollabDocument::load("test", &doc1.save()).unwrap();
+
+        // Two peers create the same file offline
+        doc1.create_file("b", "main.rs", "main.rs", None, "rust").unwrap();
+        doc2.create_file("a", "main.rs", "main.rs", None, "rust").unwrap();
+        assert!(doc1.path_conflicts().unwrap().is_empty());
+
+        doc1.apply_changes(doc2.get_changes_since(&[])).unwrap();
+        assert_eq!(
+            doc1.path_conflicts().unwrap(),
+            vec![PathConflict {
+                path: "main.rs".to_string(),
+                node_ids: vec!["a".to_string(), "b".to_string()],
+            }]
+        );
+        assert_eq!(doc1.get_node_by_path("main.rs").unwrap().unwrap().id, "a");
+
+        // Renaming one of them resolves it
+        doc1.rename_node("b", "lib.rs").unwrap();
+        assert!(doc1.path_conflicts().unwrap().is_empty());
+        assert_eq!(doc1.get_node_by_path("lib.rs").unwrap().unwrap().id, "b");
+        assert_eq!(doc1.get_node_by_path("main.rs").unwrap().unwrap().id, "a");
+        assert!(doc1.get_file_content("main.rs").unwrap().is_some());
+        assert!(doc1.get_file_content("lib.rs").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_to_nested_tree() {
+        let mut doc = CollabDocument::new("test").unwrap();
+        doc.create_file("readme", "README.md", "README.md", None, "markdown")
+            .unwrap();
+        doc.create_folder("src", "src", "src", None).unwrap();
+        doc.create_file("main", "main.rs", "src/main.rs", Some("src"), "rust")
+            .unwrap();
+        doc.create_folder("util", "util", "src/util", Some("src")).unwrap();
+        doc.set_file_content("src/main.rs", "fn main() {}").unwrap();
+
+        let tree = doc.to_nested_tree().unwrap();
+        let roots: Vec<&str> = tree.iter().map(|n| n.path.as_str()).collect();
+        assert_eq!(roots, vec!["README.md", "src"]);
+
+        let src = &tree[1];
+        assert!(src.is_dir);
+        let children = src.children.as_ref().unwrap();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0].name, "main.rs");
+        assert_eq!(children[0].extension.as_deref(), Some("rs"));
+        assert_eq!(children[0].language.as_deref(), Some("rust"));
+        assert_eq!(children[0].size, 12);
+        assert!(children[1].is_dir && children[1].children.is_none());
+
+        // Serializes the way FileTree::to_nested does
+        let json = serde_json::to_value(&tree[0]).unwrap();
+        assert_eq!(json["name"], "README.md");
+        assert_eq!(json["is_dir"], false);
+    }
+
+    #[test]
+    fn test_set_file_content_diff() {
+        let mut doc = CollabDocument::new("test").unwrap();
+        doc.create_file("file", "main.rs", "main.rs", None, "rust")
+            .unwrap();
+        let original = "fn main() {\n    println!(\"héllo\");\n}\n".repeat(50);
+        doc.set_file_content("main.rs", &original).unwrap();
+        let heads = doc.get_heads();
+
+        // Changing a word in the middle only touches that word
+        let edited = original.replacen("héllo", "wörld", 1);
+        doc.set_file_content("main.rs", &edited).unwrap();
+        let file = doc.get_file_content("main.rs").unwrap().unwrap();
+        assert_eq!(file.content, edited);
+        assert_eq!(file.version, 3);
+        let patch: usize = doc
+            .get_changes_since(&heads)
+            .iter()
+            .map(|change| change.len())
+            .sum();
+        assert!(patch < 20, "{} ops for a word", patch);
+
+        // Several regions, including the very start and end
+        let edited = format!("// top\n{}// bottom\n", edited.replace("main", "run"));
+        doc.set_file_content("main.rs", &edited).unwrap();
+        assert_eq!(doc.get_file_content("main.rs").unwrap().unwrap().content, edited);
+        doc.set_file_content("main.rs", "").unwrap();
+        assert_eq!(doc.get_file_content("main.rs").unwrap().unwrap().content, "");
+
+        // Saving the same text is not a change
+        let version = doc.get_file_content("main.rs").unwrap().unwrap().version;
+        let heads = doc.get_heads();
+        doc.set_file_content("main.rs", "").unwrap();
+        assert_eq!(doc.get_file_content("main.rs").unwrap().unwrap().version, version);
+        assert!(doc.get_changes_since(&heads).is_empty());
+    }
+
     #[test]
     fn test_cursor_stability() {
         let mut doc = CollabDocument::new("test").unwrap();
@@ -903,6 +2121,201 @@ mod tests {
         assert_eq!(content.content, "Say Hello World");
     }
 
+    #[test]
+    fn test_get_file_at_heads() {
+        let mut doc = CollabDocument::new("test").unwrap();
+        let empty = doc.get_heads();
+        doc.create_file("file", "main.rs", "main.rs", None, "rust")
+            .unwrap();
+        doc.set_file_content("main.rs", "fn main() {}").unwrap();
+        let first = doc.get_heads();
+        doc.set_file_content("main.rs", "fn main() { run() }").unwrap();
+        doc.rename_node("file", "lib.rs").unwrap();
+
+        let file = doc.get_file_at_heads("main.rs", &first).unwrap().unwrap();
+        assert_eq!(file.content, "fn main() {}");
+        assert_eq!(file.language, "rust");
+        assert_eq!(file.version, 2);
+        assert!(doc.get_file_at_heads("main.rs", &empty).unwrap().is_none());
+        assert!(doc.get_file_at_heads("lib.rs", &first).unwrap().is_none());
+
+        // The present is untouched and unknown heads are refused
+        assert_eq!(
+            doc.get_file_content("lib.rs").unwrap().unwrap().content,
+            "fn main() { run() }"
+        );
+        let mut other = CollabDocument::new("other").unwrap();
+        assert!(matches!(
+            doc.get_file_at_heads("lib.rs", &other.get_heads()),
+            Err(DocumentError::InvalidOperation(_))
+        ));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut doc = CollabDocument::new("test").unwrap();
+        doc.create_folder("src", "src", "src", None).unwrap();
+        doc.create_file("main", "main.rs", "src/main.rs", Some("src"), "rust")
+            .unwrap();
+        doc.create_file("lib", "lib.rs", "src/lib.rs", Some("src"), "rust")
+            .unwrap();
+        doc.set_file_content("src/main.rs", "fn main() {}").unwrap();
+        doc.append_chat_message(&chat_entry("m1", "alice", "hi", 1))
+            .unwrap();
+
+        let copy = doc.snapshot("copy").unwrap();
+        assert_eq!(copy.project_id(), "copy");
+        let src = copy.get_node("src").unwrap().unwrap();
+        assert_eq!(src.children, ["main", "lib"]);
+        let main = copy.get_file_content("src/main.rs").unwrap().unwrap();
+        assert_eq!(main.content, "fn main() {}");
+        assert_eq!(main.language, "rust");
+        assert_eq!(copy.get_chat_messages(10, None).unwrap().len(), 1);
+        assert!(copy.validate().unwrap().is_empty());
+
+        // The copy has its own history
+        let original = doc.get_heads();
+        assert!(ReadDoc::get_change_by_hash(copy.automerge(), &original[0]).is_none());
+    }
+
+    fn annotation(id: &str, path: &str, start: usize, end: usize) -> Annotation {
+        Annotation {
+            id: id.to_string(),
+            path: path.to_string(),
+            start,
+            end,
+            kind: AnnotationKind::Comment,
+            author_id: "peer-1".to_string(),
+            author_name: "Alice".to_string(),
+            body: "Why?".to_string(),
+            created_at: 1_700_000_000,
+            resolved: false,
+            resolved_by: None,
+        }
+    }
+
+    #[test]
+    fn test_annotations() {
+        let mut doc = CollabDocument::new("test").unwrap();
+        doc.create_folder("src", "src", "src", None).unwrap();
+        doc.create_file("main", "main.rs", "src/main.rs", Some("src"), "rust")
+            .unwrap();
+        doc.set_file_content("src/main.rs", "let x = 1;").unwrap();
+
+        // "x" and "1;"
+        doc.add_annotation(&annotation("b", "src/main.rs", 8, 10)).unwrap();
+        doc.add_annotation(&annotation("a", "src/main.rs", 4, 5)).unwrap();
+        assert!(doc.add_annotation(&annotation("c", "src/main.rs", 5, 5)).is_err());
+        assert!(doc.add_annotation(&annotation("c", "src/main.rs", 8, 11)).is_err());
+        assert!(doc.add_annotation(&annotation("c", "nope.rs", 0, 1)).is_err());
+
+        // Ranges move with edits before them and grow with edits inside
+        doc.update_file_content("src/main.rs", 0, 0, "// hi\n").unwrap();
+        doc.update_file_content("src/main.rs", 15, 0, "00").unwrap();
+        assert_eq!(
+            doc.get_file_content("src/main.rs").unwrap().unwrap().content,
+            "// hi\nlet x = 100;"
+        );
+        let ranges: Vec<_> = doc
+            .get_annotations("src/main.rs")
+            .unwrap()
+            .into_iter()
+            .map(|a| (a.id, a.start, a.end))
+            .collect();
+        assert_eq!(ranges, vec![("a".to_string(), 10, 11), ("b".to_string(), 14, 18)]);
+
+        // Deleting the annotated text leaves an empty range where it was
+        doc.update_file_content("src/main.rs", 10, 1, "").unwrap();
+        let a = &doc.get_annotations("src/main.rs").unwrap()[0];
+        assert_eq!((a.start, a.end), (10, 10));
+
+        assert!(doc.resolve_annotation("src/main.rs", "b", true, "peer-2").unwrap());
+        assert!(!doc.resolve_annotation("src/main.rs", "zzz", true, "peer-2").unwrap());
+
+        // Renames take the annotations along, resolved state included
+        doc.rename_node("src", "lib").unwrap();
+        let moved = doc.get_annotations("lib/main.rs").unwrap();
+        assert_eq!(moved.len(), 2);
+        assert_eq!((moved[1].start, moved[1].end), (13, 17));
+        assert!(moved[1].resolved);
+        assert_eq!(moved[1].resolved_by.as_deref(), Some("peer-2"));
+
+        // And they survive a save and load
+        let loaded = CollabDocument::load("test", &doc.save()).unwrap();
+        assert_eq!(loaded.get_annotations("lib/main.rs").unwrap(), moved);
+    }
+
+    fn chat_entry(id: &str, author: &str, content: &str, timestamp: i64) -> ChatEntry {
+        ChatEntry {
+            id: id.to_string(),
+            author_id: author.to_string(),
+            author_name: author.to_uppercase(),
+            content: content.to_string(),
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn test_chat_messages() {
+        let mut doc = CollabDocument::new("test").unwrap();
+        for (i, content) in ["one", "two", "three", "four"].iter().enumerate() {
+            doc.append_chat_message(&chat_entry(
+                &format!("m{}", i),
+                "alice",
+                content,
+                i as i64 * 10,
+            ))
+            .unwrap();
+        }
+
+        let latest = doc.get_chat_messages(2, None).unwrap();
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[0].content, "three");
+        assert_eq!(latest[1], chat_entry("m3", "alice", "four", 30));
+
+        // Paging back from the oldest message shown
+        let older = doc.get_chat_messages(2, Some(latest[0].timestamp)).unwrap();
+        let contents: Vec<_> = older.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["one", "two"]);
+
+        assert!(doc.delete_chat_message("m1").unwrap());
+        assert!(!doc.delete_chat_message("m1").unwrap());
+        doc.trim_chat_messages(2).unwrap();
+        let contents: Vec<_> = doc
+            .get_chat_messages(10, None)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(contents, vec!["three", "four"]);
+    }
+
+    #[test]
+    fn test_concurrent_chat_merge() {
+        let mut doc1 = CollabDocument::new("test").unwrap();
+        doc1.append_chat_message(&chat_entry("m0", "alice", "hello", 100))
+            .unwrap();
+        let saved = doc1.save();
+        let mut doc2 = CollabDocument::load("test", &saved).unwrap();
+
+        // Both peers chat while offline
+        doc1.append_chat_message(&chat_entry("m2", "alice", "later", 300))
+            .unwrap();
+        doc2.append_chat_message(&chat_entry("m1", "bob", "earlier", 200))
+            .unwrap();
+
+        let changes = doc2.get_changes_since(&[]);
+        doc1.apply_changes(changes).unwrap();
+
+        let contents: Vec<_> = doc1
+            .get_chat_messages(10, None)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(contents, vec!["hello", "earlier", "later"]);
+    }
+
     #[test]
     fn test_concurrent_edits_simulation() {
         // Create two forks of the same document
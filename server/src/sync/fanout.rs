@@ -0,0 +1,104 @@
+//! Per-room broadcast fan-out.
+//!
+//! Handing a message to a peer takes that peer's lock, so delivering a
+//! broadcast inline would let one busy peer stall the handler that sent it
+//! and everyone waiting behind that handler. Each room instead owns a
+//! dispatcher task fed through a queue: broadcasting only enqueues, and the
+//! task delivers the room's messages in order.
+
+use parking_lot::RwLock;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc;
+
+use super::server::PeerConnection;
+use crate::telemetry;
+use collab_protocol::ServerMessage;
+
+/// A broadcast waiting for the dispatcher
+struct Delivery {
+    recipients: Vec<Arc<RwLock<PeerConnection>>>,
+    msg: ServerMessage,
+    queued_at: Instant,
+}
+
+/// Queue into a room's dispatcher task. The task ends once the room, and
+/// with it this handle, is dropped.
+pub struct Fanout {
+    tx: mpsc::UnboundedSender<Delivery>,
+}
+
+impl Fanout {
+    /// Start the dispatcher task; must be called within a Tokio runtime
+    pub fn spawn() -> Self {
+        let (tx, mut rx) = mpsc::unbounded_channel::<Delivery>();
+        tokio::spawn(async move {
+            while let Some(delivery) = rx.recv().await {
+                metrics::gauge!(telemetry::BROADCAST_QUEUE_DEPTH).decrement(1.0);
+                deliver(delivery);
+            }
+        });
+        Self { tx }
+    }
+
+    /// Queue `msg` for `recipients`
+    pub fn send(&self, recipients: Vec<Arc<RwLock<PeerConnection>>>, msg: ServerMessage) {
+        let delivery = Delivery {
+            recipients,
+            msg,
+            queued_at: Instant::now(),
+        };
+        if self.tx.send(delivery).is_ok() {
+            metrics::gauge!(telemetry::BROADCAST_QUEUE_DEPTH).increment(1.0);
+        }
+    }
+}
+
+fn deliver(delivery: Delivery) {
+    let mut sent = 0;
+    for peer in &delivery.recipients {
+        let started = Instant::now();
+        if peer.read().send(delivery.msg.clone()).is_ok() {
+            sent += 1;
+        }
+        metrics::histogram!(telemetry::BROADCAST_PEER_SEND).record(started.elapsed().as_secs_f64());
+    }
+
+    metrics::histogram!(telemetry::BROADCAST_DURATION)
+        .record(delivery.queued_at.elapsed().as_secs_f64());
+    metrics::histogram!(telemetry::BROADCAST_FANOUT).record(sent as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_delivers_in_order() {
+        let (tx, mut rx) = crate::sync::lanes::channel();
+        let peer = Arc::new(RwLock::new(PeerConnection::new(
+            "peer-1", "Alice", "#ff0000", "token-1", tx,
+        )));
+        let fanout = Fanout::spawn();
+        for peer_count in 1..=3 {
+            fanout.send(
+                vec![peer.clone()],
+                ServerMessage::PeerCountChanged {
+                    project_id: "project-1".to_string(),
+                    peer_count,
+                },
+            );
+        }
+        // Nothing is delivered by the caller itself
+        assert!(rx.try_recv().is_err());
+
+        let mut counts = Vec::new();
+        while counts.len() < 3 {
+            match rx.recv().await {
+                Some(ServerMessage::PeerCountChanged { peer_count, .. }) => counts.push(peer_count),
+                other => panic!("Unexpected message: {:?}", other),
+            }
+        }
+        assert_eq!(counts, [1, 2, 3]);
+    }
+}
@@ -0,0 +1,386 @@
+//! Plain file access to a project document for HTTP clients.
+//!
+//! CI bots and scripts read and write project files without joining over
+//! WebSocket. Writes go through the same CRDT operations as editor changes,
+//! so connected peers merge them like any other edit. Concurrent HTTP writers
+//! are kept apart with ETags: a file's ETag is a hash of its content, and a
+//! request carrying `If-Match` is refused when the file has changed since.
+//! The file tree has no content of its own, so its ETag is a hash of the
+//! document heads: it changes with every edit to the project.
+
+use automerge::ChangeHash;
+use collab_protocol::HostedEntry;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::document::{CollabDocument, DocumentError, DocumentResult, FileContent};
+use crate::room::NestedNode;
+use super::hosting::apply_tree_changes;
+
+/// Result of writing a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileWrite {
+    /// The file did not exist; carries the new ETag
+    Created(String),
+    /// The file was overwritten; carries the new ETag
+    Updated(String),
+    /// `If-Match` did not match; carries the current ETag, if the file exists
+    PreconditionFailed(Option<String>),
+}
+
+/// Files that appeared and disappeared between two versions of a document.
+/// A renamed or moved file shows up as deleted at its old path and created
+/// at its new one, and is also listed in `renamed`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileChanges {
+    pub created: Vec<String>,
+    pub deleted: Vec<String>,
+    /// Old and new path of each file that kept its node but not its path
+    pub renamed: Vec<(String, String)>,
+}
+
+impl FileChanges {
+    /// Compare two versions, given as the path of each file by node ID
+    pub fn between(before: &BTreeMap<String, String>, after: &BTreeMap<String, String>) -> Self {
+        let paths_before: BTreeSet<&String> = before.values().collect();
+        let paths_after: BTreeSet<&String> = after.values().collect();
+        Self {
+            created: paths_after
+                .difference(&paths_before)
+                .map(|path| path.to_string())
+                .collect(),
+            deleted: paths_before
+                .difference(&paths_after)
+                .map(|path| path.to_string())
+                .collect(),
+            renamed: before
+                .iter()
+                .filter_map(|(id, old)| {
+                    after
+                        .get(id)
+                        .filter(|new| *new != old)
+                        .map(|new| (old.clone(), new.clone()))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Result of deleting a file or folder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileDelete {
+    Deleted,
+    NotFound,
+    /// `If-Match` did not match; carries the current ETag, if any
+    PreconditionFailed(Option<String>),
+}
+
+/// Strong ETag (quoted) for a file's content
+pub fn etag(content: &str) -> String {
+    let digest = Sha256::digest(content.as_bytes());
+    format!("\"{}\"", hex::encode(&digest[..16]))
+}
+
+/// Strong ETag (quoted) for the state of a whole document
+pub fn heads_etag(heads: &[ChangeHash]) -> String {
+    let mut heads = heads.to_vec();
+    heads.sort();
+    let mut hasher = Sha256::new();
+    for head in &heads {
+        hasher.update(head.0);
+    }
+    format!("\"{}\"", hex::encode(&hasher.finalize()[..16]))
+}
+
+/// Check an `If-None-Match` header value against the current ETag.
+///
+/// True when the client's copy is current, so it can be answered with 304.
+/// Unlike `If-Match`, weak tags compare by their value.
+pub fn if_none_match(header: &str, current: &str) -> bool {
+    let current = current.trim_start_matches("W/");
+    header
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == current)
+}
+
+/// Check an `If-Match` header value against the current ETag.
+///
+/// `*` matches any existing file; otherwise one of the comma separated tags
+/// must equal the current one. Weak tags never match.
+pub fn if_match(header: &str, current: Option<&str>) -> bool {
+    let Some(current) = current else {
+        return false;
+    };
+    header
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag == current)
+}
+
+/// Normalize a request path to the document's form (`src/main.rs`).
+///
+/// Returns None for empty paths and paths that try to leave the project.
+pub fn normalize_path(path: &str) -> Option<String> {
+    let mut parts = Vec::new();
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => continue,
+            ".." => return None,
+            part => parts.push(part),
+        }
+    }
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("/"))
+    }
+}
+
+/// Read a file, along with its ETag
+pub fn read_file(
+    doc: &CollabDocument,
+    path: &str,
+) -> DocumentResult<Option<(FileContent, String)>> {
+    Ok(doc.get_file_content(path)?.map(|file| {
+        let tag = etag(&file.content);
+        (file, tag)
+    }))
+}
+
+/// Paths of every file in the document, without folders
+pub fn file_paths(doc: &CollabDocument) -> DocumentResult<BTreeSet<String>> {
+    Ok(doc
+        .get_all_nodes()?
+        .into_iter()
+        .filter(|node| !node.is_dir)
+        .map(|node| node.path)
+        .collect())
+}
+
+/// Path of every file, by node ID
+pub fn file_nodes(doc: &CollabDocument) -> DocumentResult<BTreeMap<String, String>> {
+    Ok(doc
+        .get_all_nodes()?
+        .into_iter()
+        .filter(|node| !node.is_dir)
+        .map(|node| (node.id, node.path))
+        .collect())
+}
+
+/// Path and content of every file, ordered by path
+pub fn file_contents(doc: &CollabDocument) -> DocumentResult<Vec<(String, String)>> {
+    let mut files = Vec::new();
+    for path in file_paths(doc)? {
+        if let Some(file) = doc.get_file_content(&path)? {
+            files.push((path, file.content));
+        }
+    }
+    Ok(files)
+}
+
+/// Read the file tree, along with its ETag
+pub fn read_tree(doc: &mut CollabDocument) -> DocumentResult<(Vec<NestedNode>, String)> {
+    let tag = heads_etag(&doc.get_heads());
+    Ok((doc.to_nested_tree()?, tag))
+}
+
+/// Write a file, creating it and its parent folders if needed
+pub fn write_file(
+    doc: &mut CollabDocument,
+    path: &str,
+    content: &str,
+    if_match_header: Option<&str>,
+) -> DocumentResult<FileWrite> {
+    let current = doc.get_file_content(path)?.map(|file| etag(&file.content));
+    if let Some(header) = if_match_header {
+        if !if_match(header, current.as_deref()) {
+            return Ok(FileWrite::PreconditionFailed(current));
+        }
+    }
+
+    let tag = etag(content);
+    if current.is_some() {
+        if current.as_deref() != Some(tag.as_str()) {
+            doc.set_file_content(path, content)?;
+        }
+        return Ok(FileWrite::Updated(tag));
+    }
+
+    // Folders first, then the file itself; existing ones are left alone
+    let mut entries: Vec<HostedEntry> = path
+        .match_indices('/')
+        .map(|(i, _)| HostedEntry {
+            path: path[..i].to_string(),
+            is_dir: true,
+            size: 0,
+        })
+        .collect();
+    entries.push(HostedEntry {
+        path: path.to_string(),
+        is_dir: false,
+        size: content.len() as u64,
+    });
+    apply_tree_changes(doc, &entries, &[])?;
+
+    if doc.get_file_content(path)?.is_none() {
+        // A file sits where one of the parent folders should be
+        return Err(DocumentError::FileNotFound(path.to_string()));
+    }
+    doc.set_file_content(path, content)?;
+    Ok(FileWrite::Created(tag))
+}
+
+/// Delete a file, or a folder with everything below it
+pub fn delete_file(
+    doc: &mut CollabDocument,
+    path: &str,
+    if_match_header: Option<&str>,
+) -> DocumentResult<FileDelete> {
+    if doc.get_node_by_path(path)?.is_none() {
+        return Ok(FileDelete::NotFound);
+    }
+
+    // Folders have no ETag, so only `*` matches them
+    if let Some(header) = if_match_header {
+        let current = doc.get_file_content(path)?.map(|file| etag(&file.content));
+        if header.trim() != "*" && !if_match(header, current.as_deref()) {
+            return Ok(FileDelete::PreconditionFailed(current));
+        }
+    }
+
+    apply_tree_changes(doc, &[], &[path.to_string()])?;
+    Ok(FileDelete::Deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_if_match() {
+        let tag = etag("hello");
+        assert!(if_match(&tag, Some(&tag)));
+        assert!(if_match(&format!("\"other\", {}", tag), Some(&tag)));
+        assert!(if_match("*", Some(&tag)));
+        assert!(!if_match("*", None));
+        assert!(!if_match("\"other\"", Some(&tag)));
+        assert!(!if_match(&format!("W/{}", tag), Some(&tag)));
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        let tag = etag("hello");
+        assert!(if_none_match(&tag, &tag));
+        assert!(if_none_match(&format!("W/{}", tag), &tag));
+        assert!(if_none_match(&format!("\"other\", {}", tag), &tag));
+        assert!(if_none_match("*", &tag));
+        assert!(!if_none_match("\"other\"", &tag));
+    }
+
+    #[test]
+    fn test_tree_etag_follows_heads() {
+        let mut doc = CollabDocument::new("project-1").unwrap();
+        write_file(&mut doc, "src/main.rs", "fn main() {}", None).unwrap();
+
+        let (tree, tag) = read_tree(&mut doc).unwrap();
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].path, "src");
+        assert_eq!(read_tree(&mut doc).unwrap().1, tag);
+
+        // Any change gives a new tag, even one the tree doesn't show
+        write_file(&mut doc, "src/main.rs", "fn main() { }", None).unwrap();
+        assert_ne!(read_tree(&mut doc).unwrap().1, tag);
+    }
+
+    #[test]
+    fn test_file_changes() {
+        let mut doc = CollabDocument::new("project-1").unwrap();
+        write_file(&mut doc, "src/main.rs", "", None).unwrap();
+        write_file(&mut doc, "README.md", "", None).unwrap();
+        let before = file_nodes(&doc).unwrap();
+        assert_eq!(before.len(), 2);
+
+        delete_file(&mut doc, "README.md", None).unwrap();
+        write_file(&mut doc, "src/lib.rs", "", None).unwrap();
+        let changes = FileChanges::between(&before, &file_nodes(&doc).unwrap());
+        assert_eq!(changes.created, vec!["src/lib.rs"]);
+        assert_eq!(changes.deleted, vec!["README.md"]);
+        assert!(changes.renamed.is_empty());
+
+        write_file(&mut doc, "src/lib.rs", "pub fn a() {}", None).unwrap();
+        assert_eq!(
+            file_contents(&doc).unwrap(),
+            vec![
+                ("src/lib.rs".to_string(), "pub fn a() {}".to_string()),
+                ("src/main.rs".to_string(), String::new()),
+            ]
+        );
+
+        // A renamed file keeps its node
+        let before = file_nodes(&doc).unwrap();
+        let id = before.iter().find(|(_, path)| *path == "src/lib.rs").unwrap().0;
+        doc.rename_node(id, "mod.rs").unwrap();
+        let changes = FileChanges::between(&before, &file_nodes(&doc).unwrap());
+        assert_eq!(changes.created, vec!["src/mod.rs"]);
+        assert_eq!(changes.deleted, vec!["src/lib.rs"]);
+        assert_eq!(
+            changes.renamed,
+            vec![("src/lib.rs".to_string(), "src/mod.rs".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(
+            normalize_path("src//main.rs").as_deref(),
+            Some("src/main.rs")
+        );
+        assert_eq!(normalize_path("/./a\\b.rs").as_deref(), Some("a/b.rs"));
+        assert_eq!(normalize_path("src/../secret"), None);
+        assert_eq!(normalize_path("/"), None);
+    }
+
+    #[test]
+    fn test_write_read_delete() {
+        let mut doc = CollabDocument::new("project-1").unwrap();
+
+        let created = write_file(&mut doc, "src/bin/tool.rs", "fn main() {}", None).unwrap();
+        assert_eq!(created, FileWrite::Created(etag("fn main() {}")));
+        let (file, tag) = read_file(&doc, "src/bin/tool.rs").unwrap().unwrap();
+        assert_eq!(file.content, "fn main() {}");
+        assert_eq!(file.language, "rust");
+        assert!(doc
+            .get_all_nodes()
+            .unwrap()
+            .iter()
+            .any(|node| node.path == "src/bin" && node.is_dir));
+
+        // A stale ETag is refused, the current one accepted
+        let stale = write_file(&mut doc, "src/bin/tool.rs", "x", Some("\"stale\"")).unwrap();
+        assert_eq!(stale, FileWrite::PreconditionFailed(Some(tag.clone())));
+        let updated = write_file(&mut doc, "src/bin/tool.rs", "fn main() { }", Some(&tag)).unwrap();
+        assert_eq!(updated, FileWrite::Updated(etag("fn main() { }")));
+
+        // If-Match on a missing file never matches
+        assert_eq!(
+            write_file(&mut doc, "new.rs", "", Some("*")).unwrap(),
+            FileWrite::PreconditionFailed(None)
+        );
+
+        assert_eq!(
+            delete_file(&mut doc, "src/bin/tool.rs", Some(&tag)).unwrap(),
+            FileDelete::PreconditionFailed(Some(etag("fn main() { }")))
+        );
+        assert_eq!(
+            delete_file(&mut doc, "src", None).unwrap(),
+            FileDelete::Deleted
+        );
+        assert!(read_file(&doc, "src/bin/tool.rs").unwrap().is_none());
+        assert!(doc.get_all_nodes().unwrap().is_empty());
+        assert_eq!(
+            delete_file(&mut doc, "src", None).unwrap(),
+            FileDelete::NotFound
+        );
+    }
+}
@@ -0,0 +1,303 @@
+//! Remote hosting of a folder that lives on a peer's machine.
+//!
+//! A peer hosts a project by uploading its folder tree (`HostProject`). File
+//! contents stay on the host until someone opens a file: the server then asks
+//! the host for it (`FileRequest`), stores the answer in the document and
+//! hands it to every peer that was waiting. The server never touches the
+//! host's disk, so it does not have to run on the host's machine.
+
+use collab_protocol::HostedEntry;
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+
+use super::document::{CollabDocument, DocumentResult};
+use super::{PeerId, ProjectId};
+use crate::room::detect_language;
+
+/// What happens when a peer opens a file of a project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostedFile {
+    /// The project is not hosted remotely
+    NotHosted,
+    /// The content is already in the document
+    Loaded,
+    /// First request for this file: the host must be asked
+    AskHost(PeerId),
+    /// The host was already asked; the peer has been queued
+    Waiting,
+}
+
+#[derive(Debug)]
+struct HostedProject {
+    host_peer_id: PeerId,
+    /// Files whose content has been received from the host
+    loaded: HashSet<String>,
+    /// Peers waiting for a file, keyed by path
+    waiting: HashMap<String, Vec<PeerId>>,
+}
+
+/// Hosts of remotely hosted projects and the state of their files
+#[derive(Debug, Default)]
+pub struct HostingRegistry {
+    projects: DashMap<ProjectId, Mutex<HostedProject>>,
+}
+
+impl HostingRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the host of a project
+    pub fn host_of(&self, project_id: &str) -> Option<PeerId> {
+        self.projects
+            .get(project_id)
+            .map(|project| project.lock().host_peer_id.clone())
+    }
+
+    /// Check if a peer hosts a project
+    pub fn is_host(&self, project_id: &str, peer_id: &str) -> bool {
+        self.host_of(project_id).as_deref() == Some(peer_id)
+    }
+
+    /// Make a peer the host of a project.
+    ///
+    /// Re-hosting by the same peer forgets which files were loaded, since the
+    /// tree is replaced. Fails with the current host if another peer hosts it.
+    pub fn set_host(&self, project_id: &str, peer_id: &str) -> Result<(), PeerId> {
+        let entry = self
+            .projects
+            .entry(project_id.to_string())
+            .or_insert_with(|| {
+                Mutex::new(HostedProject {
+                    host_peer_id: peer_id.to_string(),
+                    loaded: HashSet::new(),
+                    waiting: HashMap::new(),
+                })
+            });
+
+        let mut project = entry.lock();
+        if project.host_peer_id != peer_id {
+            return Err(project.host_peer_id.clone());
+        }
+        project.loaded.clear();
+        Ok(())
+    }
+
+    /// Register a peer opening a file
+    pub fn open_file(&self, project_id: &str, file_path: &str, peer_id: &str) -> HostedFile {
+        let Some(entry) = self.projects.get(project_id) else {
+            return HostedFile::NotHosted;
+        };
+        let mut project = entry.lock();
+
+        if project.loaded.contains(file_path) {
+            return HostedFile::Loaded;
+        }
+
+        let host = project.host_peer_id.clone();
+        let waiting = project.waiting.entry(file_path.to_string()).or_default();
+        let first = waiting.is_empty();
+        if !waiting.iter().any(|p| p == peer_id) {
+            waiting.push(peer_id.to_string());
+        }
+
+        if first {
+            HostedFile::AskHost(host)
+        } else {
+            HostedFile::Waiting
+        }
+    }
+
+    /// Mark a file as loaded and return the peers waiting for it
+    pub fn file_loaded(&self, project_id: &str, file_path: &str) -> Vec<PeerId> {
+        let Some(entry) = self.projects.get(project_id) else {
+            return Vec::new();
+        };
+        let mut project = entry.lock();
+
+        project.loaded.insert(file_path.to_string());
+        project.waiting.remove(file_path).unwrap_or_default()
+    }
+
+    /// The host could not provide a file; return the peers waiting for it
+    pub fn file_failed(&self, project_id: &str, file_path: &str) -> Vec<PeerId> {
+        self.projects
+            .get(project_id)
+            .and_then(|entry| entry.lock().waiting.remove(file_path))
+            .unwrap_or_default()
+    }
+
+    /// Forget deleted paths (and everything below them)
+    pub fn forget_files(&self, project_id: &str, deleted: &[String]) {
+        let Some(entry) = self.projects.get(project_id) else {
+            return;
+        };
+        let mut project = entry.lock();
+
+        project
+            .loaded
+            .retain(|path| !deleted.iter().any(|d| is_same_or_below(path, d)));
+    }
+
+    /// Stop hosting a project if the peer is its host.
+    ///
+    /// Returns the waiting requests as `(file_path, peer_id)` so they can be failed.
+    pub fn release(&self, project_id: &str, peer_id: &str) -> Vec<(String, PeerId)> {
+        let Some((_, project)) = self
+            .projects
+            .remove_if(project_id, |_, project| project.lock().host_peer_id == peer_id)
+        else {
+            return Vec::new();
+        };
+
+        project
+            .into_inner()
+            .waiting
+            .into_iter()
+            .flat_map(|(path, peers)| peers.into_iter().map(move |peer| (path.clone(), peer)))
+            .collect()
+    }
+}
+
+fn is_same_or_below(path: &str, ancestor: &str) -> bool {
+    path == ancestor
+        || path
+            .strip_prefix(ancestor)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Replace the document's file tree with the hosted entries
+pub fn replace_tree(doc: &mut CollabDocument, entries: &[HostedEntry]) -> DocumentResult<()> {
+    let wanted: HashSet<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+    let stale: Vec<String> = doc
+        .get_all_nodes()?
+        .into_iter()
+        .map(|node| node.path)
+        .filter(|path| !wanted.contains(path.as_str()))
+        .collect();
+
+    apply_tree_changes(doc, entries, &stale)
+}
+
+/// Delete and create tree nodes by path.
+///
+/// Entries whose parent folder is not in the tree are skipped; existing paths
+/// are left untouched.
+pub fn apply_tree_changes(
+    doc: &mut CollabDocument,
+    created: &[HostedEntry],
+    deleted: &[String],
+) -> DocumentResult<()> {
+    // Deepest nodes first so parents are still there while children go
+    let mut doomed: Vec<(String, String)> = doc
+        .get_all_nodes()?
+        .into_iter()
+        .filter(|node| deleted.iter().any(|d| is_same_or_below(&node.path, d)))
+        .map(|node| (node.path, node.id))
+        .collect();
+    doomed.sort_by_key(|(path, _)| std::cmp::Reverse(path.matches('/').count()));
+    for (_, id) in doomed {
+        doc.delete_node(&id)?;
+    }
+
+    let mut ids: HashMap<String, String> = doc
+        .get_all_nodes()?
+        .into_iter()
+        .map(|node| (node.path, node.id))
+        .collect();
+
+    let mut created: Vec<&HostedEntry> = created.iter().collect();
+    created.sort_by_key(|entry| entry.path.matches('/').count());
+
+    for entry in created {
+        if entry.path.is_empty() || ids.contains_key(&entry.path) {
+            continue;
+        }
+
+        let (parent_id, name) = match entry.path.rsplit_once('/') {
+            Some((parent, name)) => match ids.get(parent) {
+                Some(id) => (Some(id.clone()), name),
+                None => continue,
+            },
+            None => (None, entry.path.as_str()),
+        };
+
+        let id = uuid::Uuid::new_v4().to_string();
+        if entry.is_dir {
+            doc.create_folder(&id, name, &entry.path, parent_id.as_deref())?;
+        } else {
+            let language = detect_language(&entry.path);
+            doc.create_file(&id, name, &entry.path, parent_id.as_deref(), &language)?;
+        }
+        ids.insert(entry.path.clone(), id);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, is_dir: bool) -> HostedEntry {
+        HostedEntry {
+            path: path.to_string(),
+            is_dir,
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn test_hosted_file_requests() {
+        let hosting = HostingRegistry::new();
+        assert_eq!(hosting.open_file("p", "a.rs", "guest"), HostedFile::NotHosted);
+
+        hosting.set_host("p", "host").unwrap();
+        assert_eq!(hosting.set_host("p", "other"), Err("host".to_string()));
+
+        assert_eq!(
+            hosting.open_file("p", "a.rs", "guest-1"),
+            HostedFile::AskHost("host".to_string())
+        );
+        assert_eq!(hosting.open_file("p", "a.rs", "guest-2"), HostedFile::Waiting);
+
+        assert_eq!(hosting.file_loaded("p", "a.rs"), vec!["guest-1", "guest-2"]);
+        assert_eq!(hosting.open_file("p", "a.rs", "guest-3"), HostedFile::Loaded);
+
+        hosting.forget_files("p", &["a.rs".to_string()]);
+        assert!(matches!(hosting.open_file("p", "a.rs", "guest-3"), HostedFile::AskHost(_)));
+
+        assert!(hosting.release("p", "guest-3").is_empty());
+        assert_eq!(
+            hosting.release("p", "host"),
+            vec![("a.rs".to_string(), "guest-3".to_string())]
+        );
+        assert_eq!(hosting.host_of("p"), None);
+    }
+
+    #[test]
+    fn test_tree_changes() {
+        let mut doc = CollabDocument::new("p").unwrap();
+        let entries = vec![
+            entry("src/main.rs", false),
+            entry("src", true),
+            entry("README.md", false),
+            entry("orphan/file.rs", false),
+        ];
+        replace_tree(&mut doc, &entries).unwrap();
+
+        let mut paths: Vec<String> = doc.get_all_nodes().unwrap().into_iter().map(|n| n.path).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["README.md", "src", "src/main.rs"]);
+
+        apply_tree_changes(&mut doc, &[entry("docs", true)], &["src".to_string()]).unwrap();
+        let mut paths: Vec<String> = doc.get_all_nodes().unwrap().into_iter().map(|n| n.path).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["README.md", "docs"]);
+
+        replace_tree(&mut doc, &[entry("docs", true)]).unwrap();
+        let paths: Vec<String> = doc.get_all_nodes().unwrap().into_iter().map(|n| n.path).collect();
+        assert_eq!(paths, vec!["docs"]);
+    }
+}
@@ -0,0 +1,263 @@
+//! Priority lanes for the messages sent to a peer.
+//!
+//! Everything queued for a peer used to share one channel, so a burst of
+//! cursor updates could hold up the sync message or error behind it. Each
+//! message now goes into one of three lanes by its kind, and the connection
+//! takes from the most urgent lane that has something. A lane passed over
+//! [`STARVATION_LIMIT`] times in a row goes first once, so presence still
+//! flows while sync is busy. Messages within a lane keep their order.
+
+use tokio::sync::mpsc::{
+    self,
+    error::{SendError, TryRecvError},
+};
+
+use collab_protocol::ServerMessage;
+
+/// Messages a waiting lane lets through from more urgent lanes before it
+/// gets its turn
+pub const STARVATION_LIMIT: usize = 16;
+
+/// How urgent a message is, most urgent first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Lane {
+    /// Session, membership, chat and errors
+    Control,
+    /// Document changes and file contents
+    Sync,
+    /// Cursors, presence and other updates the next one replaces
+    Presence,
+}
+
+impl Lane {
+    const COUNT: usize = 3;
+
+    /// The lane a message travels in
+    pub fn of(msg: &ServerMessage) -> Self {
+        match msg {
+            ServerMessage::SyncMessage { .. }
+            | ServerMessage::SyncComplete { .. }
+            | ServerMessage::FileContent { .. }
+            | ServerMessage::FileNotFound { .. }
+            | ServerMessage::FileRequest { .. }
+            | ServerMessage::FileVersion { .. } => Lane::Sync,
+            ServerMessage::CursorBroadcast { .. }
+            | ServerMessage::PresenceBroadcast { .. }
+            | ServerMessage::ReactionBroadcast { .. }
+            | ServerMessage::FileOpened { .. }
+            | ServerMessage::FileClosed { .. }
+            | ServerMessage::PeerCountChanged { .. } => Lane::Presence,
+            _ => Lane::Control,
+        }
+    }
+}
+
+/// Create the lanes of one peer's connection
+pub fn channel() -> (PeerSender, PeerReceiver) {
+    let (control_tx, control_rx) = mpsc::unbounded_channel();
+    let (sync_tx, sync_rx) = mpsc::unbounded_channel();
+    let (presence_tx, presence_rx) = mpsc::unbounded_channel();
+    let sender = PeerSender {
+        lanes: [control_tx, sync_tx, presence_tx],
+    };
+    let receiver = PeerReceiver {
+        lanes: [control_rx, sync_rx, presence_rx].map(|rx| LaneReceiver {
+            rx,
+            next: None,
+            passed_over: 0,
+        }),
+    };
+    (sender, receiver)
+}
+
+/// Queues messages for a peer, each in its lane
+#[derive(Debug, Clone)]
+pub struct PeerSender {
+    lanes: [mpsc::UnboundedSender<ServerMessage>; Lane::COUNT],
+}
+
+impl PeerSender {
+    /// Queue a message; fails once the connection is gone
+    #[allow(clippy::result_large_err)] // Same error as the mpsc sender it wraps
+    pub fn send(&self, msg: ServerMessage) -> Result<(), SendError<ServerMessage>> {
+        self.lanes[Lane::of(&msg) as usize].send(msg)
+    }
+}
+
+/// One lane, with the message taken off it but not yet handed out
+#[derive(Debug)]
+struct LaneReceiver {
+    rx: mpsc::UnboundedReceiver<ServerMessage>,
+    next: Option<ServerMessage>,
+    /// Messages taken from more urgent lanes while this one waited
+    passed_over: usize,
+}
+
+impl LaneReceiver {
+    /// Whether the lane has a message waiting
+    fn ready(&mut self) -> bool {
+        if self.next.is_none() {
+            self.next = self.rx.try_recv().ok();
+        }
+        self.next.is_some()
+    }
+}
+
+/// Takes a peer's messages, most urgent first
+#[derive(Debug)]
+pub struct PeerReceiver {
+    lanes: [LaneReceiver; Lane::COUNT],
+}
+
+impl PeerReceiver {
+    /// Wait for the next message; None once every sender is gone
+    pub async fn recv(&mut self) -> Option<ServerMessage> {
+        loop {
+            match self.try_recv() {
+                Ok(msg) => return Some(msg),
+                Err(TryRecvError::Disconnected) => return None,
+                Err(TryRecvError::Empty) => {}
+            }
+
+            let [control, sync, presence] = &mut self.lanes;
+            let (lane, msg) = tokio::select! {
+                biased;
+                Some(msg) = control.rx.recv() => (Lane::Control, msg),
+                Some(msg) = sync.rx.recv() => (Lane::Sync, msg),
+                Some(msg) = presence.rx.recv() => (Lane::Presence, msg),
+                else => return None,
+            };
+            self.lanes[lane as usize].next = Some(msg);
+        }
+    }
+
+    /// Take the next message if one is waiting
+    pub fn try_recv(&mut self) -> Result<ServerMessage, TryRecvError> {
+        let ready: Vec<bool> = self.lanes.iter_mut().map(LaneReceiver::ready).collect();
+        let starved = (0..Lane::COUNT)
+            .find(|&i| ready[i] && self.lanes[i].passed_over >= STARVATION_LIMIT);
+        let Some(pick) = starved.or_else(|| ready.iter().position(|&ready| ready)) else {
+            if !self.lanes.iter().all(|lane| lane.rx.is_closed()) {
+                return Err(TryRecvError::Empty);
+            }
+            // Something may have been sent just before the senders went away
+            if self.lanes.iter_mut().any(LaneReceiver::ready) {
+                return self.try_recv();
+            }
+            return Err(TryRecvError::Disconnected);
+        };
+
+        for (i, lane) in self.lanes.iter_mut().enumerate() {
+            if i == pick {
+                lane.passed_over = 0;
+            } else if i > pick && ready[i] {
+                lane.passed_over += 1;
+            }
+        }
+        Ok(self.lanes[pick].next.take().expect("Picked lane has a message"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor(line: u32) -> ServerMessage {
+        ServerMessage::CursorBroadcast {
+            project_id: "project-1".to_string(),
+            peer_id: "peer-2".to_string(),
+            peer_name: "Bob".to_string(),
+            peer_color: "#00ff00".to_string(),
+            file_path: "main.rs".to_string(),
+            line,
+            column: 0,
+            selection_end: None,
+        }
+    }
+
+    fn sync(byte: u8) -> ServerMessage {
+        ServerMessage::SyncMessage {
+            project_id: "project-1".to_string(),
+            sync_data: vec![byte],
+            from_peer: None,
+        }
+    }
+
+    fn pong(timestamp: u64) -> ServerMessage {
+        ServerMessage::Pong {
+            timestamp,
+            server_time: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_urgent_lanes_go_first() {
+        let (tx, mut rx) = channel();
+        for line in 0..5 {
+            tx.send(cursor(line)).unwrap();
+        }
+        tx.send(sync(1)).unwrap();
+        tx.send(pong(1)).unwrap();
+
+        assert!(matches!(rx.recv().await, Some(ServerMessage::Pong { .. })));
+        assert!(matches!(rx.recv().await, Some(ServerMessage::SyncMessage { .. })));
+        for line in 0..5 {
+            match rx.recv().await {
+                Some(ServerMessage::CursorBroadcast { line: got, .. }) => assert_eq!(got, line),
+                other => panic!("Unexpected message: {:?}", other),
+            }
+        }
+        assert!(matches!(rx.try_recv(), Err(TryRecvError::Empty)));
+
+        drop(tx);
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_order_kept_within_lane() {
+        let (tx, mut rx) = channel();
+        for i in 0..50u8 {
+            tx.send(sync(i)).unwrap();
+            tx.send(pong(i as u64)).unwrap();
+        }
+
+        let mut syncs = Vec::new();
+        let mut pongs = Vec::new();
+        while let Ok(msg) = rx.try_recv() {
+            match msg {
+                ServerMessage::SyncMessage { sync_data, .. } => syncs.push(sync_data[0]),
+                ServerMessage::Pong { timestamp, .. } => pongs.push(timestamp as u8),
+                other => panic!("Unexpected message: {:?}", other),
+            }
+        }
+        let expected: Vec<u8> = (0..50).collect();
+        assert_eq!(syncs, expected);
+        assert_eq!(pongs, expected);
+    }
+
+    #[tokio::test]
+    async fn test_waiting_lane_is_not_starved() {
+        let (tx, mut rx) = channel();
+        tx.send(cursor(0)).unwrap();
+        for i in 0..100u8 {
+            tx.send(sync(i)).unwrap();
+        }
+
+        // The cursor gets through after at most STARVATION_LIMIT syncs
+        let position = (0..=STARVATION_LIMIT)
+            .position(|_| matches!(rx.try_recv(), Ok(ServerMessage::CursorBroadcast { .. })));
+        assert_eq!(position, Some(STARVATION_LIMIT));
+    }
+
+    #[tokio::test]
+    async fn test_recv_waits_for_any_lane() {
+        let (tx, mut rx) = channel();
+        let receiver = tokio::spawn(async move { rx.recv().await });
+        tokio::task::yield_now().await;
+        tx.send(cursor(7)).unwrap();
+        assert!(matches!(
+            receiver.await.unwrap(),
+            Some(ServerMessage::CursorBroadcast { line: 7, .. })
+        ));
+    }
+}
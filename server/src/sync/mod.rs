@@ -2,29 +2,30 @@
 //!
 //! This module implements the core synchronization logic using Automerge CRDTs.
 //! It provides:
-//! - Binary WebSocket protocol for efficient sync
+//! - Binary WebSocket protocol for efficient sync (defined in `collab-protocol`)
 //! - Per-peer sync state management
 //! - Document management with concurrent access
 //! - Presence and cursor synchronization
 
+pub mod activity;
+pub mod chat;
 pub mod document;
+pub mod fanout;
+pub mod files;
+pub mod hosting;
+pub mod lanes;
 pub mod presence;
-pub mod protocol;
+pub mod sharing;
 pub mod server;
+#[cfg(all(test, feature = "sim"))]
+mod sim;
 
 pub use document::CollabDocument;
 pub use server::{SyncServer, SyncServerConfig};
 
 use serde::{Deserialize, Serialize};
 
-/// Unique identifier for a project/document
-pub type ProjectId = String;
-
-/// Unique identifier for a peer/user
-pub type PeerId = String;
-
-/// Unique identifier for a file within a project
-pub type FileId = String;
+pub use collab_protocol::{PeerId, ProjectId};
 
 /// Actor ID for Automerge (derived from PeerId)
 pub type ActorId = String;
@@ -49,6 +50,8 @@ pub enum SyncError {
     ConnectionError(String),
     /// Authorization error
     Unauthorized(String),
+    /// The project is archived
+    Archived(ProjectId),
     /// Rate limited
     RateLimited,
     /// Internal server error
@@ -65,6 +68,10 @@ impl std::fmt::Display for SyncError {
             SyncError::StorageError(msg) => write!(f, "Storage error: {}", msg),
             SyncError::ConnectionError(msg) => write!(f, "Connection error: {}", msg),
             SyncError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
+            SyncError::Archived(_) => write!(
+                f,
+                "This project is archived. Its owner can unarchive it to keep working on it."
+            ),
             SyncError::RateLimited => write!(f, "Rate limited"),
             SyncError::Internal(msg) => write!(f, "Internal error: {}", msg),
         }
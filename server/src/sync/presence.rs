@@ -7,21 +7,89 @@
 //! - Typing indicators
 
 use dashmap::DashMap;
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::broadcast;
 
 use super::{PeerId, ProjectId};
 
-/// How long before a peer is considered idle (no activity)
-const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Default time before a peer is considered idle (no activity)
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
 
-/// How long before a peer is considered away
-const AWAY_TIMEOUT: Duration = Duration::from_secs(300);
+/// Default time before a peer is considered away
+const DEFAULT_AWAY_TIMEOUT: Duration = Duration::from_secs(300);
 
-/// How long to keep cursor data after peer disconnects
-const CURSOR_RETENTION: Duration = Duration::from_secs(5);
+/// Default time to keep cursor data after peer disconnects
+const DEFAULT_CURSOR_RETENTION: Duration = Duration::from_secs(5);
+
+/// How long clients should keep a reaction on screen
+pub const REACTION_TTL: Duration = Duration::from_secs(4);
+
+/// Maximum length of a reaction in characters (covers ZWJ emoji sequences)
+const MAX_REACTION_CHARS: usize = 16;
+
+/// Placeholder name used before a peer has identified itself
+pub const ANONYMOUS_NAME: &str = "Anonymous";
+
+/// Maximum display name length in characters
+pub const MAX_DISPLAY_NAME_CHARS: usize = 32;
+
+/// Attempts at picking an unused random name before falling back to a suffix
+const NAME_GENERATION_ATTEMPTS: usize = 16;
+
+/// Adjectives used for generated peer names
+const NAME_ADJECTIVES: &[&str] = &[
+    "Brave", "Calm", "Clever", "Curious", "Daring", "Eager", "Gentle", "Happy", "Jolly", "Keen",
+    "Lively", "Lucky", "Mighty", "Nimble", "Proud", "Quick", "Quiet", "Sharp", "Swift", "Witty",
+];
+
+/// Animals used for generated peer names
+const NAME_ANIMALS: &[&str] = &[
+    "Antelope", "Buffalo", "Cheetah", "Crane", "Eland", "Flamingo", "Gazelle", "Gecko", "Giraffe",
+    "Hippo", "Hornbill", "Ibis", "Impala", "Kudu", "Leopard", "Lion", "Meerkat", "Okapi",
+    "Pangolin", "Rhino", "Serval", "Weaver", "Zebra",
+];
+
+/// Tunable thresholds for presence status transitions
+#[derive(Debug, Clone)]
+pub struct PresenceConfig {
+    /// How long before a peer is considered idle (no activity)
+    pub idle_timeout: Duration,
+    /// How long before a peer is considered away
+    pub away_timeout: Duration,
+    /// How long to keep cursor data after peer disconnects
+    pub cursor_retention: Duration,
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            away_timeout: DEFAULT_AWAY_TIMEOUT,
+            cursor_retention: DEFAULT_CURSOR_RETENTION,
+        }
+    }
+}
+
+impl PresenceConfig {
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    pub fn with_away_timeout(mut self, timeout: Duration) -> Self {
+        self.away_timeout = timeout;
+        self
+    }
+
+    pub fn with_cursor_retention(mut self, retention: Duration) -> Self {
+        self.cursor_retention = retention;
+        self
+    }
+}
 
 /// Cursor position in a file
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -129,6 +197,8 @@ pub struct Presence {
     pub is_typing: bool,
     /// Files currently open by this peer
     pub open_files: Vec<String>,
+    /// Seed clients use to render a stable generated avatar
+    pub avatar_seed: String,
     /// Runtime-only last activity instant (not serialized)
     #[serde(skip)]
     last_active_instant: Option<Instant>,
@@ -148,10 +218,17 @@ impl Presence {
             last_active_ms: now.timestamp_millis(),
             is_typing: false,
             open_files: Vec::new(),
+            avatar_seed: generate_avatar_seed(),
             last_active_instant: Some(Instant::now()),
         }
     }
 
+    /// Use a specific avatar seed instead of a random one
+    pub fn with_avatar_seed(mut self, avatar_seed: impl Into<String>) -> Self {
+        self.avatar_seed = avatar_seed.into();
+        self
+    }
+
     /// Update the last activity timestamp and set status to active
     pub fn touch(&mut self) {
         self.last_active_ms = chrono::Utc::now().timestamp_millis();
@@ -165,15 +242,15 @@ impl Presence {
     }
 
     /// Update status based on inactivity
-    pub fn update_status(&mut self) {
+    pub fn update_status(&mut self, config: &PresenceConfig) {
         if self.status == PresenceStatus::Offline {
             return;
         }
 
         let elapsed = self.last_active().elapsed();
-        if elapsed > AWAY_TIMEOUT {
+        if elapsed > config.away_timeout {
             self.status = PresenceStatus::Away;
-        } else if elapsed > IDLE_TIMEOUT {
+        } else if elapsed > config.idle_timeout {
             self.status = PresenceStatus::Idle;
         }
     }
@@ -185,6 +262,14 @@ impl Presence {
         self.touch();
     }
 
+    /// Mark as disconnected, starting the cursor retention window
+    pub fn mark_offline(&mut self) {
+        self.last_active_ms = chrono::Utc::now().timestamp_millis();
+        self.last_active_instant = Some(Instant::now());
+        self.status = PresenceStatus::Offline;
+        self.is_typing = false;
+    }
+
     /// Clear cursor
     pub fn clear_cursor(&mut self) {
         self.cursor = None;
@@ -248,6 +333,50 @@ pub enum PresenceEvent {
         peer_id: PeerId,
         is_typing: bool,
     },
+    /// Peer opened a file in their editor
+    FileOpened {
+        project_id: ProjectId,
+        peer_id: PeerId,
+        file_path: String,
+    },
+    /// Peer closed a file in their editor
+    FileClosed {
+        project_id: ProjectId,
+        peer_id: PeerId,
+        file_path: String,
+    },
+    /// Peer changed their display name
+    Renamed {
+        project_id: ProjectId,
+        peer_id: PeerId,
+        name: String,
+    },
+}
+
+/// Colors handed out to peers, in order of preference
+pub const PEER_COLORS: &[&str] = &[
+    "#3b82f6", // blue
+    "#ef4444", // red
+    "#22c55e", // green
+    "#f59e0b", // amber
+    "#8b5cf6", // violet
+    "#ec4899", // pink
+    "#06b6d4", // cyan
+    "#f97316", // orange
+    "#14b8a6", // teal
+    "#a855f7", // purple
+    "#84cc16", // lime
+    "#6366f1", // indigo
+    "#d946ef", // fuchsia
+    "#0ea5e9", // sky
+];
+
+/// When each color was last handed out in a project
+#[derive(Debug, Default)]
+struct ColorUse {
+    /// Color -> value of `clock` when it was last assigned
+    last_used: HashMap<String, u64>,
+    clock: u64,
 }
 
 /// Manager for presence state within a project
@@ -257,20 +386,36 @@ pub struct ProjectPresence {
     project_id: ProjectId,
     /// Map of peer_id -> Presence
     peers: DashMap<PeerId, Presence>,
+    /// Colors handed out, so the least recently used is picked next
+    colors: Mutex<ColorUse>,
     /// Broadcast channel for presence events
     event_tx: broadcast::Sender<PresenceEvent>,
+    /// Status and retention thresholds
+    config: PresenceConfig,
 }
 
 impl ProjectPresence {
     pub fn new(project_id: impl Into<String>) -> Self {
+        Self::with_config(project_id, PresenceConfig::default())
+    }
+
+    /// Create with custom presence thresholds
+    pub fn with_config(project_id: impl Into<String>, config: PresenceConfig) -> Self {
         let (event_tx, _) = broadcast::channel(256);
         Self {
             project_id: project_id.into(),
             peers: DashMap::new(),
+            colors: Mutex::new(ColorUse::default()),
             event_tx,
+            config,
         }
     }
 
+    /// Get the project ID
+    pub fn project_id(&self) -> &str {
+        &self.project_id
+    }
+
     /// Subscribe to presence events
     pub fn subscribe(&self) -> broadcast::Receiver<PresenceEvent> {
         self.event_tx.subscribe()
@@ -294,6 +439,141 @@ impl ProjectPresence {
         Ok(())
     }
 
+    /// Add a peer, or bring its entry back if it is still kept offline from
+    /// an earlier connection, keeping its cursor and open files
+    pub fn join_peer(&self, presence: Presence) {
+        let Some(mut entry) = self.peers.get_mut(&presence.peer_id) else {
+            let _ = self.add_peer(presence);
+            return;
+        };
+        entry.name = presence.name;
+        entry.color = presence.color;
+        entry.avatar_seed = presence.avatar_seed;
+        entry.touch();
+
+        let _ = self.event_tx.send(PresenceEvent::StatusChanged {
+            project_id: self.project_id.clone(),
+            peer_id: entry.peer_id.clone(),
+            status: PresenceStatus::Active,
+            active_file: entry.active_file.clone(),
+        });
+    }
+
+    /// Remove the offline entry of a peer that reconnected under a new ID,
+    /// returning it so the new entry can carry on from it. Connected peers
+    /// are left alone.
+    pub fn take_over(&self, previous: &str) -> Option<Presence> {
+        self.peers
+            .remove_if(previous, |_, presence| presence.status == PresenceStatus::Offline)
+            .map(|(_, presence)| presence)
+    }
+
+    /// Check whether a display name is held by a connected peer (case-insensitive).
+    ///
+    /// Offline peers kept for the grace period do not hold on to their name.
+    pub fn is_name_taken(&self, name: &str, except_peer: Option<&str>) -> bool {
+        self.peers.iter().any(|entry| {
+            let presence = entry.value();
+            Some(presence.peer_id.as_str()) != except_peer
+                && presence.status != PresenceStatus::Offline
+                && presence.name.eq_ignore_ascii_case(name)
+        })
+    }
+
+    /// Pick a display name that is unique within the project.
+    ///
+    /// Blank or anonymous names are replaced by a generated one; names already
+    /// in use get a numeric suffix.
+    pub fn unique_name(&self, requested: &str) -> String {
+        let requested = requested.trim();
+
+        let base = if requested.is_empty() || requested.eq_ignore_ascii_case(ANONYMOUS_NAME) {
+            let generated = (0..NAME_GENERATION_ATTEMPTS)
+                .map(|_| generate_peer_name())
+                .find(|name| !self.is_name_taken(name, None));
+            match generated {
+                Some(name) => return name,
+                None => generate_peer_name(),
+            }
+        } else {
+            requested.chars().take(MAX_DISPLAY_NAME_CHARS).collect()
+        };
+
+        if !self.is_name_taken(&base, None) {
+            return base;
+        }
+
+        (2..)
+            .map(|n| format!("{} {}", base, n))
+            .find(|name| !self.is_name_taken(name, None))
+            .expect("unbounded suffix search")
+    }
+
+    /// Pick a color for a peer joining the project.
+    ///
+    /// A preferred `#rrggbb` color is used unless another peer has it.
+    /// Otherwise the pick is the least recently used palette color no peer
+    /// has, ties going to the one the peer ID hashes to (or the one after
+    /// it), so the same peer tends to get the same color. Peers kept offline
+    /// for the grace period still hold their color, since their cursor is
+    /// still shown. Only with more peers than colors are colors shared.
+    pub fn assign_color(&self, peer_id: &str, preferred: Option<&str>) -> String {
+        let mut colors = self.colors.lock();
+        let in_use: Vec<String> = self
+            .peers
+            .iter()
+            .filter(|entry| entry.key() != peer_id)
+            .map(|entry| entry.value().color.to_lowercase())
+            .collect();
+
+        let preferred = preferred
+            .map(|color| color.trim().to_lowercase())
+            .filter(|color| is_valid_color(color) && !in_use.contains(color));
+        let color = preferred.unwrap_or_else(|| {
+            let start = peer_color_index(peer_id);
+            let palette =
+                (0..PEER_COLORS.len()).map(|i| PEER_COLORS[(start + i) % PEER_COLORS.len()]);
+            let last_used = |color: &&str| colors.last_used.get(*color).copied().unwrap_or(0);
+            palette
+                .clone()
+                .filter(|color| !in_use.iter().any(|used| used == color))
+                .min_by_key(last_used)
+                .or_else(|| palette.min_by_key(last_used))
+                .unwrap_or(PEER_COLORS[start])
+                .to_string()
+        });
+
+        colors.clock += 1;
+        let clock = colors.clock;
+        colors.last_used.insert(color.clone(), clock);
+        color
+    }
+
+    /// Change a peer's display name, rejecting names held by other peers
+    pub fn rename_peer(&self, peer_id: &str, name: &str) -> Result<(), PresenceError> {
+        let name = validate_display_name(name)?;
+
+        if self.is_name_taken(&name, Some(peer_id)) {
+            return Err(PresenceError::NameTaken(name));
+        }
+
+        {
+            let mut presence = self
+                .peers
+                .get_mut(peer_id)
+                .ok_or_else(|| PresenceError::PeerNotFound(peer_id.to_string()))?;
+            presence.name = name.clone();
+        }
+
+        let _ = self.event_tx.send(PresenceEvent::Renamed {
+            project_id: self.project_id.clone(),
+            peer_id: peer_id.to_string(),
+            name,
+        });
+
+        Ok(())
+    }
+
     /// Remove a peer
     pub fn remove_peer(&self, peer_id: &str) -> Option<Presence> {
         let removed = self.peers.remove(peer_id).map(|(_, p)| p);
@@ -348,6 +628,23 @@ impl ProjectPresence {
         Ok(())
     }
 
+    /// Mark a peer as offline while keeping their cursor for the retention window
+    pub fn mark_offline(&self, peer_id: &str) -> Result<(), PresenceError> {
+        let mut entry = self.peers.get_mut(peer_id)
+            .ok_or_else(|| PresenceError::PeerNotFound(peer_id.to_string()))?;
+
+        entry.mark_offline();
+
+        let _ = self.event_tx.send(PresenceEvent::StatusChanged {
+            project_id: self.project_id.clone(),
+            peer_id: peer_id.to_string(),
+            status: PresenceStatus::Offline,
+            active_file: entry.active_file.clone(),
+        });
+
+        Ok(())
+    }
+
     /// Set typing indicator
     pub fn set_typing(&self, peer_id: &str, is_typing: bool) -> Result<(), PresenceError> {
         let mut entry = self.peers.get_mut(peer_id)
@@ -364,6 +661,42 @@ impl ProjectPresence {
         Ok(())
     }
 
+    /// Record that a peer opened a file
+    pub fn open_file(&self, peer_id: &str, file_path: &str) -> Result<(), PresenceError> {
+        let mut entry = self.peers.get_mut(peer_id)
+            .ok_or_else(|| PresenceError::PeerNotFound(peer_id.to_string()))?;
+
+        entry.open_file(file_path);
+        entry.active_file = Some(file_path.to_string());
+
+        let _ = self.event_tx.send(PresenceEvent::FileOpened {
+            project_id: self.project_id.clone(),
+            peer_id: peer_id.to_string(),
+            file_path: file_path.to_string(),
+        });
+
+        Ok(())
+    }
+
+    /// Record that a peer closed a file
+    pub fn close_file(&self, peer_id: &str, file_path: &str) -> Result<(), PresenceError> {
+        let mut entry = self.peers.get_mut(peer_id)
+            .ok_or_else(|| PresenceError::PeerNotFound(peer_id.to_string()))?;
+
+        entry.close_file(file_path);
+        if entry.cursor.as_ref().map(|c| c.file_path.as_str()) == Some(file_path) {
+            entry.clear_cursor();
+        }
+
+        let _ = self.event_tx.send(PresenceEvent::FileClosed {
+            project_id: self.project_id.clone(),
+            peer_id: peer_id.to_string(),
+            file_path: file_path.to_string(),
+        });
+
+        Ok(())
+    }
+
     /// Get presence for a specific peer
     pub fn get_peer(&self, peer_id: &str) -> Option<Presence> {
         self.peers.get(peer_id).map(|p| p.clone())
@@ -391,6 +724,15 @@ impl ProjectPresence {
             .collect()
     }
 
+    /// Get all peers that currently have a file open
+    pub fn get_peers_in_file(&self, file_path: &str) -> Vec<Presence> {
+        self.peers
+            .iter()
+            .filter(|entry| entry.open_files.iter().any(|p| p == file_path))
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
     /// Get number of peers
     pub fn peer_count(&self) -> usize {
         self.peers.len()
@@ -405,7 +747,7 @@ impl ProjectPresence {
     pub fn update_all_statuses(&self) {
         for mut entry in self.peers.iter_mut() {
             let old_status = entry.status;
-            entry.update_status();
+            entry.update_status(&self.config);
 
             if entry.status != old_status {
                 let _ = self.event_tx.send(PresenceEvent::StatusChanged {
@@ -418,20 +760,22 @@ impl ProjectPresence {
         }
     }
 
-    /// Clean up stale cursors from offline peers
-    pub fn cleanup_stale(&self) {
+    /// Clean up stale cursors from offline peers, returning the removed peer IDs
+    pub fn cleanup_stale(&self) -> Vec<PeerId> {
         let stale_peers: Vec<PeerId> = self.peers
             .iter()
             .filter(|e| {
                 e.status == PresenceStatus::Offline
-                    && e.last_active().elapsed() > CURSOR_RETENTION
+                    && e.last_active().elapsed() > self.config.cursor_retention
             })
             .map(|e| e.peer_id.clone())
             .collect();
 
-        for peer_id in stale_peers {
-            self.remove_peer(&peer_id);
+        for peer_id in &stale_peers {
+            self.remove_peer(peer_id);
         }
+
+        stale_peers
     }
 }
 
@@ -446,26 +790,47 @@ pub enum PresenceError {
 
     #[error("Invalid cursor position")]
     InvalidCursor,
+
+    #[error("Invalid display name: {0}")]
+    InvalidName(String),
+
+    #[error("Display name already in use: {0}")]
+    NameTaken(String),
 }
 
 /// Global presence manager across all projects
 pub struct PresenceManager {
     /// Map of project_id -> ProjectPresence
     projects: DashMap<ProjectId, Arc<ProjectPresence>>,
+    /// Thresholds applied to every project
+    config: PresenceConfig,
 }
 
 impl PresenceManager {
     pub fn new() -> Self {
+        Self::with_config(PresenceConfig::default())
+    }
+
+    /// Create with custom presence thresholds
+    pub fn with_config(config: PresenceConfig) -> Self {
         Self {
             projects: DashMap::new(),
+            config,
         }
     }
 
+    /// Get the presence configuration
+    pub fn config(&self) -> &PresenceConfig {
+        &self.config
+    }
+
     /// Get or create presence manager for a project
     pub fn get_or_create(&self, project_id: &str) -> Arc<ProjectPresence> {
         self.projects
             .entry(project_id.to_string())
-            .or_insert_with(|| Arc::new(ProjectPresence::new(project_id)))
+            .or_insert_with(|| {
+                Arc::new(ProjectPresence::with_config(project_id, self.config.clone()))
+            })
             .clone()
     }
 
@@ -479,6 +844,13 @@ impl PresenceManager {
         self.projects.remove(project_id).map(|(_, p)| p)
     }
 
+    /// Check whether a peer has presence in any project, offline or not
+    pub fn contains_peer(&self, peer_id: &str) -> bool {
+        self.projects
+            .iter()
+            .any(|project| project.get_peer(peer_id).is_some())
+    }
+
     /// Get total number of active peers across all projects
     pub fn total_peer_count(&self) -> usize {
         self.projects.iter().map(|p| p.peer_count()).sum()
@@ -496,11 +868,14 @@ impl PresenceManager {
         }
     }
 
-    /// Cleanup all stale data
-    pub fn cleanup_all(&self) {
+    /// Cleanup all stale data, returning the (project, peer) pairs whose grace period expired
+    pub fn cleanup_all(&self) -> Vec<(ProjectId, PeerId)> {
         // Clean up stale presence data
+        let mut expired = Vec::new();
         for entry in self.projects.iter() {
-            entry.cleanup_stale();
+            for peer_id in entry.cleanup_stale() {
+                expired.push((entry.key().clone(), peer_id));
+            }
         }
 
         // Remove empty projects
@@ -513,6 +888,8 @@ impl PresenceManager {
         for project_id in empty_projects {
             self.projects.remove(&project_id);
         }
+
+        expired
     }
 }
 
@@ -522,27 +899,72 @@ impl Default for PresenceManager {
     }
 }
 
-/// Helper to generate a random color for a peer
-pub fn generate_peer_color() -> String {
+/// Check that a reaction is a short, non-empty, single-line token
+pub fn is_valid_reaction(emoji: &str) -> bool {
+    let trimmed = emoji.trim();
+    !trimmed.is_empty()
+        && trimmed.chars().count() <= MAX_REACTION_CHARS
+        && !trimmed.chars().any(char::is_control)
+}
+
+/// Validate a requested display name, returning the trimmed name
+pub fn validate_display_name(name: &str) -> Result<String, PresenceError> {
+    let trimmed = name.trim();
+
+    if trimmed.is_empty() {
+        return Err(PresenceError::InvalidName("name is empty".to_string()));
+    }
+    if trimmed.chars().count() > MAX_DISPLAY_NAME_CHARS {
+        return Err(PresenceError::InvalidName(format!(
+            "name is longer than {} characters",
+            MAX_DISPLAY_NAME_CHARS
+        )));
+    }
+    if trimmed.chars().any(char::is_control) {
+        return Err(PresenceError::InvalidName(
+            "name contains control characters".to_string(),
+        ));
+    }
+    if trimmed.eq_ignore_ascii_case(ANONYMOUS_NAME) {
+        return Err(PresenceError::InvalidName("name is reserved".to_string()));
+    }
+
+    Ok(trimmed.to_string())
+}
+
+/// Helper to generate a random "Adjective Animal" display name
+pub fn generate_peer_name() -> String {
+    use rand::seq::SliceRandom;
+    let mut rng = rand::thread_rng();
+    let adjective = NAME_ADJECTIVES.choose(&mut rng).unwrap_or(&"Brave");
+    let animal = NAME_ANIMALS.choose(&mut rng).unwrap_or(&"Ibis");
+    format!("{} {}", adjective, animal)
+}
+
+/// Helper to generate a random avatar seed
+pub fn generate_avatar_seed() -> String {
     use rand::Rng;
-    let colors = [
-        "#3b82f6", // blue
-        "#ef4444", // red
-        "#22c55e", // green
-        "#f59e0b", // amber
-        "#8b5cf6", // violet
-        "#ec4899", // pink
-        "#06b6d4", // cyan
-        "#f97316", // orange
-        "#14b8a6", // teal
-        "#a855f7", // purple
-        "#84cc16", // lime
-        "#6366f1", // indigo
-        "#d946ef", // fuchsia
-        "#0ea5e9", // sky
-    ];
-    let idx = rand::thread_rng().gen_range(0..colors.len());
-    colors[idx].to_string()
+    format!("{:016x}", rand::thread_rng().gen::<u64>())
+}
+
+/// Color a peer starts out with, before a project assigns one
+pub fn peer_color(peer_id: &str) -> String {
+    PEER_COLORS[peer_color_index(peer_id)].to_string()
+}
+
+/// Palette position a peer ID hashes to (FNV-1a, stable across restarts)
+fn peer_color_index(peer_id: &str) -> usize {
+    let hash = peer_id.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    (hash % PEER_COLORS.len() as u64) as usize
+}
+
+/// Whether a color is written `#rrggbb`
+pub fn is_valid_color(color: &str) -> bool {
+    color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit())
 }
 
 #[cfg(test)]
@@ -613,6 +1035,30 @@ mod tests {
         assert!(matches!(result, Err(PresenceError::PeerExists(_))));
     }
 
+    #[test]
+    fn test_rejoin_after_disconnect() {
+        let project = ProjectPresence::new("test-project");
+        project.add_peer(Presence::new("peer-1", "Alice", "#ff0000")).unwrap();
+        project
+            .update_cursor("peer-1", Cursor::new("main.rs", 3, 1))
+            .unwrap();
+        project.mark_offline("peer-1").unwrap();
+
+        // Rejoining under the same ID brings the entry back
+        project.join_peer(Presence::new("peer-1", "Alice", "#00ff00"));
+        let alice = project.get_peer("peer-1").unwrap();
+        assert_eq!(alice.status, PresenceStatus::Active);
+        assert_eq!(alice.color, "#00ff00");
+        assert!(alice.cursor.is_some());
+
+        // Only offline entries can be taken over
+        assert!(project.take_over("peer-1").is_none());
+        project.mark_offline("peer-1").unwrap();
+        let old = project.take_over("peer-1").unwrap();
+        assert_eq!(old.cursor.unwrap().file_path, "main.rs");
+        assert!(project.is_empty());
+    }
+
     #[test]
     fn test_cursor_in_file() {
         let project = ProjectPresence::new("test-project");
@@ -650,10 +1096,148 @@ mod tests {
     }
 
     #[test]
-    fn test_generate_color() {
-        let color = generate_peer_color();
-        assert!(color.starts_with('#'));
-        assert_eq!(color.len(), 7);
+    fn test_presence_config_thresholds() {
+        let config = PresenceConfig::default()
+            .with_idle_timeout(Duration::ZERO)
+            .with_away_timeout(Duration::from_secs(3600));
+
+        let mut presence = Presence::new("peer-1", "Alice", "#ff0000");
+        std::thread::sleep(Duration::from_millis(5));
+        presence.update_status(&config);
+        assert_eq!(presence.status, PresenceStatus::Idle);
+
+        let config = config.with_away_timeout(Duration::ZERO);
+        presence.update_status(&config);
+        assert_eq!(presence.status, PresenceStatus::Away);
+
+        let manager = PresenceManager::with_config(config);
+        assert_eq!(manager.config().idle_timeout, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_offline_grace_period() {
+        let config = PresenceConfig::default().with_cursor_retention(Duration::from_secs(60));
+        let project = ProjectPresence::with_config("test-project", config);
+
+        let mut presence = Presence::new("peer-1", "Alice", "#ff0000");
+        presence.set_cursor(Cursor::new("/main.rs", 1, 1));
+        project.add_peer(presence).unwrap();
+
+        project.mark_offline("peer-1").unwrap();
+
+        // Still within the retention window: cursor remains visible
+        assert!(project.cleanup_stale().is_empty());
+        let peer = project.get_peer("peer-1").unwrap();
+        assert_eq!(peer.status, PresenceStatus::Offline);
+        assert!(peer.cursor.is_some());
+
+        let manager = PresenceManager::with_config(
+            PresenceConfig::default().with_cursor_retention(Duration::ZERO),
+        );
+        let project = manager.get_or_create("test-project");
+        project.add_peer(Presence::new("peer-1", "Alice", "#ff0000")).unwrap();
+        project.mark_offline("peer-1").unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+
+        let expired = manager.cleanup_all();
+        assert_eq!(expired, vec![("test-project".to_string(), "peer-1".to_string())]);
+        assert_eq!(manager.project_count(), 0);
+    }
+
+    #[test]
+    fn test_reaction_validation() {
+        assert!(is_valid_reaction("👍"));
+        assert!(is_valid_reaction("👩‍💻"));
+        assert!(!is_valid_reaction(""));
+        assert!(!is_valid_reaction("   "));
+        assert!(!is_valid_reaction("a\nb"));
+        assert!(!is_valid_reaction(&"x".repeat(64)));
+    }
+
+    #[test]
+    fn test_unique_names() {
+        let pp = ProjectPresence::new("proj");
+        pp.add_peer(Presence::new("peer-1", "Alice", "#ff0000")).unwrap();
+
+        assert_eq!(pp.unique_name("Bob"), "Bob");
+        assert_eq!(pp.unique_name("alice"), "alice 2");
+
+        let generated = pp.unique_name(ANONYMOUS_NAME);
+        assert_ne!(generated, ANONYMOUS_NAME);
+        assert_eq!(generated.split(' ').count(), 2);
+
+        // Offline peers release their name
+        pp.mark_offline("peer-1").unwrap();
+        assert_eq!(pp.unique_name("Alice"), "Alice");
+    }
+
+    #[test]
+    fn test_rename_peer() {
+        let pp = ProjectPresence::new("proj");
+        pp.add_peer(Presence::new("peer-1", "Alice", "#ff0000")).unwrap();
+        pp.add_peer(Presence::new("peer-2", "Bob", "#00ff00")).unwrap();
+
+        assert!(matches!(
+            pp.rename_peer("peer-2", "ALICE"),
+            Err(PresenceError::NameTaken(_))
+        ));
+        assert!(matches!(
+            pp.rename_peer("peer-2", "   "),
+            Err(PresenceError::InvalidName(_))
+        ));
+        assert!(matches!(
+            pp.rename_peer("peer-2", &"x".repeat(MAX_DISPLAY_NAME_CHARS + 1)),
+            Err(PresenceError::InvalidName(_))
+        ));
+
+        // Renaming to your own name with different case is fine
+        pp.rename_peer("peer-1", "alice").unwrap();
+        pp.rename_peer("peer-2", " Carol ").unwrap();
+        assert_eq!(pp.get_peer("peer-2").unwrap().name, "Carol");
+    }
+
+    #[test]
+    fn test_peer_color() {
+        let color = peer_color("peer-1");
+        assert!(is_valid_color(&color));
+        assert_eq!(peer_color("peer-1"), color);
+        assert!(!is_valid_color("blue"));
+        assert!(!is_valid_color("#12345g"));
+    }
+
+    #[test]
+    fn test_assign_colors() {
+        let pp = ProjectPresence::new("project-1");
+
+        // Every peer gets a different color while the palette lasts
+        let mut assigned = Vec::new();
+        for i in 0..PEER_COLORS.len() {
+            let peer_id = format!("peer-{}", i);
+            let color = pp.assign_color(&peer_id, None);
+            assert!(!assigned.contains(&color));
+            pp.add_peer(Presence::new(&peer_id, &peer_id, &color)).unwrap();
+            assigned.push(color);
+        }
+
+        // A peer that leaves frees its color for the next one
+        pp.remove_peer("peer-3");
+        assert_eq!(pp.assign_color("peer-new", None), assigned[3]);
+    }
+
+    #[test]
+    fn test_preferred_color() {
+        let pp = ProjectPresence::new("project-1");
+        assert_eq!(pp.assign_color("peer-1", Some("#ABCDEF")), "#abcdef");
+        pp.add_peer(Presence::new("peer-1", "Alice", "#abcdef"))
+            .unwrap();
+
+        // Taken or malformed preferences fall back to the palette
+        let color = pp.assign_color("peer-2", Some("#abcdef"));
+        assert!(PEER_COLORS.contains(&color.as_str()));
+        assert!(PEER_COLORS.contains(&pp.assign_color("peer-3", Some("red")).as_str()));
+
+        // Reassigning a peer ignores its own current color
+        assert_eq!(pp.assign_color("peer-1", Some("#abcdef")), "#abcdef");
     }
 
     #[test]
@@ -669,4 +1253,29 @@ mod tests {
         assert_eq!(presence.open_files.len(), 1);
         assert_eq!(presence.open_files[0], "/lib.rs");
     }
+
+    #[test]
+    fn test_peers_in_file() {
+        let project = ProjectPresence::new("test-project");
+        project.add_peer(Presence::new("peer-1", "Alice", "#ff0000")).unwrap();
+        project.add_peer(Presence::new("peer-2", "Bob", "#00ff00")).unwrap();
+
+        let mut events = project.subscribe();
+
+        project.open_file("peer-1", "/main.rs").unwrap();
+        project.open_file("peer-2", "/main.rs").unwrap();
+        project.open_file("peer-2", "/lib.rs").unwrap();
+
+        assert_eq!(project.get_peers_in_file("/main.rs").len(), 2);
+        assert_eq!(project.get_peers_in_file("/lib.rs").len(), 1);
+
+        project.close_file("peer-1", "/main.rs").unwrap();
+
+        let peers = project.get_peers_in_file("/main.rs");
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].peer_id, "peer-2");
+
+        assert!(matches!(events.try_recv(), Ok(PresenceEvent::FileOpened { .. })));
+        assert!(project.open_file("peer-3", "/main.rs").is_err());
+    }
 }
@@ -9,18 +9,43 @@
 //! ensuring that concurrent edits from multiple users are automatically merged
 //! without conflicts.
 
+use automerge::{ActorId, ChangeHash};
+use collab_protocol::{
+    ActivityEntry, AnnotationInfo, AnnotationKind, ChatHistoryItem, ErrorCode, HostedEntry,
+    PeerInfo, PresenceStatus, ServerMessage,
+};
 use dashmap::DashMap;
 use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use std::net::IpAddr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::{broadcast, mpsc};
+use tokio::sync::broadcast;
 use tracing::{debug, error, info, warn};
 
-use super::document::CollabDocument;
-use super::presence::{Presence, PresenceManager};
-use super::protocol::{PeerInfo, PresenceStatus, ServerMessage};
+use super::activity::{ActivityLog, DEFAULT_ACTIVITY_CAPACITY};
+use super::chat::{self, ChatConfig, ChatError, ChatModerator};
+use super::fanout::Fanout;
+use super::document::{
+    self, Annotation, ChatEntry, CollabDocument, DocumentError, DocumentResult, FileContent,
+    IntegrityIssue,
+};
+use super::files::{self, FileChanges, FileDelete, FileWrite};
+use super::sharing::{normalize_scope, ShareAccess, ShareError, ShareSigner, MAX_LINK_TTL};
+use super::hosting::{apply_tree_changes, replace_tree, HostedFile, HostingRegistry};
+use super::lanes::PeerSender;
+use super::presence::{
+    generate_avatar_seed, validate_display_name, Presence, PresenceConfig, PresenceError,
+    PresenceManager,
+};
 use super::{PeerId, ProjectId, SyncError, SyncResult};
-use crate::storage::{DocumentMetadata, DocumentStore};
+use crate::notify::ProjectEvent;
+use crate::room::NestedNode;
+use crate::telemetry;
+use crate::storage::{
+    AuditEvent, AuditRecord, ChatRecord, DocumentMetadata, DocumentStore, ProjectAcl, ProjectRole,
+    ShareLink, ShareRole,
+};
 
 /// Configuration for the SyncServer
 #[derive(Debug, Clone)]
@@ -35,8 +60,22 @@ pub struct SyncServerConfig {
     pub presence_interval: Duration,
     /// Cleanup interval for stale data
     pub cleanup_interval: Duration,
+    /// How long a room stays in memory after its last peer leaves
+    pub room_linger: Duration,
     /// Session timeout
     pub session_timeout: Duration,
+    /// Idle/away thresholds and cursor retention
+    pub presence: PresenceConfig,
+    /// Number of activity feed entries kept per project
+    pub activity_capacity: usize,
+    /// Secret share link tokens are signed with
+    pub share_secret: Vec<u8>,
+    /// Chat limits and history size
+    pub chat: ChatConfig,
+    /// Most entries kept in the audit log
+    pub audit_capacity: usize,
+    /// How long audit log entries are kept
+    pub audit_retention: Duration,
 }
 
 impl Default for SyncServerConfig {
@@ -47,11 +86,33 @@ impl Default for SyncServerConfig {
             save_interval: Duration::from_secs(5),
             presence_interval: Duration::from_millis(50),
             cleanup_interval: Duration::from_secs(60),
+            room_linger: Duration::from_secs(30),
             session_timeout: Duration::from_secs(300),
+            presence: PresenceConfig::default(),
+            activity_capacity: DEFAULT_ACTIVITY_CAPACITY,
+            // Links stop working on restart unless a secret is configured
+            share_secret: rand::random::<[u8; 32]>().to_vec(),
+            chat: ChatConfig::default(),
+            audit_capacity: 100_000,
+            audit_retention: Duration::from_secs(30 * 24 * 60 * 60),
         }
     }
 }
 
+impl SyncServerConfig {
+    /// Set the presence thresholds
+    pub fn with_presence(mut self, presence: PresenceConfig) -> Self {
+        self.presence = presence;
+        self
+    }
+
+    /// Set the secret share links are signed with
+    pub fn with_share_secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+        self.share_secret = secret.into();
+        self
+    }
+}
+
 /// A single peer connection with its sync state
 pub struct PeerConnection {
     /// Unique peer identifier
@@ -60,10 +121,22 @@ pub struct PeerConnection {
     pub name: String,
     /// Assigned color
     pub color: String,
+    /// Color the client asked for in its Hello
+    pub preferred_color: Option<String>,
+    /// Seed for the generated avatar
+    pub avatar_seed: String,
     /// Session token for reconnection
     pub session_token: String,
+    /// Account the peer signed in with, None for anonymous peers
+    pub user_id: Option<String>,
+    /// Address the connection came from
+    pub ip: Option<IpAddr>,
+    /// Session token of an earlier connection this one resumes
+    pub resumed_token: Option<String>,
     /// Channel to send messages to this peer
-    tx: mpsc::UnboundedSender<ServerMessage>,
+    tx: PeerSender,
+    /// When the connection was made
+    connected_at: Instant,
     /// Last activity timestamp
     last_active: Instant,
     /// Projects this peer has joined
@@ -76,14 +149,20 @@ impl PeerConnection {
         name: impl Into<String>,
         color: impl Into<String>,
         session_token: impl Into<String>,
-        tx: mpsc::UnboundedSender<ServerMessage>,
+        tx: PeerSender,
     ) -> Self {
         Self {
             peer_id: peer_id.into(),
             name: name.into(),
             color: color.into(),
+            preferred_color: None,
+            avatar_seed: generate_avatar_seed(),
             session_token: session_token.into(),
+            user_id: None,
+            ip: None,
+            resumed_token: None,
             tx,
+            connected_at: Instant::now(),
             last_active: Instant::now(),
             joined_projects: Vec::new(),
         }
@@ -135,6 +214,15 @@ struct ProjectRoom {
     last_active: RwLock<Instant>,
     /// Whether the document has unsaved changes
     dirty: RwLock<bool>,
+    /// When the last peer left; a new room starts out empty
+    emptied_at: RwLock<Option<Instant>>,
+    /// Delivers messages broadcast to the room's peers
+    fanout: Fanout,
+    /// Peer whose join created the project; it manages the project while
+    /// nobody owns it
+    creator: RwLock<Option<PeerId>>,
+    /// Whether the room was made for a new project that nobody joined yet
+    awaiting_creator: RwLock<bool>,
 }
 
 /// Per-peer sync state within a project
@@ -143,6 +231,13 @@ struct PeerSyncState {
     last_version: Mutex<u64>,
     /// Last sync timestamp
     last_sync: Instant,
+    /// Role under the project's access list or share link
+    role: ProjectRole,
+    /// Limits for peers that joined through a share link
+    access: Option<ShareAccess>,
+    /// Heads the peer is known to have: those of the document it was sent
+    /// on joining, then those of the last sync message it sent
+    known_heads: Mutex<Vec<ChangeHash>>,
 }
 
 impl ProjectRoom {
@@ -156,24 +251,62 @@ impl ProjectRoom {
             created_at: Instant::now(),
             last_active: RwLock::new(Instant::now()),
             dirty: RwLock::new(false),
+            emptied_at: RwLock::new(Some(Instant::now())),
+            fanout: Fanout::spawn(),
+            creator: RwLock::new(None),
+            awaiting_creator: RwLock::new(false),
+        }
+    }
+
+    /// Make a peer the creator of a new project, if nobody joined it yet
+    fn claim_creator(&self, peer_id: &str) {
+        let mut awaiting = self.awaiting_creator.write();
+        if *awaiting {
+            *awaiting = false;
+            *self.creator.write() = Some(peer_id.to_string());
         }
     }
 
+    /// Hand the creator's place over to the new connection of a peer
+    fn replace_creator(&self, previous: &str, peer_id: &str) {
+        let mut creator = self.creator.write();
+        if creator.as_deref() == Some(previous) {
+            *creator = Some(peer_id.to_string());
+        }
+    }
+
+    fn is_creator(&self, peer_id: &str) -> bool {
+        self.creator.read().as_deref() == Some(peer_id)
+    }
+
     /// Add a peer to the room
-    fn add_peer(&self, peer_id: &str) {
+    fn add_peer(&self, peer_id: &str, role: ProjectRole, access: Option<ShareAccess>) {
         self.peers.insert(
             peer_id.to_string(),
             PeerSyncState {
                 last_version: Mutex::new(0),
                 last_sync: Instant::now(),
+                role,
+                access,
+                known_heads: Mutex::new(Vec::new()),
             },
         );
         *self.last_active.write() = Instant::now();
+        *self.emptied_at.write() = None;
     }
 
     /// Remove a peer from the room
     fn remove_peer(&self, peer_id: &str) -> bool {
-        self.peers.remove(peer_id).is_some()
+        let removed = self.peers.remove(peer_id).is_some();
+        if removed && self.peers.is_empty() {
+            *self.emptied_at.write() = Some(Instant::now());
+        }
+        removed
+    }
+
+    /// How long the room has been without peers
+    fn empty_for(&self) -> Option<Duration> {
+        self.emptied_at.read().map(|at| at.elapsed())
     }
 
     /// Get the number of connected peers
@@ -207,6 +340,11 @@ impl ProjectRoom {
         *self.last_active.write() = Instant::now();
     }
 
+    /// Check whether the document has unsaved changes
+    fn is_dirty(&self) -> bool {
+        *self.dirty.read()
+    }
+
     /// Check and clear dirty flag
     fn take_dirty(&self) -> bool {
         let mut dirty = self.dirty.write();
@@ -222,33 +360,102 @@ impl ProjectRoom {
         Some(doc.save())
     }
 
-    /// Apply changes from a peer
+    /// Get the share link limits of a peer, None for full members
+    fn peer_access(&self, peer_id: &str) -> Option<ShareAccess> {
+        self.peers.get(peer_id).and_then(|state| state.access.clone())
+    }
+
+    /// Get the role of a peer in the room
+    fn peer_role(&self, peer_id: &str) -> Option<ProjectRole> {
+        self.peers.get(peer_id).map(|state| state.role)
+    }
+
+    /// Apply changes from a peer, returning the updated document and the
+    /// files the changes created and deleted
     fn apply_changes(
         &self,
         peer_id: &str,
         change_data: &[u8],
-    ) -> Result<Option<Vec<u8>>, SyncError> {
-        let _peer_state = self
+    ) -> Result<(Option<Vec<u8>>, FileChanges), SyncError> {
+        let (role, access) = self
             .peers
             .get(peer_id)
+            .map(|state| (state.role, state.access.clone()))
             .ok_or_else(|| SyncError::PeerNotFound(peer_id.to_string()))?;
+        if access.is_none() && role == ProjectRole::Viewer {
+            return Err(SyncError::Unauthorized(
+                "Viewers cannot edit this project".to_string(),
+            ));
+        }
 
         // For now, we treat incoming data as incremental changes
         // In a full implementation, this would use Automerge's sync protocol
         let mut doc = self.document.lock();
+        let mut file_changes = FileChanges::default();
 
         // Try to load and merge the changes
         if let Ok(mut other_doc) = CollabDocument::load(&self.project_id, change_data) {
             // Get changes from the other document
             let changes = other_doc.get_changes_since(&[]);
+            let peer_heads = other_doc.get_heads();
+
+            // Peers with a share link must stay within what it allows
+            if let Some(access) = access {
+                let mut preview = doc
+                    .fork()
+                    .map_err(|e| SyncError::AutomergeError(e.to_string()))?;
+                preview
+                    .apply_changes(changes.clone())
+                    .map_err(|e| SyncError::AutomergeError(e.to_string()))?;
+                let allowed = access
+                    .allows_change(&doc, &preview)
+                    .map_err(|e| SyncError::AutomergeError(e.to_string()))?;
+                if !allowed {
+                    return Err(SyncError::Unauthorized(
+                        "Share link does not allow this change".to_string(),
+                    ));
+                }
+            }
+
+            let known = doc.path_conflicts().unwrap_or_default();
+            let files_before = files::file_nodes(&doc).unwrap_or_default();
             doc.apply_changes(changes)
                 .map_err(|e| SyncError::AutomergeError(e.to_string()))?;
+            self.set_known_heads(peer_id, peer_heads);
+            file_changes =
+                FileChanges::between(&files_before, &files::file_nodes(&doc).unwrap_or_default());
+
+            // Concurrent creates or renames can leave two nodes at one path
+            match doc.path_conflicts() {
+                Ok(conflicts) => {
+                    for conflict in conflicts.iter().filter(|c| !known.contains(c)) {
+                        warn!(
+                            "Path {} in {} is held by nodes {:?}",
+                            conflict.path, self.project_id, conflict.node_ids
+                        );
+                    }
+                }
+                Err(e) => warn!("Failed to check paths in {}: {}", self.project_id, e),
+            }
         }
 
         self.mark_dirty();
 
         // Return updated document state
-        Ok(Some(doc.save()))
+        Ok((Some(doc.save()), file_changes))
+    }
+
+    fn set_known_heads(&self, peer_id: &str, heads: Vec<ChangeHash>) {
+        if let Some(state) = self.peers.get(peer_id) {
+            *state.known_heads.lock() = heads;
+        }
+    }
+
+    fn known_heads(&self, peer_id: &str) -> Vec<ChangeHash> {
+        self.peers
+            .get(peer_id)
+            .map(|state| state.known_heads.lock().clone())
+            .unwrap_or_default()
     }
 
     /// Get full document state for initial sync
@@ -265,13 +472,13 @@ impl ProjectRoom {
         f(&doc)
     }
 
-    /// Get document for mutation
-    fn with_document_mut<F, R>(&self, f: F) -> R
+    /// Get document for mutation, committing the changes under `actor`
+    fn with_document_mut<F, R>(&self, actor: ActorId, f: F) -> R
     where
         F: FnOnce(&mut CollabDocument) -> R,
     {
         let mut doc = self.document.lock();
-        let result = f(&mut doc);
+        let result = doc.as_actor(actor, f);
         self.mark_dirty();
         result
     }
@@ -287,29 +494,53 @@ pub struct SyncServer {
     peers: DashMap<PeerId, Arc<RwLock<PeerConnection>>>,
     /// Session token to peer ID mapping for reconnection
     sessions: DashMap<String, PeerId>,
+    /// Session tokens of disconnected peers still kept offline in some
+    /// project, so a reconnect under a new ID takes over their presence
+    departed: DashMap<String, PeerId>,
     /// Presence manager
     presence: Arc<PresenceManager>,
+    /// Recent activity per project
+    activity: Arc<ActivityLog>,
+    /// Chat limits
+    chat: ChatModerator,
+    /// Projects hosted from a peer's machine
+    hosting: HostingRegistry,
     /// Persistent storage
     storage: Arc<DocumentStore>,
     /// Server start time
     started_at: Instant,
     /// Shutdown signal
     shutdown_tx: broadcast::Sender<()>,
+    /// Project events for notifications
+    events: broadcast::Sender<ProjectEvent>,
+    /// Project list updates for lobby sockets
+    lobby: broadcast::Sender<ServerMessage>,
 }
 
 impl SyncServer {
     /// Create a new sync server
     pub fn new(storage: DocumentStore, config: SyncServerConfig) -> Self {
         let (shutdown_tx, _) = broadcast::channel(1);
+        let (events, _) = broadcast::channel(256);
+        let (lobby, _) = broadcast::channel(256);
+        let presence = Arc::new(PresenceManager::with_config(config.presence.clone()));
+        let activity = Arc::new(ActivityLog::new(config.activity_capacity));
+        let chat = ChatModerator::new(config.chat.clone());
         Self {
             config,
             rooms: DashMap::new(),
             peers: DashMap::new(),
             sessions: DashMap::new(),
-            presence: Arc::new(PresenceManager::new()),
+            departed: DashMap::new(),
+            presence,
+            activity,
+            chat,
+            hosting: HostingRegistry::new(),
             storage: Arc::new(storage),
             started_at: Instant::now(),
             shutdown_tx,
+            events,
+            lobby,
         }
     }
 
@@ -328,6 +559,60 @@ impl SyncServer {
         let _ = self.shutdown_tx.send(());
     }
 
+    /// Subscribe to project events
+    pub fn subscribe_events(&self) -> broadcast::Receiver<ProjectEvent> {
+        self.events.subscribe()
+    }
+
+    /// Publish a project event; dropped if nothing is subscribed
+    pub fn emit_event(&self, event: ProjectEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Publish an event and add a feed entry for each file created or
+    /// deleted in a project
+    fn emit_file_changes(&self, project_id: &str, peer_id: Option<&str>, changes: FileChanges) {
+        self.activity.record_file_changes(project_id, peer_id, &changes);
+        for path in changes.created {
+            self.emit_event(ProjectEvent::FileCreated {
+                project_id: project_id.to_string(),
+                path,
+            });
+        }
+        for path in changes.deleted {
+            self.emit_event(ProjectEvent::FileDeleted {
+                project_id: project_id.to_string(),
+                path,
+            });
+        }
+    }
+
+    /// Subscribe to project list updates
+    pub fn subscribe_lobby(&self) -> broadcast::Receiver<ServerMessage> {
+        self.lobby.subscribe()
+    }
+
+    /// Send a project list update to the lobby sockets
+    pub fn announce(&self, msg: ServerMessage) {
+        let _ = self.lobby.send(msg);
+    }
+
+    /// Tell the lobby sockets how many peers a project has now
+    fn announce_peer_count(&self, project_id: &str) {
+        if self.lobby.receiver_count() == 0 {
+            return;
+        }
+        let peer_count = self
+            .presence
+            .get(project_id)
+            .map(|p| p.peer_count())
+            .unwrap_or(0);
+        self.announce(ServerMessage::PeerCountChanged {
+            project_id: project_id.to_string(),
+            peer_count: peer_count as u32,
+        });
+    }
+
     /// Register a new peer connection
     pub fn register_peer(
         &self,
@@ -335,7 +620,7 @@ impl SyncServer {
         name: &str,
         color: &str,
         session_token: &str,
-        tx: mpsc::UnboundedSender<ServerMessage>,
+        tx: PeerSender,
     ) -> SyncResult<()> {
         let connection = PeerConnection::new(peer_id, name, color, session_token, tx);
 
@@ -356,10 +641,15 @@ impl SyncServer {
 
             // Remove session mapping
             self.sessions.remove(&peer.session_token);
+            self.chat.forget(peer_id);
+            if !peer.joined_projects.is_empty() {
+                self.departed
+                    .insert(peer.session_token.clone(), peer_id.to_string());
+            }
 
-            // Leave all projects
+            // Drop out of all projects, keeping presence around for the grace period
             for project_id in &peer.joined_projects {
-                let _ = self.leave_project(peer_id, project_id);
+                self.disconnect_from_project(&peer, project_id);
             }
 
             info!("Peer unregistered: {} ({})", peer.name, peer_id);
@@ -382,6 +672,68 @@ impl SyncServer {
         peer_id: &str,
         project_id: &str,
         request_state: bool,
+    ) -> SyncResult<ServerMessage> {
+        self.join_project_with_link(peer_id, project_id, request_state, None)
+            .await
+    }
+
+    /// Join a project, with the limits of a share link if a token is given.
+    ///
+    /// Without a link, the peer's account must be on the project's access
+    /// list, if it has one.
+    pub async fn join_project_with_link(
+        &self,
+        peer_id: &str,
+        project_id: &str,
+        request_state: bool,
+        share_token: Option<&str>,
+    ) -> SyncResult<ServerMessage> {
+        let archived = self
+            .storage
+            .get_metadata(project_id)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?
+            .is_some_and(|meta| meta.archived);
+        if archived {
+            return Err(SyncError::Archived(project_id.to_string()));
+        }
+
+        let (role, access) = match share_token {
+            Some(token) => {
+                let access = self
+                    .verify_share_token(project_id, token)
+                    .map_err(|e| SyncError::Unauthorized(e.to_string()))?;
+                let role = match access.role {
+                    ShareRole::Viewer => ProjectRole::Viewer,
+                    ShareRole::Editor => ProjectRole::Editor,
+                };
+                (role, Some(access))
+            }
+            None => {
+                let user_id = self
+                    .get_peer(peer_id)
+                    .and_then(|peer| peer.read().user_id.clone());
+                let role = self
+                    .project_role(project_id, user_id.as_deref())?
+                    .ok_or_else(|| {
+                        SyncError::Unauthorized(match user_id {
+                            Some(_) => "You do not have access to this project".to_string(),
+                            None => "Sign in to join this project".to_string(),
+                        })
+                    })?;
+                (role, None)
+            }
+        };
+        self.join_project_as(peer_id, project_id, request_state, role, access)
+            .await
+    }
+
+    async fn join_project_as(
+        &self,
+        peer_id: &str,
+        project_id: &str,
+        request_state: bool,
+        role: ProjectRole,
+        access: Option<ShareAccess>,
     ) -> SyncResult<ServerMessage> {
         // Get or create the project room
         let room = self.get_or_create_room(project_id).await?;
@@ -392,19 +744,81 @@ impl SyncServer {
         }
 
         // Add peer to room
-        room.add_peer(peer_id);
+        if access.is_none() && role != ProjectRole::Viewer {
+            room.claim_creator(peer_id);
+        }
+        room.add_peer(peer_id, role, access);
 
         // Update peer's joined projects
         if let Some(peer) = self.peers.get(peer_id) {
             peer.write().join_project(project_id);
         }
 
-        // Add to presence
+        // A resumed session takes over the presence its old connection left
+        // behind, instead of leaving it to time out
+        let project_presence = self.presence.get_or_create(project_id);
+        self.activity.watch(&project_presence);
+        let previous = self
+            .get_peer(peer_id)
+            .and_then(|peer| peer.read().resumed_token.clone())
+            .and_then(|token| self.departed.get(&token).map(|id| id.clone()))
+            .filter(|previous| previous != peer_id);
+        if let Some(previous) = &previous {
+            room.replace_creator(previous, peer_id);
+        }
+        let replaced = previous.and_then(|previous| project_presence.take_over(&previous));
+
+        // Add to presence under a name and color that are unique within the
+        // project
         if let Some(peer) = self.peers.get(peer_id) {
-            let peer = peer.read();
-            let presence = Presence::new(&peer.peer_id, &peer.name, &peer.color);
-            let project_presence = self.presence.get_or_create(project_id);
-            let _ = project_presence.add_peer(presence);
+            let mut peer = peer.write();
+            let name = project_presence.unique_name(&peer.name);
+            let preferred = peer
+                .preferred_color
+                .clone()
+                .or_else(|| replaced.as_ref().map(|old| old.color.clone()));
+            let color = project_presence.assign_color(&peer.peer_id, preferred.as_deref());
+            let renamed = name != peer.name || color != peer.color;
+            peer.name = name;
+            peer.color = color;
+
+            let mut presence = Presence::new(&peer.peer_id, &peer.name, &peer.color)
+                .with_avatar_seed(&peer.avatar_seed);
+            if let Some(old) = &replaced {
+                presence.cursor = old.cursor.clone();
+                presence.active_file = old.active_file.clone();
+                presence.open_files = old.open_files.clone();
+            }
+            project_presence.join_peer(presence);
+
+            // Let the client know which name and color it ended up with
+            if renamed {
+                let _ = peer.send(ServerMessage::DisplayNameChanged {
+                    project_id: project_id.to_string(),
+                    peer_id: peer.peer_id.clone(),
+                    name: peer.name.clone(),
+                    avatar_seed: peer.avatar_seed.clone(),
+                    color: Some(peer.color.clone()),
+                });
+            }
+        }
+
+        if let Some(peer) = self.get_peer(peer_id) {
+            self.audit(&peer.read(), project_id, AuditEvent::Joined);
+        }
+        if let Some(old) = replaced {
+            if !self.presence.contains_peer(&old.peer_id) {
+                self.departed.retain(|_, peer_id| *peer_id != old.peer_id);
+            }
+            self.broadcast_to_project(
+                project_id,
+                peer_id,
+                ServerMessage::PeerLeft {
+                    project_id: project_id.to_string(),
+                    peer_id: old.peer_id,
+                    reason: Some("reconnected".to_string()),
+                },
+            );
         }
 
         // Get list of other peers in the project
@@ -427,18 +841,30 @@ impl SyncServer {
                         },
                         active_file: presence.active_file,
                         joined_at: presence.joined_at,
+                        avatar_seed: presence.avatar_seed,
                     })
                     .collect()
             })
             .unwrap_or_default();
 
         // Get document state if requested
-        let document_state = if request_state {
-            Some(room.get_document_state())
+        let (document_state, document_since) = if request_state {
+            let (state, since) = self.document_state_for(&room, peer_id);
+            (Some(state), since)
         } else {
-            None
+            (None, None)
         };
 
+        // The tree is sent either way, so the peer can show it right away
+        let file_tree = room
+            .with_document(|doc| doc.to_nested_tree())
+            .map_err(|e| SyncError::AutomergeError(e.to_string()))
+            .and_then(|tree| {
+                serde_json::to_string(&tree).map_err(|e| SyncError::Internal(e.to_string()))
+            })
+            .inspect_err(|e| warn!("Failed to build file tree of {}: {}", project_id, e))
+            .ok();
+
         // Broadcast peer joined to others
         if let Some(peer) = self.peers.get(peer_id) {
             let peer = peer.read();
@@ -451,39 +877,176 @@ impl SyncServer {
                     status: PresenceStatus::Active,
                     active_file: None,
                     joined_at: chrono::Utc::now().timestamp(),
+                    avatar_seed: peer.avatar_seed.clone(),
                 },
             };
             // Send to all other peers in the room directly
             self.broadcast_to_project(project_id, peer_id, peer_joined_msg);
+            self.emit_event(ProjectEvent::PeerJoined {
+                project_id: project_id.to_string(),
+                peer_id: peer.peer_id.clone(),
+                peer_name: peer.name.clone(),
+            });
         }
 
+        self.announce_peer_count(project_id);
         info!("Peer {} joined project {}", peer_id, project_id);
 
         Ok(ServerMessage::ProjectJoined {
             project_id: project_id.to_string(),
             peers,
             document_state,
+            file_tree,
+            document_since,
         })
     }
 
+    /// The document for a peer joining a room: if it resumes a session the
+    /// room still has the heads of, only the changes made since, along with
+    /// those heads; otherwise the full document
+    fn document_state_for(
+        &self,
+        room: &ProjectRoom,
+        peer_id: &str,
+    ) -> (Vec<u8>, Option<Vec<String>>) {
+        let known = self
+            .get_peer(peer_id)
+            .and_then(|peer| peer.read().resumed_token.clone())
+            .and_then(|token| self.take_sync_state(&room.project_id, &token));
+
+        let mut doc = room.document.lock();
+        room.set_known_heads(peer_id, doc.get_heads());
+        if let Some(heads) = known {
+            if let Some(delta) = doc.save_since(&heads) {
+                debug!("Sending {} only the changes since its last session", peer_id);
+                return (delta, Some(heads.iter().map(|head| head.to_string()).collect()));
+            }
+        }
+        (doc.save(), None)
+    }
+
+    /// Remember the heads a disconnecting peer is known to have, so the
+    /// session can be resumed without sending the whole document again.
+    ///
+    /// These are not the room's heads: changes of others may still have been
+    /// on their way to the peer. Call before the peer leaves the room.
+    fn save_sync_state(&self, room: &ProjectRoom, peer: &PeerConnection) {
+        let heads = room.known_heads(&peer.peer_id);
+        if heads.is_empty() {
+            return;
+        }
+        let state = encode_sync_state(chrono::Utc::now().timestamp(), &heads);
+        if let Err(e) = self.storage.save_sync_state(
+            &room.project_id,
+            &sync_state_key(&peer.session_token),
+            &state,
+        ) {
+            warn!("Failed to save sync state of {}: {}", peer.peer_id, e);
+        }
+    }
+
+    /// Load and forget the heads kept for a session, if it can still be
+    /// resumed
+    fn take_sync_state(&self, project_id: &str, session_token: &str) -> Option<Vec<ChangeHash>> {
+        let key = sync_state_key(session_token);
+        let state = self
+            .storage
+            .load_sync_state(project_id, &key)
+            .inspect_err(|e| warn!("Failed to load sync state in {}: {}", project_id, e))
+            .ok()
+            .flatten()?;
+        let _ = self.storage.remove_sync_state(project_id, &key);
+
+        let (saved_at, heads) = decode_sync_state(&state)?;
+        let age = chrono::Utc::now().timestamp() - saved_at;
+        (age <= self.config.session_timeout.as_secs() as i64).then_some(heads)
+    }
+
     /// Broadcast a message to all peers in a project (except the sender)
-    pub fn broadcast_to_project(&self, project_id: &str, exclude_peer: &str, msg: ServerMessage) {
-        if let Some(room) = self.rooms.get(project_id) {
-            let peer_ids = room.get_peer_ids();
-            for pid in peer_ids {
-                if pid != exclude_peer {
-                    if let Some(peer_conn) = self.peers.get(&pid) {
-                        let _ = peer_conn.read().send(msg.clone());
-                    }
-                }
+    ///
+    /// The room's dispatcher delivers it in the background. Returns the
+    /// number of peers the message was queued for.
+    pub fn broadcast_to_project(&self, project_id: &str, exclude_peer: &str, msg: ServerMessage) -> usize {
+        let Some(room) = self.rooms.get(project_id).map(|room| room.clone()) else {
+            return 0;
+        };
+        let recipients: Vec<_> = room
+            .get_peer_ids()
+            .into_iter()
+            .filter(|pid| pid != exclude_peer)
+            .filter_map(|pid| self.peers.get(&pid).map(|peer| peer.clone()))
+            .collect();
+
+        let queued = recipients.len();
+        room.fanout.send(recipients, msg);
+        queued
+    }
+
+    /// Change a peer's display name in every project it has joined.
+    ///
+    /// The name must be free in all of those projects; on success each room is
+    /// notified, including the peer itself.
+    pub fn set_display_name(&self, peer_id: &str, name: &str) -> SyncResult<String> {
+        let peer = self
+            .get_peer(peer_id)
+            .ok_or_else(|| SyncError::PeerNotFound(peer_id.to_string()))?;
+        let name =
+            validate_display_name(name).map_err(|e| SyncError::InvalidMessage(e.to_string()))?;
+
+        let (joined_projects, avatar_seed) = {
+            let peer = peer.read();
+            (peer.joined_projects.clone(), peer.avatar_seed.clone())
+        };
+        let presences: Vec<_> = joined_projects
+            .iter()
+            .filter_map(|project_id| self.presence.get(project_id))
+            .collect();
+
+        if presences
+            .iter()
+            .any(|presence| presence.is_name_taken(&name, Some(peer_id)))
+        {
+            return Err(SyncError::InvalidMessage(
+                PresenceError::NameTaken(name).to_string(),
+            ));
+        }
+
+        peer.write().name = name.clone();
+
+        for presence in presences {
+            if let Err(e) = presence.rename_peer(peer_id, &name) {
+                warn!("Failed to rename peer {} in {}: {}", peer_id, presence.project_id(), e);
+                continue;
             }
+
+            let msg = ServerMessage::DisplayNameChanged {
+                project_id: presence.project_id().to_string(),
+                peer_id: peer_id.to_string(),
+                name: name.clone(),
+                avatar_seed: avatar_seed.clone(),
+                color: presence.get_peer(peer_id).map(|p| p.color),
+            };
+            self.broadcast_to_project(presence.project_id(), peer_id, msg.clone());
+            let _ = peer.read().send(msg);
         }
+
+        info!("Peer {} is now known as {}", peer_id, name);
+        Ok(name)
     }
 
     /// Leave a project/room
     pub fn leave_project(&self, peer_id: &str, project_id: &str) -> SyncResult<()> {
-        if let Some(room) = self.rooms.get(project_id) {
+        if let Some(room) = self.rooms.get(project_id).map(|room| room.clone()) {
             room.remove_peer(peer_id);
+            if let Some(peer) = self.get_peer(peer_id) {
+                self.audit(&peer.read(), project_id, AuditEvent::Left);
+            }
+            let name = self
+                .peers
+                .get(peer_id)
+                .map(|peer| peer.read().name.clone())
+                .unwrap_or_default();
+            self.release_host(peer_id, &name, project_id);
 
             // Update peer's joined projects
             if let Some(peer) = self.peers.get(peer_id) {
@@ -494,6 +1057,7 @@ impl SyncServer {
             if let Some(project_presence) = self.presence.get(project_id) {
                 project_presence.remove_peer(peer_id);
             }
+            self.announce_peer_count(project_id);
 
             // Broadcast peer left to remaining peers
             let peer_left_msg = ServerMessage::PeerLeft {
@@ -503,9 +1067,8 @@ impl SyncServer {
             };
             self.broadcast_to_project(project_id, peer_id, peer_left_msg);
 
-            // Clean up empty room after a delay
             if room.is_empty() {
-                // Could schedule cleanup here
+                self.room_emptied(&room);
             }
 
             info!("Peer {} left project {}", peer_id, project_id);
@@ -514,6 +1077,65 @@ impl SyncServer {
         Ok(())
     }
 
+    /// Remove a disconnected peer from a room but keep their presence as Offline.
+    ///
+    /// The peer's cursor stays visible until the cursor retention window expires,
+    /// at which point `expire_offline_presence` broadcasts the final PeerLeft.
+    fn disconnect_from_project(&self, peer: &PeerConnection, project_id: &str) {
+        let Some(room) = self.rooms.get(project_id).map(|room| room.clone()) else {
+            return;
+        };
+        self.save_sync_state(&room, peer);
+        room.remove_peer(&peer.peer_id);
+        self.audit(peer, project_id, AuditEvent::Disconnected);
+        self.release_host(&peer.peer_id, &peer.name, project_id);
+        if room.is_empty() {
+            self.room_emptied(&room);
+        }
+
+        let Some(project_presence) = self.presence.get(project_id) else {
+            return;
+        };
+        let Ok(()) = project_presence.mark_offline(&peer.peer_id) else {
+            return;
+        };
+
+        let offline_msg = ServerMessage::PresenceBroadcast {
+            project_id: project_id.to_string(),
+            peer_id: peer.peer_id.clone(),
+            peer_name: peer.name.clone(),
+            status: PresenceStatus::Offline,
+            active_file: project_presence
+                .get_peer(&peer.peer_id)
+                .and_then(|p| p.active_file),
+            last_active: chrono::Utc::now().timestamp(),
+        };
+        self.broadcast_to_project(project_id, &peer.peer_id, offline_msg);
+
+        info!("Peer {} disconnected from project {}", peer.peer_id, project_id);
+    }
+
+    /// Remove offline peers whose grace period has expired and notify the rooms
+    pub fn expire_offline_presence(&self) -> usize {
+        let expired = self.presence.cleanup_all();
+
+        for (project_id, peer_id) in &expired {
+            let peer_left_msg = ServerMessage::PeerLeft {
+                project_id: project_id.clone(),
+                peer_id: peer_id.clone(),
+                reason: Some("disconnected".to_string()),
+            };
+            self.broadcast_to_project(project_id, peer_id, peer_left_msg);
+            self.announce_peer_count(project_id);
+        }
+        if !expired.is_empty() {
+            self.departed
+                .retain(|_, peer_id| self.presence.contains_peer(peer_id));
+        }
+
+        expired.len()
+    }
+
     /// Handle incoming sync message from a peer
     pub async fn handle_sync_message(
         &self,
@@ -532,128 +1154,1605 @@ impl SyncServer {
         }
 
         // Process the sync message
-        let response = room.apply_changes(peer_id, &sync_data)?;
+        metrics::counter!(telemetry::SYNC_BYTES_IN).increment(sync_data.len() as u64);
+        let (response, file_changes) = room.apply_changes(peer_id, &sync_data)?;
+        self.emit_file_changes(project_id, Some(peer_id), file_changes);
 
         // Relay sync message to other peers
+        let relayed_bytes = sync_data.len() as u64;
         let sync_msg = ServerMessage::SyncMessage {
             project_id: project_id.to_string(),
             sync_data,
             from_peer: Some(peer_id.to_string()),
         };
-        self.broadcast_to_project(project_id, peer_id, sync_msg);
+        let recipients = self.broadcast_to_project(project_id, peer_id, sync_msg) as u64;
+        metrics::counter!(telemetry::SYNC_BYTES_OUT).increment(relayed_bytes * recipients);
 
         Ok(response)
     }
 
-    /// Generate sync data for a peer to bring them up to date
-    pub fn generate_sync_for_peer(&self, peer_id: &str, project_id: &str) -> Option<Vec<u8>> {
-        self.rooms
-            .get(project_id)
-            .and_then(|room| room.generate_sync_data(peer_id))
-    }
-
-    /// Get or create a project room
-    async fn get_or_create_room(&self, project_id: &str) -> SyncResult<Arc<ProjectRoom>> {
-        // Check if room already exists
-        if let Some(room) = self.rooms.get(project_id) {
-            return Ok(room.clone());
+    /// Make a peer the host of a project it joined, replacing the file tree
+    /// with the folder it uploaded. File contents are fetched from the host
+    /// on demand.
+    pub fn host_project(
+        &self,
+        peer_id: &str,
+        project_id: &str,
+        entries: &[HostedEntry],
+    ) -> SyncResult<()> {
+        let room = self.joined_room(peer_id, project_id)?;
+        if room.peer_access(peer_id).is_some() {
+            return Err(SyncError::Unauthorized(
+                "Peers joined through a share link cannot host".to_string(),
+            ));
         }
+        if room.peer_role(peer_id) == Some(ProjectRole::Viewer) {
+            return Err(SyncError::Unauthorized(
+                "Viewers cannot host this project".to_string(),
+            ));
+        }
+        self.hosting.set_host(project_id, peer_id).map_err(|host| {
+            SyncError::Unauthorized(format!("Project is already hosted by {}", host))
+        })?;
 
-        // Try to load from storage
-        let document = if let Some(data) = self
-            .storage
-            .load_document(project_id)
-            .map_err(|e| SyncError::StorageError(e.to_string()))?
-        {
-            info!("Loading document from storage: {}", project_id);
-            CollabDocument::load(project_id, &data)
-                .map_err(|e| SyncError::AutomergeError(e.to_string()))?
-        } else {
-            info!("Creating new document: {}", project_id);
-            let doc = CollabDocument::new(project_id)
-                .map_err(|e| SyncError::AutomergeError(e.to_string()))?;
+        room.with_document_mut(self.peer_actor(peer_id), |doc| replace_tree(doc, entries))
+            .map_err(|e| SyncError::AutomergeError(e.to_string()))?;
+        self.broadcast_document(&room, peer_id);
 
-            // Save metadata
-            let metadata = DocumentMetadata::new(project_id, project_id);
-            self.storage
-                .save_metadata(&metadata)
-                .map_err(|e| SyncError::StorageError(e.to_string()))?;
+        info!(
+            "Peer {} hosts project {} ({} entries)",
+            peer_id,
+            project_id,
+            entries.len()
+        );
+        Ok(())
+    }
 
-            doc
-        };
+    /// Apply files/folders created or deleted in the host's folder
+    pub fn update_hosted_tree(
+        &self,
+        peer_id: &str,
+        project_id: &str,
+        created: &[HostedEntry],
+        deleted: &[String],
+    ) -> SyncResult<()> {
+        let room = self.hosted_room(peer_id, project_id)?;
 
-        // Create the room
-        let room = Arc::new(ProjectRoom::new(project_id, document));
-        self.rooms.insert(project_id.to_string(), room.clone());
+        let file_changes = room
+            .with_document_mut(self.peer_actor(peer_id), |doc| -> DocumentResult<_> {
+                let before = files::file_nodes(doc)?;
+                apply_tree_changes(doc, created, deleted)?;
+                Ok(FileChanges::between(&before, &files::file_nodes(doc)?))
+            })
+            .map_err(|e| SyncError::AutomergeError(e.to_string()))?;
+        self.hosting.forget_files(project_id, deleted);
+        self.broadcast_document(&room, peer_id);
+        self.emit_file_changes(project_id, Some(peer_id), file_changes);
 
-        Ok(room)
+        Ok(())
     }
 
-    /// Save dirty documents to storage
-    pub async fn save_dirty_documents(&self) -> usize {
-        let mut saved = 0;
-
-        for entry in self.rooms.iter() {
-            let room = entry.value();
-            if room.take_dirty() {
-                let project_id = room.project_id.clone();
-                let data = room.get_document_state();
-
-                if let Err(e) = self.storage.save_document(&project_id, &data) {
-                    error!("Failed to save document {}: {}", project_id, e);
-                } else {
-                    debug!("Saved document: {}", project_id);
-                    saved += 1;
+    /// Serve a file of a remotely hosted project to a peer.
+    ///
+    /// Returns false if the project is not hosted by a peer. Otherwise the
+    /// content is sent to the peer now, or once the host has provided it.
+    pub fn open_hosted_file(&self, peer_id: &str, project_id: &str, file_path: &str) -> bool {
+        match self.hosting.open_file(project_id, file_path, peer_id) {
+            HostedFile::NotHosted => false,
+            HostedFile::Waiting => true,
+            HostedFile::AskHost(host_peer_id) => {
+                if let Some(host) = self.peers.get(&host_peer_id) {
+                    let _ = host.read().send(ServerMessage::FileRequest {
+                        project_id: project_id.to_string(),
+                        file_path: file_path.to_string(),
+                    });
+                }
+                true
+            }
+            HostedFile::Loaded => {
+                let msg = self.file_content_message(project_id, file_path);
+                if let Some(peer) = self.peers.get(peer_id) {
+                    let _ = peer.read().send(msg);
                 }
+                true
             }
         }
-
-        saved
     }
 
-    /// Clean up empty rooms and stale connections
-    pub fn cleanup(&self) {
-        // Clean up stale peer connections
-        let stale_peers: Vec<PeerId> = self
-            .peers
-            .iter()
-            .filter(|entry| entry.read().is_stale(self.config.session_timeout))
-            .map(|entry| entry.key().clone())
-            .collect();
+    /// Store file content sent by the host and pass it to the waiting peers.
+    ///
+    /// `None` means the host could not read the file.
+    pub fn provide_hosted_file(
+        &self,
+        peer_id: &str,
+        project_id: &str,
+        file_path: &str,
+        content: Option<&str>,
+    ) -> SyncResult<()> {
+        let room = self.hosted_room(peer_id, project_id)?;
 
-        for peer_id in stale_peers {
-            warn!("Removing stale peer: {}", peer_id);
-            self.unregister_peer(&peer_id);
-        }
+        let stored = match content {
+            Some(content) => {
+                let unchanged = room.with_document(|doc| {
+                    doc.get_file_content(file_path)
+                        .ok()
+                        .flatten()
+                        .is_some_and(|file| file.content == content)
+                });
+                if unchanged {
+                    true
+                } else {
+                    let result = room.with_document_mut(self.peer_actor(peer_id), |doc| {
+                        doc.set_file_content(file_path, content)
+                    });
+                    if result.is_ok() {
+                        self.broadcast_document(&room, peer_id);
+                    }
+                    result.is_ok()
+                }
+            }
+            None => false,
+        };
 
-        // Clean up empty rooms (keeping them for a grace period)
-        let empty_rooms: Vec<ProjectId> = self
-            .rooms
-            .iter()
-            .filter(|entry| {
-                let room = entry.value();
-                room.is_empty() && room.created_at.elapsed() > Duration::from_secs(300)
-            })
-            .map(|entry| entry.key().clone())
-            .collect();
+        let (waiting, msg) = if stored {
+            (
+                self.hosting.file_loaded(project_id, file_path),
+                self.file_content_message(project_id, file_path),
+            )
+        } else {
+            (
+                self.hosting.file_failed(project_id, file_path),
+                ServerMessage::FileNotFound {
+                    project_id: project_id.to_string(),
+                    file_path: file_path.to_string(),
+                },
+            )
+        };
 
-        for project_id in empty_rooms {
-            // Save before removing
-            if let Some((_, room)) = self.rooms.remove(&project_id) {
-                if room.take_dirty() {
-                    let data = room.get_document_state();
-                    let _ = self.storage.save_document(&project_id, &data);
-                }
-                info!("Removed empty room: {}", project_id);
+        for waiting_peer in waiting {
+            if let Some(peer) = self.peers.get(&waiting_peer) {
+                let _ = peer.read().send(msg.clone());
             }
         }
 
-        // Update presence statuses
-        self.presence.update_all_statuses();
-        self.presence.cleanup_all();
+        Ok(())
     }
 
-    /// Get server statistics
+    /// Stop hosting a project the peer left, failing requests still waiting on it
+    fn release_host(&self, peer_id: &str, peer_name: &str, project_id: &str) {
+        if self.hosting.is_host(project_id, peer_id) {
+            self.emit_event(ProjectEvent::HostLeft {
+                project_id: project_id.to_string(),
+                peer_id: peer_id.to_string(),
+                peer_name: peer_name.to_string(),
+            });
+        }
+        for (file_path, waiting_peer) in self.hosting.release(project_id, peer_id) {
+            if let Some(peer) = self.peers.get(&waiting_peer) {
+                let _ = peer.read().send(ServerMessage::FileNotFound {
+                    project_id: project_id.to_string(),
+                    file_path,
+                });
+            }
+        }
+    }
+
+    fn joined_room(&self, peer_id: &str, project_id: &str) -> SyncResult<Arc<ProjectRoom>> {
+        let room = self
+            .rooms
+            .get(project_id)
+            .map(|room| room.clone())
+            .ok_or_else(|| SyncError::DocumentNotFound(project_id.to_string()))?;
+
+        if !room.peers.contains_key(peer_id) {
+            return Err(SyncError::Unauthorized(format!(
+                "Not joined to project {}",
+                project_id
+            )));
+        }
+        Ok(room)
+    }
+
+    fn hosted_room(&self, peer_id: &str, project_id: &str) -> SyncResult<Arc<ProjectRoom>> {
+        if !self.hosting.is_host(project_id, peer_id) {
+            return Err(SyncError::Unauthorized(format!(
+                "Not the host of project {}",
+                project_id
+            )));
+        }
+        self.joined_room(peer_id, project_id)
+    }
+
+    /// Send the full document to every peer in the room but one
+    fn broadcast_document(&self, room: &ProjectRoom, exclude_peer: &str) {
+        let sync_msg = ServerMessage::SyncMessage {
+            project_id: room.project_id.clone(),
+            sync_data: room.get_document_state(),
+            from_peer: None,
+        };
+        self.broadcast_to_project(&room.project_id, exclude_peer, sync_msg);
+    }
+
+    fn file_content_message(&self, project_id: &str, file_path: &str) -> ServerMessage {
+        let file = self
+            .rooms
+            .get(project_id)
+            .and_then(|room| room.with_document(|doc| doc.get_file_content(file_path).ok().flatten()));
+
+        match file {
+            Some(file) => ServerMessage::FileContent {
+                project_id: project_id.to_string(),
+                file_path: file.path,
+                content: file.content,
+                language: file.language,
+                version: file.version,
+            },
+            None => ServerMessage::FileNotFound {
+                project_id: project_id.to_string(),
+                file_path: file_path.to_string(),
+            },
+        }
+    }
+
+    /// Read a project file for an HTTP client, along with its ETag
+    pub async fn read_file(
+        &self,
+        project_id: &str,
+        path: &str,
+    ) -> SyncResult<Option<(FileContent, String)>> {
+        let room = self.get_or_create_room(project_id).await?;
+        room.with_document(|doc| files::read_file(doc, path))
+            .map_err(|e| SyncError::AutomergeError(e.to_string()))
+    }
+
+    /// Read a project's file tree for an HTTP client, along with its ETag
+    pub async fn read_tree(&self, project_id: &str) -> SyncResult<(Vec<NestedNode>, String)> {
+        let room = self.get_or_create_room(project_id).await?;
+        let mut doc = room.document.lock();
+        files::read_tree(&mut doc).map_err(|e| SyncError::AutomergeError(e.to_string()))
+    }
+
+    /// Path and content of every file of a project, from the open room or
+    /// else from storage.
+    ///
+    /// Files of remotely hosted projects live on the host's disk, so their
+    /// contents are not available.
+    pub fn project_files(&self, project_id: &str) -> SyncResult<Vec<(String, String)>> {
+        if let Some(host) = self.hosting.host_of(project_id) {
+            return Err(SyncError::Unauthorized(format!(
+                "Project is hosted by {}",
+                host
+            )));
+        }
+        if let Some(room) = self.rooms.get(project_id) {
+            return room
+                .with_document(files::file_contents)
+                .map_err(|e| SyncError::AutomergeError(e.to_string()));
+        }
+        let bytes = self
+            .storage
+            .load_document(project_id)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?
+            .ok_or_else(|| SyncError::DocumentNotFound(project_id.to_string()))?;
+        let doc = CollabDocument::load(project_id, &bytes)
+            .map_err(|e| SyncError::AutomergeError(e.to_string()))?;
+        files::file_contents(&doc).map_err(|e| SyncError::AutomergeError(e.to_string()))
+    }
+
+    /// Write a project file for an HTTP client and push it to connected peers.
+    ///
+    /// Files of remotely hosted projects live on the host's disk and cannot
+    /// be written this way.
+    pub async fn write_file(
+        &self,
+        project_id: &str,
+        path: &str,
+        content: &str,
+        if_match: Option<&str>,
+    ) -> SyncResult<FileWrite> {
+        let room = self.http_writable_room(project_id).await?;
+
+        let result = {
+            let mut doc = room.document.lock();
+            files::write_file(&mut doc, path, content, if_match)
+                .map_err(|e| SyncError::AutomergeError(e.to_string()))?
+        };
+        if !matches!(result, FileWrite::PreconditionFailed(_)) {
+            room.mark_dirty();
+            self.broadcast_document(&room, "");
+            info!("File {} written over HTTP in project {}", path, project_id);
+        }
+        if matches!(result, FileWrite::Created(_)) {
+            let changes = FileChanges {
+                created: vec![path.to_string()],
+                ..Default::default()
+            };
+            self.emit_file_changes(project_id, None, changes);
+        }
+        Ok(result)
+    }
+
+    /// Delete a project file or folder for an HTTP client
+    pub async fn delete_file(
+        &self,
+        project_id: &str,
+        path: &str,
+        if_match: Option<&str>,
+    ) -> SyncResult<FileDelete> {
+        let room = self.http_writable_room(project_id).await?;
+
+        // A folder takes the files below it along
+        let (result, file_changes) = {
+            let mut doc = room.document.lock();
+            let before = files::file_nodes(&doc).unwrap_or_default();
+            let result = files::delete_file(&mut doc, path, if_match)
+                .map_err(|e| SyncError::AutomergeError(e.to_string()))?;
+            let after = files::file_nodes(&doc).unwrap_or_default();
+            (result, FileChanges::between(&before, &after))
+        };
+        if result == FileDelete::Deleted {
+            room.mark_dirty();
+            self.broadcast_document(&room, "");
+            info!("File {} deleted over HTTP in project {}", path, project_id);
+        }
+        self.emit_file_changes(project_id, None, file_changes);
+        Ok(result)
+    }
+
+    async fn http_writable_room(&self, project_id: &str) -> SyncResult<Arc<ProjectRoom>> {
+        if let Some(host) = self.hosting.host_of(project_id) {
+            return Err(SyncError::Unauthorized(format!(
+                "Project is hosted by {}",
+                host
+            )));
+        }
+        self.get_or_create_room(project_id).await
+    }
+
+    /// Check that a peer joined a project and may see one of its files,
+    /// as opening it or reacting on one of its lines requires
+    pub fn check_file_access(
+        &self,
+        peer_id: &str,
+        project_id: &str,
+        file_path: &str,
+    ) -> SyncResult<()> {
+        self.file_room(peer_id, project_id, file_path).map(|_| ())
+    }
+
+    /// Record that a peer opened a file
+    pub fn audit_file_opened(&self, peer_id: &str, project_id: &str, file_path: &str) {
+        if let Some(peer) = self.get_peer(peer_id) {
+            let event = AuditEvent::OpenedFile {
+                path: file_path.to_string(),
+            };
+            self.audit(&peer.read(), project_id, event);
+        }
+    }
+
+    /// Audit log entries, newest first, optionally for one project
+    pub fn audit_log(
+        &self,
+        project_id: Option<&str>,
+        limit: usize,
+    ) -> SyncResult<Vec<AuditRecord>> {
+        self.storage
+            .load_audit(project_id, limit)
+            .map_err(|e| SyncError::StorageError(e.to_string()))
+    }
+
+    fn audit(&self, peer: &PeerConnection, project_id: &str, event: AuditEvent) {
+        let record = AuditRecord {
+            timestamp: chrono::Utc::now().timestamp(),
+            project_id: project_id.to_string(),
+            peer_id: peer.peer_id.clone(),
+            peer_name: peer.name.clone(),
+            user_id: peer.user_id.clone(),
+            ip: peer.ip.map(|ip| ip.to_string()),
+            event,
+        };
+        if let Err(e) = self.storage.append_audit(&record) {
+            warn!("Failed to record audit event for {}: {}", project_id, e);
+        }
+    }
+
+    /// Create a share link for a project and its signed token
+    pub fn create_share_link(
+        &self,
+        project_id: &str,
+        role: ShareRole,
+        paths: &[String],
+        ttl: Duration,
+        created_by: Option<String>,
+    ) -> SyncResult<(ShareLink, String)> {
+        let paths = normalize_scope(paths)
+            .ok_or_else(|| SyncError::InvalidMessage("Invalid path in link scope".to_string()))?;
+        let now = chrono::Utc::now().timestamp();
+        let link = ShareLink {
+            link_id: uuid::Uuid::new_v4().to_string(),
+            project_id: project_id.to_string(),
+            role,
+            paths,
+            created_at: now,
+            expires_at: now + ttl.min(MAX_LINK_TTL).as_secs() as i64,
+            created_by,
+        };
+
+        let signer = ShareSigner::new(&self.config.share_secret);
+        let token = signer
+            .sign(&link)
+            .map_err(|e| SyncError::Internal(e.to_string()))?;
+        self.storage
+            .save_share_link(&link)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?;
+
+        info!(
+            "Share link {} created for project {} ({:?})",
+            link.link_id, project_id, role
+        );
+        Ok((link, token))
+    }
+
+    /// List the share links of a project that have not expired
+    pub fn list_share_links(&self, project_id: &str) -> SyncResult<Vec<ShareLink>> {
+        let now = chrono::Utc::now().timestamp();
+        let mut links = Vec::new();
+        for link in self
+            .storage
+            .list_share_links(project_id)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?
+        {
+            if link.expires_at <= now {
+                let _ = self.storage.remove_share_link(project_id, &link.link_id);
+            } else {
+                links.push(link);
+            }
+        }
+        links.sort_by_key(|link| link.created_at);
+        Ok(links)
+    }
+
+    /// Revoke a share link and remove the peers that joined through it.
+    ///
+    /// Returns false if there was no such link.
+    pub fn revoke_share_link(&self, project_id: &str, link_id: &str) -> SyncResult<bool> {
+        let removed = self
+            .storage
+            .remove_share_link(project_id, link_id)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?;
+
+        let affected: Vec<PeerId> = self
+            .rooms
+            .get(project_id)
+            .map(|room| {
+                room.peers
+                    .iter()
+                    .filter(|state| {
+                        state
+                            .access
+                            .as_ref()
+                            .is_some_and(|access| access.link_id == link_id)
+                    })
+                    .map(|state| state.key().clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for peer_id in affected {
+            if let Some(peer) = self.get_peer(&peer_id) {
+                let _ = peer.read().send(ServerMessage::Error {
+                    code: ErrorCode::Unauthorized,
+                    message: ShareError::Revoked.to_string(),
+                    project_id: Some(project_id.to_string()),
+                });
+            }
+            let _ = self.leave_project(&peer_id, project_id);
+        }
+
+        if removed {
+            info!("Share link {} of project {} revoked", link_id, project_id);
+        }
+        Ok(removed)
+    }
+
+    /// Check a share token against the signature and the stored links
+    pub fn verify_share_token(&self, project_id: &str, token: &str) -> Result<ShareAccess, ShareError> {
+        let access = ShareSigner::new(&self.config.share_secret).verify(token, project_id)?;
+        match self.storage.get_share_link(project_id, &access.link_id) {
+            Ok(Some(_)) => Ok(access),
+            _ => Err(ShareError::Revoked),
+        }
+    }
+
+    /// Name of the peer behind a session token if it may manage a project's
+    /// share links
+    pub fn share_manager(&self, session_token: &str, project_id: &str) -> Option<String> {
+        let peer_id = self.restore_session(session_token)?;
+        if !self.can_manage(&peer_id, project_id) {
+            return None;
+        }
+        self.get_peer(&peer_id).map(|peer| peer.read().name.clone())
+    }
+
+    /// Automerge actor for changes the server makes on a peer's behalf; every
+    /// session of a signed-in user shares one
+    fn peer_actor(&self, peer_id: &str) -> ActorId {
+        let user_id = self
+            .get_peer(peer_id)
+            .and_then(|peer| peer.read().user_id.clone());
+        document::actor_for(user_id.as_deref().unwrap_or(peer_id))
+    }
+
+    /// Whether a peer may manage a project (share links, chat): it must have
+    /// joined without a link and be the host of a hosted project, or else
+    /// the project's owner or, if nobody owns it, the peer that created it
+    fn can_manage(&self, peer_id: &str, project_id: &str) -> bool {
+        let Some(room) = self.rooms.get(project_id) else {
+            return false;
+        };
+        let Some(state) = room.peers.get(peer_id) else {
+            return false;
+        };
+        if state.access.is_some() || state.role == ProjectRole::Viewer {
+            return false;
+        }
+        let role = state.role;
+        drop(state);
+        if let Some(host) = self.hosting.host_of(project_id) {
+            return host == peer_id;
+        }
+        if role == ProjectRole::Owner {
+            return true;
+        }
+
+        let owner_id = self
+            .storage
+            .get_metadata(project_id)
+            .ok()
+            .flatten()
+            .and_then(|meta| meta.owner_id);
+        match owner_id {
+            Some(owner_id) => self
+                .get_peer(peer_id)
+                .is_some_and(|peer| peer.read().user_id.as_deref() == Some(owner_id.as_str())),
+            None => room.is_creator(peer_id),
+        }
+    }
+
+    /// Post a chat message to a project the peer has joined.
+    ///
+    /// The message is checked against the chat limits, stored in the history
+    /// and broadcast to every peer of the project, including the sender.
+    pub fn post_chat(
+        &self,
+        peer_id: &str,
+        project_id: &str,
+        content: &str,
+    ) -> SyncResult<ChatHistoryItem> {
+        if !self
+            .rooms
+            .get(project_id)
+            .is_some_and(|room| room.peers.contains_key(peer_id))
+        {
+            return Err(SyncError::Unauthorized(format!(
+                "Not a member of project {}",
+                project_id
+            )));
+        }
+        let peer_name = self
+            .get_peer(peer_id)
+            .map(|peer| peer.read().name.clone())
+            .ok_or_else(|| SyncError::PeerNotFound(peer_id.to_string()))?;
+        let content = self.chat.check(peer_id, content).map_err(|e| match e {
+            ChatError::RateLimited { .. } => SyncError::RateLimited,
+            e => SyncError::InvalidMessage(e.to_string()),
+        })?;
+
+        let record = ChatRecord {
+            message_id: chat::new_message_id(),
+            project_id: project_id.to_string(),
+            peer_id: peer_id.to_string(),
+            peer_name,
+            content,
+            timestamp: chrono::Utc::now().timestamp(),
+        };
+        self.storage
+            .save_chat_message(&record)
+            .and_then(|_| {
+                self.storage
+                    .trim_chat_history(project_id, self.config.chat.history_limit)
+            })
+            .map_err(|e| SyncError::StorageError(e.to_string()))?;
+        self.activity
+            .record_chat(project_id, peer_id, &record.peer_name, &record.content);
+        self.update_chat_document(peer_id, project_id, |doc| {
+            doc.append_chat_message(&chat_entry(&record))?;
+            doc.trim_chat_messages(self.config.chat.history_limit)
+        });
+
+        self.broadcast_to_project(
+            project_id,
+            "",
+            ServerMessage::ChatBroadcast {
+                project_id: project_id.to_string(),
+                peer_id: record.peer_id.clone(),
+                peer_name: record.peer_name.clone(),
+                content: record.content.clone(),
+                timestamp: record.timestamp,
+                message_id: Some(record.message_id.clone()),
+            },
+        );
+        Ok(chat_item(record))
+    }
+
+    /// Delete a chat message. Its author and peers who may manage the project
+    /// can delete it; it is removed from the history and the activity feed,
+    /// and every peer of the project is told.
+    pub fn delete_chat(&self, peer_id: &str, project_id: &str, message_id: &str) -> SyncResult<()> {
+        let record = self
+            .storage
+            .get_chat_message(project_id, message_id)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?
+            .ok_or_else(|| SyncError::InvalidMessage(format!("No chat message {}", message_id)))?;
+        if record.peer_id != peer_id && !self.can_manage(peer_id, project_id) {
+            return Err(SyncError::Unauthorized(
+                "Only the author or the host can delete this message".to_string(),
+            ));
+        }
+
+        self.storage
+            .redact_chat_message(project_id, message_id)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?;
+        self.activity
+            .redact_chat(project_id, &record.peer_id, &record.content);
+        self.update_chat_document(peer_id, project_id, |doc| {
+            doc.delete_chat_message(message_id).map(|_| ())
+        });
+
+        self.broadcast_to_project(
+            project_id,
+            "",
+            ServerMessage::ChatDeleted {
+                project_id: project_id.to_string(),
+                message_id: message_id.to_string(),
+                deleted_by: peer_id.to_string(),
+            },
+        );
+        info!(
+            "Chat message {} in {} deleted by {}",
+            message_id, project_id, peer_id
+        );
+        Ok(())
+    }
+
+    /// Chat history of a project, oldest first: the stored messages and
+    /// those peers merged into the document while offline
+    pub fn chat_history(&self, project_id: &str) -> SyncResult<Vec<ChatHistoryItem>> {
+        let limit = self.config.chat.history_limit;
+        let mut items: Vec<ChatHistoryItem> = self
+            .storage
+            .load_chat_history(project_id, limit)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?
+            .into_iter()
+            .map(chat_item)
+            .collect();
+
+        if let Some(room) = self.rooms.get(project_id) {
+            let entries = room
+                .document
+                .lock()
+                .get_chat_messages(limit, None)
+                .map_err(|e| SyncError::AutomergeError(e.to_string()))?;
+            for entry in entries {
+                if !items.iter().any(|item| item.message_id.as_deref() == Some(&entry.id)) {
+                    items.push(ChatHistoryItem {
+                        peer_id: entry.author_id,
+                        peer_name: entry.author_name,
+                        content: entry.content,
+                        timestamp: entry.timestamp,
+                        message_id: Some(entry.id),
+                    });
+                }
+            }
+            items.sort_by(|a, b| {
+                a.timestamp
+                    .cmp(&b.timestamp)
+                    .then_with(|| a.message_id.cmp(&b.message_id))
+            });
+            items.drain(..items.len().saturating_sub(limit));
+        }
+        Ok(items)
+    }
+
+    /// Apply a chat change made by a peer to the document of an open room.
+    /// Peers pick it up with their next sync rather than a broadcast of the
+    /// whole document per message; ChatBroadcast already delivered it live.
+    fn update_chat_document(
+        &self,
+        peer_id: &str,
+        project_id: &str,
+        update: impl FnOnce(&mut CollabDocument) -> DocumentResult<()>,
+    ) {
+        let Some(room) = self.rooms.get(project_id).map(|room| room.clone()) else {
+            return;
+        };
+        let actor = self.peer_actor(peer_id);
+        let result = room.document.lock().as_actor(actor, update);
+        match result {
+            Ok(()) => room.mark_dirty(),
+            Err(e) => warn!("Failed to update chat in document of {}: {}", project_id, e),
+        }
+    }
+
+    /// Room of a project the peer has joined, if its share link (if any)
+    /// covers `path`
+    fn file_room(
+        &self,
+        peer_id: &str,
+        project_id: &str,
+        path: &str,
+    ) -> SyncResult<Arc<ProjectRoom>> {
+        let room = self
+            .rooms
+            .get(project_id)
+            .map(|room| room.clone())
+            .filter(|room| room.peers.contains_key(peer_id))
+            .ok_or_else(|| {
+                SyncError::Unauthorized(format!("Not a member of project {}", project_id))
+            })?;
+        if room
+            .peer_access(peer_id)
+            .is_some_and(|access| !access.allows_path(path))
+        {
+            return Err(SyncError::Unauthorized(format!(
+                "Share link does not cover {}",
+                path
+            )));
+        }
+        Ok(room)
+    }
+
+    /// Comment on or highlight a range of a file's text.
+    ///
+    /// Any peer of the project may annotate, viewers included. Comments, and
+    /// highlights with a note, are held to the chat limits. The annotation is
+    /// stored in the document and every peer of the project is told.
+    pub fn add_annotation(
+        &self,
+        peer_id: &str,
+        project_id: &str,
+        path: &str,
+        range: std::ops::Range<u32>,
+        kind: AnnotationKind,
+        body: &str,
+    ) -> SyncResult<AnnotationInfo> {
+        let room = self.file_room(peer_id, project_id, path)?;
+        let author_name = self
+            .get_peer(peer_id)
+            .map(|peer| peer.read().name.clone())
+            .ok_or_else(|| SyncError::PeerNotFound(peer_id.to_string()))?;
+        let body = if kind == AnnotationKind::Comment || !body.trim().is_empty() {
+            self.chat.check(peer_id, body).map_err(|e| match e {
+                ChatError::RateLimited { .. } => SyncError::RateLimited,
+                e => SyncError::InvalidMessage(e.to_string()),
+            })?
+        } else {
+            String::new()
+        };
+
+        let annotation = Annotation {
+            id: uuid::Uuid::new_v4().to_string(),
+            path: path.to_string(),
+            start: range.start as usize,
+            end: range.end as usize,
+            kind,
+            author_id: peer_id.to_string(),
+            author_name,
+            body,
+            created_at: chrono::Utc::now().timestamp(),
+            resolved: false,
+            resolved_by: None,
+        };
+        let actor = self.peer_actor(peer_id);
+        room.document
+            .lock()
+            .as_actor(actor, |doc| doc.add_annotation(&annotation))
+            .map_err(annotation_error)?;
+        room.mark_dirty();
+
+        let info = annotation_info(annotation);
+        self.broadcast_to_project(
+            project_id,
+            "",
+            ServerMessage::AnnotationAdded {
+                project_id: project_id.to_string(),
+                annotation: info.clone(),
+            },
+        );
+        Ok(info)
+    }
+
+    /// Resolve an annotation or open it again. Its author and peers who may
+    /// manage the project can; every peer of the project is told.
+    pub fn resolve_annotation(
+        &self,
+        peer_id: &str,
+        project_id: &str,
+        path: &str,
+        annotation_id: &str,
+        resolved: bool,
+    ) -> SyncResult<()> {
+        let room = self.file_room(peer_id, project_id, path)?;
+        let actor = self.peer_actor(peer_id);
+        {
+            let mut doc = room.document.lock();
+            let annotation = doc
+                .get_annotations(path)
+                .map_err(annotation_error)?
+                .into_iter()
+                .find(|annotation| annotation.id == annotation_id)
+                .ok_or_else(|| {
+                    SyncError::InvalidMessage(format!("No annotation {}", annotation_id))
+                })?;
+            if annotation.author_id != peer_id && !self.can_manage(peer_id, project_id) {
+                return Err(SyncError::Unauthorized(
+                    "Only the author or the host can resolve this annotation".to_string(),
+                ));
+            }
+            doc.as_actor(actor, |doc| {
+                doc.resolve_annotation(path, annotation_id, resolved, peer_id)
+            })
+            .map_err(annotation_error)?;
+        }
+        room.mark_dirty();
+
+        self.broadcast_to_project(
+            project_id,
+            "",
+            ServerMessage::AnnotationResolved {
+                project_id: project_id.to_string(),
+                file_path: path.to_string(),
+                annotation_id: annotation_id.to_string(),
+                resolved,
+                resolved_by: peer_id.to_string(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Annotations of a file, in text order
+    pub fn annotations(
+        &self,
+        peer_id: &str,
+        project_id: &str,
+        path: &str,
+    ) -> SyncResult<Vec<AnnotationInfo>> {
+        let room = self.file_room(peer_id, project_id, path)?;
+        let annotations = room
+            .document
+            .lock()
+            .get_annotations(path)
+            .map_err(annotation_error)?;
+        Ok(annotations.into_iter().map(annotation_info).collect())
+    }
+
+    /// A file as it was at `heads` (hex change hashes), or `None` if it did
+    /// not exist then
+    pub fn file_version(
+        &self,
+        peer_id: &str,
+        project_id: &str,
+        path: &str,
+        heads: &[String],
+    ) -> SyncResult<Option<FileContent>> {
+        let heads = heads
+            .iter()
+            .map(|head| {
+                head.parse::<ChangeHash>().map_err(|_| {
+                    SyncError::InvalidMessage(format!("Invalid change hash: {}", head))
+                })
+            })
+            .collect::<SyncResult<Vec<_>>>()?;
+        let room = self.file_room(peer_id, project_id, path)?;
+        let file = room
+            .document
+            .lock()
+            .get_file_at_heads(path, &heads)
+            .map_err(annotation_error)?;
+        Ok(file)
+    }
+
+    /// Get a project's access list.
+    ///
+    /// A project created by a signed-in user before it had an access list
+    /// belongs to that user alone.
+    pub fn project_acl(&self, project_id: &str) -> SyncResult<Option<ProjectAcl>> {
+        if let Some(acl) = self
+            .storage
+            .get_acl(project_id)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?
+        {
+            return Ok(Some(acl));
+        }
+        Ok(self
+            .storage
+            .get_metadata(project_id)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?
+            .and_then(|meta| meta.owner_id)
+            .map(|owner_id| ProjectAcl::new(project_id, owner_id)))
+    }
+
+    /// Get a user's role in a project, None if they have no access.
+    ///
+    /// Projects without an access list may be edited by everyone.
+    pub fn project_role(
+        &self,
+        project_id: &str,
+        user_id: Option<&str>,
+    ) -> SyncResult<Option<ProjectRole>> {
+        Ok(match self.project_acl(project_id)? {
+            Some(acl) => user_id.and_then(|user_id| acl.role_of(user_id)),
+            None => Some(ProjectRole::Editor),
+        })
+    }
+
+    /// Give a user the editor or viewer role in a project, or with None take
+    /// their access away. Connected peers of the user are updated at once.
+    pub fn set_project_role(
+        &self,
+        project_id: &str,
+        user_id: &str,
+        role: Option<ProjectRole>,
+    ) -> SyncResult<ProjectAcl> {
+        let mut acl = self.project_acl(project_id)?.ok_or_else(|| {
+            SyncError::InvalidMessage("Project has no access list".to_string())
+        })?;
+        if acl.owner_id == user_id {
+            return Err(SyncError::InvalidMessage(
+                "The owner's role cannot change".to_string(),
+            ));
+        }
+        let changed = match role {
+            Some(role) => acl.grant(user_id, role),
+            None => acl.revoke(user_id),
+        };
+        if !changed {
+            return Err(SyncError::InvalidMessage(match role {
+                Some(_) => "Only the editor and viewer roles can be granted".to_string(),
+                None => format!("User {} has no access to revoke", user_id),
+            }));
+        }
+        self.save_acl(&acl)?;
+        info!(
+            "User {} now has role {:?} in project {}",
+            user_id, role, project_id
+        );
+        Ok(acl)
+    }
+
+    /// Store an access list and apply it to the peers in the project's room
+    fn save_acl(&self, acl: &ProjectAcl) -> SyncResult<()> {
+        self.storage
+            .save_acl(acl)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?;
+
+        let Some(room) = self.rooms.get(&acl.project_id).map(|room| room.clone()) else {
+            return Ok(());
+        };
+        let mut removed = Vec::new();
+        for mut state in room.peers.iter_mut() {
+            // Share link peers keep the access of their link
+            if state.access.is_some() {
+                continue;
+            }
+            let user_id = self
+                .get_peer(state.key())
+                .and_then(|peer| peer.read().user_id.clone());
+            match user_id.and_then(|user_id| acl.role_of(&user_id)) {
+                Some(role) => state.role = role,
+                None => removed.push(state.key().clone()),
+            }
+        }
+
+        for peer_id in removed {
+            if let Some(peer) = self.get_peer(&peer_id) {
+                let _ = peer.read().send(ServerMessage::Error {
+                    code: ErrorCode::Unauthorized,
+                    message: "Your access to this project was revoked".to_string(),
+                    project_id: Some(acl.project_id.clone()),
+                });
+            }
+            let _ = self.leave_project(&peer_id, &acl.project_id);
+        }
+        Ok(())
+    }
+
+    /// Generate sync data for a peer to bring them up to date
+    pub fn generate_sync_for_peer(&self, peer_id: &str, project_id: &str) -> Option<Vec<u8>> {
+        self.rooms
+            .get(project_id)
+            .and_then(|room| room.generate_sync_data(peer_id))
+    }
+
+    /// Get or create a project room
+    async fn get_or_create_room(&self, project_id: &str) -> SyncResult<Arc<ProjectRoom>> {
+        // Check if room already exists
+        if let Some(room) = self.rooms.get(project_id) {
+            return Ok(room.clone());
+        }
+
+        // Try to load from storage
+        let mut repaired = false;
+        let mut created = false;
+        let document = if let Some(data) = self
+            .storage
+            .load_document(project_id)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?
+        {
+            info!("Loading document from storage: {}", project_id);
+            let mut doc = CollabDocument::load(project_id, &data)
+                .map_err(|e| SyncError::AutomergeError(e.to_string()))?;
+
+            // Older servers left descendants of deleted folders behind
+            match doc.sweep_orphans() {
+                Ok(0) => {}
+                Ok(removed) => {
+                    info!("Removed {} orphaned entries from {}", removed, project_id);
+                    repaired = true;
+                }
+                Err(e) => warn!("Failed to sweep orphans in {}: {}", project_id, e),
+            }
+            match doc.repair() {
+                Ok(issues) if issues.is_empty() => {}
+                Ok(issues) => {
+                    warn!(
+                        "Repaired {} integrity issues in {}: {:?}",
+                        issues.len(),
+                        project_id,
+                        issues
+                    );
+                    repaired = true;
+                }
+                Err(e) => warn!("Failed to check integrity of {}: {}", project_id, e),
+            }
+            doc
+        } else {
+            info!("Creating new document: {}", project_id);
+            let doc = CollabDocument::new(project_id)
+                .map_err(|e| SyncError::AutomergeError(e.to_string()))?;
+
+            // Save metadata
+            let metadata = DocumentMetadata::new(project_id, project_id);
+            self.storage
+                .save_metadata(&metadata)
+                .map_err(|e| SyncError::StorageError(e.to_string()))?;
+
+            created = true;
+            doc
+        };
+
+        // Create the room
+        let room = Arc::new(ProjectRoom::new(project_id, document));
+        if repaired {
+            room.mark_dirty();
+        }
+        *room.awaiting_creator.write() = created;
+        self.rooms.insert(project_id.to_string(), room.clone());
+
+        Ok(room)
+    }
+
+    /// Save dirty documents to storage
+    pub async fn save_dirty_documents(&self) -> usize {
+        let rooms: Vec<Arc<ProjectRoom>> =
+            self.rooms.iter().map(|entry| entry.value().clone()).collect();
+        rooms.iter().filter(|room| self.save_if_dirty(room)).count()
+    }
+
+    /// Save a room's document if it changed since the last save. If saving
+    /// fails, it stays dirty for the next attempt.
+    fn save_if_dirty(&self, room: &ProjectRoom) -> bool {
+        if !room.take_dirty() {
+            return false;
+        }
+        let data = room.get_document_state();
+        match self.storage.save_document(&room.project_id, &data) {
+            Ok(()) => {
+                debug!("Saved document: {}", room.project_id);
+                self.emit_event(ProjectEvent::ProjectSaved {
+                    project_id: room.project_id.clone(),
+                });
+                true
+            }
+            Err(e) => {
+                error!("Failed to save document {}: {}", room.project_id, e);
+                room.mark_dirty();
+                false
+            }
+        }
+    }
+
+    /// Save a room as soon as its last peer leaves, so no edits are lost if
+    /// the server goes down before the next auto-save, and drop it right
+    /// away unless it is configured to linger
+    fn room_emptied(&self, room: &ProjectRoom) {
+        self.save_if_dirty(room);
+        if self.config.room_linger.is_zero() {
+            self.drop_room(&room.project_id);
+        }
+    }
+
+    /// Drop a room from memory if nobody has joined it in the meantime.
+    ///
+    /// Unsaved changes are saved first; a room whose save fails stays in
+    /// memory for the next cleanup pass to try again.
+    fn drop_room(&self, project_id: &str) {
+        let Some(room) = self.rooms.get(project_id).map(|room| room.clone()) else {
+            return;
+        };
+        if !room.is_empty() {
+            return;
+        }
+        self.save_if_dirty(&room);
+        if room.is_dirty() {
+            warn!("Keeping room {} in memory until it can be saved", project_id);
+            return;
+        }
+        // Changes made while saving keep the room until the next pass
+        let removed = self
+            .rooms
+            .remove_if(project_id, |_, room| room.is_empty() && !room.is_dirty());
+        if removed.is_none() {
+            return;
+        }
+        self.presence.remove(project_id);
+        self.activity.remove(project_id);
+        self.announce_peer_count(project_id);
+        info!("Removed empty room: {}", project_id);
+    }
+
+    /// Clean up empty rooms and stale connections
+    pub fn cleanup(&self) {
+        // Clean up stale peer connections
+        let stale_peers: Vec<PeerId> = self
+            .peers
+            .iter()
+            .filter(|entry| entry.read().is_stale(self.config.session_timeout))
+            .map(|entry| entry.key().clone())
+            .collect();
+
+        for peer_id in stale_peers {
+            warn!("Removing stale peer: {}", peer_id);
+            self.unregister_peer(&peer_id);
+        }
+
+        // Drop rooms that have been empty for longer than they may linger
+        let empty_rooms: Vec<ProjectId> = self
+            .rooms
+            .iter()
+            .filter(|entry| {
+                entry
+                    .value()
+                    .empty_for()
+                    .is_some_and(|empty_for| empty_for >= self.config.room_linger)
+            })
+            .map(|entry| entry.key().clone())
+            .collect();
+
+        for project_id in empty_rooms {
+            self.drop_room(&project_id);
+        }
+
+        // Update presence statuses
+        self.presence.update_all_statuses();
+        self.expire_offline_presence();
+
+        // Forget sync states of sessions that can no longer be resumed
+        let cutoff = chrono::Utc::now().timestamp() - self.config.session_timeout.as_secs() as i64;
+        let expired = self.storage.retain_sync_states(|state| {
+            decode_sync_state(state).is_some_and(|(saved_at, _)| saved_at >= cutoff)
+        });
+        if let Err(e) = expired {
+            warn!("Failed to expire sync states: {}", e);
+        }
+
+        // Trim the audit log
+        let cutoff = chrono::Utc::now().timestamp() - self.config.audit_retention.as_secs() as i64;
+        if let Err(e) = self.storage.trim_audit(self.config.audit_capacity, cutoff) {
+            warn!("Failed to trim audit log: {}", e);
+        }
+    }
+
+    /// Describe every open room and the peers in it
+    pub fn room_reports(&self) -> Vec<RoomReport> {
+        let mut reports: Vec<RoomReport> = self
+            .rooms
+            .iter()
+            .map(|entry| {
+                let room = entry.value();
+                let mut peers: Vec<RoomPeerReport> = room
+                    .peers
+                    .iter()
+                    .map(|state| {
+                        let peer = self.get_peer(state.key());
+                        let peer = peer.as_ref().map(|peer| peer.read());
+                        RoomPeerReport {
+                            peer_id: state.key().clone(),
+                            name: peer.as_ref().map(|peer| peer.name.clone()),
+                            user_id: peer.as_ref().and_then(|peer| peer.user_id.clone()),
+                            role: state.role,
+                            share_link: state.access.as_ref().map(|access| access.link_id.clone()),
+                            idle_secs: peer
+                                .as_ref()
+                                .map(|peer| peer.last_active.elapsed().as_secs()),
+                        }
+                    })
+                    .collect();
+                peers.sort_by(|a, b| a.peer_id.cmp(&b.peer_id));
+
+                RoomReport {
+                    project_id: room.project_id.clone(),
+                    peers,
+                    document_bytes: room.get_document_state().len(),
+                    dirty: *room.dirty.read(),
+                    host: self.hosting.host_of(&room.project_id),
+                    age_secs: room.created_at.elapsed().as_secs(),
+                    idle_secs: room.last_active.read().elapsed().as_secs(),
+                }
+            })
+            .collect();
+        reports.sort_by(|a, b| a.project_id.cmp(&b.project_id));
+        reports
+    }
+
+    /// Describe a connected peer and the projects it has joined
+    pub fn peer_report(&self, peer_id: &str) -> Option<PeerReport> {
+        let peer = self.get_peer(peer_id)?;
+        let peer = peer.read();
+        let mut projects: Vec<PeerProjectReport> = peer
+            .joined_projects
+            .iter()
+            .map(|project_id| {
+                let presence = self
+                    .presence
+                    .get(project_id)
+                    .and_then(|presence| presence.get_peer(peer_id));
+                PeerProjectReport {
+                    project_id: project_id.clone(),
+                    role: self
+                        .rooms
+                        .get(project_id)
+                        .and_then(|room| room.peers.get(peer_id).map(|state| state.role)),
+                    status: presence.as_ref().map(|presence| presence.status),
+                    active_file: presence.as_ref().and_then(|p| p.active_file.clone()),
+                    joined_at: presence.as_ref().map(|presence| presence.joined_at),
+                }
+            })
+            .collect();
+        projects.sort_by(|a, b| a.project_id.cmp(&b.project_id));
+
+        Some(PeerReport {
+            peer_id: peer.peer_id.clone(),
+            name: peer.name.clone(),
+            user_id: peer.user_id.clone(),
+            connected_secs: peer.connected_at.elapsed().as_secs(),
+            idle_secs: peer.last_active.elapsed().as_secs(),
+            projects,
+        })
+    }
+
+    /// Describe every connection of a signed-in user
+    pub fn user_sessions(&self, user_id: &str) -> Vec<PeerReport> {
+        let peer_ids: Vec<PeerId> = self
+            .peers
+            .iter()
+            .filter(|entry| entry.read().user_id.as_deref() == Some(user_id))
+            .map(|entry| entry.key().clone())
+            .collect();
+        let mut sessions: Vec<PeerReport> = peer_ids
+            .iter()
+            .filter_map(|peer_id| self.peer_report(peer_id))
+            .collect();
+        sessions.sort_by_key(|session| session.connected_secs);
+        sessions
+    }
+
+    /// Save open documents now, whether or not they changed; all of them, or
+    /// only the given project's. Returns how many were saved.
+    pub fn force_save(&self, project_id: Option<&str>) -> SyncResult<usize> {
+        let rooms: Vec<Arc<ProjectRoom>> = self
+            .rooms
+            .iter()
+            .filter(|entry| project_id.is_none_or(|id| entry.key() == id))
+            .map(|entry| entry.value().clone())
+            .collect();
+
+        for room in &rooms {
+            room.take_dirty();
+            self.storage
+                .save_document(&room.project_id, &room.get_document_state())
+                .map_err(|e| SyncError::StorageError(e.to_string()))?;
+        }
+        self.storage
+            .flush()
+            .map_err(|e| SyncError::StorageError(e.to_string()))?;
+
+        info!("Force-saved {} document(s)", rooms.len());
+        Ok(rooms.len())
+    }
+
+    /// Disconnect a peer, telling it why.
+    ///
+    /// Returns false if there is no such peer.
+    pub fn disconnect_peer(&self, peer_id: &str, reason: Option<String>) -> bool {
+        let Some(peer) = self.get_peer(peer_id) else {
+            return false;
+        };
+
+        let projects = peer.read().joined_projects.clone();
+        for project_id in projects {
+            let _ = self.leave_project(peer_id, &project_id);
+        }
+        // The connection closes once the Goodbye has been sent
+        let _ = peer.read().send(ServerMessage::Goodbye { reason });
+        self.unregister_peer(peer_id);
+
+        info!("Peer {} was disconnected by an administrator", peer_id);
+        true
+    }
+
+    /// Remove every peer from a project's room, save the document and drop
+    /// the room from memory.
+    ///
+    /// Returns false if the room was not open.
+    pub fn close_room(&self, project_id: &str) -> SyncResult<bool> {
+        self.shut_room(project_id, "Project was closed by an administrator")
+    }
+
+    /// Archive or unarchive a project, returning its updated metadata, or
+    /// None if there is no such project. Archiving saves and closes its room.
+    pub fn set_archived(
+        &self,
+        project_id: &str,
+        archived: bool,
+    ) -> SyncResult<Option<DocumentMetadata>> {
+        let Some(mut metadata) = self
+            .storage
+            .get_metadata(project_id)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?
+        else {
+            return Ok(None);
+        };
+
+        if metadata.archived != archived {
+            metadata.archived = archived;
+            self.storage
+                .save_metadata(&metadata)
+                .map_err(|e| SyncError::StorageError(e.to_string()))?;
+            info!(
+                "Project {} {}",
+                project_id,
+                if archived { "archived" } else { "unarchived" }
+            );
+        }
+        if archived {
+            self.shut_room(project_id, "Project was archived by its owner")?;
+        }
+        Ok(Some(metadata))
+    }
+
+    /// Delete a project: close its room and remove everything stored about
+    /// it. Returns false if there is no such project.
+    pub fn delete_project(&self, project_id: &str) -> SyncResult<bool> {
+        let exists = self
+            .storage
+            .get_metadata(project_id)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?
+            .is_some();
+        if !exists {
+            return Ok(false);
+        }
+
+        self.shut_room(project_id, "Project was deleted by its owner")?;
+        self.storage
+            .delete_document(project_id)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?;
+        self.announce(ServerMessage::ProjectDeleted {
+            project_id: project_id.to_string(),
+        });
+
+        info!("Project {} was deleted", project_id);
+        Ok(true)
+    }
+
+    /// Copy a project into a new one described by `fork`. With
+    /// `preserve_history` the copy keeps every change made so far, otherwise
+    /// it starts from a snapshot of the current files. Chat is copied only
+    /// with `include_chat`. Returns false if the source project doesn't exist.
+    pub async fn fork_project(
+        &self,
+        project_id: &str,
+        fork: &DocumentMetadata,
+        preserve_history: bool,
+        include_chat: bool,
+    ) -> SyncResult<bool> {
+        let exists = self
+            .storage
+            .get_metadata(project_id)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?
+            .is_some();
+        if !exists {
+            return Ok(false);
+        }
+
+        let room = self.get_or_create_room(project_id).await?;
+        let mut document = {
+            let mut doc = room.document.lock();
+            if preserve_history {
+                CollabDocument::load(&fork.project_id, &doc.save())
+            } else {
+                doc.snapshot(&fork.project_id)
+            }
+        }
+        .map_err(|e| SyncError::AutomergeError(e.to_string()))?;
+        if !include_chat {
+            document
+                .trim_chat_messages(0)
+                .map_err(|e| SyncError::AutomergeError(e.to_string()))?;
+        }
+
+        self.storage
+            .save_metadata(fork)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?;
+        self.storage
+            .save_document(&fork.project_id, &document.save())
+            .map_err(|e| SyncError::StorageError(e.to_string()))?;
+        if include_chat {
+            let history = self
+                .storage
+                .load_chat_history(project_id, usize::MAX)
+                .map_err(|e| SyncError::StorageError(e.to_string()))?;
+            for mut record in history {
+                record.project_id = fork.project_id.clone();
+                self.storage
+                    .save_chat_message(&record)
+                    .map_err(|e| SyncError::StorageError(e.to_string()))?;
+            }
+        }
+
+        info!("Project {} was forked into {}", project_id, fork.project_id);
+        Ok(true)
+    }
+
+    /// Save a new project made of the given files and folders
+    pub fn import_project(
+        &self,
+        metadata: &DocumentMetadata,
+        entries: &[HostedEntry],
+        contents: &[(String, String)],
+    ) -> SyncResult<()> {
+        let mut document = CollabDocument::new(&metadata.project_id)
+            .and_then(|mut doc| {
+                apply_tree_changes(&mut doc, entries, &[])?;
+                for (path, content) in contents {
+                    doc.set_file_content(path, content)?;
+                }
+                Ok(doc)
+            })
+            .map_err(|e| SyncError::AutomergeError(e.to_string()))?;
+
+        self.storage
+            .save_metadata(metadata)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?;
+        self.storage
+            .save_document(&metadata.project_id, &document.save())
+            .map_err(|e| SyncError::StorageError(e.to_string()))?;
+
+        info!(
+            "Imported {} files into project {}",
+            contents.len(),
+            metadata.project_id
+        );
+        Ok(())
+    }
+
+    /// Send a room's peers away with `reason`, save its document and drop it
+    /// from memory
+    fn shut_room(&self, project_id: &str, reason: &str) -> SyncResult<bool> {
+        let Some(room) = self.rooms.get(project_id).map(|room| room.clone()) else {
+            return Ok(false);
+        };
+
+        for peer_id in room.get_peer_ids() {
+            if let Some(peer) = self.get_peer(&peer_id) {
+                let _ = peer.read().send(ServerMessage::Error {
+                    code: ErrorCode::ProjectNotFound,
+                    message: reason.to_string(),
+                    project_id: Some(project_id.to_string()),
+                });
+            }
+            let _ = self.leave_project(&peer_id, project_id);
+        }
+
+        self.storage
+            .save_document(project_id, &room.get_document_state())
+            .map_err(|e| SyncError::StorageError(e.to_string()))?;
+        self.rooms.remove(project_id);
+        self.presence.remove(project_id);
+        self.activity.remove(project_id);
+        self.announce_peer_count(project_id);
+
+        info!("Room {} was closed: {}", project_id, reason);
+        Ok(true)
+    }
+
+    /// Check an open room's document for broken invariants, fixing them if
+    /// `repair` is set. `None` if the room is not open.
+    pub fn check_integrity(
+        &self,
+        project_id: &str,
+        repair: bool,
+    ) -> SyncResult<Option<Vec<IntegrityIssue>>> {
+        let Some(room) = self.rooms.get(project_id).map(|room| room.clone()) else {
+            return Ok(None);
+        };
+
+        let issues = {
+            let mut doc = room.document.lock();
+            if repair {
+                doc.repair()
+            } else {
+                doc.validate()
+            }
+            .map_err(|e| SyncError::AutomergeError(e.to_string()))?
+        };
+        if repair && !issues.is_empty() {
+            room.mark_dirty();
+            self.broadcast_document(&room, "");
+            warn!("Repaired {} integrity issues in {}", issues.len(), project_id);
+        }
+        Ok(Some(issues))
+    }
+
+    /// Compare a project's document in memory with the stored snapshot
+    pub fn dump_project(&self, project_id: &str) -> SyncResult<ProjectDump> {
+        let metadata = self
+            .storage
+            .get_metadata(project_id)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?;
+
+        let in_memory = match self.rooms.get(project_id) {
+            Some(room) => {
+                let bytes = room.document.lock().save();
+                Some(summarize_document(project_id, &bytes)?)
+            }
+            None => None,
+        };
+        let stored = match self
+            .storage
+            .load_document(project_id)
+            .map_err(|e| SyncError::StorageError(e.to_string()))?
+        {
+            Some(bytes) => Some(summarize_document(project_id, &bytes)?),
+            None => None,
+        };
+
+        let in_sync = match (&in_memory, &stored) {
+            (Some(memory), Some(stored)) => memory.heads == stored.heads,
+            (None, _) => true,
+            (Some(_), None) => false,
+        };
+
+        Ok(ProjectDump {
+            project_id: project_id.to_string(),
+            metadata,
+            in_memory,
+            stored,
+            in_sync,
+        })
+    }
+
+    /// Get server statistics
     pub fn stats(&self) -> ServerStats {
         ServerStats {
             active_projects: self.rooms.len(),
@@ -663,222 +2762,1609 @@ impl SyncServer {
         }
     }
 
-    /// Get presence manager
-    pub fn presence(&self) -> &Arc<PresenceManager> {
-        &self.presence
+    /// Get presence manager
+    pub fn presence(&self) -> &Arc<PresenceManager> {
+        &self.presence
+    }
+
+    /// Recent activity of a project the peer has joined, newest first
+    pub fn activity_feed(
+        &self,
+        peer_id: &str,
+        project_id: &str,
+        limit: usize,
+    ) -> SyncResult<Vec<ActivityEntry>> {
+        self.joined_room(peer_id, project_id)?;
+        Ok(self.activity.recent(project_id, limit))
+    }
+
+    /// Get activity log
+    pub fn activity(&self) -> &Arc<ActivityLog> {
+        &self.activity
+    }
+
+    /// Get storage
+    pub fn storage(&self) -> &Arc<DocumentStore> {
+        &self.storage
+    }
+
+    /// Start background tasks (save loop, cleanup loop)
+    pub fn start_background_tasks(self: Arc<Self>) -> BackgroundTaskHandles {
+        let server = self.clone();
+        let save_interval = server.config.save_interval;
+
+        // Save task
+        let save_handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(save_interval);
+            let mut shutdown = server.shutdown_receiver();
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        let saved = server.save_dirty_documents().await;
+                        if saved > 0 {
+                            debug!("Auto-saved {} documents", saved);
+                        }
+                    }
+                    _ = shutdown.recv() => {
+                        info!("Save task shutting down");
+                        // Final save
+                        server.save_dirty_documents().await;
+                        break;
+                    }
+                }
+            }
+        });
+
+        let server = self.clone();
+        let cleanup_interval = server.config.cleanup_interval;
+
+        // Cleanup task
+        let cleanup_handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(cleanup_interval);
+            let mut shutdown = server.shutdown_receiver();
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        server.cleanup();
+                    }
+                    _ = shutdown.recv() => {
+                        info!("Cleanup task shutting down");
+                        break;
+                    }
+                }
+            }
+        });
+
+        let server = self.clone();
+        let presence_interval = server.config.presence_interval;
+
+        // Presence task (expires offline peers once their grace period ends)
+        let presence_handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(presence_interval);
+            let mut shutdown = server.shutdown_receiver();
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        server.expire_offline_presence();
+                    }
+                    _ = shutdown.recv() => {
+                        info!("Presence task shutting down");
+                        break;
+                    }
+                }
+            }
+        });
+
+        BackgroundTaskHandles {
+            save_task: save_handle,
+            cleanup_task: cleanup_handle,
+            presence_task: presence_handle,
+        }
+    }
+}
+
+/// Server statistics
+#[derive(Debug, Clone)]
+pub struct ServerStats {
+    pub active_projects: usize,
+    pub active_peers: usize,
+    pub total_peers_in_projects: usize,
+    pub uptime_seconds: u64,
+}
+
+/// An open room as seen by an administrator
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomReport {
+    pub project_id: ProjectId,
+    pub peers: Vec<RoomPeerReport>,
+    /// Size of the saved document
+    pub document_bytes: usize,
+    /// Whether there are changes not yet saved
+    pub dirty: bool,
+    pub host: Option<PeerId>,
+    pub age_secs: u64,
+    pub idle_secs: u64,
+}
+
+/// A peer in a room as seen by an administrator
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomPeerReport {
+    pub peer_id: PeerId,
+    /// None if the connection is already gone
+    pub name: Option<String>,
+    pub user_id: Option<String>,
+    pub role: ProjectRole,
+    /// The share link the peer joined through
+    pub share_link: Option<String>,
+    pub idle_secs: Option<u64>,
+}
+
+/// A connected peer and the projects it has joined
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerReport {
+    pub peer_id: PeerId,
+    pub name: String,
+    pub user_id: Option<String>,
+    /// How long ago the connection was made
+    pub connected_secs: u64,
+    pub idle_secs: u64,
+    pub projects: Vec<PeerProjectReport>,
+}
+
+/// A project a peer has joined
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerProjectReport {
+    pub project_id: ProjectId,
+    /// None if the room is already closed
+    pub role: Option<ProjectRole>,
+    pub status: Option<super::presence::PresenceStatus>,
+    pub active_file: Option<String>,
+    /// Unix timestamp the peer joined at
+    pub joined_at: Option<i64>,
+}
+
+/// A project's document in memory and in storage, for debugging
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectDump {
+    pub project_id: ProjectId,
+    pub metadata: Option<DocumentMetadata>,
+    /// None if the room is not open
+    pub in_memory: Option<DocumentSummary>,
+    /// None if the document was never saved
+    pub stored: Option<DocumentSummary>,
+    /// Whether storage has everything the room has
+    pub in_sync: bool,
+}
+
+/// What a document snapshot contains
+#[derive(Debug, Clone, Serialize)]
+pub struct DocumentSummary {
+    pub bytes: usize,
+    /// Automerge heads, sorted
+    pub heads: Vec<String>,
+    pub folders: usize,
+    /// Files with the ETag of their content
+    pub files: Vec<(String, String)>,
+}
+
+fn chat_item(record: ChatRecord) -> ChatHistoryItem {
+    ChatHistoryItem {
+        peer_id: record.peer_id,
+        peer_name: record.peer_name,
+        content: record.content,
+        timestamp: record.timestamp,
+        message_id: Some(record.message_id),
+    }
+}
+
+fn chat_entry(record: &ChatRecord) -> ChatEntry {
+    ChatEntry {
+        id: record.message_id.clone(),
+        author_id: record.peer_id.clone(),
+        author_name: record.peer_name.clone(),
+        content: record.content.clone(),
+        timestamp: record.timestamp,
+    }
+}
+
+fn annotation_info(annotation: Annotation) -> AnnotationInfo {
+    AnnotationInfo {
+        annotation_id: annotation.id,
+        file_path: annotation.path,
+        start: annotation.start as u32,
+        end: annotation.end as u32,
+        kind: annotation.kind,
+        author_id: annotation.author_id,
+        author_name: annotation.author_name,
+        body: annotation.body,
+        created_at: annotation.created_at,
+        resolved: annotation.resolved,
+    }
+}
+
+/// Missing files, bad ranges and unknown heads are the client's mistake
+fn annotation_error(err: DocumentError) -> SyncError {
+    match err {
+        DocumentError::FileNotFound(_) | DocumentError::InvalidOperation(_) => {
+            SyncError::InvalidMessage(err.to_string())
+        }
+        err => SyncError::AutomergeError(err.to_string()),
+    }
+}
+
+/// Key of the sync state kept for the session holding `session_token`;
+/// hashed, so the stored keys can't be used to resume sessions
+fn sync_state_key(session_token: &str) -> String {
+    use sha2::{Digest, Sha256};
+    hex::encode(Sha256::digest(session_token.as_bytes()))
+}
+
+/// A sync state: when it was saved, then the heads the peer had
+fn encode_sync_state(saved_at: i64, heads: &[ChangeHash]) -> Vec<u8> {
+    let mut state = saved_at.to_be_bytes().to_vec();
+    for head in heads {
+        state.extend_from_slice(head.as_ref());
+    }
+    state
+}
+
+fn decode_sync_state(state: &[u8]) -> Option<(i64, Vec<ChangeHash>)> {
+    let (saved_at, heads) = state.split_first_chunk::<8>()?;
+    let heads = heads
+        .chunks(32)
+        .map(|head| ChangeHash::try_from(head).ok())
+        .collect::<Option<Vec<_>>>()?;
+    Some((i64::from_be_bytes(*saved_at), heads))
+}
+
+fn summarize_document(project_id: &str, bytes: &[u8]) -> SyncResult<DocumentSummary> {
+    let mut doc = CollabDocument::load(project_id, bytes)
+        .map_err(|e| SyncError::AutomergeError(e.to_string()))?;
+    let mut heads: Vec<String> = doc.get_heads().iter().map(|head| head.to_string()).collect();
+    heads.sort();
+
+    let nodes = doc
+        .get_all_nodes()
+        .map_err(|e| SyncError::AutomergeError(e.to_string()))?;
+    let mut files = Vec::new();
+    for node in nodes.iter().filter(|node| !node.is_dir) {
+        if let Some(file) = doc
+            .get_file_content(&node.path)
+            .map_err(|e| SyncError::AutomergeError(e.to_string()))?
+        {
+            files.push((node.path.clone(), files::etag(&file.content)));
+        }
+    }
+    files.sort();
+
+    Ok(DocumentSummary {
+        bytes: bytes.len(),
+        heads,
+        folders: nodes.iter().filter(|node| node.is_dir).count(),
+        files,
+    })
+}
+
+/// Handles for background tasks
+pub struct BackgroundTaskHandles {
+    pub save_task: tokio::task::JoinHandle<()>,
+    pub cleanup_task: tokio::task::JoinHandle<()>,
+    pub presence_task: tokio::task::JoinHandle<()>,
+}
+
+impl BackgroundTaskHandles {
+    /// Wait for all tasks to complete
+    pub async fn wait(self) {
+        let _ = tokio::join!(self.save_task, self.cleanup_task, self.presence_task);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::lanes;
+    use collab_protocol::ActivityKind;
+    use automerge::ReadDoc;
+    use tempfile::tempdir;
+
+    fn test_storage() -> DocumentStore {
+        let dir = tempdir().unwrap();
+        let config = crate::storage::StorageConfig::new(
+            dir.path().join("test.sled").to_string_lossy().to_string(),
+        );
+        DocumentStore::open(config).unwrap()
+    }
+
+    /// Let the rooms' dispatchers deliver what was broadcast so far
+    async fn settle() {
+        tokio::task::yield_now().await;
+    }
+
+    #[tokio::test]
+    async fn test_server_creation() {
+        let storage = test_storage();
+        let server = SyncServer::with_storage(storage);
+
+        assert_eq!(server.stats().active_projects, 0);
+        assert_eq!(server.stats().active_peers, 0);
+    }
+
+    #[tokio::test]
+    async fn test_peer_registration() {
+        let storage = test_storage();
+        let server = SyncServer::with_storage(storage);
+
+        let (tx, _rx) = lanes::channel();
+        server
+            .register_peer("peer-1", "Alice", "#ff0000", "token-123", tx)
+            .unwrap();
+
+        assert_eq!(server.stats().active_peers, 1);
+        assert!(server.get_peer("peer-1").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_join_project() {
+        let storage = test_storage();
+        let server = SyncServer::with_storage(storage);
+
+        let (tx, _rx) = lanes::channel();
+        server
+            .register_peer("peer-1", "Alice", "#ff0000", "token-123", tx)
+            .unwrap();
+
+        let result = server.join_project("peer-1", "project-1", true).await;
+        assert!(result.is_ok());
+
+        assert_eq!(server.stats().active_projects, 1);
+    }
+
+    #[tokio::test]
+    async fn test_multiple_peers_join() {
+        let storage = test_storage();
+        let server = SyncServer::with_storage(storage);
+
+        let (tx1, _rx1) = lanes::channel();
+        let (tx2, _rx2) = lanes::channel();
+
+        server
+            .register_peer("peer-1", "Alice", "#ff0000", "token-1", tx1)
+            .unwrap();
+        server
+            .register_peer("peer-2", "Bob", "#00ff00", "token-2", tx2)
+            .unwrap();
+
+        server.join_project("peer-1", "project-1", true).await.unwrap();
+        let result = server.join_project("peer-2", "project-1", false).await.unwrap();
+
+        // Second peer should see first peer in the list
+        if let ServerMessage::ProjectJoined {
+            peers, file_tree, ..
+        } = result
+        {
+            assert_eq!(peers.len(), 1);
+            // The tree comes along even without the document state
+            assert_eq!(file_tree.as_deref(), Some("[]"));
+            assert_eq!(peers[0].name, "Alice");
+            // Each peer got its own color from the palette
+            let bob = server.get_peer("peer-2").unwrap().read().color.clone();
+            assert_ne!(peers[0].color, bob);
+            assert!(crate::sync::presence::PEER_COLORS.contains(&bob.as_str()));
+        } else {
+            panic!("Expected ProjectJoined message");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_leave_project() {
+        let storage = test_storage();
+        let server = SyncServer::with_storage(storage);
+
+        let (tx, _rx) = lanes::channel();
+        server
+            .register_peer("peer-1", "Alice", "#ff0000", "token-123", tx)
+            .unwrap();
+
+        server.join_project("peer-1", "project-1", true).await.unwrap();
+        server.leave_project("peer-1", "project-1").unwrap();
+
+        // Room still exists but peer is gone
+        let peer = server.get_peer("peer-1").unwrap();
+        assert!(peer.read().joined_projects.is_empty());
     }
 
-    /// Get storage
-    pub fn storage(&self) -> &Arc<DocumentStore> {
-        &self.storage
+    #[tokio::test]
+    async fn test_fork_project() {
+        let server = SyncServer::with_storage(test_storage());
+        let (tx, _rx) = lanes::channel();
+        server
+            .register_peer("peer-1", "Alice", "#ff0000", "token-1", tx)
+            .unwrap();
+        server
+            .storage
+            .save_metadata(&DocumentMetadata::new("project-1", "Project"))
+            .unwrap();
+        server.join_project("peer-1", "project-1", false).await.unwrap();
+        server
+            .write_file("project-1", "notes.md", "# Notes", None)
+            .await
+            .unwrap();
+        server.post_chat("peer-1", "project-1", "hi").unwrap();
+        let heads = server.rooms.get("project-1").unwrap().document.lock().get_heads();
+
+        let load = |project_id: &str| {
+            let data = server.storage.load_document(project_id).unwrap().unwrap();
+            CollabDocument::load(project_id, &data).unwrap()
+        };
+
+        // A plain fork only has the files, under a fresh history
+        let fork = DocumentMetadata::new("fork-1", "Fork");
+        assert!(server.fork_project("project-1", &fork, false, false).await.unwrap());
+        let doc = load("fork-1");
+        assert_eq!(doc.get_file_content("notes.md").unwrap().unwrap().content, "# Notes");
+        assert!(doc.get_chat_messages(10, None).unwrap().is_empty());
+        assert!(ReadDoc::get_change_by_hash(doc.automerge(), &heads[0]).is_none());
+        assert!(server.storage.load_chat_history("fork-1", 10).unwrap().is_empty());
+        assert_eq!(server.storage.get_metadata("fork-1").unwrap().unwrap().name, "Fork");
+
+        // Or it keeps the history and chat
+        let fork = DocumentMetadata::new("fork-2", "Fork");
+        assert!(server.fork_project("project-1", &fork, true, true).await.unwrap());
+        let doc = load("fork-2");
+        assert!(ReadDoc::get_change_by_hash(doc.automerge(), &heads[0]).is_some());
+        assert_eq!(doc.get_chat_messages(10, None).unwrap().len(), 1);
+        assert_eq!(server.storage.load_chat_history("fork-2", 10).unwrap().len(), 1);
+
+        let fork = DocumentMetadata::new("fork-3", "Fork");
+        assert!(!server.fork_project("missing", &fork, false, false).await.unwrap());
     }
 
-    /// Start background tasks (save loop, cleanup loop)
-    pub fn start_background_tasks(self: Arc<Self>) -> BackgroundTaskHandles {
-        let server = self.clone();
-        let save_interval = server.config.save_interval;
+    #[tokio::test]
+    async fn test_import_project() {
+        let server = SyncServer::with_storage(test_storage());
+        let entry = |path: &str, is_dir: bool| HostedEntry {
+            path: path.to_string(),
+            is_dir,
+            size: 0,
+        };
+        let entries = [entry("src/main.rs", false), entry("src", true), entry("docs", true)];
+        let contents = [("src/main.rs".to_string(), "fn main() {}".to_string())];
+        server
+            .import_project(&DocumentMetadata::new("project-1", "App"), &entries, &contents)
+            .unwrap();
 
-        // Save task
-        let save_handle = tokio::spawn(async move {
-            let mut interval = tokio::time::interval(save_interval);
-            let mut shutdown = server.shutdown_receiver();
+        assert_eq!(server.storage.get_metadata("project-1").unwrap().unwrap().name, "App");
+        assert_eq!(server.project_files("project-1").unwrap(), contents.to_vec());
+        let room = server.get_or_create_room("project-1").await.unwrap();
+        let mut paths: Vec<_> = room
+            .document
+            .lock()
+            .get_all_nodes()
+            .unwrap()
+            .into_iter()
+            .map(|node| node.path)
+            .collect();
+        paths.sort();
+        assert_eq!(paths, ["docs", "src", "src/main.rs"]);
+    }
+
+    #[tokio::test]
+    async fn test_peer_report() {
+        let server = SyncServer::with_storage(test_storage());
+        for peer_id in ["peer-1", "peer-2"] {
+            let (tx, _rx) = lanes::channel();
+            server
+                .register_peer(peer_id, "Alice", "#ff0000", "token", tx)
+                .unwrap();
+        }
+        server.get_peer("peer-1").unwrap().write().user_id = Some("alice".to_string());
+        server.join_project("peer-1", "project-1", false).await.unwrap();
+        server.join_project("peer-1", "project-2", false).await.unwrap();
+
+        let report = server.peer_report("peer-1").unwrap();
+        assert_eq!(report.user_id.as_deref(), Some("alice"));
+        let projects: Vec<_> = report.projects.iter().map(|p| p.project_id.as_str()).collect();
+        assert_eq!(projects, ["project-1", "project-2"]);
+        assert!(report.projects.iter().all(|project| project.role.is_some()));
+        assert_eq!(
+            report.projects[0].status,
+            Some(crate::sync::presence::PresenceStatus::Active)
+        );
+
+        // Only the signed-in user's connections are theirs
+        let sessions = server.user_sessions("alice");
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].peer_id, "peer-1");
+        assert!(server.user_sessions("bob").is_empty());
+
+        server.leave_project("peer-1", "project-2").unwrap();
+        assert_eq!(server.peer_report("peer-1").unwrap().projects.len(), 1);
+        assert!(server.peer_report("peer-3").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_room_saved_when_empty() {
+        let config = SyncServerConfig {
+            room_linger: Duration::ZERO,
+            ..SyncServerConfig::default()
+        };
+        let server = SyncServer::new(test_storage(), config);
+        let (tx, _rx) = lanes::channel();
+        server
+            .register_peer("peer-1", "Alice", "#ff0000", "token-1", tx)
+            .unwrap();
+        server.join_project("peer-1", "project-1", false).await.unwrap();
+        server
+            .write_file("project-1", "notes.md", "# Notes", None)
+            .await
+            .unwrap();
+
+        // The last peer leaving saves the room and, without a linger, drops it
+        server.leave_project("peer-1", "project-1").unwrap();
+        assert!(!server.rooms.contains_key("project-1"));
+        let data = server.storage.load_document("project-1").unwrap().unwrap();
+        let doc = CollabDocument::load("project-1", &data).unwrap();
+        assert_eq!(doc.get_file_content("notes.md").unwrap().unwrap().content, "# Notes");
+
+        // With one, the room stays until cleanup finds it empty for long enough
+        let config = SyncServerConfig {
+            room_linger: Duration::from_millis(20),
+            ..SyncServerConfig::default()
+        };
+        let server = SyncServer::new(test_storage(), config);
+        let (tx, _rx) = lanes::channel();
+        server
+            .register_peer("peer-1", "Alice", "#ff0000", "token-1", tx)
+            .unwrap();
+        server.join_project("peer-1", "project-1", false).await.unwrap();
+        server.leave_project("peer-1", "project-1").unwrap();
+        server.cleanup();
+        assert!(server.rooms.contains_key("project-1"));
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        server.cleanup();
+        assert!(!server.rooms.contains_key("project-1"));
+    }
+
+    #[tokio::test]
+    async fn test_audit_log() {
+        let server = SyncServer::with_storage(test_storage());
+        let (tx, _rx) = lanes::channel();
+        server
+            .register_peer("peer-1", "Alice", "#ff0000", "token-1", tx)
+            .unwrap();
+        server.get_peer("peer-1").unwrap().write().ip = Some("10.0.0.1".parse().unwrap());
+        server.join_project("peer-1", "project-1", false).await.unwrap();
+        server.audit_file_opened("peer-1", "project-1", "src/main.rs");
+        server.leave_project("peer-1", "project-1").unwrap();
+
+        let log = server.audit_log(Some("project-1"), 10).unwrap();
+        let events: Vec<_> = log.iter().map(|record| record.event.clone()).collect();
+        assert_eq!(
+            events,
+            [
+                AuditEvent::Left,
+                AuditEvent::OpenedFile {
+                    path: "src/main.rs".to_string()
+                },
+                AuditEvent::Joined,
+            ]
+        );
+        assert!(log.iter().all(|record| record.peer_name == "Alice"));
+        assert_eq!(log[0].ip.as_deref(), Some("10.0.0.1"));
+        assert!(server.audit_log(Some("project-2"), 10).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resumed_session_gets_changes_since() {
+        let server = SyncServer::with_storage(test_storage());
+        let (tx, _rx) = lanes::channel();
+        server
+            .register_peer("peer-1", "Alice", "#ff0000", "token-1", tx)
+            .unwrap();
+        let Ok(ServerMessage::ProjectJoined {
+            document_state: Some(full),
+            document_since: None,
+            ..
+        }) = server.join_project("peer-1", "project-1", true).await
+        else {
+            panic!("Expected the full document");
+        };
+        server.unregister_peer("peer-1");
+        server
+            .write_file("project-1", "notes.md", "# Notes", None)
+            .await
+            .unwrap();
+
+        // Resuming the session only sends what changed while away
+        let join = |peer_id: &'static str, resumed: Option<&str>| {
+            let (tx, _rx) = lanes::channel();
+            server
+                .register_peer(peer_id, "Alice", "#ff0000", peer_id, tx)
+                .unwrap();
+            server.get_peer(peer_id).unwrap().write().resumed_token = resumed.map(String::from);
+            server.join_project(peer_id, "project-1", true)
+        };
+        let Ok(ServerMessage::ProjectJoined {
+            document_state: Some(delta),
+            document_since: Some(since),
+            ..
+        }) = join("peer-2", Some("token-1")).await
+        else {
+            panic!("Expected the changes since the last session");
+        };
+        let mut doc = CollabDocument::load("project-1", &full).unwrap();
+        assert_eq!(
+            since,
+            doc.get_heads().iter().map(|h| h.to_string()).collect::<Vec<_>>()
+        );
+        doc.automerge_mut().load_incremental(&delta).unwrap();
+        assert_eq!(doc.get_file_content("notes.md").unwrap().unwrap().content, "# Notes");
+
+        // The state is used once, and unknown sessions get everything
+        for (peer_id, resumed) in [("peer-3", Some("token-1")), ("peer-4", None)] {
+            assert!(matches!(
+                join(peer_id, resumed).await,
+                Ok(ServerMessage::ProjectJoined {
+                    document_since: None,
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resumed_session_gets_changes_made_before_disconnect() {
+        let server = SyncServer::with_storage(test_storage());
+        let (tx, _rx) = lanes::channel();
+        server
+            .register_peer("peer-1", "Alice", "#ff0000", "token-1", tx)
+            .unwrap();
+        let Ok(ServerMessage::ProjectJoined {
+            document_state: Some(full),
+            ..
+        }) = server.join_project("peer-1", "project-1", true).await
+        else {
+            panic!("Expected the full document");
+        };
+
+        // The peer's own edit is acknowledged by its sync message
+        let mut doc = CollabDocument::load("project-1", &full).unwrap();
+        doc.create_file("f1", "main.rs", "main.rs", None, "rust").unwrap();
+        server
+            .handle_sync_message("peer-1", "project-1", doc.save())
+            .await
+            .unwrap();
+
+        // A change lands while the relay to the peer may still be queued
+        server
+            .write_file("project-1", "notes.md", "# Notes", None)
+            .await
+            .unwrap();
+        server.unregister_peer("peer-1");
+
+        let (tx, _rx) = lanes::channel();
+        server
+            .register_peer("peer-2", "Alice", "#ff0000", "token-2", tx)
+            .unwrap();
+        server.get_peer("peer-2").unwrap().write().resumed_token = Some("token-1".to_string());
+        let Ok(ServerMessage::ProjectJoined {
+            document_state: Some(delta),
+            document_since: Some(since),
+            ..
+        }) = server.join_project("peer-2", "project-1", true).await
+        else {
+            panic!("Expected the changes since the last session");
+        };
+        assert_eq!(
+            since,
+            doc.get_heads().iter().map(|h| h.to_string()).collect::<Vec<_>>()
+        );
+        doc.automerge_mut().load_incremental(&delta).unwrap();
+        assert_eq!(doc.get_file_content("notes.md").unwrap().unwrap().content, "# Notes");
+    }
+
+    #[tokio::test]
+    async fn test_session_restore() {
+        let storage = test_storage();
+        let server = SyncServer::with_storage(storage);
+
+        let (tx, _rx) = lanes::channel();
+        server
+            .register_peer("peer-1", "Alice", "#ff0000", "secret-token", tx)
+            .unwrap();
+
+        let restored = server.restore_session("secret-token");
+        assert_eq!(restored, Some("peer-1".to_string()));
+
+        let not_found = server.restore_session("wrong-token");
+        assert!(not_found.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unregister_peer() {
+        let storage = test_storage();
+        let server = SyncServer::with_storage(storage);
+
+        let (tx, _rx) = lanes::channel();
+        server
+            .register_peer("peer-1", "Alice", "#ff0000", "token-123", tx)
+            .unwrap();
+
+        server.join_project("peer-1", "project-1", true).await.unwrap();
+        server.unregister_peer("peer-1");
+
+        assert!(server.get_peer("peer-1").is_none());
+        assert!(server.restore_session("token-123").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_grace_period() {
+        let storage = test_storage();
+        let config = SyncServerConfig::default()
+            .with_presence(PresenceConfig::default().with_cursor_retention(Duration::ZERO));
+        let server = SyncServer::new(storage, config);
+
+        let (tx1, _rx1) = lanes::channel();
+        let (tx2, mut rx2) = lanes::channel();
+        server
+            .register_peer("peer-1", "Alice", "#ff0000", "token-1", tx1)
+            .unwrap();
+        server
+            .register_peer("peer-2", "Bob", "#00ff00", "token-2", tx2)
+            .unwrap();
+
+        server.join_project("peer-1", "project-1", false).await.unwrap();
+        server.join_project("peer-2", "project-1", false).await.unwrap();
+        // Drop the color assigned on join
+        settle().await;
+        while rx2.try_recv().is_ok() {}
+
+        server.unregister_peer("peer-1");
+        settle().await;
+
+        // Peer stays visible as offline instead of vanishing
+        let presence = server.presence().get("project-1").unwrap();
+        let alice = presence.get_peer("peer-1").unwrap();
+        assert_eq!(alice.status, crate::sync::presence::PresenceStatus::Offline);
+        assert!(matches!(
+            rx2.try_recv(),
+            Ok(ServerMessage::PresenceBroadcast { status: PresenceStatus::Offline, .. })
+        ));
+
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(server.expire_offline_presence(), 1);
+        assert!(presence.get_peer("peer-1").is_none());
+        settle().await;
+        assert!(matches!(rx2.try_recv(), Ok(ServerMessage::PeerLeft { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_takes_over_offline_presence() {
+        let config = SyncServerConfig::default()
+            .with_presence(PresenceConfig::default().with_cursor_retention(Duration::ZERO));
+        let server = SyncServer::new(test_storage(), config);
+        let (tx2, mut rx2) = lanes::channel();
+        server
+            .register_peer("peer-2", "Bob", "#00ff00", "token-2", tx2)
+            .unwrap();
+        server.join_project("peer-2", "project-1", false).await.unwrap();
+
+        let connect = |peer_id: &str, token: &str| {
+            let (tx, _rx) = lanes::channel();
+            server
+                .register_peer(peer_id, "Alice", "#ff0000", token, tx)
+                .unwrap();
+            server.get_peer(peer_id).unwrap().write().resumed_token = Some("token-1".to_string());
+        };
+        connect("peer-1", "token-1");
+        server.join_project("peer-1", "project-1", false).await.unwrap();
+        let presence = server.presence().get("project-1").unwrap();
+        presence
+            .update_cursor("peer-1", crate::sync::presence::Cursor::new("main.rs", 1, 1))
+            .unwrap();
+        server.unregister_peer("peer-1");
+
+        // Rejoining under the same ID makes the entry active again, so it
+        // does not expire while connected
+        connect("peer-1", "token-1");
+        server.join_project("peer-1", "project-1", false).await.unwrap();
+        let alice = presence.get_peer("peer-1").unwrap();
+        assert_eq!(alice.status, crate::sync::presence::PresenceStatus::Active);
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(server.expire_offline_presence(), 0);
+        server.unregister_peer("peer-1");
+        settle().await;
+        while rx2.try_recv().is_ok() {}
+
+        // A new ID for the same session takes over the offline entry
+        connect("peer-3", "token-3");
+        server.join_project("peer-3", "project-1", false).await.unwrap();
+        assert!(presence.get_peer("peer-1").is_none());
+        let cursor = presence.get_peer("peer-3").unwrap().cursor.unwrap();
+        assert_eq!(cursor.file_path, "main.rs");
+        settle().await;
+        let mut left = Vec::new();
+        while let Ok(message) = rx2.try_recv() {
+            if let ServerMessage::PeerLeft { peer_id, reason, .. } = message {
+                left.push((peer_id, reason));
+            }
+        }
+        assert_eq!(left, [("peer-1".to_string(), Some("reconnected".to_string()))]);
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(server.expire_offline_presence(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_names_and_rename() {
+        let storage = test_storage();
+        let server = SyncServer::new(storage, SyncServerConfig::default());
+
+        let (tx1, mut rx1) = lanes::channel();
+        let (tx2, mut rx2) = lanes::channel();
+        server
+            .register_peer("peer-1", "Anonymous", "#ff0000", "token-1", tx1)
+            .unwrap();
+        server
+            .register_peer("peer-2", "Anonymous", "#00ff00", "token-2", tx2)
+            .unwrap();
+
+        server.join_project("peer-1", "project-1", false).await.unwrap();
+        server.join_project("peer-2", "project-1", false).await.unwrap();
+
+        // Both anonymous peers get distinct generated names
+        let name1 = server.get_peer("peer-1").unwrap().read().name.clone();
+        let name2 = server.get_peer("peer-2").unwrap().read().name.clone();
+        assert_ne!(name1, "Anonymous");
+        assert_ne!(name1, name2);
+        assert!(matches!(
+            rx2.try_recv(),
+            Ok(ServerMessage::DisplayNameChanged { name, .. }) if name == name2
+        ));
+
+        // Renaming onto a taken name is rejected
+        assert!(server.set_display_name("peer-2", &name1).is_err());
+
+        assert_eq!(server.set_display_name("peer-2", "Bob").unwrap(), "Bob");
+        let presence = server.presence().get("project-1").unwrap();
+        assert_eq!(presence.get_peer("peer-2").unwrap().name, "Bob");
+
+        settle().await;
+        let renamed = std::iter::from_fn(|| rx1.try_recv().ok())
+            .any(|msg| matches!(msg, ServerMessage::DisplayNameChanged { name, .. } if name == "Bob"));
+        assert!(renamed);
+    }
+
+    #[tokio::test]
+    async fn test_remote_hosting() {
+        let storage = test_storage();
+        let server = SyncServer::new(storage, SyncServerConfig::default());
+
+        let (host_tx, mut host_rx) = lanes::channel();
+        let (guest_tx, mut guest_rx) = lanes::channel();
+        server
+            .register_peer("host", "Host", "#ff0000", "token-1", host_tx)
+            .unwrap();
+        server
+            .register_peer("guest", "Guest", "#00ff00", "token-2", guest_tx)
+            .unwrap();
+        server.join_project("host", "project-1", false).await.unwrap();
+        server.join_project("guest", "project-1", false).await.unwrap();
 
-            loop {
-                tokio::select! {
-                    _ = interval.tick() => {
-                        let saved = server.save_dirty_documents().await;
-                        if saved > 0 {
-                            debug!("Auto-saved {} documents", saved);
-                        }
-                    }
-                    _ = shutdown.recv() => {
-                        info!("Save task shutting down");
-                        // Final save
-                        server.save_dirty_documents().await;
-                        break;
-                    }
-                }
-            }
-        });
+        let entries = vec![HostedEntry {
+            path: "main.rs".to_string(),
+            is_dir: false,
+            size: 12,
+        }];
+        server.host_project("host", "project-1", &entries).unwrap();
+        assert!(server.host_project("guest", "project-1", &entries).is_err());
 
-        let server = self.clone();
-        let cleanup_interval = server.config.cleanup_interval;
+        // Opening a file asks the host for it
+        assert!(server.open_hosted_file("guest", "project-1", "main.rs"));
+        let asked = std::iter::from_fn(|| host_rx.try_recv().ok())
+            .any(|msg| matches!(msg, ServerMessage::FileRequest { file_path, .. } if file_path == "main.rs"));
+        assert!(asked);
 
-        // Cleanup task
-        let cleanup_handle = tokio::spawn(async move {
-            let mut interval = tokio::time::interval(cleanup_interval);
-            let mut shutdown = server.shutdown_receiver();
+        // Only the host may answer
+        assert!(server
+            .provide_hosted_file("guest", "project-1", "main.rs", Some("evil"))
+            .is_err());
+        server
+            .provide_hosted_file("host", "project-1", "main.rs", Some("fn main() {}"))
+            .unwrap();
 
-            loop {
-                tokio::select! {
-                    _ = interval.tick() => {
-                        server.cleanup();
-                    }
-                    _ = shutdown.recv() => {
-                        info!("Cleanup task shutting down");
-                        break;
-                    }
-                }
-            }
+        let content = std::iter::from_fn(|| guest_rx.try_recv().ok()).find_map(|msg| match msg {
+            ServerMessage::FileContent { content, .. } => Some(content),
+            _ => None,
         });
+        assert_eq!(content.as_deref(), Some("fn main() {}"));
 
-        BackgroundTaskHandles {
-            save_task: save_handle,
-            cleanup_task: cleanup_handle,
-        }
+        // Once the host leaves, the project is no longer hosted remotely
+        server.leave_project("host", "project-1").unwrap();
+        assert!(!server.open_hosted_file("guest", "project-1", "main.rs"));
     }
-}
 
-/// Server statistics
-#[derive(Debug, Clone)]
-pub struct ServerStats {
-    pub active_projects: usize,
-    pub active_peers: usize,
-    pub total_peers_in_projects: usize,
-    pub uptime_seconds: u64,
-}
+    #[tokio::test]
+    async fn test_file_access_requires_membership() {
+        let storage = test_storage();
+        let server = SyncServer::new(storage, SyncServerConfig::default());
 
-/// Handles for background tasks
-pub struct BackgroundTaskHandles {
-    pub save_task: tokio::task::JoinHandle<()>,
-    pub cleanup_task: tokio::task::JoinHandle<()>,
-}
+        let (host_tx, _host_rx) = lanes::channel();
+        let (stranger_tx, _stranger_rx) = lanes::channel();
+        server
+            .register_peer("host", "Host", "#ff0000", "token-1", host_tx)
+            .unwrap();
+        server
+            .register_peer("stranger", "Stranger", "#00ff00", "token-2", stranger_tx)
+            .unwrap();
+        server.get_peer("host").unwrap().write().user_id = Some("u-owner".to_string());
+        server
+            .save_acl(&ProjectAcl::new("project-1", "u-owner"))
+            .unwrap();
+        server.join_project("host", "project-1", false).await.unwrap();
+        let entries = vec![HostedEntry {
+            path: "secret.rs".to_string(),
+            is_dir: false,
+            size: 12,
+        }];
+        server.host_project("host", "project-1", &entries).unwrap();
 
-impl BackgroundTaskHandles {
-    /// Wait for all tasks to complete
-    pub async fn wait(self) {
-        let _ = tokio::join!(self.save_task, self.cleanup_task);
+        // A peer that never joined cannot open or react on the host's files
+        assert!(matches!(
+            server.check_file_access("stranger", "project-1", "secret.rs"),
+            Err(SyncError::Unauthorized(_))
+        ));
+        assert!(server.check_file_access("host", "project-1", "secret.rs").is_ok());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::tempdir;
+    #[tokio::test]
+    async fn test_http_file_access() {
+        let storage = test_storage();
+        let server = SyncServer::new(storage, SyncServerConfig::default());
 
-    fn test_storage() -> DocumentStore {
-        let dir = tempdir().unwrap();
-        let config = crate::storage::StorageConfig::new(
-            dir.path().join("test.sled").to_string_lossy().to_string(),
+        let (tx, mut rx) = lanes::channel();
+        server
+            .register_peer("peer-1", "Alice", "#ff0000", "token-1", tx)
+            .unwrap();
+        server.join_project("peer-1", "project-1", false).await.unwrap();
+        // Drop the color assigned on join
+        settle().await;
+        while rx.try_recv().is_ok() {}
+
+        let written = server
+            .write_file("project-1", "src/lib.rs", "pub fn a() {}", None)
+            .await
+            .unwrap();
+        let FileWrite::Created(tag) = written else {
+            panic!("Expected the file to be created");
+        };
+
+        // Connected peers receive the change
+        settle().await;
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(ServerMessage::SyncMessage { from_peer: None, .. })
+        ));
+
+        let (file, read_tag) = server.read_file("project-1", "src/lib.rs").await.unwrap().unwrap();
+        assert_eq!(file.content, "pub fn a() {}");
+        assert_eq!(read_tag, tag);
+        let (tree, tree_tag) = server.read_tree("project-1").await.unwrap();
+        assert_eq!(tree[0].path, "src");
+        assert_ne!(tree_tag, tag);
+        assert_eq!(
+            server.project_files("project-1").unwrap(),
+            vec![("src/lib.rs".to_string(), "pub fn a() {}".to_string())]
         );
-        DocumentStore::open(config).unwrap()
+
+        let conflict = server
+            .write_file("project-1", "src/lib.rs", "pub fn b() {}", Some("\"old\""))
+            .await
+            .unwrap();
+        assert_eq!(conflict, FileWrite::PreconditionFailed(Some(tag.clone())));
+        assert!(rx.try_recv().is_err());
+
+        assert_eq!(
+            server.delete_file("project-1", "src/lib.rs", Some(&tag)).await.unwrap(),
+            FileDelete::Deleted
+        );
+        assert!(server.read_file("project-1", "src/lib.rs").await.unwrap().is_none());
     }
 
     #[tokio::test]
-    async fn test_server_creation() {
+    async fn test_file_and_save_events() {
         let storage = test_storage();
-        let server = SyncServer::with_storage(storage);
+        let server = SyncServer::new(storage, SyncServerConfig::default());
+        let mut events = server.subscribe_events();
 
-        assert_eq!(server.stats().active_projects, 0);
-        assert_eq!(server.stats().active_peers, 0);
+        let (tx, _rx) = lanes::channel();
+        server
+            .register_peer("peer-1", "Alice", "#ff0000", "token-1", tx)
+            .unwrap();
+        let Ok(ServerMessage::ProjectJoined {
+            document_state: Some(state),
+            ..
+        }) = server.join_project("peer-1", "project-1", true).await
+        else {
+            panic!("Expected to join with the document");
+        };
+        assert!(matches!(events.try_recv(), Ok(ProjectEvent::PeerJoined { .. })));
+
+        // A file created by a peer's edit
+        let mut doc = CollabDocument::load("project-1", &state).unwrap();
+        doc.create_file("main", "main.rs", "main.rs", None, "rust").unwrap();
+        server
+            .handle_sync_message("peer-1", "project-1", doc.save())
+            .await
+            .unwrap();
+        assert_eq!(
+            events.try_recv().unwrap(),
+            ProjectEvent::FileCreated {
+                project_id: "project-1".to_string(),
+                path: "main.rs".to_string(),
+            }
+        );
+
+        // Edits that create or delete nothing are not reported
+        doc.set_file_content("main.rs", "fn main() {}").unwrap();
+        server
+            .handle_sync_message("peer-1", "project-1", doc.save())
+            .await
+            .unwrap();
+        assert!(events.try_recv().is_err());
+
+        // Deleting a folder over HTTP reports the files in it
+        server
+            .write_file("project-1", "src/lib.rs", "", None)
+            .await
+            .unwrap();
+        assert!(matches!(
+            events.try_recv(),
+            Ok(ProjectEvent::FileCreated { path, .. }) if path == "src/lib.rs"
+        ));
+        server.delete_file("project-1", "src", None).await.unwrap();
+        assert!(matches!(
+            events.try_recv(),
+            Ok(ProjectEvent::FileDeleted { path, .. }) if path == "src/lib.rs"
+        ));
+
+        assert_eq!(server.save_dirty_documents().await, 1);
+        assert_eq!(
+            events.try_recv().unwrap(),
+            ProjectEvent::ProjectSaved {
+                project_id: "project-1".to_string(),
+            }
+        );
     }
 
     #[tokio::test]
-    async fn test_peer_registration() {
+    async fn test_file_changes_in_activity_feed() {
         let storage = test_storage();
-        let server = SyncServer::with_storage(storage);
+        let server = SyncServer::new(storage, SyncServerConfig::default());
 
-        let (tx, _rx) = mpsc::unbounded_channel();
+        let (tx, _rx) = lanes::channel();
         server
-            .register_peer("peer-1", "Alice", "#ff0000", "token-123", tx)
+            .register_peer("peer-1", "Alice", "#ff0000", "token-1", tx)
             .unwrap();
+        let Ok(ServerMessage::ProjectJoined {
+            document_state: Some(state),
+            ..
+        }) = server.join_project("peer-1", "project-1", true).await
+        else {
+            panic!("Expected to join with the document");
+        };
+        settle().await;
 
-        assert_eq!(server.stats().active_peers, 1);
-        assert!(server.get_peer("peer-1").is_some());
+        let mut doc = CollabDocument::load("project-1", &state).unwrap();
+        doc.create_file("main", "main.rs", "main.rs", None, "rust").unwrap();
+        server
+            .handle_sync_message("peer-1", "project-1", doc.save())
+            .await
+            .unwrap();
+        server
+            .write_file("project-1", "src/lib.rs", "", None)
+            .await
+            .unwrap();
+        server.delete_file("project-1", "src", None).await.unwrap();
+
+        // A rename is one entry, not a deletion and a creation
+        doc.rename_node("main", "app.rs").unwrap();
+        server
+            .handle_sync_message("peer-1", "project-1", doc.save())
+            .await
+            .unwrap();
+        settle().await;
+
+        let files: Vec<_> = server
+            .activity_feed("peer-1", "project-1", 10)
+            .unwrap()
+            .into_iter()
+            .filter(|entry| {
+                matches!(
+                    entry.kind,
+                    ActivityKind::FileCreated { .. }
+                        | ActivityKind::Deleted { .. }
+                        | ActivityKind::Renamed { .. }
+                )
+            })
+            .collect();
+        assert_eq!(files.len(), 4);
+        assert_eq!(
+            files[0].kind,
+            ActivityKind::Renamed {
+                old_name: "main.rs".to_string(),
+                new_name: "app.rs".to_string()
+            }
+        );
+        assert_eq!(files[0].peer_name.as_deref(), Some("Alice"));
+        assert_eq!(
+            files[1].kind,
+            ActivityKind::Deleted {
+                path: "src/lib.rs".to_string()
+            }
+        );
+        assert_eq!(
+            files[2].kind,
+            ActivityKind::FileCreated {
+                path: "src/lib.rs".to_string()
+            }
+        );
+        assert_eq!(files[2].peer_id, None);
+        assert_eq!(
+            files[3].kind,
+            ActivityKind::FileCreated {
+                path: "main.rs".to_string()
+            }
+        );
+        assert_eq!(files[3].peer_name.as_deref(), Some("Alice"));
+
+        // Only members see the feed
+        let (tx, _rx) = lanes::channel();
+        server
+            .register_peer("peer-2", "Mallory", "#00ff00", "token-2", tx)
+            .unwrap();
+        assert!(matches!(
+            server.activity_feed("peer-2", "project-1", 10),
+            Err(SyncError::Unauthorized(_))
+        ));
     }
 
     #[tokio::test]
-    async fn test_join_project() {
+    async fn test_share_links() {
         let storage = test_storage();
-        let server = SyncServer::with_storage(storage);
+        let server = SyncServer::new(storage, SyncServerConfig::default());
 
-        let (tx, _rx) = mpsc::unbounded_channel();
+        let (owner_tx, _owner_rx) = lanes::channel();
+        let (guest_tx, mut guest_rx) = lanes::channel();
         server
-            .register_peer("peer-1", "Alice", "#ff0000", "token-123", tx)
+            .register_peer("owner", "Owner", "#ff0000", "token-1", owner_tx)
+            .unwrap();
+        server
+            .register_peer("guest", "Guest", "#00ff00", "token-2", guest_tx)
+            .unwrap();
+        server.join_project("owner", "project-1", false).await.unwrap();
+        server
+            .write_file("project-1", "src/main.rs", "fn main() {}", None)
+            .await
             .unwrap();
 
-        let result = server.join_project("peer-1", "project-1", true).await;
-        assert!(result.is_ok());
+        // Only members that joined without a link may manage links
+        assert_eq!(server.share_manager("token-1", "project-1").as_deref(), Some("Owner"));
+        assert!(server.share_manager("token-2", "project-1").is_none());
 
-        assert_eq!(server.stats().active_projects, 1);
+        // and of those, in a project nobody owns, only the one that created it
+        let (other_tx, _other_rx) = lanes::channel();
+        server
+            .register_peer("other", "Other", "#0000ff", "token-3", other_tx)
+            .unwrap();
+        server.join_project("other", "project-1", false).await.unwrap();
+        assert!(server.share_manager("token-3", "project-1").is_none());
+
+        let (link, token) = server
+            .create_share_link(
+                "project-1",
+                ShareRole::Viewer,
+                &["docs/".to_string()],
+                Duration::from_secs(60),
+                Some("Owner".to_string()),
+            )
+            .unwrap();
+        assert_eq!(link.paths, vec!["docs".to_string()]);
+        assert_eq!(server.list_share_links("project-1").unwrap().len(), 1);
+
+        assert!(server
+            .join_project_with_link("guest", "project-1", false, Some("bogus"))
+            .await
+            .is_err());
+        server
+            .join_project_with_link("guest", "project-1", false, Some(&token))
+            .await
+            .unwrap();
+        assert!(server.share_manager("token-2", "project-1").is_none());
+
+        // Viewers stay within their scope and cannot change the document
+        assert!(server.check_file_access("guest", "project-1", "docs/guide.md").is_ok());
+        assert!(server.check_file_access("guest", "project-1", "src/main.rs").is_err());
+        assert!(server.check_file_access("owner", "project-1", "src/main.rs").is_ok());
+
+        let mut edited = CollabDocument::new("project-1").unwrap();
+        edited
+            .create_file("f1", "notes.md", "notes.md", None, "markdown")
+            .unwrap();
+        let result = server
+            .handle_sync_message("guest", "project-1", edited.save())
+            .await;
+        assert!(matches!(result, Err(SyncError::Unauthorized(_))));
+
+        // Revoking disconnects the guest and the token stops working
+        assert!(server.revoke_share_link("project-1", &link.link_id).unwrap());
+        let kicked = std::iter::from_fn(|| guest_rx.try_recv().ok()).any(|msg| {
+            matches!(msg, ServerMessage::Error { code: ErrorCode::Unauthorized, .. })
+        });
+        assert!(kicked);
+        assert!(server.get_peer("guest").unwrap().read().joined_projects.is_empty());
+        assert_eq!(
+            server.verify_share_token("project-1", &token),
+            Err(ShareError::Revoked)
+        );
     }
 
     #[tokio::test]
-    async fn test_multiple_peers_join() {
+    async fn test_access_control() {
         let storage = test_storage();
-        let server = SyncServer::with_storage(storage);
+        let server = SyncServer::new(storage, SyncServerConfig::default());
 
-        let (tx1, _rx1) = mpsc::unbounded_channel();
-        let (tx2, _rx2) = mpsc::unbounded_channel();
+        let mut receivers = Vec::new();
+        for (peer_id, user_id) in [("owner", Some("u-owner")), ("member", Some("u-member")), ("anon", None)] {
+            let (tx, rx) = lanes::channel();
+            receivers.push(rx);
+            server
+                .register_peer(peer_id, peer_id, "#ff0000", peer_id, tx)
+                .unwrap();
+            server.get_peer(peer_id).unwrap().write().user_id = user_id.map(str::to_string);
+        }
 
+        // Open projects let everyone in; an access list removes the others
+        server.join_project("anon", "project-1", false).await.unwrap();
+        assert_eq!(
+            server.project_role("project-1", None).unwrap(),
+            Some(ProjectRole::Editor)
+        );
         server
-            .register_peer("peer-1", "Alice", "#ff0000", "token-1", tx1)
+            .save_acl(&ProjectAcl::new("project-1", "u-owner"))
             .unwrap();
+        assert!(server.get_peer("anon").unwrap().read().joined_projects.is_empty());
+
+        assert!(matches!(
+            server.join_project("anon", "project-1", false).await,
+            Err(SyncError::Unauthorized(_))
+        ));
+        assert!(server.join_project("member", "project-1", false).await.is_err());
+        server.join_project("owner", "project-1", false).await.unwrap();
+
+        // Viewers may join but not edit or host
         server
-            .register_peer("peer-2", "Bob", "#00ff00", "token-2", tx2)
+            .set_project_role("project-1", "u-member", Some(ProjectRole::Viewer))
             .unwrap();
+        server.join_project("member", "project-1", false).await.unwrap();
+        let mut edited = CollabDocument::new("project-1").unwrap();
+        assert!(matches!(
+            server.handle_sync_message("member", "project-1", edited.save()).await,
+            Err(SyncError::Unauthorized(_))
+        ));
+        assert!(server.host_project("member", "project-1", &[]).is_err());
+        assert!(server.share_manager("member", "project-1").is_none());
 
-        server.join_project("peer-1", "project-1", true).await.unwrap();
-        let result = server.join_project("peer-2", "project-1", false).await.unwrap();
+        // Promoting takes effect for the connected peer
+        server
+            .set_project_role("project-1", "u-member", Some(ProjectRole::Editor))
+            .unwrap();
+        server
+            .handle_sync_message("member", "project-1", edited.save())
+            .await
+            .unwrap();
 
-        // Second peer should see first peer in the list
-        if let ServerMessage::ProjectJoined { peers, .. } = result {
-            assert_eq!(peers.len(), 1);
-            assert_eq!(peers[0].name, "Alice");
-        } else {
-            panic!("Expected ProjectJoined message");
+        // The owner's role is fixed; revoking removes the member
+        assert!(server.set_project_role("project-1", "u-owner", None).is_err());
+        server.set_project_role("project-1", "u-member", None).unwrap();
+        assert!(server.get_peer("member").unwrap().read().joined_projects.is_empty());
+        assert!(server.set_project_role("project-1", "u-member", None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_annotations() {
+        let server = SyncServer::new(test_storage(), SyncServerConfig::default());
+        let mut receivers = Vec::new();
+        for (peer_id, user_id) in [("owner", "u-owner"), ("member", "u-member")] {
+            let (tx, rx) = lanes::channel();
+            receivers.push(rx);
+            server
+                .register_peer(peer_id, peer_id, "#ff0000", peer_id, tx)
+                .unwrap();
+            server.get_peer(peer_id).unwrap().write().user_id = Some(user_id.to_string());
         }
+        server
+            .save_acl(&ProjectAcl::new("project-1", "u-owner"))
+            .unwrap();
+        server
+            .set_project_role("project-1", "u-member", Some(ProjectRole::Viewer))
+            .unwrap();
+        server.join_project("owner", "project-1", false).await.unwrap();
+        server.join_project("member", "project-1", false).await.unwrap();
+        server
+            .write_file("project-1", "main.rs", "fn main() {}", None)
+            .await
+            .unwrap();
+
+        // Viewers may comment too; the range is checked against the text
+        let comment = server
+            .add_annotation("member", "project-1", "main.rs", 3..7, AnnotationKind::Comment, "Name?")
+            .unwrap();
+        assert!(matches!(
+            server.add_annotation("member", "project-1", "main.rs", 3..99, AnnotationKind::Highlight, ""),
+            Err(SyncError::InvalidMessage(_))
+        ));
+        assert!(matches!(
+            server.add_annotation("member", "project-1", "main.rs", 0..1, AnnotationKind::Comment, " "),
+            Err(SyncError::InvalidMessage(_))
+        ));
+        let highlight = server
+            .add_annotation("owner", "project-1", "main.rs", 0..2, AnnotationKind::Highlight, "")
+            .unwrap();
+        settle().await;
+        assert!(
+            std::iter::from_fn(|| receivers[0].try_recv().ok()).any(|msg| matches!(
+                msg,
+                ServerMessage::AnnotationAdded { annotation, .. } if annotation == comment
+            ))
+        );
+
+        // The range follows the text it was put on
+        server
+            .write_file("project-1", "main.rs", "pub fn main() {}", None)
+            .await
+            .unwrap();
+        let annotations = server.annotations("owner", "project-1", "main.rs").unwrap();
+        let ranges: Vec<_> = annotations.iter().map(|a| (a.start, a.end)).collect();
+        assert_eq!(ranges, [(4, 6), (7, 11)]);
+
+        // Only the author or the host resolves
+        assert!(matches!(
+            server.resolve_annotation("member", "project-1", "main.rs", &highlight.annotation_id, true),
+            Err(SyncError::Unauthorized(_))
+        ));
+        server
+            .resolve_annotation("owner", "project-1", "main.rs", &comment.annotation_id, true)
+            .unwrap();
+        let annotations = server.annotations("member", "project-1", "main.rs").unwrap();
+        assert!(annotations[1].resolved && !annotations[0].resolved);
+        settle().await;
+        assert!(
+            std::iter::from_fn(|| receivers[1].try_recv().ok()).any(|msg| matches!(
+                msg,
+                ServerMessage::AnnotationResolved { resolved_by, .. } if resolved_by == "owner"
+            ))
+        );
     }
 
     #[tokio::test]
-    async fn test_leave_project() {
+    async fn test_admin_operations() {
         let storage = test_storage();
-        let server = SyncServer::with_storage(storage);
+        let server = SyncServer::new(storage, SyncServerConfig::default());
 
-        let (tx, _rx) = mpsc::unbounded_channel();
+        let (tx, mut rx) = lanes::channel();
         server
-            .register_peer("peer-1", "Alice", "#ff0000", "token-123", tx)
+            .register_peer("peer-1", "Alice", "#ff0000", "token-1", tx)
+            .unwrap();
+        server.join_project("peer-1", "project-1", false).await.unwrap();
+        server
+            .write_file("project-1", "src/main.rs", "fn main() {}", None)
+            .await
             .unwrap();
 
-        server.join_project("peer-1", "project-1", true).await.unwrap();
-        server.leave_project("peer-1", "project-1").unwrap();
+        let rooms = server.room_reports();
+        assert_eq!(rooms.len(), 1);
+        assert_eq!(rooms[0].peers[0].name.as_deref(), Some("Alice"));
+        assert!(rooms[0].dirty);
 
-        // Room still exists but peer is gone
-        let peer = server.get_peer("peer-1").unwrap();
-        assert!(peer.read().joined_projects.is_empty());
+        // Unsaved changes show up as a difference from storage
+        let dump = server.dump_project("project-1").unwrap();
+        assert!(dump.stored.is_none() && !dump.in_sync);
+        assert_eq!(server.force_save(Some("project-1")).unwrap(), 1);
+        let dump = server.dump_project("project-1").unwrap();
+        assert!(dump.in_sync);
+        assert_eq!(dump.stored.unwrap().files[0].0, "src/main.rs");
+
+        assert!(server.disconnect_peer("peer-1", Some("maintenance".to_string())));
+        assert!(server.get_peer("peer-1").is_none());
+        assert!(std::iter::from_fn(|| rx.try_recv().ok())
+            .any(|msg| matches!(msg, ServerMessage::Goodbye { .. })));
+        assert!(!server.disconnect_peer("peer-1", None));
+
+        assert!(server.close_room("project-1").unwrap());
+        assert!(server.room_reports().is_empty());
+        assert!(!server.close_room("project-1").unwrap());
+        assert!(server.dump_project("project-1").unwrap().in_sync);
     }
 
     #[tokio::test]
-    async fn test_session_restore() {
+    async fn test_archive_project() {
         let storage = test_storage();
-        let server = SyncServer::with_storage(storage);
+        storage
+            .save_metadata(&DocumentMetadata::new("project-1", "Project"))
+            .unwrap();
+        let server = SyncServer::new(storage, SyncServerConfig::default());
 
-        let (tx, _rx) = mpsc::unbounded_channel();
+        let (tx, mut rx) = lanes::channel();
         server
-            .register_peer("peer-1", "Alice", "#ff0000", "secret-token", tx)
+            .register_peer("peer-1", "Alice", "#ff0000", "token-1", tx)
             .unwrap();
+        server.join_project("peer-1", "project-1", false).await.unwrap();
 
-        let restored = server.restore_session("secret-token");
-        assert_eq!(restored, Some("peer-1".to_string()));
+        // Archiving sends connected peers away and refuses new joins
+        let metadata = server.set_archived("project-1", true).unwrap().unwrap();
+        assert!(metadata.archived);
+        assert!(server.room_reports().is_empty());
+        assert!(std::iter::from_fn(|| rx.try_recv().ok()).any(|msg| matches!(
+            msg,
+            ServerMessage::Error { message, .. } if message.contains("archived")
+        )));
+        assert!(matches!(
+            server.join_project("peer-1", "project-1", false).await,
+            Err(SyncError::Archived(_))
+        ));
 
-        let not_found = server.restore_session("wrong-token");
-        assert!(not_found.is_none());
+        server.set_archived("project-1", false).unwrap();
+        server.join_project("peer-1", "project-1", false).await.unwrap();
+        assert!(server.set_archived("missing", true).unwrap().is_none());
     }
 
     #[tokio::test]
-    async fn test_unregister_peer() {
+    async fn test_chat_moderation() {
+        let config = SyncServerConfig {
+            chat: ChatConfig {
+                max_length: 20,
+                history_limit: 2,
+                ..ChatConfig::default()
+            },
+            ..SyncServerConfig::default()
+        };
+        let server = SyncServer::new(test_storage(), config);
+        let mut receivers = Vec::new();
+        for (peer_id, name) in [("peer-1", "Alice"), ("peer-2", "Bob")] {
+            let (tx, rx) = lanes::channel();
+            server
+                .register_peer(peer_id, name, "#ff0000", peer_id, tx)
+                .unwrap();
+            server.join_project(peer_id, "project-1", false).await.unwrap();
+            receivers.push(rx);
+        }
+
+        assert!(matches!(
+            server.post_chat("peer-1", "project-1", "a message that is far too long"),
+            Err(SyncError::InvalidMessage(_))
+        ));
+        assert!(matches!(
+            server.post_chat("peer-1", "other", "hi"),
+            Err(SyncError::Unauthorized(_))
+        ));
+
+        let first = server.post_chat("peer-1", "project-1", "first").unwrap();
+        let second = server.post_chat("peer-2", "project-1", "second").unwrap();
+        let third = server.post_chat("peer-1", "project-1", "third").unwrap();
+        let history = server.chat_history("project-1").unwrap();
+        let contents: Vec<_> = history.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["second", "third"]);
+        settle().await;
+        assert!(
+            std::iter::from_fn(|| receivers[0].try_recv().ok()).any(|msg| matches!(
+                msg,
+                ServerMessage::ChatBroadcast { content, .. } if content == "third"
+            ))
+        );
+
+        // Messages beyond the history limit are gone; in a project nobody
+        // owns, only the peer that created it may delete others' messages
+        let second_id = second.message_id.unwrap();
+        let third_id = third.message_id.unwrap();
+        assert!(matches!(
+            server.delete_chat("peer-1", "project-1", first.message_id.as_deref().unwrap()),
+            Err(SyncError::InvalidMessage(_))
+        ));
+        assert!(matches!(
+            server.delete_chat("peer-2", "project-1", &third_id),
+            Err(SyncError::Unauthorized(_))
+        ));
+        server
+            .delete_chat("peer-1", "project-1", &second_id)
+            .unwrap();
+        let history = server.chat_history("project-1").unwrap();
+        assert_eq!(history.len(), 1);
+        settle().await;
+        assert!(
+            std::iter::from_fn(|| receivers[1].try_recv().ok()).any(|msg| matches!(
+                msg,
+                ServerMessage::ChatDeleted { message_id, .. } if message_id == second_id
+            ))
+        );
+
+        // The document holds the same messages, so they replicate with it
+        let room = server.rooms.get("project-1").unwrap().clone();
+        let entries = room.document.lock().get_chat_messages(10, None).unwrap();
+        let contents: Vec<_> = entries.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["third"]);
+    }
+
+    #[tokio::test]
+    async fn test_changes_attributed_to_peers() {
+        let server = SyncServer::new(test_storage(), SyncServerConfig::default());
+        for peer_id in ["peer-1", "peer-2"] {
+            let (tx, _rx) = lanes::channel();
+            server
+                .register_peer(peer_id, peer_id, "#ff0000", peer_id, tx)
+                .unwrap();
+            server.join_project(peer_id, "project-1", false).await.unwrap();
+        }
+        server.get_peer("peer-1").unwrap().write().user_id = Some("u-alice".to_string());
+
+        let room = server.rooms.get("project-1").unwrap().clone();
+        let heads = room.document.lock().get_heads();
+        server.post_chat("peer-1", "project-1", "hi").unwrap();
+        server.post_chat("peer-2", "project-1", "hello").unwrap();
+
+        // A signed-in user's changes carry their user ID, a guest's its peer ID
+        let actors: Vec<ActorId> = room
+            .document
+            .lock()
+            .get_changes_since(&heads)
+            .iter()
+            .map(|change| change.actor_id().clone())
+            .collect();
+        assert_eq!(
+            actors,
+            [document::actor_for("u-alice"), document::actor_for("peer-2")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lobby_updates() {
         let storage = test_storage();
-        let server = SyncServer::with_storage(storage);
+        storage
+            .save_metadata(&DocumentMetadata::new("project-1", "Project"))
+            .unwrap();
+        let server = SyncServer::new(storage, SyncServerConfig::default());
+        let mut lobby = server.subscribe_lobby();
 
-        let (tx, _rx) = mpsc::unbounded_channel();
+        let (tx, _rx) = lanes::channel();
         server
-            .register_peer("peer-1", "Alice", "#ff0000", "token-123", tx)
+            .register_peer("peer-1", "Alice", "#ff0000", "token-1", tx)
             .unwrap();
+        server.join_project("peer-1", "project-1", false).await.unwrap();
+        server.leave_project("peer-1", "project-1").unwrap();
 
-        server.join_project("peer-1", "project-1", true).await.unwrap();
-        server.unregister_peer("peer-1");
+        let counts: Vec<u32> = std::iter::from_fn(|| lobby.try_recv().ok())
+            .filter_map(|msg| match msg {
+                ServerMessage::PeerCountChanged { peer_count, .. } => Some(peer_count),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(counts, vec![1, 0]);
 
-        assert!(server.get_peer("peer-1").is_none());
-        assert!(server.restore_session("token-123").is_none());
+        // Deleting removes the stored project and tells the lobby
+        assert!(server.delete_project("project-1").unwrap());
+        assert!(server
+            .storage()
+            .get_metadata("project-1")
+            .unwrap()
+            .is_none());
+        assert!(
+            std::iter::from_fn(|| lobby.try_recv().ok()).any(|msg| matches!(
+                msg,
+                ServerMessage::ProjectDeleted { project_id } if project_id == "project-1"
+            ))
+        );
+        assert!(!server.delete_project("project-1").unwrap());
     }
 }
@@ -0,0 +1,262 @@
+//! Share links that let someone join a project with limited capabilities.
+//!
+//! A link is a signed, expiring token naming the project, a role (viewer or
+//! editor) and optionally the paths it is limited to. The token is checked
+//! when a peer joins with it; the stored link record is what makes it valid,
+//! so removing the record revokes the link even before it expires.
+//!
+//! The path scope limits which files the peer may open and edit. The CRDT
+//! document itself is still synced whole, so a scope is not a way to keep
+//! file contents secret.
+
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+
+use super::document::{CollabDocument, DocumentResult};
+use crate::storage::{ShareLink, ShareRole};
+
+/// Lifetime of a link when none is given
+pub const DEFAULT_LINK_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Longest lifetime a link may be given
+pub const MAX_LINK_TTL: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+
+/// Errors that can occur when checking a share token
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ShareError {
+    #[error("Invalid share link")]
+    InvalidToken,
+
+    #[error("Share link has expired")]
+    Expired,
+
+    #[error("Share link is for another project")]
+    WrongProject,
+
+    #[error("Share link has been revoked")]
+    Revoked,
+}
+
+/// Claims carried by a share token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ShareClaims {
+    /// Link ID
+    jti: String,
+    /// Project ID
+    sub: String,
+    role: ShareRole,
+    paths: Vec<String>,
+    exp: i64,
+    iat: i64,
+}
+
+/// Signs and checks share tokens with a server secret
+pub struct ShareSigner {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+}
+
+impl ShareSigner {
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+        }
+    }
+
+    /// Create the token for a link
+    pub fn sign(&self, link: &ShareLink) -> Result<String, ShareError> {
+        let claims = ShareClaims {
+            jti: link.link_id.clone(),
+            sub: link.project_id.clone(),
+            role: link.role,
+            paths: link.paths.clone(),
+            exp: link.expires_at,
+            iat: link.created_at,
+        };
+        encode(&Header::new(Algorithm::HS256), &claims, &self.encoding)
+            .map_err(|_| ShareError::InvalidToken)
+    }
+
+    /// Check a token's signature, expiry and project.
+    ///
+    /// Returns the access it grants; whether the link was revoked is up to
+    /// the caller.
+    pub fn verify(&self, token: &str, project_id: &str) -> Result<ShareAccess, ShareError> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.leeway = 0;
+        let claims = decode::<ShareClaims>(token, &self.decoding, &validation)
+            .map_err(|e| match e.kind() {
+                jsonwebtoken::errors::ErrorKind::ExpiredSignature => ShareError::Expired,
+                _ => ShareError::InvalidToken,
+            })?
+            .claims;
+
+        if claims.sub != project_id {
+            return Err(ShareError::WrongProject);
+        }
+
+        Ok(ShareAccess {
+            link_id: claims.jti,
+            role: claims.role,
+            paths: claims.paths,
+        })
+    }
+}
+
+/// What a peer that joined through a share link may do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareAccess {
+    pub link_id: String,
+    pub role: ShareRole,
+    /// Empty for the whole project
+    pub paths: Vec<String>,
+}
+
+impl ShareAccess {
+    /// Check if a path is within the link's scope
+    pub fn allows_path(&self, path: &str) -> bool {
+        self.paths.is_empty() || self.paths.iter().any(|scope| is_same_or_below(path, scope))
+    }
+
+    pub fn can_edit(&self) -> bool {
+        self.role == ShareRole::Editor
+    }
+
+    /// Check if a peer with this access may apply a change to the document.
+    ///
+    /// Viewers may not change anything; scoped editors may not touch files
+    /// or folders outside their scope.
+    pub fn allows_change(&self, before: &CollabDocument, after: &CollabDocument) -> DocumentResult<bool> {
+        if !self.can_edit() {
+            return Ok(false);
+        }
+        if self.paths.is_empty() {
+            return Ok(true);
+        }
+        Ok(self.outside_scope(before)? == self.outside_scope(after)?)
+    }
+
+    /// Everything outside the scope: each node's path and kind, and file contents
+    fn outside_scope(&self, doc: &CollabDocument) -> DocumentResult<Vec<(String, bool, Option<String>)>> {
+        let mut state = Vec::new();
+        for node in doc.get_all_nodes()? {
+            if self.allows_path(&node.path) {
+                continue;
+            }
+            let content = if node.is_dir {
+                None
+            } else {
+                doc.get_file_content(&node.path)?.map(|file| file.content)
+            };
+            state.push((node.path, node.is_dir, content));
+        }
+        state.sort();
+        Ok(state)
+    }
+}
+
+/// Normalize the paths a link is limited to, dropping empty and duplicate ones
+pub fn normalize_scope(paths: &[String]) -> Option<Vec<String>> {
+    let mut scope = Vec::new();
+    for path in paths {
+        let path = super::files::normalize_path(path)?;
+        if !scope.contains(&path) {
+            scope.push(path);
+        }
+    }
+    Some(scope)
+}
+
+fn is_same_or_below(path: &str, ancestor: &str) -> bool {
+    path == ancestor
+        || path
+            .strip_prefix(ancestor)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::files::write_file;
+
+    fn link(project_id: &str, expires_at: i64) -> ShareLink {
+        ShareLink {
+            link_id: "link-1".to_string(),
+            project_id: project_id.to_string(),
+            role: ShareRole::Editor,
+            paths: vec!["src".to_string()],
+            created_at: chrono::Utc::now().timestamp(),
+            expires_at,
+            created_by: None,
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signer = ShareSigner::new(b"secret");
+        let in_an_hour = chrono::Utc::now().timestamp() + 3600;
+        let token = signer.sign(&link("proj", in_an_hour)).unwrap();
+
+        let access = signer.verify(&token, "proj").unwrap();
+        assert_eq!(access.link_id, "link-1");
+        assert_eq!(access.role, ShareRole::Editor);
+        assert_eq!(access.paths, vec!["src".to_string()]);
+
+        assert_eq!(signer.verify(&token, "other"), Err(ShareError::WrongProject));
+        assert_eq!(
+            ShareSigner::new(b"other secret").verify(&token, "proj"),
+            Err(ShareError::InvalidToken)
+        );
+
+        let expired = signer.sign(&link("proj", 1)).unwrap();
+        assert_eq!(signer.verify(&expired, "proj"), Err(ShareError::Expired));
+    }
+
+    #[test]
+    fn test_scope() {
+        let access = ShareAccess {
+            link_id: "link-1".to_string(),
+            role: ShareRole::Editor,
+            paths: vec!["src".to_string()],
+        };
+        assert!(access.allows_path("src"));
+        assert!(access.allows_path("src/main.rs"));
+        assert!(!access.allows_path("srcs/main.rs"));
+        assert!(!access.allows_path("README.md"));
+
+        assert_eq!(
+            normalize_scope(&["/src/".to_string(), "src".to_string()]),
+            Some(vec!["src".to_string()])
+        );
+        assert_eq!(normalize_scope(&["../etc".to_string()]), None);
+    }
+
+    #[test]
+    fn test_allows_change() {
+        let mut before = CollabDocument::new("proj").unwrap();
+        write_file(&mut before, "src/main.rs", "fn main() {}", None).unwrap();
+        write_file(&mut before, "README.md", "# Readme", None).unwrap();
+
+        let editor = ShareAccess {
+            link_id: "link-1".to_string(),
+            role: ShareRole::Editor,
+            paths: vec!["src".to_string()],
+        };
+        let viewer = ShareAccess {
+            role: ShareRole::Viewer,
+            ..editor.clone()
+        };
+
+        let mut inside = before.fork().unwrap();
+        inside.set_file_content("src/main.rs", "fn main() { }").unwrap();
+        assert!(editor.allows_change(&before, &inside).unwrap());
+        assert!(!viewer.allows_change(&before, &inside).unwrap());
+
+        let mut outside = before.fork().unwrap();
+        outside.set_file_content("README.md", "# Changed").unwrap();
+        assert!(!editor.allows_change(&before, &outside).unwrap());
+    }
+}
@@ -0,0 +1,379 @@
+//! Sync pipeline tests under simulated latency and faults.
+//!
+//! A [`Simulation`] runs a [`SyncServer`] on in-memory storage with scripted
+//! peers, each keeping its own replica of the document the way a client
+//! does. Everything the peers and the server send each other goes through a
+//! simulated network that delays, drops and duplicates messages. Time is
+//! counted in ticks and faults are drawn from a seed, so a failing scenario
+//! fails the same way every run. Once the script is done, `converge` lets
+//! the network settle and `assert_converged` checks every replica ended up
+//! with the server's document.
+//!
+//! Only built for `cargo test --features sim`.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::BTreeMap;
+
+use super::document::{CollabDocument, DocumentResult};
+use super::lanes::{self, PeerReceiver};
+use super::server::SyncServer;
+use crate::storage::DocumentStore;
+use collab_protocol::ServerMessage;
+
+/// How the simulated network misbehaves
+#[derive(Debug, Clone, Copy)]
+pub struct Faults {
+    /// Most ticks a message takes to arrive
+    pub max_latency: u64,
+    /// Chance a message is lost
+    pub drop_rate: f64,
+    /// Chance a message arrives twice
+    pub duplicate_rate: f64,
+}
+
+impl Faults {
+    /// A network that delivers everything, on the next tick
+    pub fn none() -> Self {
+        Self {
+            max_latency: 0,
+            drop_rate: 0.0,
+            duplicate_rate: 0.0,
+        }
+    }
+}
+
+/// A message on its way
+#[derive(Debug, Clone)]
+enum Envelope {
+    ToServer { peer: usize, data: Vec<u8> },
+    ToPeer { peer: usize, data: Vec<u8> },
+}
+
+/// A scripted client and its replica of the document
+struct SimPeer {
+    peer_id: String,
+    doc: CollabDocument,
+    rx: PeerReceiver,
+}
+
+/// A server, its peers and the network between them
+pub struct Simulation {
+    server: SyncServer,
+    project_id: String,
+    peers: Vec<SimPeer>,
+    faults: Faults,
+    rng: StdRng,
+    now: u64,
+    sent: u64,
+    /// Keyed by the tick a message arrives at, then the order it was sent in
+    in_flight: BTreeMap<(u64, u64), Envelope>,
+}
+
+impl Simulation {
+    /// Start a server with `peer_count` peers joined to one project. Joining
+    /// is not subject to faults.
+    pub async fn new(peer_count: usize, faults: Faults, seed: u64) -> Self {
+        let storage = DocumentStore::temporary().expect("Failed to open in-memory storage");
+        let server = SyncServer::with_storage(storage);
+        let project_id = "sim-project".to_string();
+
+        let mut peers = Vec::with_capacity(peer_count);
+        for i in 0..peer_count {
+            let peer_id = format!("peer-{}", i);
+            let (tx, rx) = lanes::channel();
+            server
+                .register_peer(&peer_id, &peer_id, "#ff0000", &peer_id, tx)
+                .expect("Failed to register peer");
+            let Ok(ServerMessage::ProjectJoined {
+                document_state: Some(state),
+                ..
+            }) = server.join_project(&peer_id, &project_id, true).await
+            else {
+                panic!("{} could not join", peer_id);
+            };
+            let doc = CollabDocument::load(&project_id, &state).expect("Invalid document state");
+            peers.push(SimPeer { peer_id, doc, rx });
+        }
+
+        Self {
+            server,
+            project_id,
+            peers,
+            faults,
+            rng: StdRng::seed_from_u64(seed),
+            now: 0,
+            sent: 0,
+            in_flight: BTreeMap::new(),
+        }
+    }
+
+    /// Number of peers
+    pub fn peer_count(&self) -> usize {
+        self.peers.len()
+    }
+
+    /// A peer's replica
+    pub fn replica(&self, peer: usize) -> &CollabDocument {
+        &self.peers[peer].doc
+    }
+
+    /// Edit a peer's replica and send the result to the server, as a client
+    /// does after a local change
+    pub fn edit(&mut self, peer: usize, f: impl FnOnce(&mut CollabDocument) -> DocumentResult<()>) {
+        let doc = &mut self.peers[peer].doc;
+        f(doc).unwrap_or_else(|e| panic!("Edit by peer {} failed: {}", peer, e));
+        let data = doc.save();
+        self.send(Envelope::ToServer { peer, data });
+    }
+
+    /// Advance the clock by `ticks`, delivering what arrives meanwhile
+    pub async fn run(&mut self, ticks: u64) {
+        for _ in 0..ticks {
+            self.step().await;
+        }
+    }
+
+    /// Stop injecting faults and bring everyone up to date: wait for the
+    /// messages in flight, have every peer send its replica, then every peer
+    /// ask for the server's document
+    pub async fn converge(&mut self) {
+        self.faults = Faults::none();
+        self.drain().await;
+
+        for peer in 0..self.peers.len() {
+            let data = self.peers[peer].doc.save();
+            self.send(Envelope::ToServer { peer, data });
+        }
+        self.drain().await;
+
+        for peer in 0..self.peers.len() {
+            if let Some(data) = self
+                .server
+                .generate_sync_for_peer(&self.peers[peer].peer_id, &self.project_id)
+            {
+                self.send(Envelope::ToPeer { peer, data });
+            }
+        }
+        self.drain().await;
+    }
+
+    /// Check every replica has the server's changes and files, and that the
+    /// server's document is intact
+    pub fn assert_converged(&mut self) {
+        let state = self
+            .server
+            .generate_sync_for_peer(&self.peers[0].peer_id, &self.project_id)
+            .expect("Room is not open");
+        let mut expected = CollabDocument::load(&self.project_id, &state).unwrap();
+        let issues = expected.validate().unwrap();
+        assert!(issues.is_empty(), "Server document is broken: {:?}", issues);
+
+        let heads = sorted_heads(&mut expected);
+        let expected_files = files(&expected);
+        for peer in &mut self.peers {
+            assert_eq!(
+                sorted_heads(&mut peer.doc),
+                heads,
+                "{} has other changes",
+                peer.peer_id
+            );
+            assert_eq!(
+                files(&peer.doc),
+                expected_files,
+                "{} has other files",
+                peer.peer_id
+            );
+        }
+    }
+
+    fn send(&mut self, envelope: Envelope) {
+        if self.rng.gen_bool(self.faults.drop_rate) {
+            return;
+        }
+        let copies = if self.rng.gen_bool(self.faults.duplicate_rate) {
+            2
+        } else {
+            1
+        };
+        for _ in 0..copies {
+            let arrives = self.now + 1 + self.rng.gen_range(0..=self.faults.max_latency);
+            self.sent += 1;
+            self.in_flight
+                .insert((arrives, self.sent), envelope.clone());
+        }
+    }
+
+    async fn step(&mut self) {
+        self.now += 1;
+        while let Some(entry) = self.in_flight.first_entry() {
+            if entry.key().0 > self.now {
+                break;
+            }
+            let envelope = entry.remove();
+            self.deliver(envelope).await;
+        }
+        self.collect().await;
+    }
+
+    /// Step until nothing is in flight
+    async fn drain(&mut self) {
+        while !self.in_flight.is_empty() {
+            self.step().await;
+        }
+    }
+
+    async fn deliver(&mut self, envelope: Envelope) {
+        match envelope {
+            Envelope::ToServer { peer, data } => {
+                let peer_id = self.peers[peer].peer_id.clone();
+                match self
+                    .server
+                    .handle_sync_message(&peer_id, &self.project_id, data)
+                    .await
+                {
+                    Ok(Some(data)) => self.send(Envelope::ToPeer { peer, data }),
+                    Ok(None) => {}
+                    Err(e) => panic!("Server rejected a sync from {}: {}", peer_id, e),
+                }
+            }
+            Envelope::ToPeer { peer, data } => {
+                let mut other = CollabDocument::load(&self.project_id, &data)
+                    .expect("Peer received an invalid document");
+                self.peers[peer]
+                    .doc
+                    .merge(other.automerge_mut())
+                    .expect("Peer failed to merge");
+            }
+        }
+    }
+
+    /// Put the sync messages the server's room dispatcher delivered onto
+    /// the network
+    async fn collect(&mut self) {
+        tokio::task::yield_now().await;
+        let mut outgoing = Vec::new();
+        for (peer, sim_peer) in self.peers.iter_mut().enumerate() {
+            while let Ok(msg) = sim_peer.rx.try_recv() {
+                if let ServerMessage::SyncMessage { sync_data, .. } = msg {
+                    outgoing.push(Envelope::ToPeer {
+                        peer,
+                        data: sync_data,
+                    });
+                }
+            }
+        }
+        for envelope in outgoing {
+            self.send(envelope);
+        }
+    }
+}
+
+fn sorted_heads(doc: &mut CollabDocument) -> Vec<String> {
+    let mut heads: Vec<String> = doc
+        .get_heads()
+        .iter()
+        .map(|head| head.to_string())
+        .collect();
+    heads.sort();
+    heads
+}
+
+/// Every file's path and content
+fn files(doc: &CollabDocument) -> Vec<(String, String)> {
+    let mut files: Vec<(String, String)> = doc
+        .get_all_nodes()
+        .unwrap()
+        .into_iter()
+        .filter(|node| !node.is_dir)
+        .map(|node| {
+            let content = doc
+                .get_file_content(&node.path)
+                .unwrap()
+                .map(|file| file.content)
+                .unwrap_or_default();
+            (node.path, content)
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Peer 0 creates `src/main.rs`, then every peer appends lines to it
+    /// and creates a file of its own, `rounds` times over
+    async fn edit_concurrently(sim: &mut Simulation, rounds: usize) {
+        sim.edit(0, |doc| {
+            doc.create_folder("src", "src", "src", None)?;
+            doc.create_file("main", "main.rs", "src/main.rs", Some("src"), "rust")
+        });
+        sim.converge().await;
+
+        for round in 0..rounds {
+            for peer in 0..sim.peer_count() {
+                sim.edit(peer, |doc| {
+                    let mut content = doc
+                        .get_file_content("src/main.rs")?
+                        .map(|file| file.content)
+                        .unwrap_or_default();
+                    content.push_str(&format!("// peer {} round {}\n", peer, round));
+                    doc.set_file_content("src/main.rs", &content)?;
+
+                    let id = format!("file-{}-{}", peer, round);
+                    let name = format!("{}.rs", id);
+                    let path = format!("src/{}", name);
+                    doc.create_file(&id, &name, &path, Some("src"), "rust")
+                });
+            }
+            sim.run(3).await;
+        }
+        sim.converge().await;
+    }
+
+    #[tokio::test]
+    async fn test_converges_without_faults() {
+        let mut sim = Simulation::new(3, Faults::none(), 0).await;
+        edit_concurrently(&mut sim, 5).await;
+        sim.assert_converged();
+
+        let main = sim
+            .replica(2)
+            .get_file_content("src/main.rs")
+            .unwrap()
+            .unwrap();
+        assert_eq!(main.content.lines().count(), 15);
+    }
+
+    #[tokio::test]
+    async fn test_converges_under_faults() {
+        let faults = Faults {
+            max_latency: 10,
+            drop_rate: 0.2,
+            duplicate_rate: 0.2,
+        };
+        for seed in 0..8 {
+            let mut sim = Simulation::new(4, faults, seed).await;
+            edit_concurrently(&mut sim, 6).await;
+            sim.assert_converged();
+
+            // Lost messages delay edits but never lose them
+            let replica = sim.replica(0);
+            let main = replica.get_file_content("src/main.rs").unwrap().unwrap();
+            for peer in 0..4 {
+                for round in 0..6 {
+                    let line = format!("// peer {} round {}", peer, round);
+                    assert!(
+                        main.content.contains(&line),
+                        "seed {}: missing {}",
+                        seed,
+                        line
+                    );
+                    let path = format!("src/file-{}-{}.rs", peer, round);
+                    assert!(replica.get_node_by_path(&path).unwrap().is_some());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Prometheus metrics and tracing setup.
+//!
+//! Instrumented code records through the `metrics` macros using the names
+//! below; the recorder installed here renders them for `/metrics`. Without
+//! a recorder (as in tests) recording does nothing.
+//!
+//! Each HTTP request and WebSocket message is handled under a correlation ID.
+//! It is a field of the tracing span and is returned to the client (the
+//! `x-request-id` header, or a `[ref ...]` suffix on protocol errors), so a
+//! report from a user can be matched with the server's logs. With the `otlp`
+//! feature, spans can also be exported to an OpenTelemetry collector.
+
+use collab_protocol::MessageType;
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+use std::future::Future;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+/// Protocol messages received, by `type`
+pub const MESSAGES_RECEIVED: &str = "collab_messages_received_total";
+/// Protocol messages sent, by `type`
+pub const MESSAGES_SENT: &str = "collab_messages_sent_total";
+/// WebSocket payload bytes received
+pub const WS_BYTES_RECEIVED: &str = "collab_ws_bytes_received_total";
+/// WebSocket payload bytes sent
+pub const WS_BYTES_SENT: &str = "collab_ws_bytes_sent_total";
+/// Automerge sync bytes received from peers
+pub const SYNC_BYTES_IN: &str = "collab_sync_bytes_in_total";
+/// Automerge sync bytes relayed to peers
+pub const SYNC_BYTES_OUT: &str = "collab_sync_bytes_out_total";
+/// Time from queueing a message to handing it to every peer of a project
+pub const BROADCAST_DURATION: &str = "collab_broadcast_duration_seconds";
+/// Number of peers a broadcast went to
+pub const BROADCAST_FANOUT: &str = "collab_broadcast_fanout";
+/// Broadcasts waiting for their room's dispatcher
+pub const BROADCAST_QUEUE_DEPTH: &str = "collab_broadcast_queue_depth";
+/// Time taken to hand a broadcast to one peer
+pub const BROADCAST_PEER_SEND: &str = "collab_broadcast_peer_send_seconds";
+/// Time taken to save a document snapshot
+pub const SAVE_DURATION: &str = "collab_document_save_duration_seconds";
+/// Open WebSocket connections
+pub const WS_CONNECTIONS: &str = "collab_ws_connections";
+/// WebSocket connections refused by the connection limits, by `reason`
+pub const CONNECTIONS_REJECTED: &str = "collab_connections_rejected_total";
+/// Open project rooms
+pub const ROOMS_ACTIVE: &str = "collab_rooms_active";
+/// Connected peers
+pub const PEERS_CONNECTED: &str = "collab_peers_connected";
+/// Size of the database on disk
+pub const STORAGE_SIZE: &str = "collab_storage_size_bytes";
+/// Stored documents
+pub const STORED_DOCUMENTS: &str = "collab_stored_documents";
+/// Project event notifications, by sink and result
+pub const NOTIFICATIONS_SENT: &str = "collab_notifications_total";
+
+const DURATION_BUCKETS: &[f64] = &[
+    0.0001, 0.0005, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+];
+const FANOUT_BUCKETS: &[f64] = &[1.0, 2.0, 5.0, 10.0, 20.0, 50.0];
+
+/// Prometheus exporter configured with this server's histogram buckets
+pub fn builder() -> Result<PrometheusBuilder, BuildError> {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), DURATION_BUCKETS)?
+        .set_buckets_for_metric(Matcher::Full(BROADCAST_FANOUT.to_string()), FANOUT_BUCKETS)
+}
+
+/// Install the Prometheus recorder as the global one
+pub fn install() -> Result<PrometheusHandle, BuildError> {
+    builder()?.install_recorder()
+}
+
+/// Label for an encoded protocol message, from its type byte
+pub fn message_type(frame: &[u8]) -> String {
+    frame
+        .get(1)
+        .and_then(|byte| MessageType::try_from(*byte).ok())
+        .map(|kind| format!("{:?}", kind))
+        .unwrap_or_else(|| "Unknown".to_string())
+}
+
+tokio::task_local! {
+    /// Correlation ID of the WebSocket message being handled
+    static CORRELATION_ID: String;
+}
+
+/// Generate a new correlation ID
+pub fn new_correlation_id() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+/// Run a future with a correlation ID
+pub async fn with_correlation_id<F: Future>(id: String, future: F) -> F::Output {
+    CORRELATION_ID.scope(id, future).await
+}
+
+/// The correlation ID of the message being handled, if any
+pub fn correlation_id() -> Option<String> {
+    CORRELATION_ID.try_with(Clone::clone).ok()
+}
+
+/// Set up logging from `RUST_LOG`, exporting spans to
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` if it is set and the `otlp` feature is on
+pub fn init_tracing() {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| "collab_server=info,tower_http=info".into());
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer());
+    let endpoint = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+        .ok()
+        .filter(|endpoint| !endpoint.is_empty());
+
+    #[cfg(feature = "otlp")]
+    if let Some(endpoint) = endpoint {
+        match otlp::tracer(&endpoint) {
+            Ok(tracer) => {
+                registry
+                    .with(tracing_opentelemetry::layer().with_tracer(tracer))
+                    .init();
+                tracing::info!("Exporting traces to {}", endpoint);
+                return;
+            }
+            Err(e) => eprintln!("Trace export disabled: {}", e),
+        }
+    }
+
+    registry.init();
+
+    #[cfg(not(feature = "otlp"))]
+    if endpoint.is_some() {
+        tracing::warn!(
+            "OTEL_EXPORTER_OTLP_ENDPOINT is set, but the server was built without the `otlp` feature"
+        );
+    }
+}
+
+#[cfg(feature = "otlp")]
+mod otlp {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::{trace, Resource};
+
+    /// Build a tracer that sends spans to an OTLP (gRPC) collector
+    pub fn tracer(endpoint: &str) -> Result<trace::Tracer, opentelemetry::trace::TraceError> {
+        let provider = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(endpoint),
+            )
+            .with_trace_config(trace::Config::default().with_resource(Resource::new(vec![
+                KeyValue::new("service.name", "collab-server"),
+            ])))
+            .install_batch(opentelemetry_sdk::runtime::Tokio)?;
+
+        opentelemetry::global::set_tracer_provider(provider.clone());
+        Ok(provider.tracer("collab-server"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collab_protocol::{ClientMessage, SyncProtocol};
+
+    #[test]
+    fn test_message_type() {
+        let frame = SyncProtocol::encode_client(&ClientMessage::Ping { timestamp: 0 }).unwrap();
+        assert_eq!(message_type(&frame), "Ping");
+        assert_eq!(message_type(&[1]), "Unknown");
+    }
+
+    #[tokio::test]
+    async fn test_correlation_id() {
+        assert_eq!(correlation_id(), None);
+        let id = with_correlation_id("abc".to_string(), async { correlation_id() }).await;
+        assert_eq!(id.as_deref(), Some("abc"));
+        assert_eq!(new_correlation_id().len(), 16);
+    }
+
+    #[test]
+    fn test_render() {
+        let recorder = builder().unwrap().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            metrics::counter!(MESSAGES_RECEIVED, "type" => "Ping").increment(2);
+            metrics::histogram!(SAVE_DURATION).record(0.002);
+            metrics::gauge!(ROOMS_ACTIVE).set(3.0);
+        });
+
+        let output = handle.render();
+        assert!(output.contains("collab_messages_received_total{type=\"Ping\"} 2"));
+        assert!(output.contains("collab_document_save_duration_seconds_bucket{le=\"0.005\"} 1"));
+        assert!(output.contains("collab_rooms_active 3"));
+    }
+}
@@ -0,0 +1,231 @@
+//! WebSocket connection limits.
+//!
+//! Caps the number of open connections, in total and per client IP, and
+//! throttles how often one IP can open new ones, so that a single
+//! misbehaving client cannot exhaust the server.
+
+use dashmap::DashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Window over which connection attempts are counted
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Connection limits
+#[derive(Debug, Clone)]
+pub struct ConnectionLimits {
+    /// Open connections across all clients
+    pub max_connections: usize,
+    /// Open connections from one IP
+    pub max_connections_per_ip: usize,
+    /// New connections one IP may open per minute
+    pub connections_per_minute: u32,
+}
+
+impl Default for ConnectionLimits {
+    fn default() -> Self {
+        Self {
+            max_connections: 10_000,
+            max_connections_per_ip: 20,
+            connections_per_minute: 60,
+        }
+    }
+}
+
+/// Why a connection was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitExceeded {
+    /// The server has as many connections as it accepts
+    ServerFull,
+    /// The IP has as many open connections as it may
+    TooManyConnections,
+    /// The IP opened too many connections recently
+    RateLimited { retry_after: Duration },
+}
+
+impl LimitExceeded {
+    /// Metric label
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::ServerFull => "server_full",
+            Self::TooManyConnections => "per_ip",
+            Self::RateLimited { .. } => "rate",
+        }
+    }
+}
+
+impl std::fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ServerFull => write!(f, "Server is at capacity, try again later"),
+            Self::TooManyConnections => write!(f, "Too many connections from this address"),
+            Self::RateLimited { retry_after } => write!(
+                f,
+                "Too many connection attempts, retry in {}s",
+                retry_after.as_secs().max(1)
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct IpState {
+    /// Open connections
+    connections: usize,
+    /// Start of the current rate window
+    window_start: Instant,
+    /// Connection attempts in the current window
+    attempts: u32,
+}
+
+/// Tracks open connections and recent attempts per IP
+#[derive(Debug)]
+pub struct ConnectionLimiter {
+    limits: ConnectionLimits,
+    total: AtomicUsize,
+    per_ip: DashMap<IpAddr, IpState>,
+}
+
+impl ConnectionLimiter {
+    pub fn new(limits: ConnectionLimits) -> Self {
+        Self {
+            limits,
+            total: AtomicUsize::new(0),
+            per_ip: DashMap::new(),
+        }
+    }
+
+    /// Open connections across all clients
+    pub fn connections(&self) -> usize {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// Admit a connection from `ip`. The connection counts against the
+    /// limits until the returned permit is dropped.
+    pub fn try_acquire(self: &Arc<Self>, ip: IpAddr) -> Result<ConnectionPermit, LimitExceeded> {
+        let now = Instant::now();
+        let mut state = self.per_ip.entry(ip).or_insert_with(|| IpState {
+            connections: 0,
+            window_start: now,
+            attempts: 0,
+        });
+
+        if now.duration_since(state.window_start) >= RATE_WINDOW {
+            state.window_start = now;
+            state.attempts = 0;
+        }
+        if state.attempts >= self.limits.connections_per_minute {
+            return Err(LimitExceeded::RateLimited {
+                retry_after: RATE_WINDOW.saturating_sub(now.duration_since(state.window_start)),
+            });
+        }
+        state.attempts += 1;
+
+        if state.connections >= self.limits.max_connections_per_ip {
+            return Err(LimitExceeded::TooManyConnections);
+        }
+        let admitted = self
+            .total
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |total| {
+                (total < self.limits.max_connections).then_some(total + 1)
+            });
+        if admitted.is_err() {
+            return Err(LimitExceeded::ServerFull);
+        }
+        state.connections += 1;
+
+        Ok(ConnectionPermit {
+            limiter: self.clone(),
+            ip,
+        })
+    }
+
+    /// Forget IPs with no open connections whose rate window has passed
+    pub fn sweep(&self) {
+        let now = Instant::now();
+        self.per_ip.retain(|_, state| {
+            state.connections > 0 || now.duration_since(state.window_start) < RATE_WINDOW
+        });
+    }
+
+    fn release(&self, ip: IpAddr) {
+        self.total.fetch_sub(1, Ordering::AcqRel);
+        if let Some(mut state) = self.per_ip.get_mut(&ip) {
+            state.connections = state.connections.saturating_sub(1);
+        }
+    }
+}
+
+/// An admitted connection; releases its slot when dropped
+#[derive(Debug)]
+pub struct ConnectionPermit {
+    limiter: Arc<ConnectionLimiter>,
+    ip: IpAddr,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        self.limiter.release(self.ip);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::from([192, 0, 2, last])
+    }
+
+    #[test]
+    fn test_connection_caps() {
+        let limiter = Arc::new(ConnectionLimiter::new(ConnectionLimits {
+            max_connections: 3,
+            max_connections_per_ip: 2,
+            connections_per_minute: 100,
+        }));
+
+        let a1 = limiter.try_acquire(ip(1)).unwrap();
+        let _a2 = limiter.try_acquire(ip(1)).unwrap();
+        assert_eq!(
+            limiter.try_acquire(ip(1)).unwrap_err(),
+            LimitExceeded::TooManyConnections
+        );
+
+        let _b1 = limiter.try_acquire(ip(2)).unwrap();
+        assert_eq!(
+            limiter.try_acquire(ip(3)).unwrap_err(),
+            LimitExceeded::ServerFull
+        );
+        assert_eq!(limiter.connections(), 3);
+
+        // Closing a connection frees its slot
+        drop(a1);
+        assert_eq!(limiter.connections(), 2);
+        let _a3 = limiter.try_acquire(ip(1)).unwrap();
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let limiter = Arc::new(ConnectionLimiter::new(ConnectionLimits {
+            max_connections: 100,
+            max_connections_per_ip: 100,
+            connections_per_minute: 2,
+        }));
+
+        drop(limiter.try_acquire(ip(1)).unwrap());
+        drop(limiter.try_acquire(ip(1)).unwrap());
+        let err = limiter.try_acquire(ip(1)).unwrap_err();
+        assert!(
+            matches!(err, LimitExceeded::RateLimited { retry_after } if retry_after <= RATE_WINDOW)
+        );
+
+        // Other addresses are unaffected, and the entry is kept until its
+        // window has passed
+        limiter.try_acquire(ip(2)).unwrap();
+        limiter.sweep();
+        assert!(limiter.per_ip.contains_key(&ip(1)));
+    }
+}
@@ -0,0 +1,414 @@
+//! Built-in TLS termination.
+//!
+//! For deployments without a reverse proxy the server can serve HTTPS (and
+//! HTTP/2, negotiated through ALPN) itself, with either:
+//! - certificate and key files (`TLS_CERT_PATH`, `TLS_KEY_PATH`), re-read
+//!   periodically so renewals by an external tool are picked up
+//! - a certificate obtained from an ACME CA such as Let's Encrypt
+//!   (`ACME_DOMAINS`), validated over HTTP-01 and renewed in the background
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::get,
+    Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use dashmap::DashMap;
+use instant_acme::{
+    Account, AccountCredentials, AuthorizationStatus, ChallengeType, Identifier, LetsEncrypt,
+    NewAccount, NewOrder, OrderStatus,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{error, info, warn};
+
+/// How often certificates are checked for renewal
+const RENEW_CHECK_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Age after which an ACME certificate is renewed (they last 90 days)
+const RENEW_AFTER: Duration = Duration::from_secs(60 * 24 * 60 * 60);
+
+/// Errors that can occur while setting up TLS
+#[derive(Error, Debug)]
+pub enum TlsError {
+    #[error("Invalid TLS configuration: {0}")]
+    Config(String),
+
+    #[error("Certificate I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("ACME error: {0}")]
+    Acme(String),
+}
+
+impl From<instant_acme::Error> for TlsError {
+    fn from(e: instant_acme::Error) -> Self {
+        Self::Acme(e.to_string())
+    }
+}
+
+impl From<rcgen::Error> for TlsError {
+    fn from(e: rcgen::Error) -> Self {
+        Self::Acme(e.to_string())
+    }
+}
+
+/// Where the server certificate comes from
+#[derive(Debug, Clone)]
+pub enum TlsConfig {
+    /// PEM certificate chain and private key on disk
+    Files { cert: PathBuf, key: PathBuf },
+    /// Certificate obtained over ACME
+    Acme(AcmeConfig),
+}
+
+/// Configuration for obtaining certificates over ACME
+#[derive(Debug, Clone)]
+pub struct AcmeConfig {
+    /// Domains on the certificate
+    pub domains: Vec<String>,
+    /// Contact address given to the CA
+    pub email: Option<String>,
+    /// Directory for the account credentials and issued certificate
+    pub cache_dir: PathBuf,
+    /// ACME directory URL of the CA
+    pub directory_url: String,
+    /// Port for the plain HTTP listener answering HTTP-01 challenges
+    pub http_port: u16,
+}
+
+impl TlsConfig {
+    /// Create from environment variables (`TLS_CERT_PATH`, `TLS_KEY_PATH`,
+    /// `ACME_DOMAINS`, `ACME_EMAIL`, `ACME_CACHE_DIR`, `ACME_STAGING`,
+    /// `ACME_DIRECTORY_URL`, `ACME_HTTP_PORT`).
+    ///
+    /// Returns `Ok(None)` if TLS is not configured.
+    pub fn from_env() -> Result<Option<Self>, TlsError> {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+        match (var("TLS_CERT_PATH"), var("TLS_KEY_PATH")) {
+            (Some(cert), Some(key)) => {
+                return Ok(Some(Self::Files {
+                    cert: cert.into(),
+                    key: key.into(),
+                }))
+            }
+            (None, None) => {}
+            _ => {
+                return Err(TlsError::Config(
+                    "TLS_CERT_PATH and TLS_KEY_PATH must be set together".to_string(),
+                ))
+            }
+        }
+
+        let domains = parse_domains(&var("ACME_DOMAINS").unwrap_or_default());
+        if domains.is_empty() {
+            return Ok(None);
+        }
+
+        let staging =
+            var("ACME_STAGING").is_some_and(|value| matches!(value.as_str(), "1" | "true"));
+        let directory_url = var("ACME_DIRECTORY_URL").unwrap_or_else(|| {
+            if staging {
+                LetsEncrypt::Staging.url().to_string()
+            } else {
+                LetsEncrypt::Production.url().to_string()
+            }
+        });
+        let http_port = match var("ACME_HTTP_PORT") {
+            Some(port) => port
+                .parse()
+                .map_err(|_| TlsError::Config(format!("invalid ACME_HTTP_PORT: {}", port)))?,
+            None => 80,
+        };
+
+        Ok(Some(Self::Acme(AcmeConfig {
+            domains,
+            email: var("ACME_EMAIL"),
+            cache_dir: var("ACME_CACHE_DIR")
+                .unwrap_or_else(|| "./data/acme".to_string())
+                .into(),
+            directory_url,
+            http_port,
+        })))
+    }
+}
+
+/// Pending HTTP-01 challenges: token to key authorization
+pub type Challenges = Arc<DashMap<String, String>>;
+
+/// Router answering HTTP-01 challenges under `/.well-known/acme-challenge/`
+pub fn challenge_router(challenges: Challenges) -> Router {
+    Router::new()
+        .route("/.well-known/acme-challenge/:token", get(answer_challenge))
+        .with_state(challenges)
+}
+
+async fn answer_challenge(
+    State(challenges): State<Challenges>,
+    Path(token): Path<String>,
+) -> Result<String, StatusCode> {
+    challenges
+        .get(&token)
+        .map(|key_authorization| key_authorization.clone())
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Load the certificate, obtaining one over ACME if needed
+pub async fn load(config: &TlsConfig, challenges: &Challenges) -> Result<RustlsConfig, TlsError> {
+    // Both ring and aws-lc could be compiled in through dependencies; pick one
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    match config {
+        TlsConfig::Files { cert, key } => Ok(RustlsConfig::from_pem_file(cert, key).await?),
+        TlsConfig::Acme(acme) => {
+            let (cert, key) = if acme.is_due().await {
+                acme.issue(challenges).await?
+            } else {
+                (
+                    tokio::fs::read(acme.cert_path()).await?,
+                    tokio::fs::read(acme.key_path()).await?,
+                )
+            };
+            Ok(RustlsConfig::from_pem(cert, key).await?)
+        }
+    }
+}
+
+/// Periodically reload certificate files, or renew the ACME certificate
+pub fn spawn_renewal(
+    config: TlsConfig,
+    rustls: RustlsConfig,
+    challenges: Challenges,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RENEW_CHECK_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let result = match &config {
+                TlsConfig::Files { cert, key } => rustls
+                    .reload_from_pem_file(cert, key)
+                    .await
+                    .map_err(TlsError::from),
+                TlsConfig::Acme(acme) if acme.is_due().await => {
+                    match acme.issue(&challenges).await {
+                        Ok((cert, key)) => rustls
+                            .reload_from_pem(cert, key)
+                            .await
+                            .map_err(TlsError::from),
+                        Err(e) => Err(e),
+                    }
+                }
+                TlsConfig::Acme(_) => Ok(()),
+            };
+            if let Err(e) = result {
+                error!("Certificate renewal failed: {}", e);
+            }
+        }
+    })
+}
+
+impl AcmeConfig {
+    fn cert_path(&self) -> PathBuf {
+        self.cache_dir.join("cert.pem")
+    }
+
+    fn key_path(&self) -> PathBuf {
+        self.cache_dir.join("key.pem")
+    }
+
+    fn account_path(&self) -> PathBuf {
+        self.cache_dir.join("account.json")
+    }
+
+    /// Whether the cached certificate is missing or old enough to renew
+    async fn is_due(&self) -> bool {
+        let modified = tokio::fs::metadata(self.cert_path())
+            .await
+            .and_then(|metadata| metadata.modified());
+        match modified {
+            Ok(modified) => is_due(modified.elapsed().unwrap_or_default()),
+            Err(_) => true,
+        }
+    }
+
+    /// Sign in to the CA, creating an account on first use
+    async fn account(&self) -> Result<Account, TlsError> {
+        if let Ok(json) = tokio::fs::read(self.account_path()).await {
+            let credentials: AccountCredentials = serde_json::from_slice(&json)
+                .map_err(|e| TlsError::Acme(format!("invalid account file: {}", e)))?;
+            return Ok(Account::from_credentials(credentials).await?);
+        }
+
+        let contact = self.email.as_ref().map(|email| format!("mailto:{}", email));
+        let contact: Vec<&str> = contact.iter().map(String::as_str).collect();
+        let (account, credentials) = Account::create(
+            &NewAccount {
+                contact: &contact,
+                terms_of_service_agreed: true,
+                only_return_existing: false,
+            },
+            &self.directory_url,
+            None,
+        )
+        .await?;
+
+        let json = serde_json::to_vec(&credentials)
+            .map_err(|e| TlsError::Acme(format!("could not save account: {}", e)))?;
+        tokio::fs::write(self.account_path(), json).await?;
+        info!("Created ACME account at {}", self.directory_url);
+        Ok(account)
+    }
+
+    /// Order a certificate, answer its HTTP-01 challenges and save the result
+    async fn issue(&self, challenges: &Challenges) -> Result<(Vec<u8>, Vec<u8>), TlsError> {
+        info!("Requesting certificate for {}", self.domains.join(", "));
+        tokio::fs::create_dir_all(&self.cache_dir).await?;
+        let account = self.account().await?;
+
+        let identifiers: Vec<Identifier> = self
+            .domains
+            .iter()
+            .map(|domain| Identifier::Dns(domain.clone()))
+            .collect();
+        let mut order = account
+            .new_order(&NewOrder {
+                identifiers: &identifiers,
+            })
+            .await?;
+
+        let mut tokens = Vec::new();
+        for authorization in order.authorizations().await? {
+            match authorization.status {
+                AuthorizationStatus::Pending => {}
+                AuthorizationStatus::Valid => continue,
+                status => return Err(TlsError::Acme(format!("authorization is {:?}", status))),
+            }
+            let challenge = authorization
+                .challenges
+                .iter()
+                .find(|challenge| challenge.r#type == ChallengeType::Http01)
+                .ok_or_else(|| TlsError::Acme("CA offered no HTTP-01 challenge".to_string()))?;
+
+            challenges.insert(
+                challenge.token.clone(),
+                order.key_authorization(challenge).as_str().to_string(),
+            );
+            tokens.push(challenge.token.clone());
+            order.set_challenge_ready(&challenge.url).await?;
+        }
+
+        let result = self.finish(&mut order).await;
+        for token in tokens {
+            challenges.remove(&token);
+        }
+        let (cert, key) = result?;
+
+        tokio::fs::write(self.cert_path(), &cert).await?;
+        tokio::fs::write(self.key_path(), &key).await?;
+        info!("Certificate issued for {}", self.domains.join(", "));
+        Ok((cert, key))
+    }
+
+    /// Wait for the CA to validate the order, then finalize it
+    async fn finish(
+        &self,
+        order: &mut instant_acme::Order,
+    ) -> Result<(Vec<u8>, Vec<u8>), TlsError> {
+        let mut delay = Duration::from_millis(500);
+        for _ in 0..10 {
+            tokio::time::sleep(delay).await;
+            let state = order.refresh().await?;
+            match state.status {
+                OrderStatus::Ready | OrderStatus::Valid => break,
+                OrderStatus::Invalid => {
+                    return Err(TlsError::Acme(format!(
+                        "order is invalid: {:?}",
+                        state.error
+                    )))
+                }
+                _ => delay *= 2,
+            }
+        }
+
+        let key_pair = rcgen::KeyPair::generate()?;
+        let mut params = rcgen::CertificateParams::new(self.domains.clone())?;
+        params.distinguished_name = rcgen::DistinguishedName::new();
+        let csr = params.serialize_request(&key_pair)?;
+        order.finalize(csr.der()).await?;
+
+        for _ in 0..10 {
+            if let Some(chain) = order.certificate().await? {
+                return Ok((chain.into_bytes(), key_pair.serialize_pem().into_bytes()));
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        warn!("CA did not issue the certificate in time");
+        Err(TlsError::Acme("certificate was not issued".to_string()))
+    }
+}
+
+/// Whether a certificate of this age should be renewed
+fn is_due(age: Duration) -> bool {
+    age >= RENEW_AFTER
+}
+
+/// Split a comma-separated list of domains
+fn parse_domains(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(|domain| domain.trim().trim_end_matches('.').to_ascii_lowercase())
+        .filter(|domain| !domain.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_domains() {
+        assert_eq!(
+            parse_domains(" Collab.Example.com, www.example.com.,"),
+            vec!["collab.example.com", "www.example.com"]
+        );
+        assert!(parse_domains("").is_empty());
+    }
+
+    #[test]
+    fn test_renewal_age() {
+        assert!(!is_due(Duration::from_secs(24 * 60 * 60)));
+        assert!(is_due(RENEW_AFTER));
+    }
+
+    #[tokio::test]
+    async fn test_load_certificate_files() {
+        let dir = tempdir().unwrap();
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let cert = dir.path().join("cert.pem");
+        let key = dir.path().join("key.pem");
+        std::fs::write(&cert, certified.cert.pem()).unwrap();
+        std::fs::write(&key, certified.key_pair.serialize_pem()).unwrap();
+
+        let challenges = Challenges::default();
+        let config = TlsConfig::Files { cert, key };
+        let rustls = load(&config, &challenges).await.unwrap();
+        assert_eq!(
+            rustls.get_inner().alpn_protocols,
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+        );
+
+        let missing = TlsConfig::Files {
+            cert: dir.path().join("missing.pem"),
+            key: dir.path().join("key.pem"),
+        };
+        assert!(matches!(
+            load(&missing, &challenges).await,
+            Err(TlsError::Io(_))
+        ));
+    }
+}
@@ -54,18 +54,6 @@ impl LiveKitConfig {
         }
     }
 
-    /// Create from environment variables
-    pub fn from_env() -> Result<Self, TokenError> {
-        let api_key = std::env::var("LIVEKIT_API_KEY")
-            .map_err(|_| TokenError::MissingCredentials)?;
-        let api_secret = std::env::var("LIVEKIT_API_SECRET")
-            .map_err(|_| TokenError::MissingCredentials)?;
-        let server_url = std::env::var("LIVEKIT_URL")
-            .unwrap_or_else(|_| "wss://localhost:7880".to_string());
-
-        Ok(Self::new(api_key, api_secret, server_url))
-    }
-
     /// Set token TTL
     pub fn with_ttl(mut self, seconds: u64) -> Self {
         self.token_ttl_seconds = seconds;